@@ -0,0 +1,243 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use bytes::Bytes;
+use tokio::sync::Mutex;
+use tokio::time::{interval, Duration, Instant};
+use tracing::{debug, error, info, warn};
+
+use crate::config::{AlertEvent, AlertRule, SmtpConfig};
+use crate::smtp::{self, Attachment, Email};
+use crate::AppState;
+
+// How often cameras are checked for missing frames
+const OFFLINE_CHECK_INTERVAL_SECS: u64 = 10;
+// Disk usage is checked every n-th offline check
+const DISK_CHECK_EVERY: u64 = 6;
+
+// Last mail of a rule for a camera (or disk path) and the alerts suppressed since then
+struct SentState {
+    sent_at: Instant,
+    suppressed: u32,
+}
+
+/// Sends e-mail alerts for camera-offline, disk-full and motion events with per-rule rate limiting
+pub struct AlertManager {
+    config: SmtpConfig,
+    last_sent: Mutex<HashMap<(String, String), SentState>>,
+    offline: Mutex<HashSet<(String, String)>>,
+}
+
+impl AlertManager {
+    /// Create the manager if SMTP alerting is configured and enabled
+    pub fn from_config(config: Option<&SmtpConfig>) -> Option<Arc<Self>> {
+        let config = config.filter(|c| c.enabled)?;
+        if config.rules.is_empty() {
+            warn!("SMTP alerting is enabled but no alert rules are configured");
+        }
+        info!("SMTP alerting enabled via {}:{} ({} rules)", config.host, config.port, config.rules.len());
+        Some(Arc::new(Self {
+            config: config.clone(),
+            last_sent: Mutex::new(HashMap::new()),
+            offline: Mutex::new(HashSet::new()),
+        }))
+    }
+
+    /// Start the task that watches for offline cameras and full disks
+    pub fn start_monitor(self: &Arc<Self>, state: AppState) -> tokio::task::JoinHandle<()> {
+        let manager = self.clone();
+        tokio::spawn(async move {
+            let mut interval = interval(Duration::from_secs(OFFLINE_CHECK_INTERVAL_SECS));
+            let mut tick: u64 = 0;
+            loop {
+                interval.tick().await;
+                manager.check_offline_cameras(&state).await;
+                if tick.is_multiple_of(DISK_CHECK_EVERY) {
+                    manager.check_disk_usage(&state).await;
+                }
+                tick += 1;
+            }
+        })
+    }
+
+    /// Called when a camera trigger fired
+    pub async fn motion(&self, state: &AppState, camera_id: &str, trigger_id: &str) {
+        for rule in &self.config.rules {
+            let AlertEvent::Motion { ref triggers } = rule.event else { continue };
+            if !applies_to(rule, camera_id) || !(triggers.is_empty() || triggers.iter().any(|t| t == trigger_id)) {
+                continue;
+            }
+            let snapshot = latest_frame(state, camera_id).await;
+            self.notify(
+                rule,
+                camera_id,
+                format!("Motion on camera '{}'", camera_id),
+                format!("Trigger '{}' fired on camera '{}' at {}.", trigger_id, camera_id, timestamp()),
+                snapshot,
+            ).await;
+        }
+    }
+
+    async fn check_offline_cameras(&self, state: &AppState) {
+        let cameras: Vec<_> = {
+            let camera_streams = state.camera_streams.read().await;
+            camera_streams.values()
+                .map(|info| (info.camera_id.clone(), info.pipeline_control.clone()))
+                .collect()
+        };
+
+        for (camera_id, pipeline) in cameras {
+            // FFmpeg is stopped on purpose while in standby
+            if pipeline.is_standby() {
+                continue;
+            }
+            let silent_secs = pipeline.secs_since_last_frame();
+
+            for rule in &self.config.rules {
+                let AlertEvent::CameraOffline { offline_after_secs } = rule.event else { continue };
+                if !applies_to(rule, &camera_id) {
+                    continue;
+                }
+
+                let key = (rule.id.clone(), camera_id.clone());
+                if silent_secs < offline_after_secs {
+                    if self.offline.lock().await.remove(&key) {
+                        info!("Camera '{}' is delivering frames again (alert rule '{}')", camera_id, rule.id);
+                    }
+                    continue;
+                }
+                // Alert once per offline period
+                if !self.offline.lock().await.insert(key) {
+                    continue;
+                }
+
+                let snapshot = latest_frame(state, &camera_id).await;
+                self.notify(
+                    rule,
+                    &camera_id,
+                    format!("Camera '{}' is offline", camera_id),
+                    format!("No frame was received from camera '{}' for {} seconds (checked at {}).\nThe attached image is the last frame received.",
+                            camera_id, silent_secs, timestamp()),
+                    snapshot,
+                ).await;
+            }
+        }
+    }
+
+    async fn check_disk_usage(&self, state: &AppState) {
+        for rule in &self.config.rules {
+            let AlertEvent::DiskFull { threshold_percent, ref path } = rule.event else { continue };
+
+            let mut paths: Vec<String> = match (path, &state.recording_config) {
+                (Some(path), _) => vec![path.clone()],
+                (None, Some(recording_config)) => vec![
+                    recording_config.database_path.clone(),
+                    recording_config.get_mp4_storage_path().to_string(),
+                ],
+                (None, None) => Vec::new(),
+            };
+            paths.dedup();
+
+            for path in paths {
+                let usage = match disk_usage_percent(&path).await {
+                    Some(usage) => usage,
+                    None => continue,
+                };
+                debug!("Disk usage of '{}': {}% (alert rule '{}' at {}%)", path, usage, rule.id, threshold_percent);
+                if usage < threshold_percent {
+                    continue;
+                }
+                self.notify(
+                    rule,
+                    &path,
+                    format!("Disk usage at {}% for '{}'", usage, path),
+                    format!("The filesystem holding '{}' is {}% full (threshold {}%, checked at {}).",
+                            path, usage, threshold_percent, timestamp()),
+                    None,
+                ).await;
+            }
+        }
+    }
+
+    /// Send an alert unless the rule already sent one for this subject within its minimum interval
+    async fn notify(&self, rule: &AlertRule, subject_key: &str, subject: String, body: String, snapshot: Option<Bytes>) {
+        let suppressed = {
+            let mut last_sent = self.last_sent.lock().await;
+            let key = (rule.id.clone(), subject_key.to_string());
+            match last_sent.get_mut(&key) {
+                Some(sent) if sent.sent_at.elapsed() < Duration::from_secs(rule.min_interval_secs) => {
+                    sent.suppressed += 1;
+                    debug!("Alert '{}' for '{}' rate limited ({} suppressed)", rule.id, subject_key, sent.suppressed);
+                    return;
+                }
+                _ => {}
+            }
+            last_sent.insert(key, SentState { sent_at: Instant::now(), suppressed: 0 })
+                .map(|previous| previous.suppressed)
+                .unwrap_or(0)
+        };
+
+        let mut body = body;
+        if suppressed > 0 {
+            body.push_str(&format!("\n\n{} further alerts of rule '{}' were suppressed since the last mail.", suppressed, rule.id));
+        }
+
+        let email = Email {
+            to: if rule.to.is_empty() { self.config.to.clone() } else { rule.to.clone() },
+            subject: format!("[{}] {}", rule.id, subject),
+            body,
+            attachment: snapshot.filter(|_| rule.attach_snapshot).map(|data| Attachment {
+                filename: format!("{}_{}.jpg", subject_key, chrono::Utc::now().format("%Y%m%d_%H%M%S")),
+                content_type: "image/jpeg",
+                data,
+            }),
+        };
+
+        // Never block the caller (trigger dispatch, monitor) on the mail server
+        let config = self.config.clone();
+        let rule_id = rule.id.clone();
+        tokio::spawn(async move {
+            match smtp::send_mail(&config, &email).await {
+                Ok(()) => info!("Sent alert mail '{}' to {}", email.subject, email.to.join(", ")),
+                Err(e) => error!("Failed to send alert mail for rule '{}': {}", rule_id, e),
+            }
+        });
+    }
+}
+
+fn applies_to(rule: &AlertRule, camera_id: &str) -> bool {
+    rule.cameras.is_empty() || rule.cameras.iter().any(|c| c == camera_id)
+}
+
+fn timestamp() -> String {
+    chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string()
+}
+
+async fn latest_frame(state: &AppState, camera_id: &str) -> Option<Bytes> {
+    let latest_frame = state.camera_streams.read().await
+        .get(camera_id)
+        .map(|info| info.latest_frame.clone())?;
+    let frame = latest_frame.read().await.clone();
+    frame
+}
+
+/// Usage of the filesystem holding `path` in percent, as reported by `df`
+async fn disk_usage_percent(path: &str) -> Option<u8> {
+    let output = match tokio::process::Command::new("df").arg("-Pk").arg(path).output().await {
+        Ok(output) if output.status.success() => output,
+        Ok(output) => {
+            warn!("df failed for '{}': {}", path, String::from_utf8_lossy(&output.stderr).trim());
+            return None;
+        }
+        Err(e) => {
+            warn!("Failed to run df for '{}': {}", path, e);
+            return None;
+        }
+    };
+
+    // Last line: "<filesystem> <blocks> <used> <available> <capacity>% <mount point>"
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .last()?
+        .split_whitespace()
+        .find_map(|field| field.strip_suffix('%').and_then(|p| p.parse().ok()))
+}
@@ -0,0 +1,240 @@
+use std::sync::Arc;
+use axum::{Json, response::IntoResponse, extract::Path as AxumPath};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use tracing::{error, info, warn};
+
+use crate::api_recording::{check_api_auth, ApiResponse};
+use crate::attachments::{self, PendingUpload, UploadRejection};
+use crate::config;
+use crate::database::DatabaseProvider;
+use crate::recording::RecordingManager;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateUploadRequest {
+    pub filename: String,
+    pub description: Option<String>,
+    pub captured_at: Option<DateTime<Utc>>, // When the file was recorded
+}
+
+fn error_response(status: axum::http::StatusCode, message: &str) -> axum::response::Response {
+    (status, Json(ApiResponse::<()>::error(message, status.as_u16()))).into_response()
+}
+
+/// 409 if the session is locked, 500 if the lock could not be checked
+fn locked_response(error: crate::errors::StreamError) -> axum::response::Response {
+    match error {
+        crate::errors::StreamError::Locked { message } => error_response(axum::http::StatusCode::CONFLICT, &message),
+        e => error_response(axum::http::StatusCode::INTERNAL_SERVER_ERROR, &format!("Database error: {}", e)),
+    }
+}
+
+/// Database of the camera if the session exists and belongs to it
+async fn session_database(
+    recording_manager: &RecordingManager,
+    camera_id: &str,
+    session_id: i64,
+) -> Result<Arc<dyn DatabaseProvider>, axum::response::Response> {
+    let Some(database) = recording_manager.get_camera_database(camera_id).await else {
+        return Err(error_response(axum::http::StatusCode::NOT_FOUND, &format!("Database not found for camera {}", camera_id)));
+    };
+    match database.get_recording_session(session_id).await {
+        Ok(Some(session)) if session.camera_id == camera_id => Ok(database),
+        Ok(_) => Err(error_response(axum::http::StatusCode::NOT_FOUND, &format!("Recording session {} not found", session_id))),
+        Err(e) => Err(error_response(axum::http::StatusCode::INTERNAL_SERVER_ERROR, &format!("Database error: {}", e))),
+    }
+}
+
+// POST /<camera_path>/control/recordings/:session_id/attachments/upload-url
+pub async fn api_create_upload_url(
+    headers: axum::http::HeaderMap,
+    AxumPath(session_id): AxumPath<i64>,
+    Json(request): Json<CreateUploadRequest>,
+    camera_id: String,
+    camera_config: config::CameraConfig,
+    recording_manager: Arc<RecordingManager>,
+) -> axum::response::Response {
+    if let Err(response) = check_api_auth(&headers, &camera_config, config::TokenScope::Control) {
+        return response;
+    }
+    let recording_config = recording_manager.get_recording_config();
+    if recording_config.attachment_max_mb == 0 {
+        return error_response(axum::http::StatusCode::CONFLICT, "Attachments are disabled (attachment_max_mb is 0)");
+    }
+    let Some(filename) = attachments::sanitize_filename(&request.filename) else {
+        return error_response(axum::http::StatusCode::BAD_REQUEST, "Invalid filename");
+    };
+    let database = match session_database(&recording_manager, &camera_id, session_id).await {
+        Ok(database) => database,
+        Err(response) => return response,
+    };
+    if let Err(e) = crate::database::ensure_unlocked(database.as_ref(), session_id).await {
+        return locked_response(e);
+    }
+
+    let expires_at = Utc::now() + chrono::Duration::minutes(recording_config.attachment_upload_url_minutes.max(1) as i64);
+    let upload = PendingUpload {
+        camera_id: camera_id.clone(),
+        session_id,
+        filename: filename.clone(),
+        description: request.description,
+        captured_at: request.captured_at,
+        expires_at,
+    };
+    let token = match attachments::create_upload(upload) {
+        Ok(token) => token,
+        Err(e) => return error_response(axum::http::StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+    };
+    info!("[{}] Created upload URL for attachment '{}' of session {}", camera_id, filename, session_id);
+
+    Json(ApiResponse::success(serde_json::json!({
+        "upload_url": format!("{}/control/recordings/{}/attachments/upload/{}", camera_config.path, session_id, token),
+        "method": "PUT",
+        "filename": filename,
+        "expires_at": expires_at,
+        "max_bytes": recording_config.attachment_max_mb * 1024 * 1024,
+        "allowed_types": recording_config.attachment_allowed_types,
+    }))).into_response()
+}
+
+// PUT /<camera_path>/control/recordings/:session_id/attachments/upload/:token
+// The token of the pre-signed URL is the authorization, no Authorization header is needed
+pub async fn api_upload_attachment(
+    AxumPath((session_id, token)): AxumPath<(i64, String)>,
+    body: axum::body::Body,
+    camera_id: String,
+    recording_manager: Arc<RecordingManager>,
+) -> axum::response::Response {
+    let Some(upload) = attachments::take_upload(&token, &camera_id, session_id) else {
+        return error_response(axum::http::StatusCode::FORBIDDEN, "Invalid, expired or already used upload URL");
+    };
+    // The session may have been deleted or locked since the URL was created
+    let database = match session_database(&recording_manager, &camera_id, session_id).await {
+        Ok(database) => database,
+        Err(response) => return response,
+    };
+    if let Err(e) = crate::database::ensure_unlocked(database.as_ref(), session_id).await {
+        return locked_response(e);
+    }
+
+    let attachment = match attachments::store_upload(recording_manager.get_recording_config(), &upload, body).await {
+        Ok(attachment) => attachment,
+        Err(UploadRejection::TooLarge { max_mb }) => {
+            return error_response(axum::http::StatusCode::PAYLOAD_TOO_LARGE, &format!("Attachments are limited to {} MB", max_mb));
+        }
+        Err(UploadRejection::UnsupportedType { detected }) => {
+            return error_response(axum::http::StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                &format!("Unsupported file type ({}), allowed: {}",
+                         detected.unwrap_or("unknown"),
+                         recording_manager.get_recording_config().attachment_allowed_types.join(", ")));
+        }
+        Err(UploadRejection::Failed(e)) => {
+            error!("[{}] Failed to store attachment of session {}: {}", camera_id, session_id, e);
+            return error_response(axum::http::StatusCode::INTERNAL_SERVER_ERROR, &format!("Failed to store attachment: {}", e));
+        }
+    };
+
+    if let Err(e) = database.add_session_attachment(&attachment).await {
+        error!("[{}] Failed to save attachment of session {}: {}", camera_id, session_id, e);
+        if let Err(e) = tokio::fs::remove_file(&attachment.file_path).await {
+            warn!("Failed to delete attachment file {}: {}", attachment.file_path, e);
+        }
+        return error_response(axum::http::StatusCode::INTERNAL_SERVER_ERROR, &format!("Database error: {}", e));
+    }
+
+    (axum::http::StatusCode::CREATED, Json(ApiResponse::success(attachment))).into_response()
+}
+
+// GET /<camera_path>/control/recordings/:session_id/attachments
+pub async fn api_list_attachments(
+    headers: axum::http::HeaderMap,
+    AxumPath(session_id): AxumPath<i64>,
+    camera_id: String,
+    camera_config: config::CameraConfig,
+    recording_manager: Arc<RecordingManager>,
+) -> axum::response::Response {
+    if let Err(response) = check_api_auth(&headers, &camera_config, config::TokenScope::Playback) {
+        return response;
+    }
+    let database = match session_database(&recording_manager, &camera_id, session_id).await {
+        Ok(database) => database,
+        Err(response) => return response,
+    };
+
+    match database.list_session_attachments(session_id).await {
+        Ok(attachments) => Json(ApiResponse::success(serde_json::json!({
+            "session_id": session_id,
+            "count": attachments.len(),
+            "attachments": attachments,
+        }))).into_response(),
+        Err(e) => error_response(axum::http::StatusCode::INTERNAL_SERVER_ERROR, &format!("Failed to list attachments: {}", e)),
+    }
+}
+
+// GET /<camera_path>/control/recordings/:session_id/attachments/:attachment_id
+pub async fn api_download_attachment(
+    headers: axum::http::HeaderMap,
+    AxumPath((session_id, attachment_id)): AxumPath<(i64, String)>,
+    camera_id: String,
+    camera_config: config::CameraConfig,
+    recording_manager: Arc<RecordingManager>,
+) -> axum::response::Response {
+    if let Err(response) = check_api_auth(&headers, &camera_config, config::TokenScope::Playback) {
+        return response;
+    }
+    let Some(database) = recording_manager.get_camera_database(&camera_id).await else {
+        return error_response(axum::http::StatusCode::NOT_FOUND, &format!("Database not found for camera {}", camera_id));
+    };
+    let attachment = match database.get_session_attachment(&attachment_id).await {
+        Ok(Some(attachment)) if attachment.session_id == session_id && attachment.camera_id == camera_id => attachment,
+        Ok(_) => return error_response(axum::http::StatusCode::NOT_FOUND, "Attachment not found"),
+        Err(e) => return error_response(axum::http::StatusCode::INTERNAL_SERVER_ERROR, &format!("Database error: {}", e)),
+    };
+
+    match tokio::fs::read(&attachment.file_path).await {
+        Ok(data) => axum::response::Response::builder()
+            .status(200)
+            .header("Content-Type", &attachment.content_type)
+            .header("Content-Disposition", attachments::content_disposition(&attachment.filename))
+            .header("Content-Length", data.len())
+            .header("Cache-Control", "private, no-cache")
+            .body(axum::body::Body::from(data))
+            .unwrap_or_else(|_| error_response(axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to build response")),
+        Err(e) => {
+            error!("[{}] Failed to read attachment file {}: {}", camera_id, attachment.file_path, e);
+            error_response(axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to read attachment file")
+        }
+    }
+}
+
+// DELETE /<camera_path>/control/recordings/:session_id/attachments/:attachment_id
+pub async fn api_delete_attachment(
+    headers: axum::http::HeaderMap,
+    AxumPath((session_id, attachment_id)): AxumPath<(i64, String)>,
+    camera_id: String,
+    camera_config: config::CameraConfig,
+    recording_manager: Arc<RecordingManager>,
+) -> axum::response::Response {
+    if let Err(response) = check_api_auth(&headers, &camera_config, config::TokenScope::Control) {
+        return response;
+    }
+    let Some(database) = recording_manager.get_camera_database(&camera_id).await else {
+        return error_response(axum::http::StatusCode::NOT_FOUND, &format!("Database not found for camera {}", camera_id));
+    };
+    match database.get_session_attachment(&attachment_id).await {
+        Ok(Some(attachment)) if attachment.session_id == session_id && attachment.camera_id == camera_id => {}
+        Ok(_) => return error_response(axum::http::StatusCode::NOT_FOUND, "Attachment not found"),
+        Err(e) => return error_response(axum::http::StatusCode::INTERNAL_SERVER_ERROR, &format!("Database error: {}", e)),
+    }
+    if let Err(e) = crate::database::ensure_unlocked(database.as_ref(), session_id).await {
+        return locked_response(e);
+    }
+
+    match database.delete_session_attachment(&attachment_id).await {
+        Ok(_) => {
+            info!("[{}] Deleted attachment {} of session {}", camera_id, attachment_id, session_id);
+            Json(ApiResponse::success(serde_json::json!({ "attachment_id": attachment_id }))).into_response()
+        }
+        Err(e) => error_response(axum::http::StatusCode::INTERNAL_SERVER_ERROR, &format!("Failed to delete attachment: {}", e)),
+    }
+}
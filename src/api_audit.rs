@@ -0,0 +1,93 @@
+use axum::{Json, response::IntoResponse, extract::Query};
+
+use crate::{api_recording::ApiResponse, api_config::check_admin_token, database::{AuditQuery, PageRequest}, AppState};
+
+#[derive(Debug, serde::Deserialize)]
+pub struct GetAuditLogQuery {
+    pub from: Option<chrono::DateTime<chrono::Utc>>,
+    pub to: Option<chrono::DateTime<chrono::Utc>>,
+    pub action: Option<String>,  // e.g. "camera.update"
+    pub target: Option<String>,  // e.g. a camera id
+    pub actor: Option<String>,   // Token fingerprint
+    #[serde(default = "default_sort_order_audit")]
+    pub sort_order: String,
+    #[serde(default = "default_audit_limit")]
+    pub limit: i64,
+    #[serde(default)]
+    pub offset: i64,
+}
+
+fn default_sort_order_audit() -> String {
+    "newest".to_string()
+}
+
+fn default_audit_limit() -> i64 {
+    100
+}
+
+// GET /api/admin/audit
+pub async fn api_get_audit_log(
+    headers: axum::http::HeaderMap,
+    Query(query): Query<GetAuditLogQuery>,
+    state: AppState,
+) -> axum::response::Response {
+    if !check_admin_token(&headers, &state.admin_token) {
+        return (axum::http::StatusCode::UNAUTHORIZED,
+                Json(ApiResponse::<()>::error("Unauthorized", 401)))
+               .into_response();
+    }
+
+    let Some(database) = state.audit_log.database() else {
+        return (axum::http::StatusCode::SERVICE_UNAVAILABLE,
+                Json(ApiResponse::<()>::error("Audit log storage requires a recording database", 503)))
+               .into_response();
+    };
+
+    if query.limit < 1 || query.offset < 0 {
+        return (axum::http::StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<()>::error("limit must be positive and offset must not be negative", 400)))
+               .into_response();
+    }
+
+    let filter = AuditQuery {
+        from: query.from,
+        to: query.to,
+        action: query.action.clone(),
+        target: query.target.clone(),
+        actor: query.actor.clone(),
+    };
+    let page = PageRequest {
+        limit: Some(query.limit),
+        offset: query.offset,
+        oldest_first: query.sort_order == "oldest",
+    };
+
+    let total = match database.count_audit_entries(&filter).await {
+        Ok(total) => total,
+        Err(e) => {
+            return (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ApiResponse::<()>::error(&format!("Failed to count audit entries: {}", e), 500)))
+                   .into_response();
+        }
+    };
+
+    match database.list_audit_entries(&filter, &page).await {
+        Ok(entries) => {
+            let next_offset = query.offset + entries.len() as i64;
+            Json(ApiResponse::success(serde_json::json!({
+                "entries": entries,
+                "count": entries.len(),
+                "total": total,
+                "offset": query.offset,
+                "limit": query.limit,
+                "has_more": next_offset < total,
+                "next_offset": (next_offset < total).then_some(next_offset)
+            }))).into_response()
+        }
+        Err(e) => {
+            (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+             Json(ApiResponse::<()>::error(&format!("Failed to list audit entries: {}", e), 500)))
+            .into_response()
+        }
+    }
+}
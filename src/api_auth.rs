@@ -0,0 +1,104 @@
+use axum::{Json, response::IntoResponse};
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::{api_recording::ApiResponse, ldap, AppState};
+
+#[derive(Debug, Deserialize)]
+pub struct LoginRequest {
+    pub username: String,
+    pub password: String,
+}
+
+fn bearer_token(headers: &axum::http::HeaderMap) -> Option<&str> {
+    headers.get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|auth| auth.strip_prefix("Bearer "))
+}
+
+fn session_json(token: Option<&str>, session: &ldap::Session) -> serde_json::Value {
+    let mut value = serde_json::json!({
+        "username": session.username,
+        "roles": session.roles,
+        "admin": session.admin,
+        "cameras": session.cameras,
+        "created_at": session.created_at,
+        "expires_at": session.expires_at,
+    });
+    if let Some(token) = token {
+        value["token"] = serde_json::Value::from(token);
+    }
+    value
+}
+
+// POST /api/auth/login
+pub async fn api_login(
+    Json(request): Json<LoginRequest>,
+    state: AppState,
+) -> axum::response::Response {
+    let Some(ref ldap_config) = state.server_config.ldap else {
+        return (axum::http::StatusCode::NOT_FOUND,
+                Json(ApiResponse::<()>::error("LDAP authentication is not configured", 404)))
+               .into_response();
+    };
+
+    let username = request.username.trim();
+    let groups = match ldap::authenticate(ldap_config, username, &request.password).await {
+        Ok(Some(groups)) => groups,
+        Ok(None) => {
+            warn!("LDAP login of '{}' failed: invalid credentials", username);
+            return (axum::http::StatusCode::UNAUTHORIZED,
+                    Json(ApiResponse::<()>::error("Invalid username or password", 401)))
+                   .into_response();
+        }
+        Err(e) => {
+            warn!("LDAP login of '{}' failed: {}", username, e);
+            return (axum::http::StatusCode::BAD_GATEWAY,
+                    Json(ApiResponse::<()>::error("Directory server unavailable", 502)))
+                   .into_response();
+        }
+    };
+
+    let camera_configs = state.camera_configs.read().await.clone();
+    let Some((token, session)) = ldap::start_session(ldap_config, username, &groups, &camera_configs) else {
+        warn!("LDAP login of '{}' refused: none of its {} groups is mapped to a role", username, groups.len());
+        return (axum::http::StatusCode::FORBIDDEN,
+                Json(ApiResponse::<()>::error("User is not a member of any authorized group", 403)))
+               .into_response();
+    };
+
+    info!("LDAP user '{}' logged in (roles: {}, admin: {})", username, session.roles.join(", "), session.admin);
+    Json(ApiResponse::success(session_json(Some(&token), &session))).into_response()
+}
+
+// POST /api/auth/logout
+pub async fn api_logout(
+    headers: axum::http::HeaderMap,
+) -> axum::response::Response {
+    let Some(token) = bearer_token(&headers) else {
+        return (axum::http::StatusCode::UNAUTHORIZED,
+                Json(ApiResponse::<()>::error("Unauthorized", 401)))
+               .into_response();
+    };
+    let session = ldap::get_session(token);
+    let Some(session) = session.filter(|_| ldap::end_session(token)) else {
+        return (axum::http::StatusCode::UNAUTHORIZED,
+                Json(ApiResponse::<()>::error("Unauthorized", 401)))
+               .into_response();
+    };
+
+    info!("LDAP user '{}' logged out", session.username);
+    Json(ApiResponse::success(serde_json::json!({ "username": session.username }))).into_response()
+}
+
+// GET /api/auth/session
+pub async fn api_get_session(
+    headers: axum::http::HeaderMap,
+) -> axum::response::Response {
+    match bearer_token(&headers).and_then(ldap::get_session) {
+        Some(session) => Json(ApiResponse::success(session_json(None, &session))).into_response(),
+        None => (axum::http::StatusCode::UNAUTHORIZED,
+                 Json(ApiResponse::<()>::error("Unauthorized", 401)))
+                .into_response(),
+    }
+}
@@ -3,23 +3,146 @@ use tracing::info;
 
 use crate::{config, api_recording::ApiResponse, AppState, Args};
 
-fn check_admin_token(headers: &axum::http::HeaderMap, admin_token: &Option<String>) -> bool {
-    let Some(ref expected_token) = admin_token else { return true; };
+lazy_static::lazy_static! {
+    // Held while a config or camera file is read, changed and written, so concurrent edits do not overwrite each other
+    static ref CONFIG_FILE_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::new(());
+}
+
+/// Replace the config file through a synced temporary file, so a crash never leaves a truncated config
+fn write_config_file(path: &str, content: &str) -> std::io::Result<()> {
+    let temp_path = format!("{}.tmp", path);
+    let mut file = std::fs::File::create(&temp_path)?;
+    std::io::Write::write_all(&mut file, content.as_bytes())?;
+    file.sync_all()?;
+    std::fs::rename(&temp_path, path)
+}
+
+/// Admin token in effect; shared so that rotation applies without a restart
+#[derive(Debug, Clone, Default)]
+pub struct AdminToken {
+    pub current: Option<String>,
+    pub previous: Option<config::PreviousToken>,
+}
+
+pub(crate) fn check_admin_token(headers: &axum::http::HeaderMap, admin_token: &std::sync::RwLock<AdminToken>) -> bool {
+    let admin_token = admin_token.read().unwrap_or_else(|e| e.into_inner());
+    let Some(ref expected_token) = admin_token.current else { return true; };
     if let Some(auth_header) = headers.get("Authorization") {
         if let Ok(auth_str) = auth_header.to_str() {
             let token = if auth_str.starts_with("Bearer ") { &auth_str[7..] } else { auth_str };
-            return token == expected_token;
+            return token == expected_token
+                || admin_token.previous.as_ref().is_some_and(|p| p.accepts(token))
+                || crate::ldap::session_is_admin(token);
         }
     }
     false
 }
 
+/// Caller of an admin endpoint: the global admin or the admin of one tenant
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum AdminAccess {
+    Global,
+    Tenant(String),
+}
+
+impl AdminAccess {
+    /// Whether the caller may manage the camera
+    pub(crate) fn allows(&self, camera_config: &config::CameraConfig) -> bool {
+        match self {
+            AdminAccess::Global => true,
+            AdminAccess::Tenant(tenant) => camera_config.tenant.as_deref() == Some(tenant.as_str()),
+        }
+    }
+}
+
+/// Resolve the admin token of a request; tenant admin tokens only grant access to their tenant's cameras
+pub(crate) fn admin_access(headers: &axum::http::HeaderMap, state: &AppState) -> Option<AdminAccess> {
+    let token = headers.get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .map(|auth| auth.strip_prefix("Bearer ").unwrap_or(auth));
+    if let Some(tenant) = token.and_then(|token| state.server_config.tenants.iter().find(|t| t.admin_token == token)) {
+        return Some(AdminAccess::Tenant(tenant.id.clone()));
+    }
+    check_admin_token(headers, &state.admin_token).then_some(AdminAccess::Global)
+}
+
+/// Authorize an admin request for one camera. Cameras of other tenants are reported as not found.
+pub(crate) async fn check_camera_admin(headers: &axum::http::HeaderMap, state: &AppState, camera_id: &str) -> Option<axum::response::Response> {
+    let Some(access) = admin_access(headers, state) else {
+        return Some((axum::http::StatusCode::UNAUTHORIZED,
+                     Json(ApiResponse::<()>::error("Unauthorized", 401)))
+                    .into_response());
+    };
+    // Tenant admins only see their own, existing cameras
+    let visible = match state.camera_configs.read().await.get(camera_id) {
+        Some(camera_config) => access.allows(camera_config),
+        None => access == AdminAccess::Global,
+    };
+    (!visible).then(|| (axum::http::StatusCode::NOT_FOUND,
+                     Json(ApiResponse::<()>::error("Camera not found", 404)))
+                    .into_response())
+}
+
+/// Camera settings that run programs on the server or pass arguments to them
+fn host_execution_settings(camera_config: &config::CameraConfig) -> Vec<(&'static str, serde_json::Value)> {
+    let ffmpeg = camera_config.ffmpeg.as_ref();
+    let redaction = camera_config.export_redaction.as_ref();
+    vec![
+        ("plugins", serde_json::json!(camera_config.plugins)),
+        ("ffmpeg.command", serde_json::json!(ffmpeg.and_then(|f| f.command.as_ref()))),
+        ("ffmpeg.extra_input_args", serde_json::json!(ffmpeg.and_then(|f| f.extra_input_args.as_ref()))),
+        ("ffmpeg.extra_output_args", serde_json::json!(ffmpeg.and_then(|f| f.extra_output_args.as_ref()))),
+        ("export_redaction.command", serde_json::json!(redaction.and_then(|r| r.command.as_ref()))),
+        ("export_redaction.args", serde_json::json!(redaction.map(|r| &r.args))),
+        ("export_redaction.env", serde_json::json!(redaction.map(|r| &r.env))),
+    ]
+}
+
+/// Bind a created or updated camera to the caller's tenant and validate its tenant and path.
+/// Tenant admins cannot set anything executed on the server; settings made by the global
+/// admin may be kept unchanged in an update (`previous`).
+fn assign_tenant(access: &AdminAccess, camera_config: &mut config::CameraConfig, previous: Option<&config::CameraConfig>, state: &AppState) -> Option<axum::response::Response> {
+    if let AdminAccess::Tenant(tenant) = access {
+        if camera_config.tenant.as_ref().is_some_and(|t| t != tenant) {
+            return Some((axum::http::StatusCode::FORBIDDEN,
+                         Json(ApiResponse::<()>::error("Cameras cannot be assigned to another tenant", 403)))
+                        .into_response());
+        }
+        let previous_settings = previous.map(host_execution_settings).unwrap_or_default();
+        let changed: Vec<&str> = host_execution_settings(camera_config).into_iter()
+            .filter(|(_, value)| !(value.is_null() || value == &serde_json::json!([]) || value == &serde_json::json!({})))
+            .filter(|(name, value)| !previous_settings.iter().any(|(n, v)| n == name && v == value))
+            .map(|(name, _)| name)
+            .collect();
+        if !changed.is_empty() {
+            return Some((axum::http::StatusCode::FORBIDDEN,
+                         Json(ApiResponse::<()>::error(&format!("Tenant admins cannot set {}", changed.join(", ")), 403)))
+                        .into_response());
+        }
+        // Secrets are shared by all tenants, a placeholder would send another tenant's secret to any host
+        if !crate::secrets::camera_references(camera_config).is_empty() {
+            return Some((axum::http::StatusCode::FORBIDDEN,
+                         Json(ApiResponse::<()>::error("Tenant admins cannot use ${secret:...} placeholders", 403)))
+                        .into_response());
+        }
+        camera_config.tenant = Some(tenant.clone());
+    }
+    state.server_config.validate_camera_tenant(camera_config).err().map(|e| {
+        (axum::http::StatusCode::BAD_REQUEST,
+         Json(ApiResponse::<()>::error(&e, 400)))
+        .into_response()
+    })
+}
+
 pub async fn api_get_camera_config(
-    _headers: axum::http::HeaderMap,
+    headers: axum::http::HeaderMap,
     path: AxumPath<String>,
     state: AppState,
 ) -> axum::response::Response {
     let camera_id = path.0;
+    if let Some(response) = check_camera_admin(&headers, &state, &camera_id).await {
+        return response;
+    }
     let camera_configs = state.camera_configs.read().await;
     if let Some(camera_config) = camera_configs.get(&camera_id) {
         Json(ApiResponse::success(camera_config.clone())).into_response()
@@ -41,13 +164,13 @@ pub async fn api_create_camera(
     body: axum::extract::Json<CreateCameraRequest>,
     state: AppState,
 ) -> axum::response::Response {
-    if !check_admin_token(&headers, &state.admin_token) {
+    let Some(access) = admin_access(&headers, &state) else {
         return (axum::http::StatusCode::UNAUTHORIZED,
                 Json(ApiResponse::<()>::error("Unauthorized", 401)))
                .into_response();
-    }
+    };
     let camera_id = body.camera_id.clone();
-    let camera_config = body.config.clone();
+    let mut camera_config = body.config.clone();
 
     let camera_configs = state.camera_configs.read().await;
     if camera_configs.contains_key(&camera_id) {
@@ -57,12 +180,16 @@ pub async fn api_create_camera(
     }
     drop(camera_configs);
 
-    if camera_config.path.is_empty() || camera_config.url.is_empty() {
+    if camera_config.path.is_empty() || (camera_config.url.is_empty() && camera_config.device.is_none() && !camera_config.onvif_source_enabled() && camera_config.test_pattern.is_none()) {
         return (axum::http::StatusCode::BAD_REQUEST,
-                Json(ApiResponse::<()>::error("Path and URL are required", 400)))
+                Json(ApiResponse::<()>::error("Path and URL (or device, enabled onvif or test_pattern) are required", 400)))
                .into_response();
     }
 
+    if let Some(response) = assign_tenant(&access, &mut camera_config, None, &state) {
+        return response;
+    }
+
     if let Err(e) = config::Config::save_camera_config(&camera_id, &camera_config, Some(&state.cameras_directory)) {
         return (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
                 Json(ApiResponse::<()>::error(&format!("Failed to save camera config: {}", e), 500)))
@@ -70,6 +197,8 @@ pub async fn api_create_camera(
     }
 
     info!("Camera '{}' created successfully", camera_id);
+    state.audit_log.record(&headers, "camera.create", Some(&camera_id),
+        serde_json::Value::Null, serde_json::to_value(&camera_config).unwrap_or_default()).await;
 
     Json(ApiResponse::success(serde_json::json!({
         "message": "Camera created successfully",
@@ -77,34 +206,73 @@ pub async fn api_create_camera(
     }))).into_response()
 }
 
+#[derive(serde::Deserialize)]
+pub struct ValidateCameraRequest {
+    pub camera_id: Option<String>, // Camera being edited, its own path is no collision
+    pub config: serde_json::Value,
+    #[serde(default = "default_probe")]
+    pub probe: bool, // Connect to the stream and the PTZ service
+}
+
+fn default_probe() -> bool { true }
+
+/// Check a camera config without saving it; problems are reported in the response, not as an error status
+pub async fn api_validate_camera(
+    headers: axum::http::HeaderMap,
+    body: axum::extract::Json<ValidateCameraRequest>,
+    state: AppState,
+) -> axum::response::Response {
+    let Some(access) = admin_access(&headers, &state) else {
+        return (axum::http::StatusCode::UNAUTHORIZED,
+                Json(ApiResponse::<()>::error("Unauthorized", 401)))
+               .into_response();
+    };
+    let request = body.0;
+    if let Some(ref camera_id) = request.camera_id {
+        if let Some(response) = check_camera_admin(&headers, &state, camera_id).await {
+            return response;
+        }
+    }
+
+    let resolved = crate::camera_templates::resolve(request.config, &state.cameras_directory)
+        .and_then(|config| Ok(serde_json::from_value::<config::CameraConfig>(config)?));
+    let report = match resolved {
+        Ok(camera_config) => crate::camera_validation::validate_camera(&state, &access, request.camera_id.as_deref(), camera_config, request.probe).await,
+        Err(e) => crate::camera_validation::ValidationReport::unparsable(e.to_string()),
+    };
+    Json(ApiResponse::success(report)).into_response()
+}
+
 pub async fn api_update_camera(
     headers: axum::http::HeaderMap,
     path: AxumPath<String>,
     body: axum::extract::Json<config::CameraConfig>,
     state: AppState,
 ) -> axum::response::Response {
-    if !check_admin_token(&headers, &state.admin_token) {
+    let Some(access) = admin_access(&headers, &state) else {
         return (axum::http::StatusCode::UNAUTHORIZED,
                 Json(ApiResponse::<()>::error("Unauthorized", 401)))
                .into_response();
-    }
+    };
     let camera_id = path.0;
-    let camera_config = body.0;
+    let mut camera_config = body.0;
 
-    let camera_configs = state.camera_configs.read().await;
-    if !camera_configs.contains_key(&camera_id) {
+    let Some(previous_config) = state.camera_configs.read().await.get(&camera_id).filter(|c| access.allows(c)).cloned() else {
         return (axum::http::StatusCode::NOT_FOUND,
                 Json(ApiResponse::<()>::error("Camera not found", 404)))
                .into_response();
-    }
-    drop(camera_configs);
+    };
 
-    if camera_config.path.is_empty() || camera_config.url.is_empty() {
+    if camera_config.path.is_empty() || (camera_config.url.is_empty() && camera_config.device.is_none() && !camera_config.onvif_source_enabled() && camera_config.test_pattern.is_none()) {
         return (axum::http::StatusCode::BAD_REQUEST,
-                Json(ApiResponse::<()>::error("Path and URL are required", 400)))
+                Json(ApiResponse::<()>::error("Path and URL (or device, enabled onvif or test_pattern) are required", 400)))
                .into_response();
     }
 
+    if let Some(response) = assign_tenant(&access, &mut camera_config, Some(&previous_config), &state) {
+        return response;
+    }
+
     if let Err(e) = config::Config::save_camera_config(&camera_id, &camera_config, Some(&state.cameras_directory)) {
         return (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
                 Json(ApiResponse::<()>::error(&format!("Failed to save camera config: {}", e), 500)))
@@ -112,6 +280,9 @@ pub async fn api_update_camera(
     }
 
     info!("Camera '{}' updated successfully", camera_id);
+    state.audit_log.record(&headers, "camera.update", Some(&camera_id),
+        serde_json::to_value(&previous_config).unwrap_or_default(),
+        serde_json::to_value(&camera_config).unwrap_or_default()).await;
 
     Json(ApiResponse::success(serde_json::json!({
         "message": "Camera updated successfully",
@@ -119,25 +290,396 @@ pub async fn api_update_camera(
     }))).into_response()
 }
 
-pub async fn api_delete_camera(
+#[derive(serde::Deserialize)]
+pub struct TranscodingUpdateRequest {
+    pub scale: Option<String>,           // e.g. "1280:-1", empty string removes scaling
+    pub quality: Option<u8>,             // JPEG quality 1-100
+    pub output_framerate: Option<u32>,   // 0 removes the output framerate limit
+    pub capture_framerate: Option<u32>,  // 0 captures at the camera's natural framerate
+    #[serde(default = "default_persist")]
+    pub persist: bool,                   // Also write the new settings to the camera config file
+}
+
+fn default_persist() -> bool { true }
+
+/// Change scale/quality/framerate of a running camera. Only the FFmpeg process is
+/// restarted; the broadcast channel and connected clients are kept.
+pub async fn api_update_transcoding(
     headers: axum::http::HeaderMap,
     path: AxumPath<String>,
+    body: axum::extract::Json<TranscodingUpdateRequest>,
     state: AppState,
 ) -> axum::response::Response {
-    if !check_admin_token(&headers, &state.admin_token) {
-        return (axum::http::StatusCode::UNAUTHORIZED,
-                Json(ApiResponse::<()>::error("Unauthorized", 401)))
+    let camera_id = path.0;
+    if let Some(response) = check_camera_admin(&headers, &state, &camera_id).await {
+        return response;
+    }
+    let request = body.0;
+
+    let Some(mut camera_config) = state.camera_configs.read().await.get(&camera_id).cloned() else {
+        return (axum::http::StatusCode::NOT_FOUND,
+                Json(ApiResponse::<()>::error("Camera not found", 404)))
                .into_response();
+    };
+    let previous_config = serde_json::to_value(&camera_config).unwrap_or_default();
+
+    let uses_command = crate::transcoding_profiles::effective_ffmpeg(&camera_config).ok().flatten()
+        .is_some_and(|f| f.command.is_some());
+    if uses_command {
+        return (axum::http::StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<()>::error("Camera uses a custom FFmpeg command, transcoding settings cannot be changed", 400)))
+               .into_response();
+    }
+
+    if let Some(quality) = request.quality {
+        if !(1..=100).contains(&quality) {
+            return (axum::http::StatusCode::BAD_REQUEST,
+                    Json(ApiResponse::<()>::error("Quality must be between 1 and 100", 400)))
+                   .into_response();
+        }
+    }
+
+    let mut ffmpeg_config = camera_config.ffmpeg.clone().unwrap_or_default();
+    if let Some(scale) = request.scale {
+        ffmpeg_config.scale = if scale.trim().is_empty() { None } else { Some(scale.trim().to_string()) };
+    }
+    if let Some(quality) = request.quality {
+        ffmpeg_config.quality = Some(quality);
     }
+    if let Some(output_framerate) = request.output_framerate {
+        ffmpeg_config.output_framerate = if output_framerate == 0 { None } else { Some(output_framerate) };
+    }
+    camera_config.ffmpeg = Some(ffmpeg_config);
+    // Changed settings are kept in the camera's own block, on top of its profile
+    let effective_ffmpeg = crate::transcoding_profiles::effective_ffmpeg(&camera_config)
+        .unwrap_or_else(|_| camera_config.ffmpeg.clone());
+
+    if let Some(capture_framerate) = request.capture_framerate {
+        let mut transcoding = camera_config.transcoding_override.clone()
+            .unwrap_or_else(|| state.transcoding_config.as_ref().clone());
+        transcoding.capture_framerate = capture_framerate;
+        camera_config.transcoding_override = Some(transcoding);
+    }
+    let capture_framerate = camera_config.transcoding_override.as_ref()
+        .unwrap_or(&state.transcoding_config)
+        .capture_framerate;
+
+    // Update the running pipeline, if the camera is streaming
+    let pipeline_control = {
+        let mut camera_streams = state.camera_streams.write().await;
+        camera_streams.values_mut()
+            .find(|info| info.camera_id == camera_id)
+            .map(|info| {
+                info.camera_config = camera_config.clone();
+                info.pipeline_control.clone()
+            })
+    };
+    let applied = pipeline_control.is_some();
+    if let Some(pipeline_control) = pipeline_control {
+        pipeline_control.apply(effective_ffmpeg.clone(), capture_framerate).await;
+    }
+
+    {
+        let mut camera_configs = state.camera_configs.write().await;
+        camera_configs.insert(camera_id.clone(), camera_config.clone());
+        if let Some(ref recording_manager) = state.recording_manager {
+            recording_manager.update_camera_configs(camera_configs.clone()).await;
+        }
+    }
+
+    // The file watcher skips the restart because the in-memory config already matches
+    if request.persist {
+        if let Err(e) = config::Config::save_camera_config(&camera_id, &camera_config, Some(&state.cameras_directory)) {
+            return (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ApiResponse::<()>::error(&format!("Failed to save camera config: {}", e), 500)))
+                   .into_response();
+        }
+    }
+
+    info!("Transcoding settings of camera '{}' updated (pipeline restarted: {}, persisted: {})",
+          camera_id, applied, request.persist);
+    state.audit_log.record(&headers, "camera.transcoding", Some(&camera_id),
+        previous_config, serde_json::to_value(&camera_config).unwrap_or_default()).await;
+
+    Json(ApiResponse::success(serde_json::json!({
+        "message": "Transcoding settings updated successfully",
+        "camera_id": camera_id,
+        "pipeline_restarted": applied,
+        "persisted": request.persist,
+        "ffmpeg": effective_ffmpeg,
+        "capture_framerate": capture_framerate
+    }))).into_response()
+}
+
+#[derive(serde::Deserialize)]
+pub struct PreRecordingUpdateRequest {
+    pub buffer_minutes: Option<u64>,     // Duration of the buffer, at least 1
+    pub max_size_mb: Option<u64>,        // Memory limit of the buffer, 0 removes the limit
+    #[serde(default = "default_persist")]
+    pub persist: bool,                   // Also write the new settings to the camera config file
+}
+
+// GET /api/admin/cameras/:id/pre-recording
+pub async fn api_get_pre_recording(
+    headers: axum::http::HeaderMap,
+    path: AxumPath<String>,
+    state: AppState,
+) -> axum::response::Response {
     let camera_id = path.0;
+    if let Some(response) = check_camera_admin(&headers, &state, &camera_id).await {
+        return response;
+    }
 
-    let camera_configs = state.camera_configs.read().await;
-    if !camera_configs.contains_key(&camera_id) {
+    let Some(buffer) = state.camera_streams.read().await.get(&camera_id).map(|info| info.pre_recording_buffer.clone()) else {
+        return (axum::http::StatusCode::NOT_FOUND,
+                Json(ApiResponse::<()>::error("Camera not found or not running", 404)))
+               .into_response();
+    };
+    let Some(buffer) = buffer else {
+        return (axum::http::StatusCode::CONFLICT,
+                Json(ApiResponse::<()>::error("Pre-recording is not enabled for this camera", 409)))
+               .into_response();
+    };
+
+    Json(ApiResponse::success(serde_json::json!({
+        "camera_id": camera_id,
+        "buffer": buffer.get_stats().await
+    }))).into_response()
+}
+
+/// Change duration and memory limit of a camera's pre-recording buffer. The buffer is
+/// trimmed in place, the camera keeps streaming and already buffered frames within the new
+/// limits are kept.
+pub async fn api_update_pre_recording(
+    headers: axum::http::HeaderMap,
+    path: AxumPath<String>,
+    body: axum::extract::Json<PreRecordingUpdateRequest>,
+    state: AppState,
+) -> axum::response::Response {
+    let camera_id = path.0;
+    if let Some(response) = check_camera_admin(&headers, &state, &camera_id).await {
+        return response;
+    }
+    let request = body.0;
+
+    if request.buffer_minutes == Some(0) {
+        return (axum::http::StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<()>::error("Buffer duration must be at least 1 minute", 400)))
+               .into_response();
+    }
+    let Some(mut camera_config) = state.camera_configs.read().await.get(&camera_id).cloned() else {
         return (axum::http::StatusCode::NOT_FOUND,
                 Json(ApiResponse::<()>::error("Camera not found", 404)))
                .into_response();
+    };
+    let previous_config = serde_json::to_value(&camera_config).unwrap_or_default();
+
+    let Some(buffer) = state.camera_streams.read().await.get(&camera_id).map(|info| info.pre_recording_buffer.clone()) else {
+        return (axum::http::StatusCode::NOT_FOUND,
+                Json(ApiResponse::<()>::error("Camera not found or not running", 404)))
+               .into_response();
+    };
+    let Some(buffer) = buffer else {
+        return (axum::http::StatusCode::CONFLICT,
+                Json(ApiResponse::<()>::error("Pre-recording is not enabled for this camera", 409)))
+               .into_response();
+    };
+
+    let buffer_minutes = request.buffer_minutes.unwrap_or_else(|| buffer.buffer_duration_minutes());
+    let max_size_mb = request.max_size_mb.unwrap_or_else(|| buffer.max_size_bytes() / (1024 * 1024));
+    buffer.resize(buffer_minutes, max_size_mb * 1024 * 1024).await;
+
+    let mut recording_config = camera_config.recording.clone().unwrap_or_default();
+    if request.buffer_minutes.is_some() {
+        recording_config.pre_recording_buffer_minutes = Some(buffer_minutes);
     }
-    drop(camera_configs);
+    if request.max_size_mb.is_some() {
+        recording_config.pre_recording_max_size_mb = Some(max_size_mb);
+    }
+    camera_config.recording = Some(recording_config);
+
+    {
+        let mut camera_streams = state.camera_streams.write().await;
+        if let Some(info) = camera_streams.get_mut(&camera_id) {
+            info.camera_config = camera_config.clone();
+        }
+    }
+    {
+        let mut camera_configs = state.camera_configs.write().await;
+        camera_configs.insert(camera_id.clone(), camera_config.clone());
+        if let Some(ref recording_manager) = state.recording_manager {
+            recording_manager.update_camera_configs(camera_configs.clone()).await;
+        }
+    }
+
+    // The file watcher skips the restart because the in-memory config already matches
+    if request.persist {
+        if let Err(e) = config::Config::save_camera_config(&camera_id, &camera_config, Some(&state.cameras_directory)) {
+            return (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ApiResponse::<()>::error(&format!("Failed to save camera config: {}", e), 500)))
+                   .into_response();
+        }
+    }
+
+    info!("Pre-recording buffer of camera '{}' resized to {} minutes, {} MB limit (persisted: {})",
+          camera_id, buffer_minutes, max_size_mb, request.persist);
+    state.audit_log.record(&headers, "camera.pre_recording", Some(&camera_id),
+        previous_config, serde_json::to_value(&camera_config).unwrap_or_default()).await;
+
+    Json(ApiResponse::success(serde_json::json!({
+        "message": "Pre-recording buffer updated successfully",
+        "camera_id": camera_id,
+        "persisted": request.persist,
+        "buffer": buffer.get_stats().await
+    }))).into_response()
+}
+
+// Longest overlap during which a rotated token stays valid
+const MAX_TOKEN_OVERLAP_SECS: u64 = 30 * 24 * 3600;
+
+fn default_token_overlap_secs() -> u64 { 3600 }
+
+#[derive(serde::Deserialize)]
+pub struct RotateTokenRequest {
+    /// Name of the scoped token to rotate; the camera's `token` when omitted (ignored for the admin token)
+    #[serde(default)]
+    pub name: Option<String>,
+    /// New token; a random one is generated when omitted
+    #[serde(default)]
+    pub token: Option<String>,
+    /// How long the replaced token stays valid (0 = revoke it immediately)
+    #[serde(default = "default_token_overlap_secs")]
+    pub overlap_seconds: u64,
+}
+
+impl RotateTokenRequest {
+    /// The new token and the end of the overlap window, or an error message
+    fn resolve(&self, current: Option<&str>) -> Result<(String, chrono::DateTime<chrono::Utc>), String> {
+        if self.overlap_seconds > MAX_TOKEN_OVERLAP_SECS {
+            return Err(format!("overlap_seconds must not exceed {}", MAX_TOKEN_OVERLAP_SECS));
+        }
+        let token = match self.token.as_deref().map(str::trim) {
+            Some("") => return Err("Token must not be empty".to_string()),
+            Some(token) => token.to_string(),
+            None => crate::generate_random_token(32),
+        };
+        if current == Some(token.as_str()) {
+            return Err("New token must differ from the current token".to_string());
+        }
+        Ok((token, chrono::Utc::now() + chrono::Duration::seconds(self.overlap_seconds as i64)))
+    }
+}
+
+pub async fn api_rotate_camera_token(
+    headers: axum::http::HeaderMap,
+    path: AxumPath<String>,
+    body: axum::extract::Json<RotateTokenRequest>,
+    state: AppState,
+) -> axum::response::Response {
+    let camera_id = path.0;
+    if let Some(response) = check_camera_admin(&headers, &state, &camera_id).await {
+        return response;
+    }
+    let request = body.0;
+
+    // Concurrent rotations must each see the token and previous_tokens of the one before
+    let _config_file = CONFIG_FILE_LOCK.lock().await;
+    let Some(mut camera_config) = state.camera_configs.read().await.get(&camera_id).cloned() else {
+        return (axum::http::StatusCode::NOT_FOUND,
+                Json(ApiResponse::<()>::error("Camera not found", 404)))
+               .into_response();
+    };
+    let previous_config = serde_json::to_value(&camera_config).unwrap_or_default();
+
+    let current = match request.name {
+        None => camera_config.token.clone(),
+        Some(ref name) => match camera_config.tokens.iter().find(|t| t.name.as_ref() == Some(name)) {
+            Some(scoped) => Some(scoped.token.clone()),
+            None => {
+                return (axum::http::StatusCode::NOT_FOUND,
+                        Json(ApiResponse::<()>::error(&format!("Token '{}' not found", name), 404)))
+                       .into_response();
+            }
+        },
+    };
+    let (token, valid_until) = match request.resolve(current.as_deref()) {
+        Ok(resolved) => resolved,
+        Err(e) => {
+            return (axum::http::StatusCode::BAD_REQUEST,
+                    Json(ApiResponse::<()>::error(&e, 400)))
+                   .into_response();
+        }
+    };
+
+    match request.name {
+        None => camera_config.token = Some(token.clone()),
+        Some(ref name) => {
+            if let Some(scoped) = camera_config.tokens.iter_mut().find(|t| t.name.as_ref() == Some(name)) {
+                scoped.token = token.clone();
+            }
+        }
+    }
+    camera_config.previous_tokens.retain(|p| !p.is_expired());
+    let overlapping = current.filter(|_| request.overlap_seconds > 0);
+    if let Some(ref previous) = overlapping {
+        camera_config.previous_tokens.push(config::PreviousToken {
+            name: request.name.clone(),
+            token: previous.clone(),
+            valid_until,
+        });
+    }
+
+    // WebSocket handlers check the running stream's config, the camera API routes the shared one
+    {
+        let mut camera_streams = state.camera_streams.write().await;
+        if let Some(info) = camera_streams.values_mut().find(|info| info.camera_id == camera_id) {
+            info.camera_config = camera_config.clone();
+        }
+    }
+    {
+        let mut camera_configs = state.camera_configs.write().await;
+        camera_configs.insert(camera_id.clone(), camera_config.clone());
+        if let Some(ref recording_manager) = state.recording_manager {
+            recording_manager.update_camera_configs(camera_configs.clone()).await;
+        }
+    }
+
+    // The file watcher skips the restart because the in-memory config already matches
+    if let Err(e) = config::Config::save_camera_config(&camera_id, &camera_config, Some(&state.cameras_directory)) {
+        return (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::<()>::error(&format!("Failed to save camera config: {}", e), 500)))
+               .into_response();
+    }
+
+    info!("Token {} of camera '{}' rotated (previous token valid until {})",
+          request.name.as_deref().unwrap_or("token"), camera_id,
+          if overlapping.is_some() { valid_until.to_rfc3339() } else { "now".to_string() });
+    state.audit_log.record(&headers, "camera.token_rotate", Some(&camera_id),
+        previous_config, serde_json::to_value(&camera_config).unwrap_or_default()).await;
+
+    Json(ApiResponse::success(serde_json::json!({
+        "camera_id": camera_id,
+        "name": request.name,
+        "token": token,
+        "previous_valid_until": overlapping.map(|_| valid_until)
+    }))).into_response()
+}
+
+pub async fn api_delete_camera(
+    headers: axum::http::HeaderMap,
+    path: AxumPath<String>,
+    state: AppState,
+) -> axum::response::Response {
+    let camera_id = path.0;
+    if let Some(response) = check_camera_admin(&headers, &state, &camera_id).await {
+        return response;
+    }
+
+    let Some(previous_config) = state.camera_configs.read().await.get(&camera_id).cloned() else {
+        return (axum::http::StatusCode::NOT_FOUND,
+                Json(ApiResponse::<()>::error("Camera not found", 404)))
+               .into_response();
+    };
 
     if let Err(e) = state.remove_camera(&camera_id).await {
         return (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
@@ -152,6 +694,8 @@ pub async fn api_delete_camera(
     }
 
     info!("Camera '{}' deleted successfully", camera_id);
+    state.audit_log.record(&headers, "camera.delete", Some(&camera_id),
+        serde_json::to_value(&previous_config).unwrap_or_default(), serde_json::Value::Null).await;
 
     Json(ApiResponse::success(serde_json::json!({
         "message": "Camera deleted successfully",
@@ -159,6 +703,156 @@ pub async fn api_delete_camera(
     }))).into_response()
 }
 
+fn camera_running_error(action: &str, error: crate::errors::StreamError) -> axum::response::Response {
+    match error {
+        crate::errors::StreamError::NotFound { message } => {
+            (axum::http::StatusCode::NOT_FOUND,
+             Json(ApiResponse::<()>::error(&message, 404)))
+            .into_response()
+        }
+        crate::errors::StreamError::Config { message } => {
+            (axum::http::StatusCode::CONFLICT,
+             Json(ApiResponse::<()>::error(&message, 409)))
+            .into_response()
+        }
+        e => {
+            (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+             Json(ApiResponse::<()>::error(&format!("Failed to {} camera: {}", action, e), 500)))
+            .into_response()
+        }
+    }
+}
+
+// POST /api/admin/cameras/:id/restart
+pub async fn api_restart_camera(
+    headers: axum::http::HeaderMap,
+    path: AxumPath<String>,
+    state: AppState,
+) -> axum::response::Response {
+    let camera_id = path.0;
+    if let Some(response) = check_camera_admin(&headers, &state, &camera_id).await {
+        return response;
+    }
+
+    let Some(camera_config) = state.camera_configs.read().await.get(&camera_id).cloned() else {
+        return (axum::http::StatusCode::NOT_FOUND,
+                Json(ApiResponse::<()>::error("Camera not found", 404)))
+               .into_response();
+    };
+    if !camera_config.enabled.unwrap_or(true) {
+        return camera_running_error("restart", crate::errors::StreamError::config(
+            format!("Camera '{}' is disabled in its configuration", camera_id)));
+    }
+
+    let was_running = state.camera_streams.read().await.contains_key(&camera_id);
+    // Keeps an active recording running across the restart
+    if let Err(e) = state.restart_camera(camera_id.clone(), camera_config).await {
+        return camera_running_error("restart", e);
+    }
+
+    info!("Camera '{}' restarted via API", camera_id);
+    state.audit_log.record(&headers, "camera.restart", Some(&camera_id),
+        serde_json::json!({ "running": was_running }), serde_json::json!({ "running": true })).await;
+
+    Json(ApiResponse::success(serde_json::json!({
+        "message": "Camera restarted",
+        "camera_id": camera_id,
+        "was_running": was_running
+    }))).into_response()
+}
+
+// POST /api/admin/cameras/:id/stop
+pub async fn api_stop_camera(
+    headers: axum::http::HeaderMap,
+    path: AxumPath<String>,
+    state: AppState,
+) -> axum::response::Response {
+    let camera_id = path.0;
+    if let Some(response) = check_camera_admin(&headers, &state, &camera_id).await {
+        return response;
+    }
+
+    if !state.camera_configs.read().await.contains_key(&camera_id) {
+        return (axum::http::StatusCode::NOT_FOUND,
+                Json(ApiResponse::<()>::error("Camera not found", 404)))
+               .into_response();
+    }
+
+    let was_running = state.stop_camera_stream(&camera_id).await;
+    if was_running {
+        info!("Camera '{}' stopped via API", camera_id);
+        state.audit_log.record(&headers, "camera.stop", Some(&camera_id),
+            serde_json::json!({ "running": true }), serde_json::json!({ "running": false })).await;
+    }
+
+    Json(ApiResponse::success(serde_json::json!({
+        "message": if was_running { "Camera stopped" } else { "Camera was not running" },
+        "camera_id": camera_id,
+        "was_running": was_running
+    }))).into_response()
+}
+
+// POST /api/admin/cameras/:id/start
+pub async fn api_start_camera(
+    headers: axum::http::HeaderMap,
+    path: AxumPath<String>,
+    state: AppState,
+) -> axum::response::Response {
+    let camera_id = path.0;
+    if let Some(response) = check_camera_admin(&headers, &state, &camera_id).await {
+        return response;
+    }
+
+    let started = match state.start_camera_stream(&camera_id).await {
+        Ok(started) => started,
+        Err(e) => return camera_running_error("start", e),
+    };
+    if started {
+        info!("Camera '{}' started via API", camera_id);
+        state.audit_log.record(&headers, "camera.start", Some(&camera_id),
+            serde_json::json!({ "running": false }), serde_json::json!({ "running": true })).await;
+    }
+
+    Json(ApiResponse::success(serde_json::json!({
+        "message": if started { "Camera started" } else { "Camera is already running" },
+        "camera_id": camera_id,
+        "was_running": !started
+    }))).into_response()
+}
+
+// DELETE /api/admin/cameras/:id/quarantine
+pub async fn api_clear_quarantine(
+    headers: axum::http::HeaderMap,
+    path: AxumPath<String>,
+    state: AppState,
+) -> axum::response::Response {
+    let camera_id = path.0;
+    if let Some(response) = check_camera_admin(&headers, &state, &camera_id).await {
+        return response;
+    }
+
+    let Some(pipeline_control) = state.camera_streams.read().await.get(&camera_id).map(|info| info.pipeline_control.clone()) else {
+        return (axum::http::StatusCode::NOT_FOUND,
+                Json(ApiResponse::<()>::error("Camera not found or not running", 404)))
+               .into_response();
+    };
+    let quarantined_since = pipeline_control.reconnect_status().and_then(|status| status.quarantined_since);
+    if !pipeline_control.clear_quarantine() {
+        return (axum::http::StatusCode::CONFLICT,
+                Json(ApiResponse::<()>::error("Camera is not quarantined", 409)))
+               .into_response();
+    }
+
+    info!("Camera '{}' released from quarantine via API", camera_id);
+    state.audit_log.record(&headers, "camera.quarantine.clear", Some(&camera_id),
+        serde_json::json!({ "quarantined_since": quarantined_since }), serde_json::json!({ "quarantined": false })).await;
+
+    Json(ApiResponse::success(serde_json::json!({
+        "message": "Quarantine cleared, reconnecting",
+        "camera_id": camera_id
+    }))).into_response()
+}
+
 pub async fn api_get_config(
     headers: axum::http::HeaderMap,
     args: Args,
@@ -193,6 +887,13 @@ pub async fn api_get_config(
                 transcoding: (*state.transcoding_config).clone(),
                 mqtt: None, // We don't store the full MQTT config in AppState
                 recording: state.recording_config.as_ref().map(|rc| (**rc).clone()),
+                smtp: None,
+                transcoding_profiles: crate::transcoding_profiles::all(),
+                stats: None,
+                jpeg_encoder: Some(crate::jpeg::settings()),
+                relay: None,
+                relay_server: None,
+                reports: None,
             };
             drop(cameras);
             
@@ -203,7 +904,7 @@ pub async fn api_get_config(
 
 /// Compare old and new config JSON values and return which top-level sections changed.
 fn detect_changed_sections(old_config: &serde_json::Value, new_config: &serde_json::Value) -> Vec<String> {
-    let sections = ["server", "transcoding", "mqtt", "recording"];
+    let sections = ["server", "transcoding", "transcoding_profiles", "mqtt", "recording", "stats", "relay", "relay_server", "reports"];
     let mut changed = Vec::new();
 
     for section in &sections {
@@ -249,6 +950,7 @@ pub async fn api_update_config(
     }
 
     let config_path = &args.config;
+    let _config_file = CONFIG_FILE_LOCK.lock().await;
 
     // Try to load current config from file, or use in-memory config if file doesn't exist
     let current_config = match config::Config::load(config_path) {
@@ -262,6 +964,13 @@ pub async fn api_update_config(
                 transcoding: (*state.transcoding_config).clone(),
                 mqtt: None,
                 recording: state.recording_config.as_ref().map(|rc| (**rc).clone()),
+                smtp: None,
+                transcoding_profiles: crate::transcoding_profiles::all(),
+                stats: None,
+                jpeg_encoder: Some(crate::jpeg::settings()),
+                relay: None,
+                relay_server: None,
+                reports: None,
             };
             drop(cameras);
             config
@@ -297,9 +1006,11 @@ pub async fn api_update_config(
                 }
             };
 
-            match std::fs::write(config_path, content) {
+            match write_config_file(config_path, &content) {
                 Ok(_) => {
                     let changed_sections = detect_changed_sections(&old_config_value, &current_config_value);
+                    state.audit_log.record(&headers, "config.update", None,
+                        old_config_value.clone(), current_config_value.clone()).await;
 
                     if changed_sections.is_empty() {
                         info!("Server configuration saved (no changes detected)");
@@ -310,7 +1021,7 @@ pub async fn api_update_config(
                         }))).into_response()
                     } else {
                         let camera_affecting: Vec<&String> = changed_sections.iter()
-                            .filter(|s| matches!(s.as_str(), "transcoding" | "recording" | "mqtt"))
+                            .filter(|s| matches!(s.as_str(), "transcoding" | "transcoding_profiles" | "recording" | "mqtt"))
                             .collect();
                         let camera_restart_recommended = !camera_affecting.is_empty();
 
@@ -351,3 +1062,82 @@ pub async fn api_update_config(
         }
     }
 }
+
+pub async fn api_rotate_admin_token(
+    headers: axum::http::HeaderMap,
+    body: axum::extract::Json<RotateTokenRequest>,
+    args: Args,
+    state: AppState,
+) -> axum::response::Response {
+    // Tenant admins cannot replace the global admin token
+    if !check_admin_token(&headers, &state.admin_token) {
+        return (axum::http::StatusCode::UNAUTHORIZED,
+                Json(ApiResponse::<()>::error("Unauthorized", 401)))
+               .into_response();
+    }
+    let request = body.0;
+
+    // Rotations run one at a time, each one replaces the token the previous one set
+    let _config_file = CONFIG_FILE_LOCK.lock().await;
+    let before = state.admin_token.read().unwrap_or_else(|e| e.into_inner()).clone();
+    let (token, valid_until) = match request.resolve(before.current.as_deref()) {
+        Ok(resolved) => resolved,
+        Err(e) => {
+            return (axum::http::StatusCode::BAD_REQUEST,
+                    Json(ApiResponse::<()>::error(&e, 400)))
+                   .into_response();
+        }
+    };
+    let previous = before.current.clone()
+        .filter(|_| request.overlap_seconds > 0)
+        .map(|previous| config::PreviousToken { name: None, token: previous, valid_until });
+
+    // Edit the file as JSON so that unrelated settings keep their original form (e.g. ${VAR} placeholders)
+    let config_path = &args.config;
+    let mut config_value = match std::fs::read_to_string(config_path)
+        .map_err(|e| e.to_string())
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).map_err(|e| e.to_string()))
+    {
+        Ok(value) => value,
+        Err(e) => {
+            return (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ApiResponse::<()>::error(&format!("Failed to read config file: {}", e), 500)))
+                   .into_response();
+        }
+    };
+    let Some(server) = config_value.get_mut("server").and_then(|s| s.as_object_mut()) else {
+        return (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::<()>::error("Config file has no server section", 500)))
+               .into_response();
+    };
+    server.insert("admin_token".to_string(), serde_json::Value::String(token.clone()));
+    match previous {
+        Some(ref previous) => { server.insert("previous_admin_token".to_string(), serde_json::to_value(previous).unwrap_or_default()); }
+        None => { server.remove("previous_admin_token"); }
+    }
+    let written = serde_json::to_string_pretty(&config_value)
+        .map_err(|e| e.to_string())
+        .and_then(|content| write_config_file(config_path, &content).map_err(|e| e.to_string()));
+    if let Err(e) = written {
+        return (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::<()>::error(&format!("Failed to save config file: {}", e), 500)))
+               .into_response();
+    }
+
+    {
+        let mut admin_token = state.admin_token.write().unwrap_or_else(|e| e.into_inner());
+        admin_token.current = Some(token.clone());
+        admin_token.previous = previous.clone();
+    }
+
+    info!("Admin token rotated (previous token valid until {})",
+          previous.as_ref().map(|p| p.valid_until.to_rfc3339()).unwrap_or_else(|| "now".to_string()));
+    state.audit_log.record(&headers, "admin_token.rotate", None,
+        serde_json::json!({ "admin_token": before.current, "previous_admin_token": before.previous }),
+        serde_json::json!({ "admin_token": token, "previous_admin_token": previous })).await;
+
+    Json(ApiResponse::success(serde_json::json!({
+        "token": token,
+        "previous_valid_until": previous.map(|p| p.valid_until)
+    }))).into_response()
+}
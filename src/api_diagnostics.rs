@@ -0,0 +1,140 @@
+use std::collections::BTreeMap;
+use axum::{Json, response::IntoResponse};
+use chrono::Utc;
+use serde_json::Value;
+use tracing::{info, warn};
+
+use crate::{api_recording::ApiResponse, api_config::check_admin_token, diagnostics, frame_archive::FrameArchive, AppState};
+
+// GET /api/admin/diagnostics
+pub async fn api_get_diagnostics(
+    headers: axum::http::HeaderMap,
+    config_path: String,
+    state: AppState,
+) -> axum::response::Response {
+    if !check_admin_token(&headers, &state.admin_token) {
+        return (axum::http::StatusCode::UNAUTHORIZED,
+                Json(ApiResponse::<()>::error("Unauthorized", 401)))
+               .into_response();
+    }
+
+    let generated_at = Utc::now();
+    let mut archive = FrameArchive::default();
+    let mut zip = Vec::new();
+    let mut add = |name: &str, content: Vec<u8>| {
+        zip.extend_from_slice(&archive.add_frame(name.to_string(), generated_at, &content));
+    };
+
+    let mut server = crate::api_version::version_info(&state).await;
+    if let Some(server) = server.as_object_mut() {
+        server.insert("generated_at".to_string(), Value::String(generated_at.to_rfc3339()));
+    }
+    add("server.json", to_json(&server));
+    add("config.json", to_json(&server_config(&config_path)));
+    add("cameras.json", to_json(&camera_configs(&state).await));
+    add("status.json", to_json(&camera_status(&state).await));
+    add("ffmpeg_commands.json", to_json(&diagnostics::ffmpeg_commands().into_iter().collect::<BTreeMap<_, _>>()));
+    add("database.json", to_json(&database_info(&state).await));
+    let mut logs = diagnostics::recent_logs().join("\n");
+    logs.push('\n');
+    add("logs.txt", logs.into_bytes());
+    zip.extend_from_slice(&archive.finish());
+
+    info!("[API] Diagnostic bundle generated ({} bytes)", zip.len());
+    axum::response::Response::builder()
+        .status(200)
+        .header("Content-Type", "application/zip")
+        .header("Content-Disposition", format!("attachment; filename=\"diagnostics_{}.zip\"", generated_at.format("%Y%m%dT%H%M%SZ")))
+        .header("Cache-Control", "private, no-cache")
+        .body(axum::body::Body::from(zip))
+        .unwrap_or_else(|_| {
+            Json(ApiResponse::<()>::error("Failed to build response", 500)).into_response()
+        })
+}
+
+fn to_json(value: &impl serde::Serialize) -> Vec<u8> {
+    serde_json::to_vec_pretty(value).unwrap_or_default()
+}
+
+// The config file as written, the cameras are listed separately with their templates applied
+fn server_config(config_path: &str) -> Value {
+    let config = std::fs::read_to_string(config_path)
+        .map_err(|e| e.to_string())
+        .and_then(|content| serde_json::from_str::<Value>(&content).map_err(|e| e.to_string()));
+    match config {
+        Ok(config) => diagnostics::sanitize(config),
+        Err(e) => serde_json::json!({ "error": format!("Failed to read {}: {}", config_path, e) }),
+    }
+}
+
+async fn camera_configs(state: &AppState) -> BTreeMap<String, Value> {
+    state.camera_configs.read().await
+        .iter()
+        .map(|(camera_id, camera_config)| {
+            (camera_id.clone(), diagnostics::sanitize(serde_json::to_value(camera_config).unwrap_or_default()))
+        })
+        .collect()
+}
+
+async fn camera_status(state: &AppState) -> BTreeMap<String, Value> {
+    let mqtt_status = match &state.mqtt_handle {
+        Some(mqtt_handle) => mqtt_handle.get_all_camera_status().await,
+        None => Default::default(),
+    };
+    let running: BTreeMap<String, Value> = state.camera_streams.read().await
+        .iter()
+        .map(|(camera_id, info)| (camera_id.clone(), serde_json::json!({
+            "receivers": info.frame_sender.receiver_count(),
+            "standby": info.pipeline_control.is_standby(),
+            "reconnect": info.pipeline_control.reconnect_status(),
+        })))
+        .collect();
+
+    let camera_configs = state.camera_configs.read().await.clone();
+    camera_configs.iter()
+        .map(|(camera_id, camera_config)| (camera_id.clone(), serde_json::json!({
+            "path": camera_config.path,
+            "enabled": camera_config.enabled.unwrap_or(true),
+            "running": running.contains_key(camera_id),
+            "stream": running.get(camera_id),
+            "mqtt_status": mqtt_status.get(camera_id),
+            "ffmpeg_usage": crate::resources::get_ffmpeg_usage(camera_id),
+            "stream_health": crate::stream_health::get_health(camera_id),
+            "clock_drift": crate::clock_drift::get_drift(camera_id),
+            "tamper": crate::tamper::get_state(camera_id),
+            "viewers": crate::viewers::list(Some(camera_id)).len(),
+        })))
+        .collect()
+}
+
+async fn database_info(state: &AppState) -> Value {
+    let Some(ref recording_manager) = state.recording_manager else {
+        return serde_json::json!({ "recording": false });
+    };
+    let mut databases: Vec<_> = recording_manager.databases.read().await
+        .iter()
+        .map(|(camera_id, database)| (camera_id.clone(), database.clone()))
+        .collect();
+    databases.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut cameras = BTreeMap::new();
+    for (camera_id, database) in databases {
+        let size_bytes = database.get_database_size().await.ok();
+        let schema = match database.get_schema_info().await {
+            Ok(schema) => serde_json::to_value(schema).unwrap_or_default(),
+            Err(e) => {
+                warn!("[API] Failed to read database schema of camera '{}': {}", camera_id, e);
+                serde_json::json!({ "error": e.to_string() })
+            }
+        };
+        cameras.insert(camera_id, serde_json::json!({
+            "size_bytes": size_bytes,
+            "schema": schema,
+        }));
+    }
+    serde_json::json!({
+        "recording": true,
+        "database_type": state.recording_config.as_ref().map(|r| &r.database_type),
+        "cameras": cameras,
+    })
+}
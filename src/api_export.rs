@@ -11,12 +11,16 @@ use tracing::{info, error};
 
 use crate::config;
 use crate::export_jobs::{ExportJobManager, ExportJobStatus};
-use crate::api_recording::{ApiResponse, check_api_auth};
+use crate::recording::RecordingManager;
+use crate::api_recording::{ApiResponse, check_api_auth, check_recording_auth};
 
 #[derive(Debug, Deserialize)]
 pub struct ExportQuery {
     pub from: DateTime<Utc>,
     pub to: DateTime<Utc>,
+    /// Apply the camera's `export_redaction` to the exported file
+    #[serde(default)]
+    pub redact: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -31,20 +35,37 @@ pub async fn api_export_start(
     camera_id: String,
     camera_config: config::CameraConfig,
     export_manager: Arc<ExportJobManager>,
+    recording_manager: Option<Arc<RecordingManager>>,
 ) -> Response {
     // Check authentication
-    if let Err(e) = check_api_auth(&headers, &camera_config) {
+    if let Some(e) = check_recording_auth(&headers, &camera_config) {
         return e.into_response();
     }
 
+    // Archived segments have to be rehydrated before they can be exported
+    if let Some(recording_manager) = recording_manager {
+        if let Some(database) = recording_manager.get_camera_database(&camera_id).await {
+            if let Some(response) = crate::cold_storage::rehydration_response(&database, &camera_id, query.from, query.to).await {
+                return response;
+            }
+        }
+    }
+
+    if query.redact && camera_config.export_redaction.is_none() {
+        let response = ApiResponse::<()>::error("Export redaction is not configured for this camera", 400);
+        return (StatusCode::BAD_REQUEST, Json(response)).into_response();
+    }
+    let redaction = camera_config.export_redaction.clone()
+        .filter(|redaction| query.redact || redaction.required);
+
     info!(
-        "[{}] Starting export job from {} to {}",
-        camera_id, query.from, query.to
+        "[{}] Starting export job from {} to {} (redacted: {})",
+        camera_id, query.from, query.to, redaction.is_some()
     );
 
     // Create the export job
     let job_id = export_manager
-        .create_job(camera_id.clone(), query.from, query.to)
+        .create_job(camera_id.clone(), query.from, query.to, redaction)
         .await;
 
     let job = export_manager.get_job(&job_id).await;
@@ -57,6 +78,7 @@ pub async fn api_export_start(
                 "output_filename": job.output_filename,
                 "from_time": job.from_time,
                 "to_time": job.to_time,
+                "redacted": job.redacted,
             }));
 
             (StatusCode::OK, Json(response)).into_response()
@@ -77,7 +99,7 @@ pub async fn api_export_get_job(
     export_manager: Arc<ExportJobManager>,
 ) -> Response {
     // Check authentication
-    if let Err(e) = check_api_auth(&headers, &camera_config) {
+    if let Err(e) = check_api_auth(&headers, &camera_config, config::TokenScope::Playback) {
         return e.into_response();
     }
 
@@ -108,7 +130,7 @@ pub async fn api_export_list_jobs(
     export_manager: Arc<ExportJobManager>,
 ) -> Response {
     // Check authentication
-    if let Err(e) = check_api_auth(&headers, &camera_config) {
+    if let Err(e) = check_api_auth(&headers, &camera_config, config::TokenScope::Playback) {
         return e.into_response();
     }
 
@@ -145,7 +167,7 @@ pub async fn api_export_download(
     export_manager: Arc<ExportJobManager>,
 ) -> Response {
     // Check authentication
-    if let Err(e) = check_api_auth(&headers, &camera_config) {
+    if let Some(e) = check_recording_auth(&headers, &camera_config) {
         return e.into_response();
     }
 
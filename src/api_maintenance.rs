@@ -0,0 +1,465 @@
+use axum::{Json, response::IntoResponse, extract::{Path, Query}};
+use tracing::{info, warn};
+
+use crate::{config, integrity, api_recording::ApiResponse, api_config::{check_admin_token, check_camera_admin}, AppState};
+
+#[derive(Debug, serde::Deserialize)]
+pub struct VacuumQuery {
+    pub camera_id: Option<String>,
+    pub mode: Option<config::VacuumMode>,
+}
+
+pub async fn api_vacuum(
+    headers: axum::http::HeaderMap,
+    Query(query): Query<VacuumQuery>,
+    state: AppState,
+) -> axum::response::Response {
+    if !check_admin_token(&headers, &state.admin_token) {
+        return (axum::http::StatusCode::UNAUTHORIZED,
+                Json(ApiResponse::<()>::error("Unauthorized", 401)))
+               .into_response();
+    }
+
+    let Some(ref recording_manager) = state.recording_manager else {
+        return (axum::http::StatusCode::SERVICE_UNAVAILABLE,
+                Json(ApiResponse::<()>::error("Recording is not enabled", 503)))
+               .into_response();
+    };
+
+    let mode = query.mode.unwrap_or(recording_manager.get_recording_config().vacuum_mode);
+    info!("[API] Vacuum requested (camera: {:?}, mode: {})", query.camera_id, mode);
+
+    match recording_manager.vacuum_databases(query.camera_id.as_deref(), mode).await {
+        Ok(reports) => {
+            let total_reclaimed: i64 = reports.iter().map(|r| r.reclaimed_bytes).sum();
+            state.audit_log.record(&headers, "maintenance.vacuum", query.camera_id.as_deref(),
+                serde_json::Value::Null, serde_json::json!({ "mode": mode })).await;
+            Json(ApiResponse::success(serde_json::json!({
+                "mode": mode,
+                "databases": reports,
+                "total_reclaimed_bytes": total_reclaimed,
+            }))).into_response()
+        }
+        Err(crate::errors::StreamError::NotFound { message }) => {
+            (axum::http::StatusCode::NOT_FOUND,
+             Json(ApiResponse::<()>::error(&message, 404)))
+            .into_response()
+        }
+        Err(e) => {
+            (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+             Json(ApiResponse::<()>::error(&format!("Vacuum failed: {}", e), 500)))
+            .into_response()
+        }
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct VerifyQuery {
+    pub camera: String,
+    pub from: chrono::DateTime<chrono::Utc>,
+    pub to: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct MergeSessionsRequest {
+    pub session_ids: [i64; 2],
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct SplitSessionRequest {
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct LockSessionRequest {
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct UnlockSessionRequest {
+    pub unlock_token: Option<String>,
+}
+
+// How long an unlock request waits for the second admin
+const UNLOCK_REQUEST_HOURS: i64 = 24;
+
+async fn camera_database(
+    state: &AppState,
+    camera_id: &str,
+) -> std::result::Result<std::sync::Arc<dyn crate::database::DatabaseProvider>, axum::response::Response> {
+    let Some(ref recording_manager) = state.recording_manager else {
+        return Err((axum::http::StatusCode::SERVICE_UNAVAILABLE,
+                    Json(ApiResponse::<()>::error("Recording is not enabled", 503)))
+                   .into_response());
+    };
+
+    let databases = recording_manager.databases.read().await;
+    databases.get(camera_id).cloned().ok_or_else(|| {
+        (axum::http::StatusCode::NOT_FOUND,
+         Json(ApiResponse::<()>::error(&format!("Database not found for camera {}", camera_id), 404)))
+        .into_response()
+    })
+}
+
+/// Reject sessions of other cameras; in a shared database the ids of all cameras are mixed
+async fn check_session_camera(
+    database: &std::sync::Arc<dyn crate::database::DatabaseProvider>,
+    camera_id: &str,
+    session_id: i64,
+) -> Option<axum::response::Response> {
+    match database.get_recording_session(session_id).await {
+        Ok(Some(session)) if session.camera_id == camera_id => None,
+        Ok(_) => Some((axum::http::StatusCode::NOT_FOUND,
+                       Json(ApiResponse::<()>::error(&format!("Recording session {} not found", session_id), 404)))
+                      .into_response()),
+        Err(e) => Some((axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(ApiResponse::<()>::error(&format!("Database error: {}", e), 500)))
+                       .into_response()),
+    }
+}
+
+fn session_error_response(action: &str, error: crate::errors::StreamError) -> axum::response::Response {
+    match error {
+        crate::errors::StreamError::NotFound { message } => {
+            (axum::http::StatusCode::NOT_FOUND,
+             Json(ApiResponse::<()>::error(&message, 404)))
+            .into_response()
+        }
+        crate::errors::StreamError::Config { message } => {
+            (axum::http::StatusCode::BAD_REQUEST,
+             Json(ApiResponse::<()>::error(&message, 400)))
+            .into_response()
+        }
+        crate::errors::StreamError::Locked { message } => {
+            (axum::http::StatusCode::CONFLICT,
+             Json(ApiResponse::<()>::error(&message, 409)))
+            .into_response()
+        }
+        e => {
+            (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+             Json(ApiResponse::<()>::error(&format!("{} failed: {}", action, e), 500)))
+            .into_response()
+        }
+    }
+}
+
+// POST /api/admin/cameras/:id/sessions/merge
+pub async fn api_merge_sessions(
+    headers: axum::http::HeaderMap,
+    Path(camera_id): Path<String>,
+    Json(request): Json<MergeSessionsRequest>,
+    state: AppState,
+) -> axum::response::Response {
+    if let Some(response) = check_camera_admin(&headers, &state, &camera_id).await {
+        return response;
+    }
+
+    let database = match camera_database(&state, &camera_id).await {
+        Ok(database) => database,
+        Err(response) => return response,
+    };
+
+    let [first, second] = request.session_ids;
+    for session_id in [first, second] {
+        if let Some(response) = check_session_camera(&database, &camera_id, session_id).await {
+            return response;
+        }
+    }
+    info!("[API] Merging recording sessions {} and {} of camera '{}'", first, second, camera_id);
+
+    match database.merge_recording_sessions(first, second).await {
+        Ok(stats) => {
+            crate::segment_cache::invalidate_camera(&camera_id);
+            state.audit_log.record(&headers, "session.merge", Some(&camera_id),
+                serde_json::json!({ "session_ids": [first, second] }),
+                serde_json::json!({ "session_ids": [stats.session_id] })).await;
+            Json(ApiResponse::success(serde_json::json!({
+                "session_id": stats.session_id,
+                "merged_session_id": stats.merged_session_id,
+                "moved": {
+                    "frames": stats.frames_moved,
+                    "mp4_segments": stats.mp4_segments_moved,
+                    "hls_segments": stats.hls_segments_moved
+                }
+            }))).into_response()
+        }
+        Err(e) => session_error_response("Merge", e),
+    }
+}
+
+// POST /api/admin/cameras/:id/sessions/:session_id/split
+pub async fn api_split_session(
+    headers: axum::http::HeaderMap,
+    Path((camera_id, session_id)): Path<(String, i64)>,
+    Json(request): Json<SplitSessionRequest>,
+    state: AppState,
+) -> axum::response::Response {
+    if let Some(response) = check_camera_admin(&headers, &state, &camera_id).await {
+        return response;
+    }
+
+    let database = match camera_database(&state, &camera_id).await {
+        Ok(database) => database,
+        Err(response) => return response,
+    };
+
+    if let Some(response) = check_session_camera(&database, &camera_id, session_id).await {
+        return response;
+    }
+
+    info!("[API] Splitting recording session {} of camera '{}' at {}", session_id, camera_id, request.timestamp);
+
+    match database.split_recording_session(session_id, request.timestamp).await {
+        Ok(stats) => {
+            crate::segment_cache::invalidate_camera(&camera_id);
+            state.audit_log.record(&headers, "session.split", Some(&camera_id),
+                serde_json::json!({ "session_ids": [stats.session_id] }),
+                serde_json::json!({ "session_ids": [stats.session_id, stats.new_session_id], "split_time": stats.split_time })).await;
+            Json(ApiResponse::success(serde_json::json!({
+                "session_id": stats.session_id,
+                "new_session_id": stats.new_session_id,
+                "split_time": stats.split_time,
+                "moved": {
+                    "frames": stats.frames_moved,
+                    "mp4_segments": stats.mp4_segments_moved,
+                    "hls_segments": stats.hls_segments_moved
+                }
+            }))).into_response()
+        }
+        Err(e) => session_error_response("Split", e),
+    }
+}
+
+// POST /api/admin/verify?camera=&from=&to=
+pub async fn api_verify(
+    headers: axum::http::HeaderMap,
+    Query(query): Query<VerifyQuery>,
+    state: AppState,
+) -> axum::response::Response {
+    if let Some(response) = check_camera_admin(&headers, &state, &query.camera).await {
+        return response;
+    }
+    if query.from > query.to {
+        return (axum::http::StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<()>::error("'from' must not be after 'to'", 400)))
+               .into_response();
+    }
+
+    let database = match camera_database(&state, &query.camera).await {
+        Ok(database) => database,
+        Err(response) => return response,
+    };
+
+    info!("[API] Verifying recordings of camera '{}' from {} to {}", query.camera, query.from, query.to);
+
+    match integrity::verify(&database, &query.camera, query.from, query.to).await {
+        Ok(report) => {
+            if !report.verified {
+                warn!("[API] Integrity check of camera '{}' found {} modified and {} missing items",
+                      query.camera, report.modified, report.missing);
+            }
+            Json(ApiResponse::success(report)).into_response()
+        }
+        Err(e) => {
+            (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+             Json(ApiResponse::<()>::error(&format!("Verification failed: {}", e), 500)))
+            .into_response()
+        }
+    }
+}
+
+/// Who acts in a two-admin unlock: the LDAP user of a session, the tenant of a tenant admin token,
+/// the global admin for the current and the rotated admin token alike, else the fingerprint of the token
+fn admin_identity(headers: &axum::http::HeaderMap, state: &AppState) -> String {
+    let Some(token) = headers.get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .map(|auth| auth.strip_prefix("Bearer ").unwrap_or(auth)) else {
+        return crate::audit::actor_fingerprint(headers);
+    };
+    if let Some(session) = crate::ldap::get_session(token) {
+        return format!("ldap:{}", session.username);
+    }
+    if let Some(tenant) = state.server_config.tenants.iter().find(|t| t.admin_token == token) {
+        return format!("tenant:{}", tenant.id);
+    }
+    let admin_token = state.admin_token.read().unwrap_or_else(|e| e.into_inner());
+    if admin_token.current.as_deref() == Some(token)
+        || admin_token.previous.as_ref().is_some_and(|p| p.accepts(token)) {
+        return "admin".to_string();
+    }
+    crate::audit::actor_fingerprint(headers)
+}
+
+fn sha256_hex(value: &str) -> String {
+    aws_lc_rs::digest::digest(&aws_lc_rs::digest::SHA256, value.as_bytes())
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+// POST /api/admin/cameras/:id/sessions/:session_id/lock
+pub async fn api_lock_session(
+    headers: axum::http::HeaderMap,
+    Path((camera_id, session_id)): Path<(String, i64)>,
+    Json(request): Json<LockSessionRequest>,
+    state: AppState,
+) -> axum::response::Response {
+    if let Some(response) = check_camera_admin(&headers, &state, &camera_id).await {
+        return response;
+    }
+
+    let database = match camera_database(&state, &camera_id).await {
+        Ok(database) => database,
+        Err(response) => return response,
+    };
+
+    if let Some(response) = check_session_camera(&database, &camera_id, session_id).await {
+        return response;
+    }
+
+    let mut bytes = [0u8; 24];
+    if aws_lc_rs::rand::fill(&mut bytes).is_err() {
+        return (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::<()>::error("Failed to generate unlock token", 500)))
+               .into_response();
+    }
+    let unlock_token: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+
+    let lock = crate::database::SessionLock {
+        session_id,
+        camera_id: camera_id.clone(),
+        locked_at: chrono::Utc::now(),
+        locked_by: admin_identity(&headers, &state),
+        reason: request.reason,
+        unlock_token_hash: sha256_hex(&unlock_token),
+        unlock_requested_by: None,
+        unlock_requested_at: None,
+    };
+    info!("[API] Locking recording session {} of camera '{}'", session_id, camera_id);
+
+    match database.lock_recording_session(&lock).await {
+        Ok(()) => {
+            state.audit_log.record(&headers, "session.lock", Some(&camera_id),
+                serde_json::Value::Null, serde_json::to_value(&lock).unwrap_or_default()).await;
+            let mut data = serde_json::to_value(&lock).unwrap_or_default();
+            // Only shown once, the server keeps its hash
+            data["unlock_token"] = serde_json::Value::String(unlock_token);
+            Json(ApiResponse::success(data)).into_response()
+        }
+        Err(e) => session_error_response("Lock", e),
+    }
+}
+
+// POST /api/admin/cameras/:id/sessions/:session_id/unlock
+// Releases the lock with its unlock token, or once a second admin confirms the request of a first one
+pub async fn api_unlock_session(
+    headers: axum::http::HeaderMap,
+    Path((camera_id, session_id)): Path<(String, i64)>,
+    Json(request): Json<UnlockSessionRequest>,
+    state: AppState,
+) -> axum::response::Response {
+    if let Some(response) = check_camera_admin(&headers, &state, &camera_id).await {
+        return response;
+    }
+
+    let database = match camera_database(&state, &camera_id).await {
+        Ok(database) => database,
+        Err(response) => return response,
+    };
+
+    let lock = match database.get_session_lock(session_id).await {
+        Ok(Some(lock)) if lock.camera_id == camera_id => lock,
+        Ok(_) => {
+            return (axum::http::StatusCode::NOT_FOUND,
+                    Json(ApiResponse::<()>::error(&format!("Recording session {} is not locked", session_id), 404)))
+                   .into_response();
+        }
+        Err(e) => return session_error_response("Unlock", e),
+    };
+    let identity = admin_identity(&headers, &state);
+
+    let confirmation = if let Some(ref unlock_token) = request.unlock_token {
+        if sha256_hex(unlock_token) != lock.unlock_token_hash {
+            warn!("[API] Invalid unlock token for recording session {} of camera '{}'", session_id, camera_id);
+            return (axum::http::StatusCode::FORBIDDEN,
+                    Json(ApiResponse::<()>::error("Invalid unlock token", 403)))
+                   .into_response();
+        }
+        "unlock_token"
+    } else {
+        // Without an admin token every caller is admin, so a second admin proves nothing
+        if state.admin_token.read().unwrap_or_else(|e| e.into_inner()).current.is_none() {
+            return (axum::http::StatusCode::FORBIDDEN,
+                    Json(ApiResponse::<()>::error("Two-admin unlock requires an admin_token, use the unlock token", 403)))
+                   .into_response();
+        }
+        let pending = lock.unlock_requested_by.as_deref().filter(|_| {
+            lock.unlock_requested_at.is_some_and(|at| chrono::Utc::now() - at < chrono::Duration::hours(UNLOCK_REQUEST_HOURS))
+        });
+        match pending {
+            None => {
+                if let Err(e) = database.request_session_unlock(session_id, &identity).await {
+                    return session_error_response("Unlock", e);
+                }
+                state.audit_log.record(&headers, "session.unlock_request", Some(&camera_id),
+                    serde_json::Value::Null,
+                    serde_json::json!({ "session_id": session_id, "requested_by": identity })).await;
+                return (axum::http::StatusCode::ACCEPTED, Json(ApiResponse::success(serde_json::json!({
+                    "session_id": session_id,
+                    "locked": true,
+                    "unlock_requested_by": identity,
+                    "expires_at": chrono::Utc::now() + chrono::Duration::hours(UNLOCK_REQUEST_HOURS),
+                    "message": "Unlock requested, it must be confirmed by a second admin",
+                })))).into_response();
+            }
+            Some(requested_by) if requested_by == identity => {
+                return (axum::http::StatusCode::CONFLICT,
+                        Json(ApiResponse::<()>::error("The unlock must be confirmed by a different admin", 409)))
+                       .into_response();
+            }
+            Some(_) => "second_admin",
+        }
+    };
+
+    match database.unlock_recording_session(session_id).await {
+        Ok(_) => {
+            info!("[API] Unlocked recording session {} of camera '{}' ({})", session_id, camera_id, confirmation);
+            state.audit_log.record(&headers, "session.unlock", Some(&camera_id),
+                serde_json::to_value(&lock).unwrap_or_default(),
+                serde_json::json!({ "session_id": session_id, "unlocked_by": identity, "confirmation": confirmation })).await;
+            Json(ApiResponse::success(serde_json::json!({
+                "session_id": session_id,
+                "locked": false,
+                "confirmation": confirmation,
+                "message": format!("Session {} is unlocked, it stays protected from purging until its keep flag is cleared", session_id),
+            }))).into_response()
+        }
+        Err(e) => session_error_response("Unlock", e),
+    }
+}
+
+// GET /api/admin/cameras/:id/sessions/locks
+pub async fn api_list_session_locks(
+    headers: axum::http::HeaderMap,
+    Path(camera_id): Path<String>,
+    state: AppState,
+) -> axum::response::Response {
+    if let Some(response) = check_camera_admin(&headers, &state, &camera_id).await {
+        return response;
+    }
+
+    let database = match camera_database(&state, &camera_id).await {
+        Ok(database) => database,
+        Err(response) => return response,
+    };
+
+    match database.list_session_locks(&camera_id).await {
+        Ok(locks) => Json(ApiResponse::success(serde_json::json!({
+            "camera_id": camera_id,
+            "count": locks.len(),
+            "locks": locks,
+        }))).into_response(),
+        Err(e) => session_error_response("Listing locks", e),
+    }
+}
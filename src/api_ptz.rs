@@ -3,7 +3,9 @@ use axum::{Json, response::IntoResponse};
 use serde::Deserialize;
 
 use crate::config;
-use crate::ptz::{PtzVelocity, PtzPresetRequest, PtzController, onvif_ptz::OnvifPtz};
+use crate::recording::RecordingManager;
+use crate::ptz::{PtzCapabilities, PtzVelocity, PtzPresetRequest, PtzController, onvif_ptz::OnvifPtz};
+use crate::ptz_arbiter::{self, PtzClient, PtzConflict};
 
 #[derive(Debug, Deserialize)]
 pub struct MoveRequest {
@@ -13,6 +15,17 @@ pub struct MoveRequest {
     pub timeout_secs: Option<u64>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct RelativeMoveRequest {
+    #[serde(default)]
+    pub pan: f32,
+    #[serde(default)]
+    pub tilt: f32,
+    #[serde(default)]
+    pub zoom: f32,
+    pub speed: Option<PtzVelocity>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct PresetRequest {
     pub token: String,
@@ -24,24 +37,57 @@ pub struct SetPresetRequest {
     pub token: Option<String>,
 }
 
-fn check_auth(headers: &axum::http::HeaderMap, camera_config: &config::CameraConfig) -> std::result::Result<(), axum::response::Response> {
-    if let Some(expected_token) = &camera_config.token {
-        if let Some(auth_header) = headers.get("authorization") {
-            if let Ok(auth_str) = auth_header.to_str() {
-                if let Some(token) = auth_str.strip_prefix("Bearer ") {
-                    if token == expected_token { return Ok(()); }
-                }
+fn check_auth(headers: &axum::http::HeaderMap, camera_config: &config::CameraConfig) -> std::result::Result<PtzClient, axum::response::Response> {
+    let token = headers.get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if camera_config.requires_token() {
+        if let Some(token) = token {
+            if camera_config.token_grants(token, config::TokenScope::Ptz) { return Ok(PtzClient::from_token(camera_config, Some(token))); }
+            if camera_config.token_known(token) {
+                return Err((axum::http::StatusCode::FORBIDDEN, "Token does not grant 'ptz' scope").into_response());
             }
         }
         return Err((axum::http::StatusCode::UNAUTHORIZED, "Invalid or missing Authorization header").into_response());
     }
-    Ok(())
+    Ok(PtzClient::from_token(camera_config, token))
+}
+
+/// Take control of the camera for a command, refused if another client has a higher or equal priority
+async fn arbitrate(camera_id: &str, client: &PtzClient, camera_config: &config::CameraConfig) -> std::result::Result<(), PtzConflict> {
+    match camera_config.ptz {
+        Some(ref ptz_cfg) => ptz_arbiter::acquire(camera_id, client, ptz_cfg).await,
+        None => Ok(()),
+    }
+}
+
+fn conflict_response(conflict: PtzConflict) -> axum::response::Response {
+    (axum::http::StatusCode::CONFLICT, Json(serde_json::json!({
+        "error": conflict.message(),
+        "holder": conflict.holder,
+    }))).into_response()
+}
+
+/// Why a vector that only moves axes the camera lacks is refused; other unsupported axes are left out by the controller
+fn unsupported_axes(capabilities: &PtzCapabilities, vector: PtzVelocity) -> Option<String> {
+    let missing: Vec<&str> = [("pan", vector.pan, capabilities.pan), ("tilt", vector.tilt, capabilities.tilt), ("zoom", vector.zoom, capabilities.zoom)]
+        .into_iter()
+        .filter(|(_, value, supported)| *value != 0.0 && !supported)
+        .map(|(axis, _, _)| axis)
+        .collect();
+    let remaining = capabilities.restrict(vector);
+    (!missing.is_empty() && remaining.pan == 0.0 && remaining.tilt == 0.0 && remaining.zoom == 0.0)
+        .then(|| format!("Camera does not support {}", missing.join(", ")))
 }
 
-fn build_ptz_controller(camera_config: &config::CameraConfig) -> Result<Arc<dyn PtzController>, axum::response::Response> {
+pub(crate) fn build_ptz_controller(camera_config: &config::CameraConfig) -> Result<Arc<dyn PtzController>, axum::response::Response> {
     let ptz_cfg = match &camera_config.ptz { Some(p) if p.enabled => p, _ => {
         return Err((axum::http::StatusCode::SERVICE_UNAVAILABLE, "PTZ not enabled for this camera").into_response());
     }};
+    let mut ptz_cfg = ptz_cfg.clone();
+    for value in [&mut ptz_cfg.onvif_url, &mut ptz_cfg.username, &mut ptz_cfg.password].into_iter().flatten() {
+        *value = crate::secrets::resolve(value).map_err(|e| (axum::http::StatusCode::SERVICE_UNAVAILABLE, e.to_string()).into_response())?;
+    }
     if ptz_cfg.protocol.to_lowercase() == "onvif" {
         let endpoint = ptz_cfg.onvif_url.clone().ok_or_else(|| (axum::http::StatusCode::BAD_REQUEST, "Missing onvif_url in PTZ config").into_response())?;
         let profile = ptz_cfg.profile_token.clone().unwrap_or_else(|| "profile1".to_string());
@@ -52,35 +98,88 @@ fn build_ptz_controller(camera_config: &config::CameraConfig) -> Result<Arc<dyn
     }
 }
 
-pub async fn api_ptz_move(headers: axum::http::HeaderMap, axum::extract::Json(req): Json<MoveRequest>, camera_config: config::CameraConfig) -> axum::response::Response {
-    if let Err(resp) = check_auth(&headers, &camera_config) { return resp; }
+pub async fn api_ptz_move(headers: axum::http::HeaderMap, axum::extract::Json(req): Json<MoveRequest>, camera_id: String, camera_config: config::CameraConfig, recording_manager: Option<Arc<RecordingManager>>) -> axum::response::Response {
+    let client = match check_auth(&headers, &camera_config) { Ok(c) => c, Err(r) => return r };
     let ctrl = match build_ptz_controller(&camera_config) { Ok(c) => c, Err(r) => return r };
     let vel = PtzVelocity { pan: req.pan, tilt: req.tilt, zoom: req.zoom.unwrap_or(0.0) };
+    match ctrl.capabilities().await {
+        Ok(capabilities) => if let Some(message) = unsupported_axes(&capabilities, vel) {
+            return (axum::http::StatusCode::BAD_REQUEST, message).into_response();
+        },
+        Err(e) => return (axum::http::StatusCode::BAD_GATEWAY, format!("PTZ capability detection failed: {}", e)).into_response(),
+    }
+    if let Err(conflict) = arbitrate(&camera_id, &client, &camera_config).await { return conflict_response(conflict); }
     match ctrl.continuous_move(vel, req.timeout_secs).await {
-        Ok(_) => (axum::http::StatusCode::OK, "ok").into_response(),
+        Ok(_) => {
+            if let Some(rm) = recording_manager {
+                rm.record_event(&camera_id, "ptz", "move", serde_json::json!({
+                    "pan": req.pan, "tilt": req.tilt, "zoom": req.zoom, "timeout_secs": req.timeout_secs
+                })).await;
+            }
+            (axum::http::StatusCode::OK, "ok").into_response()
+        }
         Err(e) => (axum::http::StatusCode::BAD_GATEWAY, format!("PTZ move failed: {}", e)).into_response(),
     }
 }
 
-pub async fn api_ptz_stop(headers: axum::http::HeaderMap, camera_config: config::CameraConfig) -> axum::response::Response {
-    if let Err(resp) = check_auth(&headers, &camera_config) { return resp; }
+pub async fn api_ptz_relative_move(headers: axum::http::HeaderMap, axum::extract::Json(req): Json<RelativeMoveRequest>, camera_id: String, camera_config: config::CameraConfig, recording_manager: Option<Arc<RecordingManager>>) -> axum::response::Response {
+    let client = match check_auth(&headers, &camera_config) { Ok(c) => c, Err(r) => return r };
     let ctrl = match build_ptz_controller(&camera_config) { Ok(c) => c, Err(r) => return r };
+    let translation = PtzVelocity { pan: req.pan, tilt: req.tilt, zoom: req.zoom };
+    match ctrl.capabilities().await {
+        Ok(capabilities) if !capabilities.relative_move => {
+            return (axum::http::StatusCode::BAD_REQUEST, "Camera does not support relative moves").into_response();
+        }
+        Ok(capabilities) => if let Some(message) = unsupported_axes(&capabilities, translation) {
+            return (axum::http::StatusCode::BAD_REQUEST, message).into_response();
+        },
+        Err(e) => return (axum::http::StatusCode::BAD_GATEWAY, format!("PTZ capability detection failed: {}", e)).into_response(),
+    }
+    if let Err(conflict) = arbitrate(&camera_id, &client, &camera_config).await { return conflict_response(conflict); }
+    match ctrl.relative_move(translation, req.speed).await {
+        Ok(_) => {
+            if let Some(rm) = recording_manager {
+                rm.record_event(&camera_id, "ptz", "relative move", serde_json::json!({
+                    "pan": req.pan, "tilt": req.tilt, "zoom": req.zoom, "speed": req.speed
+                })).await;
+            }
+            (axum::http::StatusCode::OK, "ok").into_response()
+        }
+        Err(e) => (axum::http::StatusCode::BAD_GATEWAY, format!("PTZ relative move failed: {}", e)).into_response(),
+    }
+}
+
+pub async fn api_ptz_stop(headers: axum::http::HeaderMap, camera_id: String, camera_config: config::CameraConfig) -> axum::response::Response {
+    let client = match check_auth(&headers, &camera_config) { Ok(c) => c, Err(r) => return r };
+    let ctrl = match build_ptz_controller(&camera_config) { Ok(c) => c, Err(r) => return r };
+    if let Err(conflict) = arbitrate(&camera_id, &client, &camera_config).await { return conflict_response(conflict); }
     match ctrl.stop().await {
-        Ok(_) => (axum::http::StatusCode::OK, "ok").into_response(),
+        Ok(_) => {
+            // Stopping hands control back to others right away instead of when the lease runs out
+            ptz_arbiter::release(&camera_id, &client.id);
+            (axum::http::StatusCode::OK, "ok").into_response()
+        }
         Err(e) => (axum::http::StatusCode::BAD_GATEWAY, format!("PTZ stop failed: {}", e)).into_response(),
     }
 }
 
-pub async fn api_ptz_goto_preset(headers: axum::http::HeaderMap, axum::extract::Json(req): Json<PresetRequest>, camera_config: config::CameraConfig) -> axum::response::Response {
-    if let Err(resp) = check_auth(&headers, &camera_config) { return resp; }
+pub async fn api_ptz_goto_preset(headers: axum::http::HeaderMap, axum::extract::Json(req): Json<PresetRequest>, camera_id: String, camera_config: config::CameraConfig, recording_manager: Option<Arc<RecordingManager>>) -> axum::response::Response {
+    let client = match check_auth(&headers, &camera_config) { Ok(c) => c, Err(r) => return r };
     let ctrl = match build_ptz_controller(&camera_config) { Ok(c) => c, Err(r) => return r };
+    if let Err(conflict) = arbitrate(&camera_id, &client, &camera_config).await { return conflict_response(conflict); }
     match ctrl.goto_preset(&req.token, None).await {
-        Ok(_) => (axum::http::StatusCode::OK, "ok").into_response(),
+        Ok(_) => {
+            if let Some(rm) = recording_manager {
+                rm.record_event(&camera_id, "ptz", &format!("preset {}", req.token), serde_json::json!({ "preset": req.token })).await;
+            }
+            (axum::http::StatusCode::OK, "ok").into_response()
+        }
         Err(e) => (axum::http::StatusCode::BAD_GATEWAY, format!("PTZ goto preset failed: {}", e)).into_response(),
     }
 }
 
 pub async fn api_ptz_set_preset(headers: axum::http::HeaderMap, axum::extract::Json(req): Json<SetPresetRequest>, camera_config: config::CameraConfig) -> axum::response::Response {
+    // Saving a preset does not move the camera and needs no control
     if let Err(resp) = check_auth(&headers, &camera_config) { return resp; }
     let ctrl = match build_ptz_controller(&camera_config) { Ok(c) => c, Err(r) => return r };
     match ctrl.set_preset(PtzPresetRequest { name: req.name, token: req.token }).await {
@@ -88,3 +187,12 @@ pub async fn api_ptz_set_preset(headers: axum::http::HeaderMap, axum::extract::J
         Err(e) => (axum::http::StatusCode::BAD_GATEWAY, format!("PTZ set preset failed: {}", e)).into_response(),
     }
 }
+
+pub async fn api_ptz_capabilities(headers: axum::http::HeaderMap, camera_config: config::CameraConfig) -> axum::response::Response {
+    if let Err(resp) = check_auth(&headers, &camera_config) { return resp; }
+    let ctrl = match build_ptz_controller(&camera_config) { Ok(c) => c, Err(r) => return r };
+    match ctrl.capabilities().await {
+        Ok(capabilities) => (axum::http::StatusCode::OK, Json(capabilities)).into_response(),
+        Err(e) => (axum::http::StatusCode::BAD_GATEWAY, format!("PTZ capability detection failed: {}", e)).into_response(),
+    }
+}
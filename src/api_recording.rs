@@ -7,14 +7,48 @@ use tokio::sync::broadcast;
 use bytes::Bytes;
 
 use crate::config;
-use crate::recording::RecordingManager;
+use crate::recording::{RecordingManager, StorageKind};
+use crate::database::PageRequest;
 use crate::mp4::HlsTimeRangeQuery;
 
 #[derive(Debug, Deserialize)]
 pub struct StartRecordingRequest {
     pub reason: Option<String>,
+    pub max_duration: Option<String>, // Overrides max_recording_duration (e.g., "2h", "0" = no limit)
 }
 
+#[derive(Debug, Deserialize)]
+pub struct ExtendRecordingRequest {
+    pub duration: String, // e.g., "30m"
+}
+
+// Parse a humantime duration, "0" for none
+fn parse_recording_duration(value: &str) -> Result<Option<chrono::Duration>, String> {
+    if value.trim() == "0" {
+        return Ok(None);
+    }
+    humantime::parse_duration(value.trim())
+        .map_err(|e| e.to_string())
+        .and_then(|d| chrono::Duration::from_std(d).map_err(|e| e.to_string()))
+        .map(Some)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateClipQuery {
+    #[serde(default = "default_clip_seconds")]
+    pub last_seconds: u64, // Taken from the pre-recording buffer
+    #[serde(default = "default_clip_seconds")]
+    pub next_seconds: u64, // Live frames following the request
+    pub reason: Option<String>,
+}
+
+fn default_clip_seconds() -> u64 {
+    30
+}
+
+// Longest live part of an instant clip, the request returns before it is captured
+const MAX_CLIP_NEXT_SECONDS: u64 = 600;
+
 #[derive(Debug, Deserialize)]
 pub struct SetKeepSessionQuery {
     #[serde(default = "default_true")]
@@ -29,9 +63,20 @@ fn default_true() -> bool {
 pub struct GetRecordingsQuery {
     pub from: Option<chrono::DateTime<chrono::Utc>>,
     pub to: Option<chrono::DateTime<chrono::Utc>>,
-    pub reason: Option<String>, // Filter by recording reason using SQL wildcards (e.g., 'Manual' or '%alarm%')
+    pub reason: Option<String>, // Filter by configured reason name or SQL wildcards (e.g., 'motion', 'Manual' or '%alarm%')
     #[serde(default = "default_sort_order_recordings")]
     pub sort_order: String,
+    pub limit: Option<i64>,  // Page size (all recordings if not set)
+    #[serde(default)]
+    pub offset: i64,
+    #[serde(default = "default_true")]
+    pub stats: bool,  // Include frame count, sizes, average FPS and gaps of every session
+    #[serde(default = "default_gap_threshold_ms")]
+    pub gap_threshold_ms: i64,
+}
+
+fn default_gap_threshold_ms() -> i64 {
+    2000
 }
 
 fn default_sort_order_recordings() -> String {
@@ -42,12 +87,62 @@ fn default_sort_order_recordings() -> String {
 pub struct GetFramesQuery {
     pub from: Option<chrono::DateTime<chrono::Utc>>,
     pub to: Option<chrono::DateTime<chrono::Utc>>,
+    pub format: Option<crate::jpeg::FrameFormat>, // Carried over into the frame URLs
+    pub quality: Option<u8>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GetFramesZipQuery {
+    pub from: Option<chrono::DateTime<chrono::Utc>>, // Default: session start
+    pub to: Option<chrono::DateTime<chrono::Utc>>,   // Default: session end (or now while recording)
+    pub every_nth: Option<usize>,                    // Only export every nth frame (default: 1 = all)
+    pub format: Option<crate::jpeg::FrameFormat>,    // Re-encode the frames (default: the stored JPEG)
+    pub quality: Option<u8>,                         // Quality 1-100 of the re-encoded frames
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Mp4ConcatQuery {
+    pub from: chrono::DateTime<chrono::Utc>,
+    pub to: chrono::DateTime<chrono::Utc>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct GetFrameByTimestampQuery {
     #[serde(default)]
     pub tolerance: Option<String>, // e.g., "30s", "5m", "1h" - default is no tolerance (exact match)
+    pub format: Option<crate::jpeg::FrameFormat>, // Re-encode the frame, default: negotiated via the Accept header
+    pub quality: Option<u8>,                      // Quality 1-100 of the re-encoded frame
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GetSessionReportQuery {
+    #[serde(default = "default_gap_threshold_ms")]
+    pub gap_threshold_ms: i64,
+    #[serde(default = "default_report_max_gaps")]
+    pub max_gaps: i64,  // Longest list of frame gaps and uncovered MP4 ranges
+}
+
+fn default_report_max_gaps() -> i64 {
+    1000
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GetThumbnailQuery {
+    pub segment: Option<chrono::DateTime<chrono::Utc>>, // Start time of an MP4 segment of the session
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GetClipQuery {
+    pub from: chrono::DateTime<chrono::Utc>,
+    pub to: chrono::DateTime<chrono::Utc>,
+    pub fps: Option<u32>,   // Default: 5
+    pub width: Option<u32>, // Output width in pixels, height keeps the aspect ratio (default: 480)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GetHeatmapQuery {
+    pub from: Option<chrono::DateTime<chrono::Utc>>, // Default: 24 hours before `to`
+    pub to: Option<chrono::DateTime<chrono::Utc>>,   // Default: now
 }
 
 #[derive(Debug, Deserialize)]
@@ -57,6 +152,8 @@ pub struct GetMp4SegmentsQuery {
     pub reason: Option<String>,
     #[serde(default = "default_segments_limit")]
     pub limit: i64,
+    #[serde(default)]
+    pub offset: i64,
     #[serde(default = "default_sort_order_recordings")]
     pub sort_order: String,
 }
@@ -65,6 +162,51 @@ fn default_segments_limit() -> i64 {
     1000
 }
 
+#[derive(Debug, Deserialize)]
+pub struct GetEventsQuery {
+    pub from: chrono::DateTime<chrono::Utc>,
+    pub to: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GetEventFramesQuery {
+    #[serde(default = "default_event_before_secs")]
+    pub before_secs: u64,  // Seconds before the event included in the window
+    #[serde(default = "default_event_after_secs")]
+    pub after_secs: u64,   // Seconds after the event (or the end of its condition)
+    pub format: Option<String>, // "json" (default) or "mp4"
+}
+
+fn default_event_before_secs() -> u64 {
+    5
+}
+
+fn default_event_after_secs() -> u64 {
+    10
+}
+
+// Longest condition (motion, tamper) an event window follows, and the longest margins around it
+const MAX_EVENT_CONDITION_SECS: i64 = 600;
+const MAX_EVENT_MARGIN_SECS: u64 = 300;
+
+#[derive(Debug, Deserialize)]
+pub struct AddBookmarkRequest {
+    pub label: String,
+    pub timestamp: Option<chrono::DateTime<chrono::Utc>>, // Default: now
+    #[serde(default)]
+    pub data: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateHlsSignedUrlRequest {
+    #[serde(flatten)]
+    pub range: HlsTimeRangeQuery, // t1, t2, segment_duration, session_id as for the timerange playlist
+    pub expires_in_minutes: Option<u64>, // Default: hls_signed_url_minutes
+}
+
+// Longest validity of a signed HLS URL
+pub(crate) const MAX_HLS_SIGNED_URL_MINUTES: u64 = 7 * 24 * 60;
+
 #[derive(Debug, Serialize)]
 pub struct ApiResponse<T> {
     status: String,
@@ -96,14 +238,19 @@ impl<T> ApiResponse<T> {
     }
 }
 
-pub fn check_api_auth(headers: &axum::http::HeaderMap, camera_config: &config::CameraConfig) -> std::result::Result<(), axum::response::Response> {
-    if let Some(expected_token) = &camera_config.token {
+pub fn check_api_auth(headers: &axum::http::HeaderMap, camera_config: &config::CameraConfig, scope: config::TokenScope) -> std::result::Result<(), axum::response::Response> {
+    if camera_config.requires_token() {
         if let Some(auth_header) = headers.get("authorization") {
             if let Ok(auth_str) = auth_header.to_str() {
                 if let Some(token) = auth_str.strip_prefix("Bearer ") {
-                    if token == expected_token {
+                    if camera_config.token_grants(token, scope) {
                         return Ok(());
                     }
+                    if camera_config.token_known(token) {
+                        return Err((axum::http::StatusCode::FORBIDDEN,
+                                   Json(ApiResponse::<()>::error(&format!("Token does not grant '{}' scope", scope), 403)))
+                                   .into_response());
+                    }
                 }
             }
         }
@@ -114,6 +261,43 @@ pub fn check_api_auth(headers: &axum::http::HeaderMap, camera_config: &config::C
     Ok(())
 }
 
+/// `check_api_auth` with playback scope for endpoints serving recorded frames or video. Recordings
+/// cannot carry the per-client watermark, so they are refused for cameras with `watermark` enabled;
+/// admins review them through synchronized playback. Returns the rejection, if any.
+pub fn check_recording_auth(headers: &axum::http::HeaderMap, camera_config: &config::CameraConfig) -> Option<axum::response::Response> {
+    if let Err(response) = check_api_auth(headers, camera_config, config::TokenScope::Playback) {
+        return Some(response);
+    }
+    crate::watermark::required(camera_config).then(|| {
+        (axum::http::StatusCode::FORBIDDEN,
+         Json(ApiResponse::<()>::error("Recordings of watermarked cameras are only available to admins", 403)))
+         .into_response()
+    })
+}
+
+/// Like `check_api_auth`, but the `expires` and `signature` of a signed HLS URL grant playback as well.
+/// `resource` is the requested URL relative to `control/recordings/hls/`. Returns the rejection, if any.
+fn check_hls_auth(
+    headers: &axum::http::HeaderMap,
+    camera_id: &str,
+    camera_config: &config::CameraConfig,
+    recording_config: &config::RecordingConfig,
+    resource: &str,
+    expires: Option<i64>,
+    signature: Option<&str>,
+) -> Option<axum::response::Response> {
+    match (expires, signature) {
+        (Some(expires), Some(signature)) if camera_config.requires_token() => {
+            (!crate::hls_signing::verify(recording_config, camera_id, resource, expires, signature)).then(|| {
+                (axum::http::StatusCode::FORBIDDEN,
+                 Json(ApiResponse::<()>::error("Invalid or expired signature", 403)))
+                 .into_response()
+            })
+        }
+        _ => check_recording_auth(headers, camera_config),
+    }
+}
+
 pub async fn api_start_recording(
     headers: axum::http::HeaderMap,
     Json(request): Json<StartRecordingRequest>,
@@ -123,10 +307,22 @@ pub async fn api_start_recording(
     frame_sender: Arc<broadcast::Sender<Bytes>>,
     pre_recording_buffer: Option<crate::pre_recording_buffer::PreRecordingBuffer>,
 ) -> axum::response::Response {
-    if let Err(response) = check_api_auth(&headers, &camera_config) {
+    if let Err(response) = check_api_auth(&headers, &camera_config, config::TokenScope::Control) {
         return response;
     }
 
+    let max_duration = match request.max_duration.as_deref() {
+        Some(value) => match parse_recording_duration(value) {
+            Ok(duration) => duration,
+            Err(e) => {
+                return (axum::http::StatusCode::BAD_REQUEST,
+                        Json(ApiResponse::<()>::error(&format!("Invalid max_duration '{}': {}", value, e), 400)))
+                        .into_response();
+            }
+        },
+        None => recording_manager.max_recording_duration(&camera_config),
+    };
+
     // Check if already recording
     if recording_manager.is_recording(&camera_id).await {
         return (axum::http::StatusCode::CONFLICT, 
@@ -144,10 +340,15 @@ pub async fn api_start_recording(
         pre_recording_buffer.as_ref(),
     ).await {
         Ok(session_id) => {
+            let stop_at = max_duration.map(|duration| chrono::Utc::now() + duration);
+            if stop_at.is_some() {
+                recording_manager.set_recording_stop_at(&camera_id, stop_at).await;
+            }
             let data = serde_json::json!({
                 "session_id": session_id,
                 "message": "Recording started",
-                "camera_id": camera_id
+                "camera_id": camera_id,
+                "stop_at": stop_at
             });
             Json(ApiResponse::success(data)).into_response()
         }
@@ -159,13 +360,59 @@ pub async fn api_start_recording(
     }
 }
 
+pub async fn api_extend_recording(
+    headers: axum::http::HeaderMap,
+    Json(request): Json<ExtendRecordingRequest>,
+    camera_id: String,
+    camera_config: config::CameraConfig,
+    recording_manager: Arc<RecordingManager>,
+) -> axum::response::Response {
+    if let Err(response) = check_api_auth(&headers, &camera_config, config::TokenScope::Control) {
+        return response;
+    }
+
+    let duration = match parse_recording_duration(&request.duration) {
+        Ok(Some(duration)) => duration,
+        Ok(None) => {
+            return (axum::http::StatusCode::BAD_REQUEST,
+                    Json(ApiResponse::<()>::error("duration must be greater than 0", 400)))
+                    .into_response();
+        }
+        Err(e) => {
+            return (axum::http::StatusCode::BAD_REQUEST,
+                    Json(ApiResponse::<()>::error(&format!("Invalid duration '{}': {}", request.duration, e), 400)))
+                    .into_response();
+        }
+    };
+
+    match recording_manager.extend_recording(&camera_id, duration).await {
+        Some(stop_at) => {
+            recording_manager.record_event(&camera_id, "recording", "extended", serde_json::json!({
+                "duration": request.duration,
+                "stop_at": stop_at,
+            })).await;
+            let data = serde_json::json!({
+                "message": "Recording extended",
+                "camera_id": camera_id,
+                "stop_at": stop_at
+            });
+            Json(ApiResponse::success(data)).into_response()
+        }
+        None => {
+            (axum::http::StatusCode::CONFLICT,
+             Json(ApiResponse::<()>::error("No active recording with a max duration", 409)))
+             .into_response()
+        }
+    }
+}
+
 pub async fn api_stop_recording(
     headers: axum::http::HeaderMap,
     camera_id: String,
     camera_config: config::CameraConfig,
     recording_manager: Arc<RecordingManager>,
 ) -> axum::response::Response {
-    if let Err(response) = check_api_auth(&headers, &camera_config) {
+    if let Err(response) = check_api_auth(&headers, &camera_config, config::TokenScope::Control) {
         return response;
     }
 
@@ -193,6 +440,77 @@ pub async fn api_stop_recording(
     }
 }
 
+// POST /<camera_path>/control/clip
+pub async fn api_create_clip(
+    headers: axum::http::HeaderMap,
+    Query(query): Query<CreateClipQuery>,
+    camera_id: String,
+    camera_config: config::CameraConfig,
+    recording_manager: Arc<RecordingManager>,
+    frame_sender: Arc<broadcast::Sender<Bytes>>,
+    pre_recording_buffer: Option<crate::pre_recording_buffer::PreRecordingBuffer>,
+) -> axum::response::Response {
+    if let Err(response) = check_api_auth(&headers, &camera_config, config::TokenScope::Control) {
+        return response;
+    }
+
+    if query.next_seconds > MAX_CLIP_NEXT_SECONDS {
+        return (axum::http::StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<()>::error(&format!("next_seconds must not exceed {}", MAX_CLIP_NEXT_SECONDS), 400)))
+                .into_response();
+    }
+    if query.last_seconds == 0 && query.next_seconds == 0 {
+        return (axum::http::StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<()>::error("last_seconds or next_seconds must be greater than 0", 400)))
+                .into_response();
+    }
+    if query.last_seconds > 0 && pre_recording_buffer.is_none() {
+        return (axum::http::StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<()>::error("Pre-recording is not enabled for this camera, use last_seconds=0", 400)))
+                .into_response();
+    }
+    if recording_manager.get_storage_type_for_camera(&camera_config) == config::Mp4StorageType::Disabled {
+        return (axum::http::StatusCode::CONFLICT,
+                Json(ApiResponse::<()>::error("MP4 storage is disabled for this camera", 409)))
+                .into_response();
+    }
+
+    let reason = query.reason.as_deref().filter(|r| !r.is_empty()).unwrap_or("clip");
+    match recording_manager.create_clip(
+        &camera_id,
+        reason,
+        query.last_seconds,
+        query.next_seconds,
+        frame_sender,
+        &camera_config,
+        pre_recording_buffer.as_ref(),
+    ).await {
+        Ok((session_id, start_time)) => {
+            let end_time = chrono::Utc::now() + chrono::Duration::seconds(query.next_seconds as i64);
+            recording_manager.record_event(&camera_id, "recording", "clip", serde_json::json!({
+                "session_id": session_id,
+                "last_seconds": query.last_seconds,
+                "next_seconds": query.next_seconds,
+            })).await;
+            let data = serde_json::json!({
+                "session_id": session_id,
+                "message": "Clip is being recorded",
+                "camera_id": camera_id,
+                "reason": reason,
+                "start_time": start_time,
+                "end_time": end_time,
+            });
+            (axum::http::StatusCode::ACCEPTED, Json(ApiResponse::success(data))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Failed to create clip for camera '{}': {}", camera_id, e);
+            (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+             Json(ApiResponse::<()>::error("Failed to create clip", 500)))
+             .into_response()
+        }
+    }
+}
+
 pub async fn api_list_recordings(
     headers: axum::http::HeaderMap,
     Query(query): Query<GetRecordingsQuery>,
@@ -200,18 +518,56 @@ pub async fn api_list_recordings(
     camera_config: config::CameraConfig,
     recording_manager: Arc<RecordingManager>,
 ) -> axum::response::Response {
-    if let Err(response) = check_api_auth(&headers, &camera_config) {
+    if let Err(response) = check_api_auth(&headers, &camera_config, config::TokenScope::Playback) {
         return response;
     }
 
-    match recording_manager.list_recordings_filtered(Some(&camera_id), query.from, query.to, query.reason.as_deref()).await {
-        Ok(mut recordings) => {
-            // Sort recordings based on sort_order parameter
-            match query.sort_order.as_str() {
-                "oldest" => recordings.sort_by(|a, b| a.start_time.cmp(&b.start_time)),
-                _ => recordings.sort_by(|a, b| b.start_time.cmp(&a.start_time)), // "newest" (default)
+    if query.limit.is_some_and(|l| l < 1) || query.offset < 0 {
+        return (axum::http::StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<()>::error("limit must be positive and offset must not be negative", 400)))
+                .into_response();
+    }
+
+    let page = PageRequest {
+        limit: query.limit,
+        offset: query.offset,
+        oldest_first: query.sort_order == "oldest",
+    };
+
+    // A configured reason name filters by its canonical pattern
+    let reason = query.reason.as_deref()
+        .map(|reason| recording_manager.get_recording_config().resolve_reason_filter(reason));
+
+    let total = match recording_manager.count_recordings_filtered(Some(&camera_id), query.from, query.to, reason).await {
+        Ok(total) => total,
+        Err(_) => {
+            return (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ApiResponse::<()>::error("Failed to count recordings", 500)))
+                    .into_response();
+        }
+    };
+
+    match recording_manager.list_recordings_filtered(Some(&camera_id), query.from, query.to, reason, &page).await {
+        Ok(recordings) => {
+            let mut session_stats = std::collections::HashMap::new();
+            if query.stats {
+                if let Some(database) = recording_manager.get_camera_database(&camera_id).await {
+                    let session_ids: Vec<i64> = recordings.iter().map(|r| r.session_id).collect();
+                    match database.get_session_stats(&session_ids, query.gap_threshold_ms.max(0)).await {
+                        Ok(stats) => session_stats = stats,
+                        Err(e) => tracing::warn!("[{}] Failed to compute recording session statistics: {}", camera_id, e),
+                    }
+                }
+            }
+
+            let mut locked_sessions = std::collections::HashSet::new();
+            if let Some(database) = recording_manager.get_camera_database(&camera_id).await {
+                match database.list_session_locks(&camera_id).await {
+                    Ok(locks) => locked_sessions = locks.into_iter().map(|lock| lock.session_id).collect(),
+                    Err(e) => tracing::warn!("[{}] Failed to list session locks: {}", camera_id, e),
+                }
             }
-            
+
             let recordings_data: Vec<serde_json::Value> = recordings
                 .into_iter()
                 .map(|r| serde_json::json!({
@@ -223,57 +579,605 @@ pub async fn api_list_recordings(
                     "status": format!("{:?}", r.status).to_lowercase(),
                     "duration_seconds": r.end_time
                         .map(|end| end.signed_duration_since(r.start_time).num_seconds()),
-                    "keep_session": r.keep_session
+                    "keep_session": r.keep_session,
+                    "locked": locked_sessions.contains(&r.session_id),
+                    "thumbnail_url": format!("{}/control/recordings/{}/thumbnail", camera_config.path, r.session_id),
+                    "stats": session_stats.get(&r.session_id)
                 }))
                 .collect();
 
+            let next_offset = query.offset + recordings_data.len() as i64;
             let data = serde_json::json!({
                 "recordings": recordings_data,
                 "count": recordings_data.len(),
+                "total": total,
+                "offset": query.offset,
+                "limit": query.limit,
+                "has_more": next_offset < total,
+                "next_offset": (next_offset < total).then_some(next_offset),
                 "camera_id": camera_id
             });
             Json(ApiResponse::success(data)).into_response()
         }
-        Err(_) => {
+        Err(_) => {
+            (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+             Json(ApiResponse::<()>::error("Failed to list recordings", 500)))
+             .into_response()
+        }
+    }
+}
+
+pub async fn api_get_recorded_frames(
+    headers: axum::http::HeaderMap,
+    AxumPath(session_id): AxumPath<i64>,
+    Query(query): Query<GetFramesQuery>,
+    camera_config: config::CameraConfig,
+    recording_manager: Arc<RecordingManager>,
+) -> axum::response::Response {
+    if let Some(response) = check_recording_auth(&headers, &camera_config) {
+        return response;
+    }
+
+    // Frame URLs request the same format and quality as the listing
+    let format_query = [
+        query.format.map(|format| format!("format={}", format.name())),
+        query.quality.map(|quality| format!("quality={}", quality)),
+    ].into_iter().flatten().collect::<Vec<_>>().join("&");
+
+    match recording_manager.get_recorded_frames(session_id, query.from, query.to).await {
+        Ok(frames) => {
+            let frames_data: Vec<serde_json::Value> = frames
+                .into_iter()
+                .map(|f| {
+                    let mut url = format!("{}/control/recordings/frames/{}", camera_config.path,
+                                          f.timestamp.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true));
+                    if !format_query.is_empty() {
+                        url = format!("{}?{}", url, format_query);
+                    }
+                    serde_json::json!({
+                        "timestamp": f.timestamp,
+                        "frame_size": f.frame_data.len(),
+                        "url": url
+                        // Note: Not including actual frame_data in JSON response due to size
+                    })
+                })
+                .collect();
+
+            let data = serde_json::json!({
+                "session_id": session_id,
+                "frames": frames_data,
+                "count": frames_data.len()
+            });
+            Json(ApiResponse::success(data)).into_response()
+        }
+        Err(_) => {
+            (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+             Json(ApiResponse::<()>::error("Failed to get recorded frames", 500)))
+             .into_response()
+        }
+    }
+}
+
+pub async fn api_get_frames_zip(
+    headers: axum::http::HeaderMap,
+    AxumPath(session_id): AxumPath<i64>,
+    Query(query): Query<GetFramesZipQuery>,
+    camera_id: String,
+    camera_config: config::CameraConfig,
+    recording_manager: Arc<RecordingManager>,
+) -> axum::response::Response {
+    if let Some(response) = check_recording_auth(&headers, &camera_config) {
+        return response;
+    }
+
+    let every_nth = query.every_nth.unwrap_or(1);
+    if every_nth == 0 {
+        return (axum::http::StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<()>::error("every_nth must be at least 1", 400)))
+                .into_response();
+    }
+    if query.quality.is_some_and(|q| !(1..=100).contains(&q)) {
+        return (axum::http::StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<()>::error("Quality must be between 1 and 100", 400)))
+               .into_response();
+    }
+    let format = query.format.unwrap_or(crate::jpeg::FrameFormat::Jpeg);
+    let quality = query.quality;
+    let transcode = format != crate::jpeg::FrameFormat::Jpeg || quality.is_some();
+
+    let Some(database) = recording_manager.get_camera_database(&camera_id).await else {
+        return (axum::http::StatusCode::NOT_FOUND,
+                Json(ApiResponse::<()>::error(&format!("Database not found for camera {}", camera_id), 404)))
+                .into_response();
+    };
+
+    let session = match database.get_recording_session(session_id).await {
+        Ok(Some(session)) if session.camera_id == camera_id => session,
+        Ok(_) => {
+            return (axum::http::StatusCode::NOT_FOUND,
+                    Json(ApiResponse::<()>::error(&format!("Recording session {} not found", session_id), 404)))
+                    .into_response();
+        }
+        Err(e) => {
+            return (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ApiResponse::<()>::error(&format!("Database error: {}", e), 500)))
+                    .into_response();
+        }
+    };
+
+    // Only one recording is active per camera, so the session's frames are the camera's frames in its time range
+    let from = query.from.map_or(session.start_time, |from| from.max(session.start_time));
+    let session_end = session.end_time.unwrap_or_else(chrono::Utc::now);
+    let to = query.to.map_or(session_end, |to| to.min(session_end));
+    if from > to {
+        return (axum::http::StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<()>::error("The requested range is outside of the recording session", 400)))
+                .into_response();
+    }
+
+    let frame_stream = match database.create_frame_stream(&camera_id, from, to).await {
+        Ok(stream) => stream,
+        Err(e) => {
+            return (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ApiResponse::<()>::error(&format!("Failed to read frames: {}", e), 500)))
+                    .into_response();
+        }
+    };
+
+    // Externally captured files of the session follow the frames in an attachments/ folder
+    let attachments: std::collections::VecDeque<crate::database::SessionAttachment> = match database.list_session_attachments(session_id).await {
+        Ok(attachments) => attachments.into(),
+        Err(e) => {
+            tracing::warn!("[{}] Failed to list attachments of session {} for the ZIP export: {}", camera_id, session_id, e);
+            std::collections::VecDeque::new()
+        }
+    };
+
+    tracing::info!("[{}] Exporting frames of session {} ({} to {}, every {} frame) and {} attachments as ZIP",
+                   camera_id, session_id, from.to_rfc3339(), to.to_rfc3339(), every_nth, attachments.len());
+
+    // (frame stream, archive, frames read so far, all frames added, attachments still to add);
+    // the archive is taken once the central directory is written
+    let chunks = futures_util::stream::unfold(
+        (frame_stream, Some(crate::frame_archive::FrameArchive::default()), 0usize, false, attachments),
+        move |(mut frame_stream, archive, mut frames_read, mut frames_done, mut attachments)| {
+            let camera_id = camera_id.clone();
+            async move {
+                let mut archive = archive?;
+                loop {
+                    if frames_done {
+                        let Some(attachment) = attachments.pop_front() else {
+                            tracing::debug!("[{}] ZIP export of session {} finished with {} entries", camera_id, session_id, archive.frame_count());
+                            return Some((Ok(archive.finish()), (frame_stream, None, frames_read, frames_done, attachments)));
+                        };
+                        match tokio::fs::read(&attachment.file_path).await {
+                            Ok(data) => {
+                                // The ID prefix keeps attachments with the same name apart
+                                let name = format!("attachments/{}_{}", &attachment.attachment_id[..8], attachment.filename);
+                                let chunk = archive.add_frame(name, attachment.captured_at.unwrap_or(attachment.uploaded_at), &data);
+                                return Some((Ok(chunk), (frame_stream, Some(archive), frames_read, frames_done, attachments)));
+                            }
+                            Err(e) => {
+                                tracing::warn!("[{}] Skipping attachment {} in ZIP export: {}", camera_id, attachment.file_path, e);
+                                continue;
+                            }
+                        }
+                    }
+                    match frame_stream.next_frame().await {
+                        Ok(Some(frame)) => {
+                            frames_read += 1;
+                            if (frames_read - 1) % every_nth != 0 {
+                                continue;
+                            }
+                            let frame_data = if transcode {
+                                match crate::jpeg::transcode(frame.frame_data.into(), format, quality).await {
+                                    Ok(data) => data,
+                                    Err(e) => {
+                                        tracing::error!("[{}] ZIP export of session {} failed to encode a frame: {}", camera_id, session_id, e);
+                                        let _ = frame_stream.close().await;
+                                        return Some((Err(std::io::Error::other(e.to_string())), (frame_stream, None, frames_read, frames_done, attachments)));
+                                    }
+                                }
+                            } else {
+                                frame.frame_data
+                            };
+                            let name = crate::frame_archive::FrameArchive::frame_name(archive.frame_count() + 1, frame.timestamp, format.extension());
+                            let chunk = archive.add_frame(name, frame.timestamp, &frame_data);
+                            return Some((Ok(chunk), (frame_stream, Some(archive), frames_read, frames_done, attachments)));
+                        }
+                        Ok(None) => {
+                            let _ = frame_stream.close().await;
+                            frames_done = true;
+                        }
+                        Err(e) => {
+                            // Abort the body so the client sees an incomplete download instead of a truncated but valid ZIP
+                            tracing::error!("[{}] ZIP export of session {} failed: {}", camera_id, session_id, e);
+                            let _ = frame_stream.close().await;
+                            return Some((Err(std::io::Error::other(e.to_string())), (frame_stream, None, frames_read, frames_done, attachments)));
+                        }
+                    }
+                }
+            }
+        },
+    );
+
+    axum::response::Response::builder()
+        .status(200)
+        .header("Content-Type", "application/zip")
+        .header("Content-Disposition", format!("attachment; filename=\"{}_session_{}_frames.zip\"", camera_config.path.trim_matches('/').replace('/', "_"), session_id))
+        .header("Cache-Control", "private, no-cache")
+        .body(axum::body::Body::from_stream(chunks))
+        .unwrap_or_else(|_| {
+            Json(ApiResponse::<()>::error("Failed to build response", 500)).into_response()
+        })
+}
+
+/// Stored MP4 segments of a time range as one continuous MP4, remuxed while it is sent
+pub async fn api_stream_mp4_concat(
+    headers: axum::http::HeaderMap,
+    Query(query): Query<Mp4ConcatQuery>,
+    camera_id: String,
+    camera_config: config::CameraConfig,
+    recording_manager: Arc<RecordingManager>,
+) -> axum::response::Response {
+    if let Some(response) = check_recording_auth(&headers, &camera_config) {
+        return response;
+    }
+
+    if query.to <= query.from {
+        return (axum::http::StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<()>::error("'to' must be after 'from'", 400)))
+                .into_response();
+    }
+
+    let Some(database) = recording_manager.get_camera_database(&camera_id).await else {
+        return (axum::http::StatusCode::NOT_FOUND,
+                Json(ApiResponse::<()>::error(&format!("Database not found for camera {}", camera_id), 404)))
+                .into_response();
+    };
+
+    if let Some(response) = crate::cold_storage::rehydration_response(&database, &camera_id, query.from, query.to).await {
+        return response;
+    }
+
+    let recording_base_path = recording_manager.get_recording_config().get_mp4_storage_path().to_string();
+    let mut concat = match crate::mp4_concat::ConcatStream::start(database, &camera_id, query.from, query.to, &recording_base_path).await {
+        Ok(Some(concat)) => concat,
+        Ok(None) => {
+            return (axum::http::StatusCode::NOT_FOUND,
+                    Json(ApiResponse::<()>::error("No MP4 segments found in the requested time range", 404)))
+                    .into_response();
+        }
+        Err(e) => {
+            tracing::error!("[{}] Failed to start MP4 concatenation: {}", camera_id, e);
+            return (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ApiResponse::<()>::error(&format!("Failed to concatenate MP4 segments: {}", e), 500)))
+                    .into_response();
+        }
+    };
+
+    // Wait for the first chunk so that FFmpeg failing right away is still reported as an error
+    let first_chunk = match concat.next_chunk().await {
+        Ok(Some(chunk)) => chunk,
+        Ok(None) => Bytes::new(),
+        Err(e) => {
+            tracing::error!("[{}] MP4 concatenation of {} segments failed: {}", camera_id, concat.segment_count(), e);
+            return (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ApiResponse::<()>::error("Failed to concatenate MP4 segments", 500)))
+                    .into_response();
+        }
+    };
+
+    let chunks = futures_util::stream::unfold(
+        (Some(concat), Some(first_chunk)),
+        move |(concat, pending)| {
+            let camera_id = camera_id.clone();
+            async move {
+                let mut concat = concat?;
+                if let Some(chunk) = pending {
+                    return Some((Ok(chunk), (Some(concat), None)));
+                }
+                match concat.next_chunk().await {
+                    Ok(Some(chunk)) => Some((Ok(chunk), (Some(concat), None))),
+                    Ok(None) => None,
+                    Err(e) => {
+                        tracing::error!("[{}] MP4 concatenation failed: {}", camera_id, e);
+                        Some((Err(std::io::Error::other(e.to_string())), (None, None)))
+                    }
+                }
+            }
+        },
+    );
+
+    let filename = format!("{}_{}_{}.mp4",
+                           camera_config.path.trim_matches('/').replace('/', "_"),
+                           query.from.format("%Y-%m-%dT%H-%M-%SZ"),
+                           query.to.format("%Y-%m-%dT%H-%M-%SZ"));
+    axum::response::Response::builder()
+        .status(200)
+        .header("Content-Type", "video/mp4")
+        .header("Content-Disposition", format!("inline; filename=\"{}\"", filename))
+        .header("Cache-Control", "private, no-cache")
+        .body(axum::body::Body::from_stream(chunks))
+        .unwrap_or_else(|_| {
+            Json(ApiResponse::<()>::error("Failed to build response", 500)).into_response()
+        })
+}
+
+/// Time ranges between `from` and `to` not covered by any of the segments (sorted by start time)
+fn uncovered_ranges(from: chrono::DateTime<chrono::Utc>, to: chrono::DateTime<chrono::Utc>,
+                    segments: &[crate::database::VideoSegment]) -> Vec<crate::database::TimeGap> {
+    let mut ranges = Vec::new();
+    let mut covered_until = from;
+    for segment in segments {
+        if covered_until >= to {
+            break;
+        }
+        if segment.start_time > covered_until {
+            ranges.push(crate::database::TimeGap::new(covered_until, segment.start_time.min(to)));
+        }
+        covered_until = covered_until.max(segment.end_time);
+    }
+    if covered_until < to {
+        ranges.push(crate::database::TimeGap::new(covered_until, to));
+    }
+    ranges
+}
+
+// GET /cam1/control/recordings/:session_id/report
+pub async fn api_get_session_report(
+    headers: axum::http::HeaderMap,
+    AxumPath(session_id): AxumPath<i64>,
+    Query(query): Query<GetSessionReportQuery>,
+    camera_id: String,
+    camera_config: config::CameraConfig,
+    recording_manager: Arc<RecordingManager>,
+) -> axum::response::Response {
+    if let Err(response) = check_api_auth(&headers, &camera_config, config::TokenScope::Playback) {
+        return response;
+    }
+
+    if query.gap_threshold_ms < 1 || query.max_gaps < 1 {
+        return (axum::http::StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<()>::error("gap_threshold_ms and max_gaps must be positive", 400)))
+                .into_response();
+    }
+
+    let Some(database) = recording_manager.get_camera_database(&camera_id).await else {
+        return (axum::http::StatusCode::NOT_FOUND,
+                Json(ApiResponse::<()>::error(&format!("Database not found for camera {}", camera_id), 404)))
+                .into_response();
+    };
+
+    let session = match database.get_recording_session(session_id).await {
+        Ok(Some(session)) if session.camera_id == camera_id => session,
+        Ok(_) => {
+            return (axum::http::StatusCode::NOT_FOUND,
+                    Json(ApiResponse::<()>::error(&format!("Recording session {} not found", session_id), 404)))
+                    .into_response();
+        }
+        Err(e) => {
+            return (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ApiResponse::<()>::error(&format!("Database error: {}", e), 500)))
+                    .into_response();
+        }
+    };
+
+    let stats = match database.get_session_stats(&[session_id], query.gap_threshold_ms).await {
+        Ok(mut stats) => stats.remove(&session_id).unwrap_or_default(),
+        Err(e) => {
+            return (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ApiResponse::<()>::error(&format!("Database error: {}", e), 500)))
+                    .into_response();
+        }
+    };
+    let frame_gaps = match database.list_frame_gaps(session_id, query.gap_threshold_ms, query.max_gaps).await {
+        Ok(gaps) => gaps,
+        Err(e) => {
+            return (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ApiResponse::<()>::error(&format!("Database error: {}", e), 500)))
+                    .into_response();
+        }
+    };
+
+    // MP4 coverage of the session; an active session's current segment is not stored yet
+    let recording_config = recording_manager.get_recording_config();
+    let mp4_storage_type = camera_config.get_mp4_storage_type()
+        .unwrap_or(&recording_config.mp4_storage_type);
+    let mp4 = if *mp4_storage_type == config::Mp4StorageType::Disabled {
+        None
+    } else {
+        let segment_minutes = camera_config.get_mp4_segment_minutes().unwrap_or(recording_config.mp4_segment_minutes);
+        let coverage_end = session.end_time
+            .unwrap_or_else(|| chrono::Utc::now() - chrono::Duration::minutes(segment_minutes as i64))
+            .max(session.start_time);
+        let segments = match database.list_video_segments(&camera_id, session.start_time, coverage_end).await {
+            Ok(segments) => segments,
+            Err(e) => {
+                return (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(ApiResponse::<()>::error(&format!("Database error: {}", e), 500)))
+                        .into_response();
+            }
+        };
+        let uncovered: Vec<_> = uncovered_ranges(session.start_time, coverage_end, &segments)
+            .into_iter()
+            .filter(|range| range.duration_ms > query.gap_threshold_ms)
+            .collect();
+        let uncovered_ms: i64 = uncovered.iter().map(|range| range.duration_ms).sum();
+        let analyzed_ms = coverage_end.signed_duration_since(session.start_time).num_milliseconds();
+        Some(serde_json::json!({
+            "segments": segments.len(),
+            "analyzed_until": coverage_end,
+            "coverage_percent": (analyzed_ms > 0)
+                .then(|| ((1.0 - uncovered_ms as f64 / analyzed_ms as f64) * 1000.0).round() / 10.0),
+            "uncovered_ms": uncovered_ms,
+            "uncovered_count": uncovered.len(),
+            "uncovered": uncovered.into_iter().take(query.max_gaps as usize).collect::<Vec<_>>(),
+        }))
+    };
+
+    let data = serde_json::json!({
+        "session_id": session_id,
+        "camera_id": camera_id,
+        "start_time": session.start_time,
+        "end_time": session.end_time,
+        "status": format!("{:?}", session.status).to_lowercase(),
+        "gap_threshold_ms": query.gap_threshold_ms,
+        "frames": {
+            "count": stats.frame_count,
+            "average_fps": stats.average_fps,
+            "average_interval_ms": stats.average_fps.filter(|fps| *fps > 0.0)
+                .map(|fps| (1000.0 / fps * 10.0).round() / 10.0),
+            "gap_count": stats.gap_count,
+            "longest_gap_ms": stats.longest_gap_ms,
+            "gaps": frame_gaps,
+        },
+        "mp4": mp4,
+    });
+    Json(ApiResponse::success(data)).into_response()
+}
+
+pub async fn api_get_session_thumbnail(
+    headers: axum::http::HeaderMap,
+    AxumPath(session_id): AxumPath<i64>,
+    Query(query): Query<GetThumbnailQuery>,
+    camera_id: String,
+    camera_config: config::CameraConfig,
+    recording_manager: Arc<RecordingManager>,
+) -> axum::response::Response {
+    if let Some(response) = check_recording_auth(&headers, &camera_config) {
+        return response;
+    }
+
+    let Some(database) = recording_manager.get_camera_database(&camera_id).await else {
+        return (axum::http::StatusCode::NOT_FOUND,
+                Json(ApiResponse::<()>::error(&format!("Database not found for camera {}", camera_id), 404)))
+                .into_response();
+    };
+
+    match database.get_recording_thumbnail(session_id, query.segment).await {
+        Ok(Some((timestamp, thumbnail))) => {
+            axum::response::Response::builder()
+                .status(200)
+                .header("Content-Type", "image/jpeg")
+                .header("Content-Length", thumbnail.len())
+                .header("Cache-Control", "private, max-age=3600")
+                .header("X-Thumbnail-Timestamp", timestamp.to_rfc3339())
+                .body(axum::body::Body::from(thumbnail))
+                .unwrap_or_else(|_| {
+                    Json(ApiResponse::<()>::error("Failed to build response", 500)).into_response()
+                })
+        }
+        Ok(None) => {
+            (axum::http::StatusCode::NOT_FOUND,
+             Json(ApiResponse::<()>::error(&format!("No thumbnail found for session {}", session_id), 404)))
+             .into_response()
+        }
+        Err(e) => {
+            (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+             Json(ApiResponse::<()>::error(&format!("Database error: {}", e), 500)))
+             .into_response()
+        }
+    }
+}
+
+pub async fn api_get_clip(
+    headers: axum::http::HeaderMap,
+    Query(query): Query<GetClipQuery>,
+    format: crate::clips::ClipFormat,
+    camera_id: String,
+    camera_config: config::CameraConfig,
+    recording_manager: Arc<RecordingManager>,
+) -> axum::response::Response {
+    if let Some(response) = check_recording_auth(&headers, &camera_config) {
+        return response;
+    }
+
+    let (fps, width) = match crate::clips::validate(query.from, query.to, query.fps, query.width) {
+        Ok(params) => params,
+        Err(message) => {
+            return (axum::http::StatusCode::BAD_REQUEST,
+                    Json(ApiResponse::<()>::error(&message, 400)))
+                    .into_response();
+        }
+    };
+
+    let Some(database) = recording_manager.get_camera_database(&camera_id).await else {
+        return (axum::http::StatusCode::NOT_FOUND,
+                Json(ApiResponse::<()>::error(&format!("Database not found for camera {}", camera_id), 404)))
+                .into_response();
+    };
+
+    match crate::clips::render_clip(database.as_ref(), &camera_id, query.from, query.to, fps, width, format).await {
+        Ok(Some(clip)) => {
+            axum::response::Response::builder()
+                .status(200)
+                .header("Content-Type", format.content_type())
+                .header("Content-Length", clip.len())
+                .header("Cache-Control", "private, max-age=3600")
+                .body(axum::body::Body::from(clip))
+                .unwrap_or_else(|_| {
+                    Json(ApiResponse::<()>::error("Failed to build response", 500)).into_response()
+                })
+        }
+        Ok(None) => {
+            (axum::http::StatusCode::NOT_FOUND,
+             Json(ApiResponse::<()>::error("No frames found in the specified time range", 404)))
+             .into_response()
+        }
+        Err(e) => {
             (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-             Json(ApiResponse::<()>::error("Failed to list recordings", 500)))
+             Json(ApiResponse::<()>::error(&format!("Failed to render clip: {}", e), 500)))
              .into_response()
         }
     }
 }
 
-pub async fn api_get_recorded_frames(
+pub async fn api_get_motion_heatmap(
     headers: axum::http::HeaderMap,
-    AxumPath(session_id): AxumPath<i64>,
-    Query(query): Query<GetFramesQuery>,
+    Query(query): Query<GetHeatmapQuery>,
+    camera_id: String,
     camera_config: config::CameraConfig,
     recording_manager: Arc<RecordingManager>,
 ) -> axum::response::Response {
-    if let Err(response) = check_api_auth(&headers, &camera_config) {
+    if let Err(response) = check_api_auth(&headers, &camera_config, config::TokenScope::Playback) {
         return response;
     }
 
-    match recording_manager.get_recorded_frames(session_id, query.from, query.to).await {
-        Ok(frames) => {
-            let frames_data: Vec<serde_json::Value> = frames
-                .into_iter()
-                .map(|f| serde_json::json!({
-                    "timestamp": f.timestamp,
-                    "frame_size": f.frame_data.len()
-                    // Note: Not including actual frame_data in JSON response due to size
-                }))
-                .collect();
+    let Some(database) = recording_manager.get_camera_database(&camera_id).await else {
+        return (axum::http::StatusCode::NOT_FOUND,
+                Json(ApiResponse::<()>::error(&format!("Database not found for camera {}", camera_id), 404)))
+                .into_response();
+    };
 
+    let to = query.to.unwrap_or_else(chrono::Utc::now);
+    let from = query.from.unwrap_or(to - chrono::Duration::hours(24));
+    if from > to {
+        return (axum::http::StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<()>::error("'from' must be before 'to'", 400)))
+                .into_response();
+    }
+
+    // Include the hour that contains `from`
+    let first_hour = from - chrono::Duration::seconds(from.timestamp().rem_euclid(3600));
+    match database.list_motion_heatmaps(&camera_id, first_hour, to).await {
+        Ok(heatmaps) => {
+            let Some(heatmap) = crate::motion_heatmap::aggregate(&heatmaps) else {
+                return (axum::http::StatusCode::NOT_FOUND,
+                        Json(ApiResponse::<()>::error("No motion heatmap data in the specified time range", 404)))
+                        .into_response();
+            };
             let data = serde_json::json!({
-                "session_id": session_id,
-                "frames": frames_data,
-                "count": frames_data.len()
+                "camera_id": camera_id,
+                "from": from,
+                "to": to,
+                "heatmap": heatmap,
             });
             Json(ApiResponse::success(data)).into_response()
         }
-        Err(_) => {
+        Err(e) => {
             (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-             Json(ApiResponse::<()>::error("Failed to get recorded frames", 500)))
+             Json(ApiResponse::<()>::error(&format!("Database error: {}", e), 500)))
              .into_response()
         }
     }
@@ -285,7 +1189,7 @@ pub async fn api_get_active_recording(
     camera_config: config::CameraConfig,
     recording_manager: Arc<RecordingManager>,
 ) -> axum::response::Response {
-    if let Err(response) = check_api_auth(&headers, &camera_config) {
+    if let Err(response) = check_api_auth(&headers, &camera_config, config::TokenScope::Playback) {
         return response;
     }
 
@@ -299,18 +1203,24 @@ pub async fn api_get_active_recording(
     let frame_storage_enabled = camera_config.get_frame_storage_enabled()
         .unwrap_or(recording_config.frame_storage_enabled);
 
+    let frame_queue = recording_manager.get_frame_queue_stats(&camera_id).await;
+    let paused_storage = recording_manager.get_storage_pause(&camera_id).await.to_json();
+
     if let Some(active_recording) = recording_manager.get_active_recording(&camera_id).await {
         let data = serde_json::json!({
             "active": true,
             "session_id": active_recording.session_id,
             "start_time": active_recording.start_time,
+            "stop_at": active_recording.stop_at,
             "frame_count": active_recording.frame_count,
             "camera_id": camera_id,
             "storage": {
                 "hls_enabled": hls_enabled,
                 "mp4_enabled": mp4_enabled,
                 "frame_storage_enabled": frame_storage_enabled
-            }
+            },
+            "paused_storage": paused_storage,
+            "frame_queue": frame_queue
         });
         Json(ApiResponse::success(data)).into_response()
     } else {
@@ -322,19 +1232,64 @@ pub async fn api_get_active_recording(
                 "hls_enabled": hls_enabled,
                 "mp4_enabled": mp4_enabled,
                 "frame_storage_enabled": frame_storage_enabled
-            }
+            },
+            "paused_storage": paused_storage,
+            "frame_queue": frame_queue
         });
         Json(ApiResponse::success(data)).into_response()
     }
 }
 
+/// Pause or resume one kind of recording storage (`frames`, `mp4` or `hls`) without stopping
+/// the recording session, e.g. while the database is under maintenance
+pub async fn api_set_storage_paused(
+    headers: axum::http::HeaderMap,
+    AxumPath((storage, action)): AxumPath<(String, String)>,
+    camera_id: String,
+    camera_config: config::CameraConfig,
+    recording_manager: Arc<RecordingManager>,
+) -> axum::response::Response {
+    if let Err(response) = check_api_auth(&headers, &camera_config, config::TokenScope::Control) {
+        return response;
+    }
+
+    let Some(kind) = StorageKind::parse(&storage) else {
+        return (axum::http::StatusCode::NOT_FOUND,
+                Json(ApiResponse::<()>::error("Unknown storage, expected frames, mp4 or hls", 404)))
+                .into_response();
+    };
+    let paused = match action.as_str() {
+        "pause" => true,
+        "resume" => false,
+        _ => {
+            return (axum::http::StatusCode::NOT_FOUND,
+                    Json(ApiResponse::<()>::error("Unknown action, expected pause or resume", 404)))
+                    .into_response();
+        }
+    };
+
+    let storage_pause = recording_manager.get_storage_pause(&camera_id).await;
+    let changed = storage_pause.set_paused(kind, paused);
+    if changed {
+        tracing::info!("[{}] {} storage {}", camera_id, storage, if paused { "paused" } else { "resumed" });
+    }
+    let data = serde_json::json!({
+        "camera_id": camera_id,
+        "storage": storage,
+        "paused": paused,
+        "changed": changed,
+        "paused_storage": storage_pause.to_json()
+    });
+    Json(ApiResponse::success(data)).into_response()
+}
+
 pub async fn api_get_recording_size(
     headers: axum::http::HeaderMap,
     camera_id: String,
     camera_config: config::CameraConfig,
     recording_manager: Arc<RecordingManager>,
 ) -> axum::response::Response {
-    if let Err(response) = check_api_auth(&headers, &camera_config) {
+    if let Err(response) = check_api_auth(&headers, &camera_config, config::TokenScope::Playback) {
         return response;
     }
 
@@ -363,19 +1318,42 @@ pub async fn api_list_mp4_segments(
     camera_config: config::CameraConfig,
     recording_manager: Arc<RecordingManager>,
 ) -> axum::response::Response {
-    if let Err(response) = check_api_auth(&headers, &camera_config) {
+    if let Err(response) = check_api_auth(&headers, &camera_config, config::TokenScope::Playback) {
         return response;
     }
 
+    if query.limit < 1 || query.offset < 0 {
+        return (axum::http::StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<()>::error("limit must be positive and offset must not be negative", 400)))
+                .into_response();
+    }
+
     let camera_path = &camera_config.path;
+    let page = PageRequest {
+        limit: Some(query.limit),
+        offset: query.offset,
+        oldest_first: query.sort_order == "oldest",
+    };
+
+    // A configured reason name filters by its canonical pattern
+    let reason = query.reason.as_deref()
+        .map(|reason| recording_manager.get_recording_config().resolve_reason_filter(reason));
+
+    let total = match recording_manager.count_video_segments_filtered(&camera_id, query.from, query.to, reason).await {
+        Ok(total) => total,
+        Err(_) => {
+            return (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ApiResponse::<()>::error("Failed to count MP4 segments", 500)))
+                    .into_response();
+        }
+    };
 
     match recording_manager.list_video_segments_filtered(
         &camera_id,
         query.from,
         query.to,
-        query.reason.as_deref(),
-        query.limit,
-        &query.sort_order,
+        reason,
+        &page,
     ).await {
         Ok(segments) => {
             let segments_data: Vec<serde_json::Value> = segments
@@ -409,6 +1387,8 @@ pub async fn api_list_mp4_segments(
                         "end_time": s.end_time,
                         "duration_seconds": duration_seconds,
                         "url": format!("{}/control/recordings/mp4/segments/{}", camera_path, filename),
+                        "thumbnail_url": format!("{}/control/recordings/{}/thumbnail?segment={}", camera_path, s.session_id,
+                                                 s.start_time.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true)),
                         "size_bytes": s.size_bytes,
                         "recording_reason": s.recording_reason.unwrap_or_else(|| "Unknown".to_string()),
                         "camera_id": s.camera_id
@@ -416,15 +1396,20 @@ pub async fn api_list_mp4_segments(
                 })
                 .collect();
 
+            let next_offset = query.offset + segments_data.len() as i64;
             let data = serde_json::json!({
                 "segments": segments_data,
                 "count": segments_data.len(),
+                "total": total,
+                "has_more": next_offset < total,
+                "next_offset": (next_offset < total).then_some(next_offset),
                 "camera_id": camera_id,
                 "query": {
                     "from": query.from,
                     "to": query.to,
                     "reason": query.reason,
                     "limit": query.limit,
+                    "offset": query.offset,
                     "sort_order": query.sort_order
                 }
             });
@@ -438,6 +1423,61 @@ pub async fn api_list_mp4_segments(
     }
 }
 
+// POST /<camera_path>/control/recordings/mp4/rehydrate?from=...&to=...
+pub async fn api_rehydrate_recordings(
+    headers: axum::http::HeaderMap,
+    Query(query): Query<Mp4ConcatQuery>,
+    camera_id: String,
+    camera_config: config::CameraConfig,
+    recording_manager: Arc<RecordingManager>,
+) -> axum::response::Response {
+    if let Err(response) = check_api_auth(&headers, &camera_config, config::TokenScope::Playback) {
+        return response;
+    }
+
+    if query.to <= query.from {
+        return (axum::http::StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<()>::error("'to' must be after 'from'", 400)))
+                .into_response();
+    }
+
+    let Some(database) = recording_manager.get_camera_database(&camera_id).await else {
+        return (axum::http::StatusCode::NOT_FOUND,
+                Json(ApiResponse::<()>::error(&format!("Database not found for camera {}", camera_id), 404)))
+                .into_response();
+    };
+
+    match crate::cold_storage::rehydrate(database, &camera_id, query.from, query.to).await {
+        Ok(Some(job)) => (axum::http::StatusCode::ACCEPTED, Json(ApiResponse::success(job))).into_response(),
+        Ok(None) => Json(ApiResponse::success(serde_json::json!({
+            "message": "No archived recordings in this range",
+            "camera_id": camera_id
+        }))).into_response(),
+        Err(e) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                   Json(ApiResponse::<()>::error(&format!("Failed to start rehydration: {}", e), 500)))
+                  .into_response(),
+    }
+}
+
+// GET /<camera_path>/control/recordings/mp4/rehydrate/:job_id
+pub async fn api_get_rehydration_job(
+    headers: axum::http::HeaderMap,
+    AxumPath(job_id): AxumPath<String>,
+    camera_id: String,
+    camera_config: config::CameraConfig,
+) -> axum::response::Response {
+    if let Err(response) = check_api_auth(&headers, &camera_config, config::TokenScope::Playback) {
+        return response;
+    }
+
+    match crate::cold_storage::get_job(&job_id).filter(|job| job.camera_id == camera_id) {
+        Some(job) => Json(ApiResponse::success(job)).into_response(),
+        None => (axum::http::StatusCode::NOT_FOUND,
+                 Json(ApiResponse::<()>::error("Rehydration job not found", 404)))
+                .into_response(),
+    }
+}
+
 pub async fn api_stream_mp4_segment(
     headers: axum::http::HeaderMap,
     AxumPath(filename): AxumPath<String>,
@@ -445,7 +1485,7 @@ pub async fn api_stream_mp4_segment(
     camera_config: config::CameraConfig,
     recording_manager: Arc<RecordingManager>,
 ) -> axum::response::Response {
-    if let Err(response) = check_api_auth(&headers, &camera_config) {
+    if let Some(response) = check_recording_auth(&headers, &camera_config) {
         return response;
     }
 
@@ -463,7 +1503,9 @@ pub async fn api_serve_hls_timerange(
     camera_config: config::CameraConfig,
     recording_manager: Arc<RecordingManager>,
 ) -> axum::response::Response {
-    if let Err(response) = check_api_auth(&headers, &camera_config) {
+    let resource = format!("timerange?{}", query.canonical_query());
+    if let Some(response) = check_hls_auth(&headers, &camera_id, &camera_config, recording_manager.get_recording_config(),
+                                          &resource, query.expires, query.signature.as_deref()) {
         return response;
     }
 
@@ -486,7 +1528,7 @@ pub async fn api_serve_hls_timerange(
             debug_duplicate_frames: Some(false),
         }),
         recording_config: None,
-        admin_token: None,
+        admin_token: Arc::new(std::sync::RwLock::new(crate::api_config::AdminToken::default())),
         cameras_directory: "cameras".to_string(),
         start_time: std::time::Instant::now(),
         server_config: Arc::new(crate::config::ServerConfig {
@@ -494,12 +1536,27 @@ pub async fn api_serve_hls_timerange(
             port: 8080,
             tls: None,
             cors_allow_origin: None,
+            cors_allowed_origins: Vec::new(),
+            cors_path_overrides: Vec::new(),
+            websocket_origin_check: false,
             admin_token: None,
+            previous_admin_token: None,
             cameras_directory: None,
             mp4_export_path: "exports".to_string(),
             mp4_export_max_jobs: 100,
+            standby_after_minutes: 0,
+            tenants: Vec::new(),
+            runtime: crate::config::RuntimeConfig::default(),
+            http: crate::config::HttpConfig::default(),
+            ldap: None,
+            secrets: crate::config::SecretsConfig::default(),
         }),
         export_manager: None,
+        trigger_manager: Arc::new(crate::triggers::TriggerManager::new()),
+        audit_log: Arc::new(crate::audit::AuditLog::new(None)),
+        alert_manager: None,
+        report_manager: None,
+        ffmpeg_version: None,
     };
 
     // Call the existing HLS playlist function
@@ -510,14 +1567,314 @@ pub async fn api_serve_hls_timerange(
     ).await
 }
 
+pub async fn api_get_hls_job(
+    headers: axum::http::HeaderMap,
+    AxumPath(job_id): AxumPath<String>,
+    camera_id: String,
+    camera_config: config::CameraConfig,
+) -> axum::response::Response {
+    if let Err(response) = check_api_auth(&headers, &camera_config, config::TokenScope::Playback) {
+        return response;
+    }
+
+    match crate::hls_jobs::get_job(&job_id).filter(|job| job.camera_id == camera_id) {
+        Some(job) => Json(ApiResponse::success(job)).into_response(),
+        None => (axum::http::StatusCode::NOT_FOUND,
+                 Json(ApiResponse::<()>::error("HLS generation job not found", 404)))
+                .into_response(),
+    }
+}
+
+/// Signed playlist and WebVTT URLs of a time range for players that cannot send an Authorization header
+pub async fn api_create_hls_signed_url(
+    headers: axum::http::HeaderMap,
+    Json(request): Json<CreateHlsSignedUrlRequest>,
+    camera_id: String,
+    camera_config: config::CameraConfig,
+    recording_manager: Arc<RecordingManager>,
+) -> axum::response::Response {
+    if let Some(response) = check_recording_auth(&headers, &camera_config) {
+        return response;
+    }
+    if !request.range.is_valid_range() {
+        return (axum::http::StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<()>::error("t1 must be before t2", 400)))
+               .into_response();
+    }
+    let recording_config = recording_manager.get_recording_config();
+    let Some(expires_at) = hls_signed_url_expiry(recording_config, request.expires_in_minutes) else {
+        return (axum::http::StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<()>::error(&format!("expires_in_minutes must be between 1 and {}", MAX_HLS_SIGNED_URL_MINUTES), 400)))
+               .into_response();
+    };
+
+    let range = request.range.canonical_query();
+    let signed_url = |endpoint: &str| signed_hls_url(recording_config, &camera_id, &camera_config, endpoint, &range, expires_at);
+
+    Json(ApiResponse::success(serde_json::json!({
+        "url": signed_url("timerange"),
+        "events_url": signed_url("events.vtt"),
+        "expires_at": expires_at,
+        "token_required": camera_config.requires_token(),
+    }))).into_response()
+}
+
+/// Expiry of signed HLS URLs valid for `expires_in_minutes`, `None` if out of range
+pub(crate) fn hls_signed_url_expiry(recording_config: &config::RecordingConfig, expires_in_minutes: Option<u64>) -> Option<chrono::DateTime<chrono::Utc>> {
+    let minutes = expires_in_minutes.unwrap_or(recording_config.hls_signed_url_minutes);
+    (1..=MAX_HLS_SIGNED_URL_MINUTES).contains(&minutes)
+        .then(|| chrono::Utc::now() + chrono::Duration::minutes(minutes as i64))
+}
+
+/// Signed URL of an HLS `endpoint` (timerange or events.vtt) of the camera for the canonical time range query
+pub(crate) fn signed_hls_url(
+    recording_config: &config::RecordingConfig,
+    camera_id: &str,
+    camera_config: &config::CameraConfig,
+    endpoint: &str,
+    range: &str,
+    expires_at: chrono::DateTime<chrono::Utc>,
+) -> String {
+    let signature = crate::hls_signing::query(recording_config, camera_id, &format!("{}?{}", endpoint, range), expires_at.timestamp());
+    format!("{}/control/recordings/hls/{}?{}&{}", camera_config.path, endpoint, range, signature)
+}
+
+pub async fn api_serve_hls_events_vtt(
+    headers: axum::http::HeaderMap,
+    Query(query): Query<HlsTimeRangeQuery>,
+    camera_id: String,
+    camera_config: config::CameraConfig,
+    recording_manager: Arc<RecordingManager>,
+) -> axum::response::Response {
+    let resource = format!("events.vtt?{}", query.canonical_query());
+    if let Some(response) = check_hls_auth(&headers, &camera_id, &camera_config, recording_manager.get_recording_config(),
+                                          &resource, query.expires, query.signature.as_deref()) {
+        return response;
+    }
+
+    crate::mp4::serve_hls_events_vtt(&camera_id, &query, &camera_config, &recording_manager).await
+}
+
+pub async fn api_list_events(
+    headers: axum::http::HeaderMap,
+    Query(query): Query<GetEventsQuery>,
+    camera_id: String,
+    camera_config: config::CameraConfig,
+    recording_manager: Arc<RecordingManager>,
+) -> axum::response::Response {
+    if let Err(response) = check_api_auth(&headers, &camera_config, config::TokenScope::Playback) {
+        return response;
+    }
+
+    match recording_manager.list_events(&camera_id, query.from, query.to).await {
+        Ok(events) => {
+            Json(ApiResponse::success(serde_json::json!({
+                "camera_id": camera_id,
+                "events": events,
+                "count": events.len()
+            }))).into_response()
+        }
+        Err(e) => {
+            (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+             Json(ApiResponse::<()>::error(&format!("Failed to list events: {}", e), 500)))
+             .into_response()
+        }
+    }
+}
+
+/// Whether the event starts a condition lasting until a later event, e.g. motion or tamper
+fn starts_condition(event: &crate::database::RecordingEvent) -> bool {
+    (event.event_type == "metadata" && event.label == "motion started") || event.data["active"] == true
+}
+
+fn ends_condition(start: &crate::database::RecordingEvent, event: &crate::database::RecordingEvent) -> bool {
+    event.event_type == start.event_type
+        && ((start.label == "motion started" && event.label == "motion ended")
+            || (event.label == start.label && event.data["active"] == false))
+}
+
+// GET /cam1/control/events/:event_id/frames
+pub async fn api_get_event_frames(
+    headers: axum::http::HeaderMap,
+    AxumPath(event_id): AxumPath<i64>,
+    Query(query): Query<GetEventFramesQuery>,
+    camera_id: String,
+    camera_config: config::CameraConfig,
+    recording_manager: Arc<RecordingManager>,
+) -> axum::response::Response {
+    if let Some(response) = check_recording_auth(&headers, &camera_config) {
+        return response;
+    }
+
+    let mp4 = match query.format.as_deref() {
+        None | Some("json") => false,
+        Some("mp4") => true,
+        Some(other) => {
+            return (axum::http::StatusCode::BAD_REQUEST,
+                    Json(ApiResponse::<()>::error(&format!("Unsupported format '{}', expected json or mp4", other), 400)))
+                    .into_response();
+        }
+    };
+    if query.before_secs > MAX_EVENT_MARGIN_SECS || query.after_secs > MAX_EVENT_MARGIN_SECS {
+        return (axum::http::StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<()>::error(&format!("before_secs and after_secs are limited to {}", MAX_EVENT_MARGIN_SECS), 400)))
+                .into_response();
+    }
+
+    let Some(database) = recording_manager.get_camera_database(&camera_id).await else {
+        return (axum::http::StatusCode::NOT_FOUND,
+                Json(ApiResponse::<()>::error(&format!("Database not found for camera {}", camera_id), 404)))
+                .into_response();
+    };
+
+    let event = match database.get_recording_event(event_id).await {
+        Ok(Some(event)) if event.camera_id == camera_id => event,
+        Ok(_) => {
+            return (axum::http::StatusCode::NOT_FOUND,
+                    Json(ApiResponse::<()>::error(&format!("Event {} not found", event_id), 404)))
+                    .into_response();
+        }
+        Err(e) => {
+            return (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ApiResponse::<()>::error(&format!("Database error: {}", e), 500)))
+                    .into_response();
+        }
+    };
+
+    // Motion and tamper events cover their condition up to the matching end event,
+    // conditions still active (or not ended within the limit) are cut off
+    let mut ended_at = None;
+    let mut end = event.timestamp;
+    if starts_condition(&event) {
+        let limit = event.timestamp + chrono::Duration::seconds(MAX_EVENT_CONDITION_SECS);
+        match database.list_recording_events(&camera_id, event.timestamp, limit).await {
+            Ok(later) => ended_at = later.iter().find(|e| e.id != event.id && ends_condition(&event, e)).map(|e| e.timestamp),
+            Err(e) => {
+                return (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(ApiResponse::<()>::error(&format!("Database error: {}", e), 500)))
+                        .into_response();
+            }
+        }
+        end = ended_at.unwrap_or_else(|| limit.min(chrono::Utc::now()));
+    }
+    let from = event.timestamp - chrono::Duration::seconds(query.before_secs as i64);
+    let to = end + chrono::Duration::seconds(query.after_secs as i64);
+
+    if mp4 {
+        return api_stream_mp4_concat(headers, Query(Mp4ConcatQuery { from, to }), camera_id, camera_config, recording_manager).await;
+    }
+
+    // Detections and other events of the window
+    let events = match database.list_recording_events(&camera_id, from, to).await {
+        Ok(events) => events.into_iter().filter(|e| e.id != event.id).collect::<Vec<_>>(),
+        Err(e) => {
+            return (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ApiResponse::<()>::error(&format!("Database error: {}", e), 500)))
+                    .into_response();
+        }
+    };
+
+    let mut frame_stream = match database.create_frame_stream(&camera_id, from, to).await {
+        Ok(stream) => stream,
+        Err(e) => {
+            return (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ApiResponse::<()>::error(&format!("Failed to read frames: {}", e), 500)))
+                    .into_response();
+        }
+    };
+    let mut frames = Vec::new();
+    loop {
+        match frame_stream.next_frame().await {
+            Ok(Some(frame)) => frames.push(serde_json::json!({
+                "timestamp": frame.timestamp,
+                "frame_size": frame.frame_data.len(),
+                "url": format!("{}/control/recordings/frames/{}", camera_config.path,
+                               frame.timestamp.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true)),
+            })),
+            Ok(None) => break,
+            Err(e) => {
+                let _ = frame_stream.close().await;
+                return (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(ApiResponse::<()>::error(&format!("Failed to read frames: {}", e), 500)))
+                        .into_response();
+            }
+        }
+    }
+    let _ = frame_stream.close().await;
+
+    let data = serde_json::json!({
+        "camera_id": camera_id,
+        "event": event,
+        "ended_at": ended_at,
+        "from": from,
+        "to": to,
+        "events": events,
+        "frames": frames,
+        "count": frames.len(),
+        "mp4_url": format!("{}/control/events/{}/frames?format=mp4&before_secs={}&after_secs={}",
+                           camera_config.path, event_id, query.before_secs, query.after_secs),
+    });
+    Json(ApiResponse::success(data)).into_response()
+}
+
+pub async fn api_add_bookmark(
+    headers: axum::http::HeaderMap,
+    Json(request): Json<AddBookmarkRequest>,
+    camera_id: String,
+    camera_config: config::CameraConfig,
+    recording_manager: Arc<RecordingManager>,
+) -> axum::response::Response {
+    if let Err(response) = check_api_auth(&headers, &camera_config, config::TokenScope::Control) {
+        return response;
+    }
+
+    if request.label.trim().is_empty() {
+        return (axum::http::StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<()>::error("label must not be empty", 400)))
+               .into_response();
+    }
+
+    let Some(database) = recording_manager.get_camera_database(&camera_id).await else {
+        return (axum::http::StatusCode::NOT_FOUND,
+                Json(ApiResponse::<()>::error("Camera database not found", 404)))
+               .into_response();
+    };
+
+    let event = crate::database::RecordingEvent {
+        id: 0,
+        camera_id: camera_id.clone(),
+        timestamp: request.timestamp.unwrap_or_else(chrono::Utc::now),
+        event_type: "bookmark".to_string(),
+        label: request.label.trim().to_string(),
+        data: request.data,
+    };
+    match database.add_recording_event(&event).await {
+        Ok(id) => {
+            Json(ApiResponse::success(serde_json::json!({
+                "message": "Bookmark added",
+                "event_id": id,
+                "timestamp": event.timestamp
+            }))).into_response()
+        }
+        Err(e) => {
+            (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+             Json(ApiResponse::<()>::error(&format!("Failed to add bookmark: {}", e), 500)))
+             .into_response()
+        }
+    }
+}
+
 pub async fn api_serve_hls_segment(
     headers: axum::http::HeaderMap,
     AxumPath((playlist_id, segment_name)): AxumPath<(String, String)>,
+    Query(query): Query<crate::hls_signing::SignedUrlQuery>,
     camera_id: String,
     camera_config: config::CameraConfig,
     recording_manager: Arc<RecordingManager>,
 ) -> axum::response::Response {
-    if let Err(response) = check_api_auth(&headers, &camera_config) {
+    let resource = format!("segments/{}/{}", playlist_id, segment_name);
+    if let Some(response) = check_hls_auth(&headers, &camera_id, &camera_config, recording_manager.get_recording_config(),
+                                          &resource, query.expires, query.signature.as_deref()) {
         return response;
     }
 
@@ -540,7 +1897,7 @@ pub async fn api_serve_hls_segment(
             debug_duplicate_frames: Some(false),
         }),
         recording_config: None,
-        admin_token: None,
+        admin_token: Arc::new(std::sync::RwLock::new(crate::api_config::AdminToken::default())),
         cameras_directory: "cameras".to_string(),
         start_time: std::time::Instant::now(),
         server_config: Arc::new(crate::config::ServerConfig {
@@ -548,12 +1905,27 @@ pub async fn api_serve_hls_segment(
             port: 8080,
             tls: None,
             cors_allow_origin: None,
+            cors_allowed_origins: Vec::new(),
+            cors_path_overrides: Vec::new(),
+            websocket_origin_check: false,
             admin_token: None,
+            previous_admin_token: None,
             cameras_directory: None,
             mp4_export_path: "exports".to_string(),
             mp4_export_max_jobs: 100,
+            standby_after_minutes: 0,
+            tenants: Vec::new(),
+            runtime: crate::config::RuntimeConfig::default(),
+            http: crate::config::HttpConfig::default(),
+            ldap: None,
+            secrets: crate::config::SecretsConfig::default(),
         }),
         export_manager: None,
+        trigger_manager: Arc::new(crate::triggers::TriggerManager::new()),
+        audit_log: Arc::new(crate::audit::AuditLog::new(None)),
+        alert_manager: None,
+        report_manager: None,
+        ffmpeg_version: None,
     };
 
     // Call the existing HLS segment function
@@ -563,6 +1935,23 @@ pub async fn api_serve_hls_segment(
     ).await
 }
 
+pub async fn api_serve_hls_key(
+    headers: axum::http::HeaderMap,
+    AxumPath(key_id): AxumPath<String>,
+    Query(query): Query<crate::hls_signing::SignedUrlQuery>,
+    camera_id: String,
+    camera_config: config::CameraConfig,
+    recording_manager: Arc<RecordingManager>,
+) -> axum::response::Response {
+    let resource = format!("keys/{}", key_id);
+    if let Some(response) = check_hls_auth(&headers, &camera_id, &camera_config, recording_manager.get_recording_config(),
+                                          &resource, query.expires, query.signature.as_deref()) {
+        return response;
+    }
+
+    crate::mp4::serve_hls_key(&camera_id, &key_id, &recording_manager).await
+}
+
 /// Parse tolerance string like "30s", "5m", "1h" into seconds
 fn parse_tolerance_string(tolerance: &str) -> Result<i64, String> {
     if tolerance.is_empty() {
@@ -593,7 +1982,7 @@ pub async fn api_get_frame_by_timestamp(
     camera_config: config::CameraConfig,
     recording_manager: Arc<RecordingManager>,
 ) -> axum::response::Response {
-    if let Err(response) = check_api_auth(&headers, &camera_config) {
+    if let Some(response) = check_recording_auth(&headers, &camera_config) {
         return response;
     }
 
@@ -617,16 +2006,37 @@ pub async fn api_get_frame_by_timestamp(
         None // Default: no tolerance (exact match)
     };
 
+    if query.quality.is_some_and(|q| !(1..=100).contains(&q)) {
+        return (axum::http::StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<()>::error("Quality must be between 1 and 100", 400)))
+               .into_response();
+    }
+    let format = crate::jpeg::FrameFormat::negotiate(query.format, &headers);
+
     // Get the frame
     match recording_manager.get_frame_at_timestamp(&camera_id, timestamp, tolerance_seconds).await {
         Ok(Some(frame)) => {
-            // Return raw JPEG data
+            // Stored frames are JPEG, other formats and qualities are encoded on the fly
+            let frame_data = if format == crate::jpeg::FrameFormat::Jpeg && query.quality.is_none() {
+                frame.frame_data
+            } else {
+                match crate::jpeg::transcode(frame.frame_data.into(), format, query.quality).await {
+                    Ok(data) => data,
+                    Err(e) => {
+                        tracing::warn!("[{}] Failed to encode frame as {}: {}", camera_id, format.name(), e);
+                        return (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                                Json(ApiResponse::<()>::error(&format!("Failed to encode frame as {}: {}", format.name(), e), 500)))
+                               .into_response();
+                    }
+                }
+            };
             axum::response::Response::builder()
                 .status(200)
-                .header("Content-Type", "image/jpeg")
-                .header("Content-Length", frame.frame_data.len())
+                .header("Content-Type", format.content_type())
+                .header("Content-Length", frame_data.len())
+                .header("Vary", "Accept")
                 .header("X-Frame-Timestamp", frame.timestamp.to_rfc3339())
-                .body(axum::body::Body::from(frame.frame_data))
+                .body(axum::body::Body::from(frame_data))
                 .unwrap_or_else(|_| {
                     Json(ApiResponse::<()>::error("Failed to build response", 500)).into_response()
                 })
@@ -665,7 +2075,7 @@ pub async fn api_set_session_keep_flag(
     camera_config: config::CameraConfig,
     recording_manager: Arc<RecordingManager>,
 ) -> axum::response::Response {
-    if let Err(response) = check_api_auth(&headers, &camera_config) {
+    if let Err(response) = check_api_auth(&headers, &camera_config, config::TokenScope::Control) {
         return response;
     }
 
@@ -689,14 +2099,20 @@ pub async fn api_set_session_keep_flag(
             });
             Json(ApiResponse::success(data)).into_response()
         }
-        Err(e) => {
-            (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-             Json(ApiResponse::<()>::error(&format!("Database error: {}", e), 500)))
-             .into_response()
-        }
+        Err(e) => session_write_error("Database error", e),
     }
 }
 
+/// 409 if the session is locked, 500 for other errors
+fn session_write_error(prefix: &str, error: crate::errors::StreamError) -> axum::response::Response {
+    let status = match error {
+        crate::errors::StreamError::Locked { .. } => axum::http::StatusCode::CONFLICT,
+        _ => axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, Json(ApiResponse::<()>::error(&format!("{}: {}", prefix, error), status.as_u16())))
+        .into_response()
+}
+
 // DELETE /cam1/control/recordings/sessions/:session_id
 pub async fn api_delete_recording_session(
     headers: axum::http::HeaderMap,
@@ -706,7 +2122,7 @@ pub async fn api_delete_recording_session(
     recording_manager: Arc<RecordingManager>,
 ) -> impl IntoResponse {
     // Check authentication
-    if let Err(response) = check_api_auth(&headers, &camera_config) {
+    if let Err(response) = check_api_auth(&headers, &camera_config, config::TokenScope::Control) {
         return response;
     }
 
@@ -715,6 +2131,7 @@ pub async fn api_delete_recording_session(
     if let Some(database) = databases.get(&camera_id) {
         match database.delete_recording_session(session_id).await {
             Ok(stats) => {
+                crate::segment_cache::invalidate_camera(&camera_id);
                 let data = serde_json::json!({
                     "success": true,
                     "deleted": {
@@ -726,11 +2143,7 @@ pub async fn api_delete_recording_session(
                 });
                 Json(ApiResponse::success(data)).into_response()
             }
-            Err(e) => {
-                (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                 Json(ApiResponse::<()>::error(&format!("Delete error: {}", e), 500)))
-                    .into_response()
-            }
+            Err(e) => session_write_error("Delete error", e),
         }
     } else {
         (axum::http::StatusCode::NOT_FOUND,
@@ -748,7 +2161,7 @@ pub async fn api_delete_mp4_segment(
     recording_manager: Arc<RecordingManager>,
 ) -> impl IntoResponse {
     // Check authentication
-    if let Err(response) = check_api_auth(&headers, &camera_config) {
+    if let Err(response) = check_api_auth(&headers, &camera_config, config::TokenScope::Control) {
         return response;
     }
 
@@ -757,6 +2170,7 @@ pub async fn api_delete_mp4_segment(
     if let Some(database) = databases.get(&camera_id) {
         match database.delete_mp4_segment_by_filename(&camera_id, &filename).await {
             Ok(size_bytes) => {
+                crate::segment_cache::invalidate_camera(&camera_id);
                 let data = serde_json::json!({
                     "success": true,
                     "deleted": {
@@ -766,11 +2180,7 @@ pub async fn api_delete_mp4_segment(
                 });
                 Json(ApiResponse::success(data)).into_response()
             }
-            Err(e) => {
-                (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                 Json(ApiResponse::<()>::error(&format!("Delete error: {}", e), 500)))
-                    .into_response()
-            }
+            Err(e) => session_write_error("Delete error", e),
         }
     } else {
         (axum::http::StatusCode::NOT_FOUND,
@@ -793,7 +2203,7 @@ pub async fn api_delete_mp4_segments_bulk(
     Json(request): Json<BulkDeleteMp4Request>,
 ) -> impl IntoResponse {
     // Check authentication
-    if let Err(response) = check_api_auth(&headers, &camera_config) {
+    if let Err(response) = check_api_auth(&headers, &camera_config, config::TokenScope::Control) {
         return response;
     }
 
@@ -802,6 +2212,7 @@ pub async fn api_delete_mp4_segments_bulk(
     if let Some(database) = databases.get(&camera_id) {
         match database.delete_mp4_segments_bulk(&camera_id, request.filenames).await {
             Ok(result) => {
+                crate::segment_cache::invalidate_camera(&camera_id);
                 let data = serde_json::json!({
                     "success": true,
                     "deleted_count": result.deleted_count,
@@ -810,11 +2221,7 @@ pub async fn api_delete_mp4_segments_bulk(
                 });
                 Json(ApiResponse::success(data)).into_response()
             }
-            Err(e) => {
-                (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                 Json(ApiResponse::<()>::error(&format!("Delete error: {}", e), 500)))
-                    .into_response()
-            }
+            Err(e) => session_write_error("Delete error", e),
         }
     } else {
         (axum::http::StatusCode::NOT_FOUND,
@@ -832,7 +2239,7 @@ pub async fn api_delete_hls_segments_by_session(
     recording_manager: Arc<RecordingManager>,
 ) -> impl IntoResponse {
     // Check authentication
-    if let Err(response) = check_api_auth(&headers, &camera_config) {
+    if let Err(response) = check_api_auth(&headers, &camera_config, config::TokenScope::Control) {
         return response;
     }
 
@@ -841,6 +2248,7 @@ pub async fn api_delete_hls_segments_by_session(
     if let Some(database) = databases.get(&camera_id) {
         match database.delete_hls_segments_by_session(session_id).await {
             Ok(deleted_count) => {
+                crate::segment_cache::invalidate_camera(&camera_id);
                 let data = serde_json::json!({
                     "success": true,
                     "deleted_segments": deleted_count,
@@ -848,11 +2256,7 @@ pub async fn api_delete_hls_segments_by_session(
                 });
                 Json(ApiResponse::success(data)).into_response()
             }
-            Err(e) => {
-                (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                 Json(ApiResponse::<()>::error(&format!("Delete error: {}", e), 500)))
-                    .into_response()
-            }
+            Err(e) => session_write_error("Delete error", e),
         }
     } else {
         (axum::http::StatusCode::NOT_FOUND,
@@ -876,7 +2280,7 @@ pub async fn api_delete_hls_segments_by_timerange(
     recording_manager: Arc<RecordingManager>,
 ) -> impl IntoResponse {
     // Check authentication
-    if let Err(response) = check_api_auth(&headers, &camera_config) {
+    if let Err(response) = check_api_auth(&headers, &camera_config, config::TokenScope::Control) {
         return response;
     }
 
@@ -885,6 +2289,7 @@ pub async fn api_delete_hls_segments_by_timerange(
     if let Some(database) = databases.get(&camera_id) {
         match database.delete_hls_segments_by_timerange(&camera_id, query.from, query.to).await {
             Ok(deleted_count) => {
+                crate::segment_cache::invalidate_camera(&camera_id);
                 let data = serde_json::json!({
                     "success": true,
                     "deleted_segments": deleted_count,
@@ -893,11 +2298,7 @@ pub async fn api_delete_hls_segments_by_timerange(
                 });
                 Json(ApiResponse::success(data)).into_response()
             }
-            Err(e) => {
-                (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                 Json(ApiResponse::<()>::error(&format!("Delete error: {}", e), 500)))
-                    .into_response()
-            }
+            Err(e) => session_write_error("Delete error", e),
         }
     } else {
         (axum::http::StatusCode::NOT_FOUND,
@@ -0,0 +1,119 @@
+use axum::{Json, response::IntoResponse};
+use tracing::{info, warn};
+
+use crate::{api_recording::ApiResponse, api_config::check_admin_token, AppState};
+
+fn unauthorized() -> axum::response::Response {
+    (axum::http::StatusCode::UNAUTHORIZED,
+     Json(ApiResponse::<()>::error("Unauthorized", 401)))
+    .into_response()
+}
+
+fn not_enabled() -> axum::response::Response {
+    (axum::http::StatusCode::SERVICE_UNAVAILABLE,
+     Json(ApiResponse::<()>::error("Reports are not enabled", 503)))
+    .into_response()
+}
+
+// GET /api/admin/reports
+pub async fn api_list_reports(
+    headers: axum::http::HeaderMap,
+    state: AppState,
+) -> axum::response::Response {
+    if !check_admin_token(&headers, &state.admin_token) {
+        return unauthorized();
+    }
+    let Some(ref report_manager) = state.report_manager else {
+        return not_enabled();
+    };
+
+    let mut files = Vec::new();
+    match tokio::fs::read_dir(report_manager.directory()).await {
+        Ok(mut entries) => {
+            while let Ok(Some(entry)) = entries.next_entry().await {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if !(name.ends_with(".json") || name.ends_with(".html")) || name == "uptime.json" {
+                    continue;
+                }
+                let metadata = entry.metadata().await.ok();
+                files.push(serde_json::json!({
+                    "name": name,
+                    "size_bytes": metadata.as_ref().map(|m| m.len()),
+                    "modified": metadata.and_then(|m| m.modified().ok()).map(chrono::DateTime::<chrono::Utc>::from),
+                }));
+            }
+        }
+        Err(e) => warn!("[API] Failed to read reports directory: {}", e),
+    }
+    files.sort_by(|a, b| b["name"].as_str().cmp(&a["name"].as_str()));
+
+    Json(ApiResponse::success(serde_json::json!({
+        "schedules": report_manager.schedules(),
+        "files": files,
+    }))).into_response()
+}
+
+// GET /api/admin/reports/files/:name
+pub async fn api_get_report_file(
+    headers: axum::http::HeaderMap,
+    axum::extract::Path(name): axum::extract::Path<String>,
+    state: AppState,
+) -> axum::response::Response {
+    if !check_admin_token(&headers, &state.admin_token) {
+        return unauthorized();
+    }
+    let Some(ref report_manager) = state.report_manager else {
+        return not_enabled();
+    };
+
+    let content_type = if name.ends_with(".html") { "text/html; charset=utf-8" } else { "application/json" };
+    if name.contains('/') || name.contains('\\') || name.starts_with('.') {
+        return (axum::http::StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<()>::error("Invalid report file name", 400)))
+               .into_response();
+    }
+    match tokio::fs::read(report_manager.directory().join(&name)).await {
+        Ok(content) => axum::response::Response::builder()
+            .status(200)
+            .header("Content-Type", content_type)
+            .header("Cache-Control", "private, no-cache")
+            .body(axum::body::Body::from(content))
+            .unwrap_or_else(|_| {
+                Json(ApiResponse::<()>::error("Failed to build response", 500)).into_response()
+            }),
+        Err(_) => (axum::http::StatusCode::NOT_FOUND,
+                   Json(ApiResponse::<()>::error(&format!("Report file '{}' not found", name), 404)))
+                  .into_response(),
+    }
+}
+
+// POST /api/admin/reports/:id/run
+pub async fn api_run_report(
+    headers: axum::http::HeaderMap,
+    axum::extract::Path(id): axum::extract::Path<String>,
+    state: AppState,
+) -> axum::response::Response {
+    if !check_admin_token(&headers, &state.admin_token) {
+        return unauthorized();
+    }
+    let Some(ref report_manager) = state.report_manager else {
+        return not_enabled();
+    };
+    let Some(schedule) = report_manager.schedules().iter().find(|s| s.id == id) else {
+        return (axum::http::StatusCode::NOT_FOUND,
+                Json(ApiResponse::<()>::error(&format!("Report schedule '{}' not found", id), 404)))
+               .into_response();
+    };
+
+    info!("[API] Report '{}' requested", id);
+    match report_manager.run(&state, schedule).await {
+        Ok(report) => {
+            state.audit_log.record(&headers, "report.run", Some(&id),
+                serde_json::Value::Null, serde_json::json!({ "from": report["from"], "to": report["to"] })).await;
+            Json(ApiResponse::success(report)).into_response()
+        }
+        Err(e) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                   Json(ApiResponse::<()>::error(&format!("Failed to create report: {}", e), 500)))
+                  .into_response(),
+    }
+}
@@ -0,0 +1,140 @@
+use axum::{Json, response::IntoResponse, extract::Path as AxumPath};
+use serde::Deserialize;
+use tracing::{error, info};
+
+use crate::{api_recording::ApiResponse, api_config::{admin_access, AdminAccess}, secrets, AppState};
+
+#[derive(Debug, Deserialize)]
+pub struct SetSecretRequest {
+    pub value: String,
+}
+
+fn unauthorized() -> axum::response::Response {
+    (axum::http::StatusCode::UNAUTHORIZED,
+     Json(ApiResponse::<()>::error("Unauthorized", 401)))
+    .into_response()
+}
+
+/// IDs of the cameras whose configuration references the secret
+async fn referencing_cameras(state: &AppState, name: &str) -> Vec<String> {
+    let mut camera_ids: Vec<String> = state.camera_configs.read().await.iter()
+        .filter(|(_, config)| secrets::camera_references(config).iter().any(|r| r == name))
+        .map(|(camera_id, _)| camera_id.clone())
+        .collect();
+    camera_ids.sort();
+    camera_ids
+}
+
+// GET /api/admin/secrets
+pub async fn api_list_secrets(
+    headers: axum::http::HeaderMap,
+    state: AppState,
+) -> axum::response::Response {
+    if admin_access(&headers, &state) != Some(AdminAccess::Global) {
+        return unauthorized();
+    }
+
+    // Stored secrets plus the ones only referenced, so missing secrets show up
+    let mut references: std::collections::BTreeMap<String, Vec<String>> =
+        secrets::names().into_iter().map(|name| (name, Vec::new())).collect();
+    for (camera_id, config) in state.camera_configs.read().await.iter() {
+        for name in secrets::camera_references(config) {
+            references.entry(name).or_default().push(camera_id.clone());
+        }
+    }
+
+    let secrets: Vec<_> = references.into_iter().map(|(name, mut cameras)| {
+        cameras.sort();
+        serde_json::json!({
+            "name": name,
+            "source": secrets::source(&name),
+            "cameras": cameras,
+        })
+    }).collect();
+
+    Json(ApiResponse::success(serde_json::json!({
+        "file": state.server_config.secrets.file(),
+        "secrets": secrets,
+    }))).into_response()
+}
+
+// PUT /api/admin/secrets/:name
+pub async fn api_set_secret(
+    headers: axum::http::HeaderMap,
+    AxumPath(name): AxumPath<String>,
+    Json(request): Json<SetSecretRequest>,
+    state: AppState,
+) -> axum::response::Response {
+    if admin_access(&headers, &state) != Some(AdminAccess::Global) {
+        return unauthorized();
+    }
+    if !secrets::is_valid_name(&name) {
+        return (axum::http::StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<()>::error("Secret names may only contain letters, digits, '_', '-' and '.'", 400)))
+               .into_response();
+    }
+
+    let existed = secrets::source(&name) == secrets::SecretSource::File;
+    if let Err(e) = secrets::set(&name, &request.value) {
+        error!("Failed to store secret '{}': {}", name, e);
+        return (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::<()>::error(&format!("Failed to store secret: {}", e), 500)))
+               .into_response();
+    }
+    info!("Secret '{}' {} via API", name, if existed { "updated" } else { "created" });
+    state.audit_log.record(&headers, "secret.set", Some(&name),
+        serde_json::json!({ "stored": existed }), serde_json::json!({ "stored": true })).await;
+
+    // Running cameras only pick up the new value with a fresh pipeline
+    let cameras = referencing_cameras(&state, &name).await;
+    let mut restarted = Vec::new();
+    for camera_id in &cameras {
+        if !state.camera_streams.read().await.contains_key(camera_id) {
+            continue;
+        }
+        let Some(camera_config) = state.camera_configs.read().await.get(camera_id).cloned() else { continue };
+        match state.restart_camera(camera_id.clone(), camera_config).await {
+            Ok(()) => restarted.push(camera_id.clone()),
+            Err(e) => error!("Failed to restart camera '{}' after secret '{}' changed: {}", camera_id, name, e),
+        }
+    }
+
+    Json(ApiResponse::success(serde_json::json!({
+        "name": name,
+        "cameras": cameras,
+        "restarted_cameras": restarted,
+    }))).into_response()
+}
+
+// DELETE /api/admin/secrets/:name
+pub async fn api_delete_secret(
+    headers: axum::http::HeaderMap,
+    AxumPath(name): AxumPath<String>,
+    state: AppState,
+) -> axum::response::Response {
+    if admin_access(&headers, &state) != Some(AdminAccess::Global) {
+        return unauthorized();
+    }
+
+    match secrets::remove(&name) {
+        Ok(true) => {
+            info!("Secret '{}' deleted via API", name);
+            state.audit_log.record(&headers, "secret.delete", Some(&name),
+                serde_json::json!({ "stored": true }), serde_json::json!({ "stored": false })).await;
+            // Running cameras keep the old value until they restart
+            Json(ApiResponse::success(serde_json::json!({
+                "name": name,
+                "cameras": referencing_cameras(&state, &name).await,
+            }))).into_response()
+        }
+        Ok(false) => (axum::http::StatusCode::NOT_FOUND,
+                      Json(ApiResponse::<()>::error("Secret not found", 404)))
+                     .into_response(),
+        Err(e) => {
+            error!("Failed to delete secret '{}': {}", name, e);
+            (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+             Json(ApiResponse::<()>::error(&format!("Failed to delete secret: {}", e), 500)))
+            .into_response()
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use std::collections::HashMap;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::{extract::Query, response::IntoResponse, Json};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::{api_config::{admin_access, AdminAccess}, api_recording::{check_api_auth, ApiResponse}, config, AppState};
+
+// Throughput stats are stored per second, longer ranges would return millions of rows
+const MAX_RANGE_DAYS: i64 = 7;
+
+const DEFAULT_SOCKET_INTERVAL_SECS: u64 = 2;
+const MAX_SOCKET_INTERVAL_SECS: u64 = 60;
+// Disk usage needs database queries and is shared by all stats sockets
+const DISK_USAGE_REFRESH_SECS: u64 = 60;
+
+lazy_static::lazy_static! {
+    static ref DISK_USAGE: tokio::sync::Mutex<Option<(std::time::Instant, HashMap<String, i64>)>> =
+        tokio::sync::Mutex::new(None);
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GetThroughputStatsQuery {
+    pub from: Option<DateTime<Utc>>, // Default: one hour before `to`
+    pub to: Option<DateTime<Utc>>,   // Default: now
+}
+
+// GET /<camera_path>/control/stats/throughput
+pub async fn api_get_throughput_stats(
+    headers: axum::http::HeaderMap,
+    Query(query): Query<GetThroughputStatsQuery>,
+    camera_id: String,
+    state: AppState,
+) -> axum::response::Response {
+    let Some(camera_config) = state.camera_configs.read().await.get(&camera_id).cloned() else {
+        return (axum::http::StatusCode::NOT_FOUND,
+                Json(ApiResponse::<()>::error("Camera not found", 404)))
+               .into_response();
+    };
+    if let Err(response) = check_api_auth(&headers, &camera_config, config::TokenScope::Playback) {
+        return response;
+    }
+
+    let to = query.to.unwrap_or_else(Utc::now);
+    let from = query.from.unwrap_or(to - chrono::Duration::hours(1));
+    if from > to {
+        return (axum::http::StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<()>::error("from must be before to", 400)))
+               .into_response();
+    }
+    if to - from > chrono::Duration::days(MAX_RANGE_DAYS) {
+        return (axum::http::StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<()>::error(&format!("Time range must not exceed {} days", MAX_RANGE_DAYS), 400)))
+               .into_response();
+    }
+
+    // The stats backend if configured, else the camera's recording database
+    let backend = crate::throughput_tracker::get_global_tracker()
+        .and_then(|tracker| tracker.stats_backend().map(|backend| backend.store.clone()));
+    let (source, result) = if let Some(store) = backend {
+        ("stats_backend", store.get_throughput_stats(&camera_id, from, to).await)
+    } else {
+        let database = match state.recording_manager {
+            Some(ref recording_manager) => recording_manager.get_camera_database(&camera_id).await,
+            None => None,
+        };
+        let Some(database) = database else {
+            return (axum::http::StatusCode::NOT_FOUND,
+                    Json(ApiResponse::<()>::error("No stats backend or recording database for this camera", 404)))
+                   .into_response();
+        };
+        ("recording_database", database.get_throughput_stats(&camera_id, from, to).await)
+    };
+
+    match result {
+        Ok(stats) => Json(ApiResponse::success(serde_json::json!({
+            "camera_id": camera_id,
+            "source": source,
+            "from": from,
+            "to": to,
+            "count": stats.len(),
+            "stats": stats,
+        }))).into_response(),
+        Err(e) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                   Json(ApiResponse::<()>::error(&format!("Failed to get throughput stats: {}", e), 500)))
+                  .into_response(),
+    }
+}
+
+// GET /api/metrics
+pub async fn api_get_metrics(
+    headers: axum::http::HeaderMap,
+    state: AppState,
+) -> axum::response::Response {
+    let Some(access) = admin_access(&headers, &state) else {
+        return (axum::http::StatusCode::UNAUTHORIZED,
+                Json(ApiResponse::<()>::error("Unauthorized", 401)))
+               .into_response();
+    };
+    let camera_ids: Vec<String> = state.camera_configs.read().await
+        .iter()
+        .filter(|(_, camera_config)| access.allows(camera_config))
+        .map(|(id, _)| id.clone())
+        .collect();
+
+    (
+        [(axum::http::header::CONTENT_TYPE, "text/plain; version=0.0.4; charset=utf-8")],
+        crate::pipeline_metrics::render(&camera_ids),
+    ).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StatsSocketQuery {
+    pub token: Option<String>,  // Admin token, browsers cannot set headers on WebSocket requests
+    pub interval: Option<u64>,  // Seconds between messages, default 2
+}
+
+// GET /api/stats/ws
+pub async fn api_stats_socket(
+    ws: WebSocketUpgrade,
+    mut headers: axum::http::HeaderMap,
+    Query(query): Query<StatsSocketQuery>,
+    state: AppState,
+) -> axum::response::Response {
+    if let Some(token) = query.token.as_deref().filter(|_| !headers.contains_key(axum::http::header::AUTHORIZATION)) {
+        if let Ok(value) = axum::http::HeaderValue::from_str(&format!("Bearer {}", token)) {
+            headers.insert(axum::http::header::AUTHORIZATION, value);
+        }
+    }
+    let Some(access) = admin_access(&headers, &state) else {
+        return (axum::http::StatusCode::UNAUTHORIZED,
+                Json(ApiResponse::<()>::error("Unauthorized", 401)))
+               .into_response();
+    };
+    let interval = query.interval.unwrap_or(DEFAULT_SOCKET_INTERVAL_SECS);
+    if !(1..=MAX_SOCKET_INTERVAL_SECS).contains(&interval) {
+        return (axum::http::StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<()>::error(&format!("interval must be between 1 and {} seconds", MAX_SOCKET_INTERVAL_SECS), 400)))
+               .into_response();
+    }
+
+    ws.on_upgrade(move |socket| stats_socket(socket, access, interval, state))
+}
+
+async fn stats_socket(mut socket: WebSocket, access: AdminAccess, interval: u64, state: AppState) {
+    debug!("[API] Stats WebSocket connected (every {}s)", interval);
+    let mut ticker = tokio::time::interval(std::time::Duration::from_secs(interval));
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {
+                let message = server_stats(&access, &state).await.to_string();
+                if socket.send(Message::Text(message)).await.is_err() {
+                    break;
+                }
+            }
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                _ => {}
+            }
+        }
+    }
+    debug!("[API] Stats WebSocket disconnected");
+}
+
+/// Server-wide live stats of the cameras the caller may see
+async fn server_stats(access: &AdminAccess, state: &AppState) -> serde_json::Value {
+    let mut camera_configs: Vec<(String, config::CameraConfig)> = state.camera_configs.read().await
+        .iter()
+        .filter(|(_, camera_config)| access.allows(camera_config))
+        .map(|(id, camera_config)| (id.clone(), camera_config.clone()))
+        .collect();
+    camera_configs.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut streams = HashMap::new();
+    {
+        let camera_streams = state.camera_streams.read().await;
+        for (camera_id, info) in camera_streams.iter() {
+            let capture_fps = *info.capture_fps.read().await;
+            let pre_recording_buffer = match info.pre_recording_buffer {
+                Some(ref buffer) => Some(buffer.get_stats().await),
+                None => None,
+            };
+            let (mp4_frames, mp4_size_kb) = {
+                let mp4_stats = info.mp4_buffer_stats.read().await;
+                (mp4_stats.frame_count, mp4_stats.size_kb())
+            };
+            streams.insert(camera_id.clone(), serde_json::json!({
+                "capture_fps": capture_fps,
+                "clients_connected": info.frame_sender.receiver_count(),
+                "standby": info.pipeline_control.is_standby(),
+                "pre_recording_buffer_frames": pre_recording_buffer.as_ref().map_or(0, |stats| stats.frame_count),
+                "pre_recording_buffer_size_kb": pre_recording_buffer.as_ref().map_or(0, |stats| (stats.total_size_bytes as f64 / 1024.0).round() as u64),
+                "mp4_buffered_frames": mp4_frames,
+                "mp4_buffered_size_kb": mp4_size_kb
+            }));
+        }
+    }
+
+    let mut viewers: HashMap<String, usize> = HashMap::new();
+    for viewer in crate::viewers::list(None) {
+        *viewers.entry(viewer.camera_id).or_default() += 1;
+    }
+    let disk_usage = disk_usage(state).await;
+
+    let mut total_clients = 0;
+    let mut total_viewers = 0;
+    let mut total_disk_bytes = 0;
+    let mut active_cameras = 0;
+    let cameras: Vec<serde_json::Value> = camera_configs.iter().map(|(camera_id, camera_config)| {
+        let stream = streams.remove(camera_id);
+        let camera_viewers = viewers.get(camera_id).copied().unwrap_or(0);
+        let disk_bytes = disk_usage.get(camera_id).copied();
+        total_viewers += camera_viewers;
+        total_disk_bytes += disk_bytes.unwrap_or(0);
+        if let Some(ref stream) = stream {
+            active_cameras += 1;
+            total_clients += stream["clients_connected"].as_u64().unwrap_or(0);
+        }
+        let ffmpeg_usage = stream.as_ref().and_then(|_| crate::resources::get_ffmpeg_usage(camera_id));
+        serde_json::json!({
+            "id": camera_id,
+            "enabled": camera_config.enabled.unwrap_or(true),
+            "active": stream.is_some(),
+            "stream": stream,
+            "viewers": camera_viewers,
+            "ffmpeg_cpu_percent": ffmpeg_usage.as_ref().map(|usage| usage.cpu_percent),
+            "ffmpeg_rss_kb": ffmpeg_usage.as_ref().map(|usage| usage.rss_kb),
+            "tamper": crate::tamper::get_state(camera_id),
+            "disk_bytes": disk_bytes
+        })
+    }).collect();
+
+    serde_json::json!({
+        "timestamp": Utc::now(),
+        "uptime_secs": state.start_time.elapsed().as_secs(),
+        "total_cameras": cameras.len(),
+        "active_cameras": active_cameras,
+        "total_clients": total_clients,
+        "total_viewers": total_viewers,
+        "total_disk_bytes": total_disk_bytes,
+        "cameras": cameras
+    })
+}
+
+/// Per-camera disk usage as counted for tenant quotas, refreshed at most every DISK_USAGE_REFRESH_SECS
+async fn disk_usage(state: &AppState) -> HashMap<String, i64> {
+    let mut cached = DISK_USAGE.lock().await;
+    if let Some((updated, ref usage)) = *cached {
+        if updated.elapsed().as_secs() < DISK_USAGE_REFRESH_SECS {
+            return usage.clone();
+        }
+    }
+    let (Some(recording_manager), Some(recording_config)) = (&state.recording_manager, &state.recording_config) else {
+        return HashMap::new();
+    };
+
+    let camera_ids = recording_manager.get_all_camera_ids().await;
+    let mut usage = HashMap::new();
+    for camera_id in camera_ids {
+        if let Some(bytes) = crate::tenants::camera_storage_bytes(recording_manager, &recording_config.database_type, &camera_id).await {
+            usage.insert(camera_id, bytes);
+        }
+    }
+    *cached = Some((std::time::Instant::now(), usage.clone()));
+    usage
+}
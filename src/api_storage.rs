@@ -0,0 +1,148 @@
+use axum::{Json, response::IntoResponse};
+use tracing::warn;
+
+use crate::{config, api_recording::ApiResponse, api_config::check_admin_token, database::StorageStats, AppState};
+
+/// Effective retention settings of a camera (camera override or global recording config)
+fn retention_settings(camera_config: Option<&config::CameraConfig>, recording_config: &config::RecordingConfig) -> serde_json::Value {
+    let frame_storage_enabled = camera_config.and_then(|c| c.get_frame_storage_enabled())
+        .unwrap_or(recording_config.frame_storage_enabled);
+    let frame_storage_retention = camera_config.and_then(|c| c.get_frame_storage_retention())
+        .unwrap_or(&recording_config.frame_storage_retention);
+    let mp4_storage_type = camera_config.and_then(|c| c.get_mp4_storage_type())
+        .unwrap_or(&recording_config.mp4_storage_type);
+    let mp4_storage_retention = camera_config.and_then(|c| c.get_mp4_storage_retention())
+        .unwrap_or(&recording_config.mp4_storage_retention);
+    let hls_storage_enabled = camera_config.and_then(|c| c.get_hls_storage_enabled())
+        .unwrap_or(recording_config.hls_storage_enabled);
+    let hls_storage_retention = camera_config.and_then(|c| c.get_hls_storage_retention())
+        .unwrap_or(&recording_config.hls_storage_retention);
+
+    serde_json::json!({
+        "frame_storage_enabled": frame_storage_enabled,
+        "frame_storage_retention": frame_storage_retention,
+        "mp4_storage_type": mp4_storage_type,
+        "mp4_storage_retention": mp4_storage_retention,
+        "hls_storage_enabled": hls_storage_enabled,
+        "hls_storage_retention": hls_storage_retention
+    })
+}
+
+// GET /api/storage
+pub async fn api_get_storage(
+    headers: axum::http::HeaderMap,
+    state: AppState,
+) -> axum::response::Response {
+    if !check_admin_token(&headers, &state.admin_token) {
+        return (axum::http::StatusCode::UNAUTHORIZED,
+                Json(ApiResponse::<()>::error("Unauthorized", 401)))
+               .into_response();
+    }
+
+    let (Some(recording_manager), Some(recording_config)) = (&state.recording_manager, &state.recording_config) else {
+        return (axum::http::StatusCode::SERVICE_UNAVAILABLE,
+                Json(ApiResponse::<()>::error("Recording is not enabled", 503)))
+               .into_response();
+    };
+
+    // Snapshot the database handles so no lock is held during the queries
+    let mut databases: Vec<_> = recording_manager.databases.read().await
+        .iter()
+        .map(|(camera_id, database)| (camera_id.clone(), database.clone()))
+        .collect();
+    databases.sort_by(|a, b| a.0.cmp(&b.0));
+    let camera_configs = state.camera_configs.read().await.clone();
+
+    let mut cameras = Vec::new();
+    let mut totals = StorageStats::default();
+    for (camera_id, database) in databases {
+        let database_size_bytes = match database.get_database_size().await {
+            Ok(size) => Some(size),
+            Err(e) => {
+                warn!("[API] Failed to get database size for camera '{}': {}", camera_id, e);
+                None
+            }
+        };
+        let stats = match database.get_storage_stats(&camera_id).await {
+            Ok(stats) => stats,
+            Err(e) => {
+                warn!("[API] Failed to get storage stats for camera '{}': {}", camera_id, e);
+                cameras.push(serde_json::json!({
+                    "camera_id": camera_id,
+                    "database_size_bytes": database_size_bytes,
+                    "error": e.to_string()
+                }));
+                continue;
+            }
+        };
+
+        totals.frame_count += stats.frame_count;
+        totals.mp4_file_segments += stats.mp4_file_segments;
+        totals.mp4_file_bytes += stats.mp4_file_bytes;
+        totals.mp4_database_segments += stats.mp4_database_segments;
+        totals.mp4_database_bytes += stats.mp4_database_bytes;
+        totals.hls_segments += stats.hls_segments;
+        totals.hls_bytes += stats.hls_bytes;
+        totals.oldest_recording = totals.oldest_recording.into_iter().chain(stats.oldest_recording).min();
+        totals.newest_recording = totals.newest_recording.into_iter().chain(stats.newest_recording).max();
+
+        cameras.push(serde_json::json!({
+            "camera_id": camera_id,
+            "database_size_bytes": database_size_bytes,
+            "frames": stats.frame_count,
+            "mp4": {
+                "file_segments": stats.mp4_file_segments,
+                "file_bytes": stats.mp4_file_bytes,
+                "database_segments": stats.mp4_database_segments,
+                "database_bytes": stats.mp4_database_bytes
+            },
+            "hls": {
+                "segments": stats.hls_segments,
+                "bytes": stats.hls_bytes
+            },
+            "oldest_recording": stats.oldest_recording,
+            "newest_recording": stats.newest_recording,
+            "retention": retention_settings(camera_configs.get(&camera_id), recording_config)
+        }));
+    }
+
+    Json(ApiResponse::success(serde_json::json!({
+        "database_type": recording_config.database_type,
+        "media_database_type": recording_config.media_database.as_ref().map(|media| &media.database_type),
+        "mp4_storage_path": recording_config.get_mp4_storage_path(),
+        "cameras": cameras,
+        "totals": totals
+    }))).into_response()
+}
+
+// GET /api/admin/recording/state
+pub async fn api_get_recording_state(
+    headers: axum::http::HeaderMap,
+    state: AppState,
+) -> axum::response::Response {
+    if !check_admin_token(&headers, &state.admin_token) {
+        return (axum::http::StatusCode::UNAUTHORIZED,
+                Json(ApiResponse::<()>::error("Unauthorized", 401)))
+               .into_response();
+    }
+
+    let Some(recording_manager) = &state.recording_manager else {
+        return (axum::http::StatusCode::SERVICE_UNAVAILABLE,
+                Json(ApiResponse::<()>::error("Recording is not enabled", 503)))
+               .into_response();
+    };
+
+    let mut camera_ids = recording_manager.get_all_camera_ids().await;
+    camera_ids.sort();
+
+    let mut cameras = Vec::new();
+    for camera_id in &camera_ids {
+        cameras.push(recording_manager.get_recording_state(camera_id).await);
+    }
+    let active_recordings = cameras.iter().filter(|camera| camera["recording"] == true).count();
+
+    Json(ApiResponse::success(serde_json::json!({
+        "active_recordings": active_recordings,
+        "cameras": cameras
+    }))).into_response()
+}
@@ -0,0 +1,113 @@
+use axum::{Json, response::IntoResponse};
+use tracing::{debug, error};
+
+use crate::{api_recording::{self, ApiResponse}, api_config::admin_access, config, mp4::HlsTimeRangeQuery, AppState};
+
+// Most cameras reviewed side by side in one request
+const MAX_SYNC_CAMERAS: usize = 16;
+
+#[derive(Debug, serde::Deserialize)]
+pub struct SyncPlaybackRequest {
+    pub cameras: Vec<String>, // Camera ids in display order
+    #[serde(flatten)]
+    pub range: HlsTimeRangeQuery, // t1, t2, segment_duration, variant shared by all playlists
+    pub expires_in_minutes: Option<u64>, // Default: hls_signed_url_minutes
+}
+
+/// Admins of the camera (global or its tenant) and tokens with playback scope may review it;
+/// recordings of watermarked cameras only admins
+fn check_playback_access(headers: &axum::http::HeaderMap, state: &AppState, camera_config: &config::CameraConfig) -> Option<axum::response::Response> {
+    if admin_access(headers, state).is_some_and(|access| access.allows(camera_config)) {
+        return None;
+    }
+    api_recording::check_recording_auth(headers, camera_config)
+}
+
+// POST /api/recordings/sync
+pub async fn api_sync_playback(
+    headers: axum::http::HeaderMap,
+    Json(request): Json<SyncPlaybackRequest>,
+    state: AppState,
+) -> axum::response::Response {
+    let Some(ref recording_manager) = state.recording_manager else {
+        return (axum::http::StatusCode::SERVICE_UNAVAILABLE,
+                Json(ApiResponse::<()>::error("Recording is not configured", 503)))
+               .into_response();
+    };
+    let mut camera_ids: Vec<String> = Vec::new();
+    for camera_id in request.cameras {
+        if !camera_ids.contains(&camera_id) {
+            camera_ids.push(camera_id);
+        }
+    }
+    if camera_ids.is_empty() || camera_ids.len() > MAX_SYNC_CAMERAS {
+        return (axum::http::StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<()>::error(&format!("cameras must list between 1 and {} cameras", MAX_SYNC_CAMERAS), 400)))
+               .into_response();
+    }
+    if !request.range.is_valid_range() {
+        return (axum::http::StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<()>::error("t1 must be before t2", 400)))
+               .into_response();
+    }
+    if request.range.session_id().is_some() {
+        return (axum::http::StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<()>::error("session_id belongs to a single camera and cannot be used for synchronized playback", 400)))
+               .into_response();
+    }
+    let recording_config = recording_manager.get_recording_config();
+    let Some(expires_at) = api_recording::hls_signed_url_expiry(recording_config, request.expires_in_minutes) else {
+        return (axum::http::StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<()>::error(&format!("expires_in_minutes must be between 1 and {}", api_recording::MAX_HLS_SIGNED_URL_MINUTES), 400)))
+               .into_response();
+    };
+
+    // All cameras are authorized before anything is revealed about one of them
+    let mut cameras = Vec::new();
+    {
+        let camera_configs = state.camera_configs.read().await;
+        for camera_id in camera_ids {
+            let Some(camera_config) = camera_configs.get(&camera_id) else {
+                return (axum::http::StatusCode::NOT_FOUND,
+                        Json(ApiResponse::<()>::error(&format!("Camera '{}' not found", camera_id), 404)))
+                       .into_response();
+            };
+            if let Some(response) = check_playback_access(&headers, &state, camera_config) {
+                return response;
+            }
+            cameras.push((camera_id, camera_config.clone()));
+        }
+    }
+
+    let range = request.range.canonical_query();
+    let mut result = Vec::new();
+    for (camera_id, camera_config) in cameras {
+        let alignment = match crate::mp4::playback_alignment(&camera_id, &request.range, &camera_config, recording_manager).await {
+            Ok(alignment) => alignment,
+            Err(message) => {
+                error!("Failed to align playback of camera '{}': {}", camera_id, message);
+                return (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(ApiResponse::<()>::error(message, 500)))
+                       .into_response();
+            }
+        };
+        // Cameras without recordings in the range have nothing to play
+        let recorded = !alignment.timeline.is_empty();
+        let signed_url = |endpoint: &str| {
+            recorded.then(|| api_recording::signed_hls_url(recording_config, &camera_id, &camera_config, endpoint, &range, expires_at))
+        };
+        result.push(serde_json::json!({
+            "camera_id": camera_id,
+            "path": camera_config.path,
+            "url": signed_url("timerange"),
+            "events_url": signed_url("events.vtt"),
+            "alignment": alignment,
+        }));
+    }
+    debug!("Aligned synchronized playback of {} cameras", result.len());
+
+    Json(ApiResponse::success(serde_json::json!({
+        "cameras": result,
+        "expires_at": expires_at,
+    }))).into_response()
+}
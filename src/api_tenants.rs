@@ -0,0 +1,105 @@
+use axum::{Json, response::IntoResponse, extract::Path as AxumPath};
+
+use crate::{api_recording::ApiResponse, api_config::{admin_access, AdminAccess}, tenants, AppState};
+
+/// Authorize a request for a tenant: the global admin or the tenant's own admin token.
+/// Other tenants are reported as not found.
+fn check_tenant_access(headers: &axum::http::HeaderMap, state: &AppState, tenant_id: &str) -> Option<axum::response::Response> {
+    let Some(access) = admin_access(headers, state) else {
+        return Some((axum::http::StatusCode::UNAUTHORIZED,
+                     Json(ApiResponse::<()>::error("Unauthorized", 401)))
+                    .into_response());
+    };
+    let visible = match access {
+        AdminAccess::Global => true,
+        AdminAccess::Tenant(ref tenant) => tenant == tenant_id,
+    };
+    if !visible || state.server_config.get_tenant(tenant_id).is_none() {
+        return Some((axum::http::StatusCode::NOT_FOUND,
+                     Json(ApiResponse::<()>::error("Tenant not found", 404)))
+                    .into_response());
+    }
+    None
+}
+
+// GET /api/admin/tenants
+pub async fn api_list_tenants(
+    headers: axum::http::HeaderMap,
+    state: AppState,
+) -> axum::response::Response {
+    if admin_access(&headers, &state) != Some(AdminAccess::Global) {
+        return (axum::http::StatusCode::UNAUTHORIZED,
+                Json(ApiResponse::<()>::error("Unauthorized", 401)))
+               .into_response();
+    }
+
+    let mut tenants = Vec::new();
+    for tenant in &state.server_config.tenants {
+        let usage = tenants::storage_usage(&state, tenant).await;
+        tenants.push(serde_json::json!({
+            "id": tenant.id,
+            "name": tenant.name,
+            "path_prefix": tenant.path_prefix(),
+            "cameras": usage.cameras.iter().map(|c| &c.camera_id).collect::<Vec<_>>(),
+            "storage_bytes": usage.total_bytes,
+            "storage_quota_bytes": usage.quota_bytes,
+            "quota_exceeded": usage.quota_exceeded,
+        }));
+    }
+
+    Json(ApiResponse::success(serde_json::json!({ "tenants": tenants }))).into_response()
+}
+
+// GET /api/tenants/:tenant/cameras
+pub async fn api_get_tenant_cameras(
+    headers: axum::http::HeaderMap,
+    AxumPath(tenant_id): AxumPath<String>,
+    state: AppState,
+) -> axum::response::Response {
+    if let Some(response) = check_tenant_access(&headers, &state, &tenant_id) {
+        return response;
+    }
+
+    let camera_ids = tenants::tenant_camera_ids(&state, &tenant_id).await;
+    let camera_configs = state.camera_configs.read().await.clone();
+    let running: std::collections::HashSet<String> = state.camera_streams.read().await.keys().cloned().collect();
+
+    let mut cameras = Vec::new();
+    for camera_id in camera_ids {
+        let Some(camera_config) = camera_configs.get(&camera_id) else { continue };
+        let recording = match state.recording_manager {
+            Some(ref recording_manager) => recording_manager.is_recording(&camera_id).await,
+            None => false,
+        };
+        cameras.push(serde_json::json!({
+            "camera_id": camera_id,
+            "path": camera_config.path,
+            "enabled": camera_config.enabled.unwrap_or(true),
+            "running": running.contains(&camera_id),
+            "recording": recording,
+        }));
+    }
+
+    Json(ApiResponse::success(serde_json::json!({
+        "tenant_id": tenant_id,
+        "cameras": cameras,
+    }))).into_response()
+}
+
+// GET /api/tenants/:tenant/storage
+pub async fn api_get_tenant_storage(
+    headers: axum::http::HeaderMap,
+    AxumPath(tenant_id): AxumPath<String>,
+    state: AppState,
+) -> axum::response::Response {
+    if let Some(response) = check_tenant_access(&headers, &state, &tenant_id) {
+        return response;
+    }
+    let Some(tenant) = state.server_config.get_tenant(&tenant_id) else {
+        return (axum::http::StatusCode::NOT_FOUND,
+                Json(ApiResponse::<()>::error("Tenant not found", 404)))
+               .into_response();
+    };
+
+    Json(ApiResponse::success(tenants::storage_usage(&state, tenant).await)).into_response()
+}
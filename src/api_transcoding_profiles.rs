@@ -0,0 +1,165 @@
+use axum::{Json, response::IntoResponse, extract::Path as AxumPath};
+use tracing::{error, info};
+
+use crate::{api_recording::ApiResponse, api_config::{admin_access, AdminAccess}, config::FfmpegConfig, transcoding_profiles, AppState, Args};
+
+fn unauthorized() -> axum::response::Response {
+    (axum::http::StatusCode::UNAUTHORIZED,
+     Json(ApiResponse::<()>::error("Unauthorized", 401)))
+    .into_response()
+}
+
+/// IDs of the cameras that reference the profile
+async fn profile_cameras(state: &AppState, name: &str) -> Vec<String> {
+    let mut camera_ids: Vec<String> = state.camera_configs.read().await.iter()
+        .filter(|(_, config)| config.transcoding_profile.as_deref() == Some(name))
+        .map(|(camera_id, _)| camera_id.clone())
+        .collect();
+    camera_ids.sort();
+    camera_ids
+}
+
+// Edit the file as JSON so that unrelated settings keep their original form (e.g. ${VAR} placeholders)
+fn write_profile(config_path: &str, name: &str, profile: Option<&FfmpegConfig>) -> Result<(), String> {
+    let mut config_value = std::fs::read_to_string(config_path)
+        .map_err(|e| e.to_string())
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).map_err(|e| e.to_string()))?;
+    let config_object = config_value.as_object_mut().ok_or("Config file is not a JSON object")?;
+    let profiles = config_object.entry("transcoding_profiles")
+        .or_insert_with(|| serde_json::json!({}))
+        .as_object_mut()
+        .ok_or("transcoding_profiles is not a JSON object")?;
+    match profile {
+        Some(profile) => {
+            // Only the settings the profile actually sets
+            let mut value = serde_json::to_value(profile).map_err(|e| e.to_string())?;
+            if let Some(fields) = value.as_object_mut() {
+                fields.retain(|_, v| !v.is_null());
+            }
+            profiles.insert(name.to_string(), value);
+        }
+        None => { profiles.remove(name); }
+    }
+    if profiles.is_empty() {
+        config_object.remove("transcoding_profiles");
+    }
+    let content = serde_json::to_string_pretty(&config_value).map_err(|e| e.to_string())?;
+    std::fs::write(config_path, content).map_err(|e| e.to_string())
+}
+
+// GET /api/admin/transcoding/profiles
+pub async fn api_list_profiles(
+    headers: axum::http::HeaderMap,
+    state: AppState,
+) -> axum::response::Response {
+    if admin_access(&headers, &state) != Some(AdminAccess::Global) {
+        return unauthorized();
+    }
+
+    let mut profiles: Vec<_> = transcoding_profiles::all().into_iter().collect();
+    profiles.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut result = Vec::with_capacity(profiles.len());
+    for (name, ffmpeg) in profiles {
+        result.push(serde_json::json!({
+            "name": name,
+            "ffmpeg": ffmpeg,
+            "cameras": profile_cameras(&state, &name).await,
+        }));
+    }
+
+    Json(ApiResponse::success(serde_json::json!({ "profiles": result }))).into_response()
+}
+
+// PUT /api/admin/transcoding/profiles/:name
+pub async fn api_set_profile(
+    headers: axum::http::HeaderMap,
+    AxumPath(name): AxumPath<String>,
+    Json(profile): Json<FfmpegConfig>,
+    args: Args,
+    state: AppState,
+) -> axum::response::Response {
+    if admin_access(&headers, &state) != Some(AdminAccess::Global) {
+        return unauthorized();
+    }
+    if !transcoding_profiles::is_valid_name(&name) {
+        return (axum::http::StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<()>::error("Profile names may only contain letters, digits, '_', '-' and '.'", 400)))
+               .into_response();
+    }
+    if profile.quality.is_some_and(|q| !(1..=100).contains(&q)) {
+        return (axum::http::StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<()>::error("Quality must be between 1 and 100", 400)))
+               .into_response();
+    }
+
+    if let Err(e) = write_profile(&args.config, &name, Some(&profile)) {
+        error!("Failed to save transcoding profile '{}': {}", name, e);
+        return (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::<()>::error(&format!("Failed to save config file: {}", e), 500)))
+               .into_response();
+    }
+    let previous = transcoding_profiles::set(&name, profile.clone());
+    info!("Transcoding profile '{}' {} via API", name, if previous.is_some() { "updated" } else { "created" });
+    state.audit_log.record(&headers, "transcoding_profile.set", Some(&name),
+        serde_json::to_value(&previous).unwrap_or_default(),
+        serde_json::to_value(&profile).unwrap_or_default()).await;
+
+    // Running cameras only pick up the new settings with a fresh pipeline
+    let cameras = profile_cameras(&state, &name).await;
+    let mut restarted = Vec::new();
+    for camera_id in &cameras {
+        if !state.camera_streams.read().await.contains_key(camera_id) {
+            continue;
+        }
+        let Some(camera_config) = state.camera_configs.read().await.get(camera_id).cloned() else { continue };
+        match state.restart_camera(camera_id.clone(), camera_config).await {
+            Ok(()) => restarted.push(camera_id.clone()),
+            Err(e) => error!("Failed to restart camera '{}' after transcoding profile '{}' changed: {}", camera_id, name, e),
+        }
+    }
+
+    Json(ApiResponse::success(serde_json::json!({
+        "name": name,
+        "ffmpeg": profile,
+        "cameras": cameras,
+        "restarted_cameras": restarted,
+    }))).into_response()
+}
+
+// DELETE /api/admin/transcoding/profiles/:name
+pub async fn api_delete_profile(
+    headers: axum::http::HeaderMap,
+    AxumPath(name): AxumPath<String>,
+    args: Args,
+    state: AppState,
+) -> axum::response::Response {
+    if admin_access(&headers, &state) != Some(AdminAccess::Global) {
+        return unauthorized();
+    }
+    if transcoding_profiles::get(&name).is_none() {
+        return (axum::http::StatusCode::NOT_FOUND,
+                Json(ApiResponse::<()>::error("Transcoding profile not found", 404)))
+               .into_response();
+    }
+
+    // Cameras referencing a missing profile would fail to start
+    let cameras = profile_cameras(&state, &name).await;
+    if !cameras.is_empty() {
+        return (axum::http::StatusCode::CONFLICT,
+                Json(ApiResponse::<()>::error(&format!("Transcoding profile is used by cameras: {}", cameras.join(", ")), 409)))
+               .into_response();
+    }
+
+    if let Err(e) = write_profile(&args.config, &name, None) {
+        error!("Failed to delete transcoding profile '{}': {}", name, e);
+        return (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::<()>::error(&format!("Failed to save config file: {}", e), 500)))
+               .into_response();
+    }
+    let previous = transcoding_profiles::remove(&name);
+    info!("Transcoding profile '{}' deleted via API", name);
+    state.audit_log.record(&headers, "transcoding_profile.delete", Some(&name),
+        serde_json::to_value(&previous).unwrap_or_default(), serde_json::Value::Null).await;
+
+    Json(ApiResponse::success(serde_json::json!({ "name": name }))).into_response()
+}
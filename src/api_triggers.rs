@@ -0,0 +1,135 @@
+use axum::{Json, response::IntoResponse, extract::Path as AxumPath};
+use tracing::info;
+
+use crate::{config, api_recording::{ApiResponse, check_api_auth}, api_config::check_camera_admin, AppState};
+use crate::errors::StreamError;
+use crate::triggers::TriggerOutcome;
+
+/// Inbound webhook for triggers with an `http` source
+pub async fn api_fire_trigger(
+    headers: axum::http::HeaderMap,
+    AxumPath((camera_id, trigger_id)): AxumPath<(String, String)>,
+    state: AppState,
+) -> axum::response::Response {
+    let Some(camera_config) = state.camera_configs.read().await.get(&camera_id).cloned() else {
+        return (axum::http::StatusCode::NOT_FOUND,
+                Json(ApiResponse::<()>::error("Camera not found", 404)))
+               .into_response();
+    };
+
+    if let Err(response) = check_api_auth(&headers, &camera_config, config::TokenScope::Control) {
+        return response;
+    }
+
+    match camera_config.triggers.iter().find(|t| t.id == trigger_id) {
+        Some(trigger) if matches!(trigger.source, config::TriggerSource::Http) => {}
+        Some(_) => {
+            return (axum::http::StatusCode::BAD_REQUEST,
+                    Json(ApiResponse::<()>::error("Trigger is not an HTTP trigger", 400)))
+                   .into_response();
+        }
+        None => {
+            return (axum::http::StatusCode::NOT_FOUND,
+                    Json(ApiResponse::<()>::error("Trigger not found", 404)))
+                   .into_response();
+        }
+    }
+
+    match state.trigger_manager.fire(&state, &camera_id, &trigger_id).await {
+        Ok(outcome) => {
+            if !matches!(outcome, TriggerOutcome::Debounced { .. }) {
+                info!("[API] Trigger '{}' of camera '{}' fired via webhook", trigger_id, camera_id);
+            }
+            Json(ApiResponse::success(outcome)).into_response()
+        }
+        Err(StreamError::NotFound { message }) => {
+            (axum::http::StatusCode::NOT_FOUND,
+             Json(ApiResponse::<()>::error(&message, 404)))
+            .into_response()
+        }
+        Err(StreamError::Config { message }) => {
+            (axum::http::StatusCode::BAD_REQUEST,
+             Json(ApiResponse::<()>::error(&message, 400)))
+            .into_response()
+        }
+        Err(e) => {
+            (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+             Json(ApiResponse::<()>::error(&format!("Trigger failed: {}", e), 500)))
+            .into_response()
+        }
+    }
+}
+
+pub async fn api_get_triggers(
+    headers: axum::http::HeaderMap,
+    AxumPath(camera_id): AxumPath<String>,
+    state: AppState,
+) -> axum::response::Response {
+    if let Some(response) = check_camera_admin(&headers, &state, &camera_id).await {
+        return response;
+    }
+
+    let Some(camera_config) = state.camera_configs.read().await.get(&camera_id).cloned() else {
+        return (axum::http::StatusCode::NOT_FOUND,
+                Json(ApiResponse::<()>::error("Camera not found", 404)))
+               .into_response();
+    };
+
+    let last_fired = state.trigger_manager.last_fired_secs(&camera_id).await;
+    let triggers: Vec<serde_json::Value> = camera_config.triggers.iter().map(|t| {
+        serde_json::json!({
+            "trigger": t,
+            "last_fired_secs_ago": last_fired.get(&t.id),
+        })
+    }).collect();
+
+    Json(ApiResponse::success(serde_json::json!({
+        "camera_id": camera_id,
+        "triggers": triggers,
+    }))).into_response()
+}
+
+/// Replace the trigger list of a camera. The camera config file is rewritten and
+/// the file watcher restarts the camera with the new triggers.
+pub async fn api_update_triggers(
+    headers: axum::http::HeaderMap,
+    AxumPath(camera_id): AxumPath<String>,
+    Json(triggers): Json<Vec<config::TriggerConfig>>,
+    state: AppState,
+) -> axum::response::Response {
+    if let Some(response) = check_camera_admin(&headers, &state, &camera_id).await {
+        return response;
+    }
+
+    let Some(mut camera_config) = state.camera_configs.read().await.get(&camera_id).cloned() else {
+        return (axum::http::StatusCode::NOT_FOUND,
+                Json(ApiResponse::<()>::error("Camera not found", 404)))
+               .into_response();
+    };
+
+    let mut ids = std::collections::HashSet::new();
+    if let Some(duplicate) = triggers.iter().find(|t| !ids.insert(t.id.as_str())) {
+        return (axum::http::StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<()>::error(&format!("Duplicate trigger id '{}'", duplicate.id), 400)))
+               .into_response();
+    }
+
+    let previous_triggers = serde_json::to_value(&camera_config.triggers).unwrap_or_default();
+    camera_config.triggers = triggers;
+    if let Err(e) = config::Config::save_camera_config(&camera_id, &camera_config, Some(&state.cameras_directory)) {
+        return (axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::<()>::error(&format!("Failed to save camera config: {}", e), 500)))
+               .into_response();
+    }
+
+    info!("Triggers for camera '{}' updated ({} configured)", camera_id, camera_config.triggers.len());
+    state.audit_log.record(&headers, "camera.triggers", Some(&camera_id),
+        serde_json::json!({ "triggers": previous_triggers }),
+        serde_json::json!({ "triggers": camera_config.triggers })).await;
+
+    Json(ApiResponse::success(serde_json::json!({
+        "message": "Triggers updated successfully",
+        "camera_id": camera_id,
+        "triggers": camera_config.triggers,
+    }))).into_response()
+}
@@ -0,0 +1,63 @@
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+
+use crate::api_recording::ApiResponse;
+use crate::AppState;
+
+/// Build and runtime information for fleet monitoring
+pub async fn api_get_version(state: AppState) -> Response {
+    Json(ApiResponse::success(version_info(&state).await)).into_response()
+}
+
+pub async fn version_info(state: &AppState) -> serde_json::Value {
+    let version = crate::VERSION.trim();
+    let git_commit = version.split_once('+').map(|(_, commit)| commit);
+    let camera_configs = state.camera_configs.read().await;
+    let ptz_cameras = camera_configs.values()
+        .filter(|cfg| cfg.ptz.as_ref().is_some_and(|ptz| ptz.enabled))
+        .count();
+    let total_cameras = camera_configs.len();
+    drop(camera_configs);
+    let recording = state.recording_config.as_deref();
+    let runtime = &state.server_config.runtime;
+
+    serde_json::json!({
+        "version": version,
+        "crate_version": env!("CARGO_PKG_VERSION"),
+        "git_commit": git_commit,
+        "build": {
+            "profile": if cfg!(debug_assertions) { "debug" } else { "release" },
+            "os": std::env::consts::OS,
+            "arch": std::env::consts::ARCH,
+        },
+        "ffmpeg_version": state.ffmpeg_version,
+        "features": {
+            "recording": state.recording_manager.is_some(),
+            "mqtt": state.mqtt_handle.is_some(),
+            "ptz": ptz_cameras > 0,
+            "tls": state.server_config.tls.as_ref().is_some_and(|tls| tls.enabled),
+            "ldap": state.server_config.ldap.is_some(),
+            "tenants": !state.server_config.tenants.is_empty(),
+            "frame_storage": recording.is_some_and(|r| r.frame_storage_enabled),
+            "hls_storage": recording.is_some_and(|r| r.hls_storage_enabled),
+            "mp4_storage": recording.map(|r| &r.mp4_storage_type),
+            "mp4_cold_storage": recording.is_some_and(|r| r.mp4_cold_storage.is_some()),
+        },
+        "database": recording.map(|r| &r.database_type),
+        "cameras": {
+            "total": total_cameras,
+            "ptz": ptz_cameras,
+        },
+        "limits": {
+            "cpu_count": std::thread::available_parallelism().map(|n| n.get()).ok(),
+            "worker_threads": runtime.worker_threads(),
+            "max_blocking_threads": runtime.max_blocking_threads(),
+            "frame_channel_capacity": state.transcoding_config.channel_buffer_size.unwrap_or_else(|| runtime.frame_channel_capacity()),
+            "mqtt_channel_capacity": runtime.mqtt_channel_capacity(),
+            "mp4_export_max_jobs": state.server_config.mp4_export_max_jobs,
+            "max_frame_size": recording.map(|r| r.max_frame_size),
+            "segment_cache_mb": recording.map(|r| r.segment_cache_mb),
+        },
+        "uptime_secs": state.start_time.elapsed().as_secs(),
+    })
+}
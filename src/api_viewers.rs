@@ -0,0 +1,78 @@
+use axum::{Json, response::IntoResponse, extract::{Path as AxumPath, Query}};
+use tracing::info;
+
+use crate::{api_recording::ApiResponse, api_config::{admin_access, AdminAccess}, viewers, AppState};
+
+#[derive(Debug, serde::Deserialize)]
+pub struct ListViewersQuery {
+    pub camera_id: Option<String>,
+}
+
+/// Whether the caller may see the viewers of the camera; tenant admins only see their own cameras
+async fn camera_visible(state: &AppState, access: &AdminAccess, camera_id: &str) -> bool {
+    match access {
+        AdminAccess::Global => true,
+        AdminAccess::Tenant(_) => state.camera_configs.read().await
+            .get(camera_id)
+            .is_some_and(|camera_config| access.allows(camera_config)),
+    }
+}
+
+// GET /api/admin/viewers
+pub async fn api_list_viewers(
+    headers: axum::http::HeaderMap,
+    Query(query): Query<ListViewersQuery>,
+    state: AppState,
+) -> axum::response::Response {
+    let Some(access) = admin_access(&headers, &state) else {
+        return (axum::http::StatusCode::UNAUTHORIZED,
+                Json(ApiResponse::<()>::error("Unauthorized", 401)))
+               .into_response();
+    };
+
+    let mut result = Vec::new();
+    for viewer in viewers::list(query.camera_id.as_deref()) {
+        if camera_visible(&state, &access, &viewer.camera_id).await {
+            result.push(viewer);
+        }
+    }
+    let total_bytes_sent: u64 = result.iter().map(|viewer| viewer.bytes_sent).sum();
+    let total_lagged_frames: u64 = result.iter().map(|viewer| viewer.lagged_frames).sum();
+
+    Json(ApiResponse::success(serde_json::json!({
+        "count": result.len(),
+        "total_bytes_sent": total_bytes_sent,
+        "total_lagged_frames": total_lagged_frames,
+        "viewers": result,
+    }))).into_response()
+}
+
+// DELETE /api/admin/viewers/:connection_id
+pub async fn api_kick_viewer(
+    headers: axum::http::HeaderMap,
+    AxumPath(connection_id): AxumPath<String>,
+    state: AppState,
+) -> axum::response::Response {
+    let Some(access) = admin_access(&headers, &state) else {
+        return (axum::http::StatusCode::UNAUTHORIZED,
+                Json(ApiResponse::<()>::error("Unauthorized", 401)))
+               .into_response();
+    };
+
+    // Connections of other tenants' cameras are reported as not found
+    let visible = match viewers::get(&connection_id) {
+        Some(viewer) => camera_visible(&state, &access, &viewer.camera_id).await,
+        None => false,
+    };
+    let Some(viewer) = visible.then(|| viewers::kick(&connection_id)).flatten() else {
+        return (axum::http::StatusCode::NOT_FOUND,
+                Json(ApiResponse::<()>::error("Viewer connection not found", 404)))
+               .into_response();
+    };
+
+    info!("Viewer {} ({}) of camera {} kicked via API", viewer.connection_id, viewer.remote_addr, viewer.camera_id);
+    state.audit_log.record(&headers, "viewer.kick", Some(&viewer.connection_id),
+        serde_json::to_value(&viewer).unwrap_or_default(), serde_json::Value::Null).await;
+
+    Json(ApiResponse::success(viewer)).into_response()
+}
@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use chrono::{DateTime, Utc};
+use futures_util::StreamExt;
+use tokio::io::AsyncWriteExt;
+use tracing::info;
+
+use crate::config::RecordingConfig;
+use crate::database::SessionAttachment;
+use crate::errors::StreamError;
+
+// Leading bytes needed to detect the content type
+const SNIFF_BYTES: usize = 12;
+const MAX_FILENAME_LEN: usize = 128;
+
+lazy_static::lazy_static! {
+    static ref PENDING_UPLOADS: Mutex<HashMap<String, PendingUpload>> = Mutex::new(HashMap::new());
+}
+
+/// Upload granted by a pre-signed URL
+#[derive(Debug, Clone)]
+pub struct PendingUpload {
+    pub camera_id: String,
+    pub session_id: i64,
+    pub filename: String,
+    pub description: Option<String>,
+    pub captured_at: Option<DateTime<Utc>>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Why an upload was not stored
+#[derive(Debug)]
+pub enum UploadRejection {
+    TooLarge { max_mb: u64 },
+    UnsupportedType { detected: Option<&'static str> },
+    Failed(StreamError),
+}
+
+impl From<std::io::Error> for UploadRejection {
+    fn from(e: std::io::Error) -> Self {
+        UploadRejection::Failed(StreamError::Io { source: e })
+    }
+}
+
+/// Register an upload and return the token of its URL
+pub fn create_upload(upload: PendingUpload) -> crate::errors::Result<String> {
+    let mut bytes = [0u8; 24];
+    aws_lc_rs::rand::fill(&mut bytes)
+        .map_err(|_| StreamError::internal("Failed to generate upload token"))?;
+    let token: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+
+    let mut uploads = PENDING_UPLOADS.lock().unwrap_or_else(|e| e.into_inner());
+    let now = Utc::now();
+    uploads.retain(|_, pending| pending.expires_at > now);
+    uploads.insert(token.clone(), upload);
+    Ok(token)
+}
+
+/// Take the upload of a token. Tokens are single-use: the first request for the right
+/// session consumes it, even if the upload is rejected afterwards.
+pub fn take_upload(token: &str, camera_id: &str, session_id: i64) -> Option<PendingUpload> {
+    let mut uploads = PENDING_UPLOADS.lock().unwrap_or_else(|e| e.into_inner());
+    let pending = uploads.get(token)?;
+    if pending.camera_id != camera_id || pending.session_id != session_id {
+        return None;
+    }
+    uploads.remove(token).filter(|pending| pending.expires_at > Utc::now())
+}
+
+/// Last path component of an uploaded file name without control or reserved characters
+pub fn sanitize_filename(name: &str) -> Option<String> {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let name: String = name.chars()
+        .filter(|c| !c.is_control() && !matches!(c, '"' | '<' | '>' | ':' | '|' | '?' | '*'))
+        .take(MAX_FILENAME_LEN)
+        .collect();
+    let name = name.trim().trim_start_matches('.');
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// Content-Disposition of a download: an ASCII fallback name plus the RFC 5987 encoded UTF-8 name
+pub fn content_disposition(filename: &str) -> String {
+    let fallback: String = filename.chars()
+        .map(|c| if c.is_ascii() && !c.is_ascii_control() && c != '"' && c != '\\' { c } else { '_' })
+        .collect();
+    let encoded: String = filename.bytes()
+        .map(|b| if b.is_ascii_alphanumeric() || b"!#$&+-.^_`|~".contains(&b) {
+            (b as char).to_string()
+        } else {
+            format!("%{:02X}", b)
+        })
+        .collect();
+    format!("attachment; filename=\"{}\"; filename*=UTF-8''{}", fallback, encoded)
+}
+
+/// Content type from the leading bytes of a file
+pub fn detect_content_type(head: &[u8]) -> Option<&'static str> {
+    if head.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if head.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if head.starts_with(&[0x1A, 0x45, 0xDF, 0xA3]) {
+        Some("video/x-matroska")
+    } else if head.len() >= 12 && &head[4..8] == b"ftyp" {
+        Some(if &head[8..12] == b"qt  " { "video/quicktime" } else { "video/mp4" })
+    } else {
+        None
+    }
+}
+
+fn check_content_type(head: &[u8], allowed_types: &[String]) -> Result<&'static str, UploadRejection> {
+    let detected = detect_content_type(head);
+    match detected {
+        Some(content_type) if allowed_types.iter().any(|t| t == content_type) => Ok(content_type),
+        _ => Err(UploadRejection::UnsupportedType { detected }),
+    }
+}
+
+/// Directory of a session's attachments, next to the camera's MP4 segments
+fn attachment_dir(config: &RecordingConfig, camera_id: &str, session_id: i64) -> PathBuf {
+    Path::new(config.get_mp4_storage_path())
+        .join(camera_id)
+        .join("attachments")
+        .join(session_id.to_string())
+}
+
+/// Stream an upload body to disk. The file is written under a temporary name and only
+/// kept once the size and content type checks passed.
+pub async fn store_upload(
+    config: &RecordingConfig,
+    upload: &PendingUpload,
+    body: axum::body::Body,
+) -> Result<SessionAttachment, UploadRejection> {
+    let attachment_id = uuid::Uuid::new_v4().to_string();
+    let dir = attachment_dir(config, &upload.camera_id, upload.session_id);
+    tokio::fs::create_dir_all(&dir).await?;
+
+    let extension = Path::new(&upload.filename).extension()
+        .and_then(|ext| ext.to_str())
+        .filter(|ext| ext.len() <= 8 && ext.chars().all(|c| c.is_ascii_alphanumeric()))
+        .map(|ext| ext.to_ascii_lowercase());
+    let file_path = match extension {
+        Some(ext) => dir.join(format!("{}.{}", attachment_id, ext)),
+        None => dir.join(&attachment_id),
+    };
+    let part_path = dir.join(format!("{}.part", attachment_id));
+
+    let written = write_upload(&part_path, body, config).await;
+    let (size_bytes, sha256, content_type) = match written {
+        Ok(written) => written,
+        Err(rejection) => {
+            let _ = tokio::fs::remove_file(&part_path).await;
+            return Err(rejection);
+        }
+    };
+    tokio::fs::rename(&part_path, &file_path).await?;
+
+    info!("[{}] Stored attachment '{}' of session {} ({}, {} bytes)",
+          upload.camera_id, upload.filename, upload.session_id, content_type, size_bytes);
+    Ok(SessionAttachment {
+        attachment_id,
+        session_id: upload.session_id,
+        camera_id: upload.camera_id.clone(),
+        filename: upload.filename.clone(),
+        content_type: content_type.to_string(),
+        size_bytes,
+        sha256,
+        file_path: file_path.to_string_lossy().to_string(),
+        description: upload.description.clone(),
+        captured_at: upload.captured_at,
+        uploaded_at: Utc::now(),
+    })
+}
+
+async fn write_upload(
+    path: &Path,
+    body: axum::body::Body,
+    config: &RecordingConfig,
+) -> Result<(i64, String, &'static str), UploadRejection> {
+    let max_bytes = config.attachment_max_mb.saturating_mul(1024 * 1024);
+    let mut file = tokio::fs::File::create(path).await?;
+    let mut stream = body.into_data_stream();
+    let mut digest = aws_lc_rs::digest::Context::new(&aws_lc_rs::digest::SHA256);
+    let mut head = Vec::with_capacity(SNIFF_BYTES);
+    let mut content_type = None;
+    let mut size = 0u64;
+
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| UploadRejection::Failed(StreamError::server(format!("Upload aborted: {}", e))))?;
+        size += chunk.len() as u64;
+        if size > max_bytes {
+            return Err(UploadRejection::TooLarge { max_mb: config.attachment_max_mb });
+        }
+        // Reject wrong files after the first bytes instead of receiving them completely
+        if content_type.is_none() {
+            let missing = SNIFF_BYTES - head.len();
+            head.extend_from_slice(&chunk[..missing.min(chunk.len())]);
+            if head.len() == SNIFF_BYTES {
+                content_type = Some(check_content_type(&head, &config.attachment_allowed_types)?);
+            }
+        }
+        digest.update(&chunk);
+        file.write_all(&chunk).await?;
+    }
+    let content_type = match content_type {
+        Some(content_type) => content_type,
+        None => check_content_type(&head, &config.attachment_allowed_types)?,
+    };
+    file.flush().await?;
+
+    let sha256 = digest.finish().as_ref().iter().map(|b| format!("{:02x}", b)).collect();
+    Ok((size as i64, sha256, content_type))
+}
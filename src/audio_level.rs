@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::Mutex;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::sync::broadcast;
+use tokio::time::{interval, Duration};
+use tracing::{debug, info, warn};
+
+use crate::config::{AudioLevelConfig, CameraConfig};
+use crate::errors::{Result, StreamError};
+use crate::AppState;
+
+// Sample rate the audio is resampled to before measuring
+const ANALYSIS_SAMPLE_RATE: u64 = 8000;
+// Digital silence is reported by FFmpeg as -inf dBFS
+const MIN_LEVEL_DB: f64 = -120.0;
+// Wait before restarting the analysis after FFmpeg exited
+const RESTART_DELAY_SECS: u64 = 10;
+// How often a running analysis checks whether the camera went into standby
+const STANDBY_CHECK_SECS: u64 = 5;
+
+lazy_static::lazy_static! {
+    static ref LEVEL_CHANNELS: Mutex<HashMap<String, broadcast::Sender<AudioLevel>>> = Mutex::new(HashMap::new());
+}
+
+/// Loudness of one measurement window
+#[derive(Debug, Clone, Serialize)]
+pub struct AudioLevel {
+    pub timestamp: DateTime<Utc>,
+    /// RMS level in dBFS
+    pub rms_db: f64,
+    /// Peak level in dBFS
+    pub peak_db: f64,
+}
+
+/// Subscribe to the audio levels of a camera (nothing is received while its analysis is not running)
+pub fn subscribe(camera_id: &str) -> broadcast::Receiver<AudioLevel> {
+    level_sender(camera_id).subscribe()
+}
+
+fn level_sender(camera_id: &str) -> broadcast::Sender<AudioLevel> {
+    let mut channels = LEVEL_CHANNELS.lock().unwrap_or_else(|e| e.into_inner());
+    channels.entry(camera_id.to_string())
+        .or_insert_with(|| broadcast::channel(16).0)
+        .clone()
+}
+
+/// Measure the audio level of a camera until the task is aborted, restarting FFmpeg whenever it exits
+pub async fn run_monitor(state: AppState, camera_id: String, camera_config: CameraConfig, config: AudioLevelConfig) {
+    let sender = level_sender(&camera_id);
+    loop {
+        // Keep the camera connection closed while the camera is in standby
+        if !is_standby(&state, &camera_id).await {
+            match measure(&state, &camera_id, &camera_config, &config, &sender).await {
+                Ok(()) => debug!("[{}] Audio level analysis stopped", camera_id),
+                Err(e) => warn!("[{}] Audio level analysis failed: {}", camera_id, e),
+            }
+        }
+        tokio::time::sleep(Duration::from_secs(RESTART_DELAY_SECS)).await;
+    }
+}
+
+async fn measure(
+    state: &AppState,
+    camera_id: &str,
+    camera_config: &CameraConfig,
+    config: &AudioLevelConfig,
+    sender: &broadcast::Sender<AudioLevel>,
+) -> Result<()> {
+    let url = config.url.as_deref().unwrap_or(&camera_config.url);
+    let window_samples = (ANALYSIS_SAMPLE_RATE * config.interval_ms / 1000).max(1);
+
+    let mut args: Vec<String> = vec!["-nostats".into(), "-loglevel".into(), "error".into()];
+    args.extend(crate::config::rtsp_input_args(url, &camera_config.transport, camera_config.tls.as_ref()));
+    args.push("-i".into());
+    args.push(url.to_string());
+    args.push("-vn".into());
+    // One astats measurement per window, printed as key=value lines to stdout
+    args.push("-af".into());
+    args.push(format!(
+        "aresample={},asetnsamples=n={}:p=0,astats=metadata=1:reset=1,ametadata=mode=print:file=-",
+        ANALYSIS_SAMPLE_RATE, window_samples
+    ));
+    args.push("-f".into());
+    args.push("null".into());
+    args.push("-".into());
+
+    let ffmpeg_path = if cfg!(windows) && std::path::Path::new("./ffmpeg.exe").exists() {
+        "./ffmpeg.exe"
+    } else {
+        "ffmpeg"
+    };
+    let mut child = tokio::process::Command::new(ffmpeg_path)
+        .args(&args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    info!("[{}] Started audio level analysis ({} ms windows)", camera_id, config.interval_ms);
+
+    let stdout = child.stdout.take().ok_or_else(|| StreamError::ffmpeg("Failed to get FFmpeg stdout"))?;
+    let stderr = child.stderr.take().ok_or_else(|| StreamError::ffmpeg("Failed to get FFmpeg stderr"))?;
+    let stderr_task = tokio::spawn({
+        let camera_id = camera_id.to_string();
+        async move {
+            let mut last_line = None;
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                debug!("[{}] Audio FFmpeg: {}", camera_id, line);
+                last_line = Some(line);
+            }
+            last_line
+        }
+    });
+
+    let mut lines = BufReader::new(stdout).lines();
+    let mut standby_check = interval(Duration::from_secs(STANDBY_CHECK_SECS));
+    let mut peak_db = None;
+    let mut standby = false;
+    loop {
+        tokio::select! {
+            line = lines.next_line() => {
+                let Some(line) = line? else { break };
+                // "frame:..." starts the values of the next window
+                if line.starts_with("frame:") {
+                    peak_db = None;
+                    continue;
+                }
+                match line.split_once('=') {
+                    Some(("lavfi.astats.Overall.Peak_level", value)) => peak_db = Some(parse_db(value)),
+                    Some(("lavfi.astats.Overall.RMS_level", value)) => {
+                        let rms_db = parse_db(value);
+                        let level = AudioLevel { timestamp: Utc::now(), rms_db, peak_db: peak_db.unwrap_or(rms_db) };
+                        publish(state, camera_id, config, sender, level).await;
+                    }
+                    _ => {}
+                }
+            }
+            _ = standby_check.tick() => {
+                if is_standby(state, camera_id).await {
+                    info!("[{}] Camera entered standby, stopping audio level analysis", camera_id);
+                    standby = true;
+                    break;
+                }
+            }
+        }
+    }
+
+    let _ = child.kill().await;
+    let last_error = stderr_task.await.ok().flatten();
+    match last_error {
+        Some(error) if !standby => Err(StreamError::ffmpeg(error)),
+        _ => Ok(()),
+    }
+}
+
+async fn publish(state: &AppState, camera_id: &str, config: &AudioLevelConfig, sender: &broadcast::Sender<AudioLevel>, level: AudioLevel) {
+    if config.mqtt {
+        if let Some(ref mqtt) = state.mqtt_handle {
+            let payload = serde_json::to_string(&level).unwrap_or_default();
+            if let Err(e) = mqtt.publish_custom(&format!("cameras/{}/audio", camera_id), &payload).await {
+                debug!("[{}] Failed to publish audio level: {}", camera_id, e);
+            }
+        }
+    }
+    // No receivers is fine, nobody is listening
+    let _ = sender.send(level);
+}
+
+fn parse_db(value: &str) -> f64 {
+    value.trim().parse::<f64>()
+        .ok()
+        .filter(|db| !db.is_nan())
+        .map_or(MIN_LEVEL_DB, |db| db.max(MIN_LEVEL_DB))
+}
+
+async fn is_standby(state: &AppState, camera_id: &str) -> bool {
+    state.camera_streams.read().await
+        .get(camera_id)
+        .is_some_and(|info| info.pipeline_control.is_standby())
+}
@@ -0,0 +1,173 @@
+use std::sync::Arc;
+use aws_lc_rs::digest::{digest, SHA256};
+use chrono::Utc;
+use serde_json::Value;
+use tracing::{error, info, warn};
+
+use crate::config::RecordingConfig;
+use crate::database::{AuditEntry, DatabaseProvider};
+
+// Name of the audit database, created next to the per-camera recording databases
+const AUDIT_DATABASE_NAME: &str = "server_audit";
+
+// Config keys whose values are never written to the audit log in clear text
+const SECRET_KEYS: [&str; 5] = ["token", "admin_token", "password", "bind_password", "hls_signing_secret"];
+
+// Config keys of maps whose values are all secrets (relay_server.edges tokens, plugin and redaction env)
+const SECRET_MAP_KEYS: [&str; 2] = ["edges", "env"];
+
+// Config keys holding URLs that may carry credentials
+const URL_KEYS: [&str; 3] = ["url", "database_url", "onvif_url"];
+
+/// Records admin API mutations with the acting token and a before/after diff
+pub struct AuditLog {
+    database: Option<Arc<dyn DatabaseProvider>>,
+}
+
+impl AuditLog {
+    pub fn new(database: Option<Arc<dyn DatabaseProvider>>) -> Self {
+        Self { database }
+    }
+
+    /// Open the audit database using the recording database settings. Without a recording
+    /// configuration audit entries are only written to the server log.
+    pub async fn open(recording_config: Option<&RecordingConfig>) -> Self {
+        let Some(recording_config) = recording_config else {
+            warn!("Recording is not configured, admin audit entries are only written to the log");
+            return Self::new(None);
+        };
+
+        let database = match crate::database::create_database_provider(recording_config, Some(AUDIT_DATABASE_NAME)).await {
+            Ok(database) => database,
+            Err(e) => {
+                error!("Failed to open audit database: {}", e);
+                return Self::new(None);
+            }
+        };
+        if let Err(e) = database.initialize().await {
+            error!("Failed to initialize audit database: {}", e);
+            return Self::new(None);
+        }
+
+        info!("Admin audit log stored in {} database '{}'", recording_config.database_type, AUDIT_DATABASE_NAME);
+        Self::new(Some(database))
+    }
+
+    pub fn database(&self) -> Option<&Arc<dyn DatabaseProvider>> {
+        self.database.as_ref()
+    }
+
+    /// Record a mutation. `before`/`after` are `Value::Null` for created/deleted objects.
+    /// Failing to store the entry is logged but never fails the request.
+    pub async fn record(
+        &self,
+        headers: &axum::http::HeaderMap,
+        action: &str,
+        target: Option<&str>,
+        before: Value,
+        after: Value,
+    ) {
+        let mut changes = Vec::new();
+        diff_values("", &redact(before), &redact(after), &mut changes);
+
+        let entry = AuditEntry {
+            id: 0,
+            timestamp: Utc::now(),
+            actor: actor_fingerprint(headers),
+            action: action.to_string(),
+            target: target.map(str::to_string),
+            changes: Value::Array(changes),
+        };
+
+        info!("[AUDIT] {} on {} by {}: {}", entry.action, entry.target.as_deref().unwrap_or("-"), entry.actor, entry.changes);
+
+        if let Some(ref database) = self.database {
+            if let Err(e) = database.add_audit_entry(&entry).await {
+                error!("Failed to store audit entry for '{}': {}", action, e);
+            }
+        }
+    }
+}
+
+/// Identify the caller by a short SHA-256 fingerprint of its bearer token
+pub fn actor_fingerprint(headers: &axum::http::HeaderMap) -> String {
+    headers.get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .map(|auth| auth.strip_prefix("Bearer ").unwrap_or(auth))
+        .filter(|token| !token.is_empty())
+        .map(fingerprint)
+        .unwrap_or_else(|| "anonymous".to_string())
+}
+
+fn fingerprint(secret: &str) -> String {
+    digest(&SHA256, secret.as_bytes()).as_ref()[..8]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Replace secrets by their fingerprint so changes stay visible without exposing the values
+fn redact(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(map.into_iter().map(|(key, value)| {
+            let value = match value {
+                Value::String(secret) if SECRET_KEYS.contains(&key.as_str()) && !secret.is_empty() => {
+                    Value::String(format!("sha256:{}", fingerprint(&secret)))
+                }
+                Value::String(url) if URL_KEYS.contains(&key.as_str()) => Value::String(redact_url(url)),
+                Value::Object(entries) if SECRET_MAP_KEYS.contains(&key.as_str()) => {
+                    Value::Object(entries.into_iter().map(|(name, secret)| {
+                        let secret = match secret {
+                            Value::String(secret) => Value::String(format!("sha256:{}", fingerprint(&secret))),
+                            other => redact(other),
+                        };
+                        (name, secret)
+                    }).collect())
+                }
+                other => redact(other),
+            };
+            (key, value)
+        }).collect()),
+        Value::Array(items) => Value::Array(items.into_iter().map(redact).collect()),
+        other => other,
+    }
+}
+
+/// URL with its password masked, for logs and diffs
+pub(crate) fn redact_url(raw: String) -> String {
+    match url::Url::parse(&raw) {
+        Ok(mut parsed) if parsed.password().is_some() => {
+            let _ = parsed.set_password(Some("***"));
+            parsed.to_string()
+        }
+        _ => raw,
+    }
+}
+
+/// Collect the changed leaves of two JSON documents as `{path, before, after}`
+fn diff_values(path: &str, before: &Value, after: &Value, changes: &mut Vec<Value>) {
+    let before_map = before.as_object();
+    let after_map = after.as_object();
+    let is_object_or_null = |v: &Value| v.is_object() || v.is_null();
+
+    if (before_map.is_some() || after_map.is_some()) && is_object_or_null(before) && is_object_or_null(after) {
+        let mut keys: Vec<&String> = before_map.into_iter().chain(after_map).flat_map(|m| m.keys()).collect();
+        keys.sort();
+        keys.dedup();
+        for key in keys {
+            let child_path = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+            diff_values(
+                &child_path,
+                before_map.and_then(|m| m.get(key)).unwrap_or(&Value::Null),
+                after_map.and_then(|m| m.get(key)).unwrap_or(&Value::Null),
+                changes,
+            );
+        }
+    } else if before != after {
+        changes.push(serde_json::json!({
+            "path": path,
+            "before": before,
+            "after": after,
+        }));
+    }
+}
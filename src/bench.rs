@@ -0,0 +1,308 @@
+use std::collections::HashMap;
+use futures_util::StreamExt;
+use serde_json::{json, Value};
+use tokio::time::{sleep, timeout, Duration, Instant};
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::errors::{Result, StreamError};
+
+// Upper bound for a benchmark camera to deliver its first frame
+const CAMERA_START_TIMEOUT_SECS: u64 = 30;
+const REQUEST_TIMEOUT_SECS: u64 = 30;
+
+/// Settings of a `bench` run
+pub struct BenchOptions {
+    pub cameras: usize,
+    pub viewers: usize,
+    pub duration: Duration,
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+    pub record: bool,
+    pub url: Option<String>,
+    pub token: Option<String>,
+    pub keep: bool,
+}
+
+#[derive(Default)]
+struct ViewerResult {
+    frames: u64,
+    bytes: u64,
+    latencies_us: Vec<i64>,
+    error: Option<String>,
+}
+
+// Recording counters of the benchmark cameras at one point in time
+#[derive(Default)]
+struct RecordingCounters {
+    frames: i64,
+    bulk_inserts: f64,
+    bulk_insert_seconds: f64,
+}
+
+struct Server {
+    client: reqwest::Client,
+    base_url: String,
+    token: Option<String>,
+}
+
+impl Server {
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let request = self.client.request(method, format!("{}{}", self.base_url, path));
+        match self.token {
+            Some(ref token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    async fn send(&self, request: reqwest::RequestBuilder, action: &str) -> Result<Value> {
+        let response = request.send().await
+            .map_err(|e| StreamError::server(format!("{}: {}", action, e)))?;
+        let status = response.status();
+        let body: Value = response.json().await.unwrap_or(Value::Null);
+        if !status.is_success() {
+            let message = body.get("message").and_then(Value::as_str).unwrap_or_default();
+            return Err(StreamError::server(format!("{}: HTTP {} {}", action, status.as_u16(), message)));
+        }
+        Ok(body.get("data").cloned().unwrap_or(Value::Null))
+    }
+}
+
+/// Start virtual cameras and WebSocket viewers on the running server, measure and remove the cameras again
+pub async fn run(config: &Config, options: BenchOptions) -> Result<()> {
+    if options.cameras == 0 {
+        return Err(StreamError::config("At least one camera is required"));
+    }
+    let base_url = match options.url {
+        Some(ref url) => url.trim_end_matches('/').to_string(),
+        None if config.server.tls.as_ref().is_some_and(|tls| tls.enabled) => {
+            return Err(StreamError::config("The server uses TLS, pass the plain HTTP address of the server with --url"));
+        }
+        None => {
+            let host = match config.server.host.as_str() {
+                "0.0.0.0" | "::" | "" => "127.0.0.1",
+                host => host,
+            };
+            format!("http://{}:{}", host, config.server.port)
+        }
+    };
+    if !base_url.starts_with("http://") {
+        return Err(StreamError::config("The benchmark only supports plain http:// servers"));
+    }
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+        .build()
+        .map_err(|e| StreamError::internal(format!("Failed to create HTTP client: {}", e)))?;
+    let server = Server {
+        client,
+        base_url,
+        token: options.token.clone().or_else(|| config.server.admin_token.clone()),
+    };
+
+    let camera_ids: Vec<String> = (1..=options.cameras).map(|i| format!("bench-{}", i)).collect();
+    let mut created = Vec::new();
+    let mut result = Ok(());
+    for camera_id in &camera_ids {
+        if let Err(e) = create_camera(&server, camera_id, &options).await {
+            result = Err(e);
+            break;
+        }
+        created.push(camera_id.clone());
+    }
+    if result.is_ok() {
+        result = measure(&server, &camera_ids, &options).await;
+    }
+
+    if options.keep {
+        println!("Keeping benchmark cameras: {}", created.join(", "));
+    } else {
+        for camera_id in &created {
+            let request = server.request(reqwest::Method::DELETE, &format!("/api/admin/cameras/{}", camera_id));
+            if let Err(e) = server.send(request, &format!("Failed to delete camera '{}'", camera_id)).await {
+                warn!("{}", e);
+            }
+        }
+    }
+    result
+}
+
+async fn create_camera(server: &Server, camera_id: &str, options: &BenchOptions) -> Result<()> {
+    info!("Creating benchmark camera '{}'", camera_id);
+    let body = json!({
+        "camera_id": camera_id,
+        "config": {
+            "enabled": true,
+            "path": format!("/{}", camera_id),
+            "transport": "tcp",
+            "reconnect_interval": 5,
+            "test_pattern": {
+                "width": options.width,
+                "height": options.height,
+                "fps": options.fps,
+            },
+        },
+    });
+    let request = server.request(reqwest::Method::POST, "/api/admin/cameras").json(&body);
+    server.send(request, &format!("Failed to create camera '{}'", camera_id)).await?;
+    Ok(())
+}
+
+// The camera runs once its snapshot endpoint returns a frame
+async fn wait_for_frames(server: &Server, camera_id: &str) -> Result<()> {
+    let deadline = Instant::now() + Duration::from_secs(CAMERA_START_TIMEOUT_SECS);
+    while Instant::now() < deadline {
+        let response = server.request(reqwest::Method::GET, &format!("/{}/snapshot", camera_id)).send().await;
+        if response.is_ok_and(|r| r.status().is_success()) {
+            return Ok(());
+        }
+        sleep(Duration::from_millis(250)).await;
+    }
+    Err(StreamError::server(format!("Camera '{}' delivered no frame within {} seconds", camera_id, CAMERA_START_TIMEOUT_SECS)))
+}
+
+async fn measure(server: &Server, camera_ids: &[String], options: &BenchOptions) -> Result<()> {
+    for camera_id in camera_ids {
+        wait_for_frames(server, camera_id).await?;
+    }
+
+    let mut before = RecordingCounters::default();
+    if options.record {
+        for camera_id in camera_ids {
+            let request = server.request(reqwest::Method::POST, &format!("/{}/control/recording/start", camera_id))
+                .json(&json!({ "reason": "bench" }));
+            server.send(request, &format!("Failed to start recording of '{}'", camera_id)).await?;
+        }
+        before = recording_counters(server, camera_ids).await?;
+    }
+
+    info!("Running benchmark for {} seconds with {} viewers", options.duration.as_secs(), options.viewers);
+    let started = Instant::now();
+    let viewers: Vec<_> = (0..options.viewers)
+        .map(|index| {
+            let url = format!("{}/{}/live", server.base_url.replacen("http://", "ws://", 1), camera_ids[index % camera_ids.len()]);
+            tokio::spawn(run_viewer(url, started + options.duration))
+        })
+        .collect();
+    let mut results = Vec::new();
+    for viewer in viewers {
+        results.push(viewer.await.unwrap_or_default());
+    }
+    let elapsed = started.elapsed().as_secs_f64().max(0.001);
+
+    let recording = if options.record {
+        let after = recording_counters(server, camera_ids).await?;
+        for camera_id in camera_ids {
+            let request = server.request(reqwest::Method::POST, &format!("/{}/control/recording/stop", camera_id));
+            if let Err(e) = server.send(request, &format!("Failed to stop recording of '{}'", camera_id)).await {
+                warn!("{}", e);
+            }
+        }
+        Some((before, after))
+    } else {
+        None
+    };
+
+    report(options, &results, elapsed, recording);
+    Ok(())
+}
+
+async fn run_viewer(url: String, until: Instant) -> ViewerResult {
+    let mut result = ViewerResult::default();
+    let (mut socket, _) = match tokio_tungstenite::connect_async(url.as_str()).await {
+        Ok(connection) => connection,
+        Err(e) => {
+            result.error = Some(format!("{}: {}", url, e));
+            return result;
+        }
+    };
+    loop {
+        let message = match timeout(until.saturating_duration_since(Instant::now()), socket.next()).await {
+            Ok(Some(Ok(message))) => message,
+            Ok(Some(Err(e))) => {
+                result.error = Some(format!("{}: {}", url, e));
+                break;
+            }
+            Ok(None) | Err(_) => break,
+        };
+        if let tokio_tungstenite::tungstenite::Message::Binary(frame) = message {
+            result.frames += 1;
+            result.bytes += frame.len() as u64;
+            if let Some(generated_at) = crate::test_pattern::timestamp_comment(&frame) {
+                result.latencies_us.push(chrono::Utc::now().timestamp_micros() - generated_at);
+            }
+        }
+    }
+    let _ = socket.close(None).await;
+    result
+}
+
+async fn recording_counters(server: &Server, camera_ids: &[String]) -> Result<RecordingCounters> {
+    let mut counters = RecordingCounters::default();
+
+    let storage = server.send(server.request(reqwest::Method::GET, "/api/storage"), "Failed to read storage statistics").await?;
+    for camera in storage.get("cameras").and_then(Value::as_array).into_iter().flatten() {
+        let camera_id = camera.get("camera_id").and_then(Value::as_str).unwrap_or_default();
+        if camera_ids.iter().any(|id| id == camera_id) {
+            counters.frames += camera.get("frames").and_then(Value::as_i64).unwrap_or(0);
+        }
+    }
+
+    // Prometheus text format, e.g. rtsp_recording_bulk_insert_seconds_count{camera="bench-1"} 12
+    let metrics = server.request(reqwest::Method::GET, "/api/metrics").send().await
+        .map_err(|e| StreamError::server(format!("Failed to read metrics: {}", e)))?
+        .text().await
+        .map_err(|e| StreamError::server(format!("Failed to read metrics: {}", e)))?;
+    let mut values: HashMap<&str, f64> = HashMap::new();
+    for line in metrics.lines().filter(|line| !line.starts_with('#')) {
+        let Some((name, value)) = line.rsplit_once(' ') else { continue };
+        let Ok(value) = value.parse::<f64>() else { continue };
+        if camera_ids.iter().any(|id| name.contains(&format!("camera=\"{}\"", id))) {
+            let metric = name.split('{').next().unwrap_or(name);
+            *values.entry(metric).or_default() += value;
+        }
+    }
+    counters.bulk_inserts = values.get("rtsp_recording_bulk_insert_seconds_count").copied().unwrap_or(0.0);
+    counters.bulk_insert_seconds = values.get("rtsp_recording_bulk_insert_seconds_sum").copied().unwrap_or(0.0);
+    Ok(counters)
+}
+
+fn report(options: &BenchOptions, results: &[ViewerResult], elapsed: f64, recording: Option<(RecordingCounters, RecordingCounters)>) {
+    let frames: u64 = results.iter().map(|r| r.frames).sum();
+    let bytes: u64 = results.iter().map(|r| r.bytes).sum();
+    let expected = (options.fps as f64 * elapsed * options.viewers as f64).max(1.0);
+    let mut latencies: Vec<i64> = results.iter().flat_map(|r| r.latencies_us.iter().copied()).collect();
+    latencies.sort_unstable();
+    let percentile = |p: f64| -> f64 {
+        let index = ((latencies.len() as f64 * p).ceil() as usize).clamp(1, latencies.len()) - 1;
+        latencies[index] as f64 / 1000.0
+    };
+
+    println!();
+    println!("Benchmark: {} cameras ({}x{} @ {} fps), {} viewers, {:.1} s",
+             options.cameras, options.width, options.height, options.fps, options.viewers, elapsed);
+    if options.viewers > 0 {
+        println!("Viewers:        {} frames, {:.1} frames/s total, {:.1} frames/s per viewer ({:.1}% of the generated frames)",
+                 frames, frames as f64 / elapsed, frames as f64 / elapsed / options.viewers as f64, frames as f64 * 100.0 / expected);
+        println!("Throughput:     {:.2} MB/s, {:.1} KB per frame",
+                 bytes as f64 / elapsed / 1_000_000.0, bytes as f64 / frames.max(1) as f64 / 1000.0);
+    }
+    if !latencies.is_empty() {
+        println!("Frame latency:  p50 {:.1} ms, p90 {:.1} ms, p99 {:.1} ms, max {:.1} ms ({} frames)",
+                 percentile(0.5), percentile(0.9), percentile(0.99), percentile(1.0), latencies.len());
+    }
+    if let Some((before, after)) = recording {
+        let inserts = after.bulk_inserts - before.bulk_inserts;
+        let insert_seconds = after.bulk_insert_seconds - before.bulk_insert_seconds;
+        println!("Recording:      {} frames stored ({:.1} frames/s), {} bulk inserts ({:.2}/s, {:.1} ms average)",
+                 after.frames - before.frames, (after.frames - before.frames) as f64 / elapsed,
+                 inserts, inserts / elapsed, if inserts > 0.0 { insert_seconds * 1000.0 / inserts } else { 0.0 });
+    }
+    let errors: Vec<&String> = results.iter().filter_map(|r| r.error.as_ref()).collect();
+    if !errors.is_empty() {
+        println!("Viewer errors:  {}", errors.len());
+        for error in errors.iter().take(5) {
+            println!("  {}", error);
+        }
+    }
+}
@@ -1,14 +1,41 @@
-use std::sync::Arc;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
 use tracing::{info, error, warn};
 
 use crate::config;
-use crate::errors::Result;
+use crate::errors::{Result, StreamError};
 use crate::video_stream::VideoStream;
 // DatabaseProvider import removed - now using database::create_database_provider
 use crate::{AppState, CameraStreamInfo};
 
+lazy_static::lazy_static! {
+    // Cameras whose stream is being created, so concurrent starts never create two pipelines
+    static ref STARTING: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+}
+
+/// Marks a camera as starting until dropped
+struct StartingGuard(String);
+
+impl StartingGuard {
+    fn acquire(camera_id: &str) -> Option<Self> {
+        STARTING.lock().unwrap().insert(camera_id.to_string())
+            .then(|| StartingGuard(camera_id.to_string()))
+    }
+}
+
+impl Drop for StartingGuard {
+    fn drop(&mut self) {
+        STARTING.lock().unwrap().remove(&self.0);
+    }
+}
+
 impl AppState {
-    pub async fn add_camera(&self, camera_id: String, camera_config: config::CameraConfig) -> Result<()> {
+    /// Store a camera's configuration and start its stream if it is enabled and not running yet.
+    /// Returns whether a new stream was started.
+    pub async fn add_camera(&self, camera_id: String, camera_config: config::CameraConfig) -> Result<bool> {
+        self.server_config.validate_camera_tenant(&camera_config)
+            .map_err(|e| StreamError::config(format!("Camera '{}': {}", camera_id, e)))?;
+
         // Check if camera is enabled first (before acquiring any locks)
         let is_enabled = camera_config.enabled.unwrap_or(true);
         
@@ -38,17 +65,26 @@ impl AppState {
                     task_handle.abort();
                 }
             }
-            return Ok(());
+            self.trigger_manager.stop_camera(&camera_id).await;
+            return Ok(false);
         }
         
-        // Check if camera stream already exists (separate lock scope)
-        {
+        // Check for a running stream and mark the camera as starting under the same lock; the
+        // mark is held until the stream is inserted, so a concurrent start sees one or the other
+        let _starting = {
             let camera_streams = self.camera_streams.read().await;
             if camera_streams.contains_key(&camera_id) {
                 info!("Camera '{}' stream already exists, updating config only", camera_id);
-                return Ok(());
+                return Ok(false);
             }
-        }
+            match StartingGuard::acquire(&camera_id) {
+                Some(guard) => guard,
+                None => {
+                    info!("Camera '{}' stream is already starting, updating config only", camera_id);
+                    return Ok(false);
+                }
+            }
+        };
         
         info!("Adding camera '{}' on path '{}'...", camera_id, camera_config.path);
         
@@ -74,7 +110,7 @@ impl AppState {
                     if let Some(recording_config) = &self.recording_config {
                         info!("Creating {} database for camera '{}'", recording_config.database_type, camera_id);
                         
-                        match crate::database::create_database_provider(recording_config, Some(&camera_id)).await {
+                        match crate::database::create_recording_database_provider(recording_config, &camera_id).await {
                             Ok(database) => {
                                 if let Err(e) = recording_manager_ref.add_camera_database(&camera_id, database).await {
                                     error!("Failed to add database for camera '{}': {}", camera_id, e);
@@ -92,6 +128,7 @@ impl AppState {
                 // Extract frame sender, fps counter, and pre-recording buffer before starting (since start() consumes the video_stream)
                 let frame_sender = video_stream.frame_sender.clone();
                 let fps_counter = video_stream.get_fps_counter();
+                let pipeline_control = video_stream.get_pipeline_control();
                 let pre_recording_buffer = video_stream.pre_recording_buffer.clone();
                 
                 // Start the video stream and get the task handle
@@ -123,16 +160,20 @@ impl AppState {
                     mp4_buffer_stats,
                     shutdown_flag,
                     latest_frame,
+                    pipeline_control,
                 };
                 
                 // Add to camera streams
+                crate::standby::register_path(&camera_config.path, &camera_id, camera_stream_info.pipeline_control.clone());
                 {
                     let mut camera_streams = self.camera_streams.write().await;
                     camera_streams.insert(camera_id.clone(), camera_stream_info);
                 }
                 
+                self.trigger_manager.start_camera(self, &camera_id, &camera_config).await;
+
                 info!("Camera '{}' added and started successfully", camera_id);
-                Ok(())
+                Ok(true)
             }
             Err(e) => {
                 error!("Failed to create video stream for camera '{}': {}", camera_id, e);
@@ -143,7 +184,7 @@ impl AppState {
     
     pub async fn remove_camera(&self, camera_id: &str) -> Result<()> {
         info!("Removing camera '{}'...", camera_id);
-        
+
         // Remove from camera configurations
         {
             let mut camera_configs = self.camera_configs.write().await;
@@ -154,7 +195,24 @@ impl AppState {
                 recording_manager.update_camera_configs(camera_configs.clone()).await;
             }
         }
-        
+
+        crate::stream_health::clear_health(camera_id);
+        crate::offline_card::unregister(camera_id);
+        crate::watermark::forget(camera_id);
+
+        if self.stop_camera_stream(camera_id).await {
+            info!("Camera '{}' removed successfully", camera_id);
+        } else {
+            warn!("Camera '{}' was not found in active streams", camera_id);
+        }
+        Ok(())
+    }
+
+    /// Stop the stream task of a camera but keep its configuration.
+    /// Returns false if the camera was not running.
+    pub async fn stop_camera_stream(&self, camera_id: &str) -> bool {
+        self.trigger_manager.stop_camera(camera_id).await;
+
         // Remove from camera streams and get the camera info for cleanup
         let removed = {
             let mut camera_streams = self.camera_streams.write().await;
@@ -162,6 +220,8 @@ impl AppState {
         };
         
         if let Some(camera_info) = removed {
+            crate::standby::unregister_path(camera_id);
+
             // Signal graceful shutdown first
             info!("Signalling graceful shutdown for camera '{}'", camera_id);
             camera_info.shutdown_flag.store(true, std::sync::atomic::Ordering::Relaxed);
@@ -189,14 +249,22 @@ impl AppState {
             // The frame_sender will be dropped which will close all WebSocket connections
             // for this camera automatically when the last reference is dropped
             info!("Frame sender dropped for camera '{}' - WebSocket connections will close", camera_id);
-            
-            info!("Camera '{}' removed successfully", camera_id);
-            Ok(())
+            true
         } else {
-            warn!("Camera '{}' was not found in active streams", camera_id);
-            Ok(())
+            false
         }
     }
+
+    /// Start the stream of a configured camera that is not running.
+    /// Returns false if the camera was already running or being started.
+    pub async fn start_camera_stream(&self, camera_id: &str) -> Result<bool> {
+        let camera_config = self.camera_configs.read().await.get(camera_id).cloned()
+            .ok_or_else(|| StreamError::not_found(format!("Camera '{}' not found", camera_id)))?;
+        if !camera_config.enabled.unwrap_or(true) {
+            return Err(StreamError::config(format!("Camera '{}' is disabled in its configuration", camera_id)));
+        }
+        self.add_camera(camera_id.to_string(), camera_config).await
+    }
     
     pub async fn restart_camera(&self, camera_id: String, camera_config: config::CameraConfig) -> Result<()> {
         info!("Restarting camera '{}'...", camera_id);
@@ -222,7 +290,7 @@ impl AppState {
                     Err(_) => "Camera restart".to_string()
                 };
                 
-                Some((recording.requested_duration, original_reason.to_string()))
+                Some((recording.requested_duration, recording.stop_at, original_reason.to_string()))
             } else {
                 None
             }
@@ -237,7 +305,7 @@ impl AppState {
         self.add_camera(camera_id.clone(), camera_config.clone()).await?;
         
         // Restart recording if it was previously active
-        if let Some((requested_duration, reason)) = was_recording {
+        if let Some((requested_duration, stop_at, reason)) = was_recording {
             info!("Restarting recording for camera '{}' after restart", camera_id);
             if let Some(ref recording_manager_ref) = &self.recording_manager {
                 // Get the frame sender and pre-recording buffer for this camera
@@ -256,6 +324,10 @@ impl AppState {
                     ).await {
                         Ok(session_id) => {
                             info!("Successfully restarted recording for camera '{}' with session ID {}", camera_id, session_id);
+                            // Keep the max duration stop time of the interrupted recording
+                            if stop_at.is_some() {
+                                recording_manager_ref.set_recording_stop_at(&camera_id, stop_at).await;
+                            }
                         }
                         Err(e) => {
                             error!("Failed to restart recording for camera '{}': {}", camera_id, e);
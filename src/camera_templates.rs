@@ -0,0 +1,120 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde_json::Value;
+
+use crate::config::CameraConfig;
+use crate::errors::{Result, StreamError};
+
+/// Subdirectory of the cameras directory holding the templates, one `<name>.json` per template
+pub const TEMPLATES_SUBDIRECTORY: &str = "templates";
+
+// Templates may name a template themselves; deeper chains are treated as a cycle
+const MAX_TEMPLATE_DEPTH: usize = 8;
+
+pub fn templates_dir(cameras_dir: &str) -> PathBuf {
+    Path::new(cameras_dir).join(TEMPLATES_SUBDIRECTORY)
+}
+
+/// Whether the name can be used as a template name
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name.len() <= 64
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+        && !name.starts_with('.')
+}
+
+/// Parse a camera config file with the template it names merged in
+pub fn parse_camera_config(content: &str, cameras_dir: &str) -> Result<CameraConfig> {
+    let camera: Value = serde_json::from_str(content)?;
+    Ok(serde_json::from_value(resolve(camera, cameras_dir)?)?)
+}
+
+/// Camera config with its template (and the template's template) applied. Objects are merged
+/// recursively; any other value set in the camera replaces the template's, `null` keeps it.
+pub fn resolve(camera: Value, cameras_dir: &str) -> Result<Value> {
+    let mut chain = vec![camera];
+    while let Some(name) = template_name(chain.last().unwrap_or(&Value::Null)) {
+        if chain.len() > MAX_TEMPLATE_DEPTH {
+            return Err(StreamError::config(format!("Template chain of '{}' is too deep or circular", name)));
+        }
+        chain.push(load(&name, cameras_dir)?);
+    }
+    let camera = chain.remove(0);
+    let Some(mut resolved) = chain.pop() else {
+        return Ok(camera);
+    };
+    for layer in chain.into_iter().rev().chain(std::iter::once(camera.clone())) {
+        merge(&mut resolved, layer);
+    }
+    // The camera keeps naming its own template, not the last one of the chain
+    if let (Some(resolved), Some(name)) = (resolved.as_object_mut(), camera.get("template")) {
+        resolved.insert("template".to_string(), name.clone());
+    }
+    Ok(resolved)
+}
+
+/// Remove the values a camera inherits unchanged from its template, so saving a resolved
+/// config writes only the camera's overrides and later template changes still apply
+pub fn strip_inherited(camera: &mut Value, cameras_dir: &str) -> Result<()> {
+    let Some(name) = template_name(camera) else {
+        return Ok(());
+    };
+    let template = resolve(serde_json::json!({ "template": name }), cameras_dir)?;
+    strip(camera, &template);
+    Ok(())
+}
+
+fn template_name(config: &Value) -> Option<String> {
+    config.get("template").and_then(Value::as_str).map(str::to_string)
+}
+
+fn load(name: &str, cameras_dir: &str) -> Result<Value> {
+    if !is_valid_name(name) {
+        return Err(StreamError::config(format!("Invalid template name '{}'", name)));
+    }
+    let path = templates_dir(cameras_dir).join(format!("{}.json", name));
+    let content = fs::read_to_string(&path)
+        .map_err(|e| StreamError::config(format!("Failed to read camera template {}: {}", path.display(), e)))?;
+    let template: Value = serde_json::from_str(&content)
+        .map_err(|e| StreamError::config(format!("Failed to parse camera template {}: {}", path.display(), e)))?;
+    if !template.is_object() {
+        return Err(StreamError::config(format!("Camera template {} is not a JSON object", path.display())));
+    }
+    Ok(template)
+}
+
+fn merge(base: &mut Value, overrides: Value) {
+    match (base, overrides) {
+        (Value::Object(base), Value::Object(overrides)) => {
+            for (key, value) in overrides {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None if !value.is_null() => {
+                        base.insert(key, value);
+                    }
+                    None => {}
+                }
+            }
+        }
+        (_, Value::Null) => {}
+        (base, overrides) => *base = overrides,
+    }
+}
+
+fn strip(camera: &mut Value, template: &Value) {
+    let (Value::Object(camera), Value::Object(template)) = (camera, template) else {
+        return;
+    };
+    camera.retain(|key, value| {
+        if key == "template" {
+            return true;
+        }
+        match template.get(key) {
+            Some(inherited) if inherited == value => false,
+            Some(inherited) => {
+                strip(value, inherited);
+                !value.as_object().is_some_and(|o| o.is_empty())
+            }
+            None => true,
+        }
+    });
+}
@@ -0,0 +1,277 @@
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use serde::Serialize;
+use tokio::process::Command;
+
+use crate::api_config::AdminAccess;
+use crate::config::CameraConfig;
+use crate::ptz::onvif_ptz::OnvifPtz;
+use crate::AppState;
+
+// Time the stream probe and the PTZ login may take before they are reported as unreachable
+const PROBE_TIMEOUT_SECS: u64 = 10;
+const PTZ_TIMEOUT_SECS: u64 = 5;
+
+// Paths served by the server itself, cameras cannot use them
+const RESERVED_PATHS: &[&str] = &["/api", "/dashboard", "/debug", "/recordings", "/hls.js", "/dashboard.js", "/dark-theme.css"];
+
+/// Problem found in a camera config. `field` is the JSON path of the offending setting.
+#[derive(Debug, Clone, Serialize)]
+pub struct ValidationIssue {
+    pub field: String,
+    pub code: &'static str,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Ok,
+    Failed,
+    Skipped,
+}
+
+/// Outcome of a check against the camera itself (stream probe, PTZ login)
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckResult {
+    pub status: CheckStatus,
+    pub message: String,
+    pub duration_ms: Option<u64>,
+}
+
+impl CheckResult {
+    fn skipped(message: impl Into<String>) -> Self {
+        Self { status: CheckStatus::Skipped, message: message.into(), duration_ms: None }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ValidationReport {
+    pub valid: bool,
+    pub errors: Vec<ValidationIssue>,
+    pub stream: CheckResult,
+    pub ptz: CheckResult,
+}
+
+impl ValidationReport {
+    /// Report for a config that does not even deserialize
+    pub fn unparsable(message: String) -> Self {
+        Self {
+            valid: false,
+            errors: vec![ValidationIssue { field: "config".to_string(), code: "invalid", message }],
+            stream: CheckResult::skipped("Config is invalid"),
+            ptz: CheckResult::skipped("Config is invalid"),
+        }
+    }
+}
+
+fn issue(errors: &mut Vec<ValidationIssue>, field: &str, code: &'static str, message: impl Into<String>) {
+    errors.push(ValidationIssue { field: field.to_string(), code, message: message.into() });
+}
+
+/// Check a camera config without saving it. `camera_id` is the camera being edited, if any;
+/// `probe` also connects to the stream and the PTZ endpoint.
+pub async fn validate_camera(state: &AppState, access: &AdminAccess, camera_id: Option<&str>, mut camera_config: CameraConfig, probe: bool) -> ValidationReport {
+    let mut errors = Vec::new();
+
+    if camera_config.url.is_empty() && camera_config.device.is_none() && !camera_config.onvif_source_enabled() && camera_config.test_pattern.is_none() {
+        issue(&mut errors, "url", "required", "URL (or device, enabled onvif or test_pattern) is required");
+    }
+    check_path(state, access, camera_id, &camera_config, &mut errors).await;
+
+    let mut secrets_allowed = true;
+    if let AdminAccess::Tenant(tenant) = access {
+        if camera_config.tenant.as_ref().is_some_and(|t| t != tenant) {
+            issue(&mut errors, "tenant", "forbidden", "Cameras cannot be assigned to another tenant");
+        }
+        if !crate::secrets::camera_references(&camera_config).is_empty() {
+            issue(&mut errors, "secrets", "forbidden", "Tenant admins cannot use ${secret:...} placeholders");
+            secrets_allowed = false;
+        }
+        camera_config.tenant = Some(tenant.clone());
+    }
+    if let Err(e) = state.server_config.validate_camera_tenant(&camera_config) {
+        issue(&mut errors, "tenant", "invalid", e);
+    }
+
+    check_retentions(&camera_config, &mut errors);
+    check_schedule(&camera_config, &mut errors);
+    check_tls(&camera_config, &mut errors);
+
+    let resolved = if !secrets_allowed {
+        None
+    } else {
+        match crate::secrets::resolve_camera_config(&camera_config) {
+            Ok(resolved) => Some(resolved),
+            Err(e) => {
+                issue(&mut errors, "secrets", "unresolved", e.to_string());
+                None
+            }
+        }
+    };
+
+    let (stream, ptz) = match resolved {
+        Some(resolved) if probe => (probe_stream(&resolved).await, check_ptz_login(&resolved).await),
+        Some(_) => (CheckResult::skipped("Probing disabled"), CheckResult::skipped("Probing disabled")),
+        None => (CheckResult::skipped("Secrets could not be resolved"), CheckResult::skipped("Secrets could not be resolved")),
+    };
+    if stream.status == CheckStatus::Failed {
+        issue(&mut errors, "url", "unreachable", stream.message.clone());
+    }
+    if ptz.status == CheckStatus::Failed {
+        issue(&mut errors, "ptz", "login_failed", ptz.message.clone());
+    }
+
+    ValidationReport { valid: errors.is_empty(), errors, stream, ptz }
+}
+
+async fn check_path(state: &AppState, access: &AdminAccess, camera_id: Option<&str>, camera_config: &CameraConfig, errors: &mut Vec<ValidationIssue>) {
+    let path = camera_config.path.trim_end_matches('/');
+    if path.is_empty() {
+        issue(errors, "path", "required", "Path is required");
+        return;
+    }
+    if !path.starts_with('/') {
+        issue(errors, "path", "invalid", "Path must start with '/'");
+        return;
+    }
+    let overlaps = |other: &str| path == other || path.starts_with(&format!("{}/", other)) || other.starts_with(&format!("{}/", path));
+    if let Some(reserved) = RESERVED_PATHS.iter().find(|reserved| overlaps(reserved)) {
+        issue(errors, "path", "reserved", format!("Path collides with the server's '{}' routes", reserved));
+    }
+    for (other_id, other) in state.camera_configs.read().await.iter() {
+        if Some(other_id.as_str()) == camera_id || !overlaps(other.path.trim_end_matches('/')) {
+            continue;
+        }
+        // Cameras of other tenants are not named
+        let message = if access.allows(other) {
+            format!("Path collides with '{}' of camera '{}'", other.path, other_id)
+        } else {
+            "Path collides with another camera".to_string()
+        };
+        issue(errors, "path", "conflict", message);
+    }
+}
+
+/// Retentions and limits are "0" (keep forever / no limit) or a duration like "30d"
+fn check_retentions(camera_config: &CameraConfig, errors: &mut Vec<ValidationIssue>) {
+    let recording = camera_config.recording.as_ref();
+    let retentions = [
+        ("recording.frame_storage_retention", recording.and_then(|r| r.frame_storage_retention.as_deref())),
+        ("recording.mp4_storage_retention", recording.and_then(|r| r.mp4_storage_retention.as_deref())),
+        ("recording.hls_storage_retention", recording.and_then(|r| r.hls_storage_retention.as_deref())),
+        ("motion_heatmap.retention", camera_config.motion_heatmap.as_ref().map(|h| h.retention.as_str())),
+        ("recording.max_recording_duration", recording.and_then(|r| r.max_recording_duration.as_deref())),
+    ];
+    for (field, retention) in retentions {
+        let Some(retention) = retention else { continue };
+        if retention == "0" {
+            continue;
+        }
+        if let Err(e) = humantime::parse_duration(retention) {
+            issue(errors, field, "invalid_duration", format!("Invalid duration '{}': {}", retention, e));
+        }
+    }
+}
+
+fn check_schedule(camera_config: &CameraConfig, errors: &mut Vec<ValidationIssue>) {
+    for (i, window) in camera_config.get_recording_schedule().iter().enumerate() {
+        for (name, time) in [("start", &window.start), ("end", &window.end)] {
+            if crate::recording_schedule::parse_time(time).is_none() {
+                let field = format!("recording.schedule[{}].{}", i, name);
+                issue(errors, &field, "invalid_time", format!("Invalid time '{}', expected HH:MM", time));
+            }
+        }
+    }
+}
+
+/// RTSPS tunnels RTP through the TLS connection, and the TLS files must be readable by the server
+fn check_tls(camera_config: &CameraConfig, errors: &mut Vec<ValidationIssue>) {
+    let is_rtsps = camera_config.url.to_lowercase().starts_with("rtsps://");
+    if is_rtsps && camera_config.transport != "tcp" {
+        issue(errors, "transport", "invalid", "rtsps:// sources only support the tcp transport");
+    }
+    let Some(ref tls) = camera_config.tls else { return };
+    // ONVIF cameras may resolve to an rtsps:// URI at runtime
+    if !is_rtsps && !camera_config.onvif_source_enabled() {
+        issue(errors, "tls", "invalid", "TLS settings only apply to rtsps:// URLs");
+    }
+    if tls.cert_file.is_some() != tls.key_file.is_some() {
+        let field = if tls.cert_file.is_some() { "tls.key_file" } else { "tls.cert_file" };
+        issue(errors, field, "required", "Client certificate and key must be configured together");
+    }
+    for (field, file) in [("tls.ca_file", &tls.ca_file), ("tls.cert_file", &tls.cert_file), ("tls.key_file", &tls.key_file)] {
+        if let Some(file) = file {
+            if !std::path::Path::new(file).is_file() {
+                issue(errors, field, "not_found", format!("File '{}' does not exist", file));
+            }
+        }
+    }
+}
+
+/// Read one frame of the stream with FFmpeg
+async fn probe_stream(camera_config: &CameraConfig) -> CheckResult {
+    if camera_config.device.is_some() {
+        return CheckResult::skipped("Local devices are not probed");
+    }
+    if camera_config.test_pattern.is_some() {
+        return CheckResult::skipped("Test pattern cameras have no stream to probe");
+    }
+    if camera_config.url.is_empty() {
+        return CheckResult::skipped("The stream URL is resolved via ONVIF when the camera starts");
+    }
+    let url = &camera_config.url;
+    let mut command = Command::new("ffmpeg");
+    command.args(["-hide_banner", "-loglevel", "error"]);
+    command.args(crate::config::rtsp_input_args(url, &camera_config.transport, camera_config.tls.as_ref()));
+    command.args(["-i", url, "-frames:v", "1", "-f", "null", "-"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    let started = Instant::now();
+    let result = tokio::time::timeout(Duration::from_secs(PROBE_TIMEOUT_SECS), command.output()).await;
+    let duration_ms = Some(started.elapsed().as_millis() as u64);
+    let message = match result {
+        Ok(Ok(output)) if output.status.success() => {
+            return CheckResult { status: CheckStatus::Ok, message: "Stream delivered a frame".to_string(), duration_ms };
+        }
+        Ok(Ok(output)) => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let last_line = stderr.lines().rev().find(|line| !line.trim().is_empty()).unwrap_or("FFmpeg exited without output");
+            // FFmpeg repeats the URL in its errors, credentials included
+            last_line.replace(url.as_str(), &crate::audit::redact_url(url.clone()))
+        }
+        Ok(Err(e)) => format!("Failed to run FFmpeg: {}", e),
+        Err(_) => format!("No frame within {} seconds", PROBE_TIMEOUT_SECS),
+    };
+    CheckResult { status: CheckStatus::Failed, message, duration_ms }
+}
+
+/// Authenticated GetNodes request against the ONVIF PTZ service
+async fn check_ptz_login(camera_config: &CameraConfig) -> CheckResult {
+    let Some(ptz) = camera_config.ptz.as_ref().filter(|ptz| ptz.enabled) else {
+        return CheckResult::skipped("PTZ is not enabled");
+    };
+    let failed = |message: String, duration_ms| CheckResult { status: CheckStatus::Failed, message, duration_ms };
+    if !ptz.protocol.eq_ignore_ascii_case("onvif") {
+        return failed(format!("Unsupported PTZ protocol '{}'", ptz.protocol), None);
+    }
+    let Some(ref url) = ptz.onvif_url else {
+        return failed("Missing onvif_url in PTZ config".to_string(), None);
+    };
+
+    let soap = OnvifPtz::new(url.clone(), ptz.username.clone(), ptz.password.clone(), String::new());
+    let started = Instant::now();
+    let result = tokio::time::timeout(
+        Duration::from_secs(PTZ_TIMEOUT_SECS),
+        soap.call(url, "http://www.onvif.org/ver20/ptz/wsdl/GetNodes", "<tptz:GetNodes/>"),
+    ).await;
+    let duration_ms = Some(started.elapsed().as_millis() as u64);
+    match result {
+        Ok(Ok(_)) => CheckResult { status: CheckStatus::Ok, message: "PTZ service accepted the credentials".to_string(), duration_ms },
+        Ok(Err(e)) => failed(e.to_string(), duration_ms),
+        Err(_) => failed(format!("No response within {} seconds", PTZ_TIMEOUT_SECS), duration_ms),
+    }
+}
@@ -0,0 +1,268 @@
+use std::path::Path;
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use clap::Subcommand;
+use tracing::{info, warn};
+
+use crate::bench::{self, BenchOptions};
+use crate::config::{Config, DatabaseType, RecordingConfig};
+use crate::database::{DatabaseProvider, ReasonScope};
+use crate::errors::{Result, StreamError};
+use crate::export_jobs::{ExportJobManager, ExportJobStatus};
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Run the streaming server (default)
+    Serve,
+    /// Concatenate the MP4 segments of a camera in a time range into one file
+    Export {
+        /// Camera ID
+        #[arg(long)]
+        camera: String,
+        /// Start of the range (RFC 3339, e.g. 2025-01-15T10:00:00Z)
+        #[arg(long)]
+        from: DateTime<Utc>,
+        /// End of the range (RFC 3339)
+        #[arg(long)]
+        to: DateTime<Utc>,
+        /// Output file
+        #[arg(long)]
+        out: String,
+        /// Blur the export with the camera's `export_redaction` settings
+        #[arg(long)]
+        redact: bool,
+    },
+    /// Delete frames, MP4 and HLS segments older than a duration, regardless of the retention settings.
+    /// Kept and locked sessions stay untouched.
+    Prune {
+        /// Age of the oldest data to keep, e.g. "30d" or "12h"
+        #[arg(long)]
+        older_than: String,
+        /// Only prune this camera
+        #[arg(long)]
+        camera: Option<String>,
+    },
+    /// Re-hash stored frame batches and MP4 segments and compare them with their integrity hashes
+    VerifyDb {
+        /// Only verify this camera
+        #[arg(long)]
+        camera: Option<String>,
+        /// Start of the range (default: all recordings)
+        #[arg(long)]
+        from: Option<DateTime<Utc>>,
+        /// End of the range (default: now)
+        #[arg(long)]
+        to: Option<DateTime<Utc>>,
+    },
+    /// Load test the running server with virtual test pattern cameras and WebSocket viewers
+    Bench {
+        /// Number of virtual cameras
+        #[arg(long, default_value_t = 4)]
+        cameras: usize,
+        /// Number of WebSocket viewers, spread evenly over the cameras
+        #[arg(long, default_value_t = 8)]
+        viewers: usize,
+        /// Length of the measurement, e.g. "30s" or "5m"
+        #[arg(long, default_value = "30s")]
+        duration: String,
+        /// Frame width of the virtual cameras
+        #[arg(long, default_value_t = 640)]
+        width: u32,
+        /// Frame height of the virtual cameras
+        #[arg(long, default_value_t = 480)]
+        height: u32,
+        /// Frame rate of the virtual cameras
+        #[arg(long, default_value_t = 15)]
+        fps: u32,
+        /// Record the virtual cameras and report database insert rates
+        #[arg(long)]
+        record: bool,
+        /// Address of the server (default: http://127.0.0.1:<server.port>)
+        #[arg(long)]
+        url: Option<String>,
+        /// Admin token (default: server.admin_token of the configuration)
+        #[arg(long)]
+        token: Option<String>,
+        /// Keep the virtual cameras after the run
+        #[arg(long)]
+        keep: bool,
+    },
+}
+
+/// Run a maintenance command against the recording databases of the configuration
+pub async fn run(command: Command, config_path: &str) -> Result<()> {
+    let config = Config::load(config_path)
+        .map_err(|e| StreamError::config(format!("Failed to load configuration {}: {}", config_path, e)))?;
+    if let Command::Bench { cameras, viewers, duration, width, height, fps, record, url, token, keep } = command {
+        let duration = humantime::parse_duration(&duration)
+            .map_err(|e| StreamError::config(format!("Invalid duration '{}': {}", duration, e)))?;
+        let options = BenchOptions { cameras, viewers, duration, width, height, fps, record, url, token, keep };
+        return bench::run(&config, options).await;
+    }
+    let recording_config = config.recording
+        .as_ref()
+        .ok_or_else(|| StreamError::config("Recording is not configured"))?;
+
+    match command {
+        Command::Serve | Command::Bench { .. } => Ok(()),
+        Command::Export { camera, from, to, out, redact } => {
+            export(&config, recording_config, &camera, from, to, &out, redact).await
+        }
+        Command::Prune { older_than, camera } => {
+            prune(&config, recording_config, &older_than, camera.as_deref()).await
+        }
+        Command::VerifyDb { camera, from, to } => {
+            verify(&config, recording_config, camera.as_deref(), from, to).await
+        }
+    }
+}
+
+/// Cameras to work on: the given one, or every configured camera
+fn camera_ids(config: &Config, camera: Option<&str>) -> Result<Vec<String>> {
+    match camera {
+        Some(camera) if config.cameras.contains_key(camera) => Ok(vec![camera.to_string()]),
+        Some(camera) => Err(StreamError::not_found(format!("Camera '{}' not found", camera))),
+        None => {
+            let mut camera_ids: Vec<String> = config.cameras.keys().cloned().collect();
+            camera_ids.sort();
+            Ok(camera_ids)
+        }
+    }
+}
+
+/// Open the recording database of a camera; None for a SQLite camera that never recorded
+async fn open_database(recording_config: &RecordingConfig, camera_id: &str) -> Result<Option<Arc<dyn DatabaseProvider>>> {
+    if recording_config.database_type == DatabaseType::SQLite
+        && !Path::new(&format!("{}/{}.db", recording_config.database_path, camera_id)).exists()
+    {
+        return Ok(None);
+    }
+    let database = crate::database::create_recording_database_provider(recording_config, camera_id).await?;
+    database.initialize().await?;
+    Ok(Some(database))
+}
+
+async fn export(
+    config: &Config,
+    recording_config: &RecordingConfig,
+    camera_id: &str,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    out: &str,
+    redact: bool,
+) -> Result<()> {
+    if from >= to {
+        return Err(StreamError::config("'from' must be before 'to'"));
+    }
+    camera_ids(config, Some(camera_id))?;
+    let redaction = config.cameras.get(camera_id).and_then(|camera| camera.export_redaction.clone());
+    if redact && redaction.is_none() {
+        return Err(StreamError::config(format!("Export redaction is not configured for camera '{}'", camera_id)));
+    }
+    let redaction = redaction.filter(|redaction| redact || redaction.required);
+    let database = open_database(recording_config, camera_id)
+        .await?
+        .ok_or_else(|| StreamError::not_found(format!("No recordings found for camera '{}'", camera_id)))?;
+
+    // The job writes next to the output file, which keeps the final rename on one file system
+    let out_dir = Path::new(out)
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let export_manager = ExportJobManager::new(out_dir.to_string_lossy().to_string(), 1);
+    let job_id = export_manager.create_job(camera_id.to_string(), from, to, redaction).await;
+    export_manager.process_job(&job_id, database, recording_config.get_mp4_storage_path()).await?;
+
+    let job = export_manager
+        .get_job(&job_id)
+        .await
+        .filter(|job| job.status == ExportJobStatus::Completed)
+        .ok_or_else(|| StreamError::internal("Export did not complete"))?;
+    std::fs::rename(&job.output_path, out)
+        .map_err(|e| StreamError::internal(format!("Failed to move export to {}: {}", out, e)))?;
+    if let Err(e) = std::fs::remove_dir(out_dir.join("temp")) {
+        warn!("Failed to remove temporary export directory: {}", e);
+    }
+
+    println!("Exported camera '{}' from {} to {}: {} ({} bytes)", camera_id, from, to, out, job.file_size_bytes.unwrap_or(0));
+    for attachment in &job.attachments {
+        println!("  attachment of session {}: {} ({})", attachment.session_id, attachment.filename, attachment.file_path);
+    }
+    Ok(())
+}
+
+async fn prune(config: &Config, recording_config: &RecordingConfig, older_than: &str, camera: Option<&str>) -> Result<()> {
+    let duration = humantime::parse_duration(older_than)
+        .map_err(|e| StreamError::config(format!("Invalid duration '{}': {}", older_than, e)))?;
+    let cutoff = Utc::now() - chrono::Duration::from_std(duration)
+        .map_err(|e| StreamError::config(format!("Invalid duration '{}': {}", older_than, e)))?;
+    let scope = ReasonScope::default();
+
+    for camera_id in camera_ids(config, camera)? {
+        let Some(database) = open_database(recording_config, &camera_id).await? else {
+            continue;
+        };
+        info!("Pruning recordings of camera '{}' older than {}", camera_id, cutoff);
+        let lock = database.lock_for_cleanup().await;
+
+        let frames = database.delete_old_frames(Some(&camera_id), cutoff, &scope).await?;
+        let mp4_segments = database.delete_old_video_segments(Some(&camera_id), cutoff, &scope).await?;
+        let hls_segments = database.delete_old_recording_hls_segments(older_than, Some(&camera_id), &scope).await?;
+        for data_type in [crate::integrity::DATA_TYPE_FRAMES, crate::integrity::DATA_TYPE_MP4] {
+            database.delete_old_integrity_hashes(Some(&camera_id), data_type, cutoff, &scope).await?;
+        }
+        database.delete_old_recording_events(Some(&camera_id), cutoff, &scope).await?;
+        let sessions = database.delete_unused_sessions(Some(&camera_id)).await?;
+        database.delete_orphaned_thumbnails(Some(&camera_id)).await?;
+        database.delete_orphaned_attachments(Some(&camera_id)).await?;
+        drop(lock);
+
+        println!(
+            "Camera '{}': deleted {} frames, {} MP4 segments, {} HLS segments, {} sessions",
+            camera_id, frames, mp4_segments, hls_segments, sessions
+        );
+        if frames + mp4_segments + hls_segments + sessions > 0 && recording_config.vacuum_after_cleanup {
+            database.compact_database(recording_config.vacuum_mode).await?;
+        }
+    }
+    Ok(())
+}
+
+async fn verify(
+    config: &Config,
+    recording_config: &RecordingConfig,
+    camera: Option<&str>,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+) -> Result<()> {
+    let from = from.unwrap_or(DateTime::<Utc>::UNIX_EPOCH);
+    let to = to.unwrap_or_else(Utc::now);
+    let mut failed_cameras = 0;
+
+    for camera_id in camera_ids(config, camera)? {
+        let Some(database) = open_database(recording_config, &camera_id).await? else {
+            continue;
+        };
+        let report = crate::integrity::verify(&database, &camera_id, from, to).await?;
+        println!(
+            "Camera '{}': {} checked, {} ok, {} modified, {} missing",
+            camera_id, report.checked, report.ok, report.modified, report.missing
+        );
+        for failure in &report.failures {
+            println!(
+                "  {} {} {} - {}: expected {} items, found {}",
+                failure.status, failure.data_type, failure.start_time, failure.end_time,
+                failure.expected_count, failure.actual_count
+            );
+        }
+        if !report.failures.is_empty() {
+            failed_cameras += 1;
+        }
+    }
+
+    if failed_cameras == 0 {
+        Ok(())
+    } else {
+        Err(StreamError::internal(format!("Integrity check failed for {} camera(s)", failed_cameras)))
+    }
+}
@@ -0,0 +1,139 @@
+use chrono::{DateTime, Utc};
+use tracing::{debug, info};
+
+use crate::config::JpegEncoderBackend;
+use crate::database::DatabaseProvider;
+use crate::jpeg;
+use crate::errors::{Result, StreamError};
+
+pub const DEFAULT_FPS: u32 = 5;
+pub const DEFAULT_WIDTH: u32 = 480;
+const MAX_FPS: u32 = 15;
+const MAX_WIDTH: u32 = 1280;
+// Clips are meant as short previews, longer ranges should use the MP4 export
+const MAX_DURATION_SECS: i64 = 120;
+const MAX_FRAMES: usize = 600;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClipFormat {
+    Gif,
+    Webp,
+}
+
+impl ClipFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ClipFormat::Gif => "image/gif",
+            ClipFormat::Webp => "image/webp",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ClipFormat::Gif => "gif",
+            ClipFormat::Webp => "webp",
+        }
+    }
+}
+
+/// Check the clip parameters, returning the effective fps and width
+pub fn validate(from: DateTime<Utc>, to: DateTime<Utc>, fps: Option<u32>, width: Option<u32>) -> std::result::Result<(u32, u32), String> {
+    if to <= from {
+        return Err("'to' must be after 'from'".to_string());
+    }
+    if (to - from).num_seconds() > MAX_DURATION_SECS {
+        return Err(format!("Clips are limited to {} seconds", MAX_DURATION_SECS));
+    }
+    let fps = fps.unwrap_or(DEFAULT_FPS);
+    if !(1..=MAX_FPS).contains(&fps) {
+        return Err(format!("fps must be between 1 and {}", MAX_FPS));
+    }
+    let width = width.unwrap_or(DEFAULT_WIDTH);
+    if !(16..=MAX_WIDTH).contains(&width) {
+        return Err(format!("width must be between 16 and {}", MAX_WIDTH));
+    }
+    Ok((fps, width))
+}
+
+/// Render the stored frames of a time range into an animated GIF or WebP.
+/// Returns None when no frames were recorded in the range.
+pub async fn render_clip(
+    database: &dyn DatabaseProvider,
+    camera_id: &str,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    fps: u32,
+    width: u32,
+    format: ClipFormat,
+) -> Result<Option<Vec<u8>>> {
+    let frames = sample_frames(database, camera_id, from, to, fps).await?;
+    if frames.is_empty() {
+        return Ok(None);
+    }
+    info!("Rendering {} clip for camera '{}' from {} frames ({} fps, {} px wide)",
+          format.extension(), camera_id, frames.len(), fps, width);
+
+    let settings = jpeg::settings();
+    // WebP clips need FFmpeg's lossy libwebp encoder
+    if format == ClipFormat::Gif && settings.backend == JpegEncoderBackend::Native {
+        let clip = tokio::task::spawn_blocking(move || jpeg::encode_gif(&frames, fps, width, settings.gif_speed))
+            .await
+            .map_err(|e| StreamError::internal(format!("Clip task failed: {}", e)))??;
+        debug!("Rendered {} bytes gif clip for camera '{}' in-process", clip.len(), camera_id);
+        return Ok(Some(clip));
+    }
+
+    let filter = match format {
+        // A palette generated from the clip itself looks far better than the default GIF palette
+        ClipFormat::Gif => format!("scale={}:-1:flags=lanczos,split[a][b];[a]palettegen[p];[b][p]paletteuse", width),
+        ClipFormat::Webp => format!("scale={}:-1:flags=lanczos", width),
+    };
+    let fps = fps.to_string();
+    let mut args = vec![
+        "-loglevel", "error",
+        "-f", "image2pipe",
+        "-c:v", "mjpeg",
+        "-framerate", &fps,
+        "-i", "-",
+        "-vf", &filter,
+        "-loop", "0",
+    ];
+    match format {
+        ClipFormat::Gif => args.extend(["-f", "gif"]),
+        ClipFormat::Webp => args.extend(["-c:v", "libwebp", "-quality", "75", "-f", "webp"]),
+    }
+    args.push("-");
+
+    let clip = jpeg::run_ffmpeg("Clip rendering failed", &args, frames).await?;
+    debug!("Rendered {} bytes {} clip for camera '{}'", clip.len(), format.extension(), camera_id);
+    Ok(Some(clip))
+}
+
+/// Pick the first stored frame of every `1/fps` interval of the range
+async fn sample_frames(
+    database: &dyn DatabaseProvider,
+    camera_id: &str,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    fps: u32,
+) -> Result<Vec<Vec<u8>>> {
+    let interval = chrono::Duration::milliseconds(1000 / fps as i64);
+    let mut stream = database.create_frame_stream(camera_id, from, to).await?;
+    let mut frames = Vec::new();
+    let mut next_time = from;
+    while let Some(frame) = stream.next_frame().await? {
+        if frame.timestamp < next_time {
+            continue;
+        }
+        frames.push(frame.frame_data);
+        if frames.len() >= MAX_FRAMES {
+            break;
+        }
+        // Gaps in the recording are skipped instead of repeating the last frame
+        while next_time <= frame.timestamp {
+            next_time += interval;
+        }
+    }
+    stream.close().await?;
+    Ok(frames)
+}
@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use chrono::{DateTime, NaiveDate, Utc};
+use futures_util::StreamExt;
+use serde::Serialize;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+use crate::config::{CameraConfig, ClockDriftConfig};
+use crate::errors::{Result, StreamError};
+use crate::ptz::onvif_ptz::OnvifPtz;
+use crate::snapshot_source::urlencoding_decode;
+
+// Timeout of a single camera time query
+const PROBE_TIMEOUT_SECS: u64 = 5;
+// Largest RTSP response header read while looking for the Date header
+const MAX_RTSP_RESPONSE_BYTES: usize = 8192;
+// Longest wait for an RTCP sender report, cameras send one every few seconds
+const RTCP_TIMEOUT_SECS: u64 = 15;
+
+lazy_static::lazy_static! {
+    static ref DRIFTS: Mutex<HashMap<String, ClockDrift>> = Mutex::new(HashMap::new());
+}
+
+/// Latest comparison of a camera's clock with the server clock
+#[derive(Debug, Clone, Serialize)]
+pub struct ClockDrift {
+    /// Camera time minus server time in milliseconds (positive = camera clock is ahead)
+    pub drift_ms: i64,
+    /// Where the camera time was read from: "onvif", "rtsp" or "rtcp"
+    pub source: &'static str,
+    pub round_trip_ms: u64,
+    pub measured_at: DateTime<Utc>,
+    pub exceeds_threshold: bool,
+    /// Stored frame timestamps are shifted by the drift
+    pub corrects_timestamps: bool,
+}
+
+/// Latest drift measured for a camera
+pub fn get_drift(camera_id: &str) -> Option<ClockDrift> {
+    DRIFTS.lock().ok()?.get(camera_id).cloned()
+}
+
+/// Capture time of a frame that arrived at `arrival`: the arrival time shifted by the latest
+/// drift if the camera corrects its timestamps, else the arrival time itself
+pub fn capture_time(camera_id: &str, arrival: DateTime<Utc>) -> DateTime<Utc> {
+    let drift_ms = DRIFTS.lock().ok()
+        .and_then(|drifts| drifts.get(camera_id).filter(|d| d.corrects_timestamps).map(|d| d.drift_ms));
+    match drift_ms {
+        Some(drift_ms) => arrival + chrono::Duration::milliseconds(drift_ms),
+        None => arrival,
+    }
+}
+
+/// Forget the drift of a camera, e.g. when its monitor is stopped
+pub fn clear_drift(camera_id: &str) {
+    if let Ok(mut drifts) = DRIFTS.lock() {
+        drifts.remove(camera_id);
+    }
+}
+
+/// Compare the camera clock with the server clock every `interval_secs` until the task is aborted
+pub async fn run_monitor(camera_id: String, camera_config: CameraConfig, config: ClockDriftConfig) {
+    let mut exceeded = false;
+    loop {
+        match measure(&camera_config, &config).await {
+            Ok((drift_ms, source, round_trip_ms)) => {
+                let exceeds_threshold = drift_ms.unsigned_abs() > config.threshold_ms;
+                debug!("[{}] Camera clock drift {} ms ({}, round trip {} ms)", camera_id, drift_ms, source, round_trip_ms);
+                if exceeds_threshold && !exceeded {
+                    warn!("[{}] Camera clock is {} ms {} the server clock (threshold {} ms); recording timestamps will not match the camera overlay",
+                          camera_id, drift_ms.abs(), if drift_ms > 0 { "ahead of" } else { "behind" }, config.threshold_ms);
+                } else if !exceeds_threshold && exceeded {
+                    info!("[{}] Camera clock drift back within threshold ({} ms)", camera_id, drift_ms);
+                }
+                exceeded = exceeds_threshold;
+                if let Ok(mut drifts) = DRIFTS.lock() {
+                    drifts.insert(camera_id.clone(), ClockDrift {
+                        drift_ms,
+                        source,
+                        round_trip_ms,
+                        measured_at: Utc::now(),
+                        exceeds_threshold,
+                        corrects_timestamps: config.correct_timestamps,
+                    });
+                }
+            }
+            Err(e) => debug!("[{}] Camera clock drift measurement failed: {}", camera_id, e),
+        }
+        tokio::time::sleep(Duration::from_secs(config.interval_secs.max(10))).await;
+    }
+}
+
+/// Read the camera time and return (drift_ms, source, round_trip_ms). The camera time is
+/// compared with the server time halfway through the request.
+async fn measure(camera_config: &CameraConfig, config: &ClockDriftConfig) -> Result<(i64, &'static str, u64)> {
+    if config.rtcp {
+        let url = camera_config.url.clone();
+        return tokio::time::timeout(Duration::from_secs(PROBE_TIMEOUT_SECS + RTCP_TIMEOUT_SECS), rtcp_sender_report(&url))
+            .await
+            .map_err(|_| StreamError::server("No RTCP sender report received from the camera"))?;
+    }
+    let onvif_url = config.onvif_url.clone()
+        .or_else(|| camera_config.ptz.as_ref().and_then(|p| p.onvif_url.clone()));
+    let sent_at = Utc::now();
+    let started = Instant::now();
+    let (camera_time, source) = tokio::time::timeout(Duration::from_secs(PROBE_TIMEOUT_SECS), async {
+        match onvif_url {
+            Some(url) => {
+                let ptz = camera_config.ptz.as_ref();
+                let time = onvif_system_time(url, ptz.and_then(|p| p.username.clone()), ptz.and_then(|p| p.password.clone())).await?;
+                Ok::<_, StreamError>((time, "onvif"))
+            }
+            None => Ok((rtsp_date_header(&camera_config.url).await?, "rtsp")),
+        }
+    }).await.map_err(|_| StreamError::server("Camera time request timed out"))??;
+    let round_trip = started.elapsed();
+    let server_time = sent_at + chrono::Duration::from_std(round_trip / 2).unwrap_or_default();
+
+    let mut drift_ms = (camera_time - server_time).num_milliseconds();
+    if source == "rtsp" {
+        // The Date header has a resolution of one second, the camera time is somewhere within it
+        drift_ms += 500;
+    }
+    Ok((drift_ms, source, round_trip.as_millis() as u64))
+}
+
+/// Offset of the camera clock from an RTCP sender report: the report's NTP time is when the camera
+/// sent it, compared with when it arrived. Unlike the other sources this includes the network and
+/// encoder latency, so the drift is the offset between camera capture time and server arrival time.
+async fn rtcp_sender_report(camera_url: &str) -> Result<(i64, &'static str, u64)> {
+    let mut url = url::Url::parse(camera_url).map_err(|e| StreamError::config(format!("Invalid camera URL: {}", e)))?;
+    if url.scheme() != "rtsp" {
+        return Err(StreamError::config("Clock drift from RTCP requires an rtsp:// camera URL"));
+    }
+    // Retina takes the credentials separately
+    let creds = (!url.username().is_empty()).then(|| retina::client::Credentials {
+        username: urlencoding_decode(url.username()),
+        password: url.password().map(urlencoding_decode).unwrap_or_default(),
+    });
+    let _ = url.set_username("");
+    let _ = url.set_password(None);
+
+    let options = retina::client::SessionOptions::default()
+        .creds(creds)
+        .user_agent("rtsp-streaming-server".to_string());
+    let started = Instant::now();
+    let mut session = retina::client::Session::describe(url, options).await
+        .map_err(|e| StreamError::server(format!("RTSP DESCRIBE failed: {}", e)))?;
+    let round_trip = started.elapsed();
+    let video = session.streams().iter().position(|s| s.media() == "video")
+        .ok_or_else(|| StreamError::server("Camera offers no video stream"))?;
+    session.setup(video, retina::client::SetupOptions::default()).await
+        .map_err(|e| StreamError::server(format!("RTSP SETUP failed: {}", e)))?;
+    let mut session = session.play(retina::client::PlayOptions::default()).await
+        .map_err(|e| StreamError::server(format!("RTSP PLAY failed: {}", e)))?;
+
+    // The session is torn down when dropped
+    while let Some(item) = session.next().await {
+        let retina::client::PacketItem::Rtcp(packet) = item.map_err(|e| StreamError::server(format!("RTSP session failed: {}", e)))? else {
+            continue;
+        };
+        let received_at = DateTime::<Utc>::from(std::time::SystemTime::from(packet.ctx().received_wall()));
+        for report in packet.pkts().filter_map(|p| p.as_sender_report().ok().flatten()) {
+            let Some(camera_time) = ntp_to_utc(report.ntp_timestamp()) else {
+                continue;
+            };
+            return Ok(((camera_time - received_at).num_milliseconds(), "rtcp", round_trip.as_millis() as u64));
+        }
+    }
+    Err(StreamError::server("RTSP session ended without an RTCP sender report"))
+}
+
+/// UTC time of an NTP timestamp; cameras without a wall clock send 0 or times before 2000
+fn ntp_to_utc(ntp: retina::NtpTimestamp) -> Option<DateTime<Utc>> {
+    let since_epoch = ntp.0.checked_sub(retina::UNIX_EPOCH.0)?;
+    let secs = (since_epoch >> 32) as i64;
+    let nanos = (((since_epoch & 0xFFFF_FFFF) * 1_000_000_000) >> 32) as u32;
+    DateTime::from_timestamp(secs, nanos).filter(|t| t.timestamp() >= 946_684_800)
+}
+
+/// Camera UTC time from the ONVIF device service (GetSystemDateAndTime)
+async fn onvif_system_time(url: String, username: Option<String>, password: Option<String>) -> Result<DateTime<Utc>> {
+    let soap = OnvifPtz::new(url.clone(), username, password, String::new());
+    let body = "<tds:GetSystemDateAndTime xmlns:tds=\"http://www.onvif.org/ver10/device/wsdl\"/>";
+    let resp = soap.call(&url, "http://www.onvif.org/ver10/device/wsdl/GetSystemDateAndTime", body).await?;
+
+    let utc = resp.find("UTCDateTime")
+        .map(|pos| &resp[pos..])
+        .ok_or_else(|| StreamError::server("ONVIF GetSystemDateAndTime response has no UTCDateTime"))?;
+    let field = |name: &str| -> Result<u32> {
+        crate::ptz::onvif_events::extract_element_text(utc, name)
+            .and_then(|v| v.parse().ok())
+            .ok_or_else(|| StreamError::server(format!("ONVIF UTCDateTime has no valid {}", name)))
+    };
+    let (year, month, day) = (field("Year")?, field("Month")?, field("Day")?);
+    let (hour, minute, second) = (field("Hour")?, field("Minute")?, field("Second")?);
+    NaiveDate::from_ymd_opt(year as i32, month, day)
+        .and_then(|d| d.and_hms_opt(hour, minute, second))
+        .map(|t| t.and_utc())
+        .ok_or_else(|| StreamError::server("ONVIF UTCDateTime is not a valid date"))
+}
+
+/// Camera time from the Date header of an RTSP OPTIONS response
+async fn rtsp_date_header(camera_url: &str) -> Result<DateTime<Utc>> {
+    let url = url::Url::parse(camera_url).map_err(|e| StreamError::config(format!("Invalid camera URL: {}", e)))?;
+    if url.scheme() != "rtsp" {
+        return Err(StreamError::config("Clock drift without onvif_url requires an rtsp:// camera URL"));
+    }
+    let host = url.host_str().ok_or_else(|| StreamError::config("Camera URL has no host"))?;
+    let port = url.port().unwrap_or(554);
+
+    let mut stream = tokio::net::TcpStream::connect((host, port)).await?;
+    let request = format!(
+        "OPTIONS rtsp://{}:{}{} RTSP/1.0\r\nCSeq: 1\r\nUser-Agent: rtsp-streaming-server\r\n\r\n",
+        host, port, url.path()
+    );
+    stream.write_all(request.as_bytes()).await?;
+
+    let mut response = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !response.windows(4).any(|w| w == b"\r\n\r\n") && response.len() < MAX_RTSP_RESPONSE_BYTES {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        response.extend_from_slice(&buf[..n]);
+    }
+
+    // Unauthenticated requests are answered too (possibly with 401), the Date header is present either way
+    String::from_utf8_lossy(&response)
+        .lines()
+        .find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            if !name.trim().eq_ignore_ascii_case("date") {
+                return None;
+            }
+            DateTime::parse_from_rfc2822(value.trim()).ok().map(|t| t.with_timezone(&Utc))
+        })
+        .ok_or_else(|| StreamError::server("RTSP response has no Date header"))
+}
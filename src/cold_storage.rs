@@ -0,0 +1,302 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tracing::{error, info, warn};
+
+use crate::api_recording::ApiResponse;
+use crate::config::{ColdStorageConfig, SegmentContainer};
+use crate::database::{DatabaseProvider, Mp4Archive, VideoSegment};
+use crate::errors::{Result, StreamError};
+use crate::recording::RecordingManager;
+
+// Segments listed per query, a run continues until no segment is left to archive
+const ARCHIVE_BATCH_SIZE: i64 = 100;
+// Finished rehydration jobs stay queryable for this long
+const FINISHED_JOB_MINUTES: i64 = 60;
+// Seconds a client should wait before retrying playback of an archived range
+const RETRY_AFTER_SECS: u64 = 5;
+
+lazy_static::lazy_static! {
+    static ref JOBS: Mutex<HashMap<String, RehydrationJob>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RehydrationStatus {
+    Running,
+    Completed,
+    Failed,
+}
+
+/// Copy of archived MP4 segments from the cold tier back into hot storage
+#[derive(Debug, Clone, Serialize)]
+pub struct RehydrationJob {
+    pub job_id: String,
+    pub camera_id: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub status: RehydrationStatus,
+    pub total_segments: usize,
+    pub restored_segments: usize,
+    pub total_bytes: i64,
+    pub restored_bytes: i64,
+    pub progress_percent: f64,
+    pub error: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+fn parse_age(name: &str, value: &str) -> Result<chrono::Duration> {
+    humantime::parse_duration(value).ok()
+        .and_then(|d| chrono::Duration::from_std(d).ok())
+        .filter(|d| *d > chrono::Duration::zero())
+        .ok_or_else(|| StreamError::config(format!("Invalid cold storage {} '{}'", name, value)))
+}
+
+/// Start the task that moves old MP4 segments to the cold tier and evicts expired rehydrated copies
+pub fn start_archiver(recording_manager: Arc<RecordingManager>, config: ColdStorageConfig) -> Option<tokio::task::JoinHandle<()>> {
+    let (archive_after, rehydrated_retention) = match (parse_age("archive_after", &config.archive_after), parse_age("rehydrated_retention", &config.rehydrated_retention)) {
+        (Ok(archive_after), Ok(rehydrated_retention)) => (archive_after, rehydrated_retention),
+        (Err(e), _) | (_, Err(e)) => {
+            error!("MP4 cold storage disabled: {}", e);
+            return None;
+        }
+    };
+    info!("MP4 cold storage: segments older than {} are moved to {}", config.archive_after, config.path);
+
+    Some(tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(config.check_interval_minutes.max(1) * 60));
+        loop {
+            interval.tick().await;
+            for camera_id in recording_manager.get_all_camera_ids().await {
+                let Some(database) = recording_manager.get_camera_database(&camera_id).await else { continue };
+                let now = Utc::now();
+                let archived = archive_camera(&database, &camera_id, &config.path, now - archive_after).await;
+                let evicted = evict_rehydrated(&database, &camera_id, now - rehydrated_retention).await;
+                let orphaned = database.delete_orphaned_mp4_archives(&camera_id).await.unwrap_or_else(|e| {
+                    warn!("[{}] Failed to delete archived MP4 segments of deleted recordings: {}", camera_id, e);
+                    0
+                });
+                if archived + evicted + orphaned > 0 {
+                    info!("[{}] Cold storage: archived {} MP4 segments, evicted {} rehydrated, deleted {} orphaned",
+                          camera_id, archived, evicted, orphaned);
+                }
+            }
+        }
+    }))
+}
+
+async fn archive_camera(database: &Arc<dyn DatabaseProvider>, camera_id: &str, cold_path: &str, older_than: DateTime<Utc>) -> usize {
+    let mut archived = 0;
+    loop {
+        let segments = match database.list_mp4_segments_to_archive(camera_id, older_than, ARCHIVE_BATCH_SIZE).await {
+            Ok(segments) => segments,
+            Err(e) => {
+                error!("[{}] Failed to list MP4 segments to archive: {}", camera_id, e);
+                return archived;
+            }
+        };
+        let batch_size = segments.len();
+        let mut failed = 0;
+        for segment in segments {
+            match archive_segment(database, camera_id, cold_path, &segment).await {
+                Ok(()) => archived += 1,
+                Err(e) => {
+                    warn!("[{}] Failed to archive MP4 segment {}: {}", camera_id, segment.start_time, e);
+                    failed += 1;
+                }
+            }
+        }
+        // Failed segments are listed again, stop instead of retrying them in a loop
+        if (batch_size as i64) < ARCHIVE_BATCH_SIZE || failed > 0 {
+            return archived;
+        }
+    }
+}
+
+/// Copy a segment to the cold tier, then replace it with a stub and delete the hot file
+async fn archive_segment(database: &Arc<dyn DatabaseProvider>, camera_id: &str, cold_path: &str, segment: &VideoSegment) -> Result<()> {
+    let dir = Path::new(cold_path).join(camera_id).join(segment.start_time.format("%Y/%m/%d").to_string());
+    tokio::fs::create_dir_all(&dir).await?;
+
+    let archive_path = match segment.file_path {
+        Some(ref file_path) => {
+            let file_name = Path::new(file_path).file_name()
+                .ok_or_else(|| StreamError::internal(format!("Invalid MP4 segment path {}", file_path)))?;
+            let archive_path = dir.join(file_name);
+            tokio::fs::copy(file_path, &archive_path).await?;
+            archive_path
+        }
+        None => {
+            let data = database.get_video_segment_by_time(camera_id, segment.start_time).await?
+                .and_then(|segment| segment.mp4_data)
+                .ok_or_else(|| StreamError::not_found("MP4 segment data not found"))?;
+            let extension = SegmentContainer::from_data(&data).extension();
+            let archive_path = dir.join(format!("{}.{}", segment.start_time.format("%Y-%m-%dT%H-%M-%S%.3fZ"), extension));
+            tokio::fs::write(&archive_path, &data).await?;
+            archive_path
+        }
+    };
+
+    let archive = Mp4Archive {
+        camera_id: camera_id.to_string(),
+        start_time: segment.start_time,
+        end_time: segment.end_time,
+        archive_path: archive_path.to_string_lossy().to_string(),
+        original_path: segment.file_path.clone(),
+        size_bytes: segment.size_bytes,
+        archived_at: Utc::now(),
+        rehydrated_at: None,
+    };
+    if let Err(e) = database.archive_mp4_segment(&archive).await {
+        let _ = tokio::fs::remove_file(&archive_path).await;
+        return Err(e);
+    }
+    if let Some(ref file_path) = segment.file_path {
+        if let Err(e) = tokio::fs::remove_file(file_path).await {
+            warn!("[{}] Failed to delete archived MP4 file {}: {}", camera_id, file_path, e);
+        }
+    }
+    Ok(())
+}
+
+/// Drop hot copies rehydrated before `before`, their cold copies stay
+async fn evict_rehydrated(database: &Arc<dyn DatabaseProvider>, camera_id: &str, before: DateTime<Utc>) -> usize {
+    let archives = match database.list_rehydrated_mp4_segments(camera_id, before).await {
+        Ok(archives) => archives,
+        Err(e) => {
+            error!("[{}] Failed to list rehydrated MP4 segments: {}", camera_id, e);
+            return 0;
+        }
+    };
+    let mut evicted = 0;
+    for archive in archives {
+        if let Err(e) = database.evict_mp4_segment(camera_id, archive.start_time).await {
+            warn!("[{}] Failed to evict rehydrated MP4 segment {}: {}", camera_id, archive.start_time, e);
+            continue;
+        }
+        if let Some(ref original_path) = archive.original_path {
+            if let Err(e) = tokio::fs::remove_file(original_path).await {
+                warn!("[{}] Failed to delete rehydrated MP4 file {}: {}", camera_id, original_path, e);
+            }
+        }
+        evicted += 1;
+    }
+    evicted
+}
+
+/// Start rehydrating the archived segments overlapping `from..to`. Returns the running job that
+/// already covers the range if there is one, None if nothing in the range is archived.
+pub async fn rehydrate(database: Arc<dyn DatabaseProvider>, camera_id: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Option<RehydrationJob>> {
+    let archives = database.list_archived_mp4_segments(camera_id, from, to).await?;
+    if archives.is_empty() {
+        return Ok(None);
+    }
+
+    let job = {
+        let mut jobs = JOBS.lock().unwrap_or_else(|e| e.into_inner());
+        let expired = Utc::now() - chrono::Duration::minutes(FINISHED_JOB_MINUTES);
+        jobs.retain(|_, job| job.finished_at.is_none_or(|finished_at| finished_at > expired));
+        if let Some(job) = jobs.values().find(|job| {
+            job.camera_id == camera_id && job.status == RehydrationStatus::Running && job.from <= from && job.to >= to
+        }) {
+            return Ok(Some(job.clone()));
+        }
+
+        let job = RehydrationJob {
+            job_id: uuid::Uuid::new_v4().to_string(),
+            camera_id: camera_id.to_string(),
+            from,
+            to,
+            status: RehydrationStatus::Running,
+            total_segments: archives.len(),
+            restored_segments: 0,
+            total_bytes: archives.iter().map(|archive| archive.size_bytes).sum(),
+            restored_bytes: 0,
+            progress_percent: 0.0,
+            error: None,
+            started_at: Utc::now(),
+            finished_at: None,
+        };
+        jobs.insert(job.job_id.clone(), job.clone());
+        job
+    };
+
+    info!("[{}] Rehydrating {} archived MP4 segments ({} bytes) between {} and {}",
+          camera_id, job.total_segments, job.total_bytes, from, to);
+    let job_id = job.job_id.clone();
+    tokio::spawn(async move {
+        let result = run_rehydration(&job_id, &database, archives).await;
+        update_job(&job_id, |job| {
+            job.finished_at = Some(Utc::now());
+            match result {
+                Ok(()) => job.status = RehydrationStatus::Completed,
+                Err(ref e) => {
+                    error!("[{}] Rehydration job {} failed: {}", job.camera_id, job.job_id, e);
+                    job.status = RehydrationStatus::Failed;
+                    job.error = Some(e.to_string());
+                }
+            }
+        });
+    });
+    Ok(Some(job))
+}
+
+async fn run_rehydration(job_id: &str, database: &Arc<dyn DatabaseProvider>, archives: Vec<Mp4Archive>) -> Result<()> {
+    for archive in archives {
+        let data = tokio::fs::read(&archive.archive_path).await
+            .map_err(|e| StreamError::internal(format!("Failed to read {}: {}", archive.archive_path, e)))?;
+        match archive.original_path {
+            Some(ref original_path) => {
+                if let Some(parent) = PathBuf::from(original_path).parent() {
+                    tokio::fs::create_dir_all(parent).await?;
+                }
+                tokio::fs::write(original_path, &data).await?;
+                database.restore_mp4_segment(&archive.camera_id, archive.start_time, Some(original_path), None).await?;
+            }
+            None => database.restore_mp4_segment(&archive.camera_id, archive.start_time, None, Some(&data)).await?,
+        }
+        update_job(job_id, |job| {
+            job.restored_segments += 1;
+            job.restored_bytes += archive.size_bytes;
+            job.progress_percent = (job.restored_segments as f64 * 1000.0 / job.total_segments.max(1) as f64).round() / 10.0;
+        });
+    }
+    Ok(())
+}
+
+fn update_job(job_id: &str, update: impl FnOnce(&mut RehydrationJob)) {
+    if let Some(job) = JOBS.lock().unwrap_or_else(|e| e.into_inner()).get_mut(job_id) {
+        update(job);
+    }
+}
+
+pub fn get_job(job_id: &str) -> Option<RehydrationJob> {
+    JOBS.lock().unwrap_or_else(|e| e.into_inner()).get(job_id).cloned()
+}
+
+/// `202 Accepted` with the rehydration job if part of `from..to` is archived, for playback
+/// endpoints to return instead of an incomplete recording
+pub async fn rehydration_response(database: &Arc<dyn DatabaseProvider>, camera_id: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> Option<Response> {
+    match rehydrate(database.clone(), camera_id, from, to).await {
+        Ok(Some(job)) => Some((
+            axum::http::StatusCode::ACCEPTED,
+            [(axum::http::header::RETRY_AFTER, RETRY_AFTER_SECS.to_string())],
+            Json(ApiResponse::success(serde_json::json!({
+                "message": "Recordings in this range are archived and being rehydrated, retry when the job has completed",
+                "job": job,
+            }))),
+        ).into_response()),
+        Ok(None) => None,
+        Err(e) => {
+            error!("[{}] Failed to check for archived MP4 segments: {}", camera_id, e);
+            Some((axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                  Json(ApiResponse::<()>::error(&format!("Failed to check for archived recordings: {}", e), 500)))
+                 .into_response())
+        }
+    }
+}
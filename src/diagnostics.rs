@@ -0,0 +1,128 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::Write;
+use std::sync::Mutex;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use tracing_subscriber::fmt::MakeWriter;
+
+// Log lines kept in memory for diagnostic bundles
+const RECENT_LOG_LINES: usize = 5000;
+
+// Keys whose string values are replaced in sanitized configs
+const SECRET_KEY_PARTS: [&str; 4] = ["password", "token", "secret", "passphrase"];
+
+// Maps whose values are all secrets: relay edge tokens, plugin and redaction environment
+const SECRET_MAP_KEYS: [&str; 2] = ["edges", "env"];
+
+lazy_static::lazy_static! {
+    static ref RECENT_LOGS: Mutex<VecDeque<String>> = Mutex::new(VecDeque::with_capacity(RECENT_LOG_LINES));
+    static ref FFMPEG_COMMANDS: Mutex<HashMap<String, FfmpegCommand>> = Mutex::new(HashMap::new());
+}
+
+/// Last FFmpeg command line a camera's stream was started with
+#[derive(Debug, Clone, Serialize)]
+pub struct FfmpegCommand {
+    pub command: String,
+    pub started_at: DateTime<Utc>,
+}
+
+/// Log writer keeping the most recent lines in memory, added as a second output of the logger
+pub struct RecentLogs;
+
+pub struct RecentLogWriter {
+    buffer: Vec<u8>,
+}
+
+impl<'a> MakeWriter<'a> for RecentLogs {
+    type Writer = RecentLogWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RecentLogWriter { buffer: Vec::new() }
+    }
+}
+
+impl Write for RecentLogWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+// One writer is created per event, the complete line is stored when it is dropped
+impl Drop for RecentLogWriter {
+    fn drop(&mut self) {
+        let line = String::from_utf8_lossy(&self.buffer);
+        let line = line.trim_end();
+        if line.is_empty() {
+            return;
+        }
+        let mut logs = RECENT_LOGS.lock().unwrap_or_else(|e| e.into_inner());
+        if logs.len() >= RECENT_LOG_LINES {
+            logs.pop_front();
+        }
+        logs.push_back(line.to_string());
+    }
+}
+
+/// Recent log lines, oldest first, with URL credentials masked
+pub fn recent_logs() -> Vec<String> {
+    RECENT_LOGS.lock().unwrap_or_else(|e| e.into_inner()).iter().map(|line| sanitize_text(line)).collect()
+}
+
+/// Remember the command line of a camera's FFmpeg process with credentials in URLs masked
+pub fn record_ffmpeg_command(camera_id: &str, program: &str, args: &[String]) {
+    let args: Vec<String> = args.iter().map(|arg| sanitize_text(arg)).collect();
+    let command = FfmpegCommand {
+        command: format!("{} {}", program, args.join(" ")),
+        started_at: Utc::now(),
+    };
+    FFMPEG_COMMANDS.lock().unwrap_or_else(|e| e.into_inner()).insert(camera_id.to_string(), command);
+}
+
+pub fn ffmpeg_commands() -> HashMap<String, FfmpegCommand> {
+    FFMPEG_COMMANDS.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Config with tokens, passwords and secrets replaced and URL credentials masked
+pub fn sanitize(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(map.into_iter().map(|(key, value)| {
+            let lower = key.to_ascii_lowercase();
+            let value = match value {
+                Value::String(secret) if SECRET_KEY_PARTS.iter().any(|part| lower.contains(part)) && !secret.is_empty() => {
+                    Value::String("***".to_string())
+                }
+                Value::Array(items) if SECRET_KEY_PARTS.iter().any(|part| lower.contains(part)) => {
+                    Value::Array(items.into_iter().map(|item| match item {
+                        Value::String(_) => Value::String("***".to_string()),
+                        other => sanitize(other),
+                    }).collect())
+                }
+                Value::Object(entries) if SECRET_MAP_KEYS.contains(&lower.as_str()) => {
+                    Value::Object(entries.into_iter().map(|(name, _)| (name, Value::String("***".to_string()))).collect())
+                }
+                other => sanitize(other),
+            };
+            (key, value)
+        }).collect()),
+        Value::Array(items) => Value::Array(items.into_iter().map(sanitize).collect()),
+        Value::String(text) => Value::String(sanitize_text(&text)),
+        other => other,
+    }
+}
+
+// Mask the password of URLs, also inside longer strings such as FFmpeg arguments
+fn sanitize_text(text: &str) -> String {
+    if !text.contains("://") {
+        return text.to_string();
+    }
+    text.split(' ')
+        .map(|word| crate::audit::redact_url(word.to_string()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
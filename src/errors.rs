@@ -59,6 +59,9 @@ pub enum StreamError {
     #[error("Not found: {message}")]
     NotFound { message: String },
 
+    #[error("Locked: {message}")]
+    Locked { message: String },
+
     #[error("Internal error: {message}")]
     Internal { message: String },
 }
@@ -96,6 +99,10 @@ impl StreamError {
         Self::NotFound { message: message.into() }
     }
 
+    pub fn locked(message: impl Into<String>) -> Self {
+        Self::Locked { message: message.into() }
+    }
+
     pub fn internal(message: impl Into<String>) -> Self {
         Self::Internal { message: message.into() }
     }
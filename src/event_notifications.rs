@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+use base64::{engine::general_purpose::STANDARD as B64, Engine as _};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::time::{interval, Duration};
+use tracing::{debug, warn};
+
+use crate::config::{EventNotificationConfig, EventSnapshotMode};
+use crate::AppState;
+
+// How often cameras are checked for going offline and coming back
+const OFFLINE_CHECK_INTERVAL_SECS: u64 = 5;
+const WEBHOOK_TIMEOUT_SECS: u64 = 10;
+
+lazy_static::lazy_static! {
+    static ref HTTP_CLIENT: reqwest::Client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(WEBHOOK_TIMEOUT_SECS))
+        .build()
+        .unwrap_or_default();
+}
+
+/// Payload of the webhooks and the MQTT `camera_event` message
+#[derive(Debug, Clone, Serialize)]
+pub struct CameraEvent {
+    pub camera_id: String,
+    /// `trigger`, `motion`, `offline` or `online`
+    pub event: String,
+    /// Trigger id or metadata label
+    pub label: String,
+    pub timestamp: DateTime<Utc>,
+    pub data: serde_json::Value,
+    /// Time of the attached frame (the last frame received for `offline`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snapshot_at: Option<DateTime<Utc>>,
+    /// Base64 encoded JPEG
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<String>,
+    /// MQTT topic the JPEG was published to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snapshot_topic: Option<String>,
+}
+
+/// Send an event of a camera to its webhooks and MQTT, if the camera has notifications for it.
+/// Delivery runs in the background and never delays the caller.
+pub async fn notify(state: &AppState, camera_id: &str, event: &str, label: &str, data: serde_json::Value) {
+    let config = state.camera_configs.read().await
+        .get(camera_id)
+        .and_then(|c| c.event_notifications.clone());
+    let Some(config) = config.filter(|c| c.wants(event)) else { return };
+
+    let stream = state.camera_streams.read().await
+        .get(camera_id)
+        .map(|info| (info.latest_frame.clone(), info.pipeline_control.last_frame_at()));
+    let (frame, snapshot_at) = match stream {
+        Some((latest_frame, last_frame_at)) if config.snapshot != EventSnapshotMode::None => {
+            let frame = latest_frame.read().await.clone();
+            let snapshot_at = frame.as_ref().map(|_| last_frame_at);
+            (frame, snapshot_at)
+        }
+        _ => (None, None),
+    };
+
+    let mut message = CameraEvent {
+        camera_id: camera_id.to_string(),
+        event: event.to_string(),
+        label: label.to_string(),
+        timestamp: Utc::now(),
+        data,
+        snapshot_at,
+        snapshot: None,
+        snapshot_topic: None,
+    };
+    let mqtt = state.mqtt_handle.clone();
+    tokio::spawn(async move {
+        match (config.snapshot, frame) {
+            (EventSnapshotMode::Base64, Some(frame)) => message.snapshot = Some(B64.encode(&frame)),
+            (EventSnapshotMode::Mqtt, Some(frame)) => match mqtt {
+                Some(ref mqtt) => match mqtt.publish_event_image(&message.camera_id, &message.event, &frame).await {
+                    Ok(topic) => message.snapshot_topic = topic,
+                    Err(e) => warn!("Failed to publish {} event snapshot of camera '{}': {}", message.event, message.camera_id, e),
+                },
+                None => debug!("Event snapshot of camera '{}' not published, MQTT is not enabled", message.camera_id),
+            },
+            _ => {}
+        }
+        deliver(&config, mqtt, &message).await;
+    });
+}
+
+async fn deliver(config: &EventNotificationConfig, mqtt: Option<crate::mqtt::MqttHandle>, message: &CameraEvent) {
+    if config.mqtt {
+        if let Some(ref mqtt) = mqtt {
+            if let Err(e) = mqtt.publish_camera_event(&message.camera_id, &message.event, message).await {
+                warn!("Failed to publish {} event of camera '{}' to MQTT: {}", message.event, message.camera_id, e);
+            }
+        }
+    }
+    for url in &config.webhooks {
+        match HTTP_CLIENT.post(url).json(message).send().await {
+            Ok(response) if response.status().is_success() => {
+                debug!("Posted {} event of camera '{}' to {}", message.event, message.camera_id, url);
+            }
+            Ok(response) => warn!("Webhook {} replied {} to {} event of camera '{}'", url, response.status(), message.event, message.camera_id),
+            Err(e) => warn!("Failed to post {} event of camera '{}' to {}: {}", message.event, message.camera_id, url, e),
+        }
+    }
+}
+
+/// Start the task reporting cameras that stop delivering frames (`offline`) and recover (`online`)
+pub fn start_monitor(state: AppState) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = interval(Duration::from_secs(OFFLINE_CHECK_INTERVAL_SECS));
+        // Camera id -> time of the last frame before it went offline
+        let mut offline: HashMap<String, DateTime<Utc>> = HashMap::new();
+        loop {
+            interval.tick().await;
+            let cameras: Vec<_> = {
+                let camera_configs = state.camera_configs.read().await;
+                let camera_streams = state.camera_streams.read().await;
+                camera_streams.values()
+                    .filter_map(|info| {
+                        let config = camera_configs.get(&info.camera_id)?.event_notifications.clone()?;
+                        (config.wants("offline") || config.wants("online"))
+                            .then(|| (info.camera_id.clone(), info.pipeline_control.clone(), config.offline_after_secs))
+                    })
+                    .collect()
+            };
+            offline.retain(|camera_id, _| cameras.iter().any(|(id, _, _)| id == camera_id));
+
+            for (camera_id, pipeline, offline_after_secs) in cameras {
+                // FFmpeg is stopped on purpose while in standby
+                if pipeline.is_standby() {
+                    continue;
+                }
+                let silent_secs = pipeline.secs_since_last_frame();
+                if silent_secs >= offline_after_secs {
+                    if !offline.contains_key(&camera_id) {
+                        let last_frame_at = pipeline.last_frame_at();
+                        offline.insert(camera_id.clone(), last_frame_at);
+                        notify(&state, &camera_id, "offline", "no frames", serde_json::json!({
+                            "last_frame_at": last_frame_at,
+                            "silent_secs": silent_secs,
+                        })).await;
+                    }
+                } else if let Some(last_frame_at) = offline.remove(&camera_id) {
+                    notify(&state, &camera_id, "online", "frames resumed", serde_json::json!({
+                        "offline_secs": (Utc::now() - last_frame_at).num_seconds(),
+                    })).await;
+                }
+            }
+        }
+    })
+}
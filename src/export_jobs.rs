@@ -4,9 +4,10 @@ use tokio::sync::RwLock;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use crate::errors::{StreamError, Result};
-use crate::database::DatabaseProvider;
+use crate::config::ExportRedactionConfig;
+use crate::database::{DatabaseProvider, SessionAttachment};
 use std::fs;
 use tokio::process::Command;
 use tracing::{info, error, warn, debug};
@@ -35,10 +36,18 @@ pub struct ExportJob {
     pub file_size_bytes: Option<i64>,
     pub error_message: Option<String>,
     pub progress_percent: u8,
+    /// Attachments of the exported sessions, downloaded separately from the MP4
+    #[serde(default)]
+    pub attachments: Vec<SessionAttachment>,
+    /// Faces/plates are blurred in the exported file, stored recordings are unchanged
+    #[serde(default)]
+    pub redacted: bool,
+    #[serde(skip)]
+    redaction: Option<ExportRedactionConfig>,
 }
 
 impl ExportJob {
-    fn new(camera_id: String, from_time: DateTime<Utc>, to_time: DateTime<Utc>, export_path: &str) -> Self {
+    fn new(camera_id: String, from_time: DateTime<Utc>, to_time: DateTime<Utc>, export_path: &str, redaction: Option<ExportRedactionConfig>) -> Self {
         let job_id = Uuid::new_v4().to_string();
         let output_filename = format!(
             "{}_{}_{}..mp4",
@@ -65,6 +74,9 @@ impl ExportJob {
             file_size_bytes: None,
             error_message: None,
             progress_percent: 0,
+            attachments: Vec::new(),
+            redacted: redaction.is_some(),
+            redaction,
         }
     }
 }
@@ -89,14 +101,15 @@ impl ExportJobManager {
         }
     }
 
-    /// Create a new export job
+    /// Create a new export job, redacted with the given settings if any
     pub async fn create_job(
         &self,
         camera_id: String,
         from_time: DateTime<Utc>,
         to_time: DateTime<Utc>,
+        redaction: Option<ExportRedactionConfig>,
     ) -> String {
-        let job = ExportJob::new(camera_id, from_time, to_time, &self.export_path);
+        let job = ExportJob::new(camera_id, from_time, to_time, &self.export_path, redaction);
         let job_id = job.job_id.clone();
 
         let mut jobs = self.jobs.write().await;
@@ -255,6 +268,17 @@ impl ExportJobManager {
             segments.len()
         );
 
+        match database
+            .list_session_attachments_in_range(&job.camera_id, job.from_time, job.to_time)
+            .await
+        {
+            Ok(attachments) => {
+                self.update_job(&job.job_id, |j| j.attachments = attachments)
+                    .await?;
+            }
+            Err(e) => warn!("[{}] Failed to list attachments for export: {}", job.camera_id, e),
+        }
+
         // Update progress
         self.update_job(&job.job_id, |j| j.progress_percent = 10)
             .await?;
@@ -303,6 +327,13 @@ impl ExportJobManager {
         self.update_job(&job.job_id, |j| j.progress_percent = 20)
             .await?;
 
+        // A redacted export is concatenated to a temp file first, only the redacted copy is kept
+        let unredacted_path = temp_dir.join(format!("unredacted_{}.mp4", job.job_id));
+        let concat_output = match job.redaction {
+            Some(_) => unredacted_path.to_string_lossy().to_string(),
+            None => job.output_path.clone(),
+        };
+
         // Run FFmpeg concat
         let output = Command::new("ffmpeg")
             .args(&[
@@ -315,7 +346,7 @@ impl ExportJobManager {
                 "-c",
                 "copy",
                 "-y",
-                &job.output_path,
+                &concat_output,
             ])
             .output()
             .await
@@ -326,6 +357,16 @@ impl ExportJobManager {
             return Err(StreamError::internal(format!("FFmpeg failed: {}", stderr)));
         }
 
+        if let Some(ref redaction) = job.redaction {
+            self.update_job(&job.job_id, |j| j.progress_percent = 50)
+                .await?;
+            let result = crate::export_redaction::redact(redaction, &job.camera_id, &unredacted_path, Path::new(&job.output_path)).await;
+            if let Err(e) = fs::remove_file(&unredacted_path) {
+                warn!("Failed to remove unredacted export: {}", e);
+            }
+            result?;
+        }
+
         // Update progress
         self.update_job(&job.job_id, |j| j.progress_percent = 90)
             .await?;
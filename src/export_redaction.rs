@@ -0,0 +1,58 @@
+use std::path::Path;
+use std::process::Stdio;
+use tokio::process::Command;
+use tokio::time::{timeout, Duration};
+use tracing::info;
+
+use crate::config::ExportRedactionConfig;
+use crate::errors::{Result, StreamError};
+
+/// Write a redacted copy of the exported MP4 `input` to `output`; `input` is left as it is
+pub async fn redact(config: &ExportRedactionConfig, camera_id: &str, input: &Path, output: &Path) -> Result<()> {
+    let mut command = match (&config.filter, &config.command) {
+        (Some(filter), None) => ffmpeg_command(filter, &config.encoder_args, input, output),
+        (None, Some(program)) => external_command(program, config, camera_id, input, output),
+        (Some(_), Some(_)) => return Err(StreamError::config("Export redaction needs either a filter or a command, not both")),
+        (None, None) => return Err(StreamError::config("Export redaction needs a filter or a command")),
+    };
+    command.stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    info!("[{}] Redacting export {}", camera_id, input.display());
+    let result = timeout(Duration::from_secs(config.timeout_secs.max(1)), command.output()).await
+        .map_err(|_| StreamError::internal(format!("Redaction did not finish within {} seconds", config.timeout_secs)))?
+        .map_err(|e| StreamError::internal(format!("Failed to run redaction: {}", e)))?;
+    if !result.status.success() {
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        return Err(StreamError::internal(format!("Redaction failed ({}): {}", result.status, stderr.trim())));
+    }
+    if !output.exists() {
+        return Err(StreamError::internal("Redaction did not write an output file"));
+    }
+    Ok(())
+}
+
+fn ffmpeg_command(filter: &str, encoder_args: &[String], input: &Path, output: &Path) -> Command {
+    let mut command = Command::new("ffmpeg");
+    command.args(["-hide_banner", "-loglevel", "error", "-i"])
+        .arg(input)
+        .args(["-vf", filter])
+        .args(encoder_args)
+        .args(["-c:a", "copy", "-movflags", "+faststart", "-y"])
+        .arg(output);
+    command
+}
+
+fn external_command(program: &str, config: &ExportRedactionConfig, camera_id: &str, input: &Path, output: &Path) -> Command {
+    let input = input.to_string_lossy();
+    let output = output.to_string_lossy();
+    let mut command = Command::new(program);
+    command.args(config.args.iter().map(|arg| arg.replace("{input}", &input).replace("{output}", &output)))
+        .envs(&config.env)
+        .env("RTSP_CAMERA_ID", camera_id)
+        .env("RTSP_EXPORT_INPUT", input.as_ref())
+        .env("RTSP_EXPORT_OUTPUT", output.as_ref());
+    command
+}
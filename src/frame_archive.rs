@@ -0,0 +1,143 @@
+use bytes::{BufMut, Bytes, BytesMut};
+use chrono::{DateTime, Datelike, Timelike, Utc};
+
+// Entries are stored uncompressed, JPEG data does not get smaller with deflate
+const VERSION_STORED: u16 = 10;
+const VERSION_ZIP64: u16 = 45;
+// General purpose flag bit 11: file names are UTF-8
+const FLAG_UTF8: u16 = 1 << 11;
+const LOCAL_HEADER_SIGNATURE: u32 = 0x04034b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x02014b50;
+const ZIP64_END_SIGNATURE: u32 = 0x06064b50;
+const ZIP64_LOCATOR_SIGNATURE: u32 = 0x07064b50;
+const END_SIGNATURE: u32 = 0x06054b50;
+
+struct CentralEntry {
+    name: String,
+    crc: u32,
+    size: u32,
+    dos_time: u16,
+    dos_date: u16,
+    offset: u64,
+}
+
+/// Writes a ZIP archive of JPEG frames incrementally: every added frame is returned as a
+/// chunk that can be sent right away, only the central directory entries are kept until
+/// `finish`. Archives with more than 65535 frames or over 4 GB use the ZIP64 extensions.
+#[derive(Default)]
+pub struct FrameArchive {
+    entries: Vec<CentralEntry>,
+    offset: u64,
+}
+
+impl FrameArchive {
+    /// File name of the `index`th exported frame, e.g. `000001_20240101T120000.040Z.jpg`
+    pub fn frame_name(index: usize, timestamp: DateTime<Utc>, extension: &str) -> String {
+        format!("{:06}_{}.{}", index, timestamp.format("%Y%m%dT%H%M%S%.3fZ"), extension)
+    }
+
+    /// Local file header followed by the frame data
+    pub fn add_frame(&mut self, name: String, timestamp: DateTime<Utc>, data: &[u8]) -> Bytes {
+        let crc = crc32fast::hash(data);
+        let size = data.len() as u32;
+        let (dos_time, dos_date) = dos_date_time(timestamp);
+
+        let mut chunk = BytesMut::with_capacity(30 + name.len() + data.len());
+        chunk.put_u32_le(LOCAL_HEADER_SIGNATURE);
+        chunk.put_u16_le(VERSION_STORED);
+        chunk.put_u16_le(FLAG_UTF8);
+        chunk.put_u16_le(0); // stored
+        chunk.put_u16_le(dos_time);
+        chunk.put_u16_le(dos_date);
+        chunk.put_u32_le(crc);
+        chunk.put_u32_le(size); // compressed size
+        chunk.put_u32_le(size); // uncompressed size
+        chunk.put_u16_le(name.len() as u16);
+        chunk.put_u16_le(0); // extra field length
+        chunk.put_slice(name.as_bytes());
+        chunk.put_slice(data);
+
+        self.entries.push(CentralEntry { name, crc, size, dos_time, dos_date, offset: self.offset });
+        self.offset += chunk.len() as u64;
+        chunk.freeze()
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Central directory and end records that complete the archive
+    pub fn finish(self) -> Bytes {
+        let mut chunk = BytesMut::new();
+        let directory_offset = self.offset;
+        for entry in &self.entries {
+            let zip64_offset = entry.offset >= u32::MAX as u64;
+            chunk.put_u32_le(CENTRAL_HEADER_SIGNATURE);
+            chunk.put_u16_le(if zip64_offset { VERSION_ZIP64 } else { VERSION_STORED }); // version made by
+            chunk.put_u16_le(if zip64_offset { VERSION_ZIP64 } else { VERSION_STORED }); // version needed
+            chunk.put_u16_le(FLAG_UTF8);
+            chunk.put_u16_le(0);
+            chunk.put_u16_le(entry.dos_time);
+            chunk.put_u16_le(entry.dos_date);
+            chunk.put_u32_le(entry.crc);
+            chunk.put_u32_le(entry.size);
+            chunk.put_u32_le(entry.size);
+            chunk.put_u16_le(entry.name.len() as u16);
+            chunk.put_u16_le(if zip64_offset { 12 } else { 0 }); // extra field length
+            chunk.put_u16_le(0); // comment length
+            chunk.put_u16_le(0); // disk number
+            chunk.put_u16_le(0); // internal attributes
+            chunk.put_u32_le(0); // external attributes
+            chunk.put_u32_le(if zip64_offset { u32::MAX } else { entry.offset as u32 });
+            chunk.put_slice(entry.name.as_bytes());
+            if zip64_offset {
+                // ZIP64 extended information with only the local header offset
+                chunk.put_u16_le(0x0001);
+                chunk.put_u16_le(8);
+                chunk.put_u64_le(entry.offset);
+            }
+        }
+        let directory_size = chunk.len() as u64;
+        let count = self.entries.len() as u64;
+
+        let needs_zip64 = count >= u16::MAX as u64
+            || directory_offset >= u32::MAX as u64
+            || directory_size >= u32::MAX as u64;
+        if needs_zip64 {
+            let zip64_end_offset = directory_offset + directory_size;
+            chunk.put_u32_le(ZIP64_END_SIGNATURE);
+            chunk.put_u64_le(44); // size of the remaining record
+            chunk.put_u16_le(VERSION_ZIP64);
+            chunk.put_u16_le(VERSION_ZIP64);
+            chunk.put_u32_le(0); // this disk
+            chunk.put_u32_le(0); // disk with the central directory
+            chunk.put_u64_le(count);
+            chunk.put_u64_le(count);
+            chunk.put_u64_le(directory_size);
+            chunk.put_u64_le(directory_offset);
+
+            chunk.put_u32_le(ZIP64_LOCATOR_SIGNATURE);
+            chunk.put_u32_le(0);
+            chunk.put_u64_le(zip64_end_offset);
+            chunk.put_u32_le(1); // total number of disks
+        }
+
+        chunk.put_u32_le(END_SIGNATURE);
+        chunk.put_u16_le(0);
+        chunk.put_u16_le(0);
+        chunk.put_u16_le(count.min(u16::MAX as u64) as u16);
+        chunk.put_u16_le(count.min(u16::MAX as u64) as u16);
+        chunk.put_u32_le(directory_size.min(u32::MAX as u64) as u32);
+        chunk.put_u32_le(directory_offset.min(u32::MAX as u64) as u32);
+        chunk.put_u16_le(0); // comment length
+        chunk.freeze()
+    }
+}
+
+// MS-DOS time and date of a timestamp (2 second resolution, years 1980-2107)
+fn dos_date_time(timestamp: DateTime<Utc>) -> (u16, u16) {
+    let year = timestamp.year().clamp(1980, 2107) as u16;
+    let time = ((timestamp.hour() as u16) << 11) | ((timestamp.minute() as u16) << 5) | (timestamp.second() as u16 / 2);
+    let date = ((year - 1980) << 9) | ((timestamp.month() as u16) << 5) | timestamp.day() as u16;
+    (time, date)
+}
@@ -0,0 +1,43 @@
+use chrono::{DateTime, Utc};
+use tracing::warn;
+
+// Every zstd frame starts with this magic number, JPEG frames start with FF D8
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+
+/// Compress a frame for storage. Frames that do not get smaller are stored as they are.
+pub fn compress(frame: &[u8], level: i32) -> Vec<u8> {
+    match zstd::bulk::compress(frame, level) {
+        Ok(compressed) if compressed.len() < frame.len() => compressed,
+        Ok(_) => frame.to_vec(),
+        Err(e) => {
+            warn!("Failed to compress frame, storing it uncompressed: {}", e);
+            frame.to_vec()
+        }
+    }
+}
+
+/// Compress a batch of (timestamp, frame_number, frame_data) on the blocking thread pool
+pub async fn compress_batch(batch: &[(DateTime<Utc>, i64, Vec<u8>)], level: i32) -> Vec<(DateTime<Utc>, i64, Vec<u8>)> {
+    let frames = batch.to_vec();
+    tokio::task::spawn_blocking(move || {
+        frames.into_iter()
+            .map(|(timestamp, frame_number, data)| (timestamp, frame_number, compress(&data, level)))
+            .collect()
+    })
+    .await
+    .unwrap_or_else(|_| batch.to_vec())
+}
+
+/// Stored frame data as it was captured, whether or not it was compressed when written
+pub fn decompress(data: Vec<u8>) -> Vec<u8> {
+    if !data.starts_with(&ZSTD_MAGIC) {
+        return data;
+    }
+    match zstd::stream::decode_all(data.as_slice()) {
+        Ok(frame) => frame,
+        Err(e) => {
+            warn!("Failed to decompress stored frame: {}", e);
+            data
+        }
+    }
+}
@@ -0,0 +1,344 @@
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, AtomicUsize, Ordering};
+use chrono::{DateTime, Utc};
+use tokio::sync::Mutex;
+use tracing::{info, warn, error, debug};
+
+use crate::errors::{Result, StreamError};
+
+/// A frame that could not be queued to the database writer in time
+#[derive(Debug, Clone)]
+pub struct SpilledFrame {
+    pub session_id: i64,
+    pub timestamp: DateTime<Utc>,
+    pub frame_number: i64,
+    pub data: Vec<u8>,
+}
+
+/// Queue metrics for the recording frame pipeline of one camera
+#[derive(Debug, Default)]
+pub struct FrameQueueStats {
+    pub queue_depth: AtomicUsize,
+    pub queue_capacity: AtomicUsize,
+    pub max_queue_depth: AtomicUsize,
+    pub spilled_frames: AtomicU64,
+    pub replayed_frames: AtomicU64,
+    pub dropped_frames: AtomicU64,
+    pub insert_batches: AtomicU64,
+    pub inserted_frames: AtomicU64,
+    pub failed_inserts: AtomicU64,
+    pub last_insert_us: AtomicU64,
+    pub max_insert_us: AtomicU64,
+    pub total_insert_us: AtomicU64,
+    /// Whether the database writer task of a recording is running
+    pub writer_running: AtomicBool,
+    /// Unix milliseconds of the last successful resp. failed insert, 0 before the first
+    pub last_insert_at: AtomicI64,
+    pub last_failure_at: AtomicI64,
+    pub last_error: std::sync::Mutex<Option<String>>,
+}
+
+impl FrameQueueStats {
+    pub fn update_depth(&self, depth: usize, capacity: usize) {
+        self.queue_depth.store(depth, Ordering::Relaxed);
+        self.queue_capacity.store(capacity, Ordering::Relaxed);
+        self.max_queue_depth.fetch_max(depth, Ordering::Relaxed);
+    }
+
+    /// Record the duration of one bulk insert of the database writer
+    pub fn record_insert(&self, elapsed: std::time::Duration, frames: usize, error: Option<String>) {
+        let micros = elapsed.as_micros() as u64;
+        self.last_insert_us.store(micros, Ordering::Relaxed);
+        self.max_insert_us.fetch_max(micros, Ordering::Relaxed);
+        self.total_insert_us.fetch_add(micros, Ordering::Relaxed);
+        self.insert_batches.fetch_add(1, Ordering::Relaxed);
+        let now = Utc::now().timestamp_millis();
+        match error {
+            None => {
+                self.inserted_frames.fetch_add(frames as u64, Ordering::Relaxed);
+                self.last_insert_at.store(now, Ordering::Relaxed);
+            }
+            Some(error) => {
+                self.failed_inserts.fetch_add(1, Ordering::Relaxed);
+                self.last_failure_at.store(now, Ordering::Relaxed);
+                *self.last_error.lock().unwrap_or_else(|e| e.into_inner()) = Some(error);
+            }
+        }
+    }
+
+    /// Writer health: "idle" without writer task, "failing" when the last insert failed,
+    /// "backlogged" while the queue is over 80% full or spilled batches wait for replay
+    pub fn writer_health(&self, spill_files_pending: usize) -> &'static str {
+        let capacity = self.queue_capacity.load(Ordering::Relaxed);
+        if !self.writer_running.load(Ordering::Relaxed) {
+            "idle"
+        } else if self.last_failure_at.load(Ordering::Relaxed) > self.last_insert_at.load(Ordering::Relaxed) {
+            "failing"
+        } else if spill_files_pending > 0 || (capacity > 0 && self.queue_depth.load(Ordering::Relaxed) * 5 >= capacity * 4) {
+            "backlogged"
+        } else {
+            "ok"
+        }
+    }
+}
+
+// Failed replays of a spill file before it is moved aside as `.failed`
+const MAX_REPLAY_ATTEMPTS: u32 = 5;
+
+/// Spill file taken off the queue for replay
+pub struct SpillBatch {
+    pub path: PathBuf,
+    pub frames: Vec<SpilledFrame>,
+    attempts: u32,
+}
+
+/// Per-camera overflow storage for the recording writer.
+///
+/// When the database writer channel is full, batches of frames are written to
+/// temporary files in `directory` and replayed by the writer once it has drained
+/// its channel again. Files left over from a previous run are picked up on creation.
+pub struct FrameSpillQueue {
+    camera_id: String,
+    directory: PathBuf,
+    enabled: bool,
+    max_bytes: u64,
+    files: Mutex<VecDeque<(PathBuf, u64, u32)>>, // path, size, failed replays
+    pending_bytes: AtomicU64,
+    next_seq: AtomicU64,
+    pub stats: FrameQueueStats,
+}
+
+impl FrameSpillQueue {
+    pub fn new(camera_id: &str, directory: PathBuf, enabled: bool, max_bytes: u64) -> Self {
+        let mut existing: Vec<(PathBuf, u64)> = Vec::new();
+        if let Ok(entries) = std::fs::read_dir(&directory) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().and_then(|e| e.to_str()) == Some("spill") {
+                    let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+                    existing.push((path, size));
+                }
+            }
+        }
+        existing.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let pending_bytes: u64 = existing.iter().map(|(_, size)| size).sum();
+        if !existing.is_empty() {
+            info!("Found {} spilled frame batches ({} KB) for camera '{}', will replay into database",
+                  existing.len(), pending_bytes / 1024, camera_id);
+        }
+
+        Self {
+            camera_id: camera_id.to_string(),
+            directory,
+            enabled,
+            max_bytes,
+            files: Mutex::new(existing.into_iter().map(|(path, size)| (path, size, 0)).collect()),
+            pending_bytes: AtomicU64::new(pending_bytes),
+            next_seq: AtomicU64::new(0),
+            stats: FrameQueueStats::default(),
+        }
+    }
+
+    /// Write a batch of frames to a new spill file.
+    /// Returns false if the batch was discarded because spilling is disabled or the size limit is reached.
+    pub async fn spill(&self, frames: &[SpilledFrame]) -> Result<bool> {
+        if frames.is_empty() {
+            return Ok(true);
+        }
+
+        if !self.enabled {
+            self.stats.dropped_frames.fetch_add(frames.len() as u64, Ordering::Relaxed);
+            warn!("Frame writer can't keep up for camera '{}' and spilling is disabled, dropping {} frames",
+                  self.camera_id, frames.len());
+            return Ok(false);
+        }
+
+        let encoded = encode_frames(frames);
+        if self.pending_bytes.load(Ordering::Relaxed) + encoded.len() as u64 > self.max_bytes {
+            self.stats.dropped_frames.fetch_add(frames.len() as u64, Ordering::Relaxed);
+            warn!("Frame spill limit ({} MB) reached for camera '{}', dropping {} frames",
+                  self.max_bytes / (1024 * 1024), self.camera_id, frames.len());
+            return Ok(false);
+        }
+
+        tokio::fs::create_dir_all(&self.directory).await?;
+
+        let seq = self.next_seq.fetch_add(1, Ordering::Relaxed);
+        let file_name = format!("{:020}-{:06}.spill", frames[0].timestamp.timestamp_micros(), seq);
+        let path = self.directory.join(file_name);
+        let tmp_path = path.with_extension("tmp");
+
+        // Write to a temp file first so a crash never leaves a truncated batch behind
+        tokio::fs::write(&tmp_path, &encoded).await?;
+        tokio::fs::rename(&tmp_path, &path).await?;
+
+        let size = encoded.len() as u64;
+        self.pending_bytes.fetch_add(size, Ordering::Relaxed);
+        self.stats.spilled_frames.fetch_add(frames.len() as u64, Ordering::Relaxed);
+        self.files.lock().await.push_back((path, size, 0));
+
+        debug!("Spilled {} frames ({} KB) to disk for camera '{}'", frames.len(), size / 1024, self.camera_id);
+        Ok(true)
+    }
+
+    /// Take the oldest spill file off the queue and load its frames.
+    /// The file stays on disk until `complete` or `requeue` is called for it.
+    pub async fn take_next(&self) -> Option<SpillBatch> {
+        loop {
+            let (path, size, attempts) = self.files.lock().await.pop_front()?;
+            match tokio::fs::read(&path).await {
+                Ok(bytes) => match decode_frames(&bytes) {
+                    Ok(frames) => return Some(SpillBatch { path, frames, attempts }),
+                    Err(e) => {
+                        error!("Discarding corrupt spill file {:?} for camera '{}': {}", path, self.camera_id, e);
+                        self.remove_file(&path, size).await;
+                    }
+                },
+                Err(e) => {
+                    error!("Failed to read spill file {:?} for camera '{}': {}", path, self.camera_id, e);
+                    self.pending_bytes.fetch_sub(size.min(self.pending_bytes.load(Ordering::Relaxed)), Ordering::Relaxed);
+                }
+            }
+        }
+    }
+
+    /// Remove a replayed spill file from disk
+    pub async fn complete(&self, batch: SpillBatch) {
+        let size = tokio::fs::metadata(&batch.path).await.map(|m| m.len()).unwrap_or(0);
+        self.remove_file(&batch.path, size).await;
+        self.stats.replayed_frames.fetch_add(batch.frames.len() as u64, Ordering::Relaxed);
+    }
+
+    /// Put a spill file back at the front of the queue after a failed replay. Only `remaining`,
+    /// the frames not inserted yet, are kept. After `MAX_REPLAY_ATTEMPTS` failures the file is
+    /// renamed to `.failed` so it no longer blocks the batches behind it.
+    pub async fn requeue(&self, batch: SpillBatch, remaining: &[SpilledFrame]) {
+        let size = tokio::fs::metadata(&batch.path).await.map(|m| m.len()).unwrap_or(0);
+        let replayed = batch.frames.len() - remaining.len();
+        if replayed > 0 {
+            self.stats.replayed_frames.fetch_add(replayed as u64, Ordering::Relaxed);
+        }
+
+        let attempts = batch.attempts + 1;
+        if attempts >= MAX_REPLAY_ATTEMPTS {
+            let failed_path = batch.path.with_extension("failed");
+            error!("Giving up on {} spilled frames of camera '{}' after {} failed replays, moved to {:?}",
+                   remaining.len(), self.camera_id, attempts, failed_path);
+            if let Err(e) = tokio::fs::rename(&batch.path, &failed_path).await {
+                warn!("Failed to move spill file {:?} aside: {}", batch.path, e);
+            }
+            self.stats.dropped_frames.fetch_add(remaining.len() as u64, Ordering::Relaxed);
+            let current = self.pending_bytes.load(Ordering::Relaxed);
+            self.pending_bytes.fetch_sub(size.min(current), Ordering::Relaxed);
+            return;
+        }
+
+        let mut new_size = size;
+        if replayed > 0 {
+            // Rewrite the file without the frames already in the database
+            let encoded = encode_frames(remaining);
+            let tmp_path = batch.path.with_extension("tmp");
+            let rewritten = async {
+                tokio::fs::write(&tmp_path, &encoded).await?;
+                tokio::fs::rename(&tmp_path, &batch.path).await
+            }.await;
+            match rewritten {
+                Ok(()) => {
+                    new_size = encoded.len() as u64;
+                    self.pending_bytes.fetch_sub(size.saturating_sub(new_size).min(self.pending_bytes.load(Ordering::Relaxed)), Ordering::Relaxed);
+                }
+                Err(e) => warn!("Failed to rewrite spill file {:?} for camera '{}': {}", batch.path, self.camera_id, e),
+            }
+        }
+        self.files.lock().await.push_front((batch.path, new_size, attempts));
+    }
+
+    pub async fn pending_files(&self) -> usize {
+        self.files.lock().await.len()
+    }
+
+    pub fn pending_bytes(&self) -> u64 {
+        self.pending_bytes.load(Ordering::Relaxed)
+    }
+
+    /// Snapshot of queue metrics for the API
+    pub async fn stats_json(&self) -> serde_json::Value {
+        let batches = self.stats.insert_batches.load(Ordering::Relaxed);
+        let total_insert_us = self.stats.total_insert_us.load(Ordering::Relaxed);
+        serde_json::json!({
+            "queue_depth": self.stats.queue_depth.load(Ordering::Relaxed),
+            "queue_capacity": self.stats.queue_capacity.load(Ordering::Relaxed),
+            "max_queue_depth": self.stats.max_queue_depth.load(Ordering::Relaxed),
+            "spilled_frames": self.stats.spilled_frames.load(Ordering::Relaxed),
+            "replayed_frames": self.stats.replayed_frames.load(Ordering::Relaxed),
+            "dropped_frames": self.stats.dropped_frames.load(Ordering::Relaxed),
+            "spill_files_pending": self.pending_files().await,
+            "spill_bytes_pending": self.pending_bytes(),
+            "insert_batches": batches,
+            "inserted_frames": self.stats.inserted_frames.load(Ordering::Relaxed),
+            "failed_inserts": self.stats.failed_inserts.load(Ordering::Relaxed),
+            "last_insert_ms": self.stats.last_insert_us.load(Ordering::Relaxed) as f64 / 1000.0,
+            "avg_insert_ms": if batches > 0 { total_insert_us as f64 / batches as f64 / 1000.0 } else { 0.0 },
+            "max_insert_ms": self.stats.max_insert_us.load(Ordering::Relaxed) as f64 / 1000.0,
+        })
+    }
+
+    async fn remove_file(&self, path: &PathBuf, size: u64) {
+        if let Err(e) = tokio::fs::remove_file(path).await {
+            warn!("Failed to remove spill file {:?}: {}", path, e);
+        }
+        let current = self.pending_bytes.load(Ordering::Relaxed);
+        self.pending_bytes.fetch_sub(size.min(current), Ordering::Relaxed);
+    }
+}
+
+// Record layout (little endian): session_id i64, timestamp_micros i64, frame_number i64, length u32, data
+fn encode_frames(frames: &[SpilledFrame]) -> Vec<u8> {
+    let total: usize = frames.iter().map(|f| 28 + f.data.len()).sum();
+    let mut buf = Vec::with_capacity(total);
+    for frame in frames {
+        buf.extend_from_slice(&frame.session_id.to_le_bytes());
+        buf.extend_from_slice(&frame.timestamp.timestamp_micros().to_le_bytes());
+        buf.extend_from_slice(&frame.frame_number.to_le_bytes());
+        buf.extend_from_slice(&(frame.data.len() as u32).to_le_bytes());
+        buf.extend_from_slice(&frame.data);
+    }
+    buf
+}
+
+fn decode_frames(bytes: &[u8]) -> Result<Vec<SpilledFrame>> {
+    let mut frames = Vec::new();
+    let mut pos = 0usize;
+    let read_i64 = |pos: usize| -> i64 {
+        i64::from_le_bytes(bytes[pos..pos + 8].try_into().unwrap())
+    };
+
+    while pos < bytes.len() {
+        if bytes.len() - pos < 28 {
+            return Err(StreamError::internal("Truncated spill record header"));
+        }
+        let session_id = read_i64(pos);
+        let timestamp_micros = read_i64(pos + 8);
+        let frame_number = read_i64(pos + 16);
+        let len = u32::from_le_bytes(bytes[pos + 24..pos + 28].try_into().unwrap()) as usize;
+        pos += 28;
+
+        if bytes.len() - pos < len {
+            return Err(StreamError::internal("Truncated spill record data"));
+        }
+        let timestamp = DateTime::from_timestamp_micros(timestamp_micros)
+            .ok_or_else(|| StreamError::internal("Invalid spill record timestamp"))?;
+
+        frames.push(SpilledFrame {
+            session_id,
+            timestamp,
+            frame_number,
+            data: bytes[pos..pos + len].to_vec(),
+        });
+        pos += len;
+    }
+
+    Ok(frames)
+}
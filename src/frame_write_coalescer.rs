@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use sqlx::postgres::PgPoolOptions;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::{timeout_at, Duration, Instant};
+use tracing::{debug, info, warn};
+
+use crate::errors::{Result, StreamError};
+
+lazy_static::lazy_static! {
+    // One coalescer per shared database, used by the databases of all its cameras
+    static ref COALESCERS: Mutex<HashMap<String, Arc<FrameWriteCoalescer>>> = Mutex::new(HashMap::new());
+}
+
+struct WriteRequest {
+    session_id: i64,
+    camera_id: String,
+    frames: Vec<(DateTime<Utc>, Vec<u8>)>,
+    done: oneshot::Sender<Result<u64>>,
+}
+
+/// Group commit of frame inserts: batches of all cameras arriving within `max_latency` are
+/// written with one INSERT, so the shared database flushes its WAL once per group instead
+/// of once per camera batch.
+pub struct FrameWriteCoalescer {
+    sender: mpsc::UnboundedSender<WriteRequest>,
+}
+
+impl FrameWriteCoalescer {
+    /// Coalescer of the database at `url`, connected and started on first use
+    pub async fn shared(url: &str, table: &'static str, max_latency: Duration, max_frames: usize) -> Result<Arc<Self>> {
+        if let Some(coalescer) = COALESCERS.lock().unwrap_or_else(|e| e.into_inner()).get(url) {
+            return Ok(coalescer.clone());
+        }
+        let pool = PgPoolOptions::new()
+            .max_connections(1)
+            .connect(url)
+            .await?;
+
+        let mut coalescers = COALESCERS.lock().unwrap_or_else(|e| e.into_inner());
+        // Another camera may have connected in the meantime
+        if let Some(coalescer) = coalescers.get(url) {
+            return Ok(coalescer.clone());
+        }
+        let (sender, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(run(pool, table, max_latency, max_frames.max(1), receiver));
+        info!("Frame inserts to the shared PostgreSQL database are grouped every {} ms (at most {} frames)",
+              max_latency.as_millis(), max_frames);
+        let coalescer = Arc::new(Self { sender });
+        coalescers.insert(url.to_string(), coalescer.clone());
+        Ok(coalescer)
+    }
+
+    /// Insert frames with the next group commit, returns the number of stored frames once committed
+    pub async fn insert(&self, session_id: i64, camera_id: &str, frames: &[(DateTime<Utc>, i64, Vec<u8>)]) -> Result<u64> {
+        let (done, result) = oneshot::channel();
+        let request = WriteRequest {
+            session_id,
+            camera_id: camera_id.to_string(),
+            frames: frames.iter().map(|(timestamp, _, data)| (*timestamp, data.clone())).collect(),
+            done,
+        };
+        self.sender.send(request).map_err(|_| StreamError::database("Frame write coalescer is not running"))?;
+        result.await.map_err(|_| StreamError::database("Frame write coalescer stopped"))?
+    }
+}
+
+async fn run(
+    pool: PgPool,
+    table: &'static str,
+    max_latency: Duration,
+    max_frames: usize,
+    mut receiver: mpsc::UnboundedReceiver<WriteRequest>,
+) {
+    while let Some(first) = receiver.recv().await {
+        // The first batch waits at most `max_latency` for others to join
+        let deadline = Instant::now() + max_latency;
+        let mut frame_count = first.frames.len();
+        let mut group = vec![first];
+        while frame_count < max_frames {
+            match timeout_at(deadline, receiver.recv()).await {
+                Ok(Some(request)) => {
+                    frame_count += request.frames.len();
+                    group.push(request);
+                }
+                Ok(None) | Err(_) => break,
+            }
+        }
+        commit(&pool, table, group, frame_count).await;
+    }
+}
+
+async fn commit(pool: &PgPool, table: &str, group: Vec<WriteRequest>, frame_count: usize) {
+    let start = std::time::Instant::now();
+    let mut session_ids = Vec::with_capacity(frame_count);
+    let mut camera_ids = Vec::with_capacity(frame_count);
+    let mut timestamps = Vec::with_capacity(frame_count);
+    let mut frame_data = Vec::with_capacity(frame_count);
+    for request in &group {
+        for (timestamp, data) in &request.frames {
+            session_ids.push(request.session_id);
+            camera_ids.push(request.camera_id.as_str());
+            timestamps.push(*timestamp);
+            frame_data.push(data.as_slice());
+        }
+    }
+
+    let query = format!(
+        "INSERT INTO {} (session_id, camera_id, timestamp, frame_data) SELECT * FROM UNNEST($1::bigint[], $2::text[], $3::timestamptz[], $4::bytea[])",
+        table
+    );
+    let result = sqlx::query(&query)
+        .bind(&session_ids)
+        .bind(&camera_ids)
+        .bind(&timestamps)
+        .bind(&frame_data)
+        .execute(pool)
+        .await;
+
+    match result {
+        Ok(_) => {
+            debug!("Group commit of {} frames from {} batches in {:.3}ms", frame_count, group.len(), start.elapsed().as_secs_f64() * 1000.0);
+            for request in group {
+                let _ = request.done.send(Ok(request.frames.len() as u64));
+            }
+        }
+        // A single batch (e.g. of a deleted session) must not fail the frames of the other cameras
+        Err(e) if group.len() > 1 => {
+            warn!("Group commit of {} frames from {} batches failed, inserting them one by one: {}", frame_count, group.len(), e);
+            for request in group {
+                let frames = request.frames.len();
+                let timestamps: Vec<DateTime<Utc>> = request.frames.iter().map(|(timestamp, _)| *timestamp).collect();
+                let frame_data: Vec<&[u8]> = request.frames.iter().map(|(_, data)| data.as_slice()).collect();
+                let query = format!(
+                    "INSERT INTO {} (session_id, camera_id, timestamp, frame_data) SELECT $1, $2, * FROM UNNEST($3::timestamptz[], $4::bytea[])",
+                    table
+                );
+                let result = sqlx::query(&query)
+                    .bind(request.session_id)
+                    .bind(&request.camera_id)
+                    .bind(&timestamps)
+                    .bind(&frame_data)
+                    .execute(pool)
+                    .await
+                    .map(|_| frames as u64)
+                    .map_err(StreamError::from);
+                let _ = request.done.send(result);
+            }
+        }
+        Err(e) => {
+            if let Some(request) = group.into_iter().next() {
+                let _ = request.done.send(Err(e.into()));
+            }
+        }
+    }
+}
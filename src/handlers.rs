@@ -5,7 +5,8 @@ use axum::extract::{State, Query};
 use tracing::trace;
 
 use crate::{config, AppState};
-use crate::websocket_handler::websocket_handler;
+use crate::websocket_handler::{websocket_handler, LiveStreamContext};
+use crate::live_socket::LiveUpgrade;
 use crate::websocket_control::handle_control_websocket;
 use crate::recording::RecordingManager;
 use crate::mqtt::MqttHandle;
@@ -81,7 +82,7 @@ async fn serve_test_with_mode(is_full_mode: bool) -> axum::response::Html<String
 
 // Dynamic handlers that check current state instead of using captured state
 pub async fn dynamic_camera_stream_handler(
-    ws: Option<axum::extract::WebSocketUpgrade>,
+    ws: Option<LiveUpgrade>,
     query: Query<std::collections::HashMap<String, String>>,
     addr: Option<axum::extract::ConnectInfo<std::net::SocketAddr>>,
     camera_id: String,
@@ -92,13 +93,7 @@ pub async fn dynamic_camera_stream_handler(
         let stream_info = stream_info.clone();
         drop(camera_streams);
         
-        camera_stream_handler(
-            ws, query, addr,
-            stream_info.frame_sender,
-            stream_info.camera_id,
-            stream_info.mqtt_handle,
-            stream_info.camera_config,
-        ).await
+        camera_stream_handler(ws, query, addr, LiveStreamContext::from(stream_info)).await
     } else {
         (axum::http::StatusCode::NOT_FOUND, "Camera not found").into_response()
     }
@@ -131,7 +126,7 @@ pub async fn dynamic_camera_control_handler(
 }
 
 pub async fn dynamic_camera_live_handler(
-    ws: Option<axum::extract::WebSocketUpgrade>,
+    ws: Option<LiveUpgrade>,
     query: Query<std::collections::HashMap<String, String>>,
     addr: Option<axum::extract::ConnectInfo<std::net::SocketAddr>>,
     camera_id: String,
@@ -142,13 +137,16 @@ pub async fn dynamic_camera_live_handler(
         let stream_info = stream_info.clone();
         drop(camera_streams);
         
-        camera_live_handler(
-            ws, query, addr,
-            stream_info.frame_sender,
-            stream_info.camera_id,
-            stream_info.mqtt_handle,
-            stream_info.camera_config,
-        ).await
+        if dvr_requested(&query) {
+            return camera_dvr_handler(
+                ws.map(LiveUpgrade::plain), query, addr,
+                stream_info.frame_sender,
+                stream_info.camera_id,
+                stream_info.camera_config,
+                stream_info.pre_recording_buffer,
+            ).await;
+        }
+        camera_live_handler(ws, query, addr, LiveStreamContext::from(stream_info)).await
     } else {
         (axum::http::StatusCode::NOT_FOUND, "Camera not found").into_response()
     }
@@ -156,7 +154,7 @@ pub async fn dynamic_camera_live_handler(
 
 pub async fn dynamic_camera_fallback_handler(
     uri: axum::http::Uri,
-    ws: Option<axum::extract::WebSocketUpgrade>,
+    live_ws: Option<LiveUpgrade>,
     query: axum::extract::Query<std::collections::HashMap<String, String>>,
     addr: Option<axum::extract::ConnectInfo<std::net::SocketAddr>>,
     headers: axum::http::HeaderMap,
@@ -173,24 +171,37 @@ pub async fn dynamic_camera_fallback_handler(
         if let Some(stream_info) = camera_streams.get(&camera_id) {
             let stream_info = stream_info.clone();
             drop(camera_streams);
+
+            // Only the live stream endpoints negotiate permessage-deflate
+            let (ws, live_ws) = match endpoint.as_str() {
+                "stream" => (None, live_ws),
+                "live" if !dvr_requested(&query) => (None, live_ws),
+                _ => (live_ws.map(LiveUpgrade::plain), None),
+            };
             
             match endpoint.as_str() {
                 "stream" => {
-                    camera_stream_handler(
+                    camera_stream_handler(live_ws, query, addr, LiveStreamContext::from(stream_info)).await
+                }
+                "live" if dvr_requested(&query) => {
+                    camera_dvr_handler(
                         ws, query, addr,
                         stream_info.frame_sender,
                         stream_info.camera_id,
-                        stream_info.mqtt_handle,
                         stream_info.camera_config,
+                        stream_info.pre_recording_buffer,
                     ).await
                 }
                 "live" => {
-                    camera_live_handler(
+                    camera_live_handler(live_ws, query, addr, LiveStreamContext::from(stream_info)).await
+                }
+                "roi" => {
+                    camera_roi_handler(
                         ws, query, addr,
                         stream_info.frame_sender,
                         stream_info.camera_id,
-                        stream_info.mqtt_handle,
                         stream_info.camera_config,
+                        stream_info.latest_frame,
                     ).await
                 }
                 "control" => {
@@ -212,6 +223,16 @@ pub async fn dynamic_camera_fallback_handler(
                         stream_info.latest_frame,
                     ).await
                 }
+                "mjpeg" => {
+                    camera_mjpeg_handler(
+                        headers,
+                        query,
+                        stream_info.frame_sender,
+                        stream_info.camera_id,
+                        stream_info.camera_config,
+                        stream_info.latest_frame,
+                    ).await
+                }
                 "test" => {
                     serve_test_page(query).await.into_response()
                 }
@@ -233,25 +254,26 @@ pub async fn dynamic_camera_fallback_handler(
 }
 
 pub async fn camera_live_handler(
-    ws: Option<axum::extract::WebSocketUpgrade>,
+    ws: Option<LiveUpgrade>,
     query: Query<std::collections::HashMap<String, String>>,
     addr: Option<axum::extract::ConnectInfo<std::net::SocketAddr>>,
-    frame_sender: Arc<broadcast::Sender<bytes::Bytes>>,
-    camera_id: String,
-    mqtt_handle: Option<MqttHandle>,
-    camera_config: config::CameraConfig,
+    stream: LiveStreamContext,
 ) -> axum::response::Response {
     use tracing::{trace, info, debug, warn};
+    let (camera_id, camera_config) = (stream.camera_id.clone(), &stream.camera_config);
     
-    let current_connections = frame_sender.receiver_count();
+    let current_connections = stream.frame_sender.receiver_count();
     trace!("Live handler called for camera {} (connections: {}), WS upgrade: {}", 
           camera_id, current_connections, ws.is_some());
     match ws {
         Some(ws_upgrade) => {
-            if let Some(expected_token) = &camera_config.token {
+            if camera_config.requires_token() {
                 if let Some(provided_token) = query.get("token") {
-                    if provided_token == expected_token {
+                    if camera_config.token_grants(provided_token, config::TokenScope::Live) {
                         info!("Token authentication successful for camera {}", camera_id);
+                    } else if camera_config.token_known(provided_token) {
+                        debug!("Token without live scope provided for camera {}", camera_id);
+                        return (axum::http::StatusCode::FORBIDDEN, "Token does not grant 'live' scope").into_response();
                     } else {
                         debug!("Invalid token provided for camera {}", camera_id);
                         return (axum::http::StatusCode::UNAUTHORIZED, "Invalid token").into_response();
@@ -264,12 +286,12 @@ pub async fn camera_live_handler(
             
             if let Some(connect_info) = addr {
                 trace!("Starting live WebSocket handler for camera {} from {}", camera_id, connect_info.0);
-                websocket_handler(ws_upgrade, State(frame_sender), connect_info, camera_id, mqtt_handle, camera_config).await
+                websocket_handler(ws_upgrade, connect_info, stream, query.0.clone()).await
             } else {
                 let fallback_addr = "127.0.0.1:0".parse().unwrap();
                 let connect_info = axum::extract::ConnectInfo(fallback_addr);
                 trace!("Starting live WebSocket handler for camera {} (fallback addr)", camera_id);
-                websocket_handler(ws_upgrade, State(frame_sender), connect_info, camera_id, mqtt_handle, camera_config).await
+                websocket_handler(ws_upgrade, connect_info, stream, query.0.clone()).await
             }
         },
         None => {
@@ -278,23 +300,147 @@ pub async fn camera_live_handler(
     }
 }
 
-pub async fn camera_stream_handler(
+fn dvr_requested(query: &std::collections::HashMap<String, String>) -> bool {
+    query.get("dvr").is_some_and(|v| v == "true" || v == "1")
+}
+
+/// Live WebSocket in DVR mode (`/<camera_path>/live?dvr=true`): pause, rewind and play back the pre-recording buffer
+pub async fn camera_dvr_handler(
+    ws: Option<axum::extract::WebSocketUpgrade>,
+    query: Query<std::collections::HashMap<String, String>>,
+    addr: Option<axum::extract::ConnectInfo<std::net::SocketAddr>>,
+    frame_sender: Arc<broadcast::Sender<bytes::Bytes>>,
+    camera_id: String,
+    camera_config: config::CameraConfig,
+    pre_recording_buffer: Option<crate::pre_recording_buffer::PreRecordingBuffer>,
+) -> axum::response::Response {
+    use tracing::{info, debug, warn};
+
+    let Some(ws_upgrade) = ws else {
+        return (axum::http::StatusCode::BAD_REQUEST, "Live endpoint only accepts WebSocket connections").into_response();
+    };
+    if camera_config.requires_token() {
+        if let Some(provided_token) = query.get("token") {
+            if camera_config.token_grants(provided_token, config::TokenScope::Live) {
+                info!("Token authentication successful for camera {} DVR stream", camera_id);
+            } else if camera_config.token_known(provided_token) {
+                debug!("Token without live scope provided for camera {} DVR stream", camera_id);
+                return (axum::http::StatusCode::FORBIDDEN, "Token does not grant 'live' scope").into_response();
+            } else {
+                debug!("Invalid token provided for camera {} DVR stream", camera_id);
+                return (axum::http::StatusCode::UNAUTHORIZED, "Invalid token").into_response();
+            }
+        } else {
+            warn!("Missing token for camera {} that requires authentication", camera_id);
+            return (axum::http::StatusCode::UNAUTHORIZED, "Missing token").into_response();
+        }
+    }
+    let Some(buffer) = pre_recording_buffer else {
+        return (axum::http::StatusCode::CONFLICT, "DVR mode requires the pre-recording buffer (pre_recording_enabled)").into_response();
+    };
+
+    let client_addr = addr.map_or_else(|| "127.0.0.1:0".parse().unwrap(), |connect_info| connect_info.0);
+    let connection_id = uuid::Uuid::new_v4().to_string();
+    let watermark = crate::watermark::Watermark::for_client(&camera_id, &camera_config, query.get("token").map(String::as_str), &connection_id);
+    if let Some(ref watermark) = watermark {
+        info!("DVR client {} of camera {} is watermarked as '{}'", client_addr, camera_id, watermark.text);
+    }
+    let identity = crate::viewers::identify(&camera_config, query.get("token").map(String::as_str));
+    let lag_policy = camera_config.frame_lag_policy();
+    ws_upgrade.on_upgrade(move |socket| {
+        let viewer = crate::viewers::register(&connection_id, &camera_id, crate::viewers::ViewerKind::Dvr, client_addr, identity, lag_policy);
+        crate::live_dvr::handle_dvr_socket(socket, frame_sender, buffer, camera_id, client_addr, watermark, viewer)
+    })
+}
+
+/// WebSocket stream of the live frames cropped to a region the client selects (`/<camera_path>/roi`)
+pub async fn camera_roi_handler(
     ws: Option<axum::extract::WebSocketUpgrade>,
     query: Query<std::collections::HashMap<String, String>>,
     addr: Option<axum::extract::ConnectInfo<std::net::SocketAddr>>,
     frame_sender: Arc<broadcast::Sender<bytes::Bytes>>,
     camera_id: String,
-    mqtt_handle: Option<MqttHandle>,
     camera_config: config::CameraConfig,
+    latest_frame: Arc<tokio::sync::RwLock<Option<bytes::Bytes>>>,
+) -> axum::response::Response {
+    use tracing::{info, debug, warn};
+
+    let Some(ws_upgrade) = ws else {
+        return (axum::http::StatusCode::BAD_REQUEST, "ROI endpoint only accepts WebSocket connections").into_response();
+    };
+    if camera_config.requires_token() {
+        if let Some(provided_token) = query.get("token") {
+            if camera_config.token_grants(provided_token, config::TokenScope::Live) {
+                info!("Token authentication successful for camera {} ROI stream", camera_id);
+            } else if camera_config.token_known(provided_token) {
+                debug!("Token without live scope provided for camera {} ROI stream", camera_id);
+                return (axum::http::StatusCode::FORBIDDEN, "Token does not grant 'live' scope").into_response();
+            } else {
+                debug!("Invalid token provided for camera {} ROI stream", camera_id);
+                return (axum::http::StatusCode::UNAUTHORIZED, "Invalid token").into_response();
+            }
+        } else {
+            warn!("Missing token for camera {} that requires authentication", camera_id);
+            return (axum::http::StatusCode::UNAUTHORIZED, "Missing token").into_response();
+        }
+    }
+
+    let client_addr = addr.map_or_else(|| "127.0.0.1:0".parse().unwrap(), |connect_info| connect_info.0);
+    let connection_id = uuid::Uuid::new_v4().to_string();
+    let watermark = crate::watermark::Watermark::for_client(&camera_id, &camera_config, query.get("token").map(String::as_str), &connection_id);
+    if let Some(ref watermark) = watermark {
+        info!("ROI client {} of camera {} is watermarked as '{}'", client_addr, camera_id, watermark.text);
+    }
+    let identity = crate::viewers::identify(&camera_config, query.get("token").map(String::as_str));
+    let lag_policy = camera_config.frame_lag_policy();
+    ws_upgrade.on_upgrade(move |socket| {
+        let viewer = crate::viewers::register(&connection_id, &camera_id, crate::viewers::ViewerKind::Roi, client_addr, identity, lag_policy);
+        crate::roi_stream::handle_roi_socket(socket, frame_sender, latest_frame, camera_id, client_addr, watermark, viewer)
+    })
+}
+
+pub async fn dynamic_camera_roi_handler(
+    ws: Option<axum::extract::WebSocketUpgrade>,
+    query: Query<std::collections::HashMap<String, String>>,
+    addr: Option<axum::extract::ConnectInfo<std::net::SocketAddr>>,
+    camera_id: String,
+    app_state: AppState,
+) -> axum::response::Response {
+    let camera_streams = app_state.camera_streams.read().await;
+    if let Some(stream_info) = camera_streams.get(&camera_id) {
+        let stream_info = stream_info.clone();
+        drop(camera_streams);
+
+        camera_roi_handler(
+            ws, query, addr,
+            stream_info.frame_sender,
+            stream_info.camera_id,
+            stream_info.camera_config,
+            stream_info.latest_frame,
+        ).await
+    } else {
+        (axum::http::StatusCode::NOT_FOUND, "Camera not found").into_response()
+    }
+}
+
+pub async fn camera_stream_handler(
+    ws: Option<LiveUpgrade>,
+    query: Query<std::collections::HashMap<String, String>>,
+    addr: Option<axum::extract::ConnectInfo<std::net::SocketAddr>>,
+    stream: LiveStreamContext,
 ) -> axum::response::Response {
     use tracing::{trace, info, debug, warn};
+    let (camera_id, camera_config) = (stream.camera_id.clone(), &stream.camera_config);
     
     match ws {
         Some(ws_upgrade) => {
-            if let Some(expected_token) = &camera_config.token {
+            if camera_config.requires_token() {
                 if let Some(provided_token) = query.get("token") {
-                    if provided_token == expected_token {
+                    if camera_config.token_grants(provided_token, config::TokenScope::Live) {
                         info!("Token authentication successful for camera {}", camera_id);
+                    } else if camera_config.token_known(provided_token) {
+                        debug!("Token without live scope provided for camera {}", camera_id);
+                        return (axum::http::StatusCode::FORBIDDEN, "Token does not grant 'live' scope").into_response();
                     } else {
                         debug!("Invalid token provided for camera {}", camera_id);
                         return (axum::http::StatusCode::UNAUTHORIZED, "Invalid token").into_response();
@@ -307,12 +453,12 @@ pub async fn camera_stream_handler(
             
             if let Some(connect_info) = addr {
                 trace!("Starting stream WebSocket handler for camera {} from {}", camera_id, connect_info.0);
-                websocket_handler(ws_upgrade, State(frame_sender), connect_info, camera_id, mqtt_handle, camera_config).await
+                websocket_handler(ws_upgrade, connect_info, stream, query.0.clone()).await
             } else {
                 let fallback_addr = "127.0.0.1:0".parse().unwrap();
                 let connect_info = axum::extract::ConnectInfo(fallback_addr);
                 trace!("Starting stream WebSocket handler for camera {} (fallback addr)", camera_id);
-                websocket_handler(ws_upgrade, State(frame_sender), connect_info, camera_id, mqtt_handle, camera_config).await
+                websocket_handler(ws_upgrade, connect_info, stream, query.0.clone()).await
             }
         },
         None => {
@@ -321,6 +467,77 @@ pub async fn camera_stream_handler(
     }
 }
 
+/// Check the live-scope token of a plain HTTP request (Bearer header or ?token= query parameter).
+/// Returns the error response if the request is rejected.
+fn reject_without_live_token(
+    headers: &axum::http::HeaderMap,
+    query: &std::collections::HashMap<String, String>,
+    camera_config: &config::CameraConfig,
+    camera_id: &str,
+    endpoint: &str,
+) -> Option<axum::response::Response> {
+    use tracing::{info, warn, debug};
+
+    if !camera_config.requires_token() {
+        return None;
+    }
+
+    // Check Authorization header first
+    if let Some(auth_header) = headers.get("authorization") {
+        if let Ok(auth_str) = auth_header.to_str() {
+            if let Some(token) = auth_str.strip_prefix("Bearer ") {
+                if camera_config.token_grants(token, config::TokenScope::Live) {
+                    info!("Bearer token authentication successful for camera {} {}", camera_id, endpoint);
+                    return None;
+                } else if camera_config.token_known(token) {
+                    warn!("Bearer token without live scope provided for camera {} {}", camera_id, endpoint);
+                    return Some((axum::http::StatusCode::FORBIDDEN, "Token does not grant 'live' scope").into_response());
+                } else {
+                    warn!("Invalid Bearer token provided for camera {} {}", camera_id, endpoint);
+                    return Some((axum::http::StatusCode::UNAUTHORIZED, "Invalid Bearer token").into_response());
+                }
+            }
+        }
+    }
+
+    // Fall back to the query parameter
+    if let Some(provided_token) = query.get("token") {
+        if camera_config.token_grants(provided_token, config::TokenScope::Live) {
+            info!("Query parameter token authentication successful for camera {} {}", camera_id, endpoint);
+            return None;
+        } else if camera_config.token_known(provided_token) {
+            warn!("Query parameter token without live scope provided for camera {} {}", camera_id, endpoint);
+            return Some((axum::http::StatusCode::FORBIDDEN, "Token does not grant 'live' scope").into_response());
+        } else {
+            warn!("Invalid query parameter token provided for camera {} {}", camera_id, endpoint);
+            return Some((axum::http::StatusCode::UNAUTHORIZED, "Invalid token").into_response());
+        }
+    }
+
+    debug!("Missing or invalid authentication for camera {} {}", camera_id, endpoint);
+    Some((axum::http::StatusCode::UNAUTHORIZED, "Missing or invalid authentication - provide Bearer token in Authorization header or ?token= query parameter").into_response())
+}
+
+/// Token of a plain HTTP request: the Bearer header, otherwise the ?token= query parameter
+fn request_token<'a>(headers: &'a axum::http::HeaderMap, query: &'a std::collections::HashMap<String, String>) -> Option<&'a str> {
+    headers.get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .or_else(|| query.get("token").map(String::as_str))
+}
+
+/// Watermark a frame for one client; frames that fail are not sent unmarked
+async fn watermark_frame(watermark: crate::watermark::Watermark, frame: bytes::Bytes) -> Option<bytes::Bytes> {
+    match tokio::task::spawn_blocking(move || watermark.apply(&frame)).await {
+        Ok(Ok(data)) => Some(bytes::Bytes::from(data)),
+        Ok(Err(e)) => {
+            tracing::debug!("Failed to watermark frame: {}", e);
+            None
+        }
+        Err(_) => None,
+    }
+}
+
 pub async fn camera_snapshot_handler(
     headers: axum::http::HeaderMap,
     query: Query<std::collections::HashMap<String, String>>,
@@ -328,56 +545,61 @@ pub async fn camera_snapshot_handler(
     camera_config: config::CameraConfig,
     latest_frame: Arc<tokio::sync::RwLock<Option<bytes::Bytes>>>,
 ) -> axum::response::Response {
-    use tracing::{trace, info, warn, debug};
+    use tracing::{trace, warn};
     
-    // Check authentication if token is required
-    if let Some(expected_token) = &camera_config.token {
-        let mut token_valid = false;
-        
-        // Check Authorization header first
-        if let Some(auth_header) = headers.get("authorization") {
-            if let Ok(auth_str) = auth_header.to_str() {
-                if let Some(token) = auth_str.strip_prefix("Bearer ") {
-                    if token == expected_token {
-                        info!("Bearer token authentication successful for camera {} snapshot", camera_id);
-                        token_valid = true;
-                    } else {
-                        warn!("Invalid Bearer token provided for camera {} snapshot", camera_id);
-                        return (axum::http::StatusCode::UNAUTHORIZED, "Invalid Bearer token").into_response();
-                    }
-                }
-            }
-        }
-        
-        // If not valid yet, check query parameter
-        if !token_valid {
-            if let Some(provided_token) = query.get("token") {
-                if provided_token == expected_token {
-                    info!("Query parameter token authentication successful for camera {} snapshot", camera_id);
-                    token_valid = true;
-                } else {
-                    warn!("Invalid query parameter token provided for camera {} snapshot", camera_id);
-                    return (axum::http::StatusCode::UNAUTHORIZED, "Invalid token").into_response();
-                }
-            }
-        }
-        
-        if !token_valid {
-            debug!("Missing or invalid authentication for camera {} snapshot", camera_id);
-            return (axum::http::StatusCode::UNAUTHORIZED, "Missing or invalid authentication - provide Bearer token in Authorization header or ?token= query parameter").into_response();
-        }
+    if let Some(response) = reject_without_live_token(&headers, &query, &camera_config, &camera_id, "snapshot") {
+        return response;
     }
     
+    // Optional ?width=, ?height= and ?quality= scale the snapshot down
+    let parse = |name: &str, max: u32| match query.get(name).map(|value| value.parse::<u32>()) {
+        None => Ok(None),
+        Some(Ok(value)) if (1..=max).contains(&value) => Ok(Some(value)),
+        Some(_) => Err(format!("{} must be between 1 and {}", name, max)),
+    };
+    let (max_width, max_height, quality) = match (parse("width", 8192), parse("height", 8192), parse("quality", 100)) {
+        (Ok(width), Ok(height), Ok(quality)) => (width, height, quality.map(|q| q as u8)),
+        (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => {
+            return (axum::http::StatusCode::BAD_REQUEST, e).into_response();
+        }
+    };
+
+    // Each snapshot is a connection of its own for the watermark
+    let connection_id = uuid::Uuid::new_v4().to_string();
+    let watermark = crate::watermark::Watermark::for_client(&camera_id, &camera_config, request_token(&headers, &query), &connection_id);
+    if let Some(ref watermark) = watermark {
+        tracing::info!("Snapshot of camera {} is watermarked as '{}'", camera_id, watermark.text);
+    }
+
     // Get the latest stored frame
-    let frame_guard = latest_frame.read().await;
-    if let Some(frame_data) = frame_guard.as_ref() {
+    let frame = latest_frame.read().await.clone();
+    if let Some(frame_data) = frame {
         trace!("Returning stored frame for camera {} snapshot ({} bytes)", camera_id, frame_data.len());
+        let frame_data = if max_width.is_some() || max_height.is_some() || quality.is_some() {
+            match crate::jpeg::resize(frame_data, max_width, max_height, quality).await {
+                Ok(resized) => bytes::Bytes::from(resized),
+                Err(e) => {
+                    warn!("Failed to resize snapshot of camera {}: {}", camera_id, e);
+                    return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to resize snapshot").into_response();
+                }
+            }
+        } else {
+            frame_data
+        };
+        // Drawn after resizing so the identifier stays readable
+        let body = match watermark {
+            Some(watermark) => match watermark_frame(watermark, frame_data).await {
+                Some(marked) => axum::body::Body::from(marked),
+                None => return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to watermark snapshot").into_response(),
+            },
+            None => axum::body::Body::from(frame_data),
+        };
         axum::http::Response::builder()
             .header("content-type", "image/jpeg")
             .header("cache-control", "no-cache, no-store, must-revalidate")
             .header("pragma", "no-cache")
             .header("expires", "0")
-            .body(axum::body::Body::from(frame_data.clone()))
+            .body(body)
             .unwrap()
             .into_response()
     } else {
@@ -409,6 +631,130 @@ pub async fn dynamic_camera_snapshot_handler(
     }
 }
 
+/// Multipart boundary between the JPEG parts of the MJPEG stream
+const MJPEG_BOUNDARY: &str = "mjpegframe";
+
+/// Classic `multipart/x-mixed-replace` MJPEG stream for clients without WebSocket support.
+/// An optional `?fps=` query parameter limits the frame rate sent to the client.
+pub async fn camera_mjpeg_handler(
+    headers: axum::http::HeaderMap,
+    query: Query<std::collections::HashMap<String, String>>,
+    frame_sender: Arc<broadcast::Sender<bytes::Bytes>>,
+    camera_id: String,
+    camera_config: config::CameraConfig,
+    latest_frame: Arc<tokio::sync::RwLock<Option<bytes::Bytes>>>,
+) -> axum::response::Response {
+    use futures_util::StreamExt;
+    use tracing::{info, debug, warn};
+
+    if let Some(response) = reject_without_live_token(&headers, &query, &camera_config, &camera_id, "MJPEG stream") {
+        return response;
+    }
+
+    let min_interval = match query.get("fps").map(|fps| fps.parse::<f64>()) {
+        None => None,
+        Some(Ok(fps)) if fps.is_finite() && fps > 0.0 => Some(std::time::Duration::from_secs_f64(1.0 / fps.clamp(0.01, 1000.0))),
+        Some(_) => return (axum::http::StatusCode::BAD_REQUEST, "fps must be a positive number").into_response(),
+    };
+
+    let connection_id = uuid::Uuid::new_v4().to_string();
+    let watermark = crate::watermark::Watermark::for_client(&camera_id, &camera_config, request_token(&headers, &query), &connection_id);
+    if let Some(ref watermark) = watermark {
+        info!("MJPEG client of camera {} is watermarked as '{}'", camera_id, watermark.text);
+    }
+
+    let receiver = frame_sender.subscribe();
+    info!("MJPEG client connected to camera {} (current connections: {})", camera_id, frame_sender.receiver_count());
+    let disconnect_on_lag = camera_config.frame_lag_policy() == config::LagPolicy::Disconnect;
+
+    // Start with the latest frame so clients show an image before the next frame arrives
+    let first_frame = latest_frame.read().await.clone();
+    let live_frames = futures_util::stream::unfold(
+        (receiver, None::<tokio::time::Instant>, camera_id),
+        move |(mut receiver, mut last_sent, camera_id)| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(frame) => {
+                        if let (Some(interval), Some(sent)) = (min_interval, last_sent) {
+                            if sent.elapsed() < interval {
+                                continue;
+                            }
+                        }
+                        last_sent = Some(tokio::time::Instant::now());
+                        return Some((frame, (receiver, last_sent, camera_id)));
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) if disconnect_on_lag => {
+                        warn!("MJPEG client of camera {} fell behind, skipped {} frames, disconnecting", camera_id, skipped);
+                        return None;
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        debug!("MJPEG client of camera {} lagging, skipped {} frames", camera_id, skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => {
+                        debug!("Frame channel of camera {} closed, ending MJPEG stream", camera_id);
+                        return None;
+                    }
+                }
+            }
+        },
+    );
+
+    let parts = futures_util::stream::iter(first_frame)
+        .chain(live_frames)
+        .filter_map(move |frame| {
+            let watermark = watermark.clone();
+            async move {
+                match watermark {
+                    Some(watermark) => watermark_frame(watermark, frame).await,
+                    None => Some(frame),
+                }
+            }
+        })
+        .map(|frame| {
+            let mut part = bytes::BytesMut::with_capacity(frame.len() + 96);
+            part.extend_from_slice(format!(
+                "--{}\r\nContent-Type: image/jpeg\r\nContent-Length: {}\r\n\r\n",
+                MJPEG_BOUNDARY, frame.len()
+            ).as_bytes());
+            part.extend_from_slice(&frame);
+            part.extend_from_slice(b"\r\n");
+            Ok::<_, std::convert::Infallible>(part.freeze())
+        });
+
+    axum::http::Response::builder()
+        .header("content-type", format!("multipart/x-mixed-replace; boundary={}", MJPEG_BOUNDARY))
+        .header("cache-control", "no-cache, no-store, must-revalidate")
+        .header("pragma", "no-cache")
+        .header("expires", "0")
+        .body(axum::body::Body::from_stream(parts))
+        .unwrap()
+        .into_response()
+}
+
+pub async fn dynamic_camera_mjpeg_handler(
+    headers: axum::http::HeaderMap,
+    query: Query<std::collections::HashMap<String, String>>,
+    camera_id: String,
+    app_state: AppState,
+) -> axum::response::Response {
+    let camera_streams = app_state.camera_streams.read().await;
+    if let Some(stream_info) = camera_streams.get(&camera_id) {
+        let stream_info = stream_info.clone();
+        drop(camera_streams);
+
+        camera_mjpeg_handler(
+            headers,
+            query,
+            stream_info.frame_sender,
+            stream_info.camera_id,
+            stream_info.camera_config,
+            stream_info.latest_frame,
+        ).await
+    } else {
+        (axum::http::StatusCode::NOT_FOUND, "Camera not found").into_response()
+    }
+}
+
 pub async fn camera_control_handler(
     headers: axum::http::HeaderMap,
     ws: Option<axum::extract::WebSocketUpgrade>,
@@ -424,15 +770,18 @@ pub async fn camera_control_handler(
     
     match ws {
         Some(ws_upgrade) => {
-            if let Some(expected_token) = &camera_config.token {
+            if camera_config.requires_token() {
                 let mut token_valid = false;
                 
                 if let Some(auth_header) = headers.get("authorization") {
                     if let Ok(auth_str) = auth_header.to_str() {
                         if let Some(token) = auth_str.strip_prefix("Bearer ") {
-                            if token == expected_token {
+                            if camera_config.token_grants(token, config::TokenScope::Control) {
                                 info!("Bearer token authentication successful for camera {} control", camera_id);
                                 token_valid = true;
+                            } else if camera_config.token_known(token) {
+                                warn!("Bearer token without control scope provided for camera {} control", camera_id);
+                                return (axum::http::StatusCode::FORBIDDEN, "Token does not grant 'control' scope").into_response();
                             } else {
                                 warn!("Invalid Bearer token provided for camera {} control", camera_id);
                                 return (axum::http::StatusCode::UNAUTHORIZED, "Invalid Bearer token").into_response();
@@ -449,9 +798,12 @@ pub async fn camera_control_handler(
                 
                 if !token_valid {
                     if let Some(provided_token) = query.get("token") {
-                        if provided_token == expected_token {
+                        if camera_config.token_grants(provided_token, config::TokenScope::Control) {
                             info!("Query parameter token authentication successful for camera {} control", camera_id);
                             token_valid = true;
+                        } else if camera_config.token_known(provided_token) {
+                            warn!("Query parameter token without control scope provided for camera {} control", camera_id);
+                            return (axum::http::StatusCode::FORBIDDEN, "Token does not grant 'control' scope").into_response();
                         } else {
                             warn!("Invalid query parameter token provided for camera {} control", camera_id);
                             return (axum::http::StatusCode::UNAUTHORIZED, "Invalid token").into_response();
@@ -470,7 +822,24 @@ pub async fn camera_control_handler(
                 return (axum::http::StatusCode::SERVICE_UNAVAILABLE, "Recording system not enabled").into_response();
             }
             
+            // The joystick command needs the 'ptz' scope on top of 'control'
+            let ptz_token = request_token(&headers, &query);
             let client_id = uuid::Uuid::new_v4().to_string();
+            let watermark = crate::watermark::Watermark::for_client(&camera_id, &camera_config, ptz_token, &client_id);
+            if let Some(ref watermark) = watermark {
+                info!("Control client {} of camera {} is watermarked as '{}'", client_id, camera_id, watermark.text);
+            }
+            let ptz_granted = !camera_config.requires_token() || ptz_token.is_some_and(|token| camera_config.token_grants(token, config::TokenScope::Ptz));
+            let ptz = match (crate::api_ptz::build_ptz_controller(&camera_config), camera_config.ptz.clone()) {
+                (Ok(_), Some(_)) if !ptz_granted => Err((403, "Token does not grant 'ptz' scope")),
+                (Ok(controller), Some(config)) => Ok(crate::websocket_control::PtzSession {
+                    controller,
+                    // Each connection has control on its own, with the priority of its token
+                    client: crate::ptz_arbiter::PtzClient::from_token(&camera_config, ptz_token).with_id(&client_id),
+                    config,
+                }),
+                _ => Err((503, "PTZ not enabled or not available for this camera")),
+            };
             trace!("[CONTROL] Starting control WebSocket upgrade for camera {} with client {}", camera_id, client_id);
             let camera_id_clone = camera_id.clone();
             let client_id_clone = client_id.clone();
@@ -486,6 +855,8 @@ pub async fn camera_control_handler(
                         client_id,
                         recording_manager.unwrap(),
                         frame_sender,
+                        ptz,
+                        watermark,
                     ).await;
                     trace!("[CONTROL] Control handler task completed for camera {} client {}", camera_id_task, client_id_task);
                 });
@@ -517,4 +888,35 @@ async fn parse_camera_path(path: &str, app_state: &AppState) -> Option<(String,
     }
     
     None
-}
\ No newline at end of file
+}
+
+/// Reject WebSocket upgrades whose Origin header is not allowed for the requested path.
+/// Same-origin requests and clients that send no Origin (non-browser) are let through.
+pub async fn websocket_origin_guard(
+    State(server_config): State<Arc<config::ServerConfig>>,
+    request: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> axum::response::Response {
+    use axum::http::header;
+
+    let is_websocket = request.headers().get(header::UPGRADE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.eq_ignore_ascii_case("websocket"));
+
+    if is_websocket {
+        if let Some(origin) = request.headers().get(header::ORIGIN) {
+            let origin = origin.to_str().unwrap_or_default();
+            let host = request.headers().get(header::HOST).and_then(|v| v.to_str().ok());
+            let same_origin = host.is_some_and(|h| {
+                origin.split_once("://").is_some_and(|(_, authority)| authority.eq_ignore_ascii_case(h))
+            });
+
+            if !same_origin && !server_config.is_origin_allowed(origin, request.uri().path()) {
+                tracing::warn!("Rejected WebSocket upgrade for {} from origin '{}'", request.uri().path(), origin);
+                return (axum::http::StatusCode::FORBIDDEN, "WebSocket origin not allowed").into_response();
+            }
+        }
+    }
+
+    next.run(request).await
+}
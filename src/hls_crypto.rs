@@ -0,0 +1,111 @@
+use std::path::Path;
+use std::sync::Mutex;
+use aws_lc_rs::cipher::{EncryptionContext, PaddedBlockEncryptingKey, UnboundCipherKey, AES_128};
+use aws_lc_rs::digest::{digest, SHA256};
+use aws_lc_rs::hmac;
+use aws_lc_rs::iv::FixedLength;
+use chrono::Utc;
+use rand::RngCore;
+use tracing::warn;
+
+use crate::config::{CameraConfig, RecordingConfig};
+use crate::errors::{Result, StreamError};
+
+// Name of the master secret file in the recordings directory
+const MASTER_KEY_FILE: &str = "hls_encryption.key";
+
+lazy_static::lazy_static! {
+    // Master secret the per-camera keys are derived from, loaded on first use
+    static ref MASTER_KEY: Mutex<Option<hmac::Key>> = Mutex::new(None);
+}
+
+/// AES-128 key used to encrypt the HLS segments of a camera. The id is the Unix time the
+/// key's rotation period started.
+#[derive(Clone)]
+pub struct HlsKey {
+    pub id: String,
+    pub key: [u8; 16],
+}
+
+/// Whether HLS segments of this camera are encrypted when they are generated
+pub fn is_encryption_enabled(camera_config: &CameraConfig, recording_config: &RecordingConfig) -> bool {
+    camera_config.get_hls_encryption_enabled()
+        .unwrap_or(recording_config.hls_encryption_enabled)
+}
+
+// The master secret from `<database_path>/hls_encryption.key`, generated on first use. Stored
+// segments stay encrypted with keys derived from it, so it must survive restarts.
+fn master_key(recording_config: &RecordingConfig) -> Result<hmac::Key> {
+    let mut master_key = MASTER_KEY.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(key) = master_key.as_ref() {
+        return Ok(key.clone());
+    }
+
+    let path = Path::new(&recording_config.database_path).join(MASTER_KEY_FILE);
+    let secret = match std::fs::read_to_string(&path) {
+        Ok(secret) => secret,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let mut secret = [0u8; 32];
+            rand::rngs::OsRng.fill_bytes(&mut secret);
+            let secret: String = secret.iter().map(|b| format!("{:02x}", b)).collect();
+            crate::secrets::write_private(&path, secret.as_bytes())?;
+            warn!("Generated HLS encryption key file {}, encrypted recordings cannot be played without it", path.display());
+            secret
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.trim().as_bytes());
+    *master_key = Some(key.clone());
+    Ok(key)
+}
+
+fn derive_key(recording_config: &RecordingConfig, camera_id: &str, key_id: &str) -> Result<HlsKey> {
+    let tag = hmac::sign(&master_key(recording_config)?, format!("{}\n{}", camera_id, key_id).as_bytes());
+    let mut key = [0u8; 16];
+    key.copy_from_slice(&tag.as_ref()[..16]);
+    Ok(HlsKey { id: key_id.to_string(), key })
+}
+
+/// Key new segments of a camera are encrypted with. A new key starts every
+/// `hls_key_rotation_minutes`; earlier keys stay retrievable as long as their segments exist.
+pub fn current_key(recording_config: &RecordingConfig, camera_id: &str) -> Result<HlsKey> {
+    let rotation = recording_config.hls_key_rotation_minutes.max(1) as i64 * 60;
+    let now = Utc::now().timestamp();
+    derive_key(recording_config, camera_id, &(now - now.rem_euclid(rotation)).to_string())
+}
+
+/// Look up a key by id. Keys of rotation periods that have not started yet are never handed out.
+pub fn get_key(recording_config: &RecordingConfig, camera_id: &str, key_id: &str) -> Option<HlsKey> {
+    let started = key_id.parse::<i64>().ok()?;
+    if started > Utc::now().timestamp() || started.to_string() != key_id {
+        return None;
+    }
+    derive_key(recording_config, camera_id, key_id).ok()
+}
+
+/// IV of a segment: SHA-256 over the key id, the stream the segment belongs to (recording
+/// session or generated playlist) and its index, so no two segments share key and IV
+pub fn segment_iv(key_id: &str, stream: &str, segment_index: i64) -> [u8; 16] {
+    let hash = digest(&SHA256, format!("{}\n{}\n{}", key_id, stream, segment_index).as_bytes());
+    let mut iv = [0u8; 16];
+    iv.copy_from_slice(&hash.as_ref()[..16]);
+    iv
+}
+
+/// Encrypt a segment with AES-128-CBC and PKCS#7 padding as required by HLS `METHOD=AES-128`
+pub fn encrypt_segment(key: &HlsKey, stream: &str, segment_index: i64, data: &[u8]) -> Result<Vec<u8>> {
+    let cipher_key = UnboundCipherKey::new(&AES_128, &key.key)
+        .and_then(PaddedBlockEncryptingKey::cbc_pkcs7)
+        .map_err(|_| StreamError::internal("Failed to initialize HLS encryption key"))?;
+    let mut buffer = data.to_vec();
+    let iv = segment_iv(&key.id, stream, segment_index);
+    cipher_key.less_safe_encrypt(&mut buffer, EncryptionContext::Iv128(FixedLength::from(iv)))
+        .map_err(|_| StreamError::internal("Failed to encrypt HLS segment"))?;
+    Ok(buffer)
+}
+
+/// `EXT-X-KEY` tag announcing the key and IV of the following segment
+pub fn key_tag(key_id: &str, stream: &str, segment_index: i64) -> String {
+    let iv: String = segment_iv(key_id, stream, segment_index).iter().map(|b| format!("{:02x}", b)).collect();
+    format!("#EXT-X-KEY:METHOD=AES-128,URI=\"keys/{}\",IV=0x{}", key_id, iv)
+}
@@ -0,0 +1,251 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::sync::{watch, Semaphore};
+use tracing::{info, warn};
+
+use crate::api_recording::ApiResponse;
+use crate::config::RecordingConfig;
+
+// Finished generation jobs stay queryable for this long
+const FINISHED_JOB_MINUTES: i64 = 30;
+// Seconds a client should wait before retrying a queued playlist
+const RETRY_AFTER_SECS: u64 = 5;
+
+lazy_static::lazy_static! {
+    static ref QUEUE: Mutex<HlsQueue> = Mutex::new(HlsQueue::new(1, 1, 0));
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HlsJobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+}
+
+/// Generation of an HLS playlist from MP4 segments with FFmpeg
+#[derive(Debug, Clone, Serialize)]
+pub struct HlsJob {
+    pub job_id: String,
+    pub camera_id: String,
+    pub playlist_id: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub status: HlsJobStatus,
+    /// Jobs ahead of this one while queued, 0 = next to start
+    pub queue_position: Option<usize>,
+    pub progress_percent: f64,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// Playlist content of a finished generation, or the status and message to respond with
+pub type HlsOutcome = std::result::Result<String, (StatusCode, String)>;
+
+pub enum Submission {
+    /// A slot was free, the generation runs right away and its outcome can be awaited
+    Started(watch::Receiver<Option<HlsOutcome>>),
+    /// Waiting for a slot, or an identical generation is already queued or running
+    Queued(HlsJob),
+    QueueFull,
+}
+
+struct HlsQueue {
+    max_jobs: usize,
+    max_jobs_per_camera: usize,
+    max_queued: usize,
+    slots: Arc<Semaphore>,
+    camera_slots: HashMap<String, Arc<Semaphore>>,
+    jobs: HashMap<String, HlsJob>,
+}
+
+impl HlsQueue {
+    fn new(max_jobs: usize, max_jobs_per_camera: usize, max_queued: usize) -> Self {
+        Self {
+            max_jobs,
+            max_jobs_per_camera,
+            max_queued,
+            slots: Arc::new(Semaphore::new(max_jobs)),
+            camera_slots: HashMap::new(),
+            jobs: HashMap::new(),
+        }
+    }
+
+    fn camera_slots(&mut self, camera_id: &str) -> Arc<Semaphore> {
+        let max_jobs_per_camera = self.max_jobs_per_camera;
+        self.camera_slots.entry(camera_id.to_string())
+            .or_insert_with(|| Arc::new(Semaphore::new(max_jobs_per_camera)))
+            .clone()
+    }
+
+    fn with_position(&self, job: &HlsJob) -> HlsJob {
+        let mut job = job.clone();
+        if job.status == HlsJobStatus::Queued {
+            job.queue_position = Some(self.jobs.values()
+                .filter(|other| other.status == HlsJobStatus::Queued && other.created_at < job.created_at)
+                .count());
+        }
+        job
+    }
+}
+
+/// Apply the concurrency limits of the recording config; jobs already running keep their slots
+pub fn init(config: &RecordingConfig) {
+    let mut queue = QUEUE.lock().unwrap_or_else(|e| e.into_inner());
+    let jobs = std::mem::take(&mut queue.jobs);
+    *queue = HlsQueue::new(
+        config.hls_generation_max_jobs.max(1),
+        config.hls_generation_max_jobs_per_camera.max(1),
+        config.hls_generation_max_queued,
+    );
+    queue.jobs = jobs;
+    info!("HLS generation limited to {} jobs ({} per camera), {} queued",
+          queue.max_jobs, queue.max_jobs_per_camera, queue.max_queued);
+}
+
+/// Run `generate` for the playlist once a global and a per-camera slot are free. Requests for a
+/// playlist that is already queued or running join that job instead of generating it again.
+pub fn submit<F, Fut>(playlist_id: &str, camera_id: &str, from: DateTime<Utc>, to: DateTime<Utc>, generate: F) -> Submission
+where
+    F: FnOnce(Progress) -> Fut + Send + 'static,
+    Fut: Future<Output = HlsOutcome> + Send + 'static,
+{
+    let mut queue = QUEUE.lock().unwrap_or_else(|e| e.into_inner());
+    let expired = Utc::now() - chrono::Duration::minutes(FINISHED_JOB_MINUTES);
+    queue.jobs.retain(|_, job| job.finished_at.is_none_or(|finished_at| finished_at > expired));
+    if let Some(job) = queue.jobs.values().find(|job| {
+        job.playlist_id == playlist_id && matches!(job.status, HlsJobStatus::Queued | HlsJobStatus::Running)
+    }) {
+        return Submission::Queued(queue.with_position(job));
+    }
+
+    let camera_slots = queue.camera_slots(camera_id);
+    let slots = queue.slots.clone();
+    let permits = camera_slots.clone().try_acquire_owned().ok()
+        .and_then(|camera_permit| slots.clone().try_acquire_owned().ok().map(|permit| (camera_permit, permit)));
+    if permits.is_none() {
+        let queued = queue.jobs.values().filter(|job| job.status == HlsJobStatus::Queued).count();
+        if queued >= queue.max_queued {
+            warn!("[{}] HLS generation queue is full ({} jobs), rejecting playlist {}", camera_id, queued, playlist_id);
+            return Submission::QueueFull;
+        }
+    }
+
+    let job = HlsJob {
+        job_id: uuid::Uuid::new_v4().to_string(),
+        camera_id: camera_id.to_string(),
+        playlist_id: playlist_id.to_string(),
+        from,
+        to,
+        status: if permits.is_some() { HlsJobStatus::Running } else { HlsJobStatus::Queued },
+        queue_position: None,
+        progress_percent: 0.0,
+        error: None,
+        created_at: Utc::now(),
+        started_at: permits.is_some().then(Utc::now),
+        finished_at: None,
+    };
+    queue.jobs.insert(job.job_id.clone(), job.clone());
+    let job = queue.with_position(&job);
+    drop(queue);
+
+    let (outcome_sender, outcome) = watch::channel(None);
+    let started = permits.is_some();
+    let job_id = job.job_id.clone();
+    tokio::spawn(async move {
+        // The camera slot is taken first, so a job waiting for its camera holds no global slot
+        let _permits = match permits {
+            Some(permits) => permits,
+            None => {
+                let Ok(camera_permit) = camera_slots.acquire_owned().await else { return };
+                let Ok(permit) = slots.acquire_owned().await else { return };
+                update(&job_id, |job| {
+                    job.status = HlsJobStatus::Running;
+                    job.started_at = Some(Utc::now());
+                });
+                (camera_permit, permit)
+            }
+        };
+        let result = generate(Progress { job_id: job_id.clone() }).await;
+        update(&job_id, |job| {
+            job.finished_at = Some(Utc::now());
+            match result {
+                Ok(_) => {
+                    job.status = HlsJobStatus::Completed;
+                    job.progress_percent = 100.0;
+                }
+                Err((_, ref message)) => {
+                    job.status = HlsJobStatus::Failed;
+                    job.error = Some(message.clone());
+                }
+            }
+        });
+        outcome_sender.send_replace(Some(result));
+    });
+
+    if started {
+        Submission::Started(outcome)
+    } else {
+        info!("[{}] Queued HLS generation of playlist {} as job {}", camera_id, playlist_id, job.job_id);
+        Submission::Queued(job)
+    }
+}
+
+/// Progress reporting of a running generation
+pub struct Progress {
+    job_id: String,
+}
+
+impl Progress {
+    pub fn set_percent(&self, percent: f64) {
+        update(&self.job_id, |job| job.progress_percent = percent.clamp(0.0, 99.9));
+    }
+}
+
+fn update(job_id: &str, update: impl FnOnce(&mut HlsJob)) {
+    if let Some(job) = QUEUE.lock().unwrap_or_else(|e| e.into_inner()).jobs.get_mut(job_id) {
+        update(job);
+    }
+}
+
+pub fn get_job(job_id: &str) -> Option<HlsJob> {
+    let queue = QUEUE.lock().unwrap_or_else(|e| e.into_inner());
+    queue.jobs.get(job_id).map(|job| queue.with_position(job))
+}
+
+/// Outcome of a generation that was started right away
+pub async fn wait(mut outcome: watch::Receiver<Option<HlsOutcome>>) -> HlsOutcome {
+    match outcome.wait_for(Option::is_some).await {
+        Ok(outcome) => outcome.clone().unwrap_or_else(|| Err((StatusCode::INTERNAL_SERVER_ERROR, "HLS generation ended without result".to_string()))),
+        Err(_) => Err((StatusCode::INTERNAL_SERVER_ERROR, "HLS generation was aborted".to_string())),
+    }
+}
+
+/// `202 Accepted` with the queued job, for the playlist endpoint to return until it can be generated
+pub fn queued_response(job: HlsJob) -> Response {
+    (
+        StatusCode::ACCEPTED,
+        [(axum::http::header::RETRY_AFTER, RETRY_AFTER_SECS.to_string())],
+        Json(ApiResponse::success(serde_json::json!({
+            "message": "The playlist is queued for generation, retry when the job has completed",
+            "job": job,
+        }))),
+    ).into_response()
+}
+
+pub fn queue_full_response() -> Response {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        [(axum::http::header::RETRY_AFTER, RETRY_AFTER_SECS.to_string())],
+        Json(ApiResponse::<()>::error("Too many HLS playlists are being generated, retry later", 503)),
+    ).into_response()
+}
@@ -0,0 +1,127 @@
+use aws_lc_rs::hmac;
+use chrono::Utc;
+use rand::RngCore;
+
+use crate::config::RecordingConfig;
+
+lazy_static::lazy_static! {
+    // Used when no hls_signing_secret is configured, signed URLs then end with the server process
+    static ref RANDOM_SECRET: [u8; 32] = {
+        let mut secret = [0u8; 32];
+        rand::rngs::OsRng.fill_bytes(&mut secret);
+        secret
+    };
+}
+
+/// Signature parameters of a signed URL without other query parameters
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct SignedUrlQuery {
+    pub expires: Option<i64>,
+    pub signature: Option<String>,
+}
+
+fn key(recording_config: &RecordingConfig) -> hmac::Key {
+    match recording_config.hls_signing_secret.as_deref().filter(|s| !s.is_empty()) {
+        Some(secret) => hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes()),
+        None => hmac::Key::new(hmac::HMAC_SHA256, RANDOM_SECRET.as_slice()),
+    }
+}
+
+/// The signed message: camera, the URL relative to `control/recordings/hls/` and the expiry
+fn message(camera_id: &str, resource: &str, expires: i64) -> String {
+    format!("{}\n{}\n{}", camera_id, resource, expires)
+}
+
+/// Hex HMAC-SHA256 signature of a resource, valid until `expires` (Unix seconds)
+pub fn sign(recording_config: &RecordingConfig, camera_id: &str, resource: &str, expires: i64) -> String {
+    hmac::sign(&key(recording_config), message(camera_id, resource, expires).as_bytes())
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Check the signature of a resource and that it has not expired
+pub fn verify(recording_config: &RecordingConfig, camera_id: &str, resource: &str, expires: i64, signature: &str) -> bool {
+    // The signature comes from the query string and may hold any characters
+    if expires < Utc::now().timestamp() || !signature.len().is_multiple_of(2) || !signature.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return false;
+    }
+    let Ok(signature) = (0..signature.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&signature[i..i + 2], 16))
+        .collect::<Result<Vec<u8>, _>>() else {
+        return false;
+    };
+    hmac::verify(&key(recording_config), message(camera_id, resource, expires).as_bytes(), &signature).is_ok()
+}
+
+/// Expiry of URLs signed now
+pub fn default_expiry(recording_config: &RecordingConfig) -> i64 {
+    Utc::now().timestamp() + recording_config.hls_signed_url_minutes.max(1) as i64 * 60
+}
+
+/// `expires` and `signature` query parameters for a resource
+pub fn query(recording_config: &RecordingConfig, camera_id: &str, resource: &str, expires: i64) -> String {
+    format!("expires={}&signature={}", expires, sign(recording_config, camera_id, resource, expires))
+}
+
+/// Sign every segment and key URL of a playlist, so players that cannot send an Authorization
+/// header can fetch them
+pub fn sign_playlist(playlist: &str, recording_config: &RecordingConfig, camera_id: &str) -> String {
+    let expires = default_expiry(recording_config);
+    let sign_url = |url: &str| {
+        // Variant playlists of a master playlist are signed with their time range, like playlist URLs
+        let resource = if url.starts_with("timerange?") { url } else { url.split('?').next().unwrap_or(url) };
+        let separator = if url.contains('?') { '&' } else { '?' };
+        format!("{}{}{}", url, separator, query(recording_config, camera_id, resource, expires))
+    };
+
+    let mut output = String::with_capacity(playlist.len() * 2);
+    for line in playlist.lines() {
+        if line.is_empty() {
+            output.push('\n');
+            continue;
+        }
+        if !line.starts_with('#') {
+            output.push_str(&sign_url(line));
+        } else if let Some((before, rest)) = line.split_once("URI=\"") {
+            // EXT-X-KEY and other tags referencing a URL
+            let (uri, after) = rest.split_once('"').unwrap_or((rest, ""));
+            output.push_str(&format!("{}URI=\"{}\"{}", before, sign_url(uri), after));
+        } else {
+            output.push_str(line);
+        }
+        output.push('\n');
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recording_config() -> RecordingConfig {
+        serde_json::from_value(serde_json::json!({ "database_path": "recordings", "hls_signing_secret": "test-secret" })).unwrap()
+    }
+
+    #[test]
+    fn verifies_own_signatures() {
+        let config = recording_config();
+        let expires = default_expiry(&config);
+        let signature = sign(&config, "cam1", "segments/1.ts", expires);
+        assert!(verify(&config, "cam1", "segments/1.ts", expires, &signature));
+        assert!(!verify(&config, "cam2", "segments/1.ts", expires, &signature));
+        assert!(!verify(&config, "cam1", "segments/2.ts", expires, &signature));
+        assert!(!verify(&config, "cam1", "segments/1.ts", Utc::now().timestamp() - 1, &sign(&config, "cam1", "segments/1.ts", Utc::now().timestamp() - 1)));
+    }
+
+    #[test]
+    fn rejects_malformed_signatures() {
+        let config = recording_config();
+        let expires = default_expiry(&config);
+        for signature in ["", "a", "zz", "aéa", "éé", "+1", "0x"] {
+            assert!(!verify(&config, "cam1", "segments/1.ts", expires, signature), "{:?}", signature);
+        }
+    }
+}
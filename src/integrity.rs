@@ -0,0 +1,235 @@
+use std::sync::Arc;
+use aws_lc_rs::digest::{Context, SHA256};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tracing::warn;
+
+use crate::database::{DatabaseProvider, IntegrityHash, ReasonScope, VideoSegment};
+use crate::errors::Result;
+
+pub const DATA_TYPE_FRAMES: &str = "frames";
+pub const DATA_TYPE_MP4: &str = "mp4";
+
+/// Result of re-hashing the stored data of a camera
+#[derive(Debug, Clone, Serialize)]
+pub struct VerificationReport {
+    pub camera_id: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub checked: usize,
+    pub ok: usize,
+    pub modified: usize,
+    pub missing: usize,
+    /// True if every hashed frame batch and segment in the range is unchanged
+    pub verified: bool,
+    pub failures: Vec<VerificationFailure>,
+}
+
+/// A frame batch or MP4 segment whose data no longer matches its hash
+#[derive(Debug, Clone, Serialize)]
+pub struct VerificationFailure {
+    pub data_type: String,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub status: &'static str,  // "modified" or "missing"
+    pub expected_sha256: String,
+    pub actual_sha256: Option<String>,
+    pub expected_count: i64,
+    pub actual_count: i64,
+}
+
+/// SHA-256 over the frames of a batch in write order: timestamp in microseconds and length
+/// (both big-endian) followed by the JPEG data of each frame
+fn frame_batch_digest<'a>(frames: impl IntoIterator<Item = (DateTime<Utc>, &'a [u8])>) -> String {
+    let mut context = Context::new(&SHA256);
+    for (timestamp, data) in frames {
+        context.update(&timestamp.timestamp_micros().to_be_bytes());
+        context.update(&(data.len() as u64).to_be_bytes());
+        context.update(data);
+    }
+    to_hex(context.finish().as_ref())
+}
+
+fn data_digest(data: &[u8]) -> String {
+    to_hex(aws_lc_rs::digest::digest(&SHA256, data).as_ref())
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Store the hash of a frame batch that was just written
+pub async fn store_frame_batch_hash(
+    database: &Arc<dyn DatabaseProvider>,
+    camera_id: &str,
+    batch: &[(DateTime<Utc>, i64, Vec<u8>)],
+) {
+    let (Some(first), Some(last)) = (batch.first(), batch.last()) else { return };
+    let hash = IntegrityHash {
+        camera_id: camera_id.to_string(),
+        data_type: DATA_TYPE_FRAMES.to_string(),
+        start_time: first.0,
+        end_time: last.0,
+        item_count: batch.len() as i64,
+        sha256: frame_batch_digest(batch.iter().map(|(timestamp, _, data)| (*timestamp, data.as_slice()))),
+        created_at: Utc::now(),
+        thinned_interval_ms: None,
+    };
+    if let Err(e) = database.add_integrity_hash(&hash).await {
+        warn!("Failed to store integrity hash of {} frames for camera '{}': {}", batch.len(), camera_id, e);
+    }
+}
+
+/// Store the hash of an MP4 segment that was just written
+pub async fn store_mp4_hash(database: &Arc<dyn DatabaseProvider>, segment: &VideoSegment, mp4_data: &[u8]) {
+    let hash = IntegrityHash {
+        camera_id: segment.camera_id.clone(),
+        data_type: DATA_TYPE_MP4.to_string(),
+        start_time: segment.start_time,
+        end_time: segment.end_time,
+        item_count: 1,
+        sha256: data_digest(mp4_data),
+        created_at: Utc::now(),
+        thinned_interval_ms: None,
+    };
+    if let Err(e) = database.add_integrity_hash(&hash).await {
+        warn!("Failed to store integrity hash of MP4 segment {} for camera '{}': {}", segment.start_time, segment.camera_id, e);
+    }
+}
+
+/// Re-hash the frame batches and MP4 segments of a camera that overlap the time range
+pub async fn verify(
+    database: &Arc<dyn DatabaseProvider>,
+    camera_id: &str,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<VerificationReport> {
+    let hashes = database.list_integrity_hashes(camera_id, from, to).await?;
+    let mut report = VerificationReport {
+        camera_id: camera_id.to_string(),
+        from,
+        to,
+        checked: hashes.len(),
+        ok: 0,
+        modified: 0,
+        missing: 0,
+        verified: false,
+        failures: Vec::new(),
+    };
+
+    for hash in hashes {
+        let (actual_sha256, actual_count) = match hash.data_type.as_str() {
+            DATA_TYPE_FRAMES => rehash_frames(database.as_ref(), &hash).await?,
+            DATA_TYPE_MP4 => rehash_mp4(database, &hash).await?,
+            other => {
+                warn!("Skipping integrity hash of unknown data type '{}' for camera '{}'", other, camera_id);
+                report.checked -= 1;
+                continue;
+            }
+        };
+
+        let status = match actual_sha256 {
+            Some(ref actual) if *actual == hash.sha256 && actual_count == hash.item_count => {
+                report.ok += 1;
+                continue;
+            }
+            // Thinning left no frame of this batch
+            None if hash.item_count == 0 => {
+                report.ok += 1;
+                continue;
+            }
+            Some(_) => {
+                report.modified += 1;
+                "modified"
+            }
+            None => {
+                report.missing += 1;
+                "missing"
+            }
+        };
+        report.failures.push(VerificationFailure {
+            data_type: hash.data_type,
+            start_time: hash.start_time,
+            end_time: hash.end_time,
+            status,
+            expected_sha256: hash.sha256,
+            actual_sha256,
+            expected_count: hash.item_count,
+            actual_count,
+        });
+    }
+
+    report.verified = report.failures.is_empty();
+    Ok(report)
+}
+
+/// Thin the frames of a camera older than `older_than` to one per `interval_ms` and re-hash the
+/// affected frame batches, so thinned archive frames stay verifiable. A batch that no longer
+/// matched its hash before thinning keeps the old hash and still fails verification.
+pub(crate) async fn thin_frames<D: DatabaseProvider + ?Sized>(
+    database: &D,
+    camera_id: &str,
+    older_than: DateTime<Utc>,
+    interval_ms: i64,
+    reasons: &ReasonScope,
+) -> Result<usize> {
+    let mut hashes = Vec::new();
+    for hash in database.list_integrity_hashes_to_thin(camera_id, older_than, interval_ms).await? {
+        let (actual_sha256, actual_count) = rehash_frames(database, &hash).await?;
+        let intact = actual_sha256.as_deref() == Some(hash.sha256.as_str()) && actual_count == hash.item_count;
+        if !intact {
+            warn!("Frame batch {} of camera '{}' does not match its integrity hash, keeping the hash", hash.start_time, camera_id);
+        }
+        hashes.push((hash, intact));
+    }
+
+    let thinned = database.thin_old_frames(camera_id, older_than, interval_ms, reasons).await?;
+
+    for (mut hash, intact) in hashes {
+        if intact {
+            let (actual_sha256, actual_count) = rehash_frames(database, &hash).await?;
+            hash.sha256 = actual_sha256.unwrap_or_else(|| frame_batch_digest(std::iter::empty()));
+            hash.item_count = actual_count;
+        }
+        hash.thinned_interval_ms = Some(interval_ms);
+        database.update_integrity_hash(&hash).await?;
+    }
+    Ok(thinned)
+}
+
+/// Hash of the frames stored in the batch's time range, None if none are left
+async fn rehash_frames<D: DatabaseProvider + ?Sized>(database: &D, hash: &IntegrityHash) -> Result<(Option<String>, i64)> {
+    let mut stream = database.create_frame_stream(&hash.camera_id, hash.start_time, hash.end_time).await?;
+    let mut frames = Vec::with_capacity(hash.item_count.max(0) as usize);
+    while let Some(frame) = stream.next_frame().await? {
+        frames.push(frame);
+    }
+    stream.close().await?;
+
+    if frames.is_empty() {
+        return Ok((None, 0));
+    }
+    let digest = frame_batch_digest(frames.iter().map(|f| (f.timestamp, f.frame_data.as_slice())));
+    Ok((Some(digest), frames.len() as i64))
+}
+
+/// Hash of the stored MP4 segment (file, database blob or cold storage copy), None if it is gone
+async fn rehash_mp4(database: &Arc<dyn DatabaseProvider>, hash: &IntegrityHash) -> Result<(Option<String>, i64)> {
+    let Some(segment) = database.get_video_segment_by_time(&hash.camera_id, hash.start_time).await? else {
+        return Ok((None, 0));
+    };
+    let path = match (segment.mp4_data, segment.file_path) {
+        (Some(data), _) => return Ok((Some(data_digest(&data)), 1)),
+        (None, Some(path)) => path,
+        (None, None) => match database.get_mp4_archive(&hash.camera_id, hash.start_time).await? {
+            Some(archive) => archive.archive_path,
+            None => return Ok((None, 0)),
+        },
+    };
+    let data = match tokio::fs::read(&path).await {
+        Ok(data) => data,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((None, 0)),
+        Err(e) => return Err(e.into()),
+    };
+    Ok((Some(data_digest(&data)), 1))
+}
@@ -0,0 +1,325 @@
+use std::process::Stdio;
+use std::sync::RwLock;
+use image::codecs::gif::{GifEncoder, Repeat};
+#[cfg(not(feature = "mozjpeg"))]
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, Delay, Frame, RgbImage};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::process::Command;
+use tracing::info;
+
+use crate::config::{JpegEncoderBackend, JpegEncoderConfig};
+use crate::errors::{Result, StreamError};
+
+lazy_static::lazy_static! {
+    static ref SETTINGS: RwLock<JpegEncoderConfig> = RwLock::new(JpegEncoderConfig::default());
+}
+
+pub fn configure(config: Option<&JpegEncoderConfig>) {
+    let config = config.cloned().unwrap_or_default();
+    info!("Image encoder: {:?} with {} JPEG codec (JPEG quality {}, GIF speed {})",
+          config.backend, JPEG_CODEC, config.quality, config.gif_speed);
+    *SETTINGS.write().unwrap_or_else(|e| e.into_inner()) = config;
+}
+
+pub fn settings() -> JpegEncoderConfig {
+    SETTINGS.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// JPEG codec of the in-process image operations, chosen at build time
+#[cfg(feature = "mozjpeg")]
+const JPEG_CODEC: &str = "libjpeg-turbo (mozjpeg)";
+#[cfg(not(feature = "mozjpeg"))]
+const JPEG_CODEC: &str = "pure-Rust";
+
+#[cfg(not(feature = "mozjpeg"))]
+pub fn decode(frame: &[u8]) -> Result<DynamicImage> {
+    image::load_from_memory_with_format(frame, image::ImageFormat::Jpeg)
+        .map_err(|e| StreamError::internal(format!("Failed to decode frame: {}", e)))
+}
+
+#[cfg(not(feature = "mozjpeg"))]
+pub fn encode(image: &RgbImage, quality: u8) -> Result<Vec<u8>> {
+    let mut jpeg = Vec::new();
+    JpegEncoder::new_with_quality(&mut jpeg, quality.clamp(1, 100))
+        .encode_image(image)
+        .map_err(|e| StreamError::internal(format!("Failed to encode frame: {}", e)))?;
+    Ok(jpeg)
+}
+
+// libjpeg reports errors by unwinding out of its error handler
+#[cfg(feature = "mozjpeg")]
+fn catch_codec_errors<T>(f: impl FnOnce() -> std::io::Result<T>) -> std::result::Result<T, String> {
+    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)) {
+        Ok(result) => result.map_err(|e| e.to_string()),
+        Err(_) => Err("libjpeg error".to_string()),
+    }
+}
+
+#[cfg(feature = "mozjpeg")]
+pub fn decode(frame: &[u8]) -> Result<DynamicImage> {
+    let decoded = catch_codec_errors(|| {
+        let mut decompress = mozjpeg::Decompress::new_mem(frame)?.rgb()?;
+        let (width, height) = (decompress.width() as u32, decompress.height() as u32);
+        let pixels = decompress.read_scanlines::<u8>()?;
+        decompress.finish()?;
+        Ok((width, height, pixels))
+    });
+    match decoded {
+        Ok((width, height, pixels)) => RgbImage::from_raw(width, height, pixels)
+            .map(DynamicImage::ImageRgb8)
+            .ok_or_else(|| StreamError::internal("Failed to decode frame: truncated image data")),
+        // Color spaces libjpeg cannot convert to RGB (CMYK, YCCK) are left to the pure-Rust decoder
+        Err(_) => image::load_from_memory_with_format(frame, image::ImageFormat::Jpeg)
+            .map_err(|e| StreamError::internal(format!("Failed to decode frame: {}", e))),
+    }
+}
+
+#[cfg(feature = "mozjpeg")]
+pub fn encode(image: &RgbImage, quality: u8) -> Result<Vec<u8>> {
+    catch_codec_errors(|| {
+        let mut compress = mozjpeg::Compress::new(mozjpeg::ColorSpace::JCS_RGB);
+        // Baseline libjpeg-turbo settings; mozjpeg's trellis and progressive scans cost too much per frame
+        compress.set_fastest_defaults();
+        compress.set_size(image.width() as usize, image.height() as usize);
+        compress.set_quality(quality.clamp(1, 100) as f32);
+        let mut started = compress.start_compress(Vec::new())?;
+        started.write_scanlines(image.as_raw())?;
+        started.finish()
+    }).map_err(|e| StreamError::internal(format!("Failed to encode frame: {}", e)))
+}
+
+/// Scale a JPEG frame down to fit into `max_width` x `max_height`, keeping the aspect ratio.
+/// Frames that already fit are only re-encoded.
+pub async fn resize(frame: bytes::Bytes, max_width: Option<u32>, max_height: Option<u32>, quality: Option<u8>) -> Result<Vec<u8>> {
+    let settings = settings();
+    let quality = quality.unwrap_or(settings.quality);
+    match settings.backend {
+        JpegEncoderBackend::Native => tokio::task::spawn_blocking(move || {
+            let image = decode(&frame)?;
+            let width = max_width.unwrap_or(image.width()).min(image.width());
+            let height = max_height.unwrap_or(image.height()).min(image.height());
+            let image = if width < image.width() || height < image.height() {
+                image.resize(width, height, FilterType::Triangle)
+            } else {
+                image
+            };
+            encode(&image.to_rgb8(), quality)
+        }).await.map_err(|e| StreamError::internal(format!("Resize task failed: {}", e)))?,
+        JpegEncoderBackend::Ffmpeg => {
+            let width = max_width.map_or("iw".to_string(), |w| format!("min(iw,{})", w));
+            let height = max_height.map_or("ih".to_string(), |h| format!("min(ih,{})", h));
+            let filter = format!("scale='{}':'{}':force_original_aspect_ratio=decrease", width, height);
+            let quality = ffmpeg_quality(quality).to_string();
+            run_ffmpeg("Resizing failed", &[
+                "-loglevel", "error",
+                "-f", "image2pipe", "-c:v", "mjpeg", "-i", "-",
+                "-vf", &filter,
+                "-q:v", &quality,
+                "-frames:v", "1",
+                "-f", "mjpeg", "-",
+            ], vec![frame.to_vec()]).await
+        }
+    }
+}
+
+/// Image format of frames served by the playback API; frames are stored as JPEG
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FrameFormat {
+    Jpeg,
+    Webp,
+    Avif,
+}
+
+impl FrameFormat {
+    /// Value of the `format` query parameter
+    pub fn name(&self) -> &'static str {
+        match self {
+            FrameFormat::Jpeg => "jpeg",
+            FrameFormat::Webp => "webp",
+            FrameFormat::Avif => "avif",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            FrameFormat::Jpeg => "image/jpeg",
+            FrameFormat::Webp => "image/webp",
+            FrameFormat::Avif => "image/avif",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            FrameFormat::Jpeg => "jpg",
+            FrameFormat::Webp => "webp",
+            FrameFormat::Avif => "avif",
+        }
+    }
+
+    /// The format of `?format=`, otherwise WebP or AVIF if the Accept header lists them (WebP first,
+    /// it encodes much faster), otherwise JPEG
+    pub fn negotiate(format: Option<FrameFormat>, headers: &axum::http::HeaderMap) -> Self {
+        if let Some(format) = format {
+            return format;
+        }
+        let accepted: Vec<&str> = headers.get_all(axum::http::header::ACCEPT).iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            // Types with q=0 are explicitly not acceptable
+            .filter(|item| item.split(';').skip(1)
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .all(|q| q.parse::<f32>().ok().is_none_or(|q| q > 0.0)))
+            .map(|item| item.split(';').next().unwrap_or_default().trim())
+            .collect();
+        [FrameFormat::Webp, FrameFormat::Avif].into_iter()
+            .find(|format| accepted.iter().any(|media_type| media_type.eq_ignore_ascii_case(format.content_type())))
+            .unwrap_or(FrameFormat::Jpeg)
+    }
+}
+
+/// Re-encode a stored JPEG frame. JPEG frames are only re-encoded when a quality is given.
+/// WebP and AVIF always use FFmpeg (libwebp, libaom-av1), the native encoders are lossless only.
+pub async fn transcode(frame: bytes::Bytes, format: FrameFormat, quality: Option<u8>) -> Result<Vec<u8>> {
+    let quality = quality.unwrap_or_else(|| settings().quality).clamp(1, 100);
+    match format {
+        FrameFormat::Jpeg => resize(frame, None, None, Some(quality)).await,
+        FrameFormat::Webp => {
+            let quality = quality.to_string();
+            run_ffmpeg("WebP encoding failed", &[
+                "-loglevel", "error",
+                "-f", "image2pipe", "-c:v", "mjpeg", "-i", "-",
+                "-c:v", "libwebp", "-quality", &quality,
+                "-frames:v", "1",
+                "-f", "webp", "-",
+            ], vec![frame.to_vec()]).await
+        }
+        FrameFormat::Avif => {
+            // The AVIF muxer needs a seekable output
+            let path = std::env::temp_dir().join(format!("frame-{}.avif", uuid::Uuid::new_v4()));
+            let crf = avif_crf(quality).to_string();
+            let output = path.to_string_lossy().to_string();
+            let result = run_ffmpeg_to_file("AVIF encoding failed", &[
+                "-loglevel", "error",
+                "-f", "image2pipe", "-c:v", "mjpeg", "-i", "-",
+                "-c:v", "libaom-av1", "-still-picture", "1", "-crf", &crf, "-b:v", "0", "-cpu-used", "8",
+                "-frames:v", "1",
+                "-f", "avif", "-y", &output,
+            ], frame.to_vec(), &path).await;
+            let _ = tokio::fs::remove_file(&path).await;
+            result
+        }
+    }
+}
+
+/// AV1 `-crf` (0 = lossless, 63 = worst) for a quality of 1-100
+fn avif_crf(quality: u8) -> u32 {
+    (100 - quality.clamp(1, 100) as u32) * 63 / 99
+}
+
+/// Encode JPEG frames into an animated GIF, `width` pixels wide
+pub fn encode_gif(frames: &[Vec<u8>], fps: u32, width: u32, speed: i32) -> Result<Vec<u8>> {
+    let mut gif = Vec::new();
+    {
+        let mut encoder = GifEncoder::new_with_speed(&mut gif, speed.clamp(1, 30));
+        encoder.set_repeat(Repeat::Infinite)
+            .map_err(|e| StreamError::internal(format!("Failed to encode GIF: {}", e)))?;
+        let delay = Delay::from_numer_denom_ms(1000, fps.max(1));
+        for frame in frames {
+            let image = decode(frame)?;
+            let height = (image.height() as u64 * width as u64 / image.width().max(1) as u64).max(1) as u32;
+            let image = image.resize_exact(width, height, FilterType::Triangle).to_rgba8();
+            encoder.encode_frame(Frame::from_parts(image, 0, 0, delay))
+                .map_err(|e| StreamError::internal(format!("Failed to encode GIF: {}", e)))?;
+        }
+    }
+    Ok(gif)
+}
+
+/// FFmpeg's `-q:v` (2 = best, 31 = worst) for a JPEG quality of 1-100
+fn ffmpeg_quality(quality: u8) -> u32 {
+    2 + (100 - quality.clamp(1, 100) as u32) * 29 / 99
+}
+
+/// Run FFmpeg with one frame on stdin and return the file it writes to `path`
+async fn run_ffmpeg_to_file(context: &str, args: &[&str], frame: Vec<u8>, path: &std::path::Path) -> Result<Vec<u8>> {
+    let mut child = Command::new("ffmpeg")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    let mut stdin = child.stdin.take().ok_or_else(|| StreamError::ffmpeg("Failed to open FFmpeg stdin"))?;
+    let write_task = tokio::spawn(async move {
+        let _ = stdin.write_all(&frame).await;
+    });
+    let output = child.wait_with_output().await?;
+    let _ = write_task.await;
+    let data = tokio::fs::read(path).await.unwrap_or_default();
+    if !output.status.success() || data.is_empty() {
+        return Err(StreamError::ffmpeg(format!("{}: {}", context, String::from_utf8_lossy(&output.stderr).trim())));
+    }
+    Ok(data)
+}
+
+/// Run FFmpeg with the frames on stdin and return its output. Errors start with `context`.
+pub async fn run_ffmpeg(context: &str, args: &[&str], frames: Vec<Vec<u8>>) -> Result<Vec<u8>> {
+    let mut child = Command::new("ffmpeg")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    let mut stdin = child.stdin.take().ok_or_else(|| StreamError::ffmpeg("Failed to open FFmpeg stdin"))?;
+    let mut stdout = child.stdout.take().ok_or_else(|| StreamError::ffmpeg("Failed to open FFmpeg stdout"))?;
+    let mut stderr = child.stderr.take().ok_or_else(|| StreamError::ffmpeg("Failed to open FFmpeg stderr"))?;
+
+    let write_task = tokio::spawn(async move {
+        for frame in frames {
+            if stdin.write_all(&frame).await.is_err() {
+                break;
+            }
+        }
+    });
+    let stderr_task = tokio::spawn(async move {
+        let mut output = String::new();
+        let _ = stderr.read_to_string(&mut output).await;
+        output
+    });
+
+    let mut output = Vec::new();
+    stdout.read_to_end(&mut output).await?;
+    let status = child.wait().await?;
+    let _ = write_task.await;
+    let errors = stderr_task.await.unwrap_or_default();
+    if !status.success() || output.is_empty() {
+        return Err(StreamError::ffmpeg(format!("{}: {}", context, errors.trim())));
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_and_decodes_frames() {
+        let image = RgbImage::from_fn(64, 48, |x, y| image::Rgb([(x * 4) as u8, (y * 5) as u8, 128]));
+        let jpeg = encode(&image, 90).unwrap();
+        assert_eq!(&jpeg[..2], &[0xFF, 0xD8]);
+        let decoded = decode(&jpeg).unwrap().to_rgb8();
+        assert_eq!(decoded.dimensions(), (64, 48));
+        let pixel = decoded.get_pixel(32, 24);
+        assert!(pixel.0.iter().zip([128u8, 120, 128]).all(|(a, b)| a.abs_diff(b) < 12), "{:?}", pixel);
+    }
+
+    #[test]
+    fn rejects_invalid_frames() {
+        assert!(decode(b"not a jpeg").is_err());
+        assert!(decode(&[0xFF, 0xD8, 0xFF]).is_err());
+    }
+}
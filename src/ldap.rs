@@ -0,0 +1,738 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::{timeout, Duration};
+use tokio_rustls::rustls;
+use tokio_rustls::TlsConnector;
+use tracing::{debug, info, warn};
+
+use crate::config::{CameraConfig, LdapConfig, TokenScope};
+use crate::errors::{Result, StreamError};
+
+// Largest LDAP response accepted; a user entry with its groups is far smaller
+const MAX_MESSAGE_BYTES: usize = 4 * 1024 * 1024;
+const START_TLS_OID: &str = "1.3.6.1.4.1.1466.20037";
+
+const RESULT_SUCCESS: i64 = 0;
+const RESULT_SIZE_LIMIT_EXCEEDED: i64 = 4;
+const RESULT_INVALID_CREDENTIALS: i64 = 49;
+
+// Protocol operations (RFC 4511 4.2 - 4.14)
+const TAG_BIND_REQUEST: u8 = 0x60;
+const TAG_BIND_RESPONSE: u8 = 0x61;
+const TAG_UNBIND_REQUEST: u8 = 0x42;
+const TAG_SEARCH_REQUEST: u8 = 0x63;
+const TAG_SEARCH_ENTRY: u8 = 0x64;
+const TAG_SEARCH_DONE: u8 = 0x65;
+const TAG_SEARCH_REFERENCE: u8 = 0x73;
+const TAG_EXTENDED_REQUEST: u8 = 0x77;
+const TAG_EXTENDED_RESPONSE: u8 = 0x78;
+
+lazy_static::lazy_static! {
+    static ref SESSIONS: RwLock<HashMap<String, Session>> = RwLock::new(HashMap::new());
+}
+
+static REQUIRE_LOGIN: AtomicBool = AtomicBool::new(false);
+
+/// Login of a directory user; its token is accepted like a camera token until it expires
+#[derive(Debug, Clone, Serialize)]
+pub struct Session {
+    pub username: String,
+    /// Role groups the user is a member of
+    pub roles: Vec<String>,
+    /// Granted scopes per camera path, `*` for all cameras
+    pub cameras: HashMap<String, Vec<TokenScope>>,
+    pub admin: bool,
+    /// Highest PTZ control priority of the user's roles
+    pub ptz_priority: u8,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Apply the LDAP settings of the server config
+pub fn configure(config: Option<&LdapConfig>) {
+    REQUIRE_LOGIN.store(config.is_some_and(|c| c.require_login), Ordering::Relaxed);
+    if let Some(config) = config {
+        info!("LDAP authentication enabled with {} ({} roles{})", config.url, config.roles.len(),
+              if config.require_login { ", login required for all cameras" } else { "" });
+    }
+}
+
+/// Whether cameras without their own tokens are closed to anonymous access
+pub fn login_required() -> bool {
+    REQUIRE_LOGIN.load(Ordering::Relaxed)
+}
+
+fn with_session<T>(token: &str, f: impl FnOnce(&Session) -> T) -> Option<T> {
+    let sessions = SESSIONS.read().unwrap_or_else(|e| e.into_inner());
+    sessions.get(token).filter(|s| s.expires_at > Utc::now()).map(f)
+}
+
+/// Whether a session token grants `scope` on the camera at `camera_path`
+pub fn session_grants(token: &str, camera_path: &str, scope: TokenScope) -> bool {
+    with_session(token, |session| {
+        [camera_path, "*"].iter().any(|key| session.cameras.get(*key).is_some_and(|scopes| scopes.contains(&scope)))
+    }).unwrap_or(false)
+}
+
+/// Whether the token belongs to an active session, regardless of its scopes
+pub fn session_known(token: &str) -> bool {
+    with_session(token, |_| ()).is_some()
+}
+
+pub fn session_is_admin(token: &str) -> bool {
+    with_session(token, |session| session.admin).unwrap_or(false)
+}
+
+pub fn get_session(token: &str) -> Option<Session> {
+    with_session(token, Session::clone)
+}
+
+/// Invalidate a session token, false if it was unknown or already expired
+pub fn end_session(token: &str) -> bool {
+    let now = Utc::now();
+    let mut sessions = SESSIONS.write().unwrap_or_else(|e| e.into_inner());
+    let ended = sessions.remove(token).is_some_and(|s| s.expires_at > now);
+    sessions.retain(|_, s| s.expires_at > now);
+    ended
+}
+
+/// Create a session for an authenticated user from the roles matching its groups.
+/// Returns None when none of the user's groups has a role.
+pub fn start_session(
+    config: &LdapConfig,
+    username: &str,
+    groups: &[String],
+    camera_configs: &HashMap<String, CameraConfig>,
+) -> Option<(String, Session)> {
+    let mut roles = Vec::new();
+    let mut cameras: HashMap<String, Vec<TokenScope>> = HashMap::new();
+    let mut admin = false;
+    let mut ptz_priority = 0;
+    for role in &config.roles {
+        if !groups.iter().any(|group| group_matches(&role.group, group)) {
+            continue;
+        }
+        roles.push(role.group.clone());
+        admin |= role.admin;
+        ptz_priority = ptz_priority.max(role.ptz_priority.unwrap_or(if role.admin { 100 } else { 30 }));
+        for camera in &role.cameras {
+            // Camera IDs are resolved to paths, which is all a token check knows about the camera
+            let path = if camera == "*" || camera.starts_with('/') {
+                camera.clone()
+            } else if let Some(camera_config) = camera_configs.get(camera) {
+                camera_config.path.clone()
+            } else {
+                debug!("LDAP role '{}' references unknown camera '{}'", role.group, camera);
+                continue;
+            };
+            let scopes = cameras.entry(path).or_default();
+            for scope in &role.scopes {
+                if !scopes.contains(scope) {
+                    scopes.push(*scope);
+                }
+            }
+        }
+    }
+    if roles.is_empty() {
+        return None;
+    }
+
+    let now = Utc::now();
+    let session = Session {
+        username: username.to_string(),
+        roles,
+        cameras,
+        admin,
+        ptz_priority,
+        created_at: now,
+        expires_at: now + chrono::Duration::minutes(config.session_minutes.max(1) as i64),
+    };
+    let token = crate::generate_random_token(32);
+    let mut sessions = SESSIONS.write().unwrap_or_else(|e| e.into_inner());
+    sessions.retain(|_, s| s.expires_at > now);
+    sessions.insert(token.clone(), session.clone());
+    Some((token, session))
+}
+
+// A role group given as full DN must match the group DN, a bare name matches its first RDN value
+fn group_matches(role_group: &str, group_dn: &str) -> bool {
+    let normalize = |dn: &str| dn.split(',').map(str::trim).collect::<Vec<_>>().join(",");
+    if role_group.contains('=') {
+        normalize(role_group).eq_ignore_ascii_case(&normalize(group_dn))
+    } else {
+        group_dn.split(',').next()
+            .and_then(|rdn| rdn.split_once('='))
+            .is_some_and(|(_, name)| name.trim().eq_ignore_ascii_case(role_group.trim()))
+    }
+}
+
+/// Check a user's password against the directory. Returns the user's groups, or None when the
+/// user does not exist or the password is wrong.
+pub async fn authenticate(config: &LdapConfig, username: &str, password: &str) -> Result<Option<Vec<String>>> {
+    // A bind with an empty password is an unauthenticated bind, which servers accept for any DN
+    if username.is_empty() || password.is_empty() {
+        return Ok(None);
+    }
+    timeout(Duration::from_millis(config.timeout_ms.max(100)), lookup_user(config, username, password)).await
+        .map_err(|_| StreamError::server(format!("LDAP server {} did not answer in time", config.url)))?
+}
+
+async fn lookup_user(config: &LdapConfig, username: &str, password: &str) -> Result<Option<Vec<String>>> {
+    let mut connection = Connection::open(config).await?;
+    let filter = encode_filter(&config.user_filter.replace("{username}", &escape_filter_value(username)))?;
+
+    let entry = match config.bind_dn {
+        Some(ref bind_dn) => {
+            if !connection.bind(bind_dn, config.bind_password.as_deref().unwrap_or("")).await? {
+                return Err(StreamError::config(format!("LDAP bind of service account '{}' was rejected", bind_dn)));
+            }
+            let Some(entry) = connection.find_user(&config.base_dn, &filter, &config.group_attribute).await? else {
+                debug!("LDAP user '{}' not found below '{}'", username, config.base_dn);
+                return Ok(None);
+            };
+            if !connection.bind(&entry.dn, password).await? {
+                return Ok(None);
+            }
+            entry
+        }
+        None => {
+            let template = config.user_dn_template.as_deref()
+                .ok_or_else(|| StreamError::config("LDAP needs either bind_dn or user_dn_template"))?;
+            let user_dn = template.replace("{username}", &escape_dn_value(username));
+            if !connection.bind(&user_dn, password).await? {
+                return Ok(None);
+            }
+            // Bound as the user, which may read its own entry
+            connection.find_user(&config.base_dn, &filter, &config.group_attribute).await?
+                .ok_or_else(|| StreamError::config(format!("LDAP user '{}' bound but no entry matches the user_filter", username)))?
+        }
+    };
+    connection.unbind().await;
+
+    let groups = entry.attributes.iter()
+        .filter(|(name, _)| name.eq_ignore_ascii_case(&config.group_attribute))
+        .flat_map(|(_, values)| values.iter().cloned())
+        .collect();
+    Ok(Some(groups))
+}
+
+trait LdapStream: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> LdapStream for T {}
+
+struct Entry {
+    dn: String,
+    attributes: Vec<(String, Vec<String>)>,
+}
+
+struct Connection {
+    stream: Box<dyn LdapStream>,
+    message_id: i64,
+}
+
+impl Connection {
+    async fn open(config: &LdapConfig) -> Result<Self> {
+        let url = url::Url::parse(&config.url)?;
+        let host = url.host_str()
+            .ok_or_else(|| StreamError::config(format!("LDAP URL '{}' has no host", config.url)))?
+            .to_string();
+        let (implicit_tls, default_port) = match url.scheme() {
+            "ldaps" => (true, 636),
+            "ldap" => (false, 389),
+            scheme => return Err(StreamError::config(format!("Unsupported LDAP URL scheme '{}'", scheme))),
+        };
+        let tcp = TcpStream::connect((host.as_str(), url.port().unwrap_or(default_port))).await?;
+        if implicit_tls {
+            let stream = tls_connector(config)?.connect(server_name(&host)?, tcp).await?;
+            return Ok(Self { stream: Box::new(stream), message_id: 0 });
+        }
+
+        let mut connection = Self { stream: Box::new(tcp), message_id: 0 };
+        if config.starttls {
+            let request = tlv(0x80, START_TLS_OID.as_bytes());
+            let id = connection.send(tlv(TAG_EXTENDED_REQUEST, &request)).await?;
+            let (code, message) = connection.receive_result(id, TAG_EXTENDED_RESPONSE).await?;
+            if code != RESULT_SUCCESS {
+                return Err(StreamError::server(format!("LDAP server {} refused StartTLS ({}): {}", host, code, message)));
+            }
+            let stream = tls_connector(config)?.connect(server_name(&host)?, connection.stream).await?;
+            connection.stream = Box::new(stream);
+        }
+        Ok(connection)
+    }
+
+    /// Simple bind; false if the server rejects the credentials
+    async fn bind(&mut self, dn: &str, password: &str) -> Result<bool> {
+        let mut request = integer(0x02, 3);
+        request.extend(tlv(0x04, dn.as_bytes()));
+        request.extend(tlv(0x80, password.as_bytes()));
+        let id = self.send(tlv(TAG_BIND_REQUEST, &request)).await?;
+        match self.receive_result(id, TAG_BIND_RESPONSE).await? {
+            (RESULT_SUCCESS, _) => Ok(true),
+            (RESULT_INVALID_CREDENTIALS, message) => {
+                debug!("LDAP bind of '{}' rejected: {}", dn, message);
+                Ok(false)
+            }
+            (code, message) => Err(StreamError::server(format!("LDAP bind of '{}' failed ({}): {}", dn, code, message))),
+        }
+    }
+
+    /// Subtree search expecting at most one entry
+    async fn find_user(&mut self, base_dn: &str, filter: &[u8], attribute: &str) -> Result<Option<Entry>> {
+        let mut request = tlv(0x04, base_dn.as_bytes());
+        request.extend(integer(0x0A, 2)); // wholeSubtree
+        request.extend(integer(0x0A, 0)); // neverDerefAliases
+        request.extend(integer(0x02, 2)); // size limit, a second entry makes the user ambiguous
+        request.extend(integer(0x02, 0));
+        request.extend(tlv(0x01, &[0x00])); // typesOnly = false
+        request.extend_from_slice(filter);
+        request.extend(tlv(0x30, &tlv(0x04, attribute.as_bytes())));
+        let id = self.send(tlv(TAG_SEARCH_REQUEST, &request)).await?;
+
+        let mut entries = Vec::new();
+        loop {
+            let (tag, content) = self.receive(id).await?;
+            match tag {
+                TAG_SEARCH_ENTRY => entries.push(parse_entry(&content)?),
+                TAG_SEARCH_REFERENCE => {}
+                TAG_SEARCH_DONE => {
+                    let (code, message) = parse_result(&content)?;
+                    if code != RESULT_SUCCESS && code != RESULT_SIZE_LIMIT_EXCEEDED {
+                        return Err(StreamError::server(format!("LDAP search below '{}' failed ({}): {}", base_dn, code, message)));
+                    }
+                    break;
+                }
+                tag => return Err(StreamError::server(format!("Unexpected LDAP response 0x{:02x} to a search", tag))),
+            }
+        }
+        if entries.len() > 1 {
+            warn!("LDAP user filter matches more than one entry below '{}', refusing the login", base_dn);
+            return Err(StreamError::config("LDAP user filter is ambiguous"));
+        }
+        Ok(entries.pop())
+    }
+
+    async fn unbind(&mut self) {
+        let _ = self.send(tlv(TAG_UNBIND_REQUEST, &[])).await;
+        let _ = self.stream.shutdown().await;
+    }
+
+    async fn send(&mut self, operation: Vec<u8>) -> Result<i64> {
+        self.message_id += 1;
+        let mut message = integer(0x02, self.message_id);
+        message.extend(operation);
+        self.stream.write_all(&tlv(0x30, &message)).await?;
+        self.stream.flush().await?;
+        Ok(self.message_id)
+    }
+
+    /// Next response to the request `id` as protocol operation tag and content
+    async fn receive(&mut self, id: i64) -> Result<(u8, Vec<u8>)> {
+        loop {
+            let message = self.read_message().await?;
+            let mut ber = Ber(&message);
+            let (_, message_id) = ber.next()?;
+            let (tag, content) = ber.next()?;
+            // Unsolicited notifications (message ID 0) and stale responses are skipped
+            if parse_integer(message_id) == id {
+                return Ok((tag, content.to_vec()));
+            }
+        }
+    }
+
+    async fn receive_result(&mut self, id: i64, expected_tag: u8) -> Result<(i64, String)> {
+        let (tag, content) = self.receive(id).await?;
+        if tag != expected_tag {
+            return Err(StreamError::server(format!("Unexpected LDAP response 0x{:02x} (expected 0x{:02x})", tag, expected_tag)));
+        }
+        parse_result(&content)
+    }
+
+    async fn read_message(&mut self) -> Result<Vec<u8>> {
+        if self.stream.read_u8().await? != 0x30 {
+            return Err(malformed());
+        }
+        let first = self.stream.read_u8().await?;
+        let length = if first & 0x80 == 0 {
+            first as usize
+        } else {
+            let count = (first & 0x7F) as usize;
+            if count == 0 || count > 4 {
+                return Err(malformed());
+            }
+            let mut length = 0usize;
+            for _ in 0..count {
+                length = (length << 8) | self.stream.read_u8().await? as usize;
+            }
+            length
+        };
+        if length > MAX_MESSAGE_BYTES {
+            return Err(StreamError::server(format!("LDAP response of {} bytes is too large", length)));
+        }
+        let mut content = vec![0u8; length];
+        self.stream.read_exact(&mut content).await?;
+        Ok(content)
+    }
+}
+
+fn tls_connector(config: &LdapConfig) -> Result<TlsConnector> {
+    let mut roots = rustls::RootCertStore::empty();
+    match config.ca_file {
+        Some(ref ca_file) => {
+            let pem = std::fs::read(ca_file)?;
+            for certificate in rustls_pemfile::certs(&mut pem.as_slice()) {
+                roots.add(certificate?)
+                    .map_err(|e| StreamError::config(format!("Invalid certificate in '{}': {}", ca_file, e)))?;
+            }
+        }
+        None => roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned()),
+    }
+    let tls_config = rustls::ClientConfig::builder_with_provider(Arc::new(rustls::crypto::aws_lc_rs::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(|e| StreamError::internal(format!("Failed to create TLS configuration: {}", e)))?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    Ok(TlsConnector::from(Arc::new(tls_config)))
+}
+
+fn server_name(host: &str) -> Result<rustls::pki_types::ServerName<'static>> {
+    rustls::pki_types::ServerName::try_from(host.to_string())
+        .map_err(|_| StreamError::config(format!("Invalid LDAP host name '{}'", host)))
+}
+
+fn malformed() -> StreamError {
+    StreamError::server("Malformed LDAP response")
+}
+
+// BER element with definite length
+fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut element = vec![tag];
+    let length = content.len();
+    if length < 0x80 {
+        element.push(length as u8);
+    } else {
+        let bytes = length.to_be_bytes();
+        let skip = bytes.iter().take_while(|b| **b == 0).count();
+        element.push(0x80 | (bytes.len() - skip) as u8);
+        element.extend_from_slice(&bytes[skip..]);
+    }
+    element.extend_from_slice(content);
+    element
+}
+
+// INTEGER or ENUMERATED in the fewest two's complement bytes
+fn integer(tag: u8, value: i64) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let mut start = 0;
+    while start < 7 && ((bytes[start] == 0x00 && bytes[start + 1] & 0x80 == 0) || (bytes[start] == 0xFF && bytes[start + 1] & 0x80 != 0)) {
+        start += 1;
+    }
+    tlv(tag, &bytes[start..])
+}
+
+fn parse_integer(bytes: &[u8]) -> i64 {
+    let initial = if bytes.first().is_some_and(|b| b & 0x80 != 0) { -1 } else { 0 };
+    bytes.iter().take(8).fold(initial, |value, b| (value << 8) | *b as i64)
+}
+
+struct Ber<'a>(&'a [u8]);
+
+impl<'a> Ber<'a> {
+    fn next(&mut self) -> Result<(u8, &'a [u8])> {
+        let (&tag, rest) = self.0.split_first().ok_or_else(malformed)?;
+        let (&first, mut rest) = rest.split_first().ok_or_else(malformed)?;
+        let length = if first & 0x80 == 0 {
+            first as usize
+        } else {
+            let count = (first & 0x7F) as usize;
+            if count == 0 || count > 4 || rest.len() < count {
+                return Err(malformed());
+            }
+            let length = rest[..count].iter().fold(0usize, |length, b| (length << 8) | *b as usize);
+            rest = &rest[count..];
+            length
+        };
+        if rest.len() < length {
+            return Err(malformed());
+        }
+        self.0 = &rest[length..];
+        Ok((tag, &rest[..length]))
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+// LDAPResult: result code and diagnostic message
+fn parse_result(content: &[u8]) -> Result<(i64, String)> {
+    let mut ber = Ber(content);
+    let (_, code) = ber.next()?;
+    let _matched_dn = ber.next()?;
+    let (_, message) = ber.next()?;
+    Ok((parse_integer(code), String::from_utf8_lossy(message).into_owned()))
+}
+
+fn parse_entry(content: &[u8]) -> Result<Entry> {
+    let mut ber = Ber(content);
+    let (_, dn) = ber.next()?;
+    let (_, attribute_list) = ber.next()?;
+    let mut attributes = Vec::new();
+    let mut list = Ber(attribute_list);
+    while !list.is_empty() {
+        let (_, attribute) = list.next()?;
+        let mut attribute = Ber(attribute);
+        let (_, name) = attribute.next()?;
+        let (_, value_set) = attribute.next()?;
+        let mut values = Vec::new();
+        let mut set = Ber(value_set);
+        while !set.is_empty() {
+            let (_, value) = set.next()?;
+            values.push(String::from_utf8_lossy(value).into_owned());
+        }
+        attributes.push((String::from_utf8_lossy(name).into_owned(), values));
+    }
+    Ok(Entry { dn: String::from_utf8_lossy(dn).into_owned(), attributes })
+}
+
+/// Encode an RFC 4515 search filter string, e.g. `(&(objectClass=user)(sAMAccountName=jdoe))`
+fn encode_filter(filter: &str) -> Result<Vec<u8>> {
+    let (encoded, rest) = parse_filter(filter.trim())?;
+    if !rest.trim().is_empty() {
+        return Err(invalid_filter(filter));
+    }
+    Ok(encoded)
+}
+
+fn parse_filter(input: &str) -> Result<(Vec<u8>, &str)> {
+    let inner = input.strip_prefix('(').ok_or_else(|| invalid_filter(input))?;
+    match inner.chars().next() {
+        Some(operator @ ('&' | '|')) => {
+            let mut rest = &inner[1..];
+            let mut content = Vec::new();
+            while rest.starts_with('(') {
+                let (filter, remaining) = parse_filter(rest)?;
+                content.extend(filter);
+                rest = remaining;
+            }
+            let rest = rest.strip_prefix(')').ok_or_else(|| invalid_filter(input))?;
+            Ok((tlv(if operator == '&' { 0xA0 } else { 0xA1 }, &content), rest))
+        }
+        Some('!') => {
+            let (filter, rest) = parse_filter(&inner[1..])?;
+            let rest = rest.strip_prefix(')').ok_or_else(|| invalid_filter(input))?;
+            Ok((tlv(0xA2, &filter), rest))
+        }
+        _ => {
+            // Values escape parentheses as \28 and \29, so the first ')' ends the item
+            let end = inner.find(')').ok_or_else(|| invalid_filter(input))?;
+            Ok((encode_filter_item(&inner[..end])?, &inner[end + 1..]))
+        }
+    }
+}
+
+fn encode_filter_item(item: &str) -> Result<Vec<u8>> {
+    let (left, value) = item.split_once('=').ok_or_else(|| invalid_filter(item))?;
+    let assertion = |tag: u8, attribute: &str| -> Result<Vec<u8>> {
+        let mut content = tlv(0x04, attribute.as_bytes());
+        content.extend(tlv(0x04, &unescape_filter_value(value)?));
+        Ok(tlv(tag, &content))
+    };
+    if let Some(attribute) = left.strip_suffix('>') {
+        return assertion(0xA5, attribute);
+    }
+    if let Some(attribute) = left.strip_suffix('<') {
+        return assertion(0xA6, attribute);
+    }
+    if let Some(attribute) = left.strip_suffix('~') {
+        return assertion(0xA8, attribute);
+    }
+    if let Some(rule) = left.strip_suffix(':') {
+        // Extensible match, e.g. the AD nested group rule `memberOf:1.2.840.113556.1.4.1941:=<group DN>`
+        let mut parts = rule.split(':');
+        let attribute = parts.next().unwrap_or_default();
+        let mut content = Vec::new();
+        let mut dn_attributes = false;
+        for part in parts {
+            if part.eq_ignore_ascii_case("dn") {
+                dn_attributes = true;
+            } else {
+                content.extend(tlv(0x81, part.as_bytes()));
+            }
+        }
+        if !attribute.is_empty() {
+            content.extend(tlv(0x82, attribute.as_bytes()));
+        }
+        content.extend(tlv(0x83, &unescape_filter_value(value)?));
+        if dn_attributes {
+            content.extend(tlv(0x84, &[0xFF]));
+        }
+        return Ok(tlv(0xA9, &content));
+    }
+    if value == "*" {
+        return Ok(tlv(0x87, left.as_bytes()));
+    }
+    if value.contains('*') {
+        let pieces: Vec<&str> = value.split('*').collect();
+        let mut substrings = Vec::new();
+        for (index, piece) in pieces.iter().enumerate() {
+            if piece.is_empty() {
+                continue;
+            }
+            let tag = match index {
+                0 => 0x80,
+                i if i == pieces.len() - 1 => 0x82,
+                _ => 0x81,
+            };
+            substrings.extend(tlv(tag, &unescape_filter_value(piece)?));
+        }
+        let mut content = tlv(0x04, left.as_bytes());
+        content.extend(tlv(0x30, &substrings));
+        return Ok(tlv(0xA4, &content));
+    }
+    assertion(0xA3, left)
+}
+
+fn invalid_filter(filter: &str) -> StreamError {
+    StreamError::config(format!("Invalid LDAP filter '{}'", filter))
+}
+
+fn unescape_filter_value(value: &str) -> Result<Vec<u8>> {
+    let bytes = value.as_bytes();
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\' {
+            let hex = value.get(i + 1..i + 3).ok_or_else(|| invalid_filter(value))?;
+            result.push(u8::from_str_radix(hex, 16).map_err(|_| invalid_filter(value))?);
+            i += 3;
+        } else {
+            result.push(bytes[i]);
+            i += 1;
+        }
+    }
+    Ok(result)
+}
+
+// RFC 4515 escaping of a value inserted into a filter
+fn escape_filter_value(value: &str) -> String {
+    value.bytes().map(|b| match b {
+        b'*' | b'(' | b')' | b'\\' | 0 => format!("\\{:02x}", b),
+        _ => (b as char).to_string(),
+    }).collect::<Vec<_>>().concat()
+}
+
+// RFC 4514 escaping of a value inserted into a DN
+fn escape_dn_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for (index, c) in value.chars().enumerate() {
+        let special = matches!(c, ',' | '+' | '"' | '\\' | '<' | '>' | ';' | '=')
+            || (index == 0 && (c == '#' || c == ' '))
+            || (index == value.chars().count() - 1 && c == ' ');
+        if special {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connection(response: &[u8]) -> Connection {
+        let (client, mut server) = tokio::io::duplex(64 * 1024);
+        let response = response.to_vec();
+        tokio::spawn(async move {
+            let _ = server.write_all(&response).await;
+            let _ = server.shutdown().await;
+        });
+        Connection { stream: Box::new(client), message_id: 0 }
+    }
+
+    #[test]
+    fn escapes_filter_values() {
+        assert_eq!(escape_filter_value("a*b(c)d\\e\0f"), "a\\2ab\\28c\\29d\\5ce\\00f");
+        assert_eq!(escape_filter_value("jdoe"), "jdoe");
+        let escaped = escape_filter_value("*)(uid=*");
+        assert_eq!(unescape_filter_value(&escaped).unwrap(), b"*)(uid=*");
+    }
+
+    #[test]
+    fn rejects_bad_filter_escapes() {
+        assert!(unescape_filter_value("a\\2").is_err());
+        assert!(unescape_filter_value("a\\zz").is_err());
+    }
+
+    #[test]
+    fn escaped_value_stays_one_filter_item() {
+        let filter = format!("(uid={})", escape_filter_value("*)(objectClass=*"));
+        let mut content = tlv(0x04, b"uid");
+        content.extend(tlv(0x04, b"*)(objectClass=*"));
+        assert_eq!(encode_filter(&filter).unwrap(), tlv(0xA3, &content));
+        assert!(encode_filter("(uid=a)(uid=b)").is_err());
+        assert!(encode_filter("(&(uid=a)").is_err());
+    }
+
+    #[test]
+    fn escapes_dn_values() {
+        assert_eq!(escape_dn_value("Doe, John"), "Doe\\, John");
+        assert_eq!(escape_dn_value("a+b=c;d\\e"), "a\\+b\\=c\\;d\\\\e");
+        assert_eq!(escape_dn_value(" #x "), "\\ #x\\ ");
+        assert_eq!(escape_dn_value("#x"), "\\#x");
+    }
+
+    #[test]
+    fn matches_groups_by_dn_or_name() {
+        let dn = "CN=Camera Admins, OU=Groups,DC=example,DC=com";
+        assert!(group_matches("camera admins", dn));
+        assert!(group_matches("cn=Camera Admins,ou=Groups,dc=example,dc=com", dn));
+        assert!(!group_matches("Camera", dn));
+        assert!(!group_matches("Groups", dn));
+        assert!(!group_matches("cn=Camera Admins,ou=Other,dc=example,dc=com", dn));
+    }
+
+    #[test]
+    fn rejects_malformed_ber() {
+        assert!(Ber(&[]).next().is_err());
+        assert!(Ber(&[0x04]).next().is_err());
+        assert!(Ber(&[0x04, 0x05, b'a']).next().is_err());
+        assert!(Ber(&[0x04, 0x80]).next().is_err());
+        assert!(Ber(&[0x04, 0x85, 0, 0, 0, 0, 1]).next().is_err());
+        assert!(Ber(&[0x04, 0x82, 0x01]).next().is_err());
+        assert!(parse_result(&[0x0A, 0x01, 0x00]).is_err());
+    }
+
+    #[test]
+    fn parses_results() {
+        let mut content = integer(0x0A, RESULT_INVALID_CREDENTIALS);
+        content.extend(tlv(0x04, b""));
+        content.extend(tlv(0x04, b"invalid credentials"));
+        assert_eq!(parse_result(&content).unwrap(), (RESULT_INVALID_CREDENTIALS, "invalid credentials".to_string()));
+        assert_eq!(parse_integer(&integer(0x02, 300)[2..]), 300);
+        assert_eq!(parse_integer(&integer(0x02, -1)[2..]), -1);
+    }
+
+    #[tokio::test]
+    async fn reads_messages() {
+        let message = tlv(0x30, &[0xAB; 300]);
+        assert_eq!(connection(&message).read_message().await.unwrap(), vec![0xAB; 300]);
+    }
+
+    #[tokio::test]
+    async fn rejects_oversized_or_malformed_messages() {
+        let length = (MAX_MESSAGE_BYTES as u32 + 1).to_be_bytes();
+        let oversized = [&[0x30, 0x84][..], &length].concat();
+        assert!(connection(&oversized).read_message().await.is_err());
+        assert!(connection(&[0x04, 0x00]).read_message().await.is_err());
+        assert!(connection(&[0x30, 0x80]).read_message().await.is_err());
+        assert!(connection(&[0x30, 0x85, 0, 0, 0, 0, 1]).read_message().await.is_err());
+        // Truncated content
+        assert!(connection(&[0x30, 0x05, 0x01]).read_message().await.is_err());
+    }
+}
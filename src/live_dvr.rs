@@ -0,0 +1,251 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use axum::extract::ws::{Message, WebSocket};
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use futures_util::{stream::StreamExt, SinkExt};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tokio::time::Duration;
+use tracing::{debug, info, trace};
+
+use crate::pre_recording_buffer::PreRecordingBuffer;
+use crate::viewers::ViewerGuard;
+use crate::watermark::Watermark;
+
+// Longest wait between two played back frames, so gaps in the buffer are skipped quickly
+const MAX_FRAME_DELAY_MS: i64 = 1000;
+
+/// Text message of a DVR client, e.g. `{"command":"rewind","seconds":30}`
+#[derive(Debug, Deserialize)]
+#[serde(tag = "command", rename_all = "lowercase")]
+enum DvrCommand {
+    Pause,
+    Play,
+    Live,
+    Seek { timestamp: DateTime<Utc> },
+    Rewind { seconds: f64 },
+    Status,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum DvrMode {
+    Live,
+    Paused,
+    Playback,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum DvrReply {
+    Dvr {
+        mode: DvrMode,
+        position: Option<DateTime<Utc>>,
+        buffer_start: Option<DateTime<Utc>>,
+        buffer_end: Option<DateTime<Utc>>,
+    },
+    Error { message: String },
+}
+
+enum Event {
+    Frame(Bytes),
+    Message(Option<std::result::Result<Message, axum::Error>>),
+}
+
+struct DvrState {
+    mode: DvrMode,
+    // Timestamp of the buffered frame shown last while paused or played back
+    position: Option<DateTime<Utc>>,
+}
+
+impl DvrState {
+    /// Apply a command, returning the buffered frame to show right away (after a seek)
+    async fn apply(&mut self, command: DvrCommand, buffer: &PreRecordingBuffer) -> std::result::Result<Option<Bytes>, String> {
+        match command {
+            DvrCommand::Pause => {
+                if self.mode == DvrMode::Live {
+                    self.position = buffer.get_stats().await.newest_timestamp;
+                }
+                self.mode = DvrMode::Paused;
+                Ok(None)
+            }
+            DvrCommand::Play => {
+                if self.mode == DvrMode::Paused {
+                    self.mode = DvrMode::Playback;
+                }
+                Ok(None)
+            }
+            DvrCommand::Live => {
+                self.mode = DvrMode::Live;
+                self.position = None;
+                Ok(None)
+            }
+            DvrCommand::Seek { timestamp } => self.seek(timestamp, buffer).await.map(Some),
+            DvrCommand::Rewind { seconds } => {
+                if !seconds.is_finite() || seconds <= 0.0 {
+                    return Err("seconds must be a positive number".to_string());
+                }
+                let base = match self.position {
+                    Some(position) => position,
+                    None => Utc::now(),
+                };
+                let target = base - chrono::Duration::milliseconds((seconds * 1000.0) as i64);
+                self.seek(target, buffer).await.map(Some)
+            }
+            DvrCommand::Status => Ok(None),
+        }
+    }
+
+    /// Move to the first buffered frame at `timestamp`, clamped to the buffered range.
+    /// Seeking from live starts playback, a paused client stays paused on the new frame.
+    async fn seek(&mut self, timestamp: DateTime<Utc>, buffer: &PreRecordingBuffer) -> std::result::Result<Bytes, String> {
+        let stats = buffer.get_stats().await;
+        let (Some(oldest), Some(newest)) = (stats.oldest_timestamp, stats.newest_timestamp) else {
+            return Err("The pre-recording buffer is empty".to_string());
+        };
+        let frame = buffer.frame_at_or_after(timestamp.clamp(oldest, newest), false).await
+            .ok_or_else(|| "The pre-recording buffer is empty".to_string())?;
+        self.position = Some(frame.timestamp);
+        if self.mode == DvrMode::Live {
+            self.mode = DvrMode::Playback;
+        }
+        Ok(frame.data)
+    }
+
+    async fn reply(&self, buffer: &PreRecordingBuffer) -> DvrReply {
+        let stats = buffer.get_stats().await;
+        DvrReply::Dvr {
+            mode: self.mode,
+            position: self.position,
+            buffer_start: stats.oldest_timestamp,
+            buffer_end: stats.newest_timestamp,
+        }
+    }
+}
+
+/// Live stream with DVR controls (`/<camera_path>/live?dvr=true`). The client can pause,
+/// seek or rewind within the pre-recording buffer, play back from there at the recorded
+/// pace and return to live; playback that catches up with the buffer switches to live.
+pub async fn handle_dvr_socket(
+    socket: WebSocket,
+    frame_sender: Arc<broadcast::Sender<Bytes>>,
+    buffer: PreRecordingBuffer,
+    camera_id: String,
+    client_addr: SocketAddr,
+    watermark: Option<Watermark>,
+    viewer: ViewerGuard,
+) {
+    let (mut sender, mut receiver) = socket.split();
+    let mut frames = frame_sender.subscribe();
+    info!("DVR client {} connected to camera {} (current connections: {})",
+          client_addr, camera_id, frame_sender.receiver_count());
+
+    let mut state = DvrState { mode: DvrMode::Live, position: None };
+    let mut frames_sent = 0u64;
+
+    loop {
+        let event = match state.mode {
+            DvrMode::Live => tokio::select! {
+                frame = frames.recv() => match frame {
+                    Ok(frame) => Event::Frame(frame),
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        trace!("DVR client {} of camera {} lagging, skipped {} frames", client_addr, camera_id, skipped);
+                        if viewer.lagged(skipped) {
+                            break;
+                        }
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                message = receiver.next() => Event::Message(message),
+                _ = viewer.kicked() => break,
+            },
+            DvrMode::Paused => tokio::select! {
+                message = receiver.next() => Event::Message(message),
+                _ = viewer.kicked() => break,
+            },
+            DvrMode::Playback => {
+                let position = state.position.unwrap_or_else(Utc::now);
+                match buffer.frame_at_or_after(position, true).await {
+                    Some(next) => {
+                        let delay = (next.timestamp - position).num_milliseconds().clamp(0, MAX_FRAME_DELAY_MS);
+                        tokio::select! {
+                            _ = tokio::time::sleep(Duration::from_millis(delay as u64)) => {
+                                state.position = Some(next.timestamp);
+                                Event::Frame(next.data)
+                            }
+                            message = receiver.next() => Event::Message(message),
+                            _ = viewer.kicked() => break,
+                        }
+                    }
+                    None => {
+                        debug!("DVR client {} of camera {} caught up with the live stream", client_addr, camera_id);
+                        state.mode = DvrMode::Live;
+                        state.position = None;
+                        frames = frames.resubscribe();
+                        let reply = serde_json::to_string(&state.reply(&buffer).await).unwrap_or_default();
+                        if sender.send(Message::Text(reply)).await.is_err() {
+                            break;
+                        }
+                        continue;
+                    }
+                }
+            }
+        };
+
+        let frame = match event {
+            Event::Frame(frame) => frame,
+            Event::Message(message) => {
+                let text = match message {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | None | Some(Err(_)) => break,
+                    Some(Ok(_)) => continue,
+                };
+                let was_live = state.mode == DvrMode::Live;
+                let (reply, still) = match serde_json::from_str::<DvrCommand>(&text) {
+                    Ok(command) => {
+                        debug!("DVR client {} of camera {} sent {:?}", client_addr, camera_id, command);
+                        match state.apply(command, &buffer).await {
+                            Ok(still) => (state.reply(&buffer).await, still),
+                            Err(message) => (DvrReply::Error { message }, None),
+                        }
+                    }
+                    Err(e) => (DvrReply::Error { message: format!("Invalid DVR command: {}", e) }, None),
+                };
+                if !was_live && state.mode == DvrMode::Live {
+                    // Skip the frames that queued up while the client was not watching live
+                    frames = frames.resubscribe();
+                }
+                let reply = serde_json::to_string(&reply).unwrap_or_default();
+                if sender.send(Message::Text(reply)).await.is_err() {
+                    break;
+                }
+                match still {
+                    Some(frame) => frame,
+                    None => continue,
+                }
+            }
+        };
+
+        let data = match watermark.clone() {
+            Some(watermark) => match tokio::task::spawn_blocking(move || watermark.apply(&frame)).await {
+                Ok(Ok(data)) => data,
+                Ok(Err(e)) => {
+                    debug!("Failed to watermark frame for DVR client {} of camera {}: {}", client_addr, camera_id, e);
+                    continue;
+                }
+                Err(_) => break,
+            },
+            None => frame.to_vec(),
+        };
+        let data_len = data.len();
+        if sender.send(Message::Binary(data)).await.is_err() {
+            break;
+        }
+        frames_sent += 1;
+        viewer.stats().record_sent(data_len, 1);
+    }
+
+    info!("DVR client {} disconnected from camera {} (sent: {})", client_addr, camera_id, frames_sent);
+}
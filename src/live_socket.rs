@@ -0,0 +1,424 @@
+use std::future::Future;
+use std::io;
+use std::sync::Arc;
+use axum::async_trait;
+use axum::body::Body;
+use axum::extract::ws::{CloseFrame, Message, WebSocket};
+use axum::extract::{FromRequestParts, WebSocketUpgrade};
+use axum::http::{header, request::Parts, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use base64::Engine;
+use bytes::{Buf, BufMut, BytesMut};
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress};
+use futures_util::stream::{SplitSink, SplitStream};
+use futures_util::{SinkExt, StreamExt};
+use hyper::upgrade::{OnUpgrade, Upgraded};
+use hyper_util::rt::TokioIo;
+use sha1::{Digest, Sha1};
+use tokio::io::{AsyncReadExt, AsyncWriteExt, ReadHalf, WriteHalf};
+use tokio::sync::Mutex;
+use tracing::debug;
+
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+// Live clients only send short control messages
+const MAX_MESSAGE_SIZE: usize = 1024 * 1024;
+// Empty stored block ending every sync flush, removed from sent messages (RFC 7692 7.2.1)
+const DEFLATE_TRAILER: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xA;
+
+type Io = TokioIo<Upgraded>;
+
+/// WebSocket upgrade of a `/live` request together with the client's permessage-deflate
+/// offer. axum's WebSocket does not support extensions, so compressed connections are
+/// upgraded and framed here instead.
+pub struct LiveUpgrade {
+    ws: WebSocketUpgrade,
+    deflate: Option<DeflateOffer>,
+}
+
+struct DeflateOffer {
+    key: HeaderValue,
+    on_upgrade: OnUpgrade,
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for LiveUpgrade {
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        // Cloned before WebSocketUpgrade takes the pending upgrade out of the request
+        let deflate = match (parts.headers.get(header::SEC_WEBSOCKET_KEY), parts.extensions.get::<OnUpgrade>()) {
+            (Some(key), Some(on_upgrade)) if offers_deflate(&parts.headers) => Some(DeflateOffer {
+                key: key.clone(),
+                on_upgrade: on_upgrade.clone(),
+            }),
+            _ => None,
+        };
+        let ws = WebSocketUpgrade::from_request_parts(parts, state).await.map_err(IntoResponse::into_response)?;
+        Ok(Self { ws, deflate })
+    }
+}
+
+impl LiveUpgrade {
+    /// The plain axum upgrade, for endpoints that don't negotiate compression
+    pub fn plain(self) -> WebSocketUpgrade {
+        self.ws
+    }
+
+    /// Complete the upgrade, with permessage-deflate if `compression` is set and the client offered it
+    pub fn on_upgrade<F, Fut>(self, compression: bool, callback: F) -> Response
+    where
+        F: FnOnce(LiveSocket) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let offer = match self.deflate {
+            Some(offer) if compression => offer,
+            _ => return self.ws.on_upgrade(move |socket| callback(LiveSocket::Plain(Box::new(socket)))),
+        };
+        tokio::spawn(async move {
+            match offer.on_upgrade.await {
+                Ok(upgraded) => callback(LiveSocket::Deflate(TokioIo::new(upgraded))).await,
+                Err(e) => debug!("Compressed WebSocket upgrade failed: {}", e),
+            }
+        });
+        let accept = Sha1::new()
+            .chain_update(offer.key.as_bytes())
+            .chain_update(ACCEPT_GUID.as_bytes())
+            .finalize();
+        Response::builder()
+            .status(StatusCode::SWITCHING_PROTOCOLS)
+            .header(header::CONNECTION, "upgrade")
+            .header(header::UPGRADE, "websocket")
+            .header(header::SEC_WEBSOCKET_ACCEPT, base64::engine::general_purpose::STANDARD.encode(accept))
+            // Every sent message is compressed on its own
+            .header(header::SEC_WEBSOCKET_EXTENSIONS, "permessage-deflate; server_no_context_takeover")
+            .body(Body::empty())
+            .unwrap()
+    }
+}
+
+// Whether one of the offered extensions is a permessage-deflate variant we can accept. The
+// compressor always uses a 32 KB window, so offers limiting server_max_window_bits are declined.
+fn offers_deflate(headers: &HeaderMap) -> bool {
+    headers.get_all(header::SEC_WEBSOCKET_EXTENSIONS).iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|offer| {
+            let mut params = offer.split(';').map(str::trim);
+            params.next() == Some("permessage-deflate") && params.all(|param| {
+                let (name, value) = match param.split_once('=') {
+                    Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+                    None => (param, None),
+                };
+                match name {
+                    "server_no_context_takeover" | "client_no_context_takeover" => value.is_none(),
+                    "client_max_window_bits" => value.is_none_or(|bits| bits.parse::<u8>().is_ok_and(|bits| (8..=15).contains(&bits))),
+                    "server_max_window_bits" => value == Some("15"),
+                    _ => false,
+                }
+            })
+        })
+}
+
+/// Upgraded `/live` connection, either axum's WebSocket or a raw connection framed here with
+/// permessage-deflate
+pub enum LiveSocket {
+    Plain(Box<WebSocket>),
+    Deflate(Io),
+}
+
+impl LiveSocket {
+    pub fn split(self) -> (LiveSender, LiveReceiver) {
+        match self {
+            LiveSocket::Plain(socket) => {
+                let (sender, receiver) = (*socket).split();
+                (LiveSender::Plain(sender), LiveReceiver::Plain(receiver))
+            }
+            LiveSocket::Deflate(io) => {
+                let (reader, writer) = tokio::io::split(io);
+                let writer = Arc::new(Mutex::new(FrameWriter { io: writer, pending: BytesMut::new() }));
+                let sender = DeflateSender {
+                    writer: writer.clone(),
+                    compress: Compress::new(Compression::fast(), false),
+                };
+                let receiver = DeflateReceiver {
+                    reader,
+                    writer,
+                    decompress: Decompress::new(false),
+                    closed: false,
+                };
+                (LiveSender::Deflate(sender), LiveReceiver::Deflate(receiver))
+            }
+        }
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        matches!(self, LiveSocket::Deflate(_))
+    }
+}
+
+pub enum LiveSender {
+    Plain(SplitSink<WebSocket, Message>),
+    Deflate(DeflateSender),
+}
+
+impl LiveSender {
+    pub async fn send(&mut self, message: Message) -> Result<(), axum::Error> {
+        match self {
+            LiveSender::Plain(sender) => sender.send(message).await,
+            LiveSender::Deflate(sender) => sender.send(message).await.map_err(axum::Error::new),
+        }
+    }
+
+    pub async fn flush(&mut self) -> Result<(), axum::Error> {
+        match self {
+            LiveSender::Plain(sender) => sender.flush().await,
+            LiveSender::Deflate(sender) => sender.writer.lock().await.drain().await.map_err(axum::Error::new),
+        }
+    }
+}
+
+pub enum LiveReceiver {
+    Plain(SplitStream<WebSocket>),
+    Deflate(DeflateReceiver),
+}
+
+impl LiveReceiver {
+    pub async fn next(&mut self) -> Option<Result<Message, axum::Error>> {
+        match self {
+            LiveReceiver::Plain(receiver) => receiver.next().await,
+            LiveReceiver::Deflate(receiver) => receiver.next().await,
+        }
+    }
+}
+
+pub struct DeflateSender {
+    writer: Arc<Mutex<FrameWriter>>,
+    compress: Compress,
+}
+
+impl DeflateSender {
+    async fn send(&mut self, message: Message) -> io::Result<()> {
+        match message {
+            Message::Text(text) => self.send_data(OPCODE_TEXT, text.as_bytes()).await,
+            Message::Binary(data) => self.send_data(OPCODE_BINARY, &data).await,
+            Message::Ping(data) => write_frame(&self.writer, OPCODE_PING, false, &data).await,
+            Message::Pong(data) => write_frame(&self.writer, OPCODE_PONG, false, &data).await,
+            Message::Close(frame) => {
+                let payload = frame.map_or_else(Vec::new, |frame| {
+                    let mut payload = frame.code.to_be_bytes().to_vec();
+                    payload.extend_from_slice(frame.reason.as_bytes());
+                    payload
+                });
+                write_frame(&self.writer, OPCODE_CLOSE, false, &payload).await
+            }
+        }
+    }
+
+    // Messages that don't get smaller (most JPEG frames) are sent uncompressed, RSV1 marks the others
+    async fn send_data(&mut self, opcode: u8, data: &[u8]) -> io::Result<()> {
+        match self.deflate(data) {
+            Some(compressed) => write_frame(&self.writer, opcode, true, &compressed).await,
+            None => write_frame(&self.writer, opcode, false, data).await,
+        }
+    }
+
+    fn deflate(&mut self, data: &[u8]) -> Option<Vec<u8>> {
+        self.compress.reset();
+        let mut output = Vec::with_capacity(data.len() / 2 + 64);
+        loop {
+            let consumed = self.compress.total_in() as usize;
+            if output.capacity() - output.len() < 64 {
+                output.reserve(output.capacity());
+            }
+            self.compress.compress_vec(&data[consumed..], &mut output, FlushCompress::Sync).ok()?;
+            if output.len() >= data.len() {
+                return None;
+            }
+            if self.compress.total_in() as usize == data.len() && output.len() < output.capacity() {
+                break;
+            }
+        }
+        if output.ends_with(&DEFLATE_TRAILER) {
+            output.truncate(output.len() - DEFLATE_TRAILER.len());
+        }
+        Some(output)
+    }
+}
+
+pub struct DeflateReceiver {
+    reader: ReadHalf<Io>,
+    // Pings are answered and close frames echoed right away, like tungstenite does
+    writer: Arc<Mutex<FrameWriter>>,
+    decompress: Decompress,
+    closed: bool,
+}
+
+impl DeflateReceiver {
+    async fn next(&mut self) -> Option<Result<Message, axum::Error>> {
+        if self.closed {
+            return None;
+        }
+        match self.read_message().await {
+            Ok(Some(message)) => {
+                self.closed = matches!(message, Message::Close(_));
+                Some(Ok(message))
+            }
+            Ok(None) => {
+                self.closed = true;
+                None
+            }
+            Err(e) => {
+                self.closed = true;
+                Some(Err(axum::Error::new(e)))
+            }
+        }
+    }
+
+    async fn read_message(&mut self) -> io::Result<Option<Message>> {
+        // Opcode, compression flag and payload of a fragmented data message
+        let mut pending: Option<(u8, bool, Vec<u8>)> = None;
+        loop {
+            let mut head = [0u8; 2];
+            match self.reader.read_exact(&mut head).await {
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof && pending.is_none() => return Ok(None),
+                result => result?,
+            };
+            let fin = head[0] & 0x80 != 0;
+            let compressed = head[0] & 0x40 != 0;
+            let opcode = head[0] & 0x0F;
+            if head[0] & 0x30 != 0 || (compressed && opcode >= OPCODE_CLOSE) {
+                return Err(invalid_data("Reserved WebSocket frame bits are set"));
+            }
+            if head[1] & 0x80 == 0 {
+                return Err(invalid_data("WebSocket client frame is not masked"));
+            }
+            let length = match head[1] & 0x7F {
+                126 => self.reader.read_u16().await? as u64,
+                127 => self.reader.read_u64().await?,
+                length => length as u64,
+            };
+            let buffered = pending.as_ref().map_or(0, |(_, _, data)| data.len());
+            if length > (MAX_MESSAGE_SIZE - buffered) as u64 {
+                return Err(invalid_data("WebSocket message is too large"));
+            }
+            let mut mask = [0u8; 4];
+            self.reader.read_exact(&mut mask).await?;
+            let mut payload = vec![0u8; length as usize];
+            self.reader.read_exact(&mut payload).await?;
+            for (i, byte) in payload.iter_mut().enumerate() {
+                *byte ^= mask[i % 4];
+            }
+
+            match opcode {
+                OPCODE_CLOSE => {
+                    let frame = (payload.len() >= 2).then(|| CloseFrame {
+                        code: u16::from_be_bytes([payload[0], payload[1]]),
+                        reason: String::from_utf8_lossy(&payload[2..]).into_owned().into(),
+                    });
+                    let echo = &payload[..payload.len().min(2)];
+                    let _ = write_frame(&self.writer, OPCODE_CLOSE, false, echo).await;
+                    return Ok(Some(Message::Close(frame)));
+                }
+                OPCODE_PING => {
+                    write_frame(&self.writer, OPCODE_PONG, false, &payload).await?;
+                    return Ok(Some(Message::Ping(payload)));
+                }
+                OPCODE_PONG => return Ok(Some(Message::Pong(payload))),
+                OPCODE_TEXT | OPCODE_BINARY if pending.is_none() => pending = Some((opcode, compressed, payload)),
+                OPCODE_CONTINUATION if !compressed => match pending {
+                    Some((_, _, ref mut data)) => data.extend_from_slice(&payload),
+                    None => return Err(invalid_data("Unexpected WebSocket continuation frame")),
+                },
+                _ => return Err(invalid_data("Unexpected WebSocket frame")),
+            }
+
+            if fin {
+                let Some((opcode, compressed, mut data)) = pending.take() else { continue };
+                if compressed {
+                    data = self.inflate(data)?;
+                }
+                return Ok(Some(if opcode == OPCODE_TEXT {
+                    Message::Text(String::from_utf8(data).map_err(|_| invalid_data("WebSocket text message is not UTF-8"))?)
+                } else {
+                    Message::Binary(data)
+                }));
+            }
+        }
+    }
+
+    // The client may keep its compression context between messages, so the decompressor is never reset
+    fn inflate(&mut self, mut data: Vec<u8>) -> io::Result<Vec<u8>> {
+        data.extend_from_slice(&DEFLATE_TRAILER);
+        let start = self.decompress.total_in();
+        let mut output = Vec::with_capacity(data.len() * 4);
+        loop {
+            let consumed = (self.decompress.total_in() - start) as usize;
+            if output.len() == output.capacity() {
+                if output.len() >= MAX_MESSAGE_SIZE {
+                    return Err(invalid_data("WebSocket message is too large"));
+                }
+                output.reserve(output.capacity());
+            }
+            let produced = output.len();
+            self.decompress.decompress_vec(&data[consumed..], &mut output, FlushDecompress::Sync)
+                .map_err(|e| invalid_data(&format!("Invalid compressed WebSocket message: {}", e)))?;
+            let now_consumed = (self.decompress.total_in() - start) as usize;
+            if now_consumed == data.len() && output.len() < output.capacity() {
+                return Ok(output);
+            }
+            if now_consumed == consumed && output.len() == produced {
+                return Err(invalid_data("Truncated compressed WebSocket message"));
+            }
+        }
+    }
+}
+
+// Frames are queued before they are written, so a send cancelled by a timeout leaves no
+// partial frame behind; the rest goes out ahead of the next frame
+struct FrameWriter {
+    io: WriteHalf<Io>,
+    pending: BytesMut,
+}
+
+impl FrameWriter {
+    async fn drain(&mut self) -> io::Result<()> {
+        while !self.pending.is_empty() {
+            let written = self.io.write(&self.pending).await?;
+            if written == 0 {
+                return Err(io::ErrorKind::WriteZero.into());
+            }
+            self.pending.advance(written);
+        }
+        self.io.flush().await
+    }
+}
+
+// Server frames are never masked and never fragmented
+async fn write_frame(writer: &Mutex<FrameWriter>, opcode: u8, compressed: bool, payload: &[u8]) -> io::Result<()> {
+    let mut writer = writer.lock().await;
+    writer.pending.put_u8(0x80 | if compressed { 0x40 } else { 0 } | opcode);
+    match payload.len() {
+        length if length < 126 => writer.pending.put_u8(length as u8),
+        length if length <= u16::MAX as usize => {
+            writer.pending.put_u8(126);
+            writer.pending.put_u16(length as u16);
+        }
+        length => {
+            writer.pending.put_u8(127);
+            writer.pending.put_u64(length as u64);
+        }
+    }
+    writer.pending.put_slice(payload);
+    writer.drain().await
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
@@ -17,9 +17,12 @@ mod builders;
 mod rtsp_client;
 mod websocket_handler;
 mod transcoder;
+mod test_pattern;
 mod video_stream;
 mod mqtt;
 mod database;
+mod split_database;
+mod frame_write_coalescer;
 mod recording;
 mod websocket_control;
 mod api_config;
@@ -31,9 +34,79 @@ mod handlers;
 mod pre_recording_buffer;
 mod throughput_tracker;
 mod ptz;
+mod ptz_joystick;
+mod ptz_arbiter;
 mod api_ptz;
 mod export_jobs;
+mod frame_spill;
+mod frame_compression;
 mod api_export;
+mod api_maintenance;
+mod triggers;
+mod api_triggers;
+mod resources;
+mod hls_crypto;
+mod hls_signing;
+mod standby;
+mod audit;
+mod api_audit;
+mod api_storage;
+mod smtp;
+mod alerts;
+mod plugins;
+mod audio_level;
+mod tenants;
+mod api_tenants;
+mod integrity;
+mod thumbnails;
+mod roi_stream;
+mod motion_heatmap;
+mod clips;
+mod clock_drift;
+mod tamper;
+mod watermark;
+mod offline_card;
+mod snapshot_source;
+mod frame_archive;
+mod live_dvr;
+mod resume_tokens;
+mod relay;
+mod onvif_source;
+mod mp4_concat;
+mod live_socket;
+mod ldap;
+mod api_auth;
+mod secrets;
+mod api_secrets;
+mod stream_health;
+mod transcoding_profiles;
+mod api_transcoding_profiles;
+mod segment_cache;
+mod stats_store;
+mod api_stats;
+mod api_version;
+mod viewers;
+mod viewer_recording;
+mod recording_schedule;
+mod api_viewers;
+mod api_sync_playback;
+mod attachments;
+mod api_attachments;
+mod jpeg;
+mod camera_validation;
+mod cold_storage;
+mod cli;
+mod bench;
+mod camera_templates;
+mod hls_jobs;
+mod export_redaction;
+mod pipeline_metrics;
+mod onvif_metadata;
+mod diagnostics;
+mod api_diagnostics;
+mod reports;
+mod event_notifications;
+mod api_reports;
 
 use config::Config;
 use errors::{Result, StreamError};
@@ -106,12 +179,16 @@ pub struct Args {
     /// When creating a new config, generate a random admin password instead of the default "manager"
     #[arg(long)]
     random_admin_token: bool,
+
+    #[command(subcommand)]
+    command: Option<cli::Command>,
 }
 
 #[derive(Debug, Clone)]
 pub struct Mp4BufferStats {
     pub frame_count: usize,
     pub size_bytes: usize,
+    pub segment_start: Option<chrono::DateTime<chrono::Utc>>, // Start of the segment being collected
 }
 
 impl Mp4BufferStats {
@@ -119,6 +196,7 @@ impl Mp4BufferStats {
         Self {
             frame_count: 0,
             size_bytes: 0,
+            segment_start: None,
         }
     }
     
@@ -140,6 +218,7 @@ struct CameraStreamInfo {
     mp4_buffer_stats: Arc<tokio::sync::RwLock<Mp4BufferStats>>, // MP4 buffer statistics
     shutdown_flag: Arc<std::sync::atomic::AtomicBool>, // Shared shutdown signal for graceful termination
     latest_frame: Arc<tokio::sync::RwLock<Option<bytes::Bytes>>>, // Latest frame for snapshot API
+    pipeline_control: rtsp_client::PipelineControl, // Runtime control of the FFmpeg pipeline
 }
 
 fn generate_random_token(length: usize) -> String {
@@ -154,6 +233,15 @@ fn generate_random_token(length: usize) -> String {
         .collect()
 }
 
+/// Configuration currently in effect for a camera. Routes registered at startup would otherwise keep
+/// checking the startup tokens after they were rotated or edited.
+async fn current_camera_config(state: &AppState, info: &CameraStreamInfo) -> config::CameraConfig {
+    state.camera_configs.read().await
+        .get(&info.camera_id)
+        .cloned()
+        .unwrap_or_else(|| info.camera_config.clone())
+}
+
 fn save_config_to_file(config: &Config, path: &str) -> Result<()> {
     // Don't include cameras in the saved config (they're loaded from cameras/ directory)
     let mut config_to_save = config.clone();
@@ -172,21 +260,36 @@ pub struct AppState {
     pub recording_manager: Option<Arc<RecordingManager>>,
     transcoding_config: Arc<config::TranscodingConfig>,
     pub recording_config: Option<Arc<config::RecordingConfig>>,
-    pub admin_token: Option<String>,
+    pub admin_token: Arc<std::sync::RwLock<api_config::AdminToken>>, // Replaced at runtime by token rotation
     pub cameras_directory: String,
     start_time: std::time::Instant,
     pub server_config: Arc<config::ServerConfig>, // Store full server config for API access
     pub export_manager: Option<Arc<export_jobs::ExportJobManager>>,
+    pub trigger_manager: Arc<triggers::TriggerManager>,
+    pub audit_log: Arc<audit::AuditLog>,
+    pub alert_manager: Option<Arc<alerts::AlertManager>>,
+    pub report_manager: Option<Arc<reports::ReportManager>>,
+    ffmpeg_version: Option<String>, // First line of `ffmpeg -version` at startup
 }
 
 // CreateCameraRequest moved to api::admin
 
 
-#[tokio::main(flavor = "multi_thread", worker_threads = 16)]
-async fn main() -> Result<()> {
-    // Parse command line arguments first to get verbose flag
+fn main() -> Result<()> {
+    // Parse command line arguments first to get verbose flag and config path
     let args = Args::parse();
-    
+
+    // The runtime must exist before the config is fully loaded, so only its section is read here
+    let runtime_config = config::RuntimeConfig::read_from_file(&args.config);
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(runtime_config.worker_threads())
+        .max_blocking_threads(runtime_config.max_blocking_threads())
+        .enable_all()
+        .build()?;
+    runtime.block_on(run(args, runtime_config))
+}
+
+async fn run(args: Args, runtime_config: config::RuntimeConfig) -> Result<()> {
     // Configure logging based on verbose flag
     let log_level = if args.verbose {
         // Enable verbose logs for our crate and ONVIF PTZ target
@@ -200,15 +303,28 @@ async fn main() -> Result<()> {
         .event_format(CustomFormatter)
         .fmt_fields(tracing_subscriber::fmt::format::DefaultFields::new());
     
+    // Recent lines without colors for diagnostic bundles
+    let recent_logs_layer = tracing_subscriber::fmt::layer()
+        .with_ansi(false)
+        .with_writer(diagnostics::RecentLogs);
+    
     tracing_subscriber::registry()
         .with(tracing_subscriber::EnvFilter::new(log_level))
         .with(fmt_layer)
+        .with(recent_logs_layer)
         .init();
 
     // Display version at startup
     info!("=====================================");
     info!("RTSP Streaming Server v{}", VERSION.trim());
     info!("=====================================");
+    info!("Tokio runtime: {} worker threads, up to {} blocking threads",
+          runtime_config.worker_threads(), runtime_config.max_blocking_threads());
+
+    // Maintenance commands work on the recording databases only, without cameras and HTTP server
+    if let Some(command) = args.command.clone().filter(|command| !matches!(command, cli::Command::Serve)) {
+        return cli::run(command, &args.config).await;
+    }
 
     let config = match Config::load(&args.config) {
         Ok(cfg) => {
@@ -264,6 +380,9 @@ async fn main() -> Result<()> {
         }
     };
 
+    // Cameras without an explicit channel_buffer_size use the configured frame channel capacity
+    video_stream::configure_channel_capacity(config.server.runtime.frame_channel_capacity());
+
     info!("Starting RTSP streaming server on {}:{}", config.server.host, config.server.port);
     
     // Check and create required directories
@@ -313,7 +432,7 @@ async fn main() -> Result<()> {
     }
     
     // Check FFmpeg availability
-    match tokio::process::Command::new("ffmpeg")
+    let ffmpeg_version = match tokio::process::Command::new("ffmpeg")
         .arg("-version")
         .output()
         .await
@@ -323,8 +442,10 @@ async fn main() -> Result<()> {
                 let version_output = String::from_utf8_lossy(&output.stdout);
                 if let Some(first_line) = version_output.lines().next() {
                     info!("FFmpeg found: {}", first_line);
+                    Some(first_line.to_string())
                 } else {
                     info!("FFmpeg is available");
+                    None
                 }
             } else {
                 error!("FFmpeg is installed but failed to run: {}", String::from_utf8_lossy(&output.stderr));
@@ -336,16 +457,19 @@ async fn main() -> Result<()> {
             error!("Please install FFmpeg and ensure it's available in your PATH");
             std::process::exit(1);
         }
-    }
+    };
     
     // Cleanup old HLS directories from previous runs
     mp4::cleanup_old_hls_directories().await;
+    if let Some(ref recording_config) = config.recording {
+        hls_jobs::init(recording_config);
+    }
 
     // Initialize MQTT if enabled
     let mqtt_handle: Option<MqttHandle> = if let Some(mqtt_config) = config.mqtt.clone() {
         if mqtt_config.enabled {
             info!("Initializing MQTT connection to {}", mqtt_config.broker_url);
-            match MqttPublisher::new(mqtt_config).await {
+            match MqttPublisher::new(mqtt_config, config.server.runtime.mqtt_channel_capacity()).await {
                 Ok(publisher) => {
                     match publisher.start().await {
                         Ok(handle) => {
@@ -372,6 +496,8 @@ async fn main() -> Result<()> {
 
     // Initialize recording manager if any recording mode is enabled globally or per-camera
     let recording_manager: Option<Arc<RecordingManager>> = if let Some(recording_config) = &config.recording {
+        segment_cache::configure(recording_config.segment_cache_mb, recording_config.segment_cache_ttl_seconds);
+
         // Global switches that should enable recording manager
         let global_wants_manager =
             recording_config.frame_storage_enabled ||
@@ -418,6 +544,33 @@ async fn main() -> Result<()> {
                             }
                         });
                     }
+
+                    // Start scheduled database compaction if configured
+                    if recording_config.vacuum_interval_hours > 0 {
+                        let manager_clone = manager.clone();
+                        let vacuum_interval = recording_config.vacuum_interval_hours;
+                        let vacuum_mode = recording_config.vacuum_mode;
+                        info!("Scheduling {} database vacuum every {} hours", vacuum_mode, vacuum_interval);
+                        tokio::spawn(async move {
+                            let mut interval = tokio::time::interval(
+                                tokio::time::Duration::from_secs(vacuum_interval * 3600)
+                            );
+                            // Skip the immediate first tick - don't compact during startup
+                            interval.tick().await;
+
+                            loop {
+                                interval.tick().await;
+                                match manager_clone.vacuum_databases(None, vacuum_mode).await {
+                                    Ok(reports) => {
+                                        let reclaimed: i64 = reports.iter().map(|r| r.reclaimed_bytes).sum();
+                                        info!("Scheduled vacuum completed for {} databases, reclaimed {} MB",
+                                              reports.len(), reclaimed / (1024 * 1024));
+                                    }
+                                    Err(e) => error!("Failed to run scheduled vacuum: {}", e),
+                                }
+                            }
+                        });
+                    }
                         
                     Some(manager)
                 }
@@ -446,10 +599,28 @@ async fn main() -> Result<()> {
         None
     };
 
-    // Initialize throughput tracker if MQTT is enabled (always publish to MQTT) or --throughput flag is set (database logging)
+    // Sample CPU and memory usage of the per-camera FFmpeg processes
+    resources::start_sampling_task();
+
+    // Separate stats backend, throughput stats then stay out of the recording databases
+    let stats_backend = match config.stats {
+        Some(ref stats_config) if stats_config.enabled => {
+            match stats_store::StatsBackend::connect(stats_config).await {
+                Ok(backend) => Some(backend),
+                Err(e) => {
+                    error!("Failed to connect to the stats backend at {}: {}", stats_config.url, e);
+                    None
+                }
+            }
+        }
+        _ => None,
+    };
+
+    // Initialize throughput tracker if MQTT is enabled (always publish to MQTT), a stats backend is configured or --throughput flag is set (database logging)
     let throughput_tracker: Option<Arc<throughput_tracker::ThroughputTracker>> = 
-        if mqtt_handle.is_some() || args.throughput {
-            let tracker = Arc::new(throughput_tracker::ThroughputTracker::new_with_mqtt(mqtt_handle.clone(), args.throughput));
+        if mqtt_handle.is_some() || args.throughput || stats_backend.is_some() {
+            let logs_to_backend = stats_backend.is_some();
+            let tracker = Arc::new(throughput_tracker::ThroughputTracker::new_with_mqtt(mqtt_handle.clone(), args.throughput, stats_backend));
             
             // Start the throughput tracking task
             let tracker_clone = tracker.clone();
@@ -461,7 +632,10 @@ async fn main() -> Result<()> {
                 (true, true) => info!("Throughput tracker initialized: MQTT publishing + database logging enabled"),
                 (true, false) => info!("Throughput tracker initialized: MQTT publishing enabled, database logging disabled"),
                 (false, true) => info!("Throughput tracker initialized: Database logging enabled, MQTT publishing disabled"),
-                (false, false) => info!("Throughput tracker initialized: No publishing/logging enabled"), // This shouldn't happen
+                (false, false) => info!("Throughput tracker initialized: MQTT publishing and database logging disabled"),
+            }
+            if logs_to_backend {
+                info!("Throughput stats are written to the stats backend instead of the recording databases");
             }
             
             // Set as global tracker for easy access throughout the application
@@ -472,6 +646,11 @@ async fn main() -> Result<()> {
             None
         };
 
+    ldap::configure(config.server.ldap.as_ref());
+    secrets::init(&config.server.secrets);
+    transcoding_profiles::init(&config.transcoding_profiles);
+    jpeg::configure(config.jpeg_encoder.as_ref());
+
     // Store all camera configurations (enabled and disabled)
     let all_camera_configs = config.cameras.clone();
     
@@ -485,6 +664,10 @@ async fn main() -> Result<()> {
             info!("Camera '{}' is disabled, loading config but not starting stream", camera_id);
             continue;
         }
+        if let Err(e) = config.server.validate_camera_tenant(&camera_config) {
+            error!("Camera '{}' is not started: {}", camera_id, e);
+            continue;
+        }
         
         info!("Configuring camera '{}' on path '{}'...", camera_id, camera_config.path);
         
@@ -509,7 +692,7 @@ async fn main() -> Result<()> {
                     if let Some(recording_config) = &config.recording {
                         info!("Creating {} database for camera '{}'", recording_config.database_type, camera_id);
                         
-                        match database::create_database_provider(recording_config, Some(&camera_id)).await {
+                        match database::create_recording_database_provider(recording_config, &camera_id).await {
                             Ok(database) => {
                                 if let Err(e) = recording_manager_ref.add_camera_database(&camera_id, database.clone()).await {
                                     error!("Failed to add database for camera '{}': {}", camera_id, e);
@@ -534,6 +717,7 @@ async fn main() -> Result<()> {
                 // Extract frame sender, FPS counter, and pre-recording buffer before starting (since start() consumes the video_stream)
                 let frame_sender = video_stream.frame_sender.clone();
                 let fps_counter = video_stream.get_fps_counter();
+                let pipeline_control = video_stream.get_pipeline_control();
                 let pre_recording_buffer = video_stream.pre_recording_buffer.clone();
                 
                 // Create MP4 buffer stats for this camera
@@ -553,6 +737,7 @@ async fn main() -> Result<()> {
                 }
 
                 // Store the camera stream info for this camera's path
+                standby::register_path(&camera_config.path, &camera_id, pipeline_control.clone());
                 camera_streams.insert(camera_config.path.clone(), CameraStreamInfo {
                     camera_id: camera_id.clone(),
                     frame_sender,
@@ -565,6 +750,7 @@ async fn main() -> Result<()> {
                     mp4_buffer_stats,
                     shutdown_flag,
                     latest_frame,
+                    pipeline_control,
                 });
                 info!("Started camera '{}' on path '{}'" , camera_id, camera_config.path);
             }
@@ -603,26 +789,17 @@ async fn main() -> Result<()> {
         }
     }
 
-    let cors_layer = if let Some(origin) = &config.server.cors_allow_origin {
-        if origin == "*" {
-            tower_http::cors::CorsLayer::permissive()
-        } else {
-            match origin.parse::<axum::http::HeaderValue>() {
-                Ok(origin_header) => {
-                    tower_http::cors::CorsLayer::new()
-                        .allow_origin(origin_header)
-                        .allow_methods(tower_http::cors::Any)
-                        .allow_headers(tower_http::cors::Any)
-                }
-                Err(_) => {
-                    warn!("Invalid CORS origin '{}', falling back to permissive", origin);
-                    tower_http::cors::CorsLayer::permissive()
-                }
-            }
-        }
-    } else {
-        tower_http::cors::CorsLayer::permissive()
-    };
+    let cors_server_config = config.server.clone();
+    let cors_layer = tower_http::cors::CorsLayer::new()
+        .allow_origin(tower_http::cors::AllowOrigin::predicate(move |origin, parts| {
+            origin.to_str()
+                .map(|o| cors_server_config.is_origin_allowed(o, parts.uri.path()))
+                .unwrap_or(false)
+        }))
+        .allow_methods(tower_http::cors::Any)
+        .allow_headers(tower_http::cors::Any)
+        // Browsers hide Content-Disposition, Content-Range and the like from scripts unless exposed
+        .expose_headers(tower_http::cors::Any);
 
     // Collect camera streams for API access
     
@@ -640,11 +817,19 @@ async fn main() -> Result<()> {
         recording_manager: recording_manager.clone(),
         transcoding_config: Arc::new(config.transcoding.clone()),
         recording_config: config.recording.clone().map(Arc::new),
-        admin_token: config.server.admin_token.clone(),
+        admin_token: Arc::new(std::sync::RwLock::new(api_config::AdminToken {
+            current: config.server.admin_token.clone(),
+            previous: config.server.previous_admin_token.clone(),
+        })),
         cameras_directory: config.server.cameras_directory.clone().unwrap_or_else(|| "cameras".to_string()),
         start_time: std::time::Instant::now(),
         server_config: Arc::new(config.server.clone()),
         export_manager: export_manager.clone(),
+        trigger_manager: Arc::new(triggers::TriggerManager::new()),
+        audit_log: Arc::new(audit::AuditLog::open(config.recording.as_ref()).await),
+        alert_manager: alerts::AlertManager::from_config(config.smtp.as_ref()),
+        report_manager: reports::ReportManager::from_config(config.reports.as_ref(), config.smtp.as_ref()),
+        ffmpeg_version,
     };
 
     // Build router with camera paths
@@ -703,6 +888,20 @@ async fn main() -> Result<()> {
             }
         ));
 
+        // ROI endpoint: /<camera_path>/roi (WebSocket only, frames cropped to a client-selected region)
+        let roi_path = format!("{}/roi", path);
+        let camera_id_for_roi = stream_info.camera_id.clone();
+        let state_for_roi = app_state.clone();
+        app = app.route(&roi_path, axum::routing::get(
+            move |ws, query, addr| {
+                let camera_id = camera_id_for_roi.clone();
+                let state = state_for_roi.clone();
+                async move {
+                    handlers::dynamic_camera_roi_handler(ws, query, addr, camera_id, state).await
+                }
+            }
+        ));
+
         // Camera page endpoint: /<camera_path> serves test.html
         app = app.route(&path, axum::routing::get(handlers::serve_test_page));
         
@@ -724,6 +923,34 @@ async fn main() -> Result<()> {
             }
         ));
 
+        // MJPEG endpoint: /<camera_path>/mjpeg streams frames as multipart/x-mixed-replace
+        let mjpeg_path = format!("{}/mjpeg", path);
+        let camera_id_for_mjpeg = stream_info.camera_id.clone();
+        let state_for_mjpeg = app_state.clone();
+        app = app.route(&mjpeg_path, axum::routing::get(
+            move |headers, query| {
+                let camera_id = camera_id_for_mjpeg.clone();
+                let state = state_for_mjpeg.clone();
+                async move {
+                    handlers::dynamic_camera_mjpeg_handler(headers, query, camera_id, state).await
+                }
+            }
+        ));
+
+        // Throughput stats: /<camera_path>/control/stats/throughput, from the stats backend or the recording database
+        let throughput_stats_path = format!("{}/control/stats/throughput", path);
+        let camera_id_for_stats = stream_info.camera_id.clone();
+        let state_for_stats = app_state.clone();
+        app = app.route(&throughput_stats_path, axum::routing::get(
+            move |headers, query| {
+                let camera_id = camera_id_for_stats.clone();
+                let state = state_for_stats.clone();
+                async move {
+                    api_stats::api_get_throughput_stats(headers, query, camera_id, state).await
+                }
+            }
+        ));
+
         // REST API endpoints: /<camera_path>/control/*
         if stream_info.recording_manager.is_some() {
             let api_info = stream_info.clone();
@@ -731,224 +958,806 @@ async fn main() -> Result<()> {
             // Start recording
             let start_recording_path = format!("{}/control/recording/start", path);
             let start_info = api_info.clone();
+            let start_state = app_state.clone();
             app = app.route(&start_recording_path, axum::routing::post(
-                move |headers, json| api_recording::api_start_recording(
-                    headers,
-                    json,
-                    start_info.camera_id.clone(),
-                    start_info.camera_config.clone(),
-                    start_info.recording_manager.clone().unwrap(),
-                    start_info.frame_sender.clone(),
-                    start_info.pre_recording_buffer.clone()
-                )
+                move |headers, json| {
+                    let info = start_info.clone();
+                    let state = start_state.clone();
+                    async move {
+                        api_recording::api_start_recording(
+                            headers,
+                            json,
+                            info.camera_id.clone(),
+                            current_camera_config(&state, &info).await,
+                            info.recording_manager.clone().unwrap(),
+                            info.frame_sender.clone(),
+                            info.pre_recording_buffer.clone()
+                        ).await
+                    }
+                }
+            ));
+
+            // Save the last and next seconds of the live stream as a clip session
+            let clip_session_path = format!("{}/control/clip", path);
+            let clip_session_info = api_info.clone();
+            let clip_session_state = app_state.clone();
+            app = app.route(&clip_session_path, axum::routing::post(
+                move |headers, query| {
+                    let info = clip_session_info.clone();
+                    let state = clip_session_state.clone();
+                    async move {
+                        api_recording::api_create_clip(
+                            headers,
+                            query,
+                            info.camera_id.clone(),
+                            current_camera_config(&state, &info).await,
+                            info.recording_manager.clone().unwrap(),
+                            info.frame_sender.clone(),
+                            info.pre_recording_buffer.clone()
+                        ).await
+                    }
+                }
+            ));
+
+            // Extend the max duration of the active recording
+            let extend_recording_path = format!("{}/control/recording/extend", path);
+            let extend_info = api_info.clone();
+            let extend_state = app_state.clone();
+            app = app.route(&extend_recording_path, axum::routing::post(
+                move |headers, json| {
+                    let info = extend_info.clone();
+                    let state = extend_state.clone();
+                    async move {
+                        api_recording::api_extend_recording(
+                            headers,
+                            json,
+                            info.camera_id.clone(),
+                            current_camera_config(&state, &info).await,
+                            info.recording_manager.clone().unwrap()
+                        ).await
+                    }
+                }
             ));
 
             // Stop recording
             let stop_recording_path = format!("{}/control/recording/stop", path);
             let stop_info = api_info.clone();
+            let stop_state = app_state.clone();
             app = app.route(&stop_recording_path, axum::routing::post(
-                move |headers| api_recording::api_stop_recording(
-                    headers,
-                    stop_info.camera_id.clone(),
-                    stop_info.camera_config.clone(),
-                    stop_info.recording_manager.clone().unwrap()
-                )
+                move |headers| {
+                    let info = stop_info.clone();
+                    let state = stop_state.clone();
+                    async move {
+                        api_recording::api_stop_recording(
+                            headers,
+                            info.camera_id.clone(),
+                            current_camera_config(&state, &info).await,
+                            info.recording_manager.clone().unwrap()
+                        ).await
+                    }
+                }
             ));
 
             // List recordings
             let list_recordings_path = format!("{}/control/recordings", path);
             let list_info = api_info.clone();
+            let list_state = app_state.clone();
             app = app.route(&list_recordings_path, axum::routing::get(
-                move |headers, query| api_recording::api_list_recordings(
-                    headers,
-                    query,
-                    list_info.camera_id.clone(),
-                    list_info.camera_config.clone(),
-                    list_info.recording_manager.clone().unwrap()
-                )
+                move |headers, query| {
+                    let info = list_info.clone();
+                    let state = list_state.clone();
+                    async move {
+                        api_recording::api_list_recordings(
+                            headers,
+                            query,
+                            info.camera_id.clone(),
+                            current_camera_config(&state, &info).await,
+                            info.recording_manager.clone().unwrap()
+                        ).await
+                    }
+                }
             ));
 
             // Get recorded frames
             let frames_path = format!("{}/control/recordings/:session_id/frames", path);
             let frames_info = api_info.clone();
+            let frames_state = app_state.clone();
             app = app.route(&frames_path, axum::routing::get(
-                move |headers, path, query| api_recording::api_get_recorded_frames(
-                    headers,
-                    path,
-                    query,
-                    frames_info.camera_config.clone(),
-                    frames_info.recording_manager.clone().unwrap()
-                )
+                move |headers, path, query| {
+                    let info = frames_info.clone();
+                    let state = frames_state.clone();
+                    async move {
+                        api_recording::api_get_recorded_frames(
+                            headers,
+                            path,
+                            query,
+                            current_camera_config(&state, &info).await,
+                            info.recording_manager.clone().unwrap()
+                        ).await
+                    }
+                }
+            ));
+
+            // Download the frames of a session as a ZIP of JPEGs
+            let frames_zip_path = format!("{}/control/recordings/:session_id/frames.zip", path);
+            let frames_zip_info = api_info.clone();
+            let frames_zip_state = app_state.clone();
+            app = app.route(&frames_zip_path, axum::routing::get(
+                move |headers, path, query| {
+                    let info = frames_zip_info.clone();
+                    let state = frames_zip_state.clone();
+                    async move {
+                        api_recording::api_get_frames_zip(
+                            headers,
+                            path,
+                            query,
+                            info.camera_id.clone(),
+                            current_camera_config(&state, &info).await,
+                            info.recording_manager.clone().unwrap()
+                        ).await
+                    }
+                }
+            ));
+
+            // Get session or MP4 segment thumbnail
+            let thumbnail_path = format!("{}/control/recordings/:session_id/thumbnail", path);
+            let thumbnail_info = api_info.clone();
+            let thumbnail_state = app_state.clone();
+            app = app.route(&thumbnail_path, axum::routing::get(
+                move |headers, path, query| {
+                    let info = thumbnail_info.clone();
+                    let state = thumbnail_state.clone();
+                    async move {
+                        api_recording::api_get_session_thumbnail(
+                            headers,
+                            path,
+                            query,
+                            info.camera_id.clone(),
+                            current_camera_config(&state, &info).await,
+                            info.recording_manager.clone().unwrap()
+                        ).await
+                    }
+                }
             ));
 
+            // Frame gaps and MP4 coverage of a session
+            let report_path = format!("{}/control/recordings/:session_id/report", path);
+            let report_info = api_info.clone();
+            let report_state = app_state.clone();
+            app = app.route(&report_path, axum::routing::get(
+                move |headers, path, query| {
+                    let info = report_info.clone();
+                    let state = report_state.clone();
+                    async move {
+                        api_recording::api_get_session_report(
+                            headers,
+                            path,
+                            query,
+                            info.camera_id.clone(),
+                            current_camera_config(&state, &info).await,
+                            info.recording_manager.clone().unwrap()
+                        ).await
+                    }
+                }
+            ));
+
+            // Pre-signed one-time URL for uploading an external clip into a session
+            let upload_url_path = format!("{}/control/recordings/:session_id/attachments/upload-url", path);
+            let upload_url_info = api_info.clone();
+            let upload_url_state = app_state.clone();
+            app = app.route(&upload_url_path, axum::routing::post(
+                move |headers, path, body| {
+                    let info = upload_url_info.clone();
+                    let state = upload_url_state.clone();
+                    async move {
+                        api_attachments::api_create_upload_url(
+                            headers,
+                            path,
+                            body,
+                            info.camera_id.clone(),
+                            current_camera_config(&state, &info).await,
+                            info.recording_manager.clone().unwrap()
+                        ).await
+                    }
+                }
+            ));
+
+            // Upload an attachment, authorized by the token of the pre-signed URL
+            let upload_attachment_path = format!("{}/control/recordings/:session_id/attachments/upload/:token", path);
+            let upload_attachment_info = api_info.clone();
+            app = app.route(&upload_attachment_path, axum::routing::put(
+                move |path, body: axum::body::Body| {
+                    let info = upload_attachment_info.clone();
+                    async move {
+                        api_attachments::api_upload_attachment(
+                            path,
+                            body,
+                            info.camera_id.clone(),
+                            info.recording_manager.clone().unwrap()
+                        ).await
+                    }
+                }
+            ));
+
+            // List the attachments of a session
+            let list_attachments_path = format!("{}/control/recordings/:session_id/attachments", path);
+            let list_attachments_info = api_info.clone();
+            let list_attachments_state = app_state.clone();
+            app = app.route(&list_attachments_path, axum::routing::get(
+                move |headers, path| {
+                    let info = list_attachments_info.clone();
+                    let state = list_attachments_state.clone();
+                    async move {
+                        api_attachments::api_list_attachments(
+                            headers,
+                            path,
+                            info.camera_id.clone(),
+                            current_camera_config(&state, &info).await,
+                            info.recording_manager.clone().unwrap()
+                        ).await
+                    }
+                }
+            ));
+
+            // Download an attachment
+            let download_attachment_path = format!("{}/control/recordings/:session_id/attachments/:attachment_id", path);
+            let download_attachment_info = api_info.clone();
+            let download_attachment_state = app_state.clone();
+            app = app.route(&download_attachment_path, axum::routing::get(
+                move |headers, path| {
+                    let info = download_attachment_info.clone();
+                    let state = download_attachment_state.clone();
+                    async move {
+                        api_attachments::api_download_attachment(
+                            headers,
+                            path,
+                            info.camera_id.clone(),
+                            current_camera_config(&state, &info).await,
+                            info.recording_manager.clone().unwrap()
+                        ).await
+                    }
+                }
+            ));
+
+            // Delete an attachment
+            let delete_attachment_path = format!("{}/control/recordings/:session_id/attachments/:attachment_id", path);
+            let delete_attachment_info = api_info.clone();
+            let delete_attachment_state = app_state.clone();
+            app = app.route(&delete_attachment_path, axum::routing::delete(
+                move |headers, path| {
+                    let info = delete_attachment_info.clone();
+                    let state = delete_attachment_state.clone();
+                    async move {
+                        api_attachments::api_delete_attachment(
+                            headers,
+                            path,
+                            info.camera_id.clone(),
+                            current_camera_config(&state, &info).await,
+                            info.recording_manager.clone().unwrap()
+                        ).await
+                    }
+                }
+            ));
+
+            // Get motion heatmap aggregated over a time range
+            let heatmap_path = format!("{}/control/heatmap", path);
+            let heatmap_info = api_info.clone();
+            let heatmap_state = app_state.clone();
+            app = app.route(&heatmap_path, axum::routing::get(
+                move |headers, query| {
+                    let info = heatmap_info.clone();
+                    let state = heatmap_state.clone();
+                    async move {
+                        api_recording::api_get_motion_heatmap(
+                            headers,
+                            query,
+                            info.camera_id.clone(),
+                            current_camera_config(&state, &info).await,
+                            info.recording_manager.clone().unwrap()
+                        ).await
+                    }
+                }
+            ));
+
+            // Animated GIF/WebP preview clip rendered from stored frames
+            for format in [clips::ClipFormat::Gif, clips::ClipFormat::Webp] {
+                let clip_path = format!("{}/control/recordings/clip.{}", path, format.extension());
+                let clip_info = api_info.clone();
+                let clip_state = app_state.clone();
+                app = app.route(&clip_path, axum::routing::get(
+                    move |headers, query| {
+                        let info = clip_info.clone();
+                        let state = clip_state.clone();
+                        async move {
+                            api_recording::api_get_clip(
+                                headers,
+                                query,
+                                format,
+                                info.camera_id.clone(),
+                                current_camera_config(&state, &info).await,
+                                info.recording_manager.clone().unwrap()
+                            ).await
+                        }
+                    }
+                ));
+            }
+
             // Get single frame by timestamp
             let frame_by_timestamp_path = format!("{}/control/recordings/frames/:timestamp", path);
             let frame_info = api_info.clone();
+            let frame_state = app_state.clone();
             app = app.route(&frame_by_timestamp_path, axum::routing::get(
-                move |headers, path, query| api_recording::api_get_frame_by_timestamp(
-                    headers,
-                    path,
-                    query,
-                    frame_info.camera_id.clone(),
-                    frame_info.camera_config.clone(),
-                    frame_info.recording_manager.clone().unwrap()
-                )
+                move |headers, path, query| {
+                    let info = frame_info.clone();
+                    let state = frame_state.clone();
+                    async move {
+                        api_recording::api_get_frame_by_timestamp(
+                            headers,
+                            path,
+                            query,
+                            info.camera_id.clone(),
+                            current_camera_config(&state, &info).await,
+                            info.recording_manager.clone().unwrap()
+                        ).await
+                    }
+                }
             ));
 
             // Get active recording
             let active_recording_path = format!("{}/control/recording/active", path);
             let active_info = api_info.clone();
+            let active_state = app_state.clone();
             app = app.route(&active_recording_path, axum::routing::get(
-                move |headers| api_recording::api_get_active_recording(
-                    headers,
-                    active_info.camera_id.clone(),
-                    active_info.camera_config.clone(),
-                    active_info.recording_manager.clone().unwrap()
-                )
+                move |headers| {
+                    let info = active_info.clone();
+                    let state = active_state.clone();
+                    async move {
+                        api_recording::api_get_active_recording(
+                            headers,
+                            info.camera_id.clone(),
+                            current_camera_config(&state, &info).await,
+                            info.recording_manager.clone().unwrap()
+                        ).await
+                    }
+                }
+            ));
+
+            // Pause/resume frame, MP4 or HLS storage of the running recording
+            let storage_pause_path = format!("{}/control/recording/:storage/:action", path);
+            let storage_pause_info = api_info.clone();
+            let storage_pause_state = app_state.clone();
+            app = app.route(&storage_pause_path, axum::routing::post(
+                move |headers, path| {
+                    let info = storage_pause_info.clone();
+                    let state = storage_pause_state.clone();
+                    async move {
+                        api_recording::api_set_storage_paused(
+                            headers,
+                            path,
+                            info.camera_id.clone(),
+                            current_camera_config(&state, &info).await,
+                            info.recording_manager.clone().unwrap()
+                        ).await
+                    }
+                }
             ));
 
             // Get recording database size
             let size_recording_path = format!("{}/control/recording/size", path);
             let size_info = api_info.clone();
+            let size_state = app_state.clone();
             app = app.route(&size_recording_path, axum::routing::get(
-                move |headers| api_recording::api_get_recording_size(
-                    headers,
-                    size_info.camera_id.clone(),
-                    size_info.camera_config.clone(),
-                    size_info.recording_manager.clone().unwrap()
-                )
+                move |headers| {
+                    let info = size_info.clone();
+                    let state = size_state.clone();
+                    async move {
+                        api_recording::api_get_recording_size(
+                            headers,
+                            info.camera_id.clone(),
+                            current_camera_config(&state, &info).await,
+                            info.recording_manager.clone().unwrap()
+                        ).await
+                    }
+                }
             ));
 
             // Set session keep flag
             let keep_flag_path = format!("{}/control/recordings/:session_id/keep", path);
             let keep_info = api_info.clone();
+            let keep_state = app_state.clone();
             app = app.route(&keep_flag_path, axum::routing::put(
-                move |headers, path, query| api_recording::api_set_session_keep_flag(
-                    headers,
-                    path,
-                    query,
-                    keep_info.camera_id.clone(),
-                    keep_info.camera_config.clone(),
-                    keep_info.recording_manager.clone().unwrap()
-                )
+                move |headers, path, query| {
+                    let info = keep_info.clone();
+                    let state = keep_state.clone();
+                    async move {
+                        api_recording::api_set_session_keep_flag(
+                            headers,
+                            path,
+                            query,
+                            info.camera_id.clone(),
+                            current_camera_config(&state, &info).await,
+                            info.recording_manager.clone().unwrap()
+                        ).await
+                    }
+                }
             ));
 
             // List MP4 segments
             let segments_path = format!("{}/control/recordings/mp4/segments", path);
             let segments_info = api_info.clone();
+            let segments_state = app_state.clone();
             app = app.route(&segments_path, axum::routing::get(
-                move |headers, query| api_recording::api_list_mp4_segments(
-                    headers,
-                    query,
-                    segments_info.camera_id.clone(),
-                    segments_info.camera_config.clone(),
-                    segments_info.recording_manager.clone().unwrap()
-                )
+                move |headers, query| {
+                    let info = segments_info.clone();
+                    let state = segments_state.clone();
+                    async move {
+                        api_recording::api_list_mp4_segments(
+                            headers,
+                            query,
+                            info.camera_id.clone(),
+                            current_camera_config(&state, &info).await,
+                            info.recording_manager.clone().unwrap()
+                        ).await
+                    }
+                }
             ));
 
             // Stream individual MP4 segments
             let stream_mp4_path = format!("{}/control/recordings/mp4/segments/:filename", path);
             let stream_info = api_info.clone();
+            let stream_state = app_state.clone();
             app = app.route(&stream_mp4_path, axum::routing::get(
-                move |headers, path| api_recording::api_stream_mp4_segment(
-                    headers,
-                    path,
-                    stream_info.camera_id.clone(),
-                    stream_info.camera_config.clone(),
-                    stream_info.recording_manager.clone().unwrap()
-                )
+                move |headers, path| {
+                    let info = stream_info.clone();
+                    let state = stream_state.clone();
+                    async move {
+                        api_recording::api_stream_mp4_segment(
+                            headers,
+                            path,
+                            info.camera_id.clone(),
+                            current_camera_config(&state, &info).await,
+                            info.recording_manager.clone().unwrap()
+                        ).await
+                    }
+                }
+            ));
+
+            // Stored MP4 segments of a time range as one continuous MP4
+            let mp4_concat_path = format!("{}/control/recordings/mp4/concat", path);
+            let mp4_concat_info = api_info.clone();
+            let mp4_concat_state = app_state.clone();
+            app = app.route(&mp4_concat_path, axum::routing::get(
+                move |headers, query| {
+                    let info = mp4_concat_info.clone();
+                    let state = mp4_concat_state.clone();
+                    async move {
+                        api_recording::api_stream_mp4_concat(
+                            headers,
+                            query,
+                            info.camera_id.clone(),
+                            current_camera_config(&state, &info).await,
+                            info.recording_manager.clone().unwrap()
+                        ).await
+                    }
+                }
+            ));
+
+            // Rehydrate archived MP4 segments of a time range from cold storage
+            let rehydrate_path = format!("{}/control/recordings/mp4/rehydrate", path);
+            let rehydrate_info = api_info.clone();
+            let rehydrate_state = app_state.clone();
+            app = app.route(&rehydrate_path, axum::routing::post(
+                move |headers, query| {
+                    let info = rehydrate_info.clone();
+                    let state = rehydrate_state.clone();
+                    async move {
+                        api_recording::api_rehydrate_recordings(
+                            headers,
+                            query,
+                            info.camera_id.clone(),
+                            current_camera_config(&state, &info).await,
+                            info.recording_manager.clone().unwrap()
+                        ).await
+                    }
+                }
+            ));
+
+            // Progress of a rehydration job
+            let rehydration_job_path = format!("{}/control/recordings/mp4/rehydrate/:job_id", path);
+            let rehydration_job_info = api_info.clone();
+            let rehydration_job_state = app_state.clone();
+            app = app.route(&rehydration_job_path, axum::routing::get(
+                move |headers, path| {
+                    let info = rehydration_job_info.clone();
+                    let state = rehydration_job_state.clone();
+                    async move {
+                        api_recording::api_get_rehydration_job(
+                            headers,
+                            path,
+                            info.camera_id.clone(),
+                            current_camera_config(&state, &info).await
+                        ).await
+                    }
+                }
             ));
 
             // HLS timerange playlist
             let hls_timerange_path = format!("{}/control/recordings/hls/timerange", path);
             let hls_info = api_info.clone();
+            let hls_state = app_state.clone();
             app = app.route(&hls_timerange_path, axum::routing::get(
-                move |headers, query| api_recording::api_serve_hls_timerange(
-                    headers,
-                    query,
-                    hls_info.camera_id.clone(),
-                    hls_info.camera_config.clone(),
-                    hls_info.recording_manager.clone().unwrap()
-                )
+                move |headers, query| {
+                    let info = hls_info.clone();
+                    let state = hls_state.clone();
+                    async move {
+                        api_recording::api_serve_hls_timerange(
+                            headers,
+                            query,
+                            info.camera_id.clone(),
+                            current_camera_config(&state, &info).await,
+                            info.recording_manager.clone().unwrap()
+                        ).await
+                    }
+                }
+            ));
+
+            // Progress of a queued HLS playlist generation
+            let hls_job_path = format!("{}/control/recordings/hls/jobs/:job_id", path);
+            let hls_job_info = api_info.clone();
+            let hls_job_state = app_state.clone();
+            app = app.route(&hls_job_path, axum::routing::get(
+                move |headers, path| {
+                    let info = hls_job_info.clone();
+                    let state = hls_job_state.clone();
+                    async move {
+                        api_recording::api_get_hls_job(
+                            headers,
+                            path,
+                            info.camera_id.clone(),
+                            current_camera_config(&state, &info).await
+                        ).await
+                    }
+                }
+            ));
+
+            // Signed HLS playlist URLs for players without Authorization header support
+            let hls_signed_url_path = format!("{}/control/recordings/hls/signed-url", path);
+            let hls_signed_url_info = api_info.clone();
+            let hls_signed_url_state = app_state.clone();
+            app = app.route(&hls_signed_url_path, axum::routing::post(
+                move |headers, json| {
+                    let info = hls_signed_url_info.clone();
+                    let state = hls_signed_url_state.clone();
+                    async move {
+                        api_recording::api_create_hls_signed_url(
+                            headers,
+                            json,
+                            info.camera_id.clone(),
+                            current_camera_config(&state, &info).await,
+                            info.recording_manager.clone().unwrap()
+                        ).await
+                    }
+                }
+            ));
+
+            // WebVTT track with the events of an HLS timerange playlist
+            let hls_events_path = format!("{}/control/recordings/hls/events.vtt", path);
+            let hls_events_info = api_info.clone();
+            let hls_events_state = app_state.clone();
+            app = app.route(&hls_events_path, axum::routing::get(
+                move |headers, query| {
+                    let info = hls_events_info.clone();
+                    let state = hls_events_state.clone();
+                    async move {
+                        api_recording::api_serve_hls_events_vtt(
+                            headers,
+                            query,
+                            info.camera_id.clone(),
+                            current_camera_config(&state, &info).await,
+                            info.recording_manager.clone().unwrap()
+                        ).await
+                    }
+                }
+            ));
+
+            // Timeline events (triggers, PTZ moves, bookmarks)
+            let events_path = format!("{}/control/recordings/events", path);
+            let events_info = api_info.clone();
+            let events_state = app_state.clone();
+            let bookmark_info = api_info.clone();
+            let bookmark_state = app_state.clone();
+            app = app.route(&events_path, axum::routing::get(
+                move |headers, query| {
+                    let info = events_info.clone();
+                    let state = events_state.clone();
+                    async move {
+                        api_recording::api_list_events(
+                            headers,
+                            query,
+                            info.camera_id.clone(),
+                            current_camera_config(&state, &info).await,
+                            info.recording_manager.clone().unwrap()
+                        ).await
+                    }
+                }
+            ).post(
+                move |headers, json| {
+                    let info = bookmark_info.clone();
+                    let state = bookmark_state.clone();
+                    async move {
+                        api_recording::api_add_bookmark(
+                            headers,
+                            json,
+                            info.camera_id.clone(),
+                            current_camera_config(&state, &info).await,
+                            info.recording_manager.clone().unwrap()
+                        ).await
+                    }
+                }
+            ));
+
+            // Frames or MP4 clip of an event window
+            let event_frames_path = format!("{}/control/events/:event_id/frames", path);
+            let event_frames_info = api_info.clone();
+            let event_frames_state = app_state.clone();
+            app = app.route(&event_frames_path, axum::routing::get(
+                move |headers, path, query| {
+                    let info = event_frames_info.clone();
+                    let state = event_frames_state.clone();
+                    async move {
+                        api_recording::api_get_event_frames(
+                            headers,
+                            path,
+                            query,
+                            info.camera_id.clone(),
+                            current_camera_config(&state, &info).await,
+                            info.recording_manager.clone().unwrap()
+                        ).await
+                    }
+                }
             ));
 
             // HLS segments
             let hls_segments_path = format!("{}/control/recordings/hls/segments/:playlist_id/:segment_name", path);
             let hls_segment_info = api_info.clone();
+            let hls_segment_state = app_state.clone();
             app = app.route(&hls_segments_path, axum::routing::get(
-                move |headers, path| api_recording::api_serve_hls_segment(
-                    headers,
-                    path,
-                    hls_segment_info.camera_id.clone(),
-                    hls_segment_info.camera_config.clone(),
-                    hls_segment_info.recording_manager.clone().unwrap()
-                )
+                move |headers, path, query| {
+                    let info = hls_segment_info.clone();
+                    let state = hls_segment_state.clone();
+                    async move {
+                        api_recording::api_serve_hls_segment(
+                            headers,
+                            path,
+                            query,
+                            info.camera_id.clone(),
+                            current_camera_config(&state, &info).await,
+                            info.recording_manager.clone().unwrap()
+                        ).await
+                    }
+                }
+            ));
+
+            // HLS encryption keys
+            let hls_key_path = format!("{}/control/recordings/hls/keys/:key_id", path);
+            let hls_key_info = api_info.clone();
+            let hls_key_state = app_state.clone();
+            app = app.route(&hls_key_path, axum::routing::get(
+                move |headers, path, query| {
+                    let info = hls_key_info.clone();
+                    let state = hls_key_state.clone();
+                    async move {
+                        api_recording::api_serve_hls_key(
+                            headers,
+                            path,
+                            query,
+                            info.camera_id.clone(),
+                            current_camera_config(&state, &info).await,
+                            info.recording_manager.clone().unwrap()
+                        ).await
+                    }
+                }
             ));
 
             // DELETE endpoints for recordings
             // Delete entire recording session
             let delete_session_path = format!("{}/control/recordings/sessions/:session_id", path);
             let delete_session_info = api_info.clone();
+            let delete_session_state = app_state.clone();
             app = app.route(&delete_session_path, axum::routing::delete(
-                move |headers, path| api_recording::api_delete_recording_session(
-                    headers,
-                    path,
-                    delete_session_info.camera_id.clone(),
-                    delete_session_info.camera_config.clone(),
-                    delete_session_info.recording_manager.clone().unwrap()
-                )
+                move |headers, path| {
+                    let info = delete_session_info.clone();
+                    let state = delete_session_state.clone();
+                    async move {
+                        api_recording::api_delete_recording_session(
+                            headers,
+                            path,
+                            info.camera_id.clone(),
+                            current_camera_config(&state, &info).await,
+                            info.recording_manager.clone().unwrap()
+                        ).await
+                    }
+                }
             ));
 
             // Delete single MP4 segment
             let delete_mp4_path = format!("{}/control/recordings/mp4/segments/:filename", path);
             let delete_mp4_info = api_info.clone();
+            let delete_mp4_state = app_state.clone();
             app = app.route(&delete_mp4_path, axum::routing::delete(
-                move |headers, path| api_recording::api_delete_mp4_segment(
-                    headers,
-                    path,
-                    delete_mp4_info.camera_id.clone(),
-                    delete_mp4_info.camera_config.clone(),
-                    delete_mp4_info.recording_manager.clone().unwrap()
-                )
+                move |headers, path| {
+                    let info = delete_mp4_info.clone();
+                    let state = delete_mp4_state.clone();
+                    async move {
+                        api_recording::api_delete_mp4_segment(
+                            headers,
+                            path,
+                            info.camera_id.clone(),
+                            current_camera_config(&state, &info).await,
+                            info.recording_manager.clone().unwrap()
+                        ).await
+                    }
+                }
             ));
 
             // Bulk delete MP4 segments
             let bulk_delete_mp4_path = format!("{}/control/recordings/mp4/segments", path);
             let bulk_delete_mp4_info = api_info.clone();
+            let bulk_delete_mp4_state = app_state.clone();
             app = app.route(&bulk_delete_mp4_path, axum::routing::delete(
-                move |headers, json| api_recording::api_delete_mp4_segments_bulk(
-                    headers,
-                    bulk_delete_mp4_info.camera_id.clone(),
-                    bulk_delete_mp4_info.camera_config.clone(),
-                    bulk_delete_mp4_info.recording_manager.clone().unwrap(),
-                    json
-                )
+                move |headers, json| {
+                    let info = bulk_delete_mp4_info.clone();
+                    let state = bulk_delete_mp4_state.clone();
+                    async move {
+                        api_recording::api_delete_mp4_segments_bulk(
+                            headers,
+                            info.camera_id.clone(),
+                            current_camera_config(&state, &info).await,
+                            info.recording_manager.clone().unwrap(),
+                            json
+                        ).await
+                    }
+                }
             ));
 
             // Delete HLS segments by session
             let delete_hls_session_path = format!("{}/control/recordings/hls/sessions/:session_id", path);
             let delete_hls_session_info = api_info.clone();
+            let delete_hls_session_state = app_state.clone();
             app = app.route(&delete_hls_session_path, axum::routing::delete(
-                move |headers, path| api_recording::api_delete_hls_segments_by_session(
-                    headers,
-                    path,
-                    delete_hls_session_info.camera_id.clone(),
-                    delete_hls_session_info.camera_config.clone(),
-                    delete_hls_session_info.recording_manager.clone().unwrap()
-                )
+                move |headers, path| {
+                    let info = delete_hls_session_info.clone();
+                    let state = delete_hls_session_state.clone();
+                    async move {
+                        api_recording::api_delete_hls_segments_by_session(
+                            headers,
+                            path,
+                            info.camera_id.clone(),
+                            current_camera_config(&state, &info).await,
+                            info.recording_manager.clone().unwrap()
+                        ).await
+                    }
+                }
             ));
 
             // Delete HLS segments by timerange
             let delete_hls_timerange_path = format!("{}/control/recordings/hls/timerange", path);
             let delete_hls_timerange_info = api_info.clone();
+            let delete_hls_timerange_state = app_state.clone();
             app = app.route(&delete_hls_timerange_path, axum::routing::delete(
-                move |headers, query| api_recording::api_delete_hls_segments_by_timerange(
-                    headers,
-                    query,
-                    delete_hls_timerange_info.camera_id.clone(),
-                    delete_hls_timerange_info.camera_config.clone(),
-                    delete_hls_timerange_info.recording_manager.clone().unwrap()
-                )
+                move |headers, query| {
+                    let info = delete_hls_timerange_info.clone();
+                    let state = delete_hls_timerange_state.clone();
+                    async move {
+                        api_recording::api_delete_hls_segments_by_timerange(
+                            headers,
+                            query,
+                            info.camera_id.clone(),
+                            current_camera_config(&state, &info).await,
+                            info.recording_manager.clone().unwrap()
+                        ).await
+                    }
+                }
             ));
 
             // Export endpoints (only if export_manager is available)
@@ -956,88 +1765,168 @@ async fn main() -> Result<()> {
                 // Start export job
                 let export_start_path = format!("{}/control/recordings/mp4/export", path);
                 let export_start_info = api_info.clone();
+                let export_start_state = app_state.clone();
                 let export_start_mgr = export_mgr.clone();
                 app = app.route(&export_start_path, axum::routing::post(
-                    move |headers, query| api_export::api_export_start(
-                        headers,
-                        query,
-                        export_start_info.camera_id.clone(),
-                        export_start_info.camera_config.clone(),
-                        export_start_mgr.clone()
-                    )
+                    move |headers, query| {
+                        let info = export_start_info.clone();
+                        let state = export_start_state.clone();
+                        async move {
+                            api_export::api_export_start(
+                                headers,
+                                query,
+                                info.camera_id.clone(),
+                                current_camera_config(&state, &info).await,
+                                export_start_mgr.clone(),
+                                info.recording_manager.clone()
+                            ).await
+                        }
+                    }
                 ));
 
                 // List export jobs
                 let export_list_path = format!("{}/control/recordings/mp4/export/jobs", path);
                 let export_list_info = api_info.clone();
+                let export_list_state = app_state.clone();
                 let export_list_mgr = export_mgr.clone();
                 app = app.route(&export_list_path, axum::routing::get(
-                    move |headers, query| api_export::api_export_list_jobs(
-                        headers,
-                        query,
-                        export_list_info.camera_id.clone(),
-                        export_list_info.camera_config.clone(),
-                        export_list_mgr.clone()
-                    )
+                    move |headers, query| {
+                        let info = export_list_info.clone();
+                        let state = export_list_state.clone();
+                        async move {
+                            api_export::api_export_list_jobs(
+                                headers,
+                                query,
+                                info.camera_id.clone(),
+                                current_camera_config(&state, &info).await,
+                                export_list_mgr.clone()
+                            ).await
+                        }
+                    }
                 ));
 
                 // Get export job status
                 let export_get_path = format!("{}/control/recordings/mp4/export/jobs/:job_id", path);
                 let export_get_info = api_info.clone();
+                let export_get_state = app_state.clone();
                 let export_get_mgr = export_mgr.clone();
                 app = app.route(&export_get_path, axum::routing::get(
-                    move |headers, path_param| api_export::api_export_get_job(
-                        headers,
-                        path_param,
-                        export_get_info.camera_id.clone(),
-                        export_get_info.camera_config.clone(),
-                        export_get_mgr.clone()
-                    )
+                    move |headers, path_param| {
+                        let info = export_get_info.clone();
+                        let state = export_get_state.clone();
+                        async move {
+                            api_export::api_export_get_job(
+                                headers,
+                                path_param,
+                                info.camera_id.clone(),
+                                current_camera_config(&state, &info).await,
+                                export_get_mgr.clone()
+                            ).await
+                        }
+                    }
                 ));
 
                 // Download exported file
                 let export_download_path = format!("{}/control/recordings/mp4/export/download/:job_id", path);
                 let export_download_info = api_info.clone();
+                let export_download_state = app_state.clone();
                 let export_download_mgr = export_mgr.clone();
                 app = app.route(&export_download_path, axum::routing::get(
-                    move |headers, path_param| api_export::api_export_download(
-                        headers,
-                        path_param,
-                        export_download_info.camera_id.clone(),
-                        export_download_info.camera_config.clone(),
-                        export_download_mgr.clone()
-                    )
+                    move |headers, path_param| {
+                        let info = export_download_info.clone();
+                        let state = export_download_state.clone();
+                        async move {
+                            api_export::api_export_download(
+                                headers,
+                                path_param,
+                                info.camera_id.clone(),
+                                current_camera_config(&state, &info).await,
+                                export_download_mgr.clone()
+                            ).await
+                        }
+                    }
                 ));
             }
         }
 
         // PTZ control endpoints (handlers will validate if enabled in camera config)
         let ptz_info = stream_info.clone();
+        let ptz_state = app_state.clone();
         let ptz_move_path = format!("{}/control/ptz/move", path);
         app = app.route(&ptz_move_path, axum::routing::post(move |headers, json| {
-            let cfg = ptz_info.camera_config.clone();
-            async move { api_ptz::api_ptz_move(headers, json, cfg).await }
+            let info = ptz_info.clone();
+            let state = ptz_state.clone();
+            let camera_id = info.camera_id.clone();
+            let recording_manager = info.recording_manager.clone();
+            async move {
+                let cfg = current_camera_config(&state, &info).await;
+                api_ptz::api_ptz_move(headers, json, camera_id, cfg, recording_manager).await
+            }
         }));
 
         let ptz_info2 = stream_info.clone();
+        let ptz_state2 = app_state.clone();
         let ptz_stop_path = format!("{}/control/ptz/stop", path);
         app = app.route(&ptz_stop_path, axum::routing::post(move |headers| {
-            let cfg = ptz_info2.camera_config.clone();
-            async move { api_ptz::api_ptz_stop(headers, cfg).await }
+            let info = ptz_info2.clone();
+            let state = ptz_state2.clone();
+            let camera_id = info.camera_id.clone();
+            async move {
+                let cfg = current_camera_config(&state, &info).await;
+                api_ptz::api_ptz_stop(headers, camera_id, cfg).await
+            }
         }));
 
         let ptz_info3 = stream_info.clone();
+        let ptz_state3 = app_state.clone();
         let ptz_goto_preset_path = format!("{}/control/ptz/goto_preset", path);
         app = app.route(&ptz_goto_preset_path, axum::routing::post(move |headers, json| {
-            let cfg = ptz_info3.camera_config.clone();
-            async move { api_ptz::api_ptz_goto_preset(headers, json, cfg).await }
+            let info = ptz_info3.clone();
+            let state = ptz_state3.clone();
+            let camera_id = info.camera_id.clone();
+            let recording_manager = info.recording_manager.clone();
+            async move {
+                let cfg = current_camera_config(&state, &info).await;
+                api_ptz::api_ptz_goto_preset(headers, json, camera_id, cfg, recording_manager).await
+            }
         }));
 
         let ptz_info4 = stream_info.clone();
+        let ptz_state4 = app_state.clone();
         let ptz_set_preset_path = format!("{}/control/ptz/set_preset", path);
         app = app.route(&ptz_set_preset_path, axum::routing::post(move |headers, json| {
-            let cfg = ptz_info4.camera_config.clone();
-            async move { api_ptz::api_ptz_set_preset(headers, json, cfg).await }
+            let info = ptz_info4.clone();
+            let state = ptz_state4.clone();
+            async move {
+                let cfg = current_camera_config(&state, &info).await;
+                api_ptz::api_ptz_set_preset(headers, json, cfg).await
+            }
+        }));
+
+        let ptz_info5 = stream_info.clone();
+        let ptz_state5 = app_state.clone();
+        let ptz_relative_move_path = format!("{}/control/ptz/relative_move", path);
+        app = app.route(&ptz_relative_move_path, axum::routing::post(move |headers, json| {
+            let info = ptz_info5.clone();
+            let state = ptz_state5.clone();
+            let camera_id = info.camera_id.clone();
+            let recording_manager = info.recording_manager.clone();
+            async move {
+                let cfg = current_camera_config(&state, &info).await;
+                api_ptz::api_ptz_relative_move(headers, json, camera_id, cfg, recording_manager).await
+            }
+        }));
+
+        let ptz_info6 = stream_info.clone();
+        let ptz_state6 = app_state.clone();
+        let ptz_capabilities_path = format!("{}/control/ptz/capabilities", path);
+        app = app.route(&ptz_capabilities_path, axum::routing::get(move |headers| {
+            let info = ptz_info6.clone();
+            let state = ptz_state6.clone();
+            async move {
+                let cfg = current_camera_config(&state, &info).await;
+                api_ptz::api_ptz_capabilities(headers, cfg).await
+            }
         }));
     }
     
@@ -1067,7 +1956,8 @@ async fn main() -> Result<()> {
                 "version": VERSION.trim(),
                 "uptime_secs": uptime_secs,
                 "total_clients": total_clients,
-                "total_cameras": total_cameras
+                "total_cameras": total_cameras,
+                "segment_cache": segment_cache::stats()
             });
             
             trace!("[API] /api/status returning response with uptime={}, clients={}, cameras={}", 
@@ -1076,16 +1966,61 @@ async fn main() -> Result<()> {
         }
     }));
     
+    let version_state = app_state.clone();
+    app = app.route("/api/version", axum::routing::get(move || {
+        let state = version_state.clone();
+        async move {
+            api_version::api_get_version(state).await
+        }
+    }));
+
+    let storage_state = app_state.clone();
+    app = app.route("/api/storage", axum::routing::get(move |headers: axum::http::HeaderMap| {
+        let state = storage_state.clone();
+        async move {
+            api_storage::api_get_storage(headers, state).await
+        }
+    }));
+
+    let metrics_state = app_state.clone();
+    app = app.route("/api/metrics", axum::routing::get(move |headers: axum::http::HeaderMap| {
+        let state = metrics_state.clone();
+        async move {
+            api_stats::api_get_metrics(headers, state).await
+        }
+    }));
+
+    let stats_socket_state = app_state.clone();
+    app = app.route("/api/stats/ws", axum::routing::get(move |ws: axum::extract::WebSocketUpgrade, headers: axum::http::HeaderMap, query: axum::extract::Query<api_stats::StatsSocketQuery>| {
+        let state = stats_socket_state.clone();
+        async move {
+            api_stats::api_stats_socket(ws, headers, query, state).await
+        }
+    }));
+
+    let sync_playback_state = app_state.clone();
+    app = app.route("/api/recordings/sync", axum::routing::post(move |headers: axum::http::HeaderMap, body: axum::extract::Json<api_sync_playback::SyncPlaybackRequest>| {
+        let state = sync_playback_state.clone();
+        async move {
+            api_sync_playback::api_sync_playback(headers, body, state).await
+        }
+    }));
+
     let api_state2 = app_state.clone();
-    app = app.route("/api/cameras", axum::routing::get(move || {
+    app = app.route("/api/cameras", axum::routing::get(move |headers: axum::http::HeaderMap| {
         let state = api_state2.clone();
         async move {
             trace!("[API] /api/cameras endpoint called");
             
+            // A tenant admin token limits the list to the tenant's cameras
+            let tenant_access = api_config::admin_access(&headers, &state)
+                .filter(|access| matches!(access, api_config::AdminAccess::Tenant(_)));
+
             // Get camera configurations first
             let camera_data = {
                 let camera_configs = state.camera_configs.read().await;
                 let mut data: Vec<(String, config::CameraConfig)> = camera_configs.iter()
+                    .filter(|(_, config)| tenant_access.as_ref().is_none_or(|access| access.allows(config)))
                     .map(|(id, config)| (id.clone(), config.clone()))
                     .collect();
                 data.sort_by(|a, b| a.0.cmp(&b.0));
@@ -1093,7 +2028,7 @@ async fn main() -> Result<()> {
             };
             
             // Get active stream IDs, their receiver counts, FPS, pre-recording buffer stats, and MP4 buffer stats separately to avoid holding both locks
-            let (active_stream_ids, stream_receiver_counts, stream_fps_values, pre_recording_buffer_frame_counts, pre_recording_buffer_size_kb, mp4_buffer_frame_counts, mp4_buffer_size_kb) = {
+            let (active_stream_ids, stream_receiver_counts, stream_fps_values, pre_recording_buffer_frame_counts, pre_recording_buffer_size_kb, mp4_buffer_frame_counts, mp4_buffer_size_kb, camera_standby, camera_reconnect) = {
                 let camera_streams = state.camera_streams.read().await;
                 let ids = camera_streams.keys().cloned().collect::<std::collections::HashSet<String>>();
                 let counts: std::collections::HashMap<String, usize> = camera_streams.iter()
@@ -1127,7 +2062,15 @@ async fn main() -> Result<()> {
                     mp4_buffer_kb.insert(id.clone(), mp4_stats.size_kb());
                 }
                 
-                (ids, counts, fps_values, buffer_frame_counts, buffer_size_kb, mp4_buffer_frames, mp4_buffer_kb)
+                let standby: std::collections::HashMap<String, bool> = camera_streams.iter()
+                    .map(|(id, info)| (id.clone(), info.pipeline_control.is_standby()))
+                    .collect();
+                
+                let reconnect: std::collections::HashMap<String, rtsp_client::ReconnectStatus> = camera_streams.iter()
+                    .filter_map(|(id, info)| Some((id.clone(), info.pipeline_control.reconnect_status()?)))
+                    .collect();
+                
+                (ids, counts, fps_values, buffer_frame_counts, buffer_size_kb, mp4_buffer_frames, mp4_buffer_kb, standby, reconnect)
             };
             
             trace!("[API] Got {} total configs, {} active streams", 
@@ -1145,7 +2088,19 @@ async fn main() -> Result<()> {
             for (camera_id, camera_config) in camera_data {
                 let is_enabled = camera_config.enabled.unwrap_or(true);
                 let is_active = active_stream_ids.contains(&camera_id);
-                let token_required = camera_config.token.is_some();
+                let token_required = camera_config.requires_token();
+                let ffmpeg_usage = if is_active && is_enabled {
+                    resources::get_ffmpeg_usage(&camera_id)
+                } else {
+                    None
+                };
+                let standby = camera_standby.get(&camera_id).copied().unwrap_or(false);
+                let ffmpeg_cpu_percent = ffmpeg_usage.as_ref().map(|u| u.cpu_percent);
+                let ffmpeg_rss_kb = ffmpeg_usage.as_ref().map(|u| u.rss_kb);
+                let clock_drift = clock_drift::get_drift(&camera_id);
+                let tamper = tamper::get_state(&camera_id);
+                let stream_health = stream_health::get_health(&camera_id);
+                let reconnect = camera_reconnect.get(&camera_id);
                 
                 let camera_status = if is_active && is_enabled {
                     // Camera is enabled and has an active stream
@@ -1161,6 +2116,13 @@ async fn main() -> Result<()> {
                             "last_frame_time": real_status.last_frame_time,
                             "ffmpeg_running": real_status.ffmpeg_running,
                             "duplicate_frames": real_status.duplicate_frames,
+                            "ffmpeg_cpu_percent": ffmpeg_cpu_percent,
+                            "ffmpeg_rss_kb": ffmpeg_rss_kb,
+                            "standby": standby,
+                            "reconnect": reconnect,
+                            "clock_drift": clock_drift,
+                            "tamper": tamper,
+                            "stream_health": stream_health,
                             "token_required": token_required,
                             "pre_recording_buffer_frames": pre_recording_buffer_frame_counts.get(&camera_id).copied().unwrap_or(0),
                             "pre_recording_buffer_size_kb": pre_recording_buffer_size_kb.get(&camera_id).copied().unwrap_or(0),
@@ -1183,6 +2145,13 @@ async fn main() -> Result<()> {
                             "last_frame_time": null,
                             "ffmpeg_running": true,  // If stream is active, FFmpeg must be running
                             "duplicate_frames": 0,
+                            "ffmpeg_cpu_percent": ffmpeg_cpu_percent,
+                            "ffmpeg_rss_kb": ffmpeg_rss_kb,
+                            "standby": standby,
+                            "reconnect": reconnect,
+                            "clock_drift": clock_drift,
+                            "tamper": tamper,
+                            "stream_health": stream_health,
                             "token_required": token_required,
                             "pre_recording_buffer_frames": pre_recording_buffer_frame_counts.get(&camera_id).copied().unwrap_or(0),
                             "pre_recording_buffer_size_kb": pre_recording_buffer_size_kb.get(&camera_id).copied().unwrap_or(0),
@@ -1202,6 +2171,13 @@ async fn main() -> Result<()> {
                         "last_frame_time": null,
                         "ffmpeg_running": false,
                         "duplicate_frames": 0,
+                        "ffmpeg_cpu_percent": null,
+                        "ffmpeg_rss_kb": null,
+                        "standby": false,
+                        "reconnect": null,
+                        "clock_drift": clock_drift,
+                        "tamper": tamper,
+                        "stream_health": stream_health,
                         "token_required": token_required,
                         "pre_recording_buffer_frames": 0,
                         "pre_recording_buffer_size_kb": 0,
@@ -1232,6 +2208,14 @@ async fn main() -> Result<()> {
         }
     }));
 
+    let validate_state = app_state.clone();
+    app = app.route("/api/admin/cameras/validate", axum::routing::post(move |headers: axum::http::HeaderMap, body: axum::extract::Json<api_config::ValidateCameraRequest>| {
+        let state = validate_state.clone();
+        async move {
+            api_config::api_validate_camera(headers, body, state).await
+        }
+    }));
+
     let admin_state2 = app_state.clone();
     app = app.route("/api/admin/cameras/:id", axum::routing::get(move |headers: axum::http::HeaderMap, path: axum::extract::Path<String>| {
         let state = admin_state2.clone();
@@ -1256,6 +2240,39 @@ async fn main() -> Result<()> {
         }
     }));
 
+    // Camera pipeline control (runtime only, the configuration is not changed)
+    let restart_state = app_state.clone();
+    app = app.route("/api/admin/cameras/:id/restart", axum::routing::post(move |headers: axum::http::HeaderMap, path: axum::extract::Path<String>| {
+        let state = restart_state.clone();
+        async move {
+            api_config::api_restart_camera(headers, path, state).await
+        }
+    }));
+
+    let stop_state = app_state.clone();
+    app = app.route("/api/admin/cameras/:id/stop", axum::routing::post(move |headers: axum::http::HeaderMap, path: axum::extract::Path<String>| {
+        let state = stop_state.clone();
+        async move {
+            api_config::api_stop_camera(headers, path, state).await
+        }
+    }));
+
+    let start_state = app_state.clone();
+    app = app.route("/api/admin/cameras/:id/start", axum::routing::post(move |headers: axum::http::HeaderMap, path: axum::extract::Path<String>| {
+        let state = start_state.clone();
+        async move {
+            api_config::api_start_camera(headers, path, state).await
+        }
+    }));
+
+    let quarantine_state = app_state.clone();
+    app = app.route("/api/admin/cameras/:id/quarantine", axum::routing::delete(move |headers: axum::http::HeaderMap, path: axum::extract::Path<String>| {
+        let state = quarantine_state.clone();
+        async move {
+            api_config::api_clear_quarantine(headers, path, state).await
+        }
+    }));
+
     // Server configuration management API endpoints
     let args_get = args.clone();
     let admin_config_state = app_state.clone();
@@ -1267,6 +2284,90 @@ async fn main() -> Result<()> {
         }
     }));
 
+    let diagnostics_config_path = args.config.clone();
+    let diagnostics_state = app_state.clone();
+    app = app.route("/api/admin/diagnostics", axum::routing::get(move |headers: axum::http::HeaderMap| {
+        let config_path = diagnostics_config_path.clone();
+        let state = diagnostics_state.clone();
+        async move {
+            api_diagnostics::api_get_diagnostics(headers, config_path, state).await
+        }
+    }));
+
+    let recording_state_state = app_state.clone();
+    app = app.route("/api/admin/recording/state", axum::routing::get(move |headers: axum::http::HeaderMap| {
+        let state = recording_state_state.clone();
+        async move {
+            api_storage::api_get_recording_state(headers, state).await
+        }
+    }));
+
+    let reports_state = app_state.clone();
+    app = app.route("/api/admin/reports", axum::routing::get(move |headers: axum::http::HeaderMap| {
+        let state = reports_state.clone();
+        async move {
+            api_reports::api_list_reports(headers, state).await
+        }
+    }));
+
+    let report_file_state = app_state.clone();
+    app = app.route("/api/admin/reports/files/:name", axum::routing::get(move |headers: axum::http::HeaderMap, path: axum::extract::Path<String>| {
+        let state = report_file_state.clone();
+        async move {
+            api_reports::api_get_report_file(headers, path, state).await
+        }
+    }));
+
+    let report_run_state = app_state.clone();
+    app = app.route("/api/admin/reports/:id/run", axum::routing::post(move |headers: axum::http::HeaderMap, path: axum::extract::Path<String>| {
+        let state = report_run_state.clone();
+        async move {
+            api_reports::api_run_report(headers, path, state).await
+        }
+    }));
+
+    // Edge servers connect here and are reached through this server
+    if let Some(relay_server) = config.relay_server.clone().filter(|r| r.enabled) {
+        info!("Relay server enabled for {} edge servers", relay_server.edges.len());
+        let relay_server = Arc::new(relay_server);
+
+        let relay_connect_config = relay_server.clone();
+        app = app.route("/api/relay/connect", axum::routing::get(move |ws: Option<axum::extract::WebSocketUpgrade>, headers: axum::http::HeaderMap, addr: Option<axum::extract::ConnectInfo<std::net::SocketAddr>>| {
+            let config = relay_connect_config.clone();
+            async move {
+                relay::api_relay_connect(ws, headers, addr, config).await
+            }
+        }));
+
+        let relay_edges_config = relay_server.clone();
+        let relay_edges_state = app_state.clone();
+        app = app.route("/api/relay/edges", axum::routing::get(move |headers: axum::http::HeaderMap| {
+            let config = relay_edges_config.clone();
+            let state = relay_edges_state.clone();
+            async move {
+                relay::api_list_edges(headers, config, state).await
+            }
+        }));
+
+        let relay_live_state = app_state.clone();
+        app = app.route("/relay/:edge_id/:camera_id/live", axum::routing::get(move |ws: Option<axum::extract::WebSocketUpgrade>, path: axum::extract::Path<(String, String)>, query: axum::extract::Query<std::collections::HashMap<String, String>>, headers: axum::http::HeaderMap| {
+            let state = relay_live_state.clone();
+            async move {
+                relay::relay_live_handler(ws, path, query, headers, state).await
+            }
+        }));
+
+        let relay_proxy_config = relay_server.clone();
+        let relay_proxy_state = app_state.clone();
+        app = app.route("/api/relay/:edge_id/proxy/*path", axum::routing::any(move |request: axum::extract::Request| {
+            let config = relay_proxy_config.clone();
+            let state = relay_proxy_state.clone();
+            async move {
+                relay::api_relay_proxy(request, config, state).await
+            }
+        }));
+    }
+
     let args_put = args.clone();
     let admin_update_state = app_state.clone();
     app = app.route("/api/admin/config", axum::routing::put(move |headers: axum::http::HeaderMap, body: axum::extract::Json<serde_json::Value>| {
@@ -1276,18 +2377,299 @@ async fn main() -> Result<()> {
             api_config::api_update_config(headers, body, args, state).await
         }
     }));
+
+    let args_rotate = args.clone();
+    let admin_rotate_state = app_state.clone();
+    app = app.route("/api/admin/token/rotate", axum::routing::post(move |headers: axum::http::HeaderMap, body: axum::extract::Json<api_config::RotateTokenRequest>| {
+        let args = args_rotate.clone();
+        let state = admin_rotate_state.clone();
+        async move {
+            api_config::api_rotate_admin_token(headers, body, args, state).await
+        }
+    }));
     
+    // Maintenance API endpoints
+    let vacuum_state = app_state.clone();
+    app = app.route("/api/admin/maintenance/vacuum", axum::routing::post(move |headers: axum::http::HeaderMap, query: axum::extract::Query<api_maintenance::VacuumQuery>| {
+        let state = vacuum_state.clone();
+        async move {
+            api_maintenance::api_vacuum(headers, query, state).await
+        }
+    }));
+
+    let verify_state = app_state.clone();
+    app = app.route("/api/admin/verify", axum::routing::post(move |headers: axum::http::HeaderMap, query: axum::extract::Query<api_maintenance::VerifyQuery>| {
+        let state = verify_state.clone();
+        async move {
+            api_maintenance::api_verify(headers, query, state).await
+        }
+    }));
+
+    // Audit log of admin API mutations
+    let audit_state = app_state.clone();
+    app = app.route("/api/admin/audit", axum::routing::get(move |headers: axum::http::HeaderMap, query: axum::extract::Query<api_audit::GetAuditLogQuery>| {
+        let state = audit_state.clone();
+        async move {
+            api_audit::api_get_audit_log(headers, query, state).await
+        }
+    }));
+
+    let merge_sessions_state = app_state.clone();
+    app = app.route("/api/admin/cameras/:id/sessions/merge", axum::routing::post(move |headers: axum::http::HeaderMap, path: axum::extract::Path<String>, body: axum::extract::Json<api_maintenance::MergeSessionsRequest>| {
+        let state = merge_sessions_state.clone();
+        async move {
+            api_maintenance::api_merge_sessions(headers, path, body, state).await
+        }
+    }));
+    let split_session_state = app_state.clone();
+    app = app.route("/api/admin/cameras/:id/sessions/:session_id/split", axum::routing::post(move |headers: axum::http::HeaderMap, path: axum::extract::Path<(String, i64)>, body: axum::extract::Json<api_maintenance::SplitSessionRequest>| {
+        let state = split_session_state.clone();
+        async move {
+            api_maintenance::api_split_session(headers, path, body, state).await
+        }
+    }));
+    let lock_session_state = app_state.clone();
+    app = app.route("/api/admin/cameras/:id/sessions/:session_id/lock", axum::routing::post(move |headers: axum::http::HeaderMap, path: axum::extract::Path<(String, i64)>, body: axum::extract::Json<api_maintenance::LockSessionRequest>| {
+        let state = lock_session_state.clone();
+        async move {
+            api_maintenance::api_lock_session(headers, path, body, state).await
+        }
+    }));
+    let unlock_session_state = app_state.clone();
+    app = app.route("/api/admin/cameras/:id/sessions/:session_id/unlock", axum::routing::post(move |headers: axum::http::HeaderMap, path: axum::extract::Path<(String, i64)>, body: axum::extract::Json<api_maintenance::UnlockSessionRequest>| {
+        let state = unlock_session_state.clone();
+        async move {
+            api_maintenance::api_unlock_session(headers, path, body, state).await
+        }
+    }));
+    let session_locks_state = app_state.clone();
+    app = app.route("/api/admin/cameras/:id/sessions/locks", axum::routing::get(move |headers: axum::http::HeaderMap, path: axum::extract::Path<String>| {
+        let state = session_locks_state.clone();
+        async move {
+            api_maintenance::api_list_session_locks(headers, path, state).await
+        }
+    }));
+
+    // Secrets endpoints
+    let list_secrets_state = app_state.clone();
+    app = app.route("/api/admin/secrets", axum::routing::get(move |headers: axum::http::HeaderMap| {
+        let state = list_secrets_state.clone();
+        async move {
+            api_secrets::api_list_secrets(headers, state).await
+        }
+    }));
+    let set_secret_state = app_state.clone();
+    app = app.route("/api/admin/secrets/:name", axum::routing::put(move |headers: axum::http::HeaderMap, path: axum::extract::Path<String>, body: axum::extract::Json<api_secrets::SetSecretRequest>| {
+        let state = set_secret_state.clone();
+        async move {
+            api_secrets::api_set_secret(headers, path, body, state).await
+        }
+    }));
+    let delete_secret_state = app_state.clone();
+    app = app.route("/api/admin/secrets/:name", axum::routing::delete(move |headers: axum::http::HeaderMap, path: axum::extract::Path<String>| {
+        let state = delete_secret_state.clone();
+        async move {
+            api_secrets::api_delete_secret(headers, path, state).await
+        }
+    }));
+
+    // Live viewer endpoints
+    let list_viewers_state = app_state.clone();
+    app = app.route("/api/admin/viewers", axum::routing::get(move |headers: axum::http::HeaderMap, query: axum::extract::Query<api_viewers::ListViewersQuery>| {
+        let state = list_viewers_state.clone();
+        async move {
+            api_viewers::api_list_viewers(headers, query, state).await
+        }
+    }));
+    let kick_viewer_state = app_state.clone();
+    app = app.route("/api/admin/viewers/:connection_id", axum::routing::delete(move |headers: axum::http::HeaderMap, path: axum::extract::Path<String>| {
+        let state = kick_viewer_state.clone();
+        async move {
+            api_viewers::api_kick_viewer(headers, path, state).await
+        }
+    }));
+
+    // Transcoding profile endpoints
+    let list_profiles_state = app_state.clone();
+    app = app.route("/api/admin/transcoding/profiles", axum::routing::get(move |headers: axum::http::HeaderMap| {
+        let state = list_profiles_state.clone();
+        async move {
+            api_transcoding_profiles::api_list_profiles(headers, state).await
+        }
+    }));
+    let set_profile_state = app_state.clone();
+    let args_set_profile = args.clone();
+    app = app.route("/api/admin/transcoding/profiles/:name", axum::routing::put(move |headers: axum::http::HeaderMap, path: axum::extract::Path<String>, body: axum::extract::Json<config::FfmpegConfig>| {
+        let args = args_set_profile.clone();
+        let state = set_profile_state.clone();
+        async move {
+            api_transcoding_profiles::api_set_profile(headers, path, body, args, state).await
+        }
+    }));
+    let delete_profile_state = app_state.clone();
+    let args_delete_profile = args.clone();
+    app = app.route("/api/admin/transcoding/profiles/:name", axum::routing::delete(move |headers: axum::http::HeaderMap, path: axum::extract::Path<String>| {
+        let args = args_delete_profile.clone();
+        let state = delete_profile_state.clone();
+        async move {
+            api_transcoding_profiles::api_delete_profile(headers, path, args, state).await
+        }
+    }));
+
+    // LDAP login endpoints
+    let login_state = app_state.clone();
+    app = app.route("/api/auth/login", axum::routing::post(move |body: axum::extract::Json<api_auth::LoginRequest>| {
+        let state = login_state.clone();
+        async move {
+            api_auth::api_login(body, state).await
+        }
+    }));
+    app = app.route("/api/auth/logout", axum::routing::post(api_auth::api_logout));
+    app = app.route("/api/auth/session", axum::routing::get(api_auth::api_get_session));
+
+    // Tenant endpoints
+    let list_tenants_state = app_state.clone();
+    app = app.route("/api/admin/tenants", axum::routing::get(move |headers: axum::http::HeaderMap| {
+        let state = list_tenants_state.clone();
+        async move {
+            api_tenants::api_list_tenants(headers, state).await
+        }
+    }));
+
+    let tenant_cameras_state = app_state.clone();
+    app = app.route("/api/tenants/:tenant/cameras", axum::routing::get(move |headers: axum::http::HeaderMap, path: axum::extract::Path<String>| {
+        let state = tenant_cameras_state.clone();
+        async move {
+            api_tenants::api_get_tenant_cameras(headers, path, state).await
+        }
+    }));
+
+    let tenant_storage_state = app_state.clone();
+    app = app.route("/api/tenants/:tenant/storage", axum::routing::get(move |headers: axum::http::HeaderMap, path: axum::extract::Path<String>| {
+        let state = tenant_storage_state.clone();
+        async move {
+            api_tenants::api_get_tenant_storage(headers, path, state).await
+        }
+    }));
+
+    // Trigger endpoints
+    let fire_trigger_state = app_state.clone();
+    app = app.route("/api/triggers/:camera_id/:trigger_id", axum::routing::post(move |headers: axum::http::HeaderMap, path: axum::extract::Path<(String, String)>| {
+        let state = fire_trigger_state.clone();
+        async move {
+            api_triggers::api_fire_trigger(headers, path, state).await
+        }
+    }));
+
+    let get_triggers_state = app_state.clone();
+    app = app.route("/api/admin/cameras/:id/triggers", axum::routing::get(move |headers: axum::http::HeaderMap, path: axum::extract::Path<String>| {
+        let state = get_triggers_state.clone();
+        async move {
+            api_triggers::api_get_triggers(headers, path, state).await
+        }
+    }));
+
+    let update_triggers_state = app_state.clone();
+    app = app.route("/api/admin/cameras/:id/triggers", axum::routing::put(move |headers: axum::http::HeaderMap, path: axum::extract::Path<String>, body: axum::extract::Json<Vec<config::TriggerConfig>>| {
+        let state = update_triggers_state.clone();
+        async move {
+            api_triggers::api_update_triggers(headers, path, body, state).await
+        }
+    }));
+
+    let transcoding_state = app_state.clone();
+    app = app.route("/api/admin/cameras/:id/transcoding", axum::routing::post(move |headers: axum::http::HeaderMap, path: axum::extract::Path<String>, body: axum::extract::Json<api_config::TranscodingUpdateRequest>| {
+        let state = transcoding_state.clone();
+        async move {
+            api_config::api_update_transcoding(headers, path, body, state).await
+        }
+    }));
+
+    let get_pre_recording_state = app_state.clone();
+    app = app.route("/api/admin/cameras/:id/pre-recording", axum::routing::get(move |headers: axum::http::HeaderMap, path: axum::extract::Path<String>| {
+        let state = get_pre_recording_state.clone();
+        async move {
+            api_config::api_get_pre_recording(headers, path, state).await
+        }
+    }));
+
+    let update_pre_recording_state = app_state.clone();
+    app = app.route("/api/admin/cameras/:id/pre-recording", axum::routing::post(move |headers: axum::http::HeaderMap, path: axum::extract::Path<String>, body: axum::extract::Json<api_config::PreRecordingUpdateRequest>| {
+        let state = update_pre_recording_state.clone();
+        async move {
+            api_config::api_update_pre_recording(headers, path, body, state).await
+        }
+    }));
+
+    let token_rotate_state = app_state.clone();
+    app = app.route("/api/admin/cameras/:id/tokens/rotate", axum::routing::post(move |headers: axum::http::HeaderMap, path: axum::extract::Path<String>, body: axum::extract::Json<api_config::RotateTokenRequest>| {
+        let state = token_rotate_state.clone();
+        async move {
+            api_config::api_rotate_camera_token(headers, path, body, state).await
+        }
+    }));
+
     // Add fallback handler for dynamic camera routes
     let fallback_state = app_state.clone();
-    app = app.fallback(move |uri: axum::http::Uri, ws: Option<axum::extract::WebSocketUpgrade>, query: axum::extract::Query<std::collections::HashMap<String, String>>, addr: Option<axum::extract::ConnectInfo<std::net::SocketAddr>>, headers: axum::http::HeaderMap| {
+    app = app.fallback(move |uri: axum::http::Uri, ws: Option<live_socket::LiveUpgrade>, query: axum::extract::Query<std::collections::HashMap<String, String>>, addr: Option<axum::extract::ConnectInfo<std::net::SocketAddr>>, headers: axum::http::HeaderMap| {
         let state = fallback_state.clone();
         async move {
             handlers::dynamic_camera_fallback_handler(uri, ws, query, addr, headers, state).await
         }
     });
 
+    // Requests to a camera path wake the camera from standby
+    app = app.layer(axum::middleware::from_fn(standby::standby_wake_guard));
+
+    if config.server.websocket_origin_check {
+        info!("WebSocket origin validation enabled");
+        app = app.layer(axum::middleware::from_fn_with_state(
+            Arc::new(config.server.clone()),
+            handlers::websocket_origin_guard,
+        ));
+    }
+
     app = app.layer(cors_layer);
 
+    // Start trigger listeners (MQTT subscriptions, ONVIF input polling)
+    {
+        let camera_configs = app_state.camera_configs.read().await.clone();
+        for (camera_id, camera_config) in camera_configs.iter().filter(|(_, c)| c.enabled.unwrap_or(true)) {
+            app_state.trigger_manager.start_camera(&app_state, camera_id, camera_config).await;
+        }
+        app_state.trigger_manager.start_mqtt_dispatcher(app_state.clone());
+    }
+
+    // Put cameras without viewers into standby
+    standby::start_standby_monitor(app_state.clone());
+
+    // Record cameras while authenticated viewers watch them
+    viewer_recording::start_monitor(app_state.clone());
+
+    // Record cameras during the windows of their recording schedule
+    recording_schedule::start_monitor(app_state.clone());
+
+    // E-mail alerts for offline cameras and full disks
+    if let Some(ref alert_manager) = app_state.alert_manager {
+        alert_manager.start_monitor(app_state.clone());
+    }
+
+    // Offline/online events of cameras with event notifications
+    event_notifications::start_monitor(app_state.clone());
+
+    // Scheduled per-camera summary reports
+    if let Some(ref report_manager) = app_state.report_manager {
+        report_manager.start(app_state.clone());
+    }
+
+    // Stop and refuse recordings of tenants above their storage quota
+    tenants::start_quota_monitor(app_state.clone());
+
+    // Move old MP4 segments to the cold storage tier
+    if let (Some(rec_mgr), Some(cold_storage)) = (&recording_manager, config.recording.as_ref().and_then(|r| r.mp4_cold_storage.clone())) {
+        cold_storage::start_archiver(rec_mgr.clone(), cold_storage);
+    }
+
     // Start camera configuration file watcher
     if let Err(e) = watcher::start_camera_config_watcher(app_state.clone()).await {
         error!("Failed to start camera configuration watcher: {}", e);
@@ -1343,19 +2725,25 @@ async fn main() -> Result<()> {
     
     // Check if TLS is enabled
     // Convert the router to stateless by applying the state
+    let relay_state = app_state.clone();
     let stateless_app = app.with_state(app_state);
+
+    // Tunnel cameras and API calls to a cloud server over an outbound connection
+    if let Some(relay) = config.relay.clone().filter(|r| r.enabled) {
+        relay::start_client(relay_state, stateless_app.clone(), relay);
+    }
     
     if let Some(tls_config) = &config.server.tls {
         if tls_config.enabled {
             info!("Starting HTTPS server on {}", addr);
-            start_https_server(stateless_app, &addr, tls_config).await?;
+            start_https_server(stateless_app, &addr, tls_config, &config.server.http).await?;
         } else {
             info!("Starting HTTP server on {}", addr);
-            start_http_server(stateless_app, &addr).await?;
+            start_http_server(stateless_app, &addr, &config.server.http).await?;
         }
     } else {
         info!("Starting HTTP server on {}", addr);
-        start_http_server(stateless_app, &addr).await?;
+        start_http_server(stateless_app, &addr, &config.server.http).await?;
     }
 
     Ok(())
@@ -1365,19 +2753,24 @@ async fn main() -> Result<()> {
 
 // API Request/Response structs
 
-async fn start_http_server(app: axum::Router, addr: &str) -> Result<()> {
-    use socket2::{Domain, Protocol, Socket, Type};
+/// Listening socket of the HTTP and HTTPS server
+fn bind_listener(addr: &str, http_config: &config::HttpConfig) -> Result<std::net::TcpListener> {
+    use socket2::{Domain, Protocol, Socket, TcpKeepalive, Type};
     use std::net::SocketAddr;
     
-    let addr: SocketAddr = addr.parse()?;
+    let addr: SocketAddr = addr.parse()
+        .map_err(|e| StreamError::server(format!("Invalid address '{}': {}", addr, e)))?;
     
     // Create socket with custom settings for better connection handling
     let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
     
-    // Set socket options for better performance
+    // Set socket options for better performance, accepted connections inherit them
     socket.set_reuse_address(true)?;
     socket.set_tcp_nodelay(true)?;
-    socket.set_keepalive(true)?;
+    match http_config.tcp_keepalive() {
+        Some(time) => socket.set_tcp_keepalive(&TcpKeepalive::new().with_time(time))?,
+        None => socket.set_keepalive(true)?,
+    }
     
     // Set socket to non-blocking mode for Tokio compatibility
     socket.set_nonblocking(true)?;
@@ -1386,21 +2779,56 @@ async fn start_http_server(app: axum::Router, addr: &str) -> Result<()> {
     socket.bind(&addr.into())?;
     socket.listen(1024)?; // Increased from default (usually 128)
     
-    let std_listener: std::net::TcpListener = socket.into();
-    let listener = tokio::net::TcpListener::from_std(std_listener)?;
+    Ok(socket.into())
+}
+
+/// Protocol versions and connection timeouts. Players fetch many small HLS segments, so
+/// connections are reused (HTTP/1.1 keep-alive) or multiplexed (HTTP/2) instead of reopened.
+fn configure_http(builder: &mut hyper_util::server::conn::auto::Builder<hyper_util::rt::TokioExecutor>, http_config: &config::HttpConfig) {
+    builder.http1()
+        .timer(hyper_util::rt::TokioTimer::new())
+        .keep_alive(http_config.keep_alive())
+        .header_read_timeout(http_config.idle_timeout());
+    builder.http2()
+        .timer(hyper_util::rt::TokioTimer::new())
+        .keep_alive_interval(http_config.http2_keep_alive_interval())
+        .keep_alive_timeout(http_config.http2_keep_alive_timeout())
+        .max_concurrent_streams(http_config.http2_max_concurrent_streams());
+}
+
+fn log_http_settings(http_config: &config::HttpConfig) {
+    info!("HTTP/2: {}, keep-alive: {}, idle timeout: {}",
+          if http_config.http2() { "enabled" } else { "disabled" },
+          if http_config.keep_alive() { "enabled" } else { "disabled" },
+          http_config.idle_timeout().map_or("none".to_string(), |t| format!("{}s", t.as_secs())));
+}
+
+async fn start_http_server(app: axum::Router, addr: &str, http_config: &config::HttpConfig) -> Result<()> {
+    let listener = bind_listener(addr, http_config)?;
     info!("HTTP server listening on http://{} with enhanced socket configuration", addr);
+    log_http_settings(http_config);
     
-    // Configure server with higher connection limits and better performance
-    axum::serve(listener, app.into_make_service())
-        .with_graceful_shutdown(async {
-            tokio::signal::ctrl_c().await.expect("failed to listen for ctrl+c");
-            info!("Shutting down HTTP server...");
-        })
-        .await?;
+    let handle = axum_server::Handle::new();
+    let shutdown = handle.clone();
+    tokio::spawn(async move {
+        tokio::signal::ctrl_c().await.expect("failed to listen for ctrl+c");
+        info!("Shutting down HTTP server...");
+        shutdown.graceful_shutdown(None);
+    });
+
+    let mut server = axum_server::from_tcp(listener)?.handle(handle);
+    configure_http(server.http_builder(), http_config);
+    if !http_config.http2() {
+        server = server.http1_only();
+    }
+    // Connect info gives handlers the client address, e.g. for the viewer list
+    server.serve(app.into_make_service_with_connect_info::<std::net::SocketAddr>())
+        .await
+        .map_err(|e| StreamError::server(format!("HTTP server error: {}", e)))?;
     Ok(())
 }
 
-async fn start_https_server(app: axum::Router, addr: &str, tls_cfg: &config::TlsConfig) -> Result<()> {
+async fn start_https_server(app: axum::Router, addr: &str, tls_cfg: &config::TlsConfig, http_config: &config::HttpConfig) -> Result<()> {
     // Load TLS certificates
     let cert_file = File::open(&tls_cfg.cert_path)
         .map_err(|e| StreamError::server(format!("Failed to open certificate file '{}': {}", tls_cfg.cert_path, e)))?;
@@ -1420,21 +2848,34 @@ async fn start_https_server(app: axum::Router, addr: &str, tls_cfg: &config::Tls
         .ok_or_else(|| StreamError::server("No private key found in key file"))?;
 
     // Create TLS configuration
-    let rustls_config = rustls::ServerConfig::builder()
+    // The provider is explicit since both ring and aws-lc-rs are linked by dependencies
+    let mut rustls_config = rustls::ServerConfig::builder_with_provider(Arc::new(rustls::crypto::aws_lc_rs::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(|e| StreamError::server(format!("Failed to create TLS config: {}", e)))?
         .with_no_client_auth()
         .with_single_cert(certs, private_key)
         .map_err(|e| StreamError::server(format!("Failed to create TLS config: {}", e)))?;
+    // Clients only use HTTP/2 over TLS when it is offered in the handshake
+    rustls_config.alpn_protocols = if http_config.http2() {
+        vec![b"h2".to_vec(), b"http/1.1".to_vec()]
+    } else {
+        vec![b"http/1.1".to_vec()]
+    };
 
+    let listener = bind_listener(addr, http_config)?;
     info!("HTTPS server listening on https://{}", addr);
     info!("Certificate: {}", tls_cfg.cert_path);
     info!("Private key: {}", tls_cfg.key_path);
+    log_http_settings(http_config);
 
     // Start HTTPS server
     let tls_config = axum_server::tls_rustls::RustlsConfig::from_config(Arc::new(rustls_config));
-    let socket_addr: std::net::SocketAddr = addr.parse()
-        .map_err(|e| StreamError::server(format!("Invalid address '{}': {}", addr, e)))?;
-    axum_server::bind_rustls(socket_addr, tls_config)
-        .serve(app.into_make_service())
+    let mut server = axum_server::from_tcp_rustls(listener, tls_config)?;
+    configure_http(server.http_builder(), http_config);
+    if !http_config.http2() {
+        server = server.http1_only();
+    }
+    server.serve(app.into_make_service_with_connect_info::<std::net::SocketAddr>())
         .await
         .map_err(|e| StreamError::server(format!("HTTPS server error: {}", e)))?;
 
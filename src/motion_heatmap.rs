@@ -0,0 +1,215 @@
+use std::sync::Arc;
+use chrono::{DateTime, DurationRound, Utc};
+use image::imageops::FilterType;
+use serde::Serialize;
+use tokio::sync::broadcast;
+use tokio::time::{Duration, Instant};
+use tracing::{debug, error, info, warn};
+
+use crate::config::MotionHeatmapConfig;
+use crate::database::{DatabaseProvider, MotionHeatmap};
+use crate::errors::{Result, StreamError};
+use crate::jpeg;
+use crate::AppState;
+
+// Each grid cell is compared on a block of this many pixels per side
+const CELL_PIXELS: u32 = 8;
+// How often the heatmap of the current hour is written to the database
+const FLUSH_INTERVAL_SECS: u64 = 60;
+// Wait before restarting after the camera stream or database was not available
+const RESTART_DELAY_SECS: u64 = 10;
+
+/// Aggregated motion activity of a time range, as returned by the heatmap endpoint
+#[derive(Debug, Serialize)]
+pub struct HeatmapMatrix {
+    pub columns: i32,
+    pub rows: i32,
+    pub hours: usize,
+    pub samples: i64,
+    /// Average fraction (0.0 - 1.0) of changed pixels per cell, `matrix[row][column]`
+    pub matrix: Vec<Vec<f64>>,
+    /// Highest cell value, for scaling the colors of a rendered heatmap
+    pub max: f64,
+}
+
+/// Sum the hourly heatmaps into one matrix. Hours recorded with a different grid size than
+/// the most recent one are skipped.
+pub fn aggregate(heatmaps: &[MotionHeatmap]) -> Option<HeatmapMatrix> {
+    let latest = heatmaps.last()?;
+    let (columns, rows) = (latest.columns, latest.rows);
+    let cell_count = (columns * rows) as usize;
+    let mut cells = vec![0.0; cell_count];
+    let mut samples = 0;
+    let mut hours = 0;
+    for heatmap in heatmaps.iter().filter(|h| h.columns == columns && h.rows == rows && h.cells.len() == cell_count) {
+        for (sum, value) in cells.iter_mut().zip(&heatmap.cells) {
+            *sum += value;
+        }
+        samples += heatmap.samples;
+        hours += 1;
+    }
+
+    let matrix: Vec<Vec<f64>> = cells
+        .chunks(columns.max(1) as usize)
+        .map(|row| row.iter().map(|sum| if samples > 0 { sum / samples as f64 } else { 0.0 }).collect())
+        .collect();
+    let max = matrix.iter().flatten().copied().fold(0.0, f64::max);
+    Some(HeatmapMatrix { columns, rows, hours, samples, matrix, max })
+}
+
+/// Aggregate motion of a camera's live frames until the task is aborted
+pub async fn run_aggregator(state: AppState, camera_id: String, config: MotionHeatmapConfig) {
+    loop {
+        if let Err(e) = aggregate_frames(&state, &camera_id, &config).await {
+            warn!("[{}] Motion heatmap aggregation stopped: {}", camera_id, e);
+        }
+        tokio::time::sleep(Duration::from_secs(RESTART_DELAY_SECS)).await;
+    }
+}
+
+async fn aggregate_frames(state: &AppState, camera_id: &str, config: &MotionHeatmapConfig) -> Result<()> {
+    let database = match state.recording_manager.as_ref() {
+        Some(manager) => manager.get_camera_database(camera_id).await,
+        None => None,
+    }.ok_or_else(|| StreamError::config("motion heatmaps require recording to be enabled"))?;
+    let frame_sender = state.camera_streams.read().await
+        .get(camera_id)
+        .map(|info| info.frame_sender.clone())
+        .ok_or_else(|| StreamError::not_found(format!("Camera '{}' is not running", camera_id)))?;
+
+    let columns = config.columns.clamp(1, 64);
+    let rows = config.rows.clamp(1, 64);
+    let sample_interval = Duration::from_secs_f64(1.0 / config.fps.clamp(0.01, 30.0));
+    let retention = parse_retention(&config.retention);
+    let threshold = config.threshold;
+
+    let mut current = load_hour(&database, camera_id, current_hour(), columns, rows).await;
+    let mut frames = frame_sender.subscribe();
+    let mut previous: Option<Vec<u8>> = None;
+    let mut last_sample: Option<Instant> = None;
+    let mut last_flush = Instant::now();
+    let mut dirty = false;
+    info!("[{}] Started motion heatmap aggregation ({}x{} grid)", camera_id, columns, rows);
+
+    loop {
+        let frame = match frames.recv().await {
+            Ok(frame) => frame,
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        if last_sample.is_some_and(|t| t.elapsed() < sample_interval) {
+            continue;
+        }
+        last_sample = Some(Instant::now());
+
+        let luma = match tokio::task::spawn_blocking(move || grid_luma(&frame, columns, rows)).await {
+            Ok(Ok(luma)) => luma,
+            Ok(Err(e)) => {
+                debug!("[{}] Skipping frame for motion heatmap: {}", camera_id, e);
+                continue;
+            }
+            Err(_) => break,
+        };
+
+        let hour = current_hour();
+        if hour != current.hour_start {
+            if dirty {
+                store(&database, &current).await;
+            }
+            if let Some(retention) = retention {
+                if let Err(e) = database.delete_old_motion_heatmaps(camera_id, hour - retention).await {
+                    error!("[{}] Failed to delete old motion heatmaps: {}", camera_id, e);
+                }
+            }
+            current = empty_hour(camera_id, hour, columns, rows);
+            dirty = false;
+        }
+
+        if let Some(ref previous) = previous {
+            for (sum, value) in current.cells.iter_mut().zip(cell_motion(previous, &luma, columns, rows, threshold)) {
+                *sum += value;
+            }
+            current.samples += 1;
+            dirty = true;
+        }
+        previous = Some(luma);
+
+        if dirty && last_flush.elapsed() >= Duration::from_secs(FLUSH_INTERVAL_SECS) {
+            store(&database, &current).await;
+            last_flush = Instant::now();
+            dirty = false;
+        }
+    }
+
+    if dirty {
+        store(&database, &current).await;
+    }
+    Ok(())
+}
+
+/// Decode a JPEG frame into a grayscale image of `CELL_PIXELS` per grid cell
+fn grid_luma(frame: &[u8], columns: u32, rows: u32) -> Result<Vec<u8>> {
+    let image = jpeg::decode(frame)?;
+    let luma = image
+        .resize_exact(columns * CELL_PIXELS, rows * CELL_PIXELS, FilterType::Triangle)
+        .to_luma8();
+    Ok(luma.into_raw())
+}
+
+/// Fraction of pixels per cell whose brightness changed by at least `threshold`
+fn cell_motion(previous: &[u8], current: &[u8], columns: u32, rows: u32, threshold: u8) -> Vec<f64> {
+    let width = (columns * CELL_PIXELS) as usize;
+    let mut changed = vec![0u32; (columns * rows) as usize];
+    for (i, (a, b)) in previous.iter().zip(current).enumerate() {
+        if a.abs_diff(*b) >= threshold {
+            let (x, y) = (i % width, i / width);
+            changed[(y / CELL_PIXELS as usize) * columns as usize + x / CELL_PIXELS as usize] += 1;
+        }
+    }
+    let cell_size = (CELL_PIXELS * CELL_PIXELS) as f64;
+    changed.into_iter().map(|count| count as f64 / cell_size).collect()
+}
+
+fn current_hour() -> DateTime<Utc> {
+    let now = Utc::now();
+    now.duration_trunc(chrono::Duration::hours(1)).unwrap_or(now)
+}
+
+fn empty_hour(camera_id: &str, hour_start: DateTime<Utc>, columns: u32, rows: u32) -> MotionHeatmap {
+    MotionHeatmap {
+        camera_id: camera_id.to_string(),
+        hour_start,
+        columns: columns as i32,
+        rows: rows as i32,
+        samples: 0,
+        cells: vec![0.0; (columns * rows) as usize],
+    }
+}
+
+/// Continue the stored heatmap of the hour after a restart, unless the grid size changed
+async fn load_hour(database: &Arc<dyn DatabaseProvider>, camera_id: &str, hour_start: DateTime<Utc>, columns: u32, rows: u32) -> MotionHeatmap {
+    match database.list_motion_heatmaps(camera_id, hour_start, hour_start).await {
+        Ok(heatmaps) => heatmaps.into_iter()
+            .find(|h| h.columns == columns as i32 && h.rows == rows as i32 && h.cells.len() == (columns * rows) as usize)
+            .unwrap_or_else(|| empty_hour(camera_id, hour_start, columns, rows)),
+        Err(e) => {
+            warn!("[{}] Failed to load motion heatmap of the current hour: {}", camera_id, e);
+            empty_hour(camera_id, hour_start, columns, rows)
+        }
+    }
+}
+
+async fn store(database: &Arc<dyn DatabaseProvider>, heatmap: &MotionHeatmap) {
+    if let Err(e) = database.store_motion_heatmap(heatmap).await {
+        error!("[{}] Failed to store motion heatmap: {}", heatmap.camera_id, e);
+    }
+}
+
+fn parse_retention(retention: &str) -> Option<chrono::Duration> {
+    if retention == "0" {
+        return None;
+    }
+    humantime::parse_duration(retention).ok()
+        .and_then(|d| chrono::Duration::from_std(d).ok())
+        .filter(|d| *d > chrono::Duration::zero())
+}
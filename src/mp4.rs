@@ -1,34 +1,14 @@
 use axum::response::IntoResponse;
 use chrono::{DateTime, Utc};
 use tracing::{error, info, warn, debug};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use tokio::process::Command;
 use std::sync::Arc;
-use tokio::sync::RwLock;
-use std::collections::HashMap;
-use std::time::{Duration, Instant};
 
 use crate::{config, recording::RecordingManager};
 use crate::AppState;
 use crate::database::{HlsPlaylist, HlsSegment};
 
-// Simple LRU cache for MP4 segments with 5 minute TTL
-lazy_static::lazy_static! {
-    static ref MP4_SEGMENT_CACHE: Arc<RwLock<HashMap<String, CachedSegment>>> = Arc::new(RwLock::new(HashMap::new()));
-}
-
-struct CachedSegment {
-    data: Vec<u8>,
-    size_bytes: i64,
-    cached_at: Instant,
-}
-
-impl CachedSegment {
-    fn is_expired(&self) -> bool {
-        self.cached_at.elapsed() > Duration::from_secs(300) // 5 minute TTL
-    }
-}
-
 /// Cleanup old HLS temporary directories on server startup
 /// (Only needed for any leftover temp directories from database-based HLS generation)
 pub async fn cleanup_old_hls_directories() {
@@ -92,7 +72,7 @@ pub fn calculate_range(range: Option<(u64, Option<u64>)>, file_size: u64) -> (u6
     }
 }
 
-/// Extract timestamp from MP4 filename (format: 2025-08-23T17:53:25.522501Z or 2025-08-23T14-30-00Z.mp4)
+/// Extract timestamp from segment filename (format: 2025-08-23T17:53:25.522501Z or 2025-08-23T14-30-00Z.mp4/.mkv)
 fn parse_timestamp_from_filename(filename: &str) -> Option<DateTime<Utc>> {
     // First try parsing as exact timestamp (new format without .mp4): 2025-08-23T17:53:25.522501Z
     match DateTime::parse_from_rfc3339(filename) {
@@ -105,12 +85,10 @@ fn parse_timestamp_from_filename(filename: &str) -> Option<DateTime<Utc>> {
         }
     }
     
-    // Try removing .mp4 extension for backward compatibility
-    let base = if let Some(stripped) = filename.strip_suffix(".mp4") {
-        stripped
-    } else {
-        filename
-    };
+    // Try removing the container extension
+    let base = filename.strip_suffix(".mp4")
+        .or_else(|| filename.strip_suffix(".mkv"))
+        .unwrap_or(filename);
 
     // Strip reason suffix: everything after 'Z' in "2025-08-19T10-54-00Z_Motion-detected"
     let timestamp_str = if let Some(z_pos) = base.find('Z') {
@@ -162,12 +140,57 @@ pub struct HlsTimeRangeQuery {
     #[serde(default = "default_hls_segment_duration")]
     segment_duration: u32, // seconds per HLS segment
     session_id: Option<i64>, // optional: filter to specific recording session
+    variant: Option<HlsVariant>, // optional: rendition of recorded HLS segments, master playlist if not set
+    pub expires: Option<i64>, // signed URLs: Unix time the signature expires
+    pub signature: Option<String>, // signed URLs: HMAC of the time range and expiry
 }
 
 fn default_hls_segment_duration() -> u32 {
     10 // 10 second segments by default
 }
 
+/// Rendition of the recorded HLS segments
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HlsVariant {
+    Original,
+    Proxy,
+}
+
+impl HlsVariant {
+    fn as_str(self) -> &'static str {
+        match self {
+            HlsVariant::Original => "original",
+            HlsVariant::Proxy => "proxy",
+        }
+    }
+}
+
+impl HlsTimeRangeQuery {
+    /// The time range as query string, also the signed part of signed playlist URLs
+    pub fn canonical_query(&self) -> String {
+        let mut query = url::form_urlencoded::Serializer::new(String::new());
+        query.append_pair("t1", &self.t1.to_rfc3339_opts(chrono::SecondsFormat::Millis, true));
+        query.append_pair("t2", &self.t2.to_rfc3339_opts(chrono::SecondsFormat::Millis, true));
+        query.append_pair("segment_duration", &self.segment_duration.to_string());
+        if let Some(session_id) = self.session_id {
+            query.append_pair("session_id", &session_id.to_string());
+        }
+        if let Some(variant) = self.variant {
+            query.append_pair("variant", variant.as_str());
+        }
+        query.finish()
+    }
+
+    pub fn is_valid_range(&self) -> bool {
+        self.t1 < self.t2
+    }
+
+    pub fn session_id(&self) -> Option<i64> {
+        self.session_id
+    }
+}
+
 pub async fn serve_hls_playlist(
     path: axum::extract::Path<String>, // camera_id
     axum::extract::Query(query): axum::extract::Query<HlsTimeRangeQuery>,
@@ -197,22 +220,7 @@ pub async fn serve_hls_playlist(
     };
     drop(camera_streams);
 
-    // Check for existing cached playlist
-    if let Ok(Some(cached_playlist)) = database.get_hls_playlist(&playlist_id).await {
-        info!("Reusing cached HLS playlist from database for {}", playlist_id);
-        return axum::response::Response::builder()
-            .status(axum::http::StatusCode::OK)
-            .header("Content-Type", "application/vnd.apple.mpegurl")
-            .header("Cache-Control", "public, max-age=1800") // Cache for 30 minutes
-            .header("Access-Control-Allow-Origin", "*")
-            .body(axum::body::Body::from(cached_playlist.playlist_content))
-            .unwrap_or_else(|e| {
-                error!("Failed to create cached HLS response: {}", e);
-                (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to create playlist").into_response()
-            });
-    }
-
-    // Get camera config to check if HLS storage is enabled
+    // Get camera config to check if HLS storage and encryption are enabled
     let camera_configs = app_state.camera_configs.read().await;
     let camera_config = match camera_configs.get(&camera_id) {
         Some(config) => config.clone(),
@@ -222,8 +230,21 @@ pub async fn serve_hls_playlist(
     };
     drop(camera_configs);
 
-    // Check if HLS storage is enabled for this camera
+    // Segments are encrypted when they are generated, an encrypted playlist is cached apart
+    // from a plain one of the same range
     let recording_config = recording_manager.get_recording_config();
+    let encryption_enabled = crate::hls_crypto::is_encryption_enabled(&camera_config, recording_config);
+    let playlist_id = if encryption_enabled { format!("{}_aes", playlist_id) } else { playlist_id };
+
+    let signing = camera_config.requires_token().then_some((camera_id.as_str(), recording_config));
+
+    // Check for existing cached playlist
+    if let Ok(Some(cached_playlist)) = database.get_hls_playlist(&playlist_id).await {
+        info!("Reusing cached HLS playlist from database for {}", playlist_id);
+        return hls_playlist_response(cached_playlist.playlist_content, "public, max-age=1800", signing); // Cache for 30 minutes
+    }
+
+    // Check if HLS storage is enabled for this camera
     let hls_enabled = camera_config.get_hls_storage_enabled()
         .unwrap_or(recording_config.hls_storage_enabled);
     
@@ -244,6 +265,16 @@ pub async fn serve_hls_playlist(
         // If session_id is provided, filter to only that session's segments to avoid mixing
         match database.get_recording_hls_segments_for_timerange(&camera_id, query.t1, query_end_time, query.session_id).await {
             Ok(hls_segments) if !hls_segments.is_empty() => {
+                // With proxy renditions stored, players get a master playlist to choose from
+                let has_proxy = hls_segments.iter().any(|s| s.proxy_size_bytes.is_some());
+                if has_proxy && query.variant.is_none() {
+                    debug!("Serving HLS master playlist for camera '{}'", camera_id);
+                    let playlist_content = hls_master_playlist(&query, &hls_segments);
+                    // The master playlist has no segments to encrypt, only the variant URLs are signed
+                    return hls_playlist_response(playlist_content, "public, max-age=300", signing);
+                }
+                let proxy = query.variant == Some(HlsVariant::Proxy);
+
                 // Calculate total duration and max segment duration for proper HLS headers
                 let total_duration: f64 = hls_segments.iter().map(|s| s.duration_seconds).sum();
                 let max_duration = hls_segments.iter()
@@ -276,10 +307,18 @@ pub async fn serve_hls_playlist(
                     playlist_content.push_str(&format!("#EXTINF:{:.3},\n", segment.duration_seconds));
                     // Create segment URL that will be handled by serve_hls_segment_from_database
                     // Use "db" as a placeholder playlist_id for database-stored segments
-                    let segment_url = format!("segments/db/recording_{}_{}_{}.ts",
+                    // Segments without a proxy rendition are served in the original quality
+                    let rendition = if proxy && segment.proxy_size_bytes.is_some() { "_proxy" } else { "" };
+                    if let Some(key_id) = &segment.key_id {
+                        let stream = format!("{}{}", segment.session_id, rendition);
+                        playlist_content.push_str(&crate::hls_crypto::key_tag(key_id, &stream, segment.segment_index as i64));
+                        playlist_content.push('\n');
+                    }
+                    let segment_url = format!("segments/db/recording_{}_{}_{}{}.ts",
                                             segment.session_id,
                                             segment.segment_index,
-                                            segment.start_time.timestamp());
+                                            segment.start_time.timestamp(),
+                                            rendition);
                     playlist_content.push_str(&format!("{}\n", segment_url));
                 }
 
@@ -287,16 +326,7 @@ pub async fn serve_hls_playlist(
 
                 debug!("Generated HLS playlist from {} database segments for camera '{}'", hls_segments.len(), camera_id);
                 
-                return axum::response::Response::builder()
-                    .status(axum::http::StatusCode::OK)
-                    .header("Content-Type", "application/vnd.apple.mpegurl")
-                    .header("Cache-Control", "public, max-age=300") // Cache for 5 minutes
-                    .header("Access-Control-Allow-Origin", "*")
-                    .body(axum::body::Body::from(playlist_content))
-                    .unwrap_or_else(|e| {
-                        error!("Failed to create HLS response from database segments: {}", e);
-                        (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to create playlist").into_response()
-                    });
+                return hls_playlist_response(playlist_content, "public, max-age=300", signing); // Cache for 5 minutes
             }
             Ok(_) => {
                 // When HLS is enabled but no segments found yet - NO FALLBACK
@@ -334,14 +364,17 @@ pub async fn serve_hls_playlist(
     
     info!("Using MP4 segments for camera '{}' (HLS disabled, MP4 enabled)", camera_id);
 
+    if let Some(response) = crate::cold_storage::rehydration_response(&database, &camera_id, query.t1, query.t2).await {
+        return response;
+    }
+
     // Get all video segments in the time range
     let segments = match recording_manager.list_video_segments_filtered(
         &camera_id,
         Some(query.t1),
         Some(query.t2),
         None, // no reason filter
-        1000, // max segments
-        "oldest", // chronological order
+        &crate::database::PageRequest { limit: Some(1000), offset: 0, oldest_first: true },
     ).await {
         Ok(segments) => segments,
         Err(e) => {
@@ -365,12 +398,69 @@ pub async fn serve_hls_playlist(
 
     let storage_type = recording_manager.get_storage_type_for_camera(camera_config);
     drop(camera_configs);
-    
+
+    let encryption_key = if encryption_enabled {
+        match crate::hls_crypto::current_key(recording_config, &camera_id) {
+            Ok(key) => Some(key),
+            Err(e) => {
+                error!("Failed to get HLS encryption key for camera '{}': {}", camera_id, e);
+                return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to prepare HLS encryption").into_response();
+            }
+        }
+    } else {
+        None
+    };
+
+    // FFmpeg runs are limited per camera and globally, identical requests share one run
+    let generation = HlsGeneration {
+        camera_id: camera_id.clone(),
+        playlist_id: playlist_id.clone(),
+        database,
+        segments,
+        storage_type,
+        // The configured capture framerate is more accurate than mp4_framerate
+        capture_framerate: app_state.transcoding_config.capture_framerate,
+        t1: query.t1,
+        t2: query.t2,
+        segment_duration: query.segment_duration,
+        encryption_key,
+    };
+    let submission = crate::hls_jobs::submit(&playlist_id, &camera_id, query.t1, query.t2,
+                                             move |progress| generate_hls_playlist(generation, progress));
+    match submission {
+        crate::hls_jobs::Submission::Started(outcome) => match crate::hls_jobs::wait(outcome).await {
+            Ok(playlist_content) => hls_playlist_response(playlist_content, "public, max-age=1800", signing), // Cache for 30 minutes
+            Err((status, message)) => (status, message).into_response(),
+        },
+        crate::hls_jobs::Submission::Queued(job) => crate::hls_jobs::queued_response(job),
+        crate::hls_jobs::Submission::QueueFull => crate::hls_jobs::queue_full_response(),
+    }
+}
+
+/// Inputs of an HLS playlist generated from MP4 segments
+struct HlsGeneration {
+    camera_id: String,
+    playlist_id: String,
+    database: Arc<dyn crate::database::DatabaseProvider>,
+    segments: Vec<crate::database::VideoSegment>,
+    storage_type: config::Mp4StorageType,
+    capture_framerate: u32,
+    t1: DateTime<Utc>,
+    t2: DateTime<Utc>,
+    segment_duration: u32,
+    encryption_key: Option<crate::hls_crypto::HlsKey>,
+}
+
+/// Transcode the MP4 segments to HLS with FFmpeg and store playlist and segments in the database,
+/// encrypted with the given key
+async fn generate_hls_playlist(generation: HlsGeneration, progress: crate::hls_jobs::Progress) -> crate::hls_jobs::HlsOutcome {
+    let HlsGeneration { camera_id, playlist_id, database, segments, storage_type, capture_framerate, t1, t2, segment_duration, encryption_key } = generation;
+    let failed = |status: axum::http::StatusCode, message: &str| Err((status, message.to_string()));
     // Create temporary directory for FFmpeg processing
     let temp_dir = format!("/tmp/hls_temp_{}", playlist_id);
     if let Err(e) = tokio::fs::create_dir_all(&temp_dir).await {
         error!("Failed to create temp directory: {}", e);
-        return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to create temp directory").into_response();
+        return failed(axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to create temp directory");
     }
 
     // Prepare input files for FFmpeg
@@ -381,10 +471,10 @@ pub async fn serve_hls_playlist(
         match storage_type {
             config::Mp4StorageType::Database => {
                 // Get the full segment data by timestamp (more efficient)
-                let db_segment = match database.get_video_segment_by_time(&camera_id, segment.start_time).await {
-                    Ok(Some(seg)) => seg,
+                let mp4_data = match cached_mp4_segment(&database, &camera_id, segment.start_time).await {
+                    Ok(Some(mp4_data)) => mp4_data,
                     Ok(None) => {
-                        debug!("No MP4 data found for segment at {}", segment.start_time);
+                        warn!("No MP4 data found for segment at {}", segment.start_time);
                         continue;
                     },
                     Err(e) => {
@@ -393,17 +483,14 @@ pub async fn serve_hls_playlist(
                     }
                 };
                 
-                if let Some(mp4_data) = db_segment.mp4_data {
-                    let temp_path = format!("{}/input_{:03}.mp4", temp_dir, i);
-                    if let Err(e) = tokio::fs::write(&temp_path, &mp4_data).await {
-                        error!("Failed to write temp file: {}", e);
-                        continue;
-                    }
-                    input_files.push(temp_path.clone());
-                    temp_files.push(temp_path);
-                } else {
-                    warn!("MP4 segment has no data for timestamp: {}", segment.start_time);
+                let extension = config::SegmentContainer::from_data(&mp4_data).extension();
+                let temp_path = format!("{}/input_{:03}.{}", temp_dir, i, extension);
+                if let Err(e) = tokio::fs::write(&temp_path, &mp4_data).await {
+                    error!("Failed to write temp file: {}", e);
+                    continue;
                 }
+                input_files.push(temp_path.clone());
+                temp_files.push(temp_path);
             },
             config::Mp4StorageType::Filesystem => {
                 if let Some(file_path) = &segment.file_path {
@@ -412,14 +499,14 @@ pub async fn serve_hls_playlist(
             },
             config::Mp4StorageType::Disabled => {
                 let _ = tokio::fs::remove_dir_all(&temp_dir).await;
-                return (axum::http::StatusCode::NOT_FOUND, "MP4 storage disabled").into_response();
+                return failed(axum::http::StatusCode::NOT_FOUND, "MP4 storage disabled");
             }
         }
     }
 
     if input_files.is_empty() {
         let _ = tokio::fs::remove_dir_all(&temp_dir).await;
-        return (axum::http::StatusCode::NOT_FOUND, "No valid segments found").into_response();
+        return failed(axum::http::StatusCode::NOT_FOUND, "No valid segments found");
     }
 
     // Create concat list for FFmpeg
@@ -432,15 +519,11 @@ pub async fn serve_hls_playlist(
     if let Err(e) = tokio::fs::write(&concat_list_path, &concat_content).await {
         error!("Failed to write concat list: {}", e);
         let _ = tokio::fs::remove_dir_all(&temp_dir).await;
-        return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to prepare HLS").into_response();
+        return failed(axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to prepare HLS");
     }
 
     // Generate HLS segments using FFmpeg
     let playlist_path = format!("{}/playlist.m3u8", temp_dir);
-    // Get the configured capture framerate from transcoding config (more accurate than mp4_framerate)
-    let transcoding_config = &app_state.transcoding_config;
-    let capture_framerate = transcoding_config.capture_framerate;
-    
     let mut hls_cmd = Command::new("ffmpeg");
     hls_cmd.args([
         "-f", "concat",
@@ -453,17 +536,36 @@ pub async fn serve_hls_playlist(
         "-preset", "ultrafast",
         "-avoid_negative_ts", "make_zero", // Fix timestamp issues
         "-fflags", "+genpts", // Generate presentation timestamps
-        "-hls_time", &query.segment_duration.to_string(),
+        "-hls_time", &segment_duration.to_string(),
         "-hls_playlist_type", "vod",
         "-hls_segment_type", "mpegts", // Use MPEG-TS segments for better HLS compatibility
         "-hls_segment_filename", &format!("{}/segment_%03d.ts", temp_dir),
         "-start_number", "0",
+        "-progress", "pipe:1", // Progress of the job as key=value lines on stdout
+        "-nostats",
         &playlist_path,
     ]);
-    hls_cmd.stdout(std::process::Stdio::null());
+    hls_cmd.stdout(std::process::Stdio::piped());
     hls_cmd.stderr(std::process::Stdio::null());
 
-    let ffmpeg_result = hls_cmd.status().await;
+    let total_us = segments.iter()
+        .map(|segment| (segment.end_time - segment.start_time).num_microseconds().unwrap_or(0))
+        .sum::<i64>()
+        .max(1);
+    let ffmpeg_result = match hls_cmd.spawn() {
+        Ok(mut child) => {
+            if let Some(stdout) = child.stdout.take() {
+                let mut lines = tokio::io::AsyncBufReadExt::lines(tokio::io::BufReader::new(stdout));
+                while let Ok(Some(line)) = lines.next_line().await {
+                    if let Some(out_time_us) = line.strip_prefix("out_time_us=").and_then(|v| v.trim().parse::<i64>().ok()) {
+                        progress.set_percent(out_time_us as f64 * 100.0 / total_us as f64);
+                    }
+                }
+            }
+            child.wait().await
+        }
+        Err(e) => Err(e),
+    };
     match ffmpeg_result {
         Ok(status) if status.success() => {
             info!("HLS generation completed successfully");
@@ -471,12 +573,12 @@ pub async fn serve_hls_playlist(
         Ok(status) => {
             error!("FFmpeg failed with exit code: {:?}", status.code());
             let _ = tokio::fs::remove_dir_all(&temp_dir).await;
-            return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to generate HLS segments").into_response();
+            return failed(axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to generate HLS segments");
         },
         Err(e) => {
             error!("Failed to run FFmpeg: {}", e);
             let _ = tokio::fs::remove_dir_all(&temp_dir).await;
-            return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to run FFmpeg").into_response();
+            return failed(axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to run FFmpeg");
         }
     }
 
@@ -486,7 +588,7 @@ pub async fn serve_hls_playlist(
         Err(e) => {
             error!("Failed to read generated playlist: {}", e);
             let _ = tokio::fs::remove_dir_all(&temp_dir).await;
-            return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to read playlist").into_response();
+            return failed(axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to read playlist");
         }
     };
 
@@ -499,7 +601,14 @@ pub async fn serve_hls_playlist(
         if line.starts_with("segment_") && line.ends_with(".ts") {
             // Read the segment file
             let segment_path = format!("{}/{}", temp_dir, line);
-            match tokio::fs::read(&segment_path).await {
+            let segment_data = match tokio::fs::read(&segment_path).await {
+                Ok(segment_data) => match &encryption_key {
+                    Some(key) => crate::hls_crypto::encrypt_segment(key, &playlist_id, segment_index as i64, &segment_data),
+                    None => Ok(segment_data),
+                },
+                Err(e) => Err(e.into()),
+            };
+            match segment_data {
                 Ok(segment_data) => {
                     let hls_segment = HlsSegment {
                         playlist_id: playlist_id.clone(),
@@ -512,6 +621,10 @@ pub async fn serve_hls_playlist(
                     
                     segments.push(hls_segment);
                     
+                    if let Some(key) = &encryption_key {
+                        final_playlist_content.push_str(&crate::hls_crypto::key_tag(&key.id, &playlist_id, segment_index as i64));
+                        final_playlist_content.push('\n');
+                    }
                     // Use relative URLs in playlist for better compatibility with reverse proxies
                     final_playlist_content.push_str(&format!("segments/{}/{}\n", playlist_id, line));
                     segment_index += 1;
@@ -519,7 +632,7 @@ pub async fn serve_hls_playlist(
                 Err(e) => {
                     error!("Failed to read HLS segment file {}: {}", segment_path, e);
                     let _ = tokio::fs::remove_dir_all(&temp_dir).await;
-                    return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to read HLS segment").into_response();
+                    return failed(axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to read HLS segment");
                 }
             }
         } else {
@@ -533,9 +646,9 @@ pub async fn serve_hls_playlist(
     let final_playlist = HlsPlaylist {
         playlist_id: playlist_id.clone(),
         camera_id: camera_id.clone(),
-        start_time: query.t1,
-        end_time: query.t2,
-        segment_duration: query.segment_duration as i32,
+        start_time: t1,
+        end_time: t2,
+        segment_duration: segment_duration as i32,
         playlist_content: final_playlist_content.clone(),
         created_at: Utc::now(),
         expires_at,
@@ -545,7 +658,7 @@ pub async fn serve_hls_playlist(
     if let Err(e) = database.store_hls_playlist_with_segments(&final_playlist, &segments).await {
         error!("Failed to store HLS playlist and segments in database: {}", e);
         let _ = tokio::fs::remove_dir_all(&temp_dir).await;
-        return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to store HLS data").into_response();
+        return failed(axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to store HLS data");
     }
 
     // Cleanup temp directory
@@ -563,18 +676,308 @@ pub async fn serve_hls_playlist(
         }
     });
 
+    Ok(final_playlist_content)
+}
+
+/// Master playlist referencing the original and the proxy rendition of recorded HLS segments
+fn hls_master_playlist(query: &HlsTimeRangeQuery, segments: &[crate::database::RecordingHlsSegment]) -> String {
+    let original_bandwidth = peak_bandwidth(segments, |s| s.size_bytes);
+    let proxy_bandwidth = peak_bandwidth(segments, |s| s.proxy_size_bytes.unwrap_or(s.size_bytes));
+
+    let mut playlist_content = String::new();
+    playlist_content.push_str("#EXTM3U\n");
+    playlist_content.push_str("#EXT-X-VERSION:3\n");
+    for (variant, bandwidth) in [(HlsVariant::Original, original_bandwidth), (HlsVariant::Proxy, proxy_bandwidth)] {
+        let variant_query = HlsTimeRangeQuery {
+            t1: query.t1,
+            t2: query.t2,
+            segment_duration: query.segment_duration,
+            session_id: query.session_id,
+            variant: Some(variant),
+            expires: None,
+            signature: None,
+        };
+        playlist_content.push_str(&format!("#EXT-X-STREAM-INF:BANDWIDTH={}\n", bandwidth));
+        playlist_content.push_str(&format!("timerange?{}\n", variant_query.canonical_query()));
+    }
+    playlist_content
+}
+
+// Peak bitrate of a rendition over all segments, as required for BANDWIDTH
+fn peak_bandwidth(segments: &[crate::database::RecordingHlsSegment], size: impl Fn(&crate::database::RecordingHlsSegment) -> i64) -> u64 {
+    segments.iter()
+        .filter(|s| s.duration_seconds > 0.0)
+        .map(|s| (size(s) as f64 * 8.0 / s.duration_seconds).ceil() as u64)
+        .max()
+        .unwrap_or(0)
+        .max(1)
+}
+
+/// Build the playlist response, signing the segment and key URLs when the camera requires
+/// a token. Signed playlists expire, so they may not be cached.
+fn hls_playlist_response(
+    playlist_content: String,
+    cache_control: &str,
+    signing: Option<(&str, &config::RecordingConfig)>,
+) -> axum::response::Response {
+    let (playlist_content, cache_control) = match signing {
+        Some((camera_id, recording_config)) => (crate::hls_signing::sign_playlist(&playlist_content, recording_config, camera_id), "no-store"),
+        None => (playlist_content, cache_control),
+    };
+
     axum::response::Response::builder()
         .status(axum::http::StatusCode::OK)
         .header("Content-Type", "application/vnd.apple.mpegurl")
-        .header("Cache-Control", "public, max-age=1800") // Cache for 30 minutes
+        .header("Cache-Control", cache_control)
         .header("Access-Control-Allow-Origin", "*")
-        .body(axum::body::Body::from(final_playlist_content))
+        .body(axum::body::Body::from(playlist_content))
         .unwrap_or_else(|e| {
             error!("Failed to create HLS response: {}", e);
             (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to create playlist").into_response()
         })
 }
 
+// How long an event cue stays visible in the WebVTT track
+const EVENT_CUE_SECONDS: f64 = 5.0;
+
+/// Recorded span of the playback timeline: wall-clock start and its duration in the HLS stream
+struct TimelineSpan {
+    start: DateTime<Utc>,
+    duration: f64,
+}
+
+/// Position of a wall-clock timestamp in the concatenated stream. Timestamps in a gap
+/// between recordings map to the start of the next recording.
+fn media_offset(timeline: &[TimelineSpan], timestamp: DateTime<Utc>) -> Option<f64> {
+    let mut offset = 0.0;
+    for span in timeline {
+        let into_span = (timestamp - span.start).num_milliseconds() as f64 / 1000.0;
+        if into_span < 0.0 {
+            return Some(offset);
+        }
+        if into_span < span.duration {
+            return Some(offset + into_span);
+        }
+        offset += span.duration;
+    }
+    None
+}
+
+fn vtt_timestamp(seconds: f64) -> String {
+    let millis = (seconds * 1000.0).round() as u64;
+    format!("{:02}:{:02}:{:02}.{:03}", millis / 3_600_000, (millis / 60_000) % 60, (millis / 1000) % 60, millis % 1000)
+}
+
+fn vtt_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace("-->", "--&gt;")
+}
+
+/// Build the WebVTT track for the events of a playback timeline
+fn build_events_vtt(timeline: &[TimelineSpan], events: &[crate::database::RecordingEvent]) -> String {
+    let total: f64 = timeline.iter().map(|s| s.duration).sum();
+    let mut vtt = String::from("WEBVTT\n\n");
+
+    for event in events {
+        let Some(start) = media_offset(timeline, event.timestamp) else { continue };
+        let end = (start + EVENT_CUE_SECONDS).min(total);
+        if end <= start {
+            continue;
+        }
+        let text = match event.event_type.as_str() {
+            "trigger" => format!("Trigger: {}", event.label),
+            "ptz" => format!("PTZ {}", event.label),
+            "bookmark" => format!("Bookmark: {}", event.label),
+            other => format!("{}: {}", other, event.label),
+        };
+        vtt.push_str(&format!(
+            "{}\n{} --> {}\n{} {}\n\n",
+            event.id,
+            vtt_timestamp(start),
+            vtt_timestamp(end),
+            event.timestamp.format("%H:%M:%S"),
+            vtt_escape(&text),
+        ));
+    }
+    vtt
+}
+
+/// Recorded spans of the timerange playlist of a camera, from the same segments as the playlist
+async fn playlist_timeline(
+    camera_id: &str,
+    query: &HlsTimeRangeQuery,
+    camera_config: &config::CameraConfig,
+    recording_manager: &RecordingManager,
+) -> std::result::Result<Vec<TimelineSpan>, &'static str> {
+    let recording_config = recording_manager.get_recording_config();
+    let hls_enabled = camera_config.get_hls_storage_enabled()
+        .unwrap_or(recording_config.hls_storage_enabled);
+
+    if hls_enabled {
+        // Same query as the playlist, including the grace period for late segments
+        let query_end_time = query.t2 + chrono::Duration::seconds(5);
+        match recording_manager.get_camera_database(camera_id).await {
+            Some(database) => match database.get_recording_hls_segments_for_timerange(camera_id, query.t1, query_end_time, query.session_id).await {
+                Ok(segments) => Ok(segments.iter()
+                    .map(|s| TimelineSpan { start: s.start_time, duration: s.duration_seconds })
+                    .collect()),
+                Err(e) => {
+                    error!("Failed to query HLS segments for camera '{}': {}", camera_id, e);
+                    Err("Failed to retrieve HLS segments")
+                }
+            },
+            None => Err("Camera database not found"),
+        }
+    } else {
+        match recording_manager.list_video_segments_filtered(
+            camera_id,
+            Some(query.t1),
+            Some(query.t2),
+            None,
+            &crate::database::PageRequest { limit: Some(1000), offset: 0, oldest_first: true },
+        ).await {
+            Ok(segments) => Ok(segments.iter()
+                .map(|s| TimelineSpan { start: s.start_time, duration: (s.end_time - s.start_time).num_milliseconds() as f64 / 1000.0 })
+                .collect()),
+            Err(e) => {
+                error!("Failed to list video segments: {}", e);
+                Err("Failed to list video segments")
+            }
+        }
+    }
+}
+
+// Spans closer than this are one continuous recording in the playback alignment
+const ALIGNMENT_GAP_TOLERANCE_MS: i64 = 1000;
+
+/// Continuous recording in a timerange playlist, where `position_secs` is its start in the playlist
+#[derive(Debug, Serialize)]
+pub struct AlignedSpan {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub position_secs: f64,
+}
+
+/// Part of the requested time range without recordings
+#[derive(Debug, Serialize)]
+pub struct PlaybackGap {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+/// How the timerange playlist of a camera maps to wall-clock time, for players kept in lockstep
+#[derive(Debug, Serialize)]
+pub struct PlaybackAlignment {
+    pub source: &'static str, // hls, mp4 or none: storage the playlist is built from
+    pub start_offset_secs: Option<f64>, // First recording minus t1: negative to seek into the playlist at t1, positive to delay it
+    pub duration_secs: f64, // Length of the playlist
+    pub covered_secs: f64, // Recorded part of the time range
+    pub timeline: Vec<AlignedSpan>,
+    pub gaps: Vec<PlaybackGap>,
+}
+
+/// Alignment of the timerange playlist of a camera. Gaps between recordings are skipped in
+/// the playlist, a wall-clock time is at `position_secs` plus its distance from the span start.
+pub async fn playback_alignment(
+    camera_id: &str,
+    query: &HlsTimeRangeQuery,
+    camera_config: &config::CameraConfig,
+    recording_manager: &RecordingManager,
+) -> std::result::Result<PlaybackAlignment, &'static str> {
+    let recording_config = recording_manager.get_recording_config();
+    let source = if camera_config.get_hls_storage_enabled().unwrap_or(recording_config.hls_storage_enabled) {
+        "hls"
+    } else if camera_config.get_mp4_storage_type().unwrap_or(&recording_config.mp4_storage_type) != &config::Mp4StorageType::Disabled {
+        "mp4"
+    } else {
+        "none"
+    };
+    let spans = if source == "none" {
+        Vec::new()
+    } else {
+        playlist_timeline(camera_id, query, camera_config, recording_manager).await?
+    };
+
+    let mut timeline: Vec<AlignedSpan> = Vec::new();
+    let mut position = 0.0;
+    for span in &spans {
+        let end = span.start + chrono::Duration::milliseconds((span.duration * 1000.0) as i64);
+        match timeline.last_mut() {
+            Some(last) if (span.start - last.end).num_milliseconds() <= ALIGNMENT_GAP_TOLERANCE_MS => last.end = last.end.max(end),
+            _ => timeline.push(AlignedSpan { start: span.start, end, position_secs: position }),
+        }
+        position += span.duration;
+    }
+
+    let mut gaps = Vec::new();
+    let mut cursor = query.t1;
+    for span in &timeline {
+        if (span.start - cursor).num_milliseconds() > ALIGNMENT_GAP_TOLERANCE_MS {
+            gaps.push(PlaybackGap { start: cursor, end: span.start.min(query.t2) });
+        }
+        cursor = cursor.max(span.end);
+        if cursor >= query.t2 {
+            break;
+        }
+    }
+    if (query.t2 - cursor).num_milliseconds() > ALIGNMENT_GAP_TOLERANCE_MS {
+        gaps.push(PlaybackGap { start: cursor, end: query.t2 });
+    }
+    let missing_ms: i64 = gaps.iter().map(|gap| (gap.end - gap.start).num_milliseconds()).sum();
+
+    Ok(PlaybackAlignment {
+        source,
+        start_offset_secs: timeline.first().map(|span| (span.start - query.t1).num_milliseconds() as f64 / 1000.0),
+        duration_secs: position,
+        covered_secs: ((query.t2 - query.t1).num_milliseconds() - missing_ms).max(0) as f64 / 1000.0,
+        timeline,
+        gaps,
+    })
+}
+
+/// WebVTT sidecar track with the events (triggers, PTZ moves, bookmarks) of an HLS timerange
+/// playlist. Cue times follow the same segments as the playlist, so gaps between recordings
+/// are skipped exactly like in the video.
+pub async fn serve_hls_events_vtt(
+    camera_id: &str,
+    query: &HlsTimeRangeQuery,
+    camera_config: &config::CameraConfig,
+    recording_manager: &RecordingManager,
+) -> axum::response::Response {
+    let Some(database) = recording_manager.get_camera_database(camera_id).await else {
+        return (axum::http::StatusCode::NOT_FOUND, "Camera database not found").into_response();
+    };
+
+    let timeline = match playlist_timeline(camera_id, query, camera_config, recording_manager).await {
+        Ok(timeline) => timeline,
+        Err(message) => return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, message).into_response(),
+    };
+
+    let (Some(first), Some(last)) = (timeline.first(), timeline.last()) else {
+        return (axum::http::StatusCode::NOT_FOUND, "No recordings found in the specified time range").into_response();
+    };
+    let timeline_end = last.start + chrono::Duration::milliseconds((last.duration * 1000.0) as i64);
+
+    let events = match database.list_recording_events(camera_id, first.start, timeline_end).await {
+        Ok(events) => events,
+        Err(e) => {
+            error!("Failed to list events for camera '{}': {}", camera_id, e);
+            return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to list events").into_response();
+        }
+    };
+    debug!("Serving WebVTT track with {} events for camera '{}'", events.len(), camera_id);
+
+    axum::response::Response::builder()
+        .status(axum::http::StatusCode::OK)
+        .header("Content-Type", "text/vtt; charset=utf-8")
+        .header("Cache-Control", "no-cache")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(axum::body::Body::from(build_events_vtt(&timeline, &events)))
+        .unwrap_or_else(|e| {
+            error!("Failed to create WebVTT response: {}", e);
+            (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to create WebVTT track").into_response()
+        })
+}
+
 pub async fn serve_hls_segment(
     path: axum::extract::Path<(String, String, String)>, // camera_id, playlist_id, segment_name
     axum::extract::State(app_state): axum::extract::State<AppState>,
@@ -593,7 +996,7 @@ pub async fn serve_hls_segment(
             return (axum::http::StatusCode::SERVICE_UNAVAILABLE, "Recording system not available").into_response();
         }
     };
-    
+
     // Get database for this camera
     let camera_streams = recording_manager.databases.read().await;
     let database = match camera_streams.get(&camera_id) {
@@ -607,25 +1010,43 @@ pub async fn serve_hls_segment(
     // Check if this is a database-stored HLS segment from recording
     // These use "db" as the playlist_id and segment names like "recording_1_8_timestamp.ts"
     if (playlist_id == "db" || segment_name.starts_with("recording_")) && segment_name.ends_with(".ts") {
-        // Parse the segment name: recording_{session_id}_{segment_index}_{timestamp}.ts
+        // Parse the segment name: recording_{session_id}_{segment_index}_{timestamp}[_proxy].ts
         let parts: Vec<&str> = segment_name.trim_end_matches(".ts").split('_').collect();
         if parts.len() >= 4 && parts[0] == "recording" {
             if let (Ok(session_id), Ok(segment_index)) = (parts[1].parse::<i64>(), parts[2].parse::<i32>()) {
                 debug!("Serving database-stored HLS segment from recording_hls table: session_id={}, segment_index={}", session_id, segment_index);
+
+                if parts.get(4) == Some(&"proxy") {
+                    let cache_key = crate::segment_cache::hls_proxy_key(&camera_id, session_id, segment_index);
+                    if let Some(segment_data) = crate::segment_cache::get(&cache_key) {
+                        return hls_segment_response(segment_data);
+                    }
+                    return match database.get_recording_hls_proxy_segment(session_id, segment_index).await {
+                        Ok(Some(proxy_data)) => {
+                            let segment_data = bytes::Bytes::from(proxy_data);
+                            crate::segment_cache::insert(&cache_key, segment_data.clone());
+                            hls_segment_response(segment_data)
+                        }
+                        Ok(None) => {
+                            warn!("Database-stored HLS proxy segment not found: session_id={}, segment_index={}", session_id, segment_index);
+                            (axum::http::StatusCode::NOT_FOUND, "HLS proxy segment not found in database").into_response()
+                        }
+                        Err(e) => {
+                            error!("Failed to get database-stored HLS proxy segment: {}", e);
+                            (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response()
+                        }
+                    };
+                }
                 
+                let cache_key = crate::segment_cache::hls_key(&camera_id, session_id, segment_index);
+                if let Some(segment_data) = crate::segment_cache::get(&cache_key) {
+                    return hls_segment_response(segment_data);
+                }
                 match database.get_recording_hls_segment_by_session_and_index(session_id, segment_index).await {
                     Ok(Some(hls_segment)) => {
-                        return axum::response::Response::builder()
-                            .status(axum::http::StatusCode::OK)
-                            .header("Content-Type", "video/mp2t") // MPEG-TS MIME type
-                            .header("Cache-Control", "public, max-age=3600")
-                            .header("Access-Control-Allow-Origin", "*")
-                            .header("Content-Length", hls_segment.segment_data.len().to_string())
-                            .body(axum::body::Body::from(hls_segment.segment_data))
-                            .unwrap_or_else(|e| {
-                                error!("Failed to create database HLS segment response: {}", e);
-                                (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to create response").into_response()
-                            });
+                        let segment_data = bytes::Bytes::from(hls_segment.segment_data);
+                        crate::segment_cache::insert(&cache_key, segment_data.clone());
+                        return hls_segment_response(segment_data);
                     }
                     Ok(None) => {
                         warn!("Database-stored HLS segment not found: session_id={}, segment_index={}", session_id, segment_index);
@@ -647,6 +1068,10 @@ pub async fn serve_hls_segment(
     }
     
     // Fall back to legacy HLS segment lookup (for MP4-converted segments)
+    let cache_key = crate::segment_cache::playlist_key(&camera_id, &playlist_id, &segment_name);
+    if let Some(segment_data) = crate::segment_cache::get(&cache_key) {
+        return hls_segment_response(segment_data);
+    }
     let segment = match database.get_hls_segment(&playlist_id, &segment_name).await {
         Ok(Some(segment)) => segment,
         Ok(None) => {
@@ -658,19 +1083,48 @@ pub async fn serve_hls_segment(
         }
     };
     
+    let segment_data = bytes::Bytes::from(segment.segment_data);
+    crate::segment_cache::insert(&cache_key, segment_data.clone());
+    hls_segment_response(segment_data)
+}
+
+/// Build the segment response, segments of encrypted cameras are stored encrypted
+fn hls_segment_response(segment_data: bytes::Bytes) -> axum::response::Response {
     axum::response::Response::builder()
         .status(axum::http::StatusCode::OK)
         .header("Content-Type", "video/mp2t") // MPEG-TS MIME type
         .header("Cache-Control", "public, max-age=3600")
         .header("Access-Control-Allow-Origin", "*")
-        .header("Content-Length", segment.segment_data.len().to_string())
-        .body(axum::body::Body::from(segment.segment_data))
+        .header("Content-Length", segment_data.len().to_string())
+        .body(axum::body::Body::from(segment_data))
         .unwrap_or_else(|e| {
             error!("Failed to create segment response: {}", e);
             (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to create response").into_response()
         })
 }
 
+/// Serve an HLS encryption key as the 16 raw key bytes
+pub async fn serve_hls_key(
+    camera_id: &str,
+    key_id: &str,
+    recording_manager: &RecordingManager,
+) -> axum::response::Response {
+    let Some(key) = crate::hls_crypto::get_key(recording_manager.get_recording_config(), camera_id, key_id) else {
+        return (axum::http::StatusCode::NOT_FOUND, "HLS key not found").into_response();
+    };
+
+    axum::response::Response::builder()
+        .status(axum::http::StatusCode::OK)
+        .header("Content-Type", "application/octet-stream")
+        .header("Cache-Control", "no-store")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(axum::body::Body::from(key.key.to_vec()))
+        .unwrap_or_else(|e| {
+            error!("Failed to create HLS key response: {}", e);
+            (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to create response").into_response()
+        })
+}
+
 // New reusable MP4 streaming functions for camera-specific endpoints
 
 pub async fn stream_mp4_segment(
@@ -685,6 +1139,11 @@ pub async fn stream_mp4_segment(
     // Get the storage type for this camera
     let storage_type = recording_manager.get_storage_type_for_camera(camera_config);
 
+    // Archived segments are rehydrated first
+    if let Some(response) = archived_segment_response(camera_id, filename, recording_manager).await {
+        return response;
+    }
+
     match storage_type {
         config::Mp4StorageType::Database => {
             stream_segment_from_database(camera_id, filename, range, recording_manager).await
@@ -699,6 +1158,43 @@ pub async fn stream_mp4_segment(
     }
 }
 
+/// Rehydration response if the requested segment is in the cold tier. File names may be in local
+/// time, so archives within a day of the parsed timestamp are also matched by file name.
+async fn archived_segment_response(
+    camera_id: &str,
+    filename: &str,
+    recording_manager: &RecordingManager,
+) -> Option<axum::response::Response> {
+    let timestamp = parse_timestamp_from_filename(filename)?;
+    let database = recording_manager.get_camera_database(camera_id).await?;
+    let archives = database.list_archived_mp4_segments(camera_id, timestamp - chrono::Duration::days(1), timestamp + chrono::Duration::days(1)).await.ok()?;
+    let archive = archives.iter().find(|archive| {
+        archive.start_time == timestamp
+            || archive.original_path.as_deref()
+                .and_then(|path| std::path::Path::new(path).file_name())
+                .is_some_and(|name| name == filename)
+    })?;
+    crate::cold_storage::rehydration_response(&database, camera_id, archive.start_time, archive.start_time).await
+}
+
+/// Data of a database-stored MP4 segment, served from the segment cache when possible
+async fn cached_mp4_segment(
+    database: &Arc<dyn crate::database::DatabaseProvider>,
+    camera_id: &str,
+    start_time: DateTime<Utc>,
+) -> crate::errors::Result<Option<bytes::Bytes>> {
+    let cache_key = crate::segment_cache::mp4_key(camera_id, start_time);
+    if let Some(data) = crate::segment_cache::get(&cache_key) {
+        return Ok(Some(data));
+    }
+    let Some(data) = database.get_video_segment_by_time(camera_id, start_time).await?.and_then(|segment| segment.mp4_data) else {
+        return Ok(None);
+    };
+    let data = bytes::Bytes::from(data);
+    crate::segment_cache::insert(&cache_key, data.clone());
+    Ok(Some(data))
+}
+
 async fn stream_segment_from_database(
     camera_id: &str,
     filename: &str,
@@ -724,89 +1220,39 @@ async fn stream_segment_from_database(
     };
     drop(camera_streams);
 
-    // Create cache key from camera_id and filename
-    let cache_key = format!("{}:{}", camera_id, filename);
-    
-    // Try to get from cache first
-    let (data, file_size) = {
-        let cache = MP4_SEGMENT_CACHE.read().await;
-        if let Some(cached) = cache.get(&cache_key) {
-            if !cached.is_expired() {
-                debug!("Cache HIT for segment '{}', serving from cache", cache_key);
-                (cached.data.clone(), cached.size_bytes as u64)
-            } else {
-                debug!("Cache EXPIRED for segment '{}', fetching from database", cache_key);
-                (Vec::new(), 0) // Will fetch from database
-            }
-        } else {
-            debug!("Cache MISS for segment '{}', fetching from database", cache_key);
-            (Vec::new(), 0) // Will fetch from database
-        }
+    // Extract timestamp from filename and use efficient time-based lookup
+    let Some(timestamp) = parse_timestamp_from_filename(filename) else {
+        error!("Invalid filename format: {}. Expected format: YYYY-MM-DDTHH:MM:SS.ffffffZ or YYYY-MM-DDTHH-MM-SSZ.mp4/.mkv", filename);
+        return (axum::http::StatusCode::BAD_REQUEST, "Invalid filename format").into_response();
     };
-    
-    // If not in cache or expired, fetch from database
-    let (data, file_size) = if data.is_empty() {
-        // Extract timestamp from filename and use efficient time-based lookup
-        let segment = if let Some(timestamp) = parse_timestamp_from_filename(filename) {
-            match database.get_video_segment_by_time(camera_id, timestamp).await {
-                Ok(Some(segment)) => segment,
-                Ok(None) => {
-                    return (axum::http::StatusCode::NOT_FOUND, "Recording not found").into_response();
-                }
-                Err(e) => {
-                    error!("Failed to get segment by time: {}", e);
-                    return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response();
-                }
-            }
-        } else {
-            error!("Invalid filename format: {}. Expected format: YYYY-MM-DDTHH:MM:SS.ffffffZ or YYYY-MM-DDTHH-MM-SSZ.mp4", filename);
-            return (axum::http::StatusCode::BAD_REQUEST, "Invalid filename format").into_response();
-        };
-
-        let file_size = segment.size_bytes as u64;
-        let data = match segment.mp4_data {
-            Some(blob_data) => blob_data,
-            None => {
-                return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Segment data not found in database").into_response();
-            }
-        };
-        
-        // Cache the segment data
-        {
-            let mut cache = MP4_SEGMENT_CACHE.write().await;
-            
-            // Clean up expired entries while we have write lock
-            cache.retain(|_, v| !v.is_expired());
-            
-            // Add new entry to cache
-            cache.insert(cache_key.clone(), CachedSegment {
-                data: data.clone(),
-                size_bytes: segment.size_bytes,
-                cached_at: Instant::now(),
-            });
-            debug!("Cached segment '{}' ({} bytes) for future requests", cache_key, data.len());
-        }
-        
-        (data, file_size)
-    } else {
-        (data, file_size)
+    let data = match cached_mp4_segment(&database, camera_id, timestamp).await {
+        Ok(Some(data)) => data,
+        Ok(None) => {
+            return (axum::http::StatusCode::NOT_FOUND, "Recording not found").into_response();
+        }
+        Err(e) => {
+            error!("Failed to get segment by time: {}", e);
+            return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response();
+        }
     };
+    let file_size = data.len() as u64;
     
     debug!("Database segment info: filename='{}', file_size={}", filename, file_size);
+    let content_type = config::SegmentContainer::from_data(&data).content_type();
     
     let (start, end) = calculate_range(range, file_size);
     debug!("Database range calculation: requested={:?}, file_size={}, calculated={}..{}", 
            range, file_size, start, end);
 
-    let chunk = if start == 0 && end == file_size.saturating_sub(1) {
-        data
+    let chunk = if start <= end && (end as usize) < data.len() {
+        data.slice(start as usize..=end as usize)
     } else {
-        data.get(start as usize..=(end as usize)).unwrap_or(&data).to_vec()
+        data
     };
 
     let response = axum::response::Response::builder()
         .status(if range.is_some() { axum::http::StatusCode::PARTIAL_CONTENT } else { axum::http::StatusCode::OK })
-        .header("Content-Type", "video/mp4")
+        .header("Content-Type", content_type)
         .header("Accept-Ranges", "bytes")
         .header("Content-Length", chunk.len().to_string())
         .header("Cache-Control", "public, max-age=3600");
@@ -843,28 +1289,11 @@ async fn stream_segment_from_filesystem(
     debug!("stream_segment_from_filesystem called: camera_id='{}', filename='{}', range='{}'", 
            camera_id, filename, range_str);
     
-    // Create cache key from camera_id and filename
-    let cache_key = format!("{}:{}", camera_id, filename);
-    
-    // Try to get from cache first
-    let (file_data, file_size) = {
-        let cache = MP4_SEGMENT_CACHE.read().await;
-        if let Some(cached) = cache.get(&cache_key) {
-            if !cached.is_expired() {
-                debug!("Cache HIT for filesystem segment '{}', serving from cache", cache_key);
-                (cached.data.clone(), cached.size_bytes as u64)
-            } else {
-                debug!("Cache EXPIRED for filesystem segment '{}', reading from disk", cache_key);
-                (Vec::new(), 0) // Will read from filesystem
-            }
-        } else {
-            debug!("Cache MISS for filesystem segment '{}', reading from disk", cache_key);
-            (Vec::new(), 0) // Will read from filesystem
-        }
-    };
-    
-    // If not in cache or expired, read from filesystem
-    let (file_data, file_size) = if file_data.is_empty() {
+    // Read from the filesystem unless the segment is cached
+    let cache_key = crate::segment_cache::mp4_file_key(camera_id, filename);
+    let file_data = if let Some(file_data) = crate::segment_cache::get(&cache_key) {
+        file_data
+    } else {
         let base_path = std::path::PathBuf::from(&recording_config.database_path);
 
         // Extract timestamp from filename to construct the exact path
@@ -904,56 +1333,34 @@ async fn stream_segment_from_filesystem(
             }
         };
 
-        let metadata = match tokio::fs::metadata(&file_path).await {
-            Ok(metadata) => metadata,
-            Err(e) => { 
-                error!("Failed to get file metadata: {}", e); 
-                return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to access file").into_response(); 
-            }
-        };
-
-        let file_size = metadata.len();
-        
         let file_data = match tokio::fs::read(&file_path).await {
-            Ok(data) => data,
+            Ok(data) => bytes::Bytes::from(data),
             Err(e) => { 
                 error!("Failed to read file: {}", e); 
                 return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Failed to read file").into_response(); 
             }
         };
-        
-        // Cache the file data
-        {
-            let mut cache = MP4_SEGMENT_CACHE.write().await;
-            
-            // Clean up expired entries while we have write lock
-            cache.retain(|_, v| !v.is_expired());
-            
-            // Add new entry to cache
-            cache.insert(cache_key.clone(), CachedSegment {
-                data: file_data.clone(),
-                size_bytes: file_size as i64,
-                cached_at: Instant::now(),
-            });
-            debug!("Cached filesystem segment '{}' ({} bytes) for future requests", cache_key, file_data.len());
-        }
-        
-        (file_data, file_size)
-    } else {
-        (file_data, file_size)
+        crate::segment_cache::insert(&cache_key, file_data.clone());
+        file_data
     };
+    let file_size = file_data.len() as u64;
     
     debug!("Filesystem segment info: filename='{}', file_size={}", filename, file_size);
+    let content_type = config::SegmentContainer::from_data(&file_data).content_type();
     
     let (start, end) = calculate_range(range, file_size);
     debug!("Filesystem range calculation: requested={:?}, file_size={}, calculated={}..{}", 
            range, file_size, start, end);
 
-    let chunk = file_data.get(start as usize..=(end as usize)).unwrap_or(&file_data).to_vec();
+    let chunk = if start <= end && (end as usize) < file_data.len() {
+        file_data.slice(start as usize..=end as usize)
+    } else {
+        file_data
+    };
 
     let response = axum::response::Response::builder()
         .status(if range.is_some() { axum::http::StatusCode::PARTIAL_CONTENT } else { axum::http::StatusCode::OK })
-        .header("Content-Type", "video/mp4")
+        .header("Content-Type", content_type)
         .header("Accept-Ranges", "bytes")
         .header("Content-Length", chunk.len().to_string())
         .header("Cache-Control", "public, max-age=3600");
@@ -0,0 +1,134 @@
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::Arc;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use tokio::io::AsyncReadExt;
+use tokio::process::{Child, ChildStdout, Command};
+use tracing::{debug, info, warn};
+
+use crate::database::DatabaseProvider;
+use crate::errors::{Result, StreamError};
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+// Extracted database segments and the concat list, removed when the stream is dropped
+struct TempDir(PathBuf);
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_dir_all(&self.0) {
+            warn!("Failed to remove MP4 concat directory {}: {}", self.0.display(), e);
+        }
+    }
+}
+
+/// Stored MP4 segments of a time range remuxed by FFmpeg's concat demuxer into one fragmented
+/// MP4, read from FFmpeg's stdout while it is written. Dropping the stream kills FFmpeg.
+pub struct ConcatStream {
+    child: Child,
+    stdout: ChildStdout,
+    stderr_task: tokio::task::JoinHandle<String>,
+    segment_count: usize,
+    _temp_dir: TempDir,
+}
+
+impl ConcatStream {
+    /// Start remuxing the segments overlapping `from..to`, trimmed to the range at keyframe
+    /// precision. Returns None when no segments were recorded in the range.
+    pub async fn start(
+        database: Arc<dyn DatabaseProvider>,
+        camera_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        recording_base_path: &str,
+    ) -> Result<Option<Self>> {
+        let segments = database.get_mp4_segments_in_range(camera_id, from, to).await?;
+        if segments.is_empty() {
+            return Ok(None);
+        }
+
+        let temp_dir = TempDir(std::env::temp_dir().join(format!("mp4_concat_{}", uuid::Uuid::new_v4().simple())));
+        std::fs::create_dir_all(&temp_dir.0)?;
+
+        let mut concat_list = String::from("ffconcat version 1.0\n");
+        let mut segment_count = 0;
+        for (idx, segment) in segments.iter().enumerate() {
+            let file_path = match segment.storage_path {
+                Some(ref storage_path) => {
+                    let path = PathBuf::from(recording_base_path).join(camera_id).join(storage_path);
+                    // Absolute, the concat demuxer resolves relative paths against the list file
+                    match std::fs::canonicalize(&path) {
+                        Ok(path) => path,
+                        Err(_) => {
+                            // Removed by retention since the query, leave a gap instead of failing
+                            warn!("[{}] MP4 segment {} is missing, skipping it", camera_id, path.display());
+                            continue;
+                        }
+                    }
+                }
+                None => {
+                    let path = temp_dir.0.join(format!("segment_{}", idx));
+                    database.extract_mp4_segment_to_file(camera_id, segment.start_time, &path.to_string_lossy()).await?;
+                    path
+                }
+            };
+
+            concat_list.push_str(&format!("file '{}'\n", file_path.to_string_lossy().replace('\'', "'\\''")));
+            if from > segment.start_time {
+                concat_list.push_str(&format!("inpoint {:.3}\n", (from - segment.start_time).num_milliseconds() as f64 / 1000.0));
+            }
+            if to < segment.end_time {
+                concat_list.push_str(&format!("outpoint {:.3}\n", (to - segment.start_time).num_milliseconds() as f64 / 1000.0));
+            }
+            segment_count += 1;
+        }
+        if segment_count == 0 {
+            return Ok(None);
+        }
+        let list_path = temp_dir.0.join("concat.txt");
+        std::fs::write(&list_path, concat_list)?;
+
+        info!("[{}] Streaming {} MP4 segments from {} to {} as one MP4", camera_id, segment_count, from.to_rfc3339(), to.to_rfc3339());
+        // Fragmented output can be written to a pipe and played while it is downloaded
+        let mut child = Command::new("ffmpeg")
+            .args(["-loglevel", "error", "-f", "concat", "-safe", "0", "-i"])
+            .arg(&list_path)
+            .args(["-map", "0", "-c", "copy", "-movflags", "frag_keyframe+empty_moov+default_base_moof", "-f", "mp4", "-"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+        let stdout = child.stdout.take().ok_or_else(|| StreamError::ffmpeg("Failed to open FFmpeg stdout"))?;
+        let mut stderr = child.stderr.take().ok_or_else(|| StreamError::ffmpeg("Failed to open FFmpeg stderr"))?;
+        let stderr_task = tokio::spawn(async move {
+            let mut output = String::new();
+            let _ = stderr.read_to_string(&mut output).await;
+            output
+        });
+
+        Ok(Some(Self { child, stdout, stderr_task, segment_count, _temp_dir: temp_dir }))
+    }
+
+    pub fn segment_count(&self) -> usize {
+        self.segment_count
+    }
+
+    /// Next chunk of the MP4, None once FFmpeg finished successfully
+    pub async fn next_chunk(&mut self) -> Result<Option<Bytes>> {
+        let mut buffer = vec![0u8; CHUNK_SIZE];
+        let n = self.stdout.read(&mut buffer).await?;
+        if n > 0 {
+            buffer.truncate(n);
+            return Ok(Some(Bytes::from(buffer)));
+        }
+        let status = self.child.wait().await?;
+        let errors = (&mut self.stderr_task).await.unwrap_or_default();
+        if !status.success() {
+            return Err(StreamError::ffmpeg(format!("MP4 concatenation failed: {}", errors.trim())));
+        }
+        debug!("MP4 concatenation of {} segments finished", self.segment_count);
+        Ok(None)
+    }
+}
@@ -1,13 +1,15 @@
 use crate::errors::{Result, StreamError};
-use rumqttc::{AsyncClient, Event, EventLoop, MqttOptions, Packet, QoS};
+use rumqttc::tokio_rustls::rustls;
+use rumqttc::{v5, AsyncClient, Event, EventLoop, MqttOptions, Packet, QoS, TlsConfiguration, Transport};
 use serde::Serialize;
-use std::collections::HashMap;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, RwLock};
 use tokio::time::{interval, Duration};
 use tracing::{error, info, warn};
 
-use crate::config::MqttConfig;
+use crate::config::{MqttConfig, MqttMessageConfig, MqttProtocolVersion, MqttTlsConfig, MqttTopicConfig};
 use chrono::Utc;
 
 #[derive(Debug, Clone, Serialize)]
@@ -19,6 +21,9 @@ pub struct CameraStatus {
     pub last_frame_time: Option<String>,
     pub ffmpeg_running: bool,
     pub duplicate_frames: u64,
+    pub ffmpeg_cpu_percent: Option<f32>,
+    pub ffmpeg_rss_kb: Option<u64>,
+    pub clock_drift_ms: Option<i64>, // Camera clock minus server clock, if clock_drift is enabled
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -59,17 +64,330 @@ pub struct ServerStatus {
     pub total_cameras: usize,
 }
 
+/// Topic classes with their own QoS, retain and expiry settings
+#[derive(Debug, Clone, Copy)]
+enum TopicClass {
+    Status,
+    Clients,
+    Events,
+    Images,
+}
+
+impl TopicClass {
+    fn settings(self, config: &MqttConfig) -> (&MqttTopicConfig, bool) {
+        match self {
+            Self::Status => (&config.topics.status, config.retain),
+            Self::Clients => (&config.topics.clients, config.retain),
+            Self::Events => (&config.topics.events, false),
+            Self::Images => (&config.topics.images, false),
+        }
+    }
+}
+
+/// Published messages, each with a configurable topic and payload
+#[derive(Debug, Clone, Copy)]
+enum MessageKind {
+    ServerStatus,
+    CameraStatus,
+    ClientStatus,
+    ClientConnected,
+    ClientDisconnected,
+    PictureArrival,
+    Throughput,
+    Image,
+    CameraEvent,
+    EventImage,
+}
+
+impl MessageKind {
+    const ALL: [MessageKind; 10] = [
+        Self::ServerStatus, Self::CameraStatus, Self::ClientStatus, Self::ClientConnected,
+        Self::ClientDisconnected, Self::PictureArrival, Self::Throughput, Self::Image,
+        Self::CameraEvent, Self::EventImage,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::ServerStatus => "server_status",
+            Self::CameraStatus => "camera_status",
+            Self::ClientStatus => "client_status",
+            Self::ClientConnected => "client_connected",
+            Self::ClientDisconnected => "client_disconnected",
+            Self::PictureArrival => "picture_arrival",
+            Self::Throughput => "throughput",
+            Self::Image => "image",
+            Self::CameraEvent => "camera_event",
+            Self::EventImage => "event_image",
+        }
+    }
+
+    fn settings(self, config: &MqttConfig) -> &MqttMessageConfig {
+        let messages = &config.messages;
+        match self {
+            Self::ServerStatus => &messages.server_status,
+            Self::CameraStatus => &messages.camera_status,
+            Self::ClientStatus => &messages.client_status,
+            Self::ClientConnected => &messages.client_connected,
+            Self::ClientDisconnected => &messages.client_disconnected,
+            Self::PictureArrival => &messages.picture_arrival,
+            Self::Throughput => &messages.throughput,
+            Self::Image => &messages.image,
+            Self::CameraEvent => &messages.camera_event,
+            Self::EventImage => &messages.event_image,
+        }
+    }
+
+    fn default_topic(self) -> &'static str {
+        match self {
+            Self::ServerStatus => "{prefix}/status",
+            Self::CameraStatus => "{prefix}/cameras/{camera_id}/status",
+            Self::ClientStatus => "{prefix}/clients/{client_id}/status",
+            Self::ClientConnected => "{prefix}/clients/connected",
+            Self::ClientDisconnected => "{prefix}/clients/disconnected",
+            Self::PictureArrival => "{prefix}/cameras/{camera_id}/capturing",
+            Self::Throughput => "{prefix}/cameras/{camera_id}/throughput",
+            Self::Image => "{prefix}/cameras/{camera_id}/jpg",
+            Self::CameraEvent => "{prefix}/cameras/{camera_id}/events",
+            Self::EventImage => "{prefix}/cameras/{camera_id}/events/jpg",
+        }
+    }
+
+    // Placeholders besides {prefix} that the topic of the message can use
+    fn variables(self) -> &'static [&'static str] {
+        match self {
+            Self::ServerStatus => &[],
+            Self::CameraStatus | Self::PictureArrival | Self::Throughput | Self::Image => &["camera_id"],
+            Self::ClientStatus | Self::ClientConnected | Self::ClientDisconnected => &["client_id", "camera_id"],
+            Self::CameraEvent | Self::EventImage => &["camera_id", "event"],
+        }
+    }
+
+    fn enabled(self, config: &MqttConfig) -> bool {
+        let default = match self {
+            Self::PictureArrival => config.publish_picture_arrival.unwrap_or(true),
+            _ => true,
+        };
+        self.settings(config).enabled.unwrap_or(default)
+    }
+
+    fn topic(self, config: &MqttConfig, vars: &[(&str, &str)]) -> String {
+        let template = self.settings(config).topic.as_deref().unwrap_or(self.default_topic());
+        vars.iter().fold(template.replace("{prefix}", &config.base_topic), |topic, (name, value)| {
+            topic.replace(&format!("{{{}}}", name), value)
+        })
+    }
+
+    /// The message serialized as is, or rendered through the payload template
+    fn payload<T: Serialize>(self, config: &MqttConfig, message: &T, vars: &[(&str, &str)]) -> Result<String> {
+        let Some(ref template) = self.settings(config).payload else {
+            return Ok(serde_json::to_string(message)?);
+        };
+        let mut fields = match serde_json::to_value(message)? {
+            Value::Object(fields) => fields,
+            _ => serde_json::Map::new(),
+        };
+        for (name, value) in vars {
+            fields.entry(name.to_string()).or_insert_with(|| Value::String(value.to_string()));
+        }
+        Ok(serde_json::to_string(&render_payload(template, &fields))?)
+    }
+}
+
+// A string that is exactly one placeholder keeps the field's JSON type, other strings get the text inserted
+fn render_payload(template: &Value, fields: &serde_json::Map<String, Value>) -> Value {
+    match template {
+        Value::String(text) => {
+            if let Some(value) = text.strip_prefix('{').and_then(|t| t.strip_suffix('}')).and_then(|name| fields.get(name)) {
+                return value.clone();
+            }
+            let rendered = fields.iter().fold(text.clone(), |text, (name, value)| {
+                let placeholder = format!("{{{}}}", name);
+                if !text.contains(&placeholder) {
+                    return text;
+                }
+                match value {
+                    Value::String(value) => text.replace(&placeholder, value),
+                    value => text.replace(&placeholder, &value.to_string()),
+                }
+            });
+            Value::String(rendered)
+        }
+        Value::Array(items) => Value::Array(items.iter().map(|item| render_payload(item, fields)).collect()),
+        Value::Object(map) => Value::Object(map.iter().map(|(key, value)| (key.clone(), render_payload(value, fields))).collect()),
+        other => other.clone(),
+    }
+}
+
+/// Reject topic templates with placeholders the message does not provide
+fn validate_topic_templates(config: &MqttConfig) -> Result<()> {
+    for kind in MessageKind::ALL {
+        let vars: Vec<(&str, &str)> = kind.variables().iter().map(|name| (*name, "x")).collect();
+        let topic = kind.topic(config, &vars);
+        if topic.contains('{') || topic.is_empty() {
+            return Err(StreamError::mqtt(format!(
+                "Invalid MQTT topic template for {}: '{}' (placeholders: {{prefix}}{})",
+                kind.name(), kind.settings(config).topic.as_deref().unwrap_or_default(),
+                kind.variables().iter().map(|name| format!(", {{{}}}", name)).collect::<String>())));
+        }
+    }
+    Ok(())
+}
+
+fn qos_level(qos: u8) -> QoS {
+    match qos {
+        0 => QoS::AtMostOnce,
+        1 => QoS::AtLeastOnce,
+        _ => QoS::ExactlyOnce,
+    }
+}
+
+fn qos_level_v5(qos: u8) -> v5::mqttbytes::QoS {
+    match qos {
+        0 => v5::mqttbytes::QoS::AtMostOnce,
+        1 => v5::mqttbytes::QoS::AtLeastOnce,
+        _ => v5::mqttbytes::QoS::ExactlyOnce,
+    }
+}
+
+/// Client of either protocol version
+#[derive(Clone)]
+enum MqttClient {
+    V311(AsyncClient),
+    V5(v5::AsyncClient),
+}
+
+enum MqttEventLoop {
+    V311(Box<EventLoop>),
+    V5(Box<v5::EventLoop>),
+}
+
+enum MqttEvent {
+    Connected,
+    Message(String, bytes::Bytes),
+    Disconnected,
+    Other,
+}
+
+impl MqttClient {
+    /// Publish with explicit settings; the message expiry only exists in MQTT v5
+    async fn publish_with(&self, topic: String, qos: u8, retain: bool, payload: Vec<u8>, message_expiry_secs: Option<u32>) -> Result<()> {
+        match self {
+            Self::V311(client) => client.publish(topic, qos_level(qos), retain, payload).await?,
+            Self::V5(client) => {
+                let properties = v5::mqttbytes::v5::PublishProperties {
+                    message_expiry_interval: message_expiry_secs,
+                    ..Default::default()
+                };
+                client.publish_with_properties(topic, qos_level_v5(qos), retain, payload, properties).await
+                    .map_err(|e| StreamError::mqtt(format!("MQTT client error: {}", e)))?
+            }
+        }
+        Ok(())
+    }
+
+    /// Publish with the settings of the topic class
+    async fn publish(&self, config: &MqttConfig, class: TopicClass, topic: String, payload: impl Into<Vec<u8>>) -> Result<()> {
+        let (settings, default_retain) = class.settings(config);
+        self.publish_with(
+            topic,
+            settings.qos.unwrap_or(config.qos),
+            settings.retain.unwrap_or(default_retain),
+            payload.into(),
+            settings.message_expiry_secs,
+        ).await
+    }
+
+    async fn subscribe(&self, topic: &str, qos: u8) -> Result<()> {
+        match self {
+            Self::V311(client) => client.subscribe(topic, qos_level(qos)).await?,
+            Self::V5(client) => client.subscribe(topic, qos_level_v5(qos)).await
+                .map_err(|e| StreamError::mqtt(format!("MQTT client error: {}", e)))?,
+        }
+        Ok(())
+    }
+
+    async fn unsubscribe(&self, topic: &str) -> Result<()> {
+        match self {
+            Self::V311(client) => client.unsubscribe(topic).await?,
+            Self::V5(client) => client.unsubscribe(topic).await
+                .map_err(|e| StreamError::mqtt(format!("MQTT client error: {}", e)))?,
+        }
+        Ok(())
+    }
+}
+
+impl MqttEventLoop {
+    async fn poll(&mut self) -> std::result::Result<MqttEvent, String> {
+        match self {
+            Self::V311(eventloop) => match eventloop.poll().await.map_err(|e| e.to_string())? {
+                Event::Incoming(Packet::ConnAck(_)) => Ok(MqttEvent::Connected),
+                Event::Incoming(Packet::Publish(publish)) => Ok(MqttEvent::Message(publish.topic, publish.payload)),
+                Event::Incoming(Packet::Disconnect) => Ok(MqttEvent::Disconnected),
+                _ => Ok(MqttEvent::Other),
+            },
+            Self::V5(eventloop) => match eventloop.poll().await.map_err(|e| e.to_string())? {
+                v5::Event::Incoming(v5::mqttbytes::v5::Packet::ConnAck(_)) => Ok(MqttEvent::Connected),
+                v5::Event::Incoming(v5::mqttbytes::v5::Packet::Publish(publish)) => {
+                    Ok(MqttEvent::Message(String::from_utf8_lossy(&publish.topic).into_owned(), publish.payload))
+                }
+                v5::Event::Incoming(v5::mqttbytes::v5::Packet::Disconnect(_)) => Ok(MqttEvent::Disconnected),
+                _ => Ok(MqttEvent::Other),
+            },
+        }
+    }
+}
+
+fn read_pem_certificates(path: &str) -> Result<Vec<rustls::pki_types::CertificateDer<'static>>> {
+    let pem = std::fs::read(path)
+        .map_err(|e| StreamError::mqtt(format!("Failed to read '{}': {}", path, e)))?;
+    rustls_pemfile::certs(&mut pem.as_slice())
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| StreamError::mqtt(format!("Invalid certificate in '{}': {}", path, e)))
+}
+
+fn tls_configuration(tls: &MqttTlsConfig) -> Result<TlsConfiguration> {
+    let mut roots = rustls::RootCertStore::empty();
+    match tls.ca_file {
+        Some(ref ca_file) => {
+            for certificate in read_pem_certificates(ca_file)? {
+                roots.add(certificate)
+                    .map_err(|e| StreamError::mqtt(format!("Invalid certificate in '{}': {}", ca_file, e)))?;
+            }
+        }
+        None => roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned()),
+    }
+    let builder = rustls::ClientConfig::builder().with_root_certificates(roots);
+
+    let tls_config = match (&tls.client_cert_file, &tls.client_key_file) {
+        (Some(cert_file), Some(key_file)) => {
+            let certificates = read_pem_certificates(cert_file)?;
+            let pem = std::fs::read(key_file)
+                .map_err(|e| StreamError::mqtt(format!("Failed to read '{}': {}", key_file, e)))?;
+            let key = rustls_pemfile::private_key(&mut pem.as_slice())
+                .map_err(|e| StreamError::mqtt(format!("Invalid private key in '{}': {}", key_file, e)))?
+                .ok_or_else(|| StreamError::mqtt(format!("No private key found in '{}'", key_file)))?;
+            builder.with_client_auth_cert(certificates, key)
+                .map_err(|e| StreamError::mqtt(format!("Invalid MQTT client certificate: {}", e)))?
+        }
+        (None, None) => builder.with_no_client_auth(),
+        _ => return Err(StreamError::mqtt("MQTT client certificate and key must be configured together")),
+    };
+    Ok(TlsConfiguration::Rustls(Arc::new(tls_config)))
+}
+
 pub struct MqttPublisher {
-    client: AsyncClient,
-    eventloop: EventLoop,
+    client: MqttClient,
+    eventloop: MqttEventLoop,
     config: MqttConfig,
     camera_status: Arc<RwLock<HashMap<String, CameraStatus>>>,
     client_status: Arc<RwLock<Vec<ClientStatus>>>,
     start_time: std::time::Instant,
+    incoming: broadcast::Sender<(String, bytes::Bytes)>,
+    subscriptions: Arc<RwLock<HashSet<String>>>,
 }
 
 impl MqttPublisher {
-    pub async fn new(config: MqttConfig) -> Result<Self> {
+    pub async fn new(config: MqttConfig, channel_capacity: usize) -> Result<Self> {
         // Parse the broker URL to extract host and port
         let url = url::Url::parse(&config.broker_url)
             .map_err(|e| StreamError::mqtt(format!("Invalid MQTT broker URL '{}': {}", config.broker_url, e)))?;
@@ -77,29 +395,52 @@ impl MqttPublisher {
         let host = url.host_str()
             .ok_or_else(|| StreamError::mqtt(format!("No host found in MQTT broker URL: {}", config.broker_url)))?;
         
-        let port = url.port().unwrap_or(1883);
-        
-        info!("Connecting to MQTT broker at {}:{}", host, port);
-        
-        let mut mqtt_options = MqttOptions::new(
-            &config.client_id,
-            host,
-            port,
-        );
+        validate_topic_templates(&config)?;
+
+        let use_tls = config.tls.is_some() || matches!(url.scheme(), "mqtts" | "ssl");
+        let port = url.port().unwrap_or(if use_tls { 8883 } else { 1883 });
+        let transport = if use_tls {
+            Transport::tls_with_config(tls_configuration(&config.tls.clone().unwrap_or_default())?)
+        } else {
+            Transport::tcp()
+        };
         
-        mqtt_options.set_keep_alive(Duration::from_secs(config.keep_alive_secs));
+        info!("Connecting to MQTT broker at {}:{} (MQTT {}{})", host, port,
+              if config.protocol_version == MqttProtocolVersion::V5 { "5" } else { "3.1.1" },
+              if use_tls { ", TLS" } else { "" });
         
         // Set maximum packet size (default to 256MB if not specified)
         let max_packet_size = config.max_packet_size.unwrap_or(268435455); // 256MB - 1 byte
-        mqtt_options.set_max_packet_size(max_packet_size, max_packet_size);
         
-        if let Some(username) = &config.username {
-            if let Some(password) = &config.password {
-                mqtt_options.set_credentials(username, password);
+        let (client, eventloop) = match config.protocol_version {
+            MqttProtocolVersion::V311 => {
+                let topics = &config.topics;
+                if [&topics.status, &topics.clients, &topics.events, &topics.images].iter().any(|t| t.message_expiry_secs.is_some()) {
+                    warn!("MQTT message expiry requires protocol_version \"5\", it is ignored with MQTT 3.1.1");
+                }
+                let mut mqtt_options = MqttOptions::new(&config.client_id, host, port);
+                mqtt_options.set_keep_alive(Duration::from_secs(config.keep_alive_secs));
+                mqtt_options.set_max_packet_size(max_packet_size, max_packet_size);
+                mqtt_options.set_transport(transport);
+                if let (Some(username), Some(password)) = (&config.username, &config.password) {
+                    mqtt_options.set_credentials(username, password);
+                }
+                let (client, eventloop) = AsyncClient::new(mqtt_options, 100);
+                (MqttClient::V311(client), MqttEventLoop::V311(Box::new(eventloop)))
             }
-        }
-        
-        let (client, eventloop) = AsyncClient::new(mqtt_options, 100);
+            MqttProtocolVersion::V5 => {
+                let mut mqtt_options = v5::MqttOptions::new(&config.client_id, host, port);
+                mqtt_options.set_keep_alive(Duration::from_secs(config.keep_alive_secs));
+                mqtt_options.set_max_packet_size(Some(max_packet_size.min(u32::MAX as usize) as u32));
+                mqtt_options.set_transport(transport);
+                if let (Some(username), Some(password)) = (&config.username, &config.password) {
+                    mqtt_options.set_credentials(username, password);
+                }
+                let (client, eventloop) = v5::AsyncClient::new(mqtt_options, 100);
+                (MqttClient::V5(client), MqttEventLoop::V5(Box::new(eventloop)))
+            }
+        };
+        let (incoming, _) = broadcast::channel(channel_capacity);
         
         Ok(Self {
             client,
@@ -108,6 +449,8 @@ impl MqttPublisher {
             camera_status: Arc::new(RwLock::new(HashMap::new())),
             client_status: Arc::new(RwLock::new(Vec::new())),
             start_time: std::time::Instant::now(),
+            incoming,
+            subscriptions: Arc::new(RwLock::new(HashSet::new())),
         })
     }
     
@@ -116,18 +459,32 @@ impl MqttPublisher {
         let config = self.config.clone();
         let camera_status = self.camera_status.clone();
         let client_status = self.client_status.clone();
+        let incoming = self.incoming.clone();
+        let subscriptions = self.subscriptions.clone();
+        let resubscribe_client = client.clone();
+        let qos = config.qos;
         
         // Spawn event loop handler
         let _eventloop_handle = tokio::spawn(async move {
             loop {
                 match self.eventloop.poll().await {
-                    Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                    Ok(MqttEvent::Connected) => {
                         info!("Connected to MQTT broker");
+                        // Subscriptions are not persisted by the broker for clean sessions, restore them
+                        for topic in self.subscriptions.read().await.iter() {
+                            if let Err(e) = resubscribe_client.subscribe(topic, qos).await {
+                                error!("Failed to resubscribe to MQTT topic '{}': {}", topic, e);
+                            }
+                        }
+                    }
+                    Ok(MqttEvent::Message(topic, payload)) => {
+                        // No receivers just means nobody is listening for inbound messages
+                        let _ = self.incoming.send((topic, payload));
                     }
-                    Ok(Event::Incoming(Packet::Disconnect)) => {
+                    Ok(MqttEvent::Disconnected) => {
                         warn!("Disconnected from MQTT broker");
                     }
-                    Ok(_) => {}
+                    Ok(MqttEvent::Other) => {}
                     Err(e) => {
                         error!("MQTT connection error: {}", e);
                         tokio::time::sleep(Duration::from_secs(5)).await;
@@ -159,40 +516,24 @@ impl MqttPublisher {
                     total_cameras: cameras.len(),
                 };
                 
-                if let Ok(payload) = serde_json::to_string(&status) {
-                    let topic = format!("{}/status", config_clone.base_topic);
-                    let qos = match config_clone.qos {
-                        0 => QoS::AtMostOnce,
-                        1 => QoS::AtLeastOnce,
-                        _ => QoS::ExactlyOnce,
-                    };
-                    
-                    if let Err(e) = client_clone.publish(
-                        topic,
-                        qos,
-                        config_clone.retain,
-                        payload.as_bytes(),
-                    ).await {
-                        error!("Failed to publish server status: {}", e);
+                if MessageKind::ServerStatus.enabled(&config_clone) {
+                    if let Ok(payload) = MessageKind::ServerStatus.payload(&config_clone, &status, &[]) {
+                        let topic = MessageKind::ServerStatus.topic(&config_clone, &[]);
+                        if let Err(e) = client_clone.publish(&config_clone, TopicClass::Status, topic, payload).await {
+                            error!("Failed to publish server status: {}", e);
+                        }
                     }
                 }
                 
                 // Also publish individual camera status updates at the same interval
+                if !MessageKind::CameraStatus.enabled(&config_clone) {
+                    continue;
+                }
                 for (camera_id, camera_status) in &cameras {
-                    if let Ok(payload) = serde_json::to_string(&camera_status) {
-                        let topic = format!("{}/cameras/{}/status", config_clone.base_topic, camera_id);
-                        let qos = match config_clone.qos {
-                            0 => QoS::AtMostOnce,
-                            1 => QoS::AtLeastOnce,
-                            _ => QoS::ExactlyOnce,
-                        };
-                        
-                        if let Err(e) = client_clone.publish(
-                            topic,
-                            qos,
-                            config_clone.retain,
-                            payload.as_bytes(),
-                        ).await {
+                    let vars = [("camera_id", camera_id.as_str())];
+                    if let Ok(payload) = MessageKind::CameraStatus.payload(&config_clone, &camera_status, &vars) {
+                        let topic = MessageKind::CameraStatus.topic(&config_clone, &vars);
+                        if let Err(e) = client_clone.publish(&config_clone, TopicClass::Status, topic, payload).await {
                             error!("Failed to publish camera status for {}: {}", camera_id, e);
                         }
                     }
@@ -205,19 +546,48 @@ impl MqttPublisher {
             camera_status,
             client_status,
             config,
+            incoming,
+            subscriptions,
         })
     }
 }
 
 #[derive(Clone)]
 pub struct MqttHandle {
-    client: AsyncClient,
+    client: MqttClient,
     camera_status: Arc<RwLock<HashMap<String, CameraStatus>>>,
     client_status: Arc<RwLock<Vec<ClientStatus>>>,
     config: MqttConfig,
+    incoming: broadcast::Sender<(String, bytes::Bytes)>,
+    subscriptions: Arc<RwLock<HashSet<String>>>,
 }
 
 impl MqttHandle {
+    /// Subscribe to an inbound topic. Subscriptions are restored after reconnects.
+    pub async fn subscribe(&self, topic: &str) -> Result<()> {
+        if !self.subscriptions.write().await.insert(topic.to_string()) {
+            return Ok(());
+        }
+        self.client.subscribe(topic, self.config.qos).await?;
+        info!("Subscribed to MQTT topic '{}'", topic);
+        Ok(())
+    }
+
+    /// Drop a subscription, so it is neither delivered nor restored after reconnects
+    pub async fn unsubscribe(&self, topic: &str) -> Result<()> {
+        if !self.subscriptions.write().await.remove(topic) {
+            return Ok(());
+        }
+        self.client.unsubscribe(topic).await?;
+        info!("Unsubscribed from MQTT topic '{}'", topic);
+        Ok(())
+    }
+
+    /// Receiver for messages arriving on subscribed topics as (topic, payload)
+    pub fn incoming_messages(&self) -> broadcast::Receiver<(String, bytes::Bytes)> {
+        self.incoming.subscribe()
+    }
+
     pub async fn update_camera_status(&self, camera_id: String, status: CameraStatus) {
         let mut cameras = self.camera_status.write().await;
         cameras.insert(camera_id.clone(), status.clone());
@@ -230,45 +600,29 @@ impl MqttHandle {
         let mut clients = self.client_status.write().await;
         clients.push(client.clone());
         
+        let vars = [("client_id", client.id.as_str()), ("camera_id", client.camera_id.as_str())];
+        
         // Publish client status to individual client topic
-        let topic = format!("{}/clients/{}/status", self.config.base_topic, client.id);
-        if let Ok(payload) = serde_json::to_string(&client) {
-            let qos = match self.config.qos {
-                0 => QoS::AtMostOnce,
-                1 => QoS::AtLeastOnce,
-                _ => QoS::ExactlyOnce,
-            };
-            
-            if let Err(e) = self.client.publish(
-                topic,
-                qos,
-                self.config.retain,
-                payload.as_bytes(),
-            ).await {
-                error!("Failed to publish client status: {}", e);
+        if MessageKind::ClientStatus.enabled(&self.config) {
+            let topic = MessageKind::ClientStatus.topic(&self.config, &vars);
+            if let Ok(payload) = MessageKind::ClientStatus.payload(&self.config, &client, &vars) {
+                if let Err(e) = self.client.publish(&self.config, TopicClass::Clients, topic, payload).await {
+                    error!("Failed to publish client status: {}", e);
+                }
             }
         }
         
         // Also publish connection event to global connected topic
-        let event_topic = format!("{}/clients/connected", self.config.base_topic);
-        let event = ClientEvent {
-            client_id: client.id.clone(),
-            timestamp: client.connected_at.clone(),
-        };
-        if let Ok(payload) = serde_json::to_string(&event) {
-            let qos = match self.config.qos {
-                0 => QoS::AtMostOnce,
-                1 => QoS::AtLeastOnce,
-                _ => QoS::ExactlyOnce,
+        if MessageKind::ClientConnected.enabled(&self.config) {
+            let event_topic = MessageKind::ClientConnected.topic(&self.config, &vars);
+            let event = ClientEvent {
+                client_id: client.id.clone(),
+                timestamp: client.connected_at.clone(),
             };
-            
-            if let Err(e) = self.client.publish(
-                event_topic,
-                qos,
-                false, // Don't retain events
-                payload.as_bytes(),
-            ).await {
-                error!("Failed to publish client connection event: {}", e);
+            if let Ok(payload) = MessageKind::ClientConnected.payload(&self.config, &event, &vars) {
+                if let Err(e) = self.client.publish(&self.config, TopicClass::Events, event_topic, payload).await {
+                    error!("Failed to publish client connection event: {}", e);
+                }
             }
         }
     }
@@ -278,37 +632,35 @@ impl MqttHandle {
         if let Some(pos) = clients.iter().position(|c| c.id == client_id) {
             let client = clients.remove(pos);
             
-            // Remove client status from individual client topic (publish empty retained message)
-            let topic = format!("{}/clients/{}/status", self.config.base_topic, client_id);
-            let qos = match self.config.qos {
-                0 => QoS::AtMostOnce,
-                1 => QoS::AtLeastOnce,
-                _ => QoS::ExactlyOnce,
-            };
+            let vars = [("client_id", client.id.as_str()), ("camera_id", client.camera_id.as_str())];
             
-            if let Err(e) = self.client.publish(
-                topic,
-                qos,
-                true, // Retain empty message to clear the topic
-                &[],  // Empty payload
-            ).await {
-                error!("Failed to clear client status topic: {}", e);
+            // Remove client status from individual client topic (publish empty retained message)
+            if MessageKind::ClientStatus.enabled(&self.config) {
+                let topic = MessageKind::ClientStatus.topic(&self.config, &vars);
+                let qos = self.config.topics.clients.qos.unwrap_or(self.config.qos);
+                
+                if let Err(e) = self.client.publish_with(
+                    topic,
+                    qos,
+                    true,       // Retain empty message to clear the topic
+                    Vec::new(), // Empty payload
+                    None,
+                ).await {
+                    error!("Failed to clear client status topic: {}", e);
+                }
             }
             
             // Publish client disconnection event to global disconnected topic
-            let event_topic = format!("{}/clients/disconnected", self.config.base_topic);
-            let event = ClientEvent {
-                client_id: client.id.clone(),
-                timestamp: Utc::now().to_rfc3339(),
-            };
-            if let Ok(payload) = serde_json::to_string(&event) {
-                if let Err(e) = self.client.publish(
-                    event_topic,
-                    qos,
-                    false, // Don't retain events
-                    payload.as_bytes(),
-                ).await {
-                    error!("Failed to publish client disconnection event: {}", e);
+            if MessageKind::ClientDisconnected.enabled(&self.config) {
+                let event_topic = MessageKind::ClientDisconnected.topic(&self.config, &vars);
+                let event = ClientEvent {
+                    client_id: client.id.clone(),
+                    timestamp: Utc::now().to_rfc3339(),
+                };
+                if let Ok(payload) = MessageKind::ClientDisconnected.payload(&self.config, &event, &vars) {
+                    if let Err(e) = self.client.publish(&self.config, TopicClass::Events, event_topic, payload).await {
+                        error!("Failed to publish client disconnection event: {}", e);
+                    }
                 }
             }
         }
@@ -321,48 +673,27 @@ impl MqttHandle {
             client.actual_fps = actual_fps;
             
             // Publish updated client status to individual client topic
-            let topic = format!("{}/clients/{}/status", self.config.base_topic, client_id);
-            if let Ok(payload) = serde_json::to_string(&client) {
-                let qos = match self.config.qos {
-                    0 => QoS::AtMostOnce,
-                    1 => QoS::AtLeastOnce,
-                    _ => QoS::ExactlyOnce,
-                };
-                
-                if let Err(e) = self.client.publish(
-                    topic,
-                    qos,
-                    self.config.retain,
-                    payload.as_bytes(),
-                ).await {
+            if !MessageKind::ClientStatus.enabled(&self.config) {
+                return;
+            }
+            let vars = [("client_id", client_id), ("camera_id", client.camera_id.as_str())];
+            let topic = MessageKind::ClientStatus.topic(&self.config, &vars);
+            if let Ok(payload) = MessageKind::ClientStatus.payload(&self.config, &client, &vars) {
+                if let Err(e) = self.client.publish(&self.config, TopicClass::Clients, topic, payload).await {
                     error!("Failed to publish client stats update: {}", e);
                 }
             }
         }
     }
     
-    #[allow(dead_code)]
     pub async fn publish_custom(&self, topic_suffix: &str, payload: &str) -> Result<()> {
         let topic = format!("{}/{}", self.config.base_topic, topic_suffix);
-        let qos = match self.config.qos {
-            0 => QoS::AtMostOnce,
-            1 => QoS::AtLeastOnce,
-            _ => QoS::ExactlyOnce,
-        };
-        
-        self.client.publish(
-            topic,
-            qos,
-            self.config.retain,
-            payload.as_bytes(),
-        ).await?;
-        
-        Ok(())
+        self.client.publish(&self.config, TopicClass::Status, topic, payload).await
     }
     
     pub async fn publish_picture_arrival(&self, camera_id: &str, arrival_time: u128, time_diff: u128, frame_size: usize) {
         // Check if picture arrival publishing is enabled (default: true for backward compatibility)
-        if !self.config.publish_picture_arrival.unwrap_or(true) {
+        if !MessageKind::PictureArrival.enabled(&self.config) {
             return;
         }
         
@@ -372,20 +703,10 @@ impl MqttHandle {
             s: frame_size,
         };
         
-        if let Ok(payload) = serde_json::to_string(&picture_event) {
-            let topic = format!("{}/cameras/{}/capturing", self.config.base_topic, camera_id);
-            let qos = match self.config.qos {
-                0 => QoS::AtMostOnce,
-                1 => QoS::AtLeastOnce,
-                _ => QoS::ExactlyOnce,
-            };
-            
-            if let Err(e) = self.client.publish(
-                topic,
-                qos,
-                false, // Don't retain picture arrival events
-                payload.as_bytes(),
-            ).await {
+        let vars = [("camera_id", camera_id)];
+        if let Ok(payload) = MessageKind::PictureArrival.payload(&self.config, &picture_event, &vars) {
+            let topic = MessageKind::PictureArrival.topic(&self.config, &vars);
+            if let Err(e) = self.client.publish(&self.config, TopicClass::Events, topic, payload).await {
                 error!("Failed to publish picture arrival for camera {}: {}", camera_id, e);
             }
         } else {
@@ -394,47 +715,51 @@ impl MqttHandle {
     }
     
     pub async fn publish_camera_image(&self, camera_id: &str, jpeg_data: &[u8], custom_topic: Option<&String>) -> Result<()> {
+        if !MessageKind::Image.enabled(&self.config) {
+            return Ok(());
+        }
         let topic = if let Some(custom_topic) = custom_topic {
             custom_topic.clone()
         } else {
-            format!("{}/cameras/{}/jpg", self.config.base_topic, camera_id)
+            MessageKind::Image.topic(&self.config, &[("camera_id", camera_id)])
         };
         
-        let qos = match self.config.qos {
-            0 => QoS::AtMostOnce,
-            1 => QoS::AtLeastOnce,
-            _ => QoS::ExactlyOnce,
-        };
-        
-        self.client.publish(
-            topic,
-            qos,
-            false, // Don't retain image data
-            jpeg_data,
-        ).await?;
-        
-        Ok(())
+        self.client.publish(&self.config, TopicClass::Images, topic, jpeg_data).await
     }
     
+    /// Publish a camera event (trigger, motion, offline/online)
+    pub async fn publish_camera_event<T: Serialize>(&self, camera_id: &str, event: &str, message: &T) -> Result<()> {
+        if !MessageKind::CameraEvent.enabled(&self.config) {
+            return Ok(());
+        }
+        let vars = [("camera_id", camera_id), ("event", event)];
+        let topic = MessageKind::CameraEvent.topic(&self.config, &vars);
+        let payload = MessageKind::CameraEvent.payload(&self.config, message, &vars)?;
+        self.client.publish(&self.config, TopicClass::Events, topic, payload).await
+    }
+
+    /// Publish the snapshot of a camera event and return its topic (None if the message is disabled)
+    pub async fn publish_event_image(&self, camera_id: &str, event: &str, jpeg_data: &[u8]) -> Result<Option<String>> {
+        if !MessageKind::EventImage.enabled(&self.config) {
+            return Ok(None);
+        }
+        let topic = MessageKind::EventImage.topic(&self.config, &[("camera_id", camera_id), ("event", event)]);
+        self.client.publish(&self.config, TopicClass::Images, topic.clone(), jpeg_data).await?;
+        Ok(Some(topic))
+    }
+
     pub async fn publish_throughput_stats(&self, camera_id: &str, stats: &ThroughputStats) -> Result<()> {
-        let topic = format!("{}/cameras/{}/throughput", self.config.base_topic, camera_id);
-        
-        let qos = match self.config.qos {
-            0 => QoS::AtMostOnce,
-            1 => QoS::AtLeastOnce,
-            _ => QoS::ExactlyOnce,
-        };
+        if !MessageKind::Throughput.enabled(&self.config) {
+            return Ok(());
+        }
+        let vars = [("camera_id", camera_id)];
+        let topic = MessageKind::Throughput.topic(&self.config, &vars);
         
-        let payload = serde_json::to_string(stats).map_err(|e| {
+        let payload = MessageKind::Throughput.payload(&self.config, stats, &vars).map_err(|e| {
             StreamError::mqtt(format!("Failed to serialize throughput stats: {}", e))
         })?;
         
-        self.client.publish(
-            topic,
-            qos,
-            self.config.retain,
-            payload,
-        ).await.map_err(|e| {
+        self.client.publish(&self.config, TopicClass::Status, topic, payload).await.map_err(|e| {
             StreamError::mqtt(format!("Failed to publish throughput stats: {}", e))
         })?;
         
@@ -445,4 +770,4 @@ impl MqttHandle {
         let cameras = self.camera_status.read().await;
         cameras.clone()
     }
-}
+}
\ No newline at end of file
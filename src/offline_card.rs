@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use image::{Rgb, RgbImage};
+
+use crate::config::OfflineCardConfig;
+use crate::errors::Result;
+use crate::jpeg;
+use crate::rtsp_client::PipelineControl;
+use crate::watermark::{draw_text, text_height, text_width};
+
+const BACKGROUND: Rgb<u8> = Rgb([32, 32, 32]);
+const HEADLINE: Rgb<u8> = Rgb([230, 60, 60]);
+const TEXT: Rgb<u8> = Rgb([200, 200, 200]);
+
+lazy_static::lazy_static! {
+    static ref PIPELINES: Mutex<HashMap<String, PipelineControl>> = Mutex::new(HashMap::new());
+    // Last rendered card per camera, shared by all clients while the camera stays offline
+    static ref CARDS: Mutex<HashMap<String, RenderedCard>> = Mutex::new(HashMap::new());
+}
+
+struct RenderedCard {
+    since: DateTime<Utc>,
+    width: u32,
+    height: u32,
+    frame: Bytes,
+}
+
+/// Make the pipeline of a camera known, its last frame time decides whether the camera is offline
+pub fn register(camera_id: &str, pipeline: PipelineControl) {
+    PIPELINES.lock().unwrap_or_else(|e| e.into_inner()).insert(camera_id.to_string(), pipeline);
+    CARDS.lock().unwrap_or_else(|e| e.into_inner()).remove(camera_id);
+}
+
+pub fn unregister(camera_id: &str) {
+    PIPELINES.lock().unwrap_or_else(|e| e.into_inner()).remove(camera_id);
+    CARDS.lock().unwrap_or_else(|e| e.into_inner()).remove(camera_id);
+}
+
+/// Time of the camera's last frame if it delivered none for `after_secs`. Cameras in standby
+/// stopped FFmpeg on purpose and are not offline.
+pub fn offline_since(camera_id: &str, after_secs: u64) -> Option<DateTime<Utc>> {
+    let pipelines = PIPELINES.lock().unwrap_or_else(|e| e.into_inner());
+    let pipeline = pipelines.get(camera_id)?;
+    if pipeline.is_standby() || pipeline.secs_since_last_frame() < after_secs.max(1) {
+        return None;
+    }
+    Some(pipeline.last_frame_at())
+}
+
+/// The offline card of a camera that delivered no frames for `after_secs`, None while it is online
+pub fn card(camera_id: &str, config: &OfflineCardConfig) -> Option<Result<Bytes>> {
+    let since = offline_since(camera_id, config.after_secs)?;
+    {
+        let cards = CARDS.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(card) = cards.get(camera_id)
+            .filter(|c| c.since == since && c.width == config.width && c.height == config.height) {
+            return Some(Ok(card.frame.clone()));
+        }
+    }
+
+    let frame = match render(camera_id, since, config) {
+        Ok(frame) => Bytes::from(frame),
+        Err(e) => return Some(Err(e)),
+    };
+    CARDS.lock().unwrap_or_else(|e| e.into_inner()).insert(camera_id.to_string(), RenderedCard {
+        since,
+        width: config.width,
+        height: config.height,
+        frame: frame.clone(),
+    });
+    Some(Ok(frame))
+}
+
+/// JPEG with "CAMERA OFFLINE", the camera id and the time of its last frame centered on a dark background
+fn render(camera_id: &str, since: DateTime<Utc>, config: &OfflineCardConfig) -> Result<Vec<u8>> {
+    let width = config.width.clamp(160, 3840);
+    let height = config.height.clamp(90, 2160);
+    let mut image = RgbImage::from_pixel(width, height, BACKGROUND);
+
+    // Largest scale at which a line fits into 90% of the width
+    let fit = |text: &str, scale: u32| scale.min(width * 9 / 10 / text_width(text, 1).max(1)).max(1);
+    let since = format!("SINCE {}", since.format("%Y-%m-%d %H:%M:%S UTC"));
+    let scale = fit(&since, height / 90);
+    let lines = [
+        ("CAMERA OFFLINE".to_string(), fit("CAMERA OFFLINE", scale * 2), HEADLINE),
+        (camera_id.to_string(), fit(camera_id, scale), TEXT),
+        (since, scale, TEXT),
+    ];
+    let gap = text_height(scale);
+    let total_height: u32 = lines.iter().map(|(_, s, _)| text_height(*s)).sum::<u32>() + gap * (lines.len() as u32 - 1);
+    let mut top = height.saturating_sub(total_height) / 2;
+    for (text, line_scale, color) in &lines {
+        let left = width.saturating_sub(text_width(text, *line_scale)) / 2;
+        draw_text(&mut image, text, left, top, *line_scale, *color);
+        top += text_height(*line_scale) + gap;
+    }
+
+    jpeg::encode(&image, jpeg::settings().quality)
+}
@@ -0,0 +1,300 @@
+use std::process::Stdio;
+use serde::Serialize;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+use tokio::time::{interval, Duration, Instant};
+use tracing::{debug, info, warn};
+
+use crate::config::{CameraConfig, OnvifMetadataConfig};
+use crate::errors::{Result, StreamError};
+use crate::ptz::onvif_events::extract_element_text;
+use crate::AppState;
+
+// Wait before restarting the reader after FFmpeg exited
+const RESTART_DELAY_SECS: u64 = 10;
+// How often a running reader checks whether the camera went into standby
+const STANDBY_CHECK_SECS: u64 = 5;
+// Unterminated metadata beyond this size is discarded
+const MAX_DOCUMENT_BYTES: usize = 1024 * 1024;
+
+/// Object reported by the camera's video analytics
+#[derive(Debug, Clone, Serialize)]
+pub struct MetadataObject {
+    pub object_id: Option<String>,
+    pub class: Option<String>,
+    pub likelihood: Option<f64>,
+    /// Normalized coordinates as sent by the camera, -1.0 to 1.0 with y pointing up
+    pub bounding_box: Option<BoundingBox>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BoundingBox {
+    pub left: f64,
+    pub top: f64,
+    pub right: f64,
+    pub bottom: f64,
+}
+
+/// Read the metadata track of a camera until the task is aborted, restarting FFmpeg whenever it exits
+pub async fn run_reader(state: AppState, camera_id: String, camera_config: CameraConfig, config: OnvifMetadataConfig) {
+    loop {
+        // Keep the camera connection closed while the camera is in standby
+        if !is_standby(&state, &camera_id).await {
+            match read(&state, &camera_id, &camera_config, &config).await {
+                Ok(()) => debug!("[{}] ONVIF metadata reader stopped", camera_id),
+                Err(e) => warn!("[{}] ONVIF metadata reader failed: {}", camera_id, e),
+            }
+        }
+        tokio::time::sleep(Duration::from_secs(RESTART_DELAY_SECS)).await;
+    }
+}
+
+async fn read(state: &AppState, camera_id: &str, camera_config: &CameraConfig, config: &OnvifMetadataConfig) -> Result<()> {
+    let url = config.url.as_deref().unwrap_or(&camera_config.url);
+
+    let mut args: Vec<String> = vec!["-nostats".into(), "-loglevel".into(), "error".into()];
+    args.extend(crate::config::rtsp_input_args(url, &camera_config.transport, camera_config.tls.as_ref()));
+    args.push("-i".into());
+    args.push(url.to_string());
+    // Only the metadata track, copied unchanged to stdout
+    for arg in ["-map", &config.stream, "-c", "copy", "-f", "data", "-"] {
+        args.push(arg.to_string());
+    }
+
+    let ffmpeg_path = if cfg!(windows) && std::path::Path::new("./ffmpeg.exe").exists() {
+        "./ffmpeg.exe"
+    } else {
+        "ffmpeg"
+    };
+    let mut child = tokio::process::Command::new(ffmpeg_path)
+        .args(&args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    info!("[{}] Started ONVIF metadata reader (stream {})", camera_id, config.stream);
+
+    let mut stdout = child.stdout.take().ok_or_else(|| StreamError::ffmpeg("Failed to get FFmpeg stdout"))?;
+    let stderr = child.stderr.take().ok_or_else(|| StreamError::ffmpeg("Failed to get FFmpeg stderr"))?;
+    let stderr_task = tokio::spawn({
+        let camera_id = camera_id.to_string();
+        async move {
+            let mut last_line = None;
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                debug!("[{}] Metadata FFmpeg: {}", camera_id, line);
+                last_line = Some(line);
+            }
+            last_line
+        }
+    });
+
+    let mut standby_check = interval(Duration::from_secs(STANDBY_CHECK_SECS));
+    let mut chunk = vec![0u8; 16 * 1024];
+    let mut pending = String::new();
+    let mut motion: Option<bool> = None;
+    let mut last_objects: Option<Instant> = None;
+    let mut standby = false;
+    loop {
+        tokio::select! {
+            read = stdout.read(&mut chunk) => {
+                let read = read?;
+                if read == 0 {
+                    break;
+                }
+                pending.push_str(&String::from_utf8_lossy(&chunk[..read]));
+                while let Some(document) = take_document(&mut pending) {
+                    handle_document(state, camera_id, config, &document, &mut motion, &mut last_objects).await;
+                }
+                if pending.len() > MAX_DOCUMENT_BYTES {
+                    warn!("[{}] Discarding {} bytes of unterminated ONVIF metadata", camera_id, pending.len());
+                    pending.clear();
+                }
+            }
+            _ = standby_check.tick() => {
+                if is_standby(state, camera_id).await {
+                    info!("[{}] Camera entered standby, stopping ONVIF metadata reader", camera_id);
+                    standby = true;
+                    break;
+                }
+            }
+        }
+    }
+
+    let _ = child.kill().await;
+    let last_error = stderr_task.await.ok().flatten();
+    match last_error {
+        Some(error) if !standby => Err(StreamError::ffmpeg(error)),
+        _ => Ok(()),
+    }
+}
+
+async fn handle_document(
+    state: &AppState,
+    camera_id: &str,
+    config: &OnvifMetadataConfig,
+    document: &str,
+    motion: &mut Option<bool>,
+    last_objects: &mut Option<Instant>,
+) {
+    if let Some(active) = parse_motion(document) {
+        // Cameras repeat the current state with every property event, only changes are stored
+        if *motion != Some(active) {
+            *motion = Some(active);
+            let label = if active { "motion started" } else { "motion ended" };
+            store_event(state, camera_id, config, label, serde_json::json!({ "motion": active })).await;
+            if active {
+                crate::event_notifications::notify(state, camera_id, "motion", label, serde_json::json!({ "source": "onvif_metadata" })).await;
+            }
+        }
+    }
+
+    if !config.objects {
+        return;
+    }
+    let due = last_objects.is_none_or(|last| last.elapsed() >= Duration::from_millis(config.object_interval_ms));
+    if !due {
+        return;
+    }
+    let objects = parse_objects(document);
+    if objects.is_empty() {
+        return;
+    }
+    *last_objects = Some(Instant::now());
+    let mut classes: Vec<&str> = objects.iter().filter_map(|o| o.class.as_deref()).collect();
+    classes.sort_unstable();
+    classes.dedup();
+    let label = if classes.is_empty() { "objects".to_string() } else { classes.join(", ") };
+    let utc_time = start_tag(document, "Frame").and_then(|tag| attribute(tag, "UtcTime"));
+    store_event(state, camera_id, config, &label, serde_json::json!({
+        "utc_time": utc_time,
+        "objects": objects,
+    })).await;
+}
+
+async fn store_event(state: &AppState, camera_id: &str, config: &OnvifMetadataConfig, label: &str, data: serde_json::Value) {
+    debug!("[{}] ONVIF metadata event '{}'", camera_id, label);
+    if let Some(ref recording_manager) = state.recording_manager {
+        recording_manager.record_event(camera_id, "metadata", label, data.clone()).await;
+    }
+    if config.mqtt {
+        if let Some(ref mqtt) = state.mqtt_handle {
+            let payload = serde_json::json!({ "label": label, "data": data });
+            if let Err(e) = mqtt.publish_custom(&format!("cameras/{}/metadata", camera_id), &payload.to_string()).await {
+                debug!("[{}] Failed to publish ONVIF metadata event: {}", camera_id, e);
+            }
+        }
+    }
+}
+
+/// Remove and return the first complete `MetadataStream` document of the buffer
+fn take_document(buffer: &mut String) -> Option<String> {
+    const END: &str = "MetadataStream>";
+    let mut search = 0;
+    while let Some(pos) = buffer[search..].find(END) {
+        let end = search + pos + END.len();
+        let tag_start = buffer[..search + pos].rfind('<')?;
+        if buffer[tag_start..].starts_with("</") {
+            let document: String = buffer.drain(..end).collect();
+            return Some(document);
+        }
+        search = end;
+    }
+    None
+}
+
+/// Motion state of a motion detector event (`IsMotion` of the cell motion detector, `State` of a motion alarm)
+fn parse_motion(document: &str) -> Option<bool> {
+    document.split("NotificationMessage>")
+        .filter(|message| extract_element_text(message, "Topic").is_some_and(|topic| topic.contains("Motion")))
+        .filter_map(|message| simple_item_value(message, "IsMotion").or_else(|| simple_item_value(message, "State")))
+        .map(|value| value.eq_ignore_ascii_case("true") || value == "1")
+        .last()
+}
+
+fn parse_objects(document: &str) -> Vec<MetadataObject> {
+    elements(document, "Object")
+        .into_iter()
+        .map(|(tag, content)| {
+            let class_type = start_tag(content, "Type");
+            let bounding_box = start_tag(content, "BoundingBox").and_then(|tag| Some(BoundingBox {
+                left: attribute(tag, "left")?.parse().ok()?,
+                top: attribute(tag, "top")?.parse().ok()?,
+                right: attribute(tag, "right")?.parse().ok()?,
+                bottom: attribute(tag, "bottom")?.parse().ok()?,
+            }));
+            MetadataObject {
+                object_id: attribute(tag, "ObjectId"),
+                class: extract_element_text(content, "Type").filter(|class| !class.is_empty()),
+                likelihood: class_type.and_then(|tag| attribute(tag, "Likelihood"))
+                    .or_else(|| extract_element_text(content, "Likelihood"))
+                    .and_then(|value| value.parse().ok()),
+                bounding_box,
+            }
+        })
+        .collect()
+}
+
+// Start tag and content of every element with the given local name, ignoring namespace prefixes
+fn elements<'a>(xml: &'a str, local_name: &str) -> Vec<(&'a str, &'a str)> {
+    let mut found = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        let Some(tag_end) = rest.find('>') else { break };
+        let tag = &rest[..tag_end];
+        if tag.starts_with('/') || local(tag) != local_name {
+            continue;
+        }
+        let after = &rest[tag_end + 1..];
+        if tag.ends_with('/') {
+            found.push((tag, ""));
+            continue;
+        }
+        let name = tag.split_whitespace().next().unwrap_or("");
+        let Some(close) = after.find(&format!("</{}>", name)) else { break };
+        found.push((tag, &after[..close]));
+        rest = &after[close..];
+    }
+    found
+}
+
+// Attributes of the first start tag with the given local name
+fn start_tag<'a>(xml: &'a str, local_name: &str) -> Option<&'a str> {
+    let mut rest = xml;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        let tag = &rest[..rest.find('>')?];
+        if !tag.starts_with('/') && local(tag) == local_name {
+            return Some(tag);
+        }
+    }
+    None
+}
+
+fn local(tag: &str) -> &str {
+    let name = tag.split_whitespace().next().unwrap_or("").trim_end_matches('/');
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let marker = format!(" {}=\"", name);
+    let start = tag.find(&marker)? + marker.len();
+    let end = tag[start..].find('"')?;
+    Some(tag[start..start + end].to_string())
+}
+
+// Value attribute of a SimpleItem with the given Name inside a fragment
+fn simple_item_value(fragment: &str, name: &str) -> Option<String> {
+    let marker = format!("Name=\"{}\"", name);
+    let pos = fragment.find(&marker)?;
+    let item_start = fragment[..pos].rfind('<')?;
+    let item_end = pos + fragment[pos..].find('>')?;
+    attribute(&fragment[item_start..item_end], "Value")
+}
+
+async fn is_standby(state: &AppState, camera_id: &str) -> bool {
+    state.camera_streams.read().await
+        .get(camera_id)
+        .is_some_and(|info| info.pipeline_control.is_standby())
+}
@@ -0,0 +1,120 @@
+use tokio::time::Duration;
+use tracing::{debug, info};
+
+use crate::config::OnvifSourceConfig;
+use crate::errors::{Result, StreamError};
+use crate::ptz::onvif_events::extract_element_text;
+use crate::ptz::onvif_ptz::{xml_escape, OnvifPtz};
+
+// Timeout of the whole GetCapabilities/GetProfiles/GetStreamUri exchange
+const RESOLVE_TIMEOUT_SECS: u64 = 15;
+
+const DEVICE_NS: &str = "http://www.onvif.org/ver10/device/wsdl";
+const MEDIA_NS: &str = "http://www.onvif.org/ver10/media/wsdl";
+
+/// Ask the camera's ONVIF media service for the RTSP URI of the configured (or first) profile.
+/// The URI is resolved again after connection failures, so cameras that move their RTSP
+/// port after a firmware update keep working without a config change.
+pub async fn resolve_stream_uri(camera_id: &str, config: &OnvifSourceConfig) -> Result<String> {
+    tokio::time::timeout(Duration::from_secs(RESOLVE_TIMEOUT_SECS), resolve(camera_id, config))
+        .await
+        .map_err(|_| StreamError::rtsp_connection("ONVIF stream URI resolution timed out"))?
+}
+
+async fn resolve(camera_id: &str, config: &OnvifSourceConfig) -> Result<String> {
+    let soap = OnvifPtz::new(config.url.clone(), config.username.clone(), config.password.clone(), String::new());
+
+    let media_url = match media_service_url(&soap, &config.url).await {
+        Ok(url) => url,
+        Err(e) => {
+            // Some cameras answer media requests on the device service itself
+            debug!("[{}] ONVIF GetCapabilities failed, using the device service for media requests: {}", camera_id, e);
+            config.url.clone()
+        }
+    };
+
+    let profile_token = match config.profile_token {
+        Some(ref token) => token.clone(),
+        None => {
+            let body = format!("<trt:GetProfiles xmlns:trt=\"{}\"/>", MEDIA_NS);
+            let resp = soap.call(&media_url, &format!("{}/GetProfiles", MEDIA_NS), &body).await?;
+            profile_tokens(&resp).into_iter().next()
+                .ok_or_else(|| StreamError::rtsp_connection("ONVIF camera reports no media profiles"))?
+        }
+    };
+
+    let body = format!(
+        "<trt:GetStreamUri xmlns:trt=\"{}\">\n\
+            <trt:StreamSetup>\n\
+                <tt:Stream>RTP-Unicast</tt:Stream>\n\
+                <tt:Transport><tt:Protocol>RTSP</tt:Protocol></tt:Transport>\n\
+            </trt:StreamSetup>\n\
+            <trt:ProfileToken>{}</trt:ProfileToken>\n\
+         </trt:GetStreamUri>",
+        MEDIA_NS, xml_escape(&profile_token)
+    );
+    let resp = soap.call(&media_url, &format!("{}/GetStreamUri", MEDIA_NS), &body).await?;
+    let uri = extract_element_text(&resp, "Uri")
+        .map(|uri| xml_unescape(&uri))
+        .filter(|uri| !uri.is_empty())
+        .ok_or_else(|| StreamError::rtsp_connection("ONVIF GetStreamUri response has no Uri"))?;
+    info!("[{}] ONVIF profile '{}' streams from {}", camera_id, profile_token, uri);
+
+    if !config.embed_credentials {
+        return Ok(uri);
+    }
+    with_credentials(&uri, config.username.as_deref(), config.password.as_deref())
+}
+
+/// Media service address from GetCapabilities
+async fn media_service_url(soap: &OnvifPtz, device_url: &str) -> Result<String> {
+    let body = format!(
+        "<tds:GetCapabilities xmlns:tds=\"{}\"><tds:Category>Media</tds:Category></tds:GetCapabilities>",
+        DEVICE_NS
+    );
+    let resp = soap.call(device_url, &format!("{}/GetCapabilities", DEVICE_NS), &body).await?;
+    let media = resp.find(":Media>").or_else(|| resp.find("<Media>"))
+        .map(|pos| &resp[pos..])
+        .ok_or_else(|| StreamError::rtsp_connection("ONVIF GetCapabilities response has no media service"))?;
+    extract_element_text(media, "XAddr")
+        .filter(|url| !url.is_empty())
+        .ok_or_else(|| StreamError::rtsp_connection("ONVIF media capabilities have no XAddr"))
+}
+
+// `token` attributes of the Profiles elements of a GetProfiles response, in camera order
+fn profile_tokens(xml: &str) -> Vec<String> {
+    xml.split('<')
+        .filter(|tag| {
+            let name = tag.split_whitespace().next().unwrap_or("");
+            name.rsplit(':').next() == Some("Profiles")
+        })
+        .filter_map(|tag| {
+            let start = tag.find("token=\"")? + 7;
+            let end = tag[start..].find('"')?;
+            Some(xml_unescape(&tag[start..start + end]))
+        })
+        .collect()
+}
+
+// Add the ONVIF credentials to a URI that does not carry any
+fn with_credentials(uri: &str, username: Option<&str>, password: Option<&str>) -> Result<String> {
+    let Some(username) = username.filter(|u| !u.is_empty()) else {
+        return Ok(uri.to_string());
+    };
+    let mut url = url::Url::parse(uri)?;
+    if !url.username().is_empty() {
+        return Ok(uri.to_string());
+    }
+    url.set_username(username)
+        .and_then(|_| url.set_password(password))
+        .map_err(|_| StreamError::rtsp_connection("ONVIF stream URI cannot carry credentials"))?;
+    Ok(url.to_string())
+}
+
+fn xml_unescape(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
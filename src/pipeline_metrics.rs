@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// Upper bounds of the histogram buckets, `+Inf` is added when rendering
+const BACKLOG_BUCKETS: &[f64] = &[0.0, 1.0, 2.0, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0];
+const INSERT_SECONDS_BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0];
+const MUX_SECONDS_BUCKETS: &[f64] = &[0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+const MUX_QUEUE_BUCKETS: &[f64] = &[0.0, 1.0, 2.0, 3.0, 5.0, 10.0];
+
+lazy_static::lazy_static! {
+    static ref METRICS: Mutex<HashMap<String, Arc<PipelineMetrics>>> = Mutex::new(HashMap::new());
+}
+
+pub struct Histogram {
+    bounds: &'static [f64],
+    data: Mutex<HistogramData>,
+}
+
+#[derive(Default)]
+struct HistogramData {
+    buckets: Vec<u64>, // Not cumulative, one more than bounds for +Inf
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [f64]) -> Self {
+        Self {
+            bounds,
+            data: Mutex::new(HistogramData { buckets: vec![0; bounds.len() + 1], ..Default::default() }),
+        }
+    }
+
+    pub fn observe(&self, value: f64) {
+        let index = self.bounds.iter().position(|bound| value <= *bound).unwrap_or(self.bounds.len());
+        let mut data = self.data.lock().unwrap_or_else(|e| e.into_inner());
+        data.buckets[index] += 1;
+        data.sum += value;
+        data.count += 1;
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let data = self.data.lock().unwrap_or_else(|e| e.into_inner());
+        let mut cumulative = 0;
+        for (index, count) in data.buckets.iter().enumerate() {
+            cumulative += count;
+            let le = self.bounds.get(index).map_or("+Inf".to_string(), |bound| bound.to_string());
+            let _ = writeln!(out, "{}_bucket{{{},le=\"{}\"}} {}", name, labels, le, cumulative);
+        }
+        let _ = writeln!(out, "{}_sum{{{}}} {}", name, labels, data.sum);
+        let _ = writeln!(out, "{}_count{{{}}} {}", name, labels, data.count);
+    }
+}
+
+/// Change of the lagging state of a recording consumer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SlowConsumer {
+    Started,
+    Recovered,
+}
+
+/// A task reading the camera's broadcast channel for recording
+pub struct ConsumerMetrics {
+    /// Frames not yet read from the broadcast channel, sampled on every received frame
+    pub backlog: Histogram,
+    pub lagged_frames: AtomicU64,
+    slow: AtomicBool,
+    lagging_since: Mutex<Option<Instant>>,
+}
+
+impl ConsumerMetrics {
+    fn new() -> Self {
+        Self {
+            backlog: Histogram::new(BACKLOG_BUCKETS),
+            lagged_frames: AtomicU64::new(0),
+            slow: AtomicBool::new(false),
+            lagging_since: Mutex::new(None),
+        }
+    }
+
+    /// Record the backlog and frames lost to overflow since the last call. The consumer is
+    /// reported slow once it has lagged for `warning_after`, and recovered once it caught up.
+    pub fn observe(&self, backlog: usize, skipped: u64, threshold: usize, warning_after: Option<Duration>) -> Option<SlowConsumer> {
+        self.backlog.observe(backlog as f64);
+        self.lagged_frames.fetch_add(skipped, Ordering::Relaxed);
+
+        let mut lagging_since = self.lagging_since.lock().unwrap_or_else(|e| e.into_inner());
+        if skipped > 0 || backlog >= threshold {
+            let since = *lagging_since.get_or_insert_with(Instant::now);
+            let warning_after = warning_after?;
+            if since.elapsed() >= warning_after && !self.slow.swap(true, Ordering::Relaxed) {
+                return Some(SlowConsumer::Started);
+            }
+        } else if backlog == 0 {
+            *lagging_since = None;
+            if self.slow.swap(false, Ordering::Relaxed) {
+                return Some(SlowConsumer::Recovered);
+            }
+        }
+        None
+    }
+
+    pub fn is_slow(&self) -> bool {
+        self.slow.load(Ordering::Relaxed)
+    }
+}
+
+/// Metrics of the recording pipeline of one camera
+pub struct PipelineMetrics {
+    pub frames: ConsumerMetrics,
+    pub mp4: ConsumerMetrics,
+    pub bulk_insert_seconds: Histogram,
+    pub mp4_mux_seconds: Histogram,
+    /// Segments waiting for or being muxed when another segment is queued
+    pub mp4_mux_queue: Histogram,
+    mp4_mux_pending: AtomicUsize,
+}
+
+impl PipelineMetrics {
+    fn new() -> Self {
+        Self {
+            frames: ConsumerMetrics::new(),
+            mp4: ConsumerMetrics::new(),
+            bulk_insert_seconds: Histogram::new(INSERT_SECONDS_BUCKETS),
+            mp4_mux_seconds: Histogram::new(MUX_SECONDS_BUCKETS),
+            mp4_mux_queue: Histogram::new(MUX_QUEUE_BUCKETS),
+            mp4_mux_pending: AtomicUsize::new(0),
+        }
+    }
+
+    fn consumers(&self) -> [(&'static str, &ConsumerMetrics); 2] {
+        [("frames", &self.frames), ("mp4", &self.mp4)]
+    }
+
+    /// A segment was handed to the MP4 muxer
+    pub fn mux_queued(&self) {
+        let pending = self.mp4_mux_pending.fetch_add(1, Ordering::Relaxed);
+        self.mp4_mux_queue.observe(pending as f64);
+    }
+
+    pub fn mux_finished(&self, elapsed: Duration) {
+        self.mp4_mux_pending.fetch_sub(1, Ordering::Relaxed);
+        self.mp4_mux_seconds.observe(elapsed.as_secs_f64());
+    }
+
+    /// Segments waiting for or being muxed
+    pub fn mux_pending(&self) -> usize {
+        self.mp4_mux_pending.load(Ordering::Relaxed)
+    }
+}
+
+pub fn get(camera_id: &str) -> Arc<PipelineMetrics> {
+    METRICS.lock().unwrap_or_else(|e| e.into_inner())
+        .entry(camera_id.to_string())
+        .or_insert_with(|| Arc::new(PipelineMetrics::new()))
+        .clone()
+}
+
+/// Metrics of the given cameras in the Prometheus text format
+pub fn render(camera_ids: &[String]) -> String {
+    let cameras: Vec<(String, Arc<PipelineMetrics>)> = {
+        let metrics = METRICS.lock().unwrap_or_else(|e| e.into_inner());
+        let mut cameras: Vec<_> = camera_ids.iter()
+            .filter_map(|id| metrics.get(id).map(|m| (id.clone(), m.clone())))
+            .collect();
+        cameras.sort_by(|a, b| a.0.cmp(&b.0));
+        cameras
+    };
+
+    let mut out = String::new();
+    header(&mut out, "rtsp_recording_receiver_backlog_frames", "histogram", "Frames a recording consumer is behind the live broadcast channel");
+    for (id, m) in &cameras {
+        for (consumer, metrics) in m.consumers() {
+            metrics.backlog.render(&mut out, "rtsp_recording_receiver_backlog_frames", &consumer_labels(id, consumer));
+        }
+    }
+    header(&mut out, "rtsp_recording_lagged_frames_total", "counter", "Frames a recording consumer missed because the broadcast channel overflowed");
+    for (id, m) in &cameras {
+        for (consumer, metrics) in m.consumers() {
+            let _ = writeln!(out, "rtsp_recording_lagged_frames_total{{{}}} {}", consumer_labels(id, consumer), metrics.lagged_frames.load(Ordering::Relaxed));
+        }
+    }
+    header(&mut out, "rtsp_recording_slow_consumer", "gauge", "1 while a recording consumer persistently lags the live broadcast channel");
+    for (id, m) in &cameras {
+        for (consumer, metrics) in m.consumers() {
+            let _ = writeln!(out, "rtsp_recording_slow_consumer{{{}}} {}", consumer_labels(id, consumer), u8::from(metrics.is_slow()));
+        }
+    }
+    header(&mut out, "rtsp_recording_bulk_insert_seconds", "histogram", "Duration of bulk frame inserts into the recording database");
+    for (id, m) in &cameras {
+        m.bulk_insert_seconds.render(&mut out, "rtsp_recording_bulk_insert_seconds", &camera_label(id));
+    }
+    header(&mut out, "rtsp_recording_mp4_mux_seconds", "histogram", "Duration of muxing and storing one MP4 segment");
+    for (id, m) in &cameras {
+        m.mp4_mux_seconds.render(&mut out, "rtsp_recording_mp4_mux_seconds", &camera_label(id));
+    }
+    header(&mut out, "rtsp_recording_mp4_mux_queue_segments", "histogram", "Segments already pending in the MP4 muxer when another one is queued");
+    for (id, m) in &cameras {
+        m.mp4_mux_queue.render(&mut out, "rtsp_recording_mp4_mux_queue_segments", &camera_label(id));
+    }
+    header(&mut out, "rtsp_recording_mp4_mux_pending_segments", "gauge", "Segments waiting for or being muxed");
+    for (id, m) in &cameras {
+        let _ = writeln!(out, "rtsp_recording_mp4_mux_pending_segments{{{}}} {}", camera_label(id), m.mp4_mux_pending.load(Ordering::Relaxed));
+    }
+    out
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn camera_label(camera_id: &str) -> String {
+    format!("camera=\"{}\"", escape(camera_id))
+}
+
+fn consumer_labels(camera_id: &str, consumer: &str) -> String {
+    format!("{},consumer=\"{}\"", camera_label(camera_id), consumer)
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
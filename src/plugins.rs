@@ -0,0 +1,219 @@
+use std::process::Stdio;
+use std::sync::Arc;
+use bytes::Bytes;
+use serde::Deserialize;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+#[cfg(unix)]
+use tokio::net::UnixListener;
+use tokio::process::Command;
+use tokio::sync::broadcast;
+use tokio::time::{timeout, Duration, Instant};
+use tracing::{debug, error, info, warn};
+
+use crate::config::{PluginConfig, PluginTransport, TriggerSource};
+use crate::errors::{Result, StreamError};
+use crate::triggers::TriggerManager;
+use crate::AppState;
+
+// How long a plugin may take to connect to its frame socket
+const SOCKET_CONNECT_TIMEOUT_SECS: u64 = 30;
+
+/// Stand-in on platforms without UNIX sockets, binding always fails
+#[cfg(unix)]
+type SocketListener = UnixListener;
+// Without UNIX sockets there is no listener, plugins have to use the stdin transport
+#[cfg(not(unix))]
+type SocketListener = std::convert::Infallible;
+
+#[cfg(unix)]
+fn bind_socket(path: &std::path::Path) -> Result<SocketListener> {
+    let _ = std::fs::remove_file(path);
+    Ok(UnixListener::bind(path)?)
+}
+
+#[cfg(not(unix))]
+fn bind_socket(_path: &std::path::Path) -> Result<SocketListener> {
+    Err(StreamError::config("The unix_socket plugin transport is not supported on this platform, use stdin"))
+}
+
+#[cfg(unix)]
+async fn accept_socket(listener: SocketListener) -> std::io::Result<Box<dyn AsyncWrite + Unpin + Send>> {
+    let (stream, _) = listener.accept().await?;
+    Ok(Box::new(stream))
+}
+
+#[cfg(not(unix))]
+async fn accept_socket(listener: SocketListener) -> std::io::Result<Box<dyn AsyncWrite + Unpin + Send>> {
+    match listener {}
+}
+
+/// Event line written by a plugin to stdout
+#[derive(Debug, Deserialize)]
+struct PluginEvent {
+    event: String,
+    #[serde(default)]
+    label: Option<String>,
+    #[serde(default)]
+    data: serde_json::Value,
+}
+
+/// Run an analytics plugin of a camera, restarting it whenever it exits
+pub async fn run_plugin(manager: Arc<TriggerManager>, state: AppState, camera_id: String, plugin: PluginConfig) {
+    loop {
+        match run_once(&manager, &state, &camera_id, &plugin).await {
+            Ok(()) => info!("Plugin '{}' of camera '{}' stopped", plugin.id, camera_id),
+            Err(e) => error!("Plugin '{}' of camera '{}' failed: {}", plugin.id, camera_id, e),
+        }
+        tokio::time::sleep(Duration::from_secs(plugin.restart_delay_secs.max(1))).await;
+    }
+}
+
+async fn run_once(manager: &Arc<TriggerManager>, state: &AppState, camera_id: &str, plugin: &PluginConfig) -> Result<()> {
+    let frame_sender = state.camera_streams.read().await
+        .get(camera_id)
+        .map(|info| info.frame_sender.clone())
+        .ok_or_else(|| StreamError::not_found(format!("Camera '{}' is not running", camera_id)))?;
+
+    let socket_path = std::env::temp_dir().join(format!("rtsp-plugin-{}-{}.sock", camera_id, plugin.id));
+    let listener = match plugin.transport {
+        PluginTransport::Stdin => None,
+        PluginTransport::UnixSocket => Some(bind_socket(&socket_path)?),
+    };
+
+    let mut command = Command::new(&plugin.command);
+    command.args(&plugin.args)
+        .envs(&plugin.env)
+        .env("RTSP_CAMERA_ID", camera_id)
+        .env("RTSP_PLUGIN_ID", &plugin.id)
+        .stdin(if listener.is_some() { Stdio::null() } else { Stdio::piped() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    if listener.is_some() {
+        command.env("RTSP_PLUGIN_SOCKET", &socket_path);
+    }
+
+    let mut child = command.spawn()
+        .map_err(|e| StreamError::internal(format!("Failed to start '{}': {}", plugin.command, e)))?;
+    info!("Started plugin '{}' for camera '{}' (pid {:?}, {} fps via {:?})",
+          plugin.id, camera_id, child.id(), plugin.fps, plugin.transport);
+
+    let stdin = child.stdin.take();
+    let stdout = child.stdout.take().ok_or_else(|| StreamError::internal("Plugin stdout not captured"))?;
+    let stderr = child.stderr.take().ok_or_else(|| StreamError::internal("Plugin stderr not captured"))?;
+    let stderr_task = tokio::spawn(log_stderr(stderr, camera_id.to_string(), plugin.id.clone()));
+
+    let frames = async {
+        let writer: Box<dyn AsyncWrite + Unpin + Send> = match (listener, stdin) {
+            (Some(listener), _) => {
+                timeout(Duration::from_secs(SOCKET_CONNECT_TIMEOUT_SECS), accept_socket(listener)).await
+                    .map_err(|_| StreamError::internal(format!("Plugin did not connect to {:?}", socket_path)))??
+            }
+            (None, Some(stdin)) => Box::new(stdin),
+            (None, None) => return Err(StreamError::internal("Plugin stdin not captured")),
+        };
+        send_frames(writer, frame_sender.subscribe(), plugin.fps).await
+    };
+
+    let result = tokio::select! {
+        result = frames => result,
+        _ = read_events(manager, state, camera_id, &plugin.id, stdout) => Ok(()),
+        status = child.wait() => {
+            let status = status?;
+            if status.success() { Ok(()) } else { Err(StreamError::internal(format!("Plugin exited with {}", status))) }
+        }
+    };
+
+    let _ = child.kill().await;
+    stderr_task.abort();
+    if plugin.transport == PluginTransport::UnixSocket {
+        let _ = std::fs::remove_file(&socket_path);
+    }
+    result
+}
+
+/// Write frames as 4-byte big-endian length followed by the JPEG data, at most `fps` per second.
+/// Returns when the camera's frame channel closes (camera stopped or restarted).
+async fn send_frames(mut writer: Box<dyn AsyncWrite + Unpin + Send>, mut frames: broadcast::Receiver<Bytes>, fps: f64) -> Result<()> {
+    let min_interval = (fps.is_finite() && fps > 0.0).then(|| Duration::from_secs_f64(1.0 / fps.clamp(0.01, 1000.0)));
+    let mut last_sent: Option<Instant> = None;
+
+    loop {
+        let frame = match frames.recv().await {
+            Ok(frame) => frame,
+            // A slow plugin only gets the frames it can keep up with
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => return Ok(()),
+        };
+        if let (Some(interval), Some(sent)) = (min_interval, last_sent) {
+            if sent.elapsed() < interval {
+                continue;
+            }
+        }
+        last_sent = Some(Instant::now());
+
+        writer.write_all(&(frame.len() as u32).to_be_bytes()).await?;
+        writer.write_all(&frame).await?;
+        writer.flush().await?;
+    }
+}
+
+async fn read_events(manager: &Arc<TriggerManager>, state: &AppState, camera_id: &str, plugin_id: &str, stdout: impl AsyncRead + Unpin) {
+    let mut lines = BufReader::new(stdout).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<PluginEvent>(line) {
+            Ok(event) => handle_event(manager, state, camera_id, plugin_id, event).await,
+            Err(e) => warn!("Ignoring invalid event line from plugin '{}' of camera '{}': {}", plugin_id, camera_id, e),
+        }
+    }
+}
+
+async fn handle_event(manager: &Arc<TriggerManager>, state: &AppState, camera_id: &str, plugin_id: &str, event: PluginEvent) {
+    debug!("Plugin '{}' of camera '{}' reported '{}'", plugin_id, camera_id, event.event);
+    let payload = serde_json::json!({
+        "plugin": plugin_id,
+        "event": event.event,
+        "data": event.data,
+    });
+
+    if let Some(ref recording_manager) = state.recording_manager {
+        let label = event.label.as_deref().unwrap_or(&event.event);
+        recording_manager.record_event(camera_id, "plugin", label, payload.clone()).await;
+    }
+
+    if let Some(ref mqtt) = state.mqtt_handle {
+        let topic = format!("cameras/{}/plugins/{}", camera_id, plugin_id);
+        if let Err(e) = mqtt.publish_custom(&topic, &payload.to_string()).await {
+            warn!("Failed to publish plugin event to MQTT: {}", e);
+        }
+    }
+
+    let triggers: Vec<String> = {
+        let camera_configs = state.camera_configs.read().await;
+        camera_configs.get(camera_id)
+            .map(|cfg| cfg.triggers.iter()
+                .filter(|t| match &t.source {
+                    TriggerSource::Plugin { plugin, event: expected } => {
+                        plugin == plugin_id && expected.as_deref().is_none_or(|e| e == event.event)
+                    }
+                    _ => false,
+                })
+                .map(|t| t.id.clone())
+                .collect())
+            .unwrap_or_default()
+    };
+    for trigger_id in triggers {
+        manager.fire_and_log(state, camera_id, &trigger_id, "plugin").await;
+    }
+}
+
+async fn log_stderr(stderr: impl AsyncRead + Unpin, camera_id: String, plugin_id: String) {
+    let mut lines = BufReader::new(stderr).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        info!("[plugin {}/{}] {}", camera_id, plugin_id, line);
+    }
+}
@@ -1,8 +1,10 @@
 use std::collections::VecDeque;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use tokio::sync::RwLock;
 use chrono::{DateTime, Utc, Duration};
 use bytes::Bytes;
+use serde::Serialize;
 use tracing::debug;
 
 #[derive(Debug, Clone)]
@@ -14,28 +16,66 @@ pub struct BufferedFrame {
 #[derive(Clone)]
 pub struct PreRecordingBuffer {
     buffer: Arc<RwLock<VecDeque<BufferedFrame>>>,
-    buffer_duration_minutes: u64,
+    // Duration and size limit can be changed at runtime, the buffer is trimmed in place
+    buffer_duration_minutes: Arc<AtomicU64>,
+    max_size_bytes: Arc<AtomicU64>, // 0 = no limit
+    size_bytes: Arc<AtomicUsize>, // Frame data currently buffered
     cleanup_interval_seconds: u64,
 }
 
 impl PreRecordingBuffer {
-    pub fn new(buffer_duration_minutes: u64, cleanup_interval_seconds: u64) -> Self {
+    pub fn new(buffer_duration_minutes: u64, max_size_bytes: u64, cleanup_interval_seconds: u64) -> Self {
         Self {
             buffer: Arc::new(RwLock::new(VecDeque::new())),
-            buffer_duration_minutes,
+            buffer_duration_minutes: Arc::new(AtomicU64::new(buffer_duration_minutes)),
+            max_size_bytes: Arc::new(AtomicU64::new(max_size_bytes)),
+            size_bytes: Arc::new(AtomicUsize::new(0)),
             cleanup_interval_seconds,
         }
     }
 
-    /// Add a frame to the pre-recording buffer
-    pub async fn add_frame(&self, frame_data: Bytes) {
+    /// Add a frame to the pre-recording buffer, dropping the oldest frames beyond the size limit
+    pub async fn add_frame(&self, frame_data: Bytes, timestamp: DateTime<Utc>) {
         let frame = BufferedFrame {
-            timestamp: Utc::now(),
+            timestamp,
             data: frame_data,
         };
 
         let mut buffer = self.buffer.write().await;
+        self.size_bytes.fetch_add(frame.data.len(), Ordering::Relaxed);
         buffer.push_back(frame);
+        self.trim_to_size(&mut buffer);
+    }
+
+    pub fn buffer_duration_minutes(&self) -> u64 {
+        self.buffer_duration_minutes.load(Ordering::Relaxed)
+    }
+
+    pub fn max_size_bytes(&self) -> u64 {
+        self.max_size_bytes.load(Ordering::Relaxed)
+    }
+
+    /// Change duration and size limit without recreating the buffer. Shrinking drops the
+    /// oldest frames right away; growing keeps more frames from now on.
+    pub async fn resize(&self, buffer_duration_minutes: u64, max_size_bytes: u64) {
+        self.buffer_duration_minutes.store(buffer_duration_minutes, Ordering::Relaxed);
+        self.max_size_bytes.store(max_size_bytes, Ordering::Relaxed);
+        self.cleanup_old_frames().await;
+        let mut buffer = self.buffer.write().await;
+        self.trim_to_size(&mut buffer);
+    }
+
+    fn trim_to_size(&self, buffer: &mut VecDeque<BufferedFrame>) {
+        let max_size_bytes = self.max_size_bytes() as usize;
+        if max_size_bytes == 0 {
+            return;
+        }
+        // The newest frame is kept even if it alone exceeds the limit
+        while self.size_bytes.load(Ordering::Relaxed) > max_size_bytes && buffer.len() > 1 {
+            if let Some(frame) = buffer.pop_front() {
+                self.size_bytes.fetch_sub(frame.data.len(), Ordering::Relaxed);
+            }
+        }
     }
 
     /// Get all buffered frames and return them in chronological order
@@ -52,9 +92,18 @@ impl PreRecordingBuffer {
         buffer.front().map(|frame| frame.timestamp)
     }
 
+    /// First buffered frame at `timestamp` or later (after it if `exclusive`), e.g. for DVR playback
+    pub async fn frame_at_or_after(&self, timestamp: DateTime<Utc>, exclusive: bool) -> Option<BufferedFrame> {
+        let buffer = self.buffer.read().await;
+        let index = buffer.partition_point(|frame| {
+            if exclusive { frame.timestamp <= timestamp } else { frame.timestamp < timestamp }
+        });
+        buffer.get(index).cloned()
+    }
+
     /// Clean up old frames that are older than the buffer duration
     pub async fn cleanup_old_frames(&self) {
-        let cutoff_time = Utc::now() - Duration::minutes(self.buffer_duration_minutes as i64);
+        let cutoff_time = Utc::now() - Duration::minutes(self.buffer_duration_minutes() as i64);
         let mut buffer = self.buffer.write().await;
         
         let _initial_count = buffer.len();
@@ -62,7 +111,9 @@ impl PreRecordingBuffer {
         // Remove frames older than the cutoff time
         while let Some(frame) = buffer.front() {
             if frame.timestamp < cutoff_time {
-                buffer.pop_front();
+                if let Some(frame) = buffer.pop_front() {
+                    self.size_bytes.fetch_sub(frame.data.len(), Ordering::Relaxed);
+                }
             } else {
                 break;
             }
@@ -99,21 +150,25 @@ impl PreRecordingBuffer {
         let oldest_timestamp = buffer.front().map(|f| f.timestamp);
         let newest_timestamp = buffer.back().map(|f| f.timestamp);
         
-        let total_size_bytes = buffer.iter().map(|f| f.data.len()).sum::<usize>();
+        let total_size_bytes = self.size_bytes.load(Ordering::Relaxed);
         
         BufferStats {
             frame_count,
             oldest_timestamp,
             newest_timestamp,
             total_size_bytes,
+            buffer_duration_minutes: self.buffer_duration_minutes(),
+            max_size_bytes: self.max_size_bytes(),
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct BufferStats {
     pub frame_count: usize,
     pub oldest_timestamp: Option<DateTime<Utc>>,
     pub newest_timestamp: Option<DateTime<Utc>>,
     pub total_size_bytes: usize,
+    pub buffer_duration_minutes: u64,
+    pub max_size_bytes: u64,
 }
\ No newline at end of file
@@ -25,18 +25,77 @@ pub struct PtzPresetRequest {
     pub token: Option<String>,
 }
 
+/// Axes and move modes of a PTZ device, everything is assumed to be supported if the device does not report them
+#[derive(Debug, Clone, Serialize)]
+pub struct PtzCapabilities {
+    pub pan: bool,
+    pub tilt: bool,
+    pub zoom: bool,
+    pub continuous_move: bool,
+    pub relative_move: bool,
+    pub absolute_move: bool,
+    pub presets: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_presets: Option<u32>,
+    pub home: bool,
+    /// False if the device did not describe its PTZ node and the values above are assumptions
+    pub detected: bool,
+}
+
+impl PtzCapabilities {
+    fn assumed() -> Self {
+        Self {
+            pan: true,
+            tilt: true,
+            zoom: true,
+            continuous_move: true,
+            relative_move: true,
+            absolute_move: true,
+            presets: true,
+            max_presets: None,
+            home: true,
+            detected: false,
+        }
+    }
+
+    /// The velocity or translation without the axes the device lacks
+    pub fn restrict(&self, vector: PtzVelocity) -> PtzVelocity {
+        PtzVelocity {
+            pan: if self.pan { vector.pan } else { 0.0 },
+            tilt: if self.tilt { vector.tilt } else { 0.0 },
+            zoom: if self.zoom { vector.zoom } else { 0.0 },
+        }
+    }
+}
+
 #[async_trait]
 pub trait PtzController: Send + Sync {
     async fn continuous_move(&self, velocity: PtzVelocity, timeout_secs: Option<u64>) -> Result<()>;
+    /// Move by `translation` in the generic space (-1.0..1.0 of the full range per axis)
+    async fn relative_move(&self, translation: PtzVelocity, speed: Option<PtzVelocity>) -> Result<()>;
     async fn stop(&self) -> Result<()>;
     async fn goto_preset(&self, preset_token: &str, speed: Option<PtzVelocity>) -> Result<()>;
     async fn set_preset(&self, req: PtzPresetRequest) -> Result<String>; // returns preset token
+    async fn capabilities(&self) -> Result<PtzCapabilities>;
 }
 
 pub mod onvif_ptz {
     use super::*;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use std::time::{Duration, Instant};
     use crate::errors::StreamError;
-    use tracing::{debug, trace};
+    use super::onvif_events::extract_element_text;
+    use tracing::{debug, info, trace};
+
+    lazy_static::lazy_static! {
+        // Endpoint and profile -> capabilities reported by the device
+        static ref CAPABILITIES: Mutex<HashMap<String, (Instant, PtzCapabilities)>> = Mutex::new(HashMap::new());
+    }
+
+    // How long capabilities are cached, devices that did not report them are asked again sooner
+    const CAPABILITIES_TTL: Duration = Duration::from_secs(3600);
+    const ASSUMED_CAPABILITIES_TTL: Duration = Duration::from_secs(60);
 
     #[derive(Clone)]
     pub struct OnvifPtz {
@@ -115,9 +174,19 @@ pub mod onvif_ptz {
         }
 
         async fn post(&self, action: &str, body: String) -> Result<String> {
+            self.post_to(&self.endpoint, action, body).await
+        }
+
+        /// Send a SOAP request with this client's credentials to an arbitrary ONVIF service URL
+        pub(crate) async fn call(&self, url: &str, action: &str, body: &str) -> Result<String> {
+            let env = self.soap_envelope_with_wsse(body);
+            self.post_to(url, action, env).await
+        }
+
+        async fn post_to(&self, url: &str, action: &str, body: String) -> Result<String> {
             // Avoid logging credentials; include endpoint and action only
-            debug!(target: "ptz_onvif", action = action, endpoint = %self.endpoint, "Sending ONVIF request");
-            let mut req = self.client.post(&self.endpoint)
+            debug!(target: "ptz_onvif", action = action, endpoint = %url, "Sending ONVIF request");
+            let mut req = self.client.post(url)
                 .header("Content-Type", "application/soap+xml; charset=utf-8")
                 .header("SOAPAction", action)
                 .body(body);
@@ -143,17 +212,15 @@ pub mod onvif_ptz {
     impl PtzController for OnvifPtz {
         async fn continuous_move(&self, velocity: PtzVelocity, timeout_secs: Option<u64>) -> Result<()> {
             debug!(target: "ptz_onvif", endpoint = %self.endpoint, profile = %self.profile_token, pan = velocity.pan, tilt = velocity.tilt, zoom = velocity.zoom, timeout = ?timeout_secs, "ONVIF ContinuousMove");
+            let capabilities = self.capabilities().await?;
             let body = format!(
                 "<tptz:ContinuousMove>\n\
                     <tptz:ProfileToken>{}</tptz:ProfileToken>\n\
-                    <tptz:Velocity>\n\
-                        <tt:PanTilt x=\"{}\" y=\"{}\"/>\n\
-                        <tt:Zoom x=\"{}\"/>\n\
-                    </tptz:Velocity>\n\
+                    <tptz:Velocity>{}</tptz:Velocity>\n\
                     {}\n\
                  </tptz:ContinuousMove>",
                 self.profile_token,
-                velocity.pan, velocity.tilt, velocity.zoom,
+                axes_xml(&capabilities, velocity),
                 timeout_secs.map(|t| format!("<tptz:Timeout>PT{}S</tptz:Timeout>", t)).unwrap_or_default()
             );
             let env = self.soap_envelope_with_wsse(&body);
@@ -163,19 +230,38 @@ pub mod onvif_ptz {
 
         async fn stop(&self) -> Result<()> {
             debug!(target: "ptz_onvif", endpoint = %self.endpoint, profile = %self.profile_token, "ONVIF Stop");
+            let capabilities = self.capabilities().await?;
             let body = format!(
                 "<tptz:Stop>\n\
                     <tptz:ProfileToken>{}</tptz:ProfileToken>\n\
-                    <tptz:PanTilt>true</tptz:PanTilt>\n\
-                    <tptz:Zoom>true</tptz:Zoom>\n\
+                    <tptz:PanTilt>{}</tptz:PanTilt>\n\
+                    <tptz:Zoom>{}</tptz:Zoom>\n\
                  </tptz:Stop>",
-                self.profile_token
+                self.profile_token, capabilities.pan || capabilities.tilt, capabilities.zoom
             );
             let env = self.soap_envelope_with_wsse(&body);
             let _ = self.post("http://www.onvif.org/ver20/ptz/wsdl/Stop", env).await?;
             Ok(())
         }
 
+        async fn relative_move(&self, translation: PtzVelocity, speed: Option<PtzVelocity>) -> Result<()> {
+            debug!(target: "ptz_onvif", endpoint = %self.endpoint, profile = %self.profile_token, pan = translation.pan, tilt = translation.tilt, zoom = translation.zoom, "ONVIF RelativeMove");
+            let capabilities = self.capabilities().await?;
+            let body = format!(
+                "<tptz:RelativeMove>\n\
+                    <tptz:ProfileToken>{}</tptz:ProfileToken>\n\
+                    <tptz:Translation>{}</tptz:Translation>\n\
+                    {}\n\
+                 </tptz:RelativeMove>",
+                self.profile_token,
+                axes_xml(&capabilities, translation),
+                speed.map(|s| format!("<tptz:Speed>{}</tptz:Speed>", axes_xml(&capabilities, s))).unwrap_or_default()
+            );
+            let env = self.soap_envelope_with_wsse(&body);
+            let _ = self.post("http://www.onvif.org/ver20/ptz/wsdl/RelativeMove", env).await?;
+            Ok(())
+        }
+
         async fn goto_preset(&self, preset_token: &str, _speed: Option<PtzVelocity>) -> Result<()> {
             debug!(target: "ptz_onvif", endpoint = %self.endpoint, profile = %self.profile_token, preset = preset_token, "ONVIF GotoPreset");
             let body = format!(
@@ -211,9 +297,104 @@ pub mod onvif_ptz {
             }
             Ok(String::new())
         }
+
+        async fn capabilities(&self) -> Result<PtzCapabilities> {
+            let key = format!("{}#{}", self.endpoint, self.profile_token);
+            if let Some((at, capabilities)) = CAPABILITIES.lock().unwrap_or_else(|e| e.into_inner()).get(&key) {
+                let ttl = if capabilities.detected { CAPABILITIES_TTL } else { ASSUMED_CAPABILITIES_TTL };
+                if at.elapsed() < ttl {
+                    return Ok(capabilities.clone());
+                }
+            }
+
+            let env = self.soap_envelope_with_wsse("<tptz:GetNodes/>");
+            let capabilities = match self.post("http://www.onvif.org/ver20/ptz/wsdl/GetNodes", env).await {
+                Ok(resp) => match element_fragment(&resp, "PTZNode") {
+                    Some(node) => parse_node_capabilities(node),
+                    None => {
+                        debug!(target: "ptz_onvif", endpoint = %self.endpoint, "GetNodes returned no PTZ node, assuming all capabilities");
+                        PtzCapabilities::assumed()
+                    }
+                },
+                // Devices without GetNodes are still controlled, with every axis sent as before
+                Err(e) => {
+                    debug!(target: "ptz_onvif", endpoint = %self.endpoint, error = %e, "GetNodes failed, assuming all capabilities");
+                    PtzCapabilities::assumed()
+                }
+            };
+            if capabilities.detected && !(capabilities.pan && capabilities.tilt) {
+                info!("PTZ device {} supports pan: {}, tilt: {}, zoom: {}", self.endpoint, capabilities.pan, capabilities.tilt, capabilities.zoom);
+            }
+            CAPABILITIES.lock().unwrap_or_else(|e| e.into_inner()).insert(key, (Instant::now(), capabilities.clone()));
+            Ok(capabilities)
+        }
     }
 
-    fn xml_escape(s: &str) -> String {
+    // PanTilt and Zoom elements of a velocity, translation or speed, leaving out the axes the device lacks
+    fn axes_xml(capabilities: &PtzCapabilities, vector: PtzVelocity) -> String {
+        let vector = capabilities.restrict(vector);
+        let mut xml = String::new();
+        if capabilities.pan || capabilities.tilt {
+            xml.push_str(&format!("<tt:PanTilt x=\"{}\" y=\"{}\"/>", vector.pan, vector.tilt));
+        }
+        if capabilities.zoom {
+            xml.push_str(&format!("<tt:Zoom x=\"{}\"/>", vector.zoom));
+        }
+        xml
+    }
+
+    // Content of the first element with the given local name, ignoring namespace prefixes
+    fn element_fragment<'a>(xml: &'a str, local_name: &str) -> Option<&'a str> {
+        let mut offset = 0;
+        while let Some(start) = xml[offset..].find('<') {
+            let tag_start = offset + start + 1;
+            let tag_end = tag_start + xml[tag_start..].find('>')?;
+            let tag = &xml[tag_start..tag_end];
+            let name = tag.split_whitespace().next().unwrap_or("");
+            if name.rsplit(':').next() == Some(local_name) && !tag.starts_with('/') {
+                if tag.ends_with('/') {
+                    return Some("");
+                }
+                let content = &xml[tag_end + 1..];
+                let end = content.find(&format!("</{}>", name))?;
+                return Some(&content[..end]);
+            }
+            offset = tag_end;
+        }
+        None
+    }
+
+    // True if the XRange or YRange of a space covers more than a single value
+    fn range_spans(space: &str, range: &str) -> bool {
+        let Some(range) = element_fragment(space, range) else { return false };
+        let bound = |name| extract_element_text(range, name).and_then(|v| v.parse::<f32>().ok());
+        matches!((bound("Min"), bound("Max")), (Some(min), Some(max)) if max > min)
+    }
+
+    fn parse_node_capabilities(node: &str) -> PtzCapabilities {
+        let spaces = element_fragment(node, "SupportedPTZSpaces").unwrap_or("");
+        let space = |name: &str| element_fragment(spaces, name);
+        let pan_tilt_spaces: Vec<&str> = ["AbsolutePanTiltPositionSpace", "RelativePanTiltTranslationSpace", "ContinuousPanTiltVelocitySpace"]
+            .into_iter().filter_map(space).collect();
+        let zoom_spaces: Vec<&str> = ["AbsoluteZoomPositionSpace", "RelativeZoomTranslationSpace", "ContinuousZoomVelocitySpace"]
+            .into_iter().filter_map(space).collect();
+        let max_presets = extract_element_text(node, "MaximumNumberOfPresets").and_then(|v| v.parse::<u32>().ok());
+
+        PtzCapabilities {
+            pan: pan_tilt_spaces.iter().any(|s| range_spans(s, "XRange")),
+            tilt: pan_tilt_spaces.iter().any(|s| range_spans(s, "YRange")),
+            zoom: zoom_spaces.iter().any(|s| range_spans(s, "XRange")),
+            continuous_move: space("ContinuousPanTiltVelocitySpace").is_some() || space("ContinuousZoomVelocitySpace").is_some(),
+            relative_move: space("RelativePanTiltTranslationSpace").is_some() || space("RelativeZoomTranslationSpace").is_some(),
+            absolute_move: space("AbsolutePanTiltPositionSpace").is_some() || space("AbsoluteZoomPositionSpace").is_some(),
+            presets: max_presets.is_none_or(|n| n > 0),
+            max_presets,
+            home: extract_element_text(node, "HomeSupported").is_some_and(|v| v == "true" || v == "1"),
+            detected: true,
+        }
+    }
+
+    pub(crate) fn xml_escape(s: &str) -> String {
         s.replace('&', "&amp;")
             .replace('<', "&lt;")
             .replace('>', "&gt;")
@@ -221,3 +402,106 @@ pub mod onvif_ptz {
             .replace('\'', "&apos;")
     }
 }
+
+pub mod onvif_events {
+    use super::onvif_ptz::OnvifPtz;
+    use crate::errors::{Result, StreamError};
+    use tracing::debug;
+
+    const EVENTS_NS: &str = "http://www.onvif.org/ver10/events/wsdl";
+
+    /// Digital input state change reported by the camera's event service
+    #[derive(Debug, Clone)]
+    pub struct DigitalInputEvent {
+        pub input_token: Option<String>,
+        pub active: bool,
+    }
+
+    /// Minimal ONVIF PullPoint subscription for digital input events
+    pub struct OnvifEventSubscription {
+        soap: OnvifPtz,
+        subscription_url: String,
+    }
+
+    impl OnvifEventSubscription {
+        pub async fn create(event_service_url: String, username: Option<String>, password: Option<String>) -> Result<Self> {
+            let soap = OnvifPtz::new(event_service_url.clone(), username, password, String::new());
+            let body = format!(
+                "<tev:CreatePullPointSubscription xmlns:tev=\"{}\">\n\
+                    <tev:InitialTerminationTime>PT120S</tev:InitialTerminationTime>\n\
+                 </tev:CreatePullPointSubscription>",
+                EVENTS_NS
+            );
+            let resp = soap.call(&event_service_url, "http://www.onvif.org/ver10/events/wsdl/EventPortType/CreatePullPointSubscriptionRequest", &body).await?;
+            let subscription_url = extract_element_text(&resp, "Address")
+                .ok_or_else(|| StreamError::server("ONVIF CreatePullPointSubscription response has no subscription address"))?;
+            debug!(target: "ptz_onvif", subscription = %subscription_url, "ONVIF pull point subscription created");
+            Ok(Self { soap, subscription_url })
+        }
+
+        /// Wait up to `timeout_secs` for new messages and return digital input changes
+        pub async fn pull(&self, timeout_secs: u64) -> Result<Vec<DigitalInputEvent>> {
+            let body = format!(
+                "<tev:PullMessages xmlns:tev=\"{}\">\n\
+                    <tev:Timeout>PT{}S</tev:Timeout>\n\
+                    <tev:MessageLimit>32</tev:MessageLimit>\n\
+                 </tev:PullMessages>",
+                EVENTS_NS, timeout_secs.max(1)
+            );
+            let resp = self.soap.call(&self.subscription_url, "http://www.onvif.org/ver10/events/wsdl/PullPointSubscription/PullMessagesRequest", &body).await?;
+            Ok(parse_digital_input_events(&resp))
+        }
+
+        /// Extend the subscription so it doesn't expire between pulls
+        pub async fn renew(&self) -> Result<()> {
+            let body = "<wsnt:Renew xmlns:wsnt=\"http://docs.oasis-open.org/wsn/b-2\">\n\
+                    <wsnt:TerminationTime>PT120S</wsnt:TerminationTime>\n\
+                 </wsnt:Renew>";
+            self.soap.call(&self.subscription_url, "http://docs.oasis-open.org/wsn/bw-2/SubscriptionManager/RenewRequest", body).await?;
+            Ok(())
+        }
+    }
+
+    // Text of the first element with the given local name, ignoring namespace prefixes
+    pub(crate) fn extract_element_text(xml: &str, local_name: &str) -> Option<String> {
+        let mut rest = xml;
+        while let Some(start) = rest.find('<') {
+            rest = &rest[start + 1..];
+            let tag_end = rest.find('>')?;
+            let tag = &rest[..tag_end];
+            let name = tag.split_whitespace().next().unwrap_or("");
+            let local = name.rsplit(':').next().unwrap_or(name);
+            if local == local_name && !tag.starts_with('/') && !tag.ends_with('/') {
+                let content = &rest[tag_end + 1..];
+                let end = content.find('<')?;
+                return Some(content[..end].trim().to_string());
+            }
+        }
+        None
+    }
+
+    // Value attribute of a SimpleItem with the given Name inside a fragment
+    fn simple_item_value(fragment: &str, name: &str) -> Option<String> {
+        let marker = format!("Name=\"{}\"", name);
+        let pos = fragment.find(&marker)?;
+        let item_start = fragment[..pos].rfind('<')?;
+        let item_end = pos + fragment[pos..].find('>')?;
+        let item = &fragment[item_start..item_end];
+        let value_pos = item.find("Value=\"")? + 7;
+        let value_end = item[value_pos..].find('"')?;
+        Some(item[value_pos..value_pos + value_end].to_string())
+    }
+
+    fn parse_digital_input_events(xml: &str) -> Vec<DigitalInputEvent> {
+        xml.split("NotificationMessage>")
+            .filter(|m| m.contains("DigitalInput"))
+            .filter_map(|m| {
+                let state = simple_item_value(m, "LogicalState")?;
+                Some(DigitalInputEvent {
+                    input_token: simple_item_value(m, "InputToken"),
+                    active: state.eq_ignore_ascii_case("true") || state == "1",
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::sync::{watch, Notify};
+use tokio::time::{timeout_at, Duration, Instant};
+use tracing::{debug, info};
+
+use crate::config::{CameraConfig, PtzConfig, PtzConflictPolicy};
+
+lazy_static::lazy_static! {
+    static ref ARBITERS: Mutex<HashMap<String, Arc<Arbiter>>> = Mutex::new(HashMap::new());
+}
+
+const CAMERA_TOKEN_PRIORITY: u8 = 50;
+const SCOPED_TOKEN_PRIORITY: u8 = 10;
+const ANONYMOUS_PRIORITY: u8 = 0;
+
+/// Sender of PTZ commands, clients with the same id share control
+#[derive(Debug, Clone)]
+pub struct PtzClient {
+    pub id: String,
+    pub label: String,
+    pub priority: u8,
+}
+
+impl PtzClient {
+    /// Client identified by the token it authenticated with, anonymous without one
+    pub fn from_token(camera_config: &CameraConfig, token: Option<&str>) -> Self {
+        let anonymous = Self { id: "anonymous".to_string(), label: "anonymous".to_string(), priority: ANONYMOUS_PRIORITY };
+        let Some(token) = token else { return anonymous };
+
+        if let Some(session) = crate::ldap::get_session(token) {
+            return Self { id: format!("user:{}", session.username), label: session.username, priority: session.ptz_priority };
+        }
+        let camera_token = Self { id: "token".to_string(), label: "camera token".to_string(), priority: CAMERA_TOKEN_PRIORITY };
+        if camera_config.token.as_deref().is_some_and(|t| crate::secrets::matches(t, token)) {
+            return camera_token;
+        }
+        if let Some((index, scoped)) = camera_config.tokens.iter().enumerate().find(|(_, t)| crate::secrets::matches(&t.token, token)) {
+            let name = scoped.name.clone().unwrap_or_else(|| format!("#{}", index + 1));
+            return Self::scoped_token(name, scoped.ptz_priority);
+        }
+        // A rotated token keeps the identity of the token that replaced it
+        match camera_config.previous_tokens.iter().find(|p| p.accepts(token)) {
+            Some(previous) => match previous.name {
+                Some(ref name) => {
+                    let priority = camera_config.tokens.iter().find(|t| t.name.as_ref() == Some(name)).and_then(|t| t.ptz_priority);
+                    Self::scoped_token(name.clone(), priority)
+                }
+                None => camera_token,
+            },
+            None => anonymous,
+        }
+    }
+
+    fn scoped_token(name: String, priority: Option<u8>) -> Self {
+        Self { id: format!("token:{}", name), label: format!("token '{}'", name), priority: priority.unwrap_or(SCOPED_TOKEN_PRIORITY) }
+    }
+
+    pub fn trigger(trigger_id: &str, ptz_config: &PtzConfig) -> Self {
+        Self {
+            id: format!("trigger:{}", trigger_id),
+            label: format!("trigger '{}'", trigger_id),
+            priority: ptz_config.trigger_priority,
+        }
+    }
+
+    /// The same identity, but controlling on its own (e.g. one control WebSocket connection)
+    pub fn with_id(self, id: &str) -> Self {
+        Self { id: id.to_string(), ..self }
+    }
+}
+
+/// Client currently controlling the PTZ of a camera
+#[derive(Debug, Clone, Serialize)]
+pub struct PtzHolder {
+    pub client_id: String,
+    pub label: String,
+    pub priority: u8,
+    pub since: DateTime<Utc>,
+    /// End of the lease unless the client sends another command before
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Why a command was refused
+#[derive(Debug, Clone, Serialize)]
+pub struct PtzConflict {
+    /// Client in control, None if control is free but reserved for a queued client with a higher priority
+    pub holder: Option<PtzHolder>,
+}
+
+impl PtzConflict {
+    pub fn message(&self) -> String {
+        match self.holder {
+            Some(ref holder) => format!("PTZ is controlled by {} (priority {})", holder.label, holder.priority),
+            None => "PTZ is reserved for a waiting client with higher priority".to_string(),
+        }
+    }
+}
+
+struct Arbiter {
+    holder: Mutex<Option<PtzHolder>>,
+    /// Priorities of clients waiting for control under the queue policy
+    waiting: Mutex<Vec<u8>>,
+    changes: watch::Sender<Option<PtzHolder>>,
+    released: Notify,
+}
+
+impl Arbiter {
+    fn try_acquire(&self, camera_id: &str, client: &PtzClient, lease: Duration) -> Result<(), PtzConflict> {
+        let now = Utc::now();
+        let expires_at = now + chrono::Duration::from_std(lease).unwrap_or_default();
+        let mut holder = self.holder.lock().unwrap_or_else(|e| e.into_inner());
+        match holder.as_mut() {
+            Some(current) if current.client_id == client.id => {
+                current.expires_at = expires_at;
+                return Ok(());
+            }
+            Some(current) if current.expires_at > now && current.priority >= client.priority => {
+                return Err(PtzConflict { holder: Some(current.clone()) });
+            }
+            Some(current) if current.expires_at > now => {
+                info!("PTZ control of camera '{}' taken over by {} (priority {}) from {} (priority {})",
+                      camera_id, client.label, client.priority, current.label, current.priority);
+            }
+            _ => {
+                let waiting = self.waiting.lock().unwrap_or_else(|e| e.into_inner());
+                if waiting.iter().any(|priority| *priority > client.priority) {
+                    return Err(PtzConflict { holder: None });
+                }
+                debug!("PTZ control of camera '{}' acquired by {}", camera_id, client.label);
+            }
+        }
+        *holder = Some(PtzHolder {
+            client_id: client.id.clone(),
+            label: client.label.clone(),
+            priority: client.priority,
+            since: now,
+            expires_at,
+        });
+        self.changes.send_replace(holder.clone());
+        Ok(())
+    }
+
+    fn current(&self) -> Option<PtzHolder> {
+        let holder = self.holder.lock().unwrap_or_else(|e| e.into_inner());
+        holder.clone().filter(|h| h.expires_at > Utc::now())
+    }
+}
+
+/// Removes a queued client from the waiting list, also when its request is dropped
+struct WaitingGuard<'a> {
+    arbiter: &'a Arbiter,
+    priority: u8,
+}
+
+impl Drop for WaitingGuard<'_> {
+    fn drop(&mut self) {
+        let mut waiting = self.arbiter.waiting.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(index) = waiting.iter().position(|p| *p == self.priority) {
+            waiting.remove(index);
+        }
+    }
+}
+
+fn arbiter(camera_id: &str) -> Arc<Arbiter> {
+    let mut arbiters = ARBITERS.lock().unwrap_or_else(|e| e.into_inner());
+    arbiters.entry(camera_id.to_string())
+        .or_insert_with(|| Arc::new(Arbiter {
+            holder: Mutex::new(None),
+            waiting: Mutex::new(Vec::new()),
+            changes: watch::channel(None).0,
+            released: Notify::new(),
+        }))
+        .clone()
+}
+
+/// Take or renew control for a command of `client`. A client with a higher priority takes over,
+/// otherwise the command is refused or, with the queue policy, waits for control.
+pub async fn acquire(camera_id: &str, client: &PtzClient, ptz_config: &PtzConfig) -> Result<(), PtzConflict> {
+    let arbiter = arbiter(camera_id);
+    let lease = Duration::from_secs(ptz_config.control_lease_secs);
+    let deadline = Instant::now() + Duration::from_secs(ptz_config.queue_timeout_secs);
+    let mut guard = None;
+    loop {
+        // Registered before the attempt so a release in between is not missed
+        let released = arbiter.released.notified();
+        let conflict = match arbiter.try_acquire(camera_id, client, lease) {
+            Ok(()) => return Ok(()),
+            Err(conflict) => conflict,
+        };
+        if ptz_config.conflict_policy != PtzConflictPolicy::Queue || Instant::now() >= deadline {
+            return Err(conflict);
+        }
+        if guard.is_none() {
+            debug!("PTZ command of {} for camera '{}' queued: {}", client.label, camera_id, conflict.message());
+            arbiter.waiting.lock().unwrap_or_else(|e| e.into_inner()).push(client.priority);
+            guard = Some(WaitingGuard { arbiter: &arbiter, priority: client.priority });
+        }
+        let wakeup = match conflict.holder {
+            Some(ref holder) => deadline.min(Instant::now() + (holder.expires_at - Utc::now()).to_std().unwrap_or_default()),
+            None => deadline,
+        };
+        let _ = timeout_at(wakeup, released).await;
+    }
+}
+
+/// Take or renew control without waiting, for commands that are outdated by the time control is free
+pub fn try_acquire(camera_id: &str, client: &PtzClient, ptz_config: &PtzConfig) -> Result<(), PtzConflict> {
+    arbiter(camera_id).try_acquire(camera_id, client, Duration::from_secs(ptz_config.control_lease_secs))
+}
+
+/// Give up control, ignored if `client_id` does not have it
+pub fn release(camera_id: &str, client_id: &str) {
+    let Some(arbiter) = ARBITERS.lock().unwrap_or_else(|e| e.into_inner()).get(camera_id).cloned() else { return };
+    let mut holder = arbiter.holder.lock().unwrap_or_else(|e| e.into_inner());
+    if holder.as_ref().is_some_and(|h| h.client_id == client_id) {
+        debug!("PTZ control of camera '{}' released by '{}'", camera_id, client_id);
+        *holder = None;
+        arbiter.changes.send_replace(None);
+        arbiter.released.notify_waiters();
+    }
+}
+
+/// Client in control of the camera, None if control is free
+pub fn current(camera_id: &str) -> Option<PtzHolder> {
+    ARBITERS.lock().unwrap_or_else(|e| e.into_inner()).get(camera_id).and_then(|a| a.current())
+}
+
+/// Changes of control of the camera. Leases running out are not sent, see [`current`].
+pub fn subscribe(camera_id: &str) -> watch::Receiver<Option<PtzHolder>> {
+    arbiter(camera_id).changes.subscribe()
+}
@@ -0,0 +1,163 @@
+use std::sync::Arc;
+use axum::extract::ws::{Message, WebSocket};
+use futures_util::SinkExt;
+use futures_util::stream::SplitSink;
+use tokio::sync::{watch, Mutex};
+use tokio::time::{sleep_until, Duration, Instant};
+use tracing::{debug, warn};
+
+use crate::config::PtzConfig;
+use crate::ptz::{PtzController, PtzVelocity};
+use crate::ptz_arbiter::{self, PtzClient};
+use crate::recording::RecordingManager;
+use crate::websocket_control::CommandResponse;
+
+// At most 10 commands per second go to the camera, vectors arriving in between replace each other
+const MIN_COMMAND_INTERVAL: Duration = Duration::from_millis(100);
+// While the vector stays the same, the move is repeated so the camera timeout below never runs out
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(1);
+// ContinuousMove timeout, the camera stops by itself if the server goes away
+const MOVE_TIMEOUT_SECS: u64 = 2;
+// The camera is stopped when the client sent no vector for this long
+const INPUT_TIMEOUT: Duration = Duration::from_secs(1);
+// Vector components below this are treated as a centered stick
+const DEADZONE: f32 = 0.02;
+// Changes smaller than this are not worth a new command
+const MIN_CHANGE: f32 = 0.01;
+
+/// Joystick channel of one control connection, turning a stream of pan/tilt/zoom velocity
+/// vectors into rate-limited ContinuousMove requests. Vectors are refused while another client
+/// has control, never queued. Dropping it stops a moving camera and releases control.
+pub struct PtzJoystick {
+    vectors: watch::Sender<(PtzVelocity, Instant)>,
+}
+
+impl PtzJoystick {
+    pub fn start(
+        camera_id: String,
+        controller: Arc<dyn PtzController>,
+        client: PtzClient,
+        ptz_config: PtzConfig,
+        recording_manager: RecordingManager,
+        sender: Arc<Mutex<SplitSink<WebSocket, Message>>>,
+    ) -> Self {
+        let (vectors, receiver) = watch::channel((PtzVelocity::default(), Instant::now()));
+        tokio::spawn(run(camera_id, controller, client, ptz_config, recording_manager, sender, receiver));
+        Self { vectors }
+    }
+
+    /// Latest vector of the client, replaces a vector not sent to the camera yet
+    pub fn update(&self, velocity: PtzVelocity) {
+        let _ = self.vectors.send((velocity, Instant::now()));
+    }
+}
+
+async fn run(
+    camera_id: String,
+    controller: Arc<dyn PtzController>,
+    client: PtzClient,
+    ptz_config: PtzConfig,
+    recording_manager: RecordingManager,
+    sender: Arc<Mutex<SplitSink<WebSocket, Message>>>,
+    mut receiver: watch::Receiver<(PtzVelocity, Instant)>,
+) {
+    // Vector the camera is moving with, None while it stands still
+    let mut moving: Option<PtzVelocity> = None;
+    let mut last_command = Instant::now() - MIN_COMMAND_INTERVAL;
+    let mut failing = false;
+    let mut refused = false;
+
+    loop {
+        let input_at = receiver.borrow().1;
+        let wakeup = (last_command + KEEPALIVE_INTERVAL).min(input_at + INPUT_TIMEOUT);
+        tokio::select! {
+            changed = receiver.changed() => {
+                if changed.is_err() {
+                    break;
+                }
+            }
+            _ = sleep_until(wakeup), if moving.is_some() => {}
+        }
+        sleep_until(last_command + MIN_COMMAND_INTERVAL).await;
+
+        let (velocity, input_at) = *receiver.borrow_and_update();
+        let velocity = if input_at.elapsed() >= INPUT_TIMEOUT { PtzVelocity::default() } else { velocity };
+        let result = if is_centered(velocity) {
+            let Some(_) = moving.take() else { continue };
+            // The client that took over decides where the camera goes
+            if controlled_by_other(&camera_id, &client) {
+                continue;
+            }
+            debug!("PTZ joystick of camera '{}' centered, stopping", camera_id);
+            controller.stop().await
+        } else {
+            if moving.is_some_and(|current| !changed(current, velocity)) && last_command.elapsed() < KEEPALIVE_INTERVAL {
+                continue;
+            }
+            if let Err(conflict) = ptz_arbiter::try_acquire(&camera_id, &client, &ptz_config) {
+                moving = None;
+                // Reported once until the client has control again
+                if !refused {
+                    refused = true;
+                    debug!("PTZ joystick of {} for camera '{}' refused: {}", client.label, camera_id, conflict.message());
+                    let response = CommandResponse {
+                        code: 409,
+                        text: conflict.message(),
+                        data: Some(serde_json::json!({ "holder": conflict.holder })),
+                    };
+                    send_response(&sender, &response).await;
+                }
+                continue;
+            }
+            refused = false;
+            if moving.is_none() {
+                recording_manager.record_event(&camera_id, "ptz", "joystick", serde_json::json!({
+                    "pan": velocity.pan, "tilt": velocity.tilt, "zoom": velocity.zoom
+                })).await;
+            }
+            moving = Some(velocity);
+            controller.continuous_move(velocity, Some(MOVE_TIMEOUT_SECS)).await
+        };
+        last_command = Instant::now();
+
+        match result {
+            Ok(()) => failing = false,
+            Err(e) => {
+                warn!("PTZ joystick command for camera '{}' failed: {}", camera_id, e);
+                // Reported once per run of failures, not for every coalesced vector
+                if !failing {
+                    failing = true;
+                    send_response(&sender, &CommandResponse::error(502, &format!("PTZ move failed: {}", e))).await;
+                }
+            }
+        }
+    }
+
+    if moving.is_some() && !controlled_by_other(&camera_id, &client) {
+        if let Err(e) = controller.stop().await {
+            warn!("Failed to stop PTZ of camera '{}' after the joystick ended: {}", camera_id, e);
+        }
+    }
+    ptz_arbiter::release(&camera_id, &client.id);
+    debug!("PTZ joystick of camera '{}' ended", camera_id);
+}
+
+async fn send_response(sender: &Mutex<SplitSink<WebSocket, Message>>, response: &CommandResponse) {
+    let mut message_data = vec![0x01];
+    message_data.extend_from_slice(serde_json::to_string(response).unwrap_or_default().as_bytes());
+    let _ = sender.lock().await.send(Message::Binary(message_data)).await;
+}
+
+fn controlled_by_other(camera_id: &str, client: &PtzClient) -> bool {
+    ptz_arbiter::current(camera_id).is_some_and(|holder| holder.client_id != client.id)
+}
+
+fn is_centered(velocity: PtzVelocity) -> bool {
+    velocity.pan.abs() < DEADZONE && velocity.tilt.abs() < DEADZONE && velocity.zoom.abs() < DEADZONE
+}
+
+fn changed(current: PtzVelocity, velocity: PtzVelocity) -> bool {
+    (current.pan - velocity.pan).abs() >= MIN_CHANGE
+        || (current.tilt - velocity.tilt).abs() >= MIN_CHANGE
+        || (current.zoom - velocity.zoom).abs() >= MIN_CHANGE
+}
@@ -1,4 +1,5 @@
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::collections::HashMap;
 use tokio::sync::{RwLock, broadcast, mpsc};
 use chrono::{DateTime, Utc, Local, Datelike};
@@ -8,7 +9,12 @@ use bytes::Bytes;
 use crate::config::RecordingConfig;
 use tokio::io::AsyncWriteExt;
 use tokio::process::Command;
-use crate::database::{DatabaseProvider, RecordingSession, RecordedFrame, RecordingQuery, VideoSegment, RecordingHlsSegment};
+use crate::database::{DatabaseProvider, RecordingSession, RecordedFrame, RecordingQuery, VideoSegment, RecordingHlsSegment, PageRequest, RecordingEvent};
+use crate::frame_compression;
+use crate::frame_spill::{FrameQueueStats, FrameSpillQueue, SpilledFrame};
+use crate::integrity;
+use crate::pipeline_metrics::{self, ConsumerMetrics, SlowConsumer};
+use crate::thumbnails;
 
 /// Sanitize a recording reason string for safe use in filenames.
 /// Returns None if the sanitized result is empty.
@@ -70,6 +76,17 @@ enum FrameWriterMessage {
     Flush,
 }
 
+impl From<SpilledFrame> for FrameWriterMessage {
+    fn from(frame: SpilledFrame) -> Self {
+        FrameWriterMessage::Frame {
+            session_id: frame.session_id,
+            timestamp: frame.timestamp,
+            frame_number: frame.frame_number,
+            data: frame.data,
+        }
+    }
+}
+
 /// Channel buffer size for writer - allows ~60 seconds of frames at 15fps
 const WRITER_CHANNEL_BUFFER: usize = 900;
 
@@ -77,17 +94,128 @@ const WRITER_CHANNEL_BUFFER: usize = 900;
 const BULK_WRITE_MAX_FRAMES: usize = 60;
 const BULK_WRITE_MAX_INTERVAL_MS: u64 = 1000;
 
+/// Wait time before retrying a spilled batch after a failed replay
+const SPILL_REPLAY_RETRY_SECS: u64 = 5;
+
+/// Insert one batch, store its integrity hash and record its latency in the camera's queue metrics.
+/// With a compression level the frames are stored zstd-compressed, the hash covers the original frames.
+async fn write_batch(
+    database: &Arc<dyn DatabaseProvider>,
+    stats: &FrameQueueStats,
+    session_id: i64,
+    camera_id: &str,
+    batch: &[(DateTime<Utc>, i64, Vec<u8>)],
+    compression: Option<i32>,
+) -> crate::errors::Result<u64> {
+    let compressed = match compression {
+        Some(level) => Some(frame_compression::compress_batch(batch, level).await),
+        None => None,
+    };
+    let start = std::time::Instant::now();
+    let result = database.add_recorded_frames_bulk(session_id, camera_id, compressed.as_deref().unwrap_or(batch)).await;
+    stats.record_insert(start.elapsed(), batch.len(), result.as_ref().err().map(|e| e.to_string()));
+    pipeline_metrics::get(camera_id).bulk_insert_seconds.observe(start.elapsed().as_secs_f64());
+    if result.is_ok() {
+        integrity::store_frame_batch_hash(database, camera_id, batch).await;
+    }
+    result
+}
+
+/// Sample how far a recording consumer is behind the live broadcast channel and store a
+/// warning event when it starts lagging persistently, and another one when it caught up
+async fn observe_consumer(
+    config: &RecordingConfig,
+    database: &Arc<dyn DatabaseProvider>,
+    camera_id: &str,
+    consumer: &str,
+    metrics: &ConsumerMetrics,
+    backlog: usize,
+    skipped: u64,
+) {
+    let warning_after = (config.slow_consumer_warning_secs > 0)
+        .then(|| std::time::Duration::from_secs(config.slow_consumer_warning_secs));
+    let Some(change) = metrics.observe(backlog, skipped, config.slow_consumer_backlog_frames.max(1), warning_after) else {
+        return;
+    };
+    let lagged_frames = metrics.lagged_frames.load(Ordering::Relaxed);
+    let label = match change {
+        SlowConsumer::Started => {
+            warn!("Recording consumer '{}' of camera '{}' lags the live frames for more than {}s ({} frames behind, {} frames lost so far)",
+                  consumer, camera_id, config.slow_consumer_warning_secs, backlog, lagged_frames);
+            "slow consumer"
+        }
+        SlowConsumer::Recovered => {
+            info!("Recording consumer '{}' of camera '{}' caught up with the live frames", consumer, camera_id);
+            "slow consumer recovered"
+        }
+    };
+    let event = RecordingEvent {
+        id: 0,
+        camera_id: camera_id.to_string(),
+        timestamp: Utc::now(),
+        event_type: "recording".to_string(),
+        label: label.to_string(),
+        data: serde_json::json!({
+            "consumer": consumer,
+            "backlog_frames": backlog,
+            "lagged_frames": lagged_frames,
+        }),
+    };
+    if let Err(e) = database.add_recording_event(&event).await {
+        warn!("Failed to store slow consumer event for camera '{}': {}", camera_id, e);
+    }
+}
+
+/// Replay the oldest spilled batch into the database. Returns false if nothing was replayed.
+async fn replay_spilled_batch(
+    database: &Arc<dyn DatabaseProvider>,
+    camera_id: &str,
+    spill_queue: &FrameSpillQueue,
+    compression: Option<i32>,
+) -> bool {
+    let Some(batch) = spill_queue.take_next().await else {
+        return false;
+    };
+    let frame_count = batch.frames.len();
+
+    // A spilled batch may span a session split, so insert per session
+    let mut inserted = 0;
+    for session_frames in batch.frames.chunk_by(|a, b| a.session_id == b.session_id) {
+        let session_id = session_frames[0].session_id;
+        let rows: Vec<(DateTime<Utc>, i64, Vec<u8>)> = session_frames
+            .iter()
+            .map(|f| (f.timestamp, f.frame_number, f.data.clone()))
+            .collect();
+        if let Err(e) = write_batch(database, &spill_queue.stats, session_id, camera_id, &rows, compression).await {
+            error!("Failed to replay {} spilled frames for camera '{}': {}", rows.len(), camera_id, e);
+            // Sessions inserted before the failure are not replayed again
+            let remaining = batch.frames[inserted..].to_vec();
+            spill_queue.requeue(batch, &remaining).await;
+            return false;
+        }
+        inserted += session_frames.len();
+    }
+
+    spill_queue.complete(batch).await;
+    debug!("Replayed {} spilled frames into database for camera '{}'", frame_count, camera_id);
+    true
+}
+
 /// Dedicated database writer task - receives frames via mpsc channel and writes in batches
 async fn frame_writer_loop(
     database: Arc<dyn DatabaseProvider>,
     camera_id: String,
     mut receiver: mpsc::Receiver<FrameWriterMessage>,
+    spill_queue: Arc<FrameSpillQueue>,
+    compression: Option<i32>,
 ) {
     let mut frame_buffer: Vec<(DateTime<Utc>, i64, Vec<u8>)> = Vec::with_capacity(BULK_WRITE_MAX_FRAMES);
     let mut current_session_id: Option<i64> = None;
     let mut last_flush_time = std::time::Instant::now();
+    let mut replay_retry_at: Option<std::time::Instant> = None;
 
     debug!("Frame writer started for camera '{}'", camera_id);
+    spill_queue.stats.writer_running.store(true, Ordering::Relaxed);
 
     loop {
         // Use timeout to ensure we flush periodically even without new frames
@@ -106,7 +234,7 @@ async fn frame_writer_loop(
                         if current_session_id != Some(session_id) && !frame_buffer.is_empty() {
                             if let Some(old_session_id) = current_session_id {
                                 let count = frame_buffer.len();
-                                if let Err(e) = database.add_recorded_frames_bulk(old_session_id, &camera_id, &frame_buffer).await {
+                                if let Err(e) = write_batch(&database, &spill_queue.stats, old_session_id, &camera_id, &frame_buffer, compression).await {
                                     error!("Failed to flush {} frames for old session {}: {}", count, old_session_id, e);
                                 } else {
                                     trace!("Flushed {} frames for old session {} before session change", count, old_session_id);
@@ -124,7 +252,7 @@ async fn frame_writer_loop(
                                 let count = frame_buffer.len();
                                 let total_bytes: usize = frame_buffer.iter().map(|(_, _, d)| d.len()).sum();
                                 let write_start = std::time::Instant::now();
-                                match database.add_recorded_frames_bulk(sid, &camera_id, &frame_buffer).await {
+                                match write_batch(&database, &spill_queue.stats, sid, &camera_id, &frame_buffer, compression).await {
                                     Ok(inserted) => {
                                         let write_ms = write_start.elapsed().as_millis();
                                         if write_ms > 500 {
@@ -149,7 +277,7 @@ async fn frame_writer_loop(
                         if !frame_buffer.is_empty() {
                             if let Some(old_session_id) = current_session_id {
                                 let count = frame_buffer.len();
-                                if let Err(e) = database.add_recorded_frames_bulk(old_session_id, &camera_id, &frame_buffer).await {
+                                if let Err(e) = write_batch(&database, &spill_queue.stats, old_session_id, &camera_id, &frame_buffer, compression).await {
                                     error!("Failed to flush {} frames before session change: {}", count, e);
                                 }
                                 frame_buffer.clear();
@@ -163,7 +291,7 @@ async fn frame_writer_loop(
                         if !frame_buffer.is_empty() {
                             if let Some(sid) = current_session_id {
                                 let count = frame_buffer.len();
-                                if let Err(e) = database.add_recorded_frames_bulk(sid, &camera_id, &frame_buffer).await {
+                                if let Err(e) = write_batch(&database, &spill_queue.stats, sid, &camera_id, &frame_buffer, compression).await {
                                     error!("Failed to flush {} frames on request: {}", count, e);
                                 } else {
                                     trace!("Flushed {} frames on request for camera '{}'", count, camera_id);
@@ -180,14 +308,18 @@ async fn frame_writer_loop(
                 if !frame_buffer.is_empty() {
                     if let Some(sid) = current_session_id {
                         let count = frame_buffer.len();
-                        if let Err(e) = database.add_recorded_frames_bulk(sid, &camera_id, &frame_buffer).await {
+                        if let Err(e) = write_batch(&database, &spill_queue.stats, sid, &camera_id, &frame_buffer, compression).await {
                             error!("Failed to flush {} remaining frames on shutdown: {}", count, e);
                         } else {
                             debug!("Flushed {} remaining frames on writer shutdown for camera '{}'", count, camera_id);
                         }
                     }
                 }
+                // Replay whatever is still spilled; failed batches stay on disk for the next writer
+                while replay_spilled_batch(&database, &camera_id, &spill_queue, compression).await {}
                 debug!("Frame writer stopped for camera '{}'", camera_id);
+                spill_queue.stats.queue_depth.store(0, Ordering::Relaxed);
+                spill_queue.stats.writer_running.store(false, Ordering::Relaxed);
                 break;
             }
             Err(_) => {
@@ -197,7 +329,7 @@ async fn frame_writer_loop(
                         let count = frame_buffer.len();
                         let total_bytes: usize = frame_buffer.iter().map(|(_, _, d)| d.len()).sum();
                         let write_start = std::time::Instant::now();
-                        match database.add_recorded_frames_bulk(sid, &camera_id, &frame_buffer).await {
+                        match write_batch(&database, &spill_queue.stats, sid, &camera_id, &frame_buffer, compression).await {
                             Ok(inserted) => {
                                 let write_ms = write_start.elapsed().as_millis();
                                 if write_ms > 500 {
@@ -218,15 +350,252 @@ async fn frame_writer_loop(
                 }
             }
         }
+
+        // Database has caught up with the live queue - replay one spilled batch
+        if receiver.is_empty() && replay_retry_at.is_none_or(|t| std::time::Instant::now() >= t) {
+            if spill_queue.pending_files().await > 0 && !replay_spilled_batch(&database, &camera_id, &spill_queue, compression).await {
+                replay_retry_at = Some(std::time::Instant::now() + std::time::Duration::from_secs(SPILL_REPLAY_RETRY_SECS));
+            } else {
+                replay_retry_at = None;
+            }
+        }
     }
 }
 
+/// Result of compacting one camera database
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct VacuumReport {
+    pub camera_id: String,
+    pub size_before_bytes: i64,
+    pub size_after_bytes: i64,
+    pub reclaimed_bytes: i64,
+    pub duration_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
 #[derive(Debug, Clone)]
 pub struct ActiveRecording {
     pub session_id: i64,
     pub start_time: DateTime<Utc>,
     pub frame_count: u64,
     pub requested_duration: Option<i64>,
+    pub storage_pause: Arc<StoragePause>,
+    pub stop_at: Option<DateTime<Utc>>, // Automatic stop (max recording duration), kept across session splits
+}
+
+/// Kind of recording storage that can be paused at runtime
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StorageKind {
+    Frames,
+    Mp4,
+    Hls,
+}
+
+impl StorageKind {
+    pub fn parse(kind: &str) -> Option<Self> {
+        match kind {
+            "frames" => Some(Self::Frames),
+            "mp4" => Some(Self::Mp4),
+            "hls" => Some(Self::Hls),
+            _ => None,
+        }
+    }
+}
+
+/// Storage an operator paused for a camera (e.g. during database maintenance). The recording
+/// session keeps running, paused storage just skips the incoming frames until it is resumed.
+#[derive(Debug, Default)]
+pub struct StoragePause {
+    frames: AtomicBool,
+    mp4: AtomicBool,
+    hls: AtomicBool,
+}
+
+impl StoragePause {
+    fn flag(&self, kind: StorageKind) -> &AtomicBool {
+        match kind {
+            StorageKind::Frames => &self.frames,
+            StorageKind::Mp4 => &self.mp4,
+            StorageKind::Hls => &self.hls,
+        }
+    }
+
+    pub fn is_paused(&self, kind: StorageKind) -> bool {
+        self.flag(kind).load(Ordering::Relaxed)
+    }
+
+    /// Returns true if the state changed
+    pub fn set_paused(&self, kind: StorageKind, paused: bool) -> bool {
+        self.flag(kind).swap(paused, Ordering::Relaxed) != paused
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "frames": self.is_paused(StorageKind::Frames),
+            "mp4": self.is_paused(StorageKind::Mp4),
+            "hls": self.is_paused(StorageKind::Hls),
+        })
+    }
+}
+
+/// What a recording task writes to: the camera and session bookkeeping shared by the
+/// frame, video and HLS tasks of one recording.
+#[derive(Clone)]
+struct RecordingTarget {
+    config: Arc<RecordingConfig>,
+    database: Arc<dyn DatabaseProvider>,
+    active_recordings: Arc<RwLock<HashMap<String, ActiveRecording>>>,
+    camera_id: String,
+}
+
+/// Frames collected for one video or HLS segment
+struct PendingSegment {
+    camera_id: String,
+    session_id: i64,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    frames: Vec<Bytes>,
+}
+
+/// MKV segment that FFmpeg writes straight into its file while recording. The segment is
+/// listed when it starts and its end time updated as it grows, so a crash leaves a playable
+/// partial segment instead of losing the frames buffered for it.
+struct LiveSegment {
+    child: tokio::process::Child,
+    stdin: tokio::process::ChildStdin,
+    segment: VideoSegment,
+    first_frame: Option<Bytes>,
+    frame_count: usize,
+    bytes_written: usize,
+    last_progress: std::time::Instant,
+}
+
+impl LiveSegment {
+    const PROGRESS_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
+
+    async fn start(
+        config: &RecordingConfig,
+        database: &Arc<dyn DatabaseProvider>,
+        camera_id: &str,
+        session_id: i64,
+        start_time: DateTime<Utc>,
+    ) -> crate::errors::Result<Self> {
+        let container = crate::config::SegmentContainer::Mkv;
+        let file_path = RecordingManager::segment_file_path(config, database, camera_id, session_id, start_time, container).await?;
+
+        let mut cmd = Command::new("ffmpeg");
+        cmd.args([
+            // Frames are piped as they arrive, so their arrival time is their timestamp
+            "-use_wallclock_as_timestamps", "1",
+            "-f", "mjpeg",
+            "-i", "-",
+            "-c:v", "libx264",
+            "-preset", "ultrafast",
+            // Keyframes and clusters every 2 seconds bound what a crash can lose
+            "-force_key_frames", "expr:gte(t,n_forced*2)",
+            "-f", "matroska",
+            "-cluster_time_limit", "2000",
+            "-y", &file_path,
+        ]);
+        cmd.stdin(std::process::Stdio::piped());
+        cmd.stdout(std::process::Stdio::null());
+        cmd.stderr(std::process::Stdio::null());
+
+        let mut child = cmd.spawn()?;
+        let stdin = child.stdin.take()
+            .ok_or_else(|| crate::errors::StreamError::ffmpeg("Failed to open ffmpeg stdin"))?;
+
+        let segment = VideoSegment {
+            camera_id: camera_id.to_string(),
+            session_id,
+            start_time,
+            end_time: start_time,
+            file_path: Some(file_path),
+            size_bytes: 0,
+            mp4_data: None,
+            recording_reason: None,
+        };
+        if let Err(e) = database.add_video_segment(&segment).await {
+            let _ = child.kill().await;
+            return Err(e);
+        }
+        debug!("Started live {} segment for camera '{}' at {}", container, camera_id, start_time);
+
+        Ok(Self {
+            child,
+            stdin,
+            segment,
+            first_frame: None,
+            frame_count: 0,
+            bytes_written: 0,
+            last_progress: std::time::Instant::now(),
+        })
+    }
+
+    async fn write(&mut self, database: &Arc<dyn DatabaseProvider>, frame: &Bytes) -> crate::errors::Result<()> {
+        self.stdin.write_all(frame).await?;
+        self.first_frame.get_or_insert_with(|| frame.clone());
+        self.frame_count += 1;
+        self.bytes_written += frame.len();
+
+        if self.last_progress.elapsed() >= Self::PROGRESS_INTERVAL {
+            self.last_progress = std::time::Instant::now();
+            self.segment.end_time = Utc::now();
+            if let Some(ref file_path) = self.segment.file_path {
+                if let Ok(metadata) = tokio::fs::metadata(file_path).await {
+                    self.segment.size_bytes = metadata.len() as i64;
+                }
+            }
+            if let Err(e) = database.update_video_segment_progress(&self.segment).await {
+                warn!("Failed to update live segment of camera '{}': {}", self.segment.camera_id, e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Close FFmpeg's input, wait for it to finish the file and record the final segment
+    async fn finish(self, database: Arc<dyn DatabaseProvider>, end_time: DateTime<Utc>) -> crate::errors::Result<()> {
+        let LiveSegment { mut child, stdin, mut segment, first_frame, frame_count, .. } = self;
+        drop(stdin);
+        let status = child.wait().await?;
+        if !status.success() {
+            warn!("FFmpeg exited with {} while writing live segment of camera '{}', keeping the partial file",
+                  status, segment.camera_id);
+        }
+
+        let file_path = segment.file_path.clone().unwrap_or_default();
+        let data = tokio::fs::read(&file_path).await?;
+        segment.end_time = end_time;
+        segment.size_bytes = data.len() as i64;
+        database.update_video_segment_progress(&segment).await?;
+        integrity::store_mp4_hash(&database, &segment, &data).await;
+        debug!("Finished live segment {} of camera '{}' with {} frames", file_path, segment.camera_id, frame_count);
+
+        if let Some(frame) = first_frame {
+            thumbnails::store_thumbnail(database, segment.camera_id, segment.start_time, frame).await;
+        }
+        Ok(())
+    }
+}
+
+/// Finish a live segment in the background, like buffered segments are muxed
+fn spawn_finish_live_segment(
+    live: LiveSegment,
+    database: Arc<dyn DatabaseProvider>,
+    end_time: DateTime<Utc>,
+    metrics: Arc<pipeline_metrics::PipelineMetrics>,
+) {
+    metrics.mux_queued();
+    tokio::spawn(async move {
+        let camera_id = live.segment.camera_id.clone();
+        let mux_start = std::time::Instant::now();
+        let result = live.finish(database, end_time).await;
+        metrics.mux_finished(mux_start.elapsed());
+        if let Err(e) = result {
+            error!("Failed to finish live video segment of camera '{}': {}", camera_id, e);
+        }
+    });
 }
 
 #[derive(Clone)]
@@ -237,6 +606,9 @@ pub struct RecordingManager {
     frame_subscribers: Arc<RwLock<HashMap<String, broadcast::Receiver<Bytes>>>>, // camera_id -> receiver
     camera_configs: Arc<RwLock<HashMap<String, crate::config::CameraConfig>>>, // camera configs for cleanup
     mp4_buffer_stats: Arc<RwLock<HashMap<String, Arc<tokio::sync::RwLock<crate::Mp4BufferStats>>>>>, // camera_id -> buffer stats
+    frame_spill_queues: Arc<RwLock<HashMap<String, Arc<FrameSpillQueue>>>>, // camera_id -> writer overflow queue
+    quota_exceeded: Arc<RwLock<HashMap<String, String>>>, // camera_id -> tenant over its storage quota
+    storage_pauses: Arc<RwLock<HashMap<String, Arc<StoragePause>>>>, // camera_id -> paused storage kinds
 }
 
 impl RecordingManager {
@@ -248,6 +620,9 @@ impl RecordingManager {
             frame_subscribers: Arc::new(RwLock::new(HashMap::new())),
             camera_configs: Arc::new(RwLock::new(HashMap::new())),
             mp4_buffer_stats: Arc::new(RwLock::new(HashMap::new())),
+            frame_spill_queues: Arc::new(RwLock::new(HashMap::new())),
+            quota_exceeded: Arc::new(RwLock::new(HashMap::new())),
+            storage_pauses: Arc::new(RwLock::new(HashMap::new())),
         })
     }
 
@@ -269,6 +644,104 @@ impl RecordingManager {
         buffer_stats.get(camera_id).cloned()
     }
 
+    /// Get or create the frame spill queue for a camera
+    async fn get_frame_spill_queue(&self, camera_id: &str) -> Arc<FrameSpillQueue> {
+        let mut queues = self.frame_spill_queues.write().await;
+        queues.entry(camera_id.to_string())
+            .or_insert_with(|| Arc::new(FrameSpillQueue::new(
+                camera_id,
+                self.config.get_frame_spill_path(camera_id),
+                self.config.frame_spill_enabled,
+                self.config.frame_spill_max_mb * 1024 * 1024,
+            )))
+            .clone()
+    }
+
+    /// Paused storage of a camera; kept across recording sessions until resumed
+    pub async fn get_storage_pause(&self, camera_id: &str) -> Arc<StoragePause> {
+        let mut pauses = self.storage_pauses.write().await;
+        pauses.entry(camera_id.to_string()).or_default().clone()
+    }
+
+    /// Get frame writer queue metrics for a camera
+    pub async fn get_frame_queue_stats(&self, camera_id: &str) -> Option<serde_json::Value> {
+        let queue = self.frame_spill_queues.read().await.get(camera_id).cloned()?;
+        Some(queue.stats_json().await)
+    }
+
+    /// State of the recording pipeline of a camera: session, frame writer and MP4 segmenter
+    pub async fn get_recording_state(&self, camera_id: &str) -> serde_json::Value {
+        let active = self.get_active_recording(camera_id).await;
+        let queue = self.frame_spill_queues.read().await.get(camera_id).cloned();
+        let camera_config = self.camera_configs.read().await.get(camera_id).cloned();
+
+        let writer = match queue {
+            Some(queue) => {
+                let stats = &queue.stats;
+                let spill_files_pending = queue.pending_files().await;
+                let running = stats.writer_running.load(Ordering::Relaxed);
+                let health = if !self.config.frame_storage_enabled {
+                    "disabled"
+                } else if active.is_some() && !running {
+                    "stopped"
+                } else if active.is_none() && running {
+                    "draining"
+                } else {
+                    stats.writer_health(spill_files_pending)
+                };
+                let timestamp = |millis: i64| (millis > 0).then(|| DateTime::from_timestamp_millis(millis)).flatten();
+                serde_json::json!({
+                    "health": health,
+                    "running": running,
+                    "pending_frames": stats.queue_depth.load(Ordering::Relaxed),
+                    "pending_spill_batches": spill_files_pending,
+                    "last_write_at": timestamp(stats.last_insert_at.load(Ordering::Relaxed)),
+                    "last_failure_at": timestamp(stats.last_failure_at.load(Ordering::Relaxed)),
+                    "last_error": stats.last_error.lock().unwrap_or_else(|e| e.into_inner()).clone(),
+                })
+            }
+            None => serde_json::json!({
+                "health": if self.config.frame_storage_enabled { "idle" } else { "disabled" },
+                "running": false,
+                "pending_frames": 0,
+                "pending_spill_batches": 0,
+                "last_write_at": null,
+                "last_failure_at": null,
+                "last_error": null,
+            }),
+        };
+
+        let mp4_storage = camera_config.as_ref()
+            .map_or(self.config.mp4_storage_type.clone(), |config| self.get_storage_type_for_camera(config));
+        let (buffered_frames, segment_open_since) = match self.get_mp4_buffer_stats(camera_id).await {
+            Some(stats) => {
+                let stats = stats.read().await;
+                (stats.frame_count, stats.segment_start)
+            }
+            None => (0, None),
+        };
+
+        serde_json::json!({
+            "camera_id": camera_id,
+            "recording": active.is_some(),
+            "session_id": active.as_ref().map(|r| r.session_id),
+            "started_at": active.as_ref().map(|r| r.start_time),
+            "frame_count": active.as_ref().map_or(0, |r| r.frame_count),
+            "stop_at": active.as_ref().and_then(|r| r.stop_at),
+            "writer": writer,
+            "mp4": {
+                "storage_type": mp4_storage,
+                "buffered_frames": buffered_frames,
+                "segment_open_since": segment_open_since,
+                "queued_segments": pipeline_metrics::get(camera_id).mux_pending(),
+            },
+            "storage_paused": match active.as_ref() {
+                Some(r) => r.storage_pause.to_json(),
+                None => self.get_storage_pause(camera_id).await.to_json(),
+            },
+        })
+    }
+
     /// Get the recording configuration
     pub fn get_recording_config(&self) -> &RecordingConfig {
         &self.config
@@ -299,6 +772,15 @@ impl RecordingManager {
     }
 
     /// Get the database for a specific camera
+    /// Refuse new recordings of a camera while its tenant is over the storage quota (None lifts the block)
+    pub async fn set_quota_exceeded(&self, camera_id: &str, tenant: Option<&str>) {
+        let mut quota_exceeded = self.quota_exceeded.write().await;
+        match tenant {
+            Some(tenant) => { quota_exceeded.insert(camera_id.to_string(), tenant.to_string()); }
+            None => { quota_exceeded.remove(camera_id); }
+        }
+    }
+
     pub async fn get_camera_database(&self, camera_id: &str) -> Option<Arc<dyn DatabaseProvider>> {
         let databases = self.databases.read().await;
         databases.get(camera_id).cloned()
@@ -318,6 +800,18 @@ impl RecordingManager {
             .unwrap_or(self.config.mp4_storage_type.clone())
     }
 
+    /// Get the effective segment container for a camera
+    pub fn get_container_for_camera(&self, camera_config: &crate::config::CameraConfig) -> crate::config::SegmentContainer {
+        camera_config.get_mp4_container().unwrap_or(self.config.mp4_container)
+    }
+
+    /// Get the zstd level for stored frames of a camera, None without compression
+    pub fn get_frame_compression_for_camera(&self, camera_config: &crate::config::CameraConfig) -> Option<i32> {
+        camera_config.get_frame_compression_enabled()
+            .unwrap_or(self.config.frame_compression_enabled)
+            .then_some(self.config.frame_compression_level)
+    }
+
     pub async fn start_recording(
         &self,
         camera_id: &str,
@@ -328,6 +822,10 @@ impl RecordingManager {
         camera_config: &crate::config::CameraConfig,
         pre_recording_buffer: Option<&crate::pre_recording_buffer::PreRecordingBuffer>,
     ) -> crate::errors::Result<i64> {
+        if let Some(tenant) = self.quota_exceeded.read().await.get(camera_id) {
+            return Err(crate::errors::StreamError::config(format!("Storage quota of tenant '{}' is exceeded", tenant)));
+        }
+
         // Get the database for this camera
         let database = self.get_camera_database(camera_id).await
             .ok_or_else(|| crate::errors::StreamError::config(&format!("No database found for camera '{}'", camera_id)))?;
@@ -351,8 +849,11 @@ impl RecordingManager {
 
         // If pre-recording buffer exists, store all buffered frames first using bulk insert
         let mut initial_frame_count = 0u64;
-        if let Some(buffer) = pre_recording_buffer {
+        let mut thumbnail_frame = None;
+        let storage_pause = self.get_storage_pause(camera_id).await;
+        if let Some(buffer) = pre_recording_buffer.filter(|_| !storage_pause.is_paused(StorageKind::Frames)) {
             let buffered_frames = buffer.get_buffered_frames().await;
+            thumbnail_frame = buffered_frames.first().map(|frame| frame.data.clone());
             info!("Adding {} pre-recorded frames to recording session {} using bulk insert", buffered_frames.len(), session_id);
             
             if !buffered_frames.is_empty() {
@@ -363,9 +864,15 @@ impl RecordingManager {
                     .map(|(index, frame)| (frame.timestamp, (index + 1) as i64, frame.data.to_vec()))
                     .collect();
                 
-                match database.add_recorded_frames_bulk(session_id, camera_id, &bulk_frames).await {
+                let compressed = match self.get_frame_compression_for_camera(camera_config) {
+                    Some(level) => Some(frame_compression::compress_batch(&bulk_frames, level).await),
+                    None => None,
+                };
+                let stored_frames = compressed.as_deref().unwrap_or(&bulk_frames);
+                match database.add_recorded_frames_bulk(session_id, camera_id, stored_frames).await {
                     Ok(inserted_count) => {
                         initial_frame_count = inserted_count;
+                        integrity::store_frame_batch_hash(&database, camera_id, &bulk_frames).await;
                         info!("Successfully bulk inserted {} pre-recorded frames for camera '{}'", inserted_count, camera_id);
                     }
                     Err(e) => {
@@ -378,10 +885,11 @@ impl RecordingManager {
                                 camera_id,
                                 buffered_frame.timestamp,
                                 (frame_number + 1) as i64,
-                                &buffered_frame.data,
+                                &stored_frames[frame_number].2,
                             ).await {
                                 error!("Failed to store pre-recorded frame in database: {}", e);
                             } else {
+                                integrity::store_frame_batch_hash(&database, camera_id, std::slice::from_ref(&bulk_frames[frame_number])).await;
                                 initial_frame_count += 1;
                             }
                         }
@@ -397,6 +905,8 @@ impl RecordingManager {
             start_time: recording_start_time,
             frame_count: initial_frame_count,
             requested_duration,
+            storage_pause,
+            stop_at: None,
         };
 
         // Store active recording
@@ -404,6 +914,14 @@ impl RecordingManager {
         active_recordings.insert(camera_id.to_string(), active_recording);
         drop(active_recordings);
 
+        thumbnails::spawn_session_thumbnail(
+            database.clone(),
+            camera_id.to_string(),
+            recording_start_time,
+            thumbnail_frame,
+            frame_sender.subscribe(),
+        );
+
         // Subscribe to frame stream and start recording task
         let frame_receiver = frame_sender.subscribe();
         let mut frame_subscribers = self.frame_subscribers.write().await;
@@ -418,17 +936,21 @@ impl RecordingManager {
     }
 
     async fn frame_recording_loop(
-        config: Arc<RecordingConfig>,
-        database: Arc<dyn DatabaseProvider>,
-        active_recordings: Arc<RwLock<HashMap<String, ActiveRecording>>>,
-        camera_id: String,
+        target: RecordingTarget,
         mut session_id: i64,
         mut frame_receiver: broadcast::Receiver<Bytes>,
         camera_config: crate::config::CameraConfig,
         writer_tx: mpsc::Sender<FrameWriterMessage>,
+        spill_queue: Arc<FrameSpillQueue>,
     ) {
+        let RecordingTarget { config, database, active_recordings, camera_id } = target;
         let mut frame_number = 0i64;
         let mut last_session_check = Utc::now();
+        // Frames held back while the writer channel is full, spilled to disk in batches
+        let mut overflow: Vec<SpilledFrame> = Vec::new();
+        let storage_pause = active_recordings.read().await.get(&camera_id)
+            .map(|r| r.storage_pause.clone())
+            .unwrap_or_default();
 
         // Determine the effective session segment duration
         // Priority: camera-specific setting > global setting
@@ -453,11 +975,13 @@ impl RecordingManager {
             }
         };
 
+        let metrics = pipeline_metrics::get(&camera_id);
         loop {
             match frame_receiver.recv().await {
                 Ok(frame_data) => {
+                    observe_consumer(&config, &database, &camera_id, "frames", &metrics.frames, frame_receiver.len(), 0).await;
                     frame_number += 1;
-                    let timestamp = Utc::now();
+                    let timestamp = crate::clock_drift::capture_time(&camera_id, Utc::now());
 
                     // Check if recording is still active
                     let active_recordings_guard = active_recordings.read().await;
@@ -492,9 +1016,12 @@ impl RecordingManager {
                                         info!("Stopped recording session {} for segment split", session_id);
 
                                         // Create a new session with the same reason
-                                        match database.create_recording_session(&camera_id, reason.as_deref(), Utc::now()).await {
+                                        let new_session_start = Utc::now();
+                                        match database.create_recording_session(&camera_id, reason.as_deref(), new_session_start).await {
                                             Ok(new_session_id) => {
                                                 info!("Created new recording session {} for segment continuation", new_session_id);
+                                                tokio::spawn(thumbnails::store_thumbnail(
+                                                    database.clone(), camera_id.clone(), new_session_start, frame_data.clone()));
 
                                                 // Notify writer about session change
                                                 let _ = writer_tx.send(FrameWriterMessage::SessionChanged {
@@ -525,6 +1052,18 @@ impl RecordingManager {
                         }
                     }
 
+                    if storage_pause.is_paused(StorageKind::Frames) {
+                        // Frame storage paused by an operator: the session keeps running without database writes
+                        let duration_reached = active_recordings.read().await.get(&camera_id)
+                            .and_then(|r| r.requested_duration.map(|d| timestamp.signed_duration_since(r.start_time).num_seconds() >= d))
+                            .unwrap_or(false);
+                        if duration_reached {
+                            info!("Recording duration reached for camera '{}', stopping", camera_id);
+                            break;
+                        }
+                        continue;
+                    }
+
                     // Check frame size
                     if frame_data.len() > config.max_frame_size {
                         error!("Frame size {} exceeds maximum {} for camera '{}'",
@@ -532,23 +1071,47 @@ impl RecordingManager {
                         continue;
                     }
 
-                    // Send frame to writer (non-blocking with try_send for better performance)
-                    match writer_tx.try_send(FrameWriterMessage::Frame {
+                    let frame = SpilledFrame {
                         session_id,
                         timestamp,
                         frame_number,
                         data: frame_data.to_vec(),
-                    }) {
-                        Ok(_) => {}
-                        Err(mpsc::error::TrySendError::Full(_)) => {
-                            // Channel full - writer can't keep up, but we don't block
-                            warn!("Frame writer channel full for camera '{}', dropping frame", camera_id);
-                        }
-                        Err(mpsc::error::TrySendError::Closed(_)) => {
+                    };
+
+                    // Send frame to writer (non-blocking with try_send for better performance).
+                    // This task is the only sender, so remaining capacity can't shrink underneath us.
+                    if overflow.is_empty() && writer_tx.capacity() > 0 {
+                        if writer_tx.try_send(frame.into()).is_err() {
                             error!("Frame writer channel closed for camera '{}'", camera_id);
                             break;
                         }
+                    } else {
+                        if overflow.is_empty() {
+                            warn!("Frame writer channel full for camera '{}', holding frames back", camera_id);
+                        }
+                        overflow.push(frame);
+
+                        if writer_tx.capacity() >= overflow.len() {
+                            // Writer caught up - queue held-back frames in order
+                            let mut closed = false;
+                            for frame in overflow.drain(..) {
+                                if writer_tx.try_send(frame.into()).is_err() {
+                                    closed = true;
+                                }
+                            }
+                            if closed {
+                                error!("Frame writer channel closed for camera '{}'", camera_id);
+                                break;
+                            }
+                        } else if overflow.len() >= BULK_WRITE_MAX_FRAMES {
+                            if let Err(e) = spill_queue.spill(&overflow).await {
+                                error!("Failed to spill {} frames to disk for camera '{}': {}", overflow.len(), camera_id, e);
+                                spill_queue.stats.dropped_frames.fetch_add(overflow.len() as u64, std::sync::atomic::Ordering::Relaxed);
+                            }
+                            overflow.clear();
+                        }
                     }
+                    spill_queue.stats.update_depth(writer_tx.max_capacity() - writer_tx.capacity(), writer_tx.max_capacity());
 
                     // Update frame count (quick operation, acceptable to await)
                     let mut active_recordings_guard = active_recordings.write().await;
@@ -569,6 +1132,7 @@ impl RecordingManager {
                 }
                 Err(broadcast::error::RecvError::Lagged(skipped)) => {
                     warn!("Recording lagged for camera '{}', skipped {} frames", camera_id, skipped);
+                    observe_consumer(&config, &database, &camera_id, "frames", &metrics.frames, frame_receiver.len(), skipped).await;
                 }
                 Err(broadcast::error::RecvError::Closed) => {
                     info!("Frame channel closed for camera '{}', stopping recording", camera_id);
@@ -576,6 +1140,12 @@ impl RecordingManager {
                 }
             }
         }
+        // Keep held-back frames on disk so the writer replays them before exiting
+        if !overflow.is_empty() {
+            if let Err(e) = spill_queue.spill(&overflow).await {
+                error!("Failed to spill {} frames to disk for camera '{}': {}", overflow.len(), camera_id, e);
+            }
+        }
         // Dropping writer_tx will signal the writer to flush and exit
     }
 
@@ -598,10 +1168,20 @@ impl RecordingManager {
         
         // Get the effective video storage type for this camera
         let mp4_storage_type = self.get_storage_type_for_camera(&camera_config);
+        let mp4_container = self.get_container_for_camera(&camera_config);
+        let frame_compression = self.get_frame_compression_for_camera(&camera_config);
         
         // Get MP4 buffer stats for this camera before spawning
         let mp4_stats = self.get_mp4_buffer_stats(&camera_id).await;
 
+        let spill_queue = self.get_frame_spill_queue(&camera_id).await;
+        let target = RecordingTarget {
+            config: config.clone(),
+            database: database.clone(),
+            active_recordings: active_recordings.clone(),
+            camera_id: camera_id.clone(),
+        };
+
         tokio::spawn(async move {
             let mut tasks = Vec::new();
 
@@ -612,35 +1192,32 @@ impl RecordingManager {
                 // Spawn the dedicated database writer task
                 let writer_db = database.clone();
                 let writer_camera_id = camera_id.clone();
+                let writer_spill_queue = spill_queue.clone();
                 let writer_task = tokio::spawn(async move {
-                    frame_writer_loop(writer_db, writer_camera_id, writer_rx).await;
+                    frame_writer_loop(writer_db, writer_camera_id, writer_rx, writer_spill_queue, frame_compression).await;
                 });
                 tasks.push(writer_task);
 
                 // Spawn the frame receiver task (sends to writer via channel)
                 let frame_receiver = frame_sender.subscribe();
                 let receiver_task = tokio::spawn(Self::frame_recording_loop(
-                    config.clone(),
-                    database.clone(),
-                    active_recordings.clone(),
-                    camera_id.clone(),
+                    target.clone(),
                     session_id,
                     frame_receiver,
                     camera_config.clone(),
                     writer_tx,
+                    spill_queue.clone(),
                 ));
                 tasks.push(receiver_task);
             }
 
             if mp4_storage_type != crate::config::Mp4StorageType::Disabled {
                 let segmenter_task = tokio::spawn(Self::video_segmenter_loop(
-                    config.clone(),
-                    database.clone(),
-                    active_recordings.clone(),
-                    camera_id.clone(),
-                    session_id, // Pass session_id
+                    target.clone(),
+                    session_id,
                     frame_sender.subscribe(),
                     mp4_storage_type,
+                    mp4_container,
                     mp4_stats,
                 ));
                 tasks.push(segmenter_task);
@@ -652,10 +1229,7 @@ impl RecordingManager {
             
             if hls_enabled {
                 let hls_task = tokio::spawn(Self::hls_segmenter_loop(
-                    config.clone(),
-                    database.clone(),
-                    active_recordings.clone(),
-                    camera_id.clone(),
+                    target.clone(),
                     session_id,
                     frame_sender.subscribe(),
                     camera_config.clone(),
@@ -702,6 +1276,76 @@ impl RecordingManager {
         }
     }
 
+    /// Effective max duration of manually started recordings of a camera, None without a limit
+    pub fn max_recording_duration(&self, camera_config: &crate::config::CameraConfig) -> Option<chrono::Duration> {
+        let value = camera_config.get_max_recording_duration()
+            .unwrap_or(&self.config.max_recording_duration);
+        if value.is_empty() || value == "0" {
+            return None;
+        }
+        match humantime::parse_duration(value).map(chrono::Duration::from_std) {
+            Ok(Ok(duration)) => Some(duration),
+            _ => {
+                warn!("Invalid max_recording_duration '{}', recordings are not limited", value);
+                None
+            }
+        }
+    }
+
+    /// Stop the active recording of a camera at `stop_at`, None lifts the limit. Returns false without an active recording.
+    pub async fn set_recording_stop_at(self: &Arc<Self>, camera_id: &str, stop_at: Option<DateTime<Utc>>) -> bool {
+        let mut active_recordings = self.active_recordings.write().await;
+        let Some(recording) = active_recordings.get_mut(camera_id) else {
+            return false;
+        };
+        recording.stop_at = stop_at;
+        drop(active_recordings);
+
+        if stop_at.is_some() {
+            self.spawn_stop_watchdog(camera_id.to_string());
+        }
+        true
+    }
+
+    /// Move the stop time of the active recording `by` later. None if there is no recording or it has no stop time.
+    pub async fn extend_recording(&self, camera_id: &str, by: chrono::Duration) -> Option<DateTime<Utc>> {
+        let mut active_recordings = self.active_recordings.write().await;
+        let recording = active_recordings.get_mut(camera_id)?;
+        let stop_at = recording.stop_at?.max(Utc::now()) + by;
+        recording.stop_at = Some(stop_at);
+        info!("Extended recording of camera '{}' (session {}) until {}", camera_id, recording.session_id, stop_at);
+        Some(stop_at)
+    }
+
+    // Stops the recording once its stop time passes. Follows extensions and ends when the
+    // recording is stopped or its limit lifted.
+    fn spawn_stop_watchdog(self: &Arc<Self>, camera_id: String) {
+        let manager = self.clone();
+        tokio::spawn(async move {
+            let recording = loop {
+                let Some(recording) = manager.get_active_recording(&camera_id).await else { return };
+                let Some(stop_at) = recording.stop_at else { return };
+                let remaining = stop_at - Utc::now();
+                if remaining <= chrono::Duration::zero() {
+                    break recording;
+                }
+                tokio::time::sleep(remaining.to_std().unwrap_or_default()).await;
+            };
+
+            info!("Recording of camera '{}' (session {}) reached its max duration, stopping", camera_id, recording.session_id);
+            match manager.stop_recording(&camera_id).await {
+                Ok(true) => {
+                    manager.record_event(&camera_id, "recording", "max duration reached", serde_json::json!({
+                        "session_id": recording.session_id,
+                        "stop_at": recording.stop_at,
+                    })).await;
+                }
+                Ok(false) => {}
+                Err(e) => error!("Failed to stop recording of camera '{}' at its max duration: {}", camera_id, e),
+            }
+        });
+    }
+
     async fn stop_camera_recordings(&self, camera_id: &str) -> crate::errors::Result<()> {
         // Get the database for this camera
         let database = self.get_camera_database(camera_id).await
@@ -770,11 +1414,12 @@ impl RecordingManager {
         from: Option<DateTime<Utc>>,
         to: Option<DateTime<Utc>>,
         reason: Option<&str>,
+        page: &PageRequest,
     ) -> crate::errors::Result<Vec<RecordingSession>> {
         if let Some(cam_id) = camera_id {
             // Query specific camera's database
             if let Some(database) = self.get_camera_database(cam_id).await {
-                database.list_recordings_filtered(cam_id, from, to, reason).await
+                database.list_recordings_filtered(cam_id, from, to, reason, page).await
             } else {
                 Ok(Vec::new()) // No database for this camera
             }
@@ -782,17 +1427,48 @@ impl RecordingManager {
             // Query all camera databases and combine results
             let databases = self.databases.read().await;
             let mut all_recordings = Vec::new();
+            let unpaged = PageRequest { limit: None, offset: 0, oldest_first: page.oldest_first };
             
             for (camera_id, database) in databases.iter() {
-                match database.list_recordings_filtered(camera_id, from, to, reason).await {
+                match database.list_recordings_filtered(camera_id, from, to, reason, &unpaged).await {
                     Ok(recordings) => all_recordings.extend(recordings),
                     Err(e) => error!("Failed to query recordings from database: {}", e),
                 }
             }
             
-            // Sort by start time
+            // Sort by start time, then apply paging across all cameras
             all_recordings.sort_by(|a, b| a.start_time.cmp(&b.start_time));
-            Ok(all_recordings)
+            if !page.oldest_first {
+                all_recordings.reverse();
+            }
+            let offset = page.offset.max(0) as usize;
+            let limit = page.limit.map_or(usize::MAX, |l| l.max(0) as usize);
+            Ok(all_recordings.into_iter().skip(offset).take(limit).collect())
+        }
+    }
+
+    pub async fn count_recordings_filtered(
+        &self,
+        camera_id: Option<&str>,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+        reason: Option<&str>,
+    ) -> crate::errors::Result<i64> {
+        if let Some(cam_id) = camera_id {
+            match self.get_camera_database(cam_id).await {
+                Some(database) => database.count_recordings_filtered(cam_id, from, to, reason).await,
+                None => Ok(0),
+            }
+        } else {
+            let databases = self.databases.read().await;
+            let mut total = 0;
+            for (camera_id, database) in databases.iter() {
+                match database.count_recordings_filtered(camera_id, from, to, reason).await {
+                    Ok(count) => total += count,
+                    Err(e) => error!("Failed to count recordings in database: {}", e),
+                }
+            }
+            Ok(total)
         }
     }
 
@@ -811,6 +1487,92 @@ impl RecordingManager {
         database.create_frame_stream(camera_id, from, end_time).await
     }
 
+    /// Save the last `last_seconds` of the pre-recording buffer plus the next `next_seconds` of
+    /// live frames as a session of its own with a single MP4 segment. Returns the session id and
+    /// start time right away, the segment is written once the live frames have been captured.
+    pub async fn create_clip(
+        &self,
+        camera_id: &str,
+        reason: &str,
+        last_seconds: u64,
+        next_seconds: u64,
+        frame_sender: Arc<broadcast::Sender<Bytes>>,
+        camera_config: &crate::config::CameraConfig,
+        pre_recording_buffer: Option<&crate::pre_recording_buffer::PreRecordingBuffer>,
+    ) -> crate::errors::Result<(i64, DateTime<Utc>)> {
+        if let Some(tenant) = self.quota_exceeded.read().await.get(camera_id) {
+            return Err(crate::errors::StreamError::config(format!("Storage quota of tenant '{}' is exceeded", tenant)));
+        }
+
+        let database = self.get_camera_database(camera_id).await
+            .ok_or_else(|| crate::errors::StreamError::config(format!("No database found for camera '{}'", camera_id)))?;
+
+        let now = Utc::now();
+        let since = now - chrono::Duration::seconds(last_seconds as i64);
+        let buffered_frames = match pre_recording_buffer {
+            Some(buffer) => buffer.get_buffered_frames().await,
+            None => Vec::new(),
+        };
+        let buffered_frames: Vec<_> = buffered_frames.into_iter()
+            .filter(|frame| frame.timestamp >= since)
+            .collect();
+        // Subscribe right after taking the buffered frames, so the live part follows without a gap
+        let mut frame_receiver = frame_sender.subscribe();
+
+        let start_time = buffered_frames.first().map(|frame| frame.timestamp).unwrap_or(now);
+        let session_id = database.create_recording_session(camera_id, Some(reason), start_time).await?;
+        info!("Creating clip for camera '{}' in session {}: {} buffered frames, {}s of live frames",
+              camera_id, session_id, buffered_frames.len(), next_seconds);
+
+        let config = self.config.clone();
+        let camera_id = camera_id.to_string();
+        let mp4_storage_type = self.get_storage_type_for_camera(camera_config);
+        let mp4_container = self.get_container_for_camera(camera_config);
+        let mut frames: Vec<Bytes> = buffered_frames.into_iter().map(|frame| frame.data).collect();
+        tokio::spawn(async move {
+            let deadline = tokio::time::Instant::now() + tokio::time::Duration::from_secs(next_seconds);
+            loop {
+                match tokio::time::timeout_at(deadline, frame_receiver.recv()).await {
+                    Ok(Ok(frame)) => frames.push(frame),
+                    Ok(Err(broadcast::error::RecvError::Lagged(skipped))) => {
+                        warn!("Clip for camera '{}' lagged, skipped {} frames", camera_id, skipped);
+                    }
+                    Ok(Err(broadcast::error::RecvError::Closed)) => {
+                        warn!("Frame channel closed for camera '{}', clip ends early", camera_id);
+                        break;
+                    }
+                    Err(_) => break,
+                }
+            }
+
+            let frame_count = frames.len();
+            if frame_count == 0 {
+                warn!("No frames for clip of camera '{}' in session {}", camera_id, session_id);
+            } else if let Err(e) = Self::create_video_segment(
+                config,
+                database.clone(),
+                PendingSegment {
+                    camera_id: camera_id.clone(),
+                    session_id,
+                    start_time,
+                    end_time: Utc::now(),
+                    frames,
+                },
+                mp4_storage_type,
+                mp4_container,
+            ).await {
+                error!("Failed to create clip segment for camera '{}' session {}: {}", camera_id, session_id, e);
+            } else {
+                info!("Created clip for camera '{}' in session {} with {} frames", camera_id, session_id, frame_count);
+            }
+            if let Err(e) = database.stop_recording_session(session_id).await {
+                error!("Failed to stop clip session {} of camera '{}': {}", session_id, camera_id, e);
+            }
+        });
+
+        Ok((session_id, start_time))
+    }
+
     pub async fn is_recording(&self, camera_id: &str) -> bool {
         let active_recordings = self.active_recordings.read().await;
         active_recordings.contains_key(camera_id)
@@ -860,6 +1622,54 @@ impl RecordingManager {
         Ok(())
     }
     
+    /// Compact camera databases (all, or a single camera) and report reclaimed space
+    pub async fn vacuum_databases(
+        &self,
+        camera_id: Option<&str>,
+        mode: crate::config::VacuumMode,
+    ) -> crate::errors::Result<Vec<VacuumReport>> {
+        // Collect databases first so the map isn't locked while vacuuming
+        let mut targets: Vec<(String, Arc<dyn DatabaseProvider>)> = {
+            let databases = self.databases.read().await;
+            match camera_id {
+                Some(cam_id) => {
+                    let database = databases.get(cam_id).cloned()
+                        .ok_or_else(|| crate::errors::StreamError::not_found(format!("No database found for camera '{}'", cam_id)))?;
+                    vec![(cam_id.to_string(), database)]
+                }
+                None => databases.iter().map(|(id, db)| (id.clone(), db.clone())).collect(),
+            }
+        };
+        targets.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut reports = Vec::with_capacity(targets.len());
+        for (cam_id, database) in targets {
+            let start_time = std::time::Instant::now();
+            let size_before = database.get_database_size().await.unwrap_or(0);
+            info!("Running {} vacuum for camera '{}' database ({} MB)", mode, cam_id, size_before / (1024 * 1024));
+
+            let error = match database.compact_database(mode).await {
+                Ok(()) => None,
+                Err(e) => {
+                    error!("Failed to vacuum database for camera '{}': {}", cam_id, e);
+                    Some(e.to_string())
+                }
+            };
+
+            let size_after = database.get_database_size().await.unwrap_or(size_before);
+            reports.push(VacuumReport {
+                camera_id: cam_id,
+                size_before_bytes: size_before,
+                size_after_bytes: size_after,
+                reclaimed_bytes: (size_before - size_after).max(0),
+                duration_ms: start_time.elapsed().as_millis() as u64,
+                error,
+            });
+        }
+
+        Ok(reports)
+    }
+
     pub async fn get_frame_at_timestamp(
         &self,
         camera_id: &str,
@@ -875,7 +1685,7 @@ impl RecordingManager {
 
     /// Check for active recordings at startup and restart them
     pub async fn restart_active_recordings_at_startup(
-        &self,
+        self: &Arc<Self>,
         camera_frame_senders: &HashMap<String, Arc<broadcast::Sender<Bytes>>>,
         camera_configs: &HashMap<String, crate::config::CameraConfig>,
     ) -> crate::errors::Result<()> {
@@ -941,6 +1751,8 @@ impl RecordingManager {
                             start_time: session.start_time,
                             frame_count: 0, // Will be updated as new frames come in
                             requested_duration: None, // Not tracked for restarted sessions
+                            storage_pause: self.get_storage_pause(camera_id).await,
+                            stop_at: None,
                         };
 
                         // Store active recording
@@ -957,6 +1769,11 @@ impl RecordingManager {
                         // Start recording task
                         if let Some(camera_config) = camera_configs.get(camera_id) {
                             self.start_recording_task(camera_id.clone(), session.session_id, frame_sender.clone(), camera_config.clone()).await;
+
+                            // The stop time of the previous run is not persisted, the limit starts over
+                            if let Some(limit) = self.max_recording_duration(camera_config) {
+                                self.set_recording_stop_at(camera_id, Some(Utc::now() + limit)).await;
+                            }
                         } else {
                             error!("Camera config not found for camera '{}', skipping recording restart", camera_id);
                             continue;
@@ -1021,11 +1838,10 @@ impl RecordingManager {
         from: Option<DateTime<Utc>>,
         to: Option<DateTime<Utc>>,
         reason: Option<&str>,
-        limit: i64,
-        sort_order: &str,
+        page: &PageRequest,
     ) -> crate::errors::Result<Vec<VideoSegment>> {
         if let Some(database) = self.get_camera_database(camera_id).await {
-            database.list_video_segments_filtered(camera_id, from, to, reason, limit, sort_order).await
+            database.list_video_segments_filtered(camera_id, from, to, reason, page).await
         } else {
             Err(crate::errors::StreamError::database(format!(
                 "No database found for camera '{}'", camera_id
@@ -1033,20 +1849,66 @@ impl RecordingManager {
         }
     }
 
+    pub async fn count_video_segments_filtered(
+        &self,
+        camera_id: &str,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+        reason: Option<&str>,
+    ) -> crate::errors::Result<i64> {
+        if let Some(database) = self.get_camera_database(camera_id).await {
+            database.count_video_segments_filtered(camera_id, from, to, reason).await
+        } else {
+            Err(crate::errors::StreamError::database(format!(
+                "No database found for camera '{}'", camera_id
+            )))
+        }
+    }
+
+    /// Store an event for the playback timeline. Events are best effort, failures are only logged.
+    pub async fn record_event(&self, camera_id: &str, event_type: &str, label: &str, data: serde_json::Value) {
+        let Some(database) = self.get_camera_database(camera_id).await else { return };
+        let event = RecordingEvent {
+            id: 0,
+            camera_id: camera_id.to_string(),
+            timestamp: Utc::now(),
+            event_type: event_type.to_string(),
+            label: label.to_string(),
+            data,
+        };
+        if let Err(e) = database.add_recording_event(&event).await {
+            warn!("Failed to store {} event for camera '{}': {}", event_type, camera_id, e);
+        }
+    }
+
+    pub async fn list_events(
+        &self,
+        camera_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> crate::errors::Result<Vec<RecordingEvent>> {
+        if let Some(database) = self.get_camera_database(camera_id).await {
+            database.list_recording_events(camera_id, from, to).await
+        } else {
+            Err(crate::errors::StreamError::database(format!(
+                "No database found for camera '{}'", camera_id
+            )))
+        }
+    }
+
     pub fn get_recordings_path(&self) -> &str {
         &self.config.database_path
     }
 
     async fn video_segmenter_loop(
-        config: Arc<RecordingConfig>,
-        database: Arc<dyn DatabaseProvider>,
-        active_recordings: Arc<RwLock<HashMap<String, ActiveRecording>>>,
-        camera_id: String,
-        session_id: i64, // Add session_id parameter
+        target: RecordingTarget,
+        session_id: i64,
         mut frame_receiver: broadcast::Receiver<Bytes>,
         mp4_storage_type: crate::config::Mp4StorageType,
+        mp4_container: crate::config::SegmentContainer,
         mp4_buffer_stats: Option<Arc<tokio::sync::RwLock<crate::Mp4BufferStats>>>,
     ) {
+        let RecordingTarget { config, database, active_recordings, camera_id } = target;
         let segment_duration = chrono::Duration::minutes(config.mp4_segment_minutes as i64);
         
         // Get recording start time (which may include pre-recorded frames)
@@ -1063,6 +1925,16 @@ impl RecordingManager {
         // Track current session_id - may change due to session segmentation
         let mut current_session_id = session_id;
 
+        let storage_pause = active_recordings.read().await.get(&camera_id)
+            .map(|r| r.storage_pause.clone())
+            .unwrap_or_default();
+        let mut was_paused = false;
+
+        // MKV segments on the filesystem are written while recording instead of from a buffer
+        let mut live_segments = mp4_container == crate::config::SegmentContainer::Mkv
+            && mp4_storage_type == crate::config::Mp4StorageType::Filesystem;
+        let mut live: Option<LiveSegment> = None;
+
         // Process any pre-recorded frames first if they exist
         if let Some(active_recording) = active_recordings.read().await.get(&camera_id) {
             if active_recording.frame_count > 0 {
@@ -1092,6 +1964,7 @@ impl RecordingManager {
                                 let mut stats = stats.write().await;
                                 stats.frame_count = frame_buffer.len();
                                 stats.size_bytes = buffer_size;
+                                stats.segment_start = Some(segment_start_time);
                             }
                             
                             info!("Added {} pre-recorded frames to MP4 segment buffer", frame_buffer.len());
@@ -1104,12 +1977,18 @@ impl RecordingManager {
             }
         }
 
+        let metrics = pipeline_metrics::get(&camera_id);
         loop {
             match frame_receiver.recv().await {
                 Ok(frame_data) => {
+                    observe_consumer(&config, &database, &camera_id, "mp4", &metrics.mp4, frame_receiver.len(), 0).await;
                     // Check if recording is still active
                     if !active_recordings.read().await.contains_key(&camera_id) {
                         trace!("Recording stopped for camera '{}', ending segmenter task", camera_id);
+
+                        if let Some(live) = live.take() {
+                            spawn_finish_live_segment(live, database.clone(), Utc::now(), metrics.clone());
+                        }
                         
                         // Flush remaining frames in buffer before stopping
                         if !frame_buffer.is_empty() {
@@ -1122,6 +2001,7 @@ impl RecordingManager {
                                 let mut stats = stats.write().await;
                                 stats.frame_count = 0;
                                 stats.size_bytes = 0;
+                                stats.segment_start = None;
                             }
 
                             // Spawn a task to process the final segment with current session_id
@@ -1131,17 +2011,25 @@ impl RecordingManager {
                             let final_session_id = current_session_id;
                             let final_storage_type = mp4_storage_type.clone();
                             let log_camera_id = camera_id.clone(); // Clone for logging
+                            let final_metrics = metrics.clone();
+                            final_metrics.mux_queued();
                             tokio::spawn(async move {
-                                if let Err(e) = Self::create_video_segment(
+                                let mux_start = std::time::Instant::now();
+                                let result = Self::create_video_segment(
                                     final_config,
                                     final_database,
-                                    final_camera_id,
-                                    final_session_id,
-                                    segment_start_time,
-                                    end_time,
-                                    frames_to_process,
+                                    PendingSegment {
+                                        camera_id: final_camera_id,
+                                        session_id: final_session_id,
+                                        start_time: segment_start_time,
+                                        end_time,
+                                        frames: frames_to_process,
+                                    },
                                     final_storage_type,
-                                ).await {
+                                    mp4_container,
+                                ).await;
+                                final_metrics.mux_finished(mux_start.elapsed());
+                                if let Err(e) = result {
                                     error!("Failed to create final video segment on recording stop: {}", e);
                                 } else {
                                     info!("Successfully created final video segment on recording stop for camera '{}'", log_camera_id);
@@ -1151,17 +2039,131 @@ impl RecordingManager {
                         break;
                     }
 
-                    frame_buffer.push(frame_data);
+                    if live_segments {
+                        // Pausing ends the live segment, resuming starts a new one
+                        if storage_pause.is_paused(StorageKind::Mp4) {
+                            was_paused = true;
+                            if let Some(live) = live.take() {
+                                spawn_finish_live_segment(live, database.clone(), Utc::now(), metrics.clone());
+                            }
+                            continue;
+                        }
+                        if was_paused {
+                            was_paused = false;
+                            segment_start_time = Utc::now();
+                        }
+
+                        if live.is_none() {
+                            // Pre-recorded frames lie in the past, they become a buffered segment of their own
+                            if !frame_buffer.is_empty() {
+                                let frames_to_process = std::mem::take(&mut frame_buffer);
+                                let end_time = Utc::now();
+                                let task_config = config.clone();
+                                let task_database = database.clone();
+                                let task_camera_id = camera_id.clone();
+                                let task_session_id = current_session_id;
+                                let task_storage_type = mp4_storage_type.clone();
+                                let task_metrics = metrics.clone();
+                                task_metrics.mux_queued();
+                                tokio::spawn(async move {
+                                    let mux_start = std::time::Instant::now();
+                                    let result = Self::create_video_segment(
+                                        task_config,
+                                        task_database,
+                                        PendingSegment {
+                                            camera_id: task_camera_id,
+                                            session_id: task_session_id,
+                                            start_time: segment_start_time,
+                                            end_time,
+                                            frames: frames_to_process,
+                                        },
+                                        task_storage_type,
+                                        mp4_container,
+                                    ).await;
+                                    task_metrics.mux_finished(mux_start.elapsed());
+                                    if let Err(e) = result {
+                                        error!("Failed to create video segment of pre-recorded frames: {}", e);
+                                    }
+                                });
+                                segment_start_time = end_time;
+                            }
 
-                    // Update MP4 buffer stats
-                    if let Some(ref stats) = mp4_buffer_stats {
-                        let buffer_size = frame_buffer.iter().map(|f| f.len()).sum::<usize>();
-                        let mut stats = stats.write().await;
-                        stats.frame_count = frame_buffer.len();
-                        stats.size_bytes = buffer_size;
+                            match LiveSegment::start(&config, &database, &camera_id, current_session_id, segment_start_time).await {
+                                Ok(started) => live = Some(started),
+                                Err(e) => {
+                                    error!("Failed to start live video segment for camera '{}', buffering segments instead: {}", camera_id, e);
+                                    live_segments = false;
+                                }
+                            }
+                        }
                     }
 
-                    if Utc::now().signed_duration_since(segment_start_time) >= segment_duration {
+                    if let Some(ref mut segment) = live {
+                        if let Err(e) = segment.write(&database, &frame_data).await {
+                            error!("Failed to write frame to live video segment of camera '{}': {}", camera_id, e);
+                            let end_time = Utc::now();
+                            if let Some(live) = live.take() {
+                                spawn_finish_live_segment(live, database.clone(), end_time, metrics.clone());
+                            }
+                            segment_start_time = end_time;
+                            continue;
+                        }
+
+                        if let Some(ref stats) = mp4_buffer_stats {
+                            let mut stats = stats.write().await;
+                            stats.frame_count = segment.frame_count;
+                            stats.size_bytes = segment.bytes_written;
+                            stats.segment_start = Some(segment_start_time);
+                        }
+
+                        if Utc::now().signed_duration_since(segment_start_time) >= segment_duration {
+                            let end_time = Utc::now();
+                            if let Some(live) = live.take() {
+                                spawn_finish_live_segment(live, database.clone(), end_time, metrics.clone());
+                            }
+
+                            // Check if session has changed (due to session segmentation)
+                            let new_session_id = active_recordings.read().await
+                                .get(&camera_id)
+                                .map(|r| r.session_id)
+                                .unwrap_or(current_session_id);
+                            if new_session_id != current_session_id {
+                                info!("MP4 segmenter detected session change {} -> {} for camera '{}'",
+                                      current_session_id, new_session_id, camera_id);
+                                current_session_id = new_session_id;
+                            }
+
+                            segment_start_time = end_time;
+                        }
+                        continue;
+                    }
+
+                    // While MP4 storage is paused the frames collected so far are written as a
+                    // shorter segment and new frames are dropped; resuming starts a new segment
+                    let paused = storage_pause.is_paused(StorageKind::Mp4);
+                    if paused {
+                        was_paused = true;
+                        if frame_buffer.is_empty() {
+                            continue;
+                        }
+                    } else {
+                        if was_paused {
+                            was_paused = false;
+                            segment_start_time = Utc::now();
+                        }
+                        frame_buffer.push(frame_data);
+
+                        // Update MP4 buffer stats
+                        if let Some(ref stats) = mp4_buffer_stats {
+                            let buffer_size = frame_buffer.iter().map(|f| f.len()).sum::<usize>();
+                            let mut stats = stats.write().await;
+                            stats.frame_count = frame_buffer.len();
+                            stats.size_bytes = buffer_size;
+                            stats.segment_start = Some(segment_start_time);
+                        }
+                    }
+
+                    if paused || Utc::now().signed_duration_since(segment_start_time) >= segment_duration {
                         let frames_to_process = std::mem::take(&mut frame_buffer);
 
                         // Update buffer stats after taking frames
@@ -1170,6 +2172,7 @@ impl RecordingManager {
                             let mut stats = stats.write().await;
                             stats.frame_count = frame_buffer.len();
                             stats.size_bytes = buffer_size;
+                            stats.segment_start = None;
                         }
                         let end_time = Utc::now();
 
@@ -1191,17 +2194,25 @@ impl RecordingManager {
                         let task_camera_id = camera_id.clone();
                         let task_session_id = current_session_id;
                         let task_storage_type = mp4_storage_type.clone();
+                        let task_metrics = metrics.clone();
+                        task_metrics.mux_queued();
                         tokio::spawn(async move {
-                            if let Err(e) = Self::create_video_segment(
+                            let mux_start = std::time::Instant::now();
+                            let result = Self::create_video_segment(
                                 task_config,
                                 task_database,
-                                task_camera_id,
-                                task_session_id,
-                                segment_start_time,
-                                end_time,
-                                frames_to_process,
+                                PendingSegment {
+                                    camera_id: task_camera_id,
+                                    session_id: task_session_id,
+                                    start_time: segment_start_time,
+                                    end_time,
+                                    frames: frames_to_process,
+                                },
                                 task_storage_type,
-                            ).await {
+                                mp4_container,
+                            ).await;
+                            task_metrics.mux_finished(mux_start.elapsed());
+                            if let Err(e) = result {
                                 error!("Failed to create video segment: {}", e);
                             }
                         });
@@ -1211,6 +2222,7 @@ impl RecordingManager {
                 }
                 Err(broadcast::error::RecvError::Lagged(skipped)) => {
                     warn!("Video segmenter lagged for camera '{}', skipped {} frames", camera_id, skipped);
+                    observe_consumer(&config, &database, &camera_id, "mp4", &metrics.mp4, frame_receiver.len(), skipped).await;
                 }
                 Err(broadcast::error::RecvError::Closed) => {
                     info!("Frame channel closed for camera '{}', stopping video segmenter", camera_id);
@@ -1218,41 +2230,51 @@ impl RecordingManager {
                 }
             }
         }
+
+        if let Some(live) = live.take() {
+            spawn_finish_live_segment(live, database.clone(), Utc::now(), metrics.clone());
+        }
     }
 
     async fn create_video_segment(
         config: Arc<RecordingConfig>,
         database: Arc<dyn DatabaseProvider>,
-        camera_id: String,  // Only needed for filesystem path
-        session_id: i64,
-        start_time: DateTime<Utc>,
-        end_time: DateTime<Utc>,
-        frames: Vec<Bytes>,
+        segment: PendingSegment,
         mp4_storage_type: crate::config::Mp4StorageType,
+        mp4_container: crate::config::SegmentContainer,
     ) -> crate::errors::Result<()> {
+        let PendingSegment { camera_id, session_id, start_time, end_time, frames } = segment;
         if frames.is_empty() {
             return Ok(());
         }
 
+        // The first frame becomes the segment's first keyframe and its thumbnail
+        let thumbnail_frame = frames[0].clone();
+
         // Create video segment based on storage type
-        if mp4_storage_type == crate::config::Mp4StorageType::Database {
+        let result = if mp4_storage_type == crate::config::Mp4StorageType::Database {
             // Store MP4 data in database as BLOB
-            Self::create_database_video_segment(config.clone(), database, camera_id, session_id, start_time, end_time, frames).await
+            Self::create_database_video_segment(config.clone(), database.clone(), camera_id.clone(), session_id, start_time, end_time, frames, mp4_container).await
         } else {
             // Store MP4 file on filesystem
-            Self::create_filesystem_video_segment(config.clone(), database, camera_id, session_id, start_time, end_time, frames).await
+            Self::create_filesystem_video_segment(config.clone(), database.clone(), camera_id.clone(), session_id, start_time, end_time, frames, mp4_container).await
+        };
+
+        if result.is_ok() {
+            thumbnails::store_thumbnail(database, camera_id, start_time, thumbnail_frame).await;
         }
+        result
     }
 
-    async fn create_filesystem_video_segment(
-        config: Arc<RecordingConfig>,
-        database: Arc<dyn DatabaseProvider>,
-        camera_id: String,
+    /// File of a segment in the hierarchical recordings directory, which is created if needed
+    async fn segment_file_path(
+        config: &RecordingConfig,
+        database: &Arc<dyn DatabaseProvider>,
+        camera_id: &str,
         session_id: i64,
         start_time: DateTime<Utc>,
-        end_time: DateTime<Utc>,
-        frames: Vec<Bytes>,
-    ) -> crate::errors::Result<()> {
+        container: crate::config::SegmentContainer,
+    ) -> crate::errors::Result<String> {
         let recordings_dir = config.get_mp4_storage_path();
 
         // Create hierarchical directory structure: recordings/cam1/2025/08/19/
@@ -1288,7 +2310,20 @@ impl RecordingManager {
             iso_timestamp.to_string()
         };
 
-        let file_path = format!("{}/{}.mp4", camera_dir, filename_stem);
+        Ok(format!("{}/{}.{}", camera_dir, filename_stem, container.extension()))
+    }
+
+    async fn create_filesystem_video_segment(
+        config: Arc<RecordingConfig>,
+        database: Arc<dyn DatabaseProvider>,
+        camera_id: String,
+        session_id: i64,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        frames: Vec<Bytes>,
+        container: crate::config::SegmentContainer,
+    ) -> crate::errors::Result<()> {
+        let file_path = Self::segment_file_path(&config, &database, &camera_id, session_id, start_time, container).await?;
 
         // Calculate actual framerate from frame count and duration
         let duration_secs = (end_time - start_time).num_milliseconds() as f32 / 1000.0;
@@ -1300,10 +2335,10 @@ impl RecordingManager {
             10.0 // Fallback - should rarely happen
         };
 
-        debug!("Creating MP4 segment for camera '{}': {} frames over {:.2}s = {:.2} FPS",
-               camera_id, frames.len(), duration_secs, actual_framerate);
+        debug!("Creating {} segment for camera '{}': {} frames over {:.2}s = {:.2} FPS",
+               container, camera_id, frames.len(), duration_secs, actual_framerate);
 
-        let mp4_data = Self::create_mp4_from_frames(frames, actual_framerate).await?;
+        let mp4_data = Self::create_mp4_from_frames(frames, actual_framerate, container).await?;
         
         // Write MP4 data to file
         tokio::fs::write(&file_path, &mp4_data).await?;
@@ -1320,6 +2355,7 @@ impl RecordingManager {
         };
 
         database.add_video_segment(&segment).await?;
+        integrity::store_mp4_hash(&database, &segment, &mp4_data).await;
         Ok(())
     }
 
@@ -1331,6 +2367,7 @@ impl RecordingManager {
         start_time: DateTime<Utc>,
         end_time: DateTime<Utc>,
         frames: Vec<Bytes>,
+        container: crate::config::SegmentContainer,
     ) -> crate::errors::Result<()> {
         // Calculate actual framerate from frame count and duration
         let duration_secs = (end_time - start_time).num_milliseconds() as f32 / 1000.0;
@@ -1342,10 +2379,10 @@ impl RecordingManager {
             10.0 // Fallback - should rarely happen
         };
 
-        debug!("Creating MP4 segment for camera '{}': {} frames over {:.2}s = {:.2} FPS",
-               camera_id, frames.len(), duration_secs, actual_framerate);
+        debug!("Creating {} segment for camera '{}': {} frames over {:.2}s = {:.2} FPS",
+               container, camera_id, frames.len(), duration_secs, actual_framerate);
 
-        let mp4_data = Self::create_mp4_from_frames(frames, actual_framerate).await?;
+        let mp4_data = Self::create_mp4_from_frames(frames, actual_framerate, container).await?;
         
         let segment = VideoSegment {
             camera_id: camera_id.clone(),
@@ -1359,10 +2396,13 @@ impl RecordingManager {
         };
 
         database.add_video_segment(&segment).await?;
+        if let Some(ref mp4_data) = segment.mp4_data {
+            integrity::store_mp4_hash(&database, &segment, mp4_data).await;
+        }
         Ok(())
     }
     
-    async fn create_mp4_from_frames(frames: Vec<Bytes>, framerate: f32) -> crate::errors::Result<Vec<u8>> {
+    async fn create_mp4_from_frames(frames: Vec<Bytes>, framerate: f32, container: crate::config::SegmentContainer) -> crate::errors::Result<Vec<u8>> {
         let mut cmd = Command::new("ffmpeg");
         cmd.args([
             "-f", "mjpeg",
@@ -1371,10 +2411,20 @@ impl RecordingManager {
             "-c:v", "libx264",
             "-preset", "ultrafast",
             // No output framerate - use same as input
-            "-f", "mp4", // Output format
-            "-movflags", "frag_keyframe+empty_moov", // Enable streaming-friendly MP4
-            "-", // Output to stdout
         ]);
+        match container {
+            crate::config::SegmentContainer::Mp4 => {
+                cmd.args([
+                    "-f", "mp4", // Output format
+                    "-movflags", "frag_keyframe+empty_moov", // Enable streaming-friendly MP4
+                ]);
+            }
+            crate::config::SegmentContainer::Mkv => {
+                // Matroska needs no index at the end, a truncated file still plays
+                cmd.args(["-f", "matroska"]);
+            }
+        }
+        cmd.arg("-"); // Output to stdout
         cmd.stdin(std::process::Stdio::piped());
         cmd.stdout(std::process::Stdio::piped());
         cmd.stderr(std::process::Stdio::null());
@@ -1414,19 +2464,20 @@ impl RecordingManager {
     }
 
     async fn hls_segmenter_loop(
-        config: Arc<RecordingConfig>,
-        database: Arc<dyn DatabaseProvider>,
-        active_recordings: Arc<RwLock<HashMap<String, ActiveRecording>>>,
-        camera_id: String,
+        target: RecordingTarget,
         session_id: i64,
         mut frame_receiver: broadcast::Receiver<Bytes>,
         camera_config: crate::config::CameraConfig,
     ) {
+        let RecordingTarget { config, database, active_recordings, camera_id } = target;
         // Get HLS segment duration (default 6 seconds)
         let segment_seconds = camera_config.get_hls_segment_seconds()
             .unwrap_or(config.hls_segment_seconds);
         
         let segment_duration = chrono::Duration::seconds(segment_seconds as i64);
+        let proxy_enabled = camera_config.get_hls_proxy_enabled()
+            .unwrap_or(config.hls_proxy_enabled);
+        let encryption_enabled = crate::hls_crypto::is_encryption_enabled(&camera_config, &config);
         
         // Get recording start time (which may include pre-recorded frames)
         let mut segment_start_time = {
@@ -1457,6 +2508,11 @@ impl RecordingManager {
         // Track current session_id - may change due to session segmentation
         let mut current_session_id = session_id;
 
+        let storage_pause = active_recordings.read().await.get(&camera_id)
+            .map(|r| r.storage_pause.clone())
+            .unwrap_or_default();
+        let mut was_paused = false;
+
         // Process any pre-recorded frames first if they exist
         if let Some(active_recording) = active_recordings.read().await.get(&camera_id) {
             if active_recording.frame_count > 0 {
@@ -1515,12 +2571,16 @@ impl RecordingManager {
                                 if let Err(e) = Self::create_hls_segment(
                                     final_config,
                                     final_database,
-                                    final_camera_id,
-                                    final_session_id,
+                                    PendingSegment {
+                                        camera_id: final_camera_id,
+                                        session_id: final_session_id,
+                                        start_time: segment_start_time,
+                                        end_time,
+                                        frames: frames_to_process,
+                                    },
                                     final_segment_index,
-                                    segment_start_time,
-                                    end_time,
-                                    frames_to_process,
+                                    proxy_enabled,
+                                    encryption_enabled,
                                 ).await {
                                     error!("Failed to create final HLS segment on recording stop: {}", e);
                                 } else {
@@ -1531,10 +2591,23 @@ impl RecordingManager {
                         break;
                     }
 
-                    frame_buffer.push(frame_data);
+                    // Same as MP4: pausing closes the current segment, resuming starts a new one
+                    let paused = storage_pause.is_paused(StorageKind::Hls);
+                    if paused {
+                        was_paused = true;
+                        if frame_buffer.is_empty() {
+                            continue;
+                        }
+                    } else {
+                        if was_paused {
+                            was_paused = false;
+                            segment_start_time = Utc::now();
+                        }
+                        frame_buffer.push(frame_data);
+                    }
 
                     let elapsed = Utc::now().signed_duration_since(segment_start_time);
-                    if elapsed >= segment_duration {
+                    if paused || elapsed >= segment_duration {
                         let frames_to_process = std::mem::take(&mut frame_buffer);
                         let end_time = Utc::now();
 
@@ -1563,12 +2636,16 @@ impl RecordingManager {
                             if let Err(e) = Self::create_hls_segment(
                                 task_config,
                                 task_database,
-                                task_camera_id,
-                                task_session_id,
+                                PendingSegment {
+                                    camera_id: task_camera_id,
+                                    session_id: task_session_id,
+                                    start_time: current_start_time,
+                                    end_time,
+                                    frames: frames_to_process,
+                                },
                                 current_segment_index,
-                                current_start_time,
-                                end_time,
-                                frames_to_process,
+                                proxy_enabled,
+                                encryption_enabled,
                             ).await {
                                 error!("Failed to create HLS segment: {}", e);
                             }
@@ -1594,13 +2671,12 @@ impl RecordingManager {
     async fn create_hls_segment(
         config: Arc<RecordingConfig>,
         database: Arc<dyn DatabaseProvider>,
-        camera_id: String,
-        session_id: i64,
+        segment: PendingSegment,
         segment_index: i32,
-        start_time: DateTime<Utc>,
-        end_time: DateTime<Utc>,
-        frames: Vec<Bytes>,
+        proxy_enabled: bool,
+        encryption_enabled: bool,
     ) -> crate::errors::Result<()> {
+        let PendingSegment { camera_id, session_id, start_time, end_time, frames } = segment;
         if frames.is_empty() {
             return Ok(());
         }
@@ -1622,13 +2698,44 @@ impl RecordingManager {
                segment_index, camera_id, frames.len(), duration_secs, actual_framerate);
 
         // Convert frames to MPEG-TS segment using FFmpeg
-        let segment_data = Self::create_hls_segment_from_frames(config.clone(), frames, actual_framerate).await?;
+        let proxy_frames = if proxy_enabled { Some(frames.clone()) } else { None };
+        let segment_data = Self::create_hls_segment_from_frames(config.clone(), frames, actual_framerate, false).await?;
         
         if segment_data.is_empty() {
             warn!("Generated empty HLS segment for camera '{}' segment {}", camera_id, segment_index);
             return Ok(());
         }
 
+        // The segment is still stored with only the original rendition if the proxy fails
+        let proxy_data = match proxy_frames {
+            Some(proxy_frames) => match Self::create_hls_segment_from_frames(config.clone(), proxy_frames, actual_framerate, true).await {
+                Ok(proxy_data) if !proxy_data.is_empty() => Some(proxy_data),
+                Ok(_) => {
+                    warn!("Generated empty HLS proxy segment for camera '{}' segment {}", camera_id, segment_index);
+                    None
+                }
+                Err(e) => {
+                    warn!("Failed to create HLS proxy segment {} for camera '{}': {}", segment_index, camera_id, e);
+                    None
+                }
+            },
+            None => None,
+        };
+
+        // Segments are stored encrypted, the playlist announces the key and IV of each rendition
+        let (segment_data, proxy_data, key_id) = if encryption_enabled {
+            let key = crate::hls_crypto::current_key(&config, &camera_id)?;
+            let segment_data = crate::hls_crypto::encrypt_segment(&key, &session_id.to_string(), segment_index as i64, &segment_data)?;
+            let proxy_data = match proxy_data {
+                Some(proxy_data) => Some(crate::hls_crypto::encrypt_segment(&key, &format!("{}_proxy", session_id), segment_index as i64, &proxy_data)?),
+                None => None,
+            };
+            (segment_data, proxy_data, Some(key.id))
+        } else {
+            (segment_data, proxy_data, None)
+        };
+        let proxy_size_bytes = proxy_data.as_ref().map(|data| data.len() as i64);
+
         // Calculate segment duration in seconds
         let duration_seconds = (end_time.timestamp_millis() - start_time.timestamp_millis()) as f64 / 1000.0;
         let size_bytes = segment_data.len() as i64;
@@ -1644,6 +2751,9 @@ impl RecordingManager {
             segment_data,
             size_bytes,
             created_at: Utc::now(),
+            proxy_data,
+            proxy_size_bytes,
+            key_id,
         };
 
         // Store segment in database with better error handling
@@ -1669,9 +2779,10 @@ impl RecordingManager {
     }
 
     async fn create_hls_segment_from_frames(
-        _config: Arc<RecordingConfig>,
+        config: Arc<RecordingConfig>,
         frames: Vec<Bytes>,
         framerate: f32,
+        proxy: bool,
     ) -> crate::errors::Result<Vec<u8>> {
         use tokio::process::Command;
 
@@ -1684,6 +2795,18 @@ impl RecordingManager {
             "-i", "-", // Input from stdin
             "-c:v", "libx264", // H.264 codec
             "-preset", "ultrafast", // Fast encoding
+        ]);
+        if proxy {
+            // Low-bitrate rendition, scaled down (never up) with an even width as required by libx264
+            let bitrate = format!("{}k", config.hls_proxy_bitrate_kbps);
+            cmd.args([
+                "-vf", &format!("scale=-2:'min(ih,{})'", config.hls_proxy_height),
+                "-b:v", &bitrate,
+                "-maxrate", &bitrate,
+                "-bufsize", &format!("{}k", config.hls_proxy_bitrate_kbps * 2),
+            ]);
+        }
+        cmd.args([
             "-f", "mpegts", // MPEG-TS format for HLS
             "-", // Output to stdout
         ]);
@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveTime, TimeZone, Utc};
+use tokio::time::{Duration, interval};
+use tracing::{error, info};
+
+use crate::config::{CameraConfig, RecordingWindow};
+use crate::recording::RecordingManager;
+use crate::AppState;
+
+// How often the schedules are checked
+const CHECK_INTERVAL_SECS: u64 = 10;
+
+// Cameras stay awake (or are woken) this long before a window opens, so capture runs when it does
+const WAKE_AHEAD_SECS: i64 = 120;
+
+/// Reason of sessions recorded by the schedule
+pub const REASON: &str = "scheduled";
+
+/// Parse a window's "HH:MM" time
+pub fn parse_time(time: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(time, "%H:%M").ok()
+}
+
+// Start and end of the window if it starts on `date`
+fn window_bounds(window: &RecordingWindow, date: NaiveDate) -> Option<(DateTime<Local>, DateTime<Local>)> {
+    if !window.days.is_empty() && !window.days.contains(&date.weekday()) {
+        return None;
+    }
+    let (start, end) = (parse_time(&window.start)?, parse_time(&window.end)?);
+    let end_date = if end <= start { date.succ_opt()? } else { date };
+    let start = Local.from_local_datetime(&date.and_time(start)).earliest()?;
+    let end = Local.from_local_datetime(&end_date.and_time(end)).earliest()?;
+    Some((start, end))
+}
+
+// Windows of the camera starting between yesterday and tomorrow
+fn nearby_windows(camera_config: &CameraConfig, now: DateTime<Local>) -> impl Iterator<Item = (DateTime<Local>, DateTime<Local>)> + '_ {
+    let today = now.date_naive();
+    let days = [today.pred_opt(), Some(today), today.succ_opt()];
+    camera_config.get_recording_schedule().iter()
+        .flat_map(move |window| days.into_iter().flatten().filter_map(move |date| window_bounds(window, date)))
+}
+
+/// End of the latest schedule window `now` lies in
+pub fn window_end(camera_config: &CameraConfig, now: DateTime<Local>) -> Option<DateTime<Local>> {
+    nearby_windows(camera_config, now)
+        .filter(|(start, end)| *start <= now && now < *end)
+        .map(|(_, end)| end)
+        .max()
+}
+
+/// Whether a schedule window is open or about to open, which keeps the camera out of standby
+pub fn keeps_awake(camera_config: &CameraConfig, now: DateTime<Local>) -> bool {
+    let ahead = chrono::Duration::seconds(WAKE_AHEAD_SECS);
+    nearby_windows(camera_config, now).any(|(start, end)| start - ahead <= now && now < end)
+}
+
+/// Start the task that records cameras during the windows of their `recording.schedule`.
+/// Each window starts one recording that stops at the window's end; a recording that is
+/// already running is left alone, and one stopped early is not restarted in the same window.
+pub fn start_monitor(state: AppState) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let Some(recording_manager) = state.recording_manager.clone() else {
+            return;
+        };
+        let mut interval = interval(Duration::from_secs(CHECK_INTERVAL_SECS));
+        // Camera id -> end of the window a recording was started for
+        let mut started: HashMap<String, DateTime<Local>> = HashMap::new();
+        loop {
+            interval.tick().await;
+            check_schedules(&state, &recording_manager, &mut started).await;
+        }
+    })
+}
+
+async fn check_schedules(state: &AppState, recording_manager: &Arc<RecordingManager>, started: &mut HashMap<String, DateTime<Local>>) {
+    let now = Local::now();
+    started.retain(|_, end| *end > now);
+
+    let streams: Vec<_> = {
+        let camera_streams = state.camera_streams.read().await;
+        camera_streams.values()
+            .filter_map(|info| window_end(&info.camera_config, now).map(|end| (info.clone(), end)))
+            .collect()
+    };
+
+    for (info, end) in streams {
+        let camera_id = &info.camera_id;
+        if started.get(camera_id) == Some(&end) {
+            continue;
+        }
+        started.insert(camera_id.clone(), end);
+        if recording_manager.is_recording(camera_id).await {
+            info!("Camera '{}' is already recording, schedule window until {} starts no recording", camera_id, end);
+            continue;
+        }
+
+        crate::standby::wake_camera(state, camera_id).await;
+        match recording_manager.start_recording(
+            camera_id,
+            "schedule",
+            Some(REASON),
+            None,
+            info.frame_sender.clone(),
+            &info.camera_config,
+            info.pre_recording_buffer.as_ref(),
+        ).await {
+            Ok(session_id) => {
+                recording_manager.set_recording_stop_at(camera_id, Some(end.with_timezone(&Utc))).await;
+                info!("Started scheduled recording for camera '{}' (session {}) until {}", camera_id, session_id, end);
+            }
+            Err(e) => error!("Failed to start scheduled recording for camera '{}': {}", camera_id, e),
+        }
+    }
+}
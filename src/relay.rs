@@ -0,0 +1,700 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::response::IntoResponse;
+use axum::Json;
+use base64::{engine::general_purpose::STANDARD as B64, Engine as _};
+use bytes::{BufMut, Bytes, BytesMut};
+use chrono::{DateTime, Utc};
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio::time::{timeout, Duration};
+use tokio_rustls::rustls;
+use tokio_rustls::TlsConnector;
+use tokio_tungstenite::tungstenite;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tracing::{debug, info, warn};
+
+use crate::api_config::check_admin_token;
+use crate::api_recording::ApiResponse;
+use crate::config::{RelayConfig, RelayServerConfig};
+use crate::errors::{Result, StreamError};
+use crate::AppState;
+
+// Header naming the edge when it connects, the token is sent as bearer token
+const EDGE_ID_HEADER: &str = "X-Relay-Edge";
+// Header of proxied calls that is passed on to the edge server as its Authorization header
+const EDGE_AUTHORIZATION_HEADER: &str = "x-relay-authorization";
+// Request and response bodies tunneled through the relay
+const MAX_BODY_BYTES: usize = 32 * 1024 * 1024;
+// Base64 encoded bodies exceed tungstenite's default frame limit
+const MAX_MESSAGE_BYTES: usize = 64 * 1024 * 1024;
+// Frames queued for the cloud server before frames are dropped
+const OUTGOING_QUEUE: usize = 64;
+const PING_INTERVAL_SECS: u64 = 30;
+const CONNECT_TIMEOUT_SECS: u64 = 15;
+// Frames buffered per relayed camera on the cloud server
+const VIEWER_CHANNEL_CAPACITY: usize = 16;
+// Headers that describe the tunnel connection rather than the message
+const HOP_BY_HOP_HEADERS: [&str; 6] = ["connection", "content-length", "host", "keep-alive", "transfer-encoding", "upgrade"];
+
+lazy_static::lazy_static! {
+    static ref EDGES: Mutex<HashMap<String, Arc<EdgeConnection>>> = Mutex::new(HashMap::new());
+}
+
+/// Camera offered by an edge server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelayCamera {
+    pub id: String,
+    pub path: String,
+}
+
+/// Messages sent by the cloud server
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum CloudMessage {
+    Subscribe { camera_id: String },
+    Unsubscribe { camera_id: String },
+    Request {
+        id: u64,
+        method: String,
+        path: String,
+        headers: Vec<(String, String)>,
+        body: String, // Base64
+    },
+}
+
+/// Messages sent by the edge server, frames are sent as binary messages
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum EdgeMessage {
+    Hello { cameras: Vec<RelayCamera> },
+    Response {
+        id: u64,
+        status: u16,
+        headers: Vec<(String, String)>,
+        body: String, // Base64
+    },
+}
+
+struct RelayResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+// Binary frame message: camera ID length, camera ID, JPEG
+fn encode_frame(camera_id: &str, frame: &[u8]) -> Bytes {
+    let mut message = BytesMut::with_capacity(1 + camera_id.len() + frame.len());
+    message.put_u8(camera_id.len() as u8);
+    message.put_slice(camera_id.as_bytes());
+    message.put_slice(frame);
+    message.freeze()
+}
+
+fn decode_frame(message: &[u8]) -> Option<(&str, &[u8])> {
+    let id_len = *message.first()? as usize;
+    let camera_id = std::str::from_utf8(message.get(1..1 + id_len)?).ok()?;
+    Some((camera_id, &message[1 + id_len..]))
+}
+
+fn is_hop_by_hop(name: &str) -> bool {
+    HOP_BY_HOP_HEADERS.iter().any(|header| name.eq_ignore_ascii_case(header))
+}
+
+// ---------------------------------------------------------------------------
+// Edge server: outbound connection to the cloud server
+
+trait RelayStream: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> RelayStream for T {}
+
+type RelaySocket = tokio_tungstenite::WebSocketStream<Box<dyn RelayStream>>;
+
+/// Keep the connection to the cloud server open, reconnecting whenever it drops.
+/// Tunneled API calls are answered by `app`, the router of this server.
+pub fn start_client(state: AppState, app: axum::Router, config: RelayConfig) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        info!("Starting relay client for edge '{}' to {}", config.edge_id, config.url);
+        loop {
+            match run_client(&state, &app, &config).await {
+                Ok(()) => info!("Relay connection to {} closed", config.url),
+                Err(e) => warn!("Relay connection to {} failed: {}", config.url, e),
+            }
+            tokio::time::sleep(Duration::from_secs(config.reconnect_secs.max(1))).await;
+        }
+    })
+}
+
+async fn connect(config: &RelayConfig) -> Result<RelaySocket> {
+    let url = url::Url::parse(&config.url).map_err(|e| StreamError::config(format!("Invalid relay URL '{}': {}", config.url, e)))?;
+    let host = url.host_str().ok_or_else(|| StreamError::config(format!("Relay URL '{}' has no host", config.url)))?.to_string();
+    let secure = match url.scheme() {
+        "wss" => true,
+        "ws" => false,
+        scheme => return Err(StreamError::config(format!("Relay URL must use ws:// or wss://, not {}://", scheme))),
+    };
+    let port = url.port_or_known_default().unwrap_or(if secure { 443 } else { 80 });
+
+    let tcp = TcpStream::connect((host.as_str(), port)).await?;
+    tcp.set_nodelay(true)?;
+    let stream: Box<dyn RelayStream> = if secure {
+        let server_name = rustls::pki_types::ServerName::try_from(host.clone())
+            .map_err(|e| StreamError::config(format!("Invalid relay host '{}': {}", host, e)))?;
+        Box::new(tls_connector()?.connect(server_name, tcp).await?)
+    } else {
+        Box::new(tcp)
+    };
+
+    let mut request = config.url.as_str().into_client_request()
+        .map_err(|e| StreamError::config(format!("Invalid relay URL '{}': {}", config.url, e)))?;
+    let headers = request.headers_mut();
+    headers.insert(EDGE_ID_HEADER, config.edge_id.parse().map_err(|_| StreamError::config("Invalid relay edge_id"))?);
+    headers.insert("Authorization", format!("Bearer {}", config.token).parse().map_err(|_| StreamError::config("Invalid relay token"))?);
+
+    let ws_config = tungstenite::protocol::WebSocketConfig::default()
+        .max_message_size(Some(MAX_MESSAGE_BYTES))
+        .max_frame_size(Some(MAX_MESSAGE_BYTES));
+    let (socket, _) = tokio_tungstenite::client_async_with_config(request, stream, Some(ws_config)).await
+        .map_err(|e| StreamError::server(format!("Relay handshake failed: {}", e)))?;
+    Ok(socket)
+}
+
+fn tls_connector() -> Result<TlsConnector> {
+    let mut roots = rustls::RootCertStore::empty();
+    roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    let config = rustls::ClientConfig::builder_with_provider(Arc::new(rustls::crypto::aws_lc_rs::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(|e| StreamError::internal(format!("TLS setup failed: {}", e)))?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    Ok(TlsConnector::from(Arc::new(config)))
+}
+
+async fn run_client(state: &AppState, app: &axum::Router, config: &RelayConfig) -> Result<()> {
+    let socket = timeout(Duration::from_secs(CONNECT_TIMEOUT_SECS), connect(config)).await
+        .map_err(|_| StreamError::server("Relay connection timed out"))??;
+    let (mut sink, mut stream) = socket.split();
+
+    let cameras = relayed_cameras(state, config).await;
+    info!("Relay connected to {} as edge '{}' ({} cameras)", config.url, config.edge_id, cameras.len());
+    let hello = serde_json::to_string(&EdgeMessage::Hello { cameras })?;
+    sink.send(tungstenite::Message::text(hello)).await
+        .map_err(|e| StreamError::server(format!("Relay send failed: {}", e)))?;
+
+    let (outgoing, mut outgoing_rx) = mpsc::channel::<tungstenite::Message>(OUTGOING_QUEUE);
+    let writer = tokio::spawn(async move {
+        while let Some(message) = outgoing_rx.recv().await {
+            if sink.send(message).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut subscriptions: HashMap<String, tokio::task::JoinHandle<()>> = HashMap::new();
+    let mut ping = tokio::time::interval(Duration::from_secs(PING_INTERVAL_SECS));
+    let result = loop {
+        tokio::select! {
+            message = stream.next() => {
+                let message = match message {
+                    Some(Ok(message)) => message,
+                    Some(Err(e)) => break Err(StreamError::server(format!("Relay receive failed: {}", e))),
+                    None => break Ok(()),
+                };
+                match message {
+                    tungstenite::Message::Text(text) => match serde_json::from_str::<CloudMessage>(text.as_str()) {
+                        Ok(command) => handle_command(state, app, config, command, &outgoing, &mut subscriptions).await,
+                        Err(e) => debug!("Ignoring invalid relay message: {}", e),
+                    },
+                    tungstenite::Message::Close(_) => break Ok(()),
+                    _ => {}
+                }
+            }
+            _ = ping.tick() => {
+                if outgoing.send(tungstenite::Message::Ping(Bytes::new())).await.is_err() {
+                    break Ok(());
+                }
+            }
+        }
+    };
+
+    for (_, subscription) in subscriptions {
+        subscription.abort();
+    }
+    writer.abort();
+    result
+}
+
+async fn relayed_cameras(state: &AppState, config: &RelayConfig) -> Vec<RelayCamera> {
+    let mut cameras: Vec<RelayCamera> = state.camera_streams.read().await
+        .iter()
+        .filter(|(camera_id, _)| config.cameras.is_empty() || config.cameras.contains(camera_id))
+        // Cloud viewers cannot be told apart, so watermarked cameras are not relayed
+        .filter(|(_, info)| !crate::watermark::required(&info.camera_config))
+        .map(|(camera_id, info)| RelayCamera { id: camera_id.clone(), path: info.camera_config.path.clone() })
+        .collect();
+    cameras.sort_by(|a, b| a.id.cmp(&b.id));
+    cameras
+}
+
+async fn handle_command(
+    state: &AppState,
+    app: &axum::Router,
+    config: &RelayConfig,
+    command: CloudMessage,
+    outgoing: &mpsc::Sender<tungstenite::Message>,
+    subscriptions: &mut HashMap<String, tokio::task::JoinHandle<()>>,
+) {
+    match command {
+        CloudMessage::Subscribe { camera_id } => {
+            if subscriptions.contains_key(&camera_id) {
+                return;
+            }
+            // The ID is prefixed with a one byte length to every frame
+            if camera_id.len() > u8::MAX as usize || (!config.cameras.is_empty() && !config.cameras.contains(&camera_id)) {
+                warn!("Relay requested camera '{}' which is not relayed", camera_id);
+                return;
+            }
+            let stream = state.camera_streams.read().await
+                .get(&camera_id)
+                .map(|info| (info.frame_sender.clone(), crate::watermark::required(&info.camera_config)));
+            let frame_sender = match stream {
+                Some((_, true)) => {
+                    warn!("Relay requested camera '{}' which is watermarked and not relayed", camera_id);
+                    return;
+                }
+                Some((frame_sender, false)) => frame_sender,
+                None => {
+                    warn!("Relay requested unknown camera '{}'", camera_id);
+                    return;
+                }
+            };
+            crate::standby::wake_camera(state, &camera_id).await;
+            info!("[{}] Relaying stream to the cloud server", camera_id);
+            let mut frames = frame_sender.subscribe();
+            let outgoing = outgoing.clone();
+            let relayed_id = camera_id.clone();
+            subscriptions.insert(camera_id, tokio::spawn(async move {
+                loop {
+                    match frames.recv().await {
+                        Ok(frame) => {
+                            let message = tungstenite::Message::Binary(encode_frame(&relayed_id, &frame));
+                            match outgoing.try_send(message) {
+                                Ok(()) => {}
+                                // Drop frames instead of delaying the stream while the uplink is busy
+                                Err(mpsc::error::TrySendError::Full(_)) => {}
+                                Err(mpsc::error::TrySendError::Closed(_)) => break,
+                            }
+                        }
+                        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    }
+                }
+            }));
+        }
+        CloudMessage::Unsubscribe { camera_id } => {
+            if let Some(subscription) = subscriptions.remove(&camera_id) {
+                info!("[{}] Stopped relaying stream to the cloud server", camera_id);
+                subscription.abort();
+            }
+        }
+        CloudMessage::Request { id, method, path, headers, body } => {
+            let app = app.clone();
+            let outgoing = outgoing.clone();
+            let forward_api = config.forward_api;
+            tokio::spawn(async move {
+                let response = if forward_api {
+                    forward_request(app, &method, &path, headers, &body).await
+                } else {
+                    error_response(403, "API forwarding is disabled on this edge server")
+                };
+                let message = EdgeMessage::Response {
+                    id,
+                    status: response.status,
+                    headers: response.headers,
+                    body: B64.encode(&response.body),
+                };
+                if let Ok(message) = serde_json::to_string(&message) {
+                    let _ = outgoing.send(tungstenite::Message::text(message)).await;
+                }
+            });
+        }
+    }
+}
+
+// Run a tunneled call through the router of this server, including its authentication
+async fn forward_request(mut app: axum::Router, method: &str, path: &str, headers: Vec<(String, String)>, body: &str) -> RelayResponse {
+    use tower::Service;
+
+    let Ok(body) = B64.decode(body) else {
+        return error_response(400, "Invalid request body");
+    };
+    let mut builder = axum::http::Request::builder().method(method).uri(path);
+    for (name, value) in headers.iter().filter(|(name, _)| !is_hop_by_hop(name)) {
+        builder = builder.header(name, value);
+    }
+    let request = match builder.body(axum::body::Body::from(body)) {
+        Ok(request) => request,
+        Err(e) => return error_response(400, &format!("Invalid request: {}", e)),
+    };
+
+    debug!("Relay request {} {}", method, path);
+    if let Err(e) = std::future::poll_fn(|cx| Service::<axum::extract::Request>::poll_ready(&mut app, cx)).await {
+        return error_response(502, &format!("Request failed: {}", e));
+    }
+    let response = match app.call(request).await {
+        Ok(response) => response,
+        Err(e) => return error_response(502, &format!("Request failed: {}", e)),
+    };
+    let status = response.status().as_u16();
+    let headers = response.headers().iter()
+        .filter(|(name, _)| !is_hop_by_hop(name.as_str()))
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect();
+    match axum::body::to_bytes(response.into_body(), MAX_BODY_BYTES).await {
+        Ok(body) => RelayResponse { status, headers, body: body.to_vec() },
+        Err(_) => error_response(502, &format!("Response exceeds the relay limit of {} bytes", MAX_BODY_BYTES)),
+    }
+}
+
+fn error_response(status: u16, message: &str) -> RelayResponse {
+    RelayResponse {
+        status,
+        headers: vec![("content-type".to_string(), "application/json".to_string())],
+        body: serde_json::to_vec(&ApiResponse::<()>::error(message, status)).unwrap_or_default(),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Cloud server: connected edge servers
+
+struct EdgeConnection {
+    connection_id: String,
+    remote_addr: SocketAddr,
+    connected_at: DateTime<Utc>,
+    cameras: Mutex<Vec<RelayCamera>>,
+    commands: mpsc::UnboundedSender<Message>,
+    next_request_id: AtomicU64,
+    pending: Mutex<HashMap<u64, oneshot::Sender<RelayResponse>>>,
+    streams: Mutex<HashMap<String, broadcast::Sender<Bytes>>>,
+}
+
+impl EdgeConnection {
+    fn send(&self, message: &CloudMessage) -> bool {
+        match serde_json::to_string(message) {
+            Ok(text) => self.commands.send(Message::Text(text)).is_ok(),
+            Err(_) => false,
+        }
+    }
+
+    // Viewers of a camera share one stream from the edge, started with the first viewer
+    fn subscribe(&self, camera_id: &str) -> broadcast::Receiver<Bytes> {
+        let mut streams = self.streams.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(sender) = streams.get(camera_id) {
+            return sender.subscribe();
+        }
+        let (sender, receiver) = broadcast::channel(VIEWER_CHANNEL_CAPACITY);
+        streams.insert(camera_id.to_string(), sender);
+        self.send(&CloudMessage::Subscribe { camera_id: camera_id.to_string() });
+        receiver
+    }
+
+    fn unsubscribe(&self, camera_id: &str, receiver: broadcast::Receiver<Bytes>) {
+        let mut streams = self.streams.lock().unwrap_or_else(|e| e.into_inner());
+        drop(receiver);
+        if streams.get(camera_id).is_some_and(|sender| sender.receiver_count() == 0) {
+            streams.remove(camera_id);
+            self.send(&CloudMessage::Unsubscribe { camera_id: camera_id.to_string() });
+        }
+    }
+}
+
+/// State of an edge server for the edge list
+#[derive(Debug, Serialize)]
+pub struct EdgeInfo {
+    pub edge_id: String,
+    pub connected: bool,
+    pub remote_addr: Option<String>,
+    pub connected_at: Option<DateTime<Utc>>,
+    pub cameras: Vec<RelayCamera>,
+    /// Cameras currently streamed from the edge server
+    pub active_streams: Vec<String>,
+}
+
+fn get_edge(edge_id: &str) -> Option<Arc<EdgeConnection>> {
+    EDGES.lock().unwrap_or_else(|e| e.into_inner()).get(edge_id).cloned()
+}
+
+// GET /api/relay/connect (WebSocket of an edge server)
+pub async fn api_relay_connect(
+    ws: Option<WebSocketUpgrade>,
+    headers: axum::http::HeaderMap,
+    addr: Option<axum::extract::ConnectInfo<SocketAddr>>,
+    config: Arc<RelayServerConfig>,
+) -> axum::response::Response {
+    let edge_id = headers.get(EDGE_ID_HEADER).and_then(|value| value.to_str().ok()).unwrap_or_default().to_string();
+    let token = headers.get("Authorization")
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.strip_prefix("Bearer ").unwrap_or(value))
+        .unwrap_or_default();
+    if !config.edges.get(&edge_id).is_some_and(|expected| !expected.is_empty() && expected == token) {
+        warn!("[RELAY] Rejected connection of edge '{}'", edge_id);
+        return (axum::http::StatusCode::UNAUTHORIZED,
+                Json(ApiResponse::<()>::error("Unknown edge or invalid token", 401)))
+               .into_response();
+    }
+    let Some(ws) = ws else {
+        return (axum::http::StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<()>::error("Relay endpoint only accepts WebSocket connections", 400)))
+               .into_response();
+    };
+    let remote_addr = addr.map_or_else(|| "127.0.0.1:0".parse().unwrap(), |connect_info| connect_info.0);
+    ws.max_message_size(MAX_MESSAGE_BYTES)
+        .max_frame_size(MAX_MESSAGE_BYTES)
+        .on_upgrade(move |socket| handle_edge(socket, edge_id, remote_addr))
+}
+
+async fn handle_edge(socket: WebSocket, edge_id: String, remote_addr: SocketAddr) {
+    let (mut sink, mut stream) = socket.split();
+    let (commands, mut commands_rx) = mpsc::unbounded_channel::<Message>();
+    let edge = Arc::new(EdgeConnection {
+        connection_id: uuid::Uuid::new_v4().to_string(),
+        remote_addr,
+        connected_at: Utc::now(),
+        cameras: Mutex::new(Vec::new()),
+        commands,
+        next_request_id: AtomicU64::new(1),
+        pending: Mutex::new(HashMap::new()),
+        streams: Mutex::new(HashMap::new()),
+    });
+    // A reconnecting edge replaces its stale connection
+    EDGES.lock().unwrap_or_else(|e| e.into_inner()).insert(edge_id.clone(), edge.clone());
+    info!("[RELAY] Edge '{}' connected from {}", edge_id, remote_addr);
+
+    let writer = tokio::spawn(async move {
+        while let Some(message) = commands_rx.recv().await {
+            if sink.send(message).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    while let Some(Ok(message)) = stream.next().await {
+        match message {
+            Message::Binary(data) => {
+                if let Some((camera_id, frame)) = decode_frame(&data) {
+                    let streams = edge.streams.lock().unwrap_or_else(|e| e.into_inner());
+                    if let Some(sender) = streams.get(camera_id) {
+                        let _ = sender.send(Bytes::copy_from_slice(frame));
+                    }
+                }
+            }
+            Message::Text(text) => match serde_json::from_str::<EdgeMessage>(&text) {
+                Ok(EdgeMessage::Hello { cameras }) => {
+                    debug!("[RELAY] Edge '{}' offers {} cameras", edge_id, cameras.len());
+                    *edge.cameras.lock().unwrap_or_else(|e| e.into_inner()) = cameras;
+                }
+                Ok(EdgeMessage::Response { id, status, headers, body }) => {
+                    let waiting = edge.pending.lock().unwrap_or_else(|e| e.into_inner()).remove(&id);
+                    if let Some(waiting) = waiting {
+                        let _ = waiting.send(RelayResponse { status, headers, body: B64.decode(body).unwrap_or_default() });
+                    }
+                }
+                Err(e) => debug!("[RELAY] Ignoring invalid message of edge '{}': {}", edge_id, e),
+            },
+            Message::Close(_) => break,
+            _ => {}
+        }
+    }
+
+    writer.abort();
+    {
+        let mut edges = EDGES.lock().unwrap_or_else(|e| e.into_inner());
+        if edges.get(&edge_id).is_some_and(|current| current.connection_id == edge.connection_id) {
+            edges.remove(&edge_id);
+        }
+    }
+    // Ends waiting API calls and the viewers of this connection
+    edge.pending.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    edge.streams.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    info!("[RELAY] Edge '{}' disconnected", edge_id);
+}
+
+// GET /api/relay/edges
+pub async fn api_list_edges(
+    headers: axum::http::HeaderMap,
+    config: Arc<RelayServerConfig>,
+    state: AppState,
+) -> axum::response::Response {
+    if !check_admin_token(&headers, &state.admin_token) {
+        return (axum::http::StatusCode::UNAUTHORIZED,
+                Json(ApiResponse::<()>::error("Unauthorized", 401)))
+               .into_response();
+    }
+
+    let mut edges: Vec<EdgeInfo> = config.edges.keys()
+        .map(|edge_id| match get_edge(edge_id) {
+            Some(edge) => {
+                let mut active_streams: Vec<String> = edge.streams.lock().unwrap_or_else(|e| e.into_inner()).keys().cloned().collect();
+                active_streams.sort();
+                EdgeInfo {
+                    edge_id: edge_id.clone(),
+                    connected: true,
+                    remote_addr: Some(edge.remote_addr.to_string()),
+                    connected_at: Some(edge.connected_at),
+                    cameras: edge.cameras.lock().unwrap_or_else(|e| e.into_inner()).clone(),
+                    active_streams,
+                }
+            }
+            None => EdgeInfo {
+                edge_id: edge_id.clone(),
+                connected: false,
+                remote_addr: None,
+                connected_at: None,
+                cameras: Vec::new(),
+                active_streams: Vec::new(),
+            },
+        })
+        .collect();
+    edges.sort_by(|a, b| a.edge_id.cmp(&b.edge_id));
+    Json(ApiResponse::success(edges)).into_response()
+}
+
+// GET /relay/:edge_id/:camera_id/live (WebSocket)
+pub async fn relay_live_handler(
+    ws: Option<WebSocketUpgrade>,
+    path: axum::extract::Path<(String, String)>,
+    query: axum::extract::Query<HashMap<String, String>>,
+    headers: axum::http::HeaderMap,
+    state: AppState,
+) -> axum::response::Response {
+    let (edge_id, camera_id) = path.0;
+    // Browsers cannot set headers on WebSocket connections, so the admin token may be a query parameter
+    let mut auth_headers = headers;
+    if let Some(token) = query.get("token").and_then(|token| format!("Bearer {}", token).parse().ok()) {
+        auth_headers.insert("Authorization", token);
+    }
+    if !check_admin_token(&auth_headers, &state.admin_token) {
+        return (axum::http::StatusCode::UNAUTHORIZED, "Unauthorized").into_response();
+    }
+    let Some(ws) = ws else {
+        return (axum::http::StatusCode::BAD_REQUEST, "Relay stream only accepts WebSocket connections").into_response();
+    };
+    let Some(edge) = get_edge(&edge_id) else {
+        return (axum::http::StatusCode::NOT_FOUND, format!("Edge '{}' is not connected", edge_id)).into_response();
+    };
+    if !edge.cameras.lock().unwrap_or_else(|e| e.into_inner()).iter().any(|camera| camera.id == camera_id) {
+        return (axum::http::StatusCode::NOT_FOUND, format!("Edge '{}' does not relay camera '{}'", edge_id, camera_id)).into_response();
+    }
+
+    ws.on_upgrade(move |socket| async move {
+        info!("[RELAY] Viewer connected to camera '{}' of edge '{}'", camera_id, edge_id);
+        let (mut sender, mut receiver) = socket.split();
+        let mut frames = edge.subscribe(&camera_id);
+        loop {
+            tokio::select! {
+                frame = frames.recv() => match frame {
+                    Ok(frame) => {
+                        if sender.send(Message::Binary(frame.to_vec())).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                message = receiver.next() => match message {
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    _ => {}
+                },
+            }
+        }
+        edge.unsubscribe(&camera_id, frames);
+        info!("[RELAY] Viewer disconnected from camera '{}' of edge '{}'", camera_id, edge_id);
+    })
+}
+
+// ANY /api/relay/:edge_id/proxy/*path
+pub async fn api_relay_proxy(
+    request: axum::extract::Request,
+    config: Arc<RelayServerConfig>,
+    state: AppState,
+) -> axum::response::Response {
+    if !check_admin_token(request.headers(), &state.admin_token) {
+        return (axum::http::StatusCode::UNAUTHORIZED,
+                Json(ApiResponse::<()>::error("Unauthorized", 401)))
+               .into_response();
+    }
+
+    // /api/relay/<edge_id>/proxy/<path on the edge server>
+    let uri_path = request.uri().path().to_string();
+    let Some((edge_id, edge_path)) = uri_path.strip_prefix("/api/relay/").and_then(|rest| rest.split_once("/proxy")) else {
+        return (axum::http::StatusCode::NOT_FOUND,
+                Json(ApiResponse::<()>::error("Not found", 404)))
+               .into_response();
+    };
+    let mut path = if edge_path.is_empty() { "/".to_string() } else { edge_path.to_string() };
+    if let Some(query) = request.uri().query() {
+        path = format!("{}?{}", path, query);
+    }
+    let Some(edge) = get_edge(edge_id) else {
+        return (axum::http::StatusCode::BAD_GATEWAY,
+                Json(ApiResponse::<()>::error(&format!("Edge '{}' is not connected", edge_id), 502)))
+               .into_response();
+    };
+
+    // The cloud admin token stays on the cloud server, the edge's own token is sent separately
+    let method = request.method().to_string();
+    let headers: Vec<(String, String)> = request.headers().iter()
+        .filter(|(name, _)| !is_hop_by_hop(name.as_str()) && *name != axum::http::header::AUTHORIZATION)
+        .filter_map(|(name, value)| {
+            let value = value.to_str().ok()?.to_string();
+            if name.as_str() == EDGE_AUTHORIZATION_HEADER {
+                Some(("authorization".to_string(), value))
+            } else {
+                Some((name.to_string(), value))
+            }
+        })
+        .collect();
+    let body = match axum::body::to_bytes(request.into_body(), MAX_BODY_BYTES).await {
+        Ok(body) => body,
+        Err(_) => {
+            return (axum::http::StatusCode::PAYLOAD_TOO_LARGE,
+                    Json(ApiResponse::<()>::error("Request body too large", 413)))
+                   .into_response();
+        }
+    };
+
+    let id = edge.next_request_id.fetch_add(1, Ordering::Relaxed);
+    let (response_tx, response_rx) = oneshot::channel();
+    edge.pending.lock().unwrap_or_else(|e| e.into_inner()).insert(id, response_tx);
+    debug!("[RELAY] {} {} on edge '{}'", method, path, edge_id);
+    edge.send(&CloudMessage::Request { id, method, path, headers, body: B64.encode(&body) });
+
+    let response = match timeout(Duration::from_secs(config.request_timeout_secs), response_rx).await {
+        Ok(Ok(response)) => response,
+        Ok(Err(_)) => {
+            return (axum::http::StatusCode::BAD_GATEWAY,
+                    Json(ApiResponse::<()>::error(&format!("Edge '{}' disconnected", edge_id), 502)))
+                   .into_response();
+        }
+        Err(_) => {
+            edge.pending.lock().unwrap_or_else(|e| e.into_inner()).remove(&id);
+            return (axum::http::StatusCode::GATEWAY_TIMEOUT,
+                    Json(ApiResponse::<()>::error(&format!("Edge '{}' did not answer in time", edge_id), 504)))
+                   .into_response();
+        }
+    };
+
+    let mut builder = axum::response::Response::builder().status(response.status);
+    for (name, value) in response.headers.iter().filter(|(name, _)| !is_hop_by_hop(name)) {
+        builder = builder.header(name, value);
+    }
+    builder.body(axum::body::Body::from(response.body))
+        .unwrap_or_else(|_| {
+            Json(ApiResponse::<()>::error("Failed to build response", 500)).into_response()
+        })
+}
@@ -0,0 +1,501 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::Arc;
+use bytes::Bytes;
+use chrono::{DateTime, Datelike, Local, NaiveTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tokio::time::{interval, Duration};
+use tracing::{debug, error, info, warn};
+
+use crate::config::{ReportPeriod, ReportSchedule, ReportsConfig, SmtpConfig};
+use crate::database::{DatabaseProvider, PageRequest, RecordingStatus};
+use crate::errors::{Result, StreamError};
+use crate::smtp::{self, Attachment, Email};
+use crate::AppState;
+
+// How often cameras are sampled for the uptime and the schedules are checked
+const SAMPLE_INTERVAL_SECS: u64 = 30;
+// Uptime history is kept long enough for a weekly report
+const UPTIME_HISTORY_SECS: i64 = 8 * 24 * 3600;
+const UPTIME_FILE: &str = "uptime.json";
+const SESSION_PAGE_SIZE: i64 = 100;
+const WEBHOOK_TIMEOUT_SECS: u64 = 30;
+
+/// Uptime samples of one camera hour
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+struct UptimeSamples {
+    up: u32,
+    total: u32,
+}
+
+/// camera_id -> hour start (Unix seconds) -> samples
+type UptimeHistory = HashMap<String, BTreeMap<i64, UptimeSamples>>;
+
+/// Creates the scheduled per-camera summary reports and samples the camera uptime they include
+pub struct ReportManager {
+    config: ReportsConfig,
+    smtp: Option<SmtpConfig>,
+    uptime: Mutex<UptimeHistory>,
+    // Schedule id -> scheduled time of the last report created
+    last_due: Mutex<HashMap<String, DateTime<Utc>>>,
+}
+
+impl ReportManager {
+    /// Create the manager if reports are configured and enabled
+    pub fn from_config(config: Option<&ReportsConfig>, smtp: Option<&SmtpConfig>) -> Option<Arc<Self>> {
+        let config = config.filter(|c| c.enabled)?;
+        for schedule in &config.schedules {
+            if parse_time(&schedule.time).is_none() {
+                warn!("Report schedule '{}' has an invalid time '{}' (expected HH:MM), it is never run", schedule.id, schedule.time);
+            }
+            if !schedule.email.is_empty() && !smtp.is_some_and(|s| s.enabled) {
+                warn!("Report schedule '{}' has mail recipients but SMTP is not configured", schedule.id);
+            }
+        }
+        if let Err(e) = std::fs::create_dir_all(&config.directory) {
+            warn!("Failed to create reports directory '{}': {}", config.directory, e);
+        }
+        let uptime = std::fs::read_to_string(PathBuf::from(&config.directory).join(UPTIME_FILE)).ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        info!("Reports enabled in '{}' ({} schedules)", config.directory, config.schedules.len());
+        Some(Arc::new(Self {
+            config: config.clone(),
+            smtp: smtp.cloned(),
+            uptime: Mutex::new(uptime),
+            last_due: Mutex::new(HashMap::new()),
+        }))
+    }
+
+    pub fn schedules(&self) -> &[ReportSchedule] {
+        &self.config.schedules
+    }
+
+    pub fn directory(&self) -> PathBuf {
+        PathBuf::from(&self.config.directory)
+    }
+
+    /// Start the task that samples the uptime and runs the schedules. Reports whose time
+    /// passed while the server was not running are not created afterwards.
+    pub fn start(self: &Arc<Self>, state: AppState) -> tokio::task::JoinHandle<()> {
+        let manager = self.clone();
+        tokio::spawn(async move {
+            {
+                let now = Local::now();
+                let mut last_due = manager.last_due.lock().await;
+                for schedule in &manager.config.schedules {
+                    if let Some(due) = latest_due(schedule, now) {
+                        last_due.insert(schedule.id.clone(), due);
+                    }
+                }
+            }
+
+            let mut interval = interval(Duration::from_secs(SAMPLE_INTERVAL_SECS));
+            let mut saved_hour = hour_start(Utc::now());
+            loop {
+                interval.tick().await;
+                manager.sample_uptime(&state).await;
+
+                // Persist the history once per hour so a restart keeps the closed hours
+                let hour = hour_start(Utc::now());
+                if hour != saved_hour {
+                    saved_hour = hour;
+                    manager.save_uptime().await;
+                }
+
+                manager.run_due_schedules(&state).await;
+            }
+        })
+    }
+
+    async fn sample_uptime(&self, state: &AppState) {
+        let cameras: Vec<(String, bool)> = {
+            let camera_streams = state.camera_streams.read().await;
+            camera_streams.values()
+                .map(|info| {
+                    // Standby stops FFmpeg on purpose, the camera is still available
+                    let pipeline = &info.pipeline_control;
+                    let up = pipeline.is_standby() || pipeline.secs_since_last_frame() < self.config.offline_after_secs;
+                    (info.camera_id.clone(), up)
+                })
+                .collect()
+        };
+
+        let hour = hour_start(Utc::now());
+        let mut uptime = self.uptime.lock().await;
+        for (camera_id, up) in cameras {
+            let samples = uptime.entry(camera_id).or_default().entry(hour).or_default();
+            samples.total += 1;
+            samples.up += u32::from(up);
+        }
+        for hours in uptime.values_mut() {
+            hours.retain(|start, _| *start > hour - UPTIME_HISTORY_SECS);
+        }
+        uptime.retain(|_, hours| !hours.is_empty());
+    }
+
+    async fn save_uptime(&self) {
+        let content = match serde_json::to_string(&*self.uptime.lock().await) {
+            Ok(content) => content,
+            Err(e) => {
+                error!("Failed to serialize the uptime history: {}", e);
+                return;
+            }
+        };
+        let path = self.directory().join(UPTIME_FILE);
+        if let Err(e) = tokio::fs::write(&path, content).await {
+            warn!("Failed to save the uptime history to {:?}: {}", path, e);
+        }
+    }
+
+    async fn run_due_schedules(&self, state: &AppState) {
+        let now = Local::now();
+        for schedule in &self.config.schedules {
+            let Some(due) = latest_due(schedule, now) else { continue };
+            {
+                let mut last_due = self.last_due.lock().await;
+                if last_due.get(&schedule.id).is_some_and(|last| *last >= due) {
+                    continue;
+                }
+                last_due.insert(schedule.id.clone(), due);
+            }
+            info!("Creating {:?} report '{}'", schedule.period, schedule.id);
+            if let Err(e) = self.run(state, schedule).await {
+                error!("Failed to create report '{}': {}", schedule.id, e);
+            }
+        }
+    }
+
+    /// Create the report of the last completed period of a schedule, store and deliver it
+    pub async fn run(&self, state: &AppState, schedule: &ReportSchedule) -> Result<serde_json::Value> {
+        let (from, to) = report_period(schedule.period, Local::now())
+            .ok_or_else(|| StreamError::internal("Failed to determine the report period"))?;
+        let report = self.generate(state, schedule, from, to).await;
+        let html = schedule.html.then(|| render_html(&report));
+
+        let name = format!("{}_{}", schedule.id, from.with_timezone(&Local).format("%Y-%m-%d"));
+        let directory = self.directory();
+        tokio::fs::create_dir_all(&directory).await?;
+        let json = serde_json::to_vec_pretty(&report)
+            .map_err(|e| StreamError::internal(format!("Failed to serialize report: {}", e)))?;
+        tokio::fs::write(directory.join(format!("{}.json", name)), &json).await?;
+        if let Some(ref html) = html {
+            tokio::fs::write(directory.join(format!("{}.html", name)), html).await?;
+        }
+        info!("Stored report '{}' in '{}'", name, self.config.directory);
+
+        if !schedule.email.is_empty() {
+            self.send_mail(schedule, &report, &name, html.map_or((Bytes::from(json), "application/json", "json"), |html| (Bytes::from(html), "text/html", "html"))).await;
+        }
+        if let Some(ref url) = schedule.webhook_url {
+            match post_webhook(url, &report).await {
+                Ok(()) => info!("Posted report '{}' to its webhook", name),
+                Err(e) => error!("Failed to post report '{}' to its webhook: {}", name, e),
+            }
+        }
+        Ok(report)
+    }
+
+    async fn generate(&self, state: &AppState, schedule: &ReportSchedule, from: DateTime<Utc>, to: DateTime<Utc>) -> serde_json::Value {
+        let mut camera_ids: Vec<String> = if schedule.cameras.is_empty() {
+            state.camera_configs.read().await.keys().cloned().collect()
+        } else {
+            schedule.cameras.clone()
+        };
+        camera_ids.sort();
+
+        let mut cameras = Vec::new();
+        let (mut recorded_hours, mut motion_events, mut storage_bytes) = (0.0, 0, 0);
+        for camera_id in &camera_ids {
+            let mut camera = serde_json::json!({ "camera_id": camera_id });
+            let (uptime_percent, monitored_hours) = self.uptime_between(camera_id, from, to).await;
+            camera["uptime_percent"] = serde_json::json!(uptime_percent.map(round2));
+            camera["monitored_hours"] = serde_json::json!(round2(monitored_hours));
+
+            let database = match state.recording_manager {
+                Some(ref recording_manager) => recording_manager.get_camera_database(camera_id).await,
+                None => None,
+            };
+            if let Some(database) = database {
+                match camera_recording_summary(database.as_ref(), camera_id, from, to).await {
+                    Ok(summary) => {
+                        recorded_hours += summary["recorded_hours"].as_f64().unwrap_or_default();
+                        motion_events += summary["motion_events"].as_u64().unwrap_or_default();
+                        storage_bytes += summary["storage"]["media_bytes"].as_i64().unwrap_or_default();
+                        merge(&mut camera, summary);
+                    }
+                    Err(e) => {
+                        warn!("Failed to summarize recordings of camera '{}' for report '{}': {}", camera_id, schedule.id, e);
+                        camera["error"] = serde_json::json!(e.to_string());
+                    }
+                }
+            }
+            cameras.push(camera);
+        }
+
+        serde_json::json!({
+            "report": schedule.id,
+            "period": schedule.period,
+            "from": from,
+            "to": to,
+            "generated_at": Utc::now(),
+            "cameras": cameras,
+            "totals": {
+                "recorded_hours": round2(recorded_hours),
+                "motion_events": motion_events,
+                "media_bytes": storage_bytes,
+            },
+        })
+    }
+
+    /// Uptime in percent of the sampled time and the sampled hours within the period
+    async fn uptime_between(&self, camera_id: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> (Option<f64>, f64) {
+        let uptime = self.uptime.lock().await;
+        let Some(hours) = uptime.get(camera_id) else { return (None, 0.0) };
+        let (up, total) = hours.range(hour_start(from)..to.timestamp())
+            .fold((0u64, 0u64), |(up, total), (_, samples)| (up + samples.up as u64, total + samples.total as u64));
+        let monitored_hours = (total * SAMPLE_INTERVAL_SECS) as f64 / 3600.0;
+        ((total > 0).then(|| up as f64 * 100.0 / total as f64), monitored_hours)
+    }
+
+    async fn send_mail(&self, schedule: &ReportSchedule, report: &serde_json::Value, name: &str, (data, content_type, extension): (Bytes, &'static str, &str)) {
+        let Some(config) = self.smtp.as_ref().filter(|s| s.enabled) else {
+            warn!("Report '{}' is not mailed, SMTP is not configured", name);
+            return;
+        };
+        let email = Email {
+            to: schedule.email.clone(),
+            subject: format!("[{}] Camera report {}", schedule.id, name.rsplit('_').next().unwrap_or(name)),
+            body: render_text(report),
+            attachment: Some(Attachment {
+                filename: format!("{}.{}", name, extension),
+                content_type,
+                data,
+            }),
+        };
+        match smtp::send_mail(config, &email).await {
+            Ok(()) => info!("Sent report mail '{}' to {}", email.subject, email.to.join(", ")),
+            Err(e) => error!("Failed to send report mail for '{}': {}", schedule.id, e),
+        }
+    }
+}
+
+/// Recorded hours, sessions, events and storage of a camera within the period
+async fn camera_recording_summary(database: &dyn DatabaseProvider, camera_id: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<serde_json::Value> {
+    // Sessions of a camera follow each other, so walking back from the newest one stops at the first before the period
+    let (mut sessions, mut recorded_secs) = (0, 0.0);
+    let mut offset = 0;
+    'pages: loop {
+        let page = PageRequest { limit: Some(SESSION_PAGE_SIZE), offset, oldest_first: false };
+        let page_sessions = database.list_recordings_filtered(camera_id, None, Some(to), None, &page).await?;
+        for session in &page_sessions {
+            let end = match session.end_time {
+                Some(end) => end,
+                None if session.status == RecordingStatus::Active => Utc::now(),
+                None => continue,
+            };
+            if end <= from {
+                break 'pages;
+            }
+            let seconds = (end.min(to) - session.start_time.max(from)).num_milliseconds() as f64 / 1000.0;
+            if seconds > 0.0 {
+                sessions += 1;
+                recorded_secs += seconds;
+            }
+        }
+        if (page_sessions.len() as i64) < SESSION_PAGE_SIZE {
+            break;
+        }
+        offset += SESSION_PAGE_SIZE;
+    }
+
+    let events = database.list_recording_events(camera_id, from, to).await?;
+    let mut events_by_type: BTreeMap<&str, u64> = BTreeMap::new();
+    for event in &events {
+        *events_by_type.entry(event.event_type.as_str()).or_default() += 1;
+    }
+    // Trigger firings (as for motion alerts) and motion reported in the camera's ONVIF metadata
+    let motion_events = events.iter()
+        .filter(|e| e.event_type == "trigger" || (e.event_type == "metadata" && e.label == "motion started"))
+        .count();
+
+    let stats = database.get_storage_stats(camera_id).await?;
+    let database_size = match database.get_database_size().await {
+        Ok(size) => Some(size),
+        Err(e) => {
+            debug!("Failed to get database size of camera '{}' for report: {}", camera_id, e);
+            None
+        }
+    };
+
+    Ok(serde_json::json!({
+        "sessions": sessions,
+        "recorded_hours": round2(recorded_secs / 3600.0),
+        "motion_events": motion_events,
+        "events": events_by_type,
+        "storage": {
+            "frames": stats.frame_count,
+            "mp4_bytes": stats.mp4_file_bytes + stats.mp4_database_bytes,
+            "hls_bytes": stats.hls_bytes,
+            "media_bytes": stats.mp4_file_bytes + stats.mp4_database_bytes + stats.hls_bytes,
+            "database_size_bytes": database_size,
+            "oldest_recording": stats.oldest_recording,
+        },
+    }))
+}
+
+async fn post_webhook(url: &str, report: &serde_json::Value) -> Result<()> {
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(WEBHOOK_TIMEOUT_SECS))
+        .build()
+        .map_err(|e| StreamError::internal(format!("Failed to create HTTP client: {}", e)))?;
+    let response = client.post(url).json(report).send().await
+        .map_err(|e| StreamError::internal(format!("Webhook request failed: {}", e)))?;
+    if !response.status().is_success() {
+        return Err(StreamError::internal(format!("Webhook replied {}", response.status())));
+    }
+    Ok(())
+}
+
+fn parse_time(time: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(time, "%H:%M").ok()
+}
+
+/// Most recent time the schedule was due at or before `now`
+fn latest_due(schedule: &ReportSchedule, now: DateTime<Local>) -> Option<DateTime<Utc>> {
+    let time = parse_time(&schedule.time)?;
+    let days_back = match schedule.period {
+        ReportPeriod::Daily => 0,
+        ReportPeriod::Weekly => now.weekday().num_days_from_monday() as i64,
+    };
+    let step = match schedule.period {
+        ReportPeriod::Daily => 1,
+        ReportPeriod::Weekly => 7,
+    };
+    let date = now.date_naive() - chrono::Duration::days(days_back);
+    let due = Local.from_local_datetime(&date.and_time(time)).earliest()?;
+    let due = if due > now {
+        Local.from_local_datetime(&(date - chrono::Duration::days(step)).and_time(time)).earliest()?
+    } else {
+        due
+    };
+    Some(due.with_timezone(&Utc))
+}
+
+/// Last completed local day (weekly: Monday to Sunday) before `now`
+pub fn report_period(period: ReportPeriod, now: DateTime<Local>) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let (days_back, length) = match period {
+        ReportPeriod::Daily => (0, 1),
+        ReportPeriod::Weekly => (now.weekday().num_days_from_monday() as i64, 7),
+    };
+    let end = now.date_naive() - chrono::Duration::days(days_back);
+    let start = end - chrono::Duration::days(length);
+    let midnight = |date: chrono::NaiveDate| Local.from_local_datetime(&date.and_time(NaiveTime::MIN)).earliest();
+    Some((midnight(start)?.with_timezone(&Utc), midnight(end)?.with_timezone(&Utc)))
+}
+
+fn hour_start(time: DateTime<Utc>) -> i64 {
+    time.timestamp() - time.timestamp().rem_euclid(3600)
+}
+
+fn round2(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+fn merge(target: &mut serde_json::Value, source: serde_json::Value) {
+    if let (Some(target), serde_json::Value::Object(source)) = (target.as_object_mut(), source) {
+        target.extend(source);
+    }
+}
+
+fn format_bytes(bytes: i64) -> String {
+    let mb = bytes as f64 / 1024.0 / 1024.0;
+    if mb >= 1024.0 { format!("{:.1} GB", mb / 1024.0) } else { format!("{:.1} MB", mb) }
+}
+
+/// Table cells of a camera: uptime, recorded hours, sessions, motion events, media storage
+fn camera_cells(camera: &serde_json::Value) -> [String; 5] {
+    [
+        camera["uptime_percent"].as_f64().map_or("-".to_string(), |uptime| format!("{:.1}%", uptime)),
+        camera["recorded_hours"].as_f64().map_or("-".to_string(), |hours| format!("{:.2} h", hours)),
+        camera["sessions"].as_u64().map_or("-".to_string(), |sessions| sessions.to_string()),
+        camera["motion_events"].as_u64().map_or("-".to_string(), |events| events.to_string()),
+        camera["storage"]["media_bytes"].as_i64().map_or("-".to_string(), format_bytes),
+    ]
+}
+
+fn period_title(report: &serde_json::Value) -> String {
+    let date = |key: &str| report[key].as_str()
+        .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+        .map(|time| time.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default();
+    format!("{} to {}", date("from"), date("to"))
+}
+
+/// Plain text summary for the mail body
+fn render_text(report: &serde_json::Value) -> String {
+    let mut text = format!("Camera report '{}' for {}\n\n", report["report"].as_str().unwrap_or_default(), period_title(report));
+    text.push_str(&format!("{:<24} {:>8} {:>10} {:>8} {:>8} {:>10}\n", "Camera", "Uptime", "Recorded", "Sessions", "Motion", "Storage"));
+    for camera in report["cameras"].as_array().into_iter().flatten() {
+        let [uptime, recorded, sessions, motion, storage] = camera_cells(camera);
+        text.push_str(&format!("{:<24} {:>8} {:>10} {:>8} {:>8} {:>10}\n",
+            camera["camera_id"].as_str().unwrap_or_default(), uptime, recorded, sessions, motion, storage));
+    }
+    let totals = &report["totals"];
+    text.push_str(&format!("\nTotal: {:.2} h recorded, {} motion events, {} stored\n",
+        totals["recorded_hours"].as_f64().unwrap_or_default(),
+        totals["motion_events"].as_u64().unwrap_or_default(),
+        format_bytes(totals["media_bytes"].as_i64().unwrap_or_default())));
+    text
+}
+
+pub fn render_html(report: &serde_json::Value) -> String {
+    let escape = |text: &str| text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+    let title = format!("Camera report '{}'", escape(report["report"].as_str().unwrap_or_default()));
+    let mut rows = String::new();
+    for camera in report["cameras"].as_array().into_iter().flatten() {
+        rows.push_str(&format!("<tr><td>{}</td>", escape(camera["camera_id"].as_str().unwrap_or_default())));
+        for cell in camera_cells(camera) {
+            rows.push_str(&format!("<td class=\"n\">{}</td>", cell));
+        }
+        if let Some(error) = camera["error"].as_str() {
+            rows.push_str(&format!("<td class=\"error\">{}</td>", escape(error)));
+        }
+        rows.push_str("</tr>\n");
+    }
+    let totals = &report["totals"];
+    format!(r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<style>
+body {{ font-family: sans-serif; margin: 2em; color: #222; }}
+table {{ border-collapse: collapse; }}
+th, td {{ border: 1px solid #ccc; padding: 4px 10px; text-align: left; }}
+th {{ background: #f0f0f0; }}
+td.n {{ text-align: right; }}
+td.error {{ color: #b00; }}
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+<p>{period}</p>
+<table>
+<tr><th>Camera</th><th>Uptime</th><th>Recorded</th><th>Sessions</th><th>Motion events</th><th>Storage</th></tr>
+{rows}<tr><th>Total</th><th></th><th class="n">{recorded:.2} h</th><th></th><th class="n">{motion}</th><th class="n">{storage}</th></tr>
+</table>
+<p>Generated {generated}</p>
+</body>
+</html>
+"#,
+        title = title,
+        period = period_title(report),
+        rows = rows,
+        recorded = totals["recorded_hours"].as_f64().unwrap_or_default(),
+        motion = totals["motion_events"].as_u64().unwrap_or_default(),
+        storage = format_bytes(totals["media_bytes"].as_i64().unwrap_or_default()),
+        generated = Local::now().format("%Y-%m-%d %H:%M:%S"),
+    )
+}
@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Instant;
+use serde::Serialize;
+use tokio::time::{Duration, interval};
+use tracing::{debug, info};
+
+// Kernel clock ticks per second used for utime/stime in /proc/<pid>/stat (USER_HZ).
+// This is 100 on all mainstream Linux architectures.
+const CLOCK_TICKS_PER_SEC: f64 = 100.0;
+
+// Interval between two CPU/RSS samples of the tracked FFmpeg processes
+const SAMPLE_INTERVAL_SECS: u64 = 2;
+
+lazy_static::lazy_static! {
+    static ref FFMPEG_PROCESSES: Mutex<HashMap<String, TrackedProcess>> = Mutex::new(HashMap::new());
+}
+
+/// Latest resource usage sample of a camera's FFmpeg process
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessUsage {
+    pub pid: u32,
+    pub cpu_percent: f32,
+    pub rss_kb: u64,
+}
+
+struct TrackedProcess {
+    pid: u32,
+    last_cpu_ticks: Option<u64>,
+    last_sample_time: Option<Instant>,
+    usage: Option<ProcessUsage>,
+}
+
+/// Unregisters the FFmpeg process of a camera when dropped
+pub struct ProcessGuard {
+    camera_id: String,
+    pid: u32,
+}
+
+impl Drop for ProcessGuard {
+    fn drop(&mut self) {
+        if let Ok(mut processes) = FFMPEG_PROCESSES.lock() {
+            // Only remove our own entry - a restarted FFmpeg may already have registered a new PID
+            if processes.get(&self.camera_id).is_some_and(|p| p.pid == self.pid) {
+                processes.remove(&self.camera_id);
+            }
+        }
+    }
+}
+
+/// Register the FFmpeg child process of a camera for CPU/memory accounting.
+/// The process stays registered until the returned guard is dropped.
+pub fn register_ffmpeg_process(camera_id: &str, pid: u32) -> ProcessGuard {
+    if let Ok(mut processes) = FFMPEG_PROCESSES.lock() {
+        processes.insert(camera_id.to_string(), TrackedProcess {
+            pid,
+            last_cpu_ticks: None,
+            last_sample_time: None,
+            usage: None,
+        });
+    }
+    debug!("[{}] Tracking resource usage of FFmpeg process {}", camera_id, pid);
+    ProcessGuard { camera_id: camera_id.to_string(), pid }
+}
+
+/// Get the latest resource usage sample of a camera's FFmpeg process
+pub fn get_ffmpeg_usage(camera_id: &str) -> Option<ProcessUsage> {
+    FFMPEG_PROCESSES.lock().ok()?
+        .get(camera_id)
+        .and_then(|p| p.usage.clone())
+}
+
+/// Start the background task that samples all registered FFmpeg processes
+pub fn start_sampling_task() -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        if !cfg!(target_os = "linux") {
+            info!("FFmpeg resource accounting requires /proc and is disabled on this platform");
+            return;
+        }
+
+        let mut interval = interval(Duration::from_secs(SAMPLE_INTERVAL_SECS));
+        info!("Started FFmpeg resource accounting task - sampling every {} seconds", SAMPLE_INTERVAL_SECS);
+
+        loop {
+            interval.tick().await;
+            sample_all();
+        }
+    })
+}
+
+fn sample_all() {
+    let Ok(mut processes) = FFMPEG_PROCESSES.lock() else {
+        return;
+    };
+
+    let now = Instant::now();
+    for (camera_id, process) in processes.iter_mut() {
+        let (Some(cpu_ticks), Some(rss_kb)) = (read_cpu_ticks(process.pid), read_rss_kb(process.pid)) else {
+            // Process is gone or /proc is not readable; keep the last sample until it is unregistered
+            continue;
+        };
+
+        let cpu_percent = match (process.last_cpu_ticks, process.last_sample_time) {
+            (Some(last_ticks), Some(last_time)) => {
+                let elapsed = now.duration_since(last_time).as_secs_f64();
+                if elapsed > 0.0 {
+                    let cpu_secs = cpu_ticks.saturating_sub(last_ticks) as f64 / CLOCK_TICKS_PER_SEC;
+                    (cpu_secs / elapsed * 100.0) as f32
+                } else {
+                    0.0
+                }
+            }
+            _ => 0.0,
+        };
+
+        process.last_cpu_ticks = Some(cpu_ticks);
+        process.last_sample_time = Some(now);
+        process.usage = Some(ProcessUsage { pid: process.pid, cpu_percent, rss_kb });
+
+        debug!("[{}] FFmpeg process {}: {:.1}% CPU, {} kB RSS", camera_id, process.pid, cpu_percent, rss_kb);
+    }
+}
+
+/// Sum of user and system CPU time of a process in clock ticks
+fn read_cpu_ticks(pid: u32) -> Option<u64> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // The command name is wrapped in parentheses and may contain spaces, so parse after the last ')'
+    let fields: Vec<&str> = stat.get(stat.rfind(')')? + 1..)?.split_whitespace().collect();
+    // fields[0] is the process state (field 3), utime and stime are fields 14 and 15
+    let utime: u64 = fields.get(11)?.parse().ok()?;
+    let stime: u64 = fields.get(12)?.parse().ok()?;
+    Some(utime + stime)
+}
+
+/// Resident set size of a process in kilobytes
+fn read_rss_kb(pid: u32) -> Option<u64> {
+    let status = std::fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    status.lines()
+        .find(|line| line.starts_with("VmRSS:"))
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|value| value.parse().ok())
+}
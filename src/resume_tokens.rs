@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+lazy_static::lazy_static! {
+    static ref TOKENS: Mutex<HashMap<String, ResumePoint>> = Mutex::new(HashMap::new());
+}
+
+struct ResumePoint {
+    camera_id: String,
+    last_frame_at: Arc<AtomicI64>, // Unix milliseconds of the last frame sent, 0 before the first
+    disconnected_at: Option<Instant>,
+    ttl: Duration,
+}
+
+/// Subscription of a live WebSocket client that can be continued with its token after a reconnect
+pub struct ResumeSubscription {
+    pub token: String,
+    /// Time of the last frame the client got over its previous connection
+    pub resume_from: Option<DateTime<Utc>>,
+    last_frame_at: Arc<AtomicI64>,
+}
+
+impl ResumeSubscription {
+    /// Remember the capture time of the frame delivered last
+    pub fn frame_sent(&self, timestamp: DateTime<Utc>) {
+        self.last_frame_at.store(timestamp.timestamp_millis(), Ordering::Relaxed);
+    }
+}
+
+impl Drop for ResumeSubscription {
+    // The token stays valid for `ttl` after the connection ended
+    fn drop(&mut self) {
+        let mut tokens = TOKENS.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(point) = tokens.get_mut(&self.token) {
+            if Arc::ptr_eq(&point.last_frame_at, &self.last_frame_at) {
+                point.disconnected_at = Some(Instant::now());
+            }
+        }
+    }
+}
+
+/// Continue the subscription of `token` if it belongs to the camera and has not expired,
+/// otherwise start a new one with a new token
+pub fn subscribe(camera_id: &str, token: Option<&str>, ttl: Duration) -> ResumeSubscription {
+    let mut tokens = TOKENS.lock().unwrap_or_else(|e| e.into_inner());
+    tokens.retain(|_, point| point.disconnected_at.is_none_or(|at| at.elapsed() < point.ttl));
+
+    if let Some(point) = token.and_then(|token| tokens.get_mut(token)).filter(|point| point.camera_id == camera_id) {
+        // A reconnect may arrive before the old connection noticed the drop, it takes the token over
+        let last_frame_at = Arc::new(AtomicI64::new(point.last_frame_at.load(Ordering::Relaxed)));
+        let resume_from = match last_frame_at.load(Ordering::Relaxed) {
+            0 => None,
+            millis => DateTime::from_timestamp_millis(millis),
+        };
+        point.last_frame_at = last_frame_at.clone();
+        point.disconnected_at = None;
+        point.ttl = ttl;
+        return ResumeSubscription {
+            token: token.unwrap_or_default().to_string(),
+            resume_from,
+            last_frame_at,
+        };
+    }
+
+    let token = Uuid::new_v4().simple().to_string();
+    let last_frame_at = Arc::new(AtomicI64::new(0));
+    tokens.insert(token.clone(), ResumePoint {
+        camera_id: camera_id.to_string(),
+        last_frame_at: last_frame_at.clone(),
+        disconnected_at: None,
+        ttl,
+    });
+    ResumeSubscription { token, resume_from: None, last_frame_at }
+}
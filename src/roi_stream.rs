@@ -0,0 +1,229 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use axum::extract::ws::{Message, WebSocket};
+use bytes::Bytes;
+use futures_util::{stream::StreamExt, SinkExt};
+use image::imageops::FilterType;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tracing::{debug, info, trace};
+
+use crate::errors::Result;
+use crate::jpeg;
+use crate::viewers::ViewerGuard;
+use crate::watermark::Watermark;
+
+// Output size used when the client does not ask for one
+const DEFAULT_MAX_WIDTH: u32 = 1280;
+const DEFAULT_MAX_HEIGHT: u32 = 720;
+// Upper bound for the requested output size
+const MAX_OUTPUT_SIZE: u32 = 3840;
+const DEFAULT_QUALITY: u8 = 80;
+
+/// Region requested by the client, in fractions (0.0 - 1.0) of the source frame
+#[derive(Debug, Clone, Deserialize)]
+struct RoiRequest {
+    #[serde(default)]
+    x: f64,
+    #[serde(default)]
+    y: f64,
+    #[serde(default = "full_extent")]
+    width: f64,
+    #[serde(default = "full_extent")]
+    height: f64,
+    #[serde(default)]
+    max_width: Option<u32>,
+    #[serde(default)]
+    max_height: Option<u32>,
+    #[serde(default)]
+    quality: Option<u8>,
+}
+
+fn full_extent() -> f64 {
+    1.0
+}
+
+/// Validated region of interest and output settings
+#[derive(Debug, Clone, Copy, Serialize)]
+struct Roi {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+    max_width: u32,
+    max_height: u32,
+    quality: u8,
+}
+
+impl RoiRequest {
+    fn validate(self) -> std::result::Result<Roi, String> {
+        let in_range = |v: f64| (0.0..=1.0).contains(&v);
+        if !in_range(self.x) || !in_range(self.y) {
+            return Err("x and y must be between 0 and 1".to_string());
+        }
+        if self.width <= 0.0 || self.height <= 0.0
+            || self.x + self.width > 1.0 + f64::EPSILON
+            || self.y + self.height > 1.0 + f64::EPSILON {
+            return Err("width and height must be positive and stay inside the frame".to_string());
+        }
+        let max_width = self.max_width.unwrap_or(DEFAULT_MAX_WIDTH);
+        let max_height = self.max_height.unwrap_or(DEFAULT_MAX_HEIGHT);
+        if max_width == 0 || max_height == 0 || max_width > MAX_OUTPUT_SIZE || max_height > MAX_OUTPUT_SIZE {
+            return Err(format!("max_width and max_height must be between 1 and {}", MAX_OUTPUT_SIZE));
+        }
+        let quality = self.quality.unwrap_or(DEFAULT_QUALITY);
+        if !(1..=100).contains(&quality) {
+            return Err("quality must be between 1 and 100".to_string());
+        }
+        Ok(Roi {
+            x: self.x,
+            y: self.y,
+            width: self.width.min(1.0 - self.x),
+            height: self.height.min(1.0 - self.y),
+            max_width,
+            max_height,
+            quality,
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum RoiReply {
+    Roi { roi: Option<Roi> },
+    Error { message: String },
+}
+
+/// Crop the region out of the camera's shared decoded frame at source resolution, scale it
+/// down to fit the output size and encode it again, drawing the client's watermark into the result
+fn crop_frame(camera_id: &str, frame: &Bytes, roi: &Roi, watermark: Option<&Watermark>) -> Result<Vec<u8>> {
+    let image = crate::watermark::decode_shared(camera_id, frame)?;
+    let (source_width, source_height) = (image.width() as f64, image.height() as f64);
+    let x = ((roi.x * source_width) as u32).min(image.width().saturating_sub(1));
+    let y = ((roi.y * source_height) as u32).min(image.height().saturating_sub(1));
+    let width = ((roi.width * source_width).round() as u32).clamp(1, image.width() - x);
+    let height = ((roi.height * source_height).round() as u32).clamp(1, image.height() - y);
+
+    let mut region = image::DynamicImage::ImageRgb8(image::imageops::crop_imm(image.as_ref(), x, y, width, height).to_image());
+    if width > roi.max_width || height > roi.max_height {
+        region = region.resize(roi.max_width, roi.max_height, FilterType::Triangle);
+    }
+
+    let mut region = region.to_rgb8();
+    if let Some(watermark) = watermark {
+        watermark.draw(&mut region);
+    }
+    jpeg::encode(&region, roi.quality)
+}
+
+/// Stream the live frames of a camera cropped to the region the client selects.
+/// Text messages set the region (`{"x":0.5,"y":0.5,"width":0.5,"height":0.5}`) or
+/// reset it (`{"reset":true}`); until then the frames are sent unchanged.
+pub async fn handle_roi_socket(
+    socket: WebSocket,
+    frame_sender: Arc<broadcast::Sender<Bytes>>,
+    latest_frame: Arc<tokio::sync::RwLock<Option<Bytes>>>,
+    camera_id: String,
+    client_addr: SocketAddr,
+    watermark: Option<Watermark>,
+    viewer: ViewerGuard,
+) {
+    let (mut sender, mut receiver) = socket.split();
+    let mut frames = frame_sender.subscribe();
+    info!("ROI client {} connected to camera {} (current connections: {})",
+          client_addr, camera_id, frame_sender.receiver_count());
+
+    let mut roi: Option<Roi> = None;
+    // The latest frame is re-sent after a region change so the client sees it immediately
+    let mut pending_frame: Option<Bytes> = None;
+    let mut frames_sent = 0u64;
+    let mut frames_dropped = 0u64;
+
+    loop {
+        let frame = if let Some(frame) = pending_frame.take() {
+            frame
+        } else {
+            tokio::select! {
+                frame = frames.recv() => match frame {
+                    Ok(frame) => frame,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        // Cropping is slower than the camera, keep up by skipping frames
+                        frames_dropped += skipped;
+                        trace!("ROI client {} of camera {} lagging, skipped {} frames", client_addr, camera_id, skipped);
+                        if viewer.lagged(skipped) {
+                            break;
+                        }
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                message = receiver.next() => {
+                    let text = match message {
+                        Some(Ok(Message::Text(text))) => text,
+                        Some(Ok(Message::Close(_))) | None | Some(Err(_)) => break,
+                        Some(Ok(_)) => continue,
+                    };
+                    let reply = match parse_roi_message(&text) {
+                        Ok(new_roi) => {
+                            debug!("ROI client {} of camera {} set region {:?}", client_addr, camera_id, new_roi);
+                            roi = new_roi;
+                            pending_frame = latest_frame.read().await.clone();
+                            RoiReply::Roi { roi }
+                        }
+                        Err(message) => RoiReply::Error { message },
+                    };
+                    let reply = serde_json::to_string(&reply).unwrap_or_default();
+                    if sender.send(Message::Text(reply)).await.is_err() {
+                        break;
+                    }
+                    continue;
+                }
+                _ = viewer.kicked() => break,
+            }
+        };
+
+        let data = match (roi, watermark.clone()) {
+            (None, None) => frame.to_vec(),
+            (Some(roi), watermark) => {
+                let shared_camera_id = camera_id.clone();
+                match tokio::task::spawn_blocking(move || crop_frame(&shared_camera_id, &frame, &roi, watermark.as_ref())).await {
+                    Ok(Ok(data)) => data,
+                    Ok(Err(e)) => {
+                        debug!("Failed to crop frame for ROI client {} of camera {}: {}", client_addr, camera_id, e);
+                        continue;
+                    }
+                    Err(_) => break,
+                }
+            }
+            (None, Some(watermark)) => match tokio::task::spawn_blocking(move || watermark.apply(&frame)).await {
+                Ok(Ok(data)) => data,
+                Ok(Err(e)) => {
+                    debug!("Failed to watermark frame for ROI client {} of camera {}: {}", client_addr, camera_id, e);
+                    continue;
+                }
+                Err(_) => break,
+            },
+        };
+        let data_len = data.len();
+        if sender.send(Message::Binary(data)).await.is_err() {
+            break;
+        }
+        frames_sent += 1;
+        viewer.stats().record_sent(data_len, 1);
+    }
+
+    info!("ROI client {} disconnected from camera {} (sent: {}, dropped: {})",
+          client_addr, camera_id, frames_sent, frames_dropped);
+}
+
+/// Parse a region message, None resets the stream to the unchanged frames
+fn parse_roi_message(text: &str) -> std::result::Result<Option<Roi>, String> {
+    let value: serde_json::Value = serde_json::from_str(text)
+        .map_err(|e| format!("Invalid ROI message: {}", e))?;
+    if value.get("reset").and_then(|v| v.as_bool()) == Some(true) {
+        return Ok(None);
+    }
+    let request: RoiRequest = serde_json::from_value(value)
+        .map_err(|e| format!("Invalid ROI message: {}", e))?;
+    request.validate().map(Some)
+}
@@ -1,27 +1,259 @@
-use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
-use tokio::sync::{broadcast, RwLock};
+use std::collections::VecDeque;
+use serde::Serialize;
+use tokio::sync::{broadcast, watch, Notify, RwLock};
 use tokio::time::{sleep, Duration};
-use tracing::{info, error, warn, trace};
+use tracing::{debug, info, error, warn, trace};
 use bytes::Bytes;
 use tokio::fs::OpenOptions;
 use tokio::io::AsyncWriteExt;
 
-use crate::config::{RtspConfig, FfmpegConfig, TranscodingConfig, CameraMqttConfig};
+use crate::config::{RtspConfig, ReconnectConfig, FfmpegConfig, TranscodingConfig, CameraMqttConfig, SnapshotSourceConfig, TestPattern, TestPatternConfig};
+use crate::snapshot_source::SnapshotFetcher;
 use crate::errors::{Result, StreamError};
 use crate::transcoder::FrameTranscoder;
+use crate::test_pattern::TestPatternGenerator;
 use crate::mqtt::{MqttHandle, CameraStatus};
-use chrono::Utc;
+use chrono::{DateTime, Utc};
+
+/// Connection state of a camera's capture loop
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConnectionState {
+    Connecting,
+    Streaming,
+    Retrying,
+    /// Gave up after `reconnect.fail_after_minutes` without frames
+    Failed,
+    /// Too many failures in a row, only probed at growing intervals
+    Quarantined,
+}
+
+/// Retry state of a camera. A flapping camera shows many recent failures but a recent
+/// `connected_at`, a dead one a `failing_since` far in the past.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReconnectStatus {
+    pub state: ConnectionState,
+    /// Failed attempts since the last frame
+    pub attempt: u32,
+    pub total_failures: u64,
+    pub failures_last_hour: usize,
+    pub failing_since: Option<DateTime<Utc>>,
+    pub next_retry_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    /// Start of the current (or last) period with frames
+    pub connected_at: Option<DateTime<Utc>>,
+    pub quarantined_since: Option<DateTime<Utc>>,
+    /// Failed probes since the camera was quarantined
+    pub probes: u32,
+}
+
+struct ReconnectTracker {
+    status: ReconnectStatus,
+    recent_failures: VecDeque<DateTime<Utc>>,
+}
+
+/// Shared handle to retune the FFmpeg pipeline of a running camera.
+/// Applying new settings restarts only the FFmpeg process; the broadcast channel
+/// and all connected clients stay in place. The same handle puts the camera into
+/// standby (FFmpeg stopped) and wakes it up again.
+#[derive(Clone)]
+pub struct PipelineControl {
+    ffmpeg_config: Arc<RwLock<Option<FfmpegConfig>>>,
+    capture_framerate: Arc<AtomicU32>,
+    restart: Arc<watch::Sender<u64>>, // Bumped to restart the running FFmpeg process
+    standby: Arc<AtomicBool>,
+    wake: Arc<Notify>,
+    last_activity: Arc<AtomicU64>, // Unix timestamp in seconds of the last viewer/API activity
+    last_frame: Arc<AtomicU64>, // Unix timestamp in seconds of the last frame received from the camera
+    reconnect: Arc<Mutex<ReconnectTracker>>,
+    retry_now: Arc<Notify>,
+}
+
+impl PipelineControl {
+    fn new(ffmpeg_config: Option<FfmpegConfig>, capture_framerate: u32) -> Self {
+        Self {
+            ffmpeg_config: Arc::new(RwLock::new(ffmpeg_config)),
+            capture_framerate: Arc::new(AtomicU32::new(capture_framerate)),
+            restart: Arc::new(watch::Sender::new(0)),
+            standby: Arc::new(AtomicBool::new(false)),
+            wake: Arc::new(Notify::new()),
+            last_activity: Arc::new(AtomicU64::new(Utc::now().timestamp() as u64)),
+            last_frame: Arc::new(AtomicU64::new(Utc::now().timestamp() as u64)),
+            reconnect: Arc::new(Mutex::new(ReconnectTracker {
+                status: ReconnectStatus {
+                    state: ConnectionState::Connecting,
+                    attempt: 0,
+                    total_failures: 0,
+                    failures_last_hour: 0,
+                    failing_since: None,
+                    next_retry_at: None,
+                    last_error: None,
+                    connected_at: None,
+                    quarantined_since: None,
+                    probes: 0,
+                },
+                recent_failures: VecDeque::new(),
+            })),
+            retry_now: Arc::new(Notify::new()),
+        }
+    }
+
+    /// Record viewer or API activity for the standby idle timer
+    pub fn touch(&self) {
+        self.last_activity.store(Utc::now().timestamp() as u64, Ordering::Relaxed);
+    }
+
+    /// Seconds since the last recorded activity
+    pub fn idle_secs(&self) -> u64 {
+        (Utc::now().timestamp() as u64).saturating_sub(self.last_activity.load(Ordering::Relaxed))
+    }
+
+    /// Record that a frame was received from the camera
+    pub fn frame_received(&self) {
+        self.last_frame.store(Utc::now().timestamp() as u64, Ordering::Relaxed);
+        if let Ok(mut tracker) = self.reconnect.lock() {
+            let status = &mut tracker.status;
+            if status.state != ConnectionState::Streaming {
+                status.state = ConnectionState::Streaming;
+                status.attempt = 0;
+                status.failing_since = None;
+                status.next_retry_at = None;
+                status.connected_at = Some(Utc::now());
+                status.quarantined_since = None;
+                status.probes = 0;
+            }
+        }
+    }
+
+    /// Current retry state of the camera
+    pub fn reconnect_status(&self) -> Option<ReconnectStatus> {
+        let mut tracker = self.reconnect.lock().ok()?;
+        let hour_ago = Utc::now() - chrono::Duration::hours(1);
+        while tracker.recent_failures.front().is_some_and(|t| *t < hour_ago) {
+            tracker.recent_failures.pop_front();
+        }
+        let mut status = tracker.status.clone();
+        status.failures_last_hour = tracker.recent_failures.len();
+        Some(status)
+    }
+
+    /// Record a failed connection attempt and return the delay before the next one: `retry_in`,
+    /// or the probe interval once the camera is quarantined by `policy`
+    fn connection_failed(&self, error: &str, retry_in: Duration, policy: &ReconnectConfig) -> Duration {
+        let Ok(mut tracker) = self.reconnect.lock() else { return retry_in; };
+        let now = Utc::now();
+        tracker.recent_failures.push_back(now);
+        let status = &mut tracker.status;
+        status.attempt += 1;
+        status.total_failures += 1;
+        status.failing_since.get_or_insert(now);
+        status.last_error = Some(error.to_string());
+        let delay = if policy.quarantines(status.attempt) {
+            if status.state == ConnectionState::Quarantined {
+                status.probes += 1;
+            } else {
+                status.quarantined_since = Some(now);
+            }
+            status.state = ConnectionState::Quarantined;
+            policy.probe_delay(status.probes + 1)
+        } else {
+            status.state = ConnectionState::Retrying;
+            retry_in
+        };
+        status.next_retry_at = Some(now + chrono::Duration::from_std(delay).unwrap_or_default());
+        delay
+    }
+
+    pub fn is_quarantined(&self) -> bool {
+        self.reconnect.lock().is_ok_and(|tracker| tracker.status.state == ConnectionState::Quarantined)
+    }
+
+    /// Lift the quarantine and retry right away. Returns false if the camera was not quarantined.
+    pub fn clear_quarantine(&self) -> bool {
+        let Ok(mut tracker) = self.reconnect.lock() else { return false; };
+        let status = &mut tracker.status;
+        if status.state != ConnectionState::Quarantined {
+            return false;
+        }
+        status.state = ConnectionState::Retrying;
+        status.attempt = 0;
+        status.quarantined_since = None;
+        status.probes = 0;
+        status.next_retry_at = Some(Utc::now());
+        drop(tracker);
+        self.retry_now.notify_waiters();
+        true
+    }
+
+    /// Time since the camera stopped delivering frames, None while it is streaming
+    fn failing_for(&self) -> Option<chrono::Duration> {
+        let tracker = self.reconnect.lock().ok()?;
+        tracker.status.failing_since.map(|since| Utc::now() - since)
+    }
+
+    fn connection_given_up(&self) {
+        if let Ok(mut tracker) = self.reconnect.lock() {
+            tracker.status.state = ConnectionState::Failed;
+            tracker.status.next_retry_at = None;
+        }
+    }
+
+    /// Seconds since the last frame was received (counted from startup if none arrived yet)
+    pub fn secs_since_last_frame(&self) -> u64 {
+        (Utc::now().timestamp() as u64).saturating_sub(self.last_frame.load(Ordering::Relaxed))
+    }
+
+    /// Time of the last frame received (startup if none arrived yet), second precision
+    pub fn last_frame_at(&self) -> DateTime<Utc> {
+        DateTime::from_timestamp(self.last_frame.load(Ordering::Relaxed) as i64, 0).unwrap_or_else(Utc::now)
+    }
+
+    pub fn is_standby(&self) -> bool {
+        self.standby.load(Ordering::Relaxed)
+    }
+
+    /// Stop the FFmpeg process until the camera is woken up again
+    pub fn enter_standby(&self) {
+        if !self.standby.swap(true, Ordering::Relaxed) {
+            self.request_restart();
+        }
+    }
+
+    /// Leave standby and restart FFmpeg. Returns true if the camera was in standby.
+    pub fn wake(&self) -> bool {
+        self.touch();
+        let was_standby = self.standby.swap(false, Ordering::Relaxed);
+        if was_standby {
+            // Give the restarted FFmpeg process the full offline timeout to deliver frames
+            self.frame_received();
+            self.wake.notify_one();
+        }
+        was_standby
+    }
+
+    /// Replace the FFmpeg settings and restart the FFmpeg process with them
+    pub async fn apply(&self, ffmpeg_config: Option<FfmpegConfig>, capture_framerate: u32) {
+        *self.ffmpeg_config.write().await = ffmpeg_config;
+        self.capture_framerate.store(capture_framerate, Ordering::Relaxed);
+        self.request_restart();
+    }
+
+    // Seen by the FFmpeg process running now; a process started later reads the new settings anyway
+    fn request_restart(&self) {
+        self.restart.send_modify(|generation| *generation = generation.wrapping_add(1));
+    }
+}
 
 pub struct RtspClient {
     camera_id: String,
     config: RtspConfig,
     frame_sender: Arc<broadcast::Sender<Bytes>>,
     transcoder: FrameTranscoder,
-    capture_framerate: u32,
-    ffmpeg_config: Option<FfmpegConfig>,
+    pipeline: PipelineControl,
     transcoding_config: TranscodingConfig,
     debug_capture: bool,
     debug_duplicate_frames: bool,
@@ -34,6 +266,7 @@ pub struct RtspClient {
     last_mqtt_publish_time: Arc<RwLock<Option<u128>>>, // Last MQTT image publish timestamp
     shutdown_flag: Arc<AtomicBool>,
     latest_frame: Arc<RwLock<Option<Bytes>>>, // Latest frame for snapshot API
+    resolved_url: Arc<RwLock<Option<String>>>, // RTSP URI resolved through ONVIF, cleared after failures
 }
 
 impl RtspClient {
@@ -42,6 +275,8 @@ impl RtspClient {
     }
 
     pub async fn new_from_builder(camera_id: String, config: RtspConfig, frame_sender: Arc<broadcast::Sender<Bytes>>, ffmpeg_config: Option<FfmpegConfig>, transcoding_config: TranscodingConfig, capture_framerate: u32, debug_capture: bool, debug_duplicate_frames: bool, mqtt_handle: Option<MqttHandle>, camera_mqtt_config: Option<CameraMqttConfig>, shutdown_flag: Option<Arc<AtomicBool>>, latest_frame: Arc<RwLock<Option<Bytes>>>) -> Self {
+        let pipeline = PipelineControl::new(ffmpeg_config.clone(), capture_framerate);
+        crate::offline_card::register(&camera_id, pipeline.clone());
         Self {
             camera_id,
             config,
@@ -51,8 +286,7 @@ impl RtspClient {
                     .and_then(|c| c.quality)
                     .unwrap_or(75)
             ).await,
-            capture_framerate,
-            ffmpeg_config,
+            pipeline,
             transcoding_config,
             debug_capture,
             debug_duplicate_frames,
@@ -65,12 +299,17 @@ impl RtspClient {
             last_mqtt_publish_time: Arc::new(RwLock::new(None)),
             shutdown_flag: shutdown_flag.unwrap_or_else(|| Arc::new(AtomicBool::new(false))),
             latest_frame,
+            resolved_url: Arc::new(RwLock::new(None)),
         }
     }
     
     pub fn get_fps_counter(&self) -> Arc<tokio::sync::RwLock<f32>> {
         self.capture_fps.clone()
     }
+
+    pub fn get_pipeline_control(&self) -> PipelineControl {
+        self.pipeline.clone()
+    }
     
     pub async fn start(&self) -> Result<()> {
         // Main capture loop
@@ -109,19 +348,32 @@ impl RtspClient {
                             last_frame_time: None,
                             ffmpeg_running: false,
                             duplicate_frames: 0, // No duplicates when disconnected
+                            ffmpeg_cpu_percent: None,
+                            ffmpeg_rss_kb: None,
+                            clock_drift_ms: crate::clock_drift::get_drift(&self.camera_id).map(|d| d.drift_ms),
                         };
                         mqtt.update_camera_status(self.camera_id.clone(), status).await;
                     }
                     
-                    info!("[{}] Reconnecting in {} seconds...", self.camera_id, self.config.reconnect_interval);
+                    self.forget_resolved_url().await;
+                    let delay = self.pipeline.connection_failed(&e.to_string(), Duration::from_secs(self.config.reconnect_interval), &self.config.reconnect);
+                    if let Some(fail_after) = self.config.reconnect.fail_after_minutes {
+                        if self.pipeline.failing_for().is_some_and(|d| d.num_minutes() >= fail_after as i64) {
+                            self.pipeline.connection_given_up();
+                            error!("[{}] No frames for {} minutes, giving up on the camera", self.camera_id, fail_after);
+                            return Err(StreamError::rtsp_connection(format!("Camera failed: no frames for {} minutes", fail_after)));
+                        }
+                    }
+                    if self.pipeline.is_quarantined() {
+                        warn!("[{}] Camera quarantined, probing again in {} seconds...", self.camera_id, delay.as_secs());
+                    } else {
+                        info!("[{}] Reconnecting in {} seconds...", self.camera_id, delay.as_secs());
+                    }
                     
                     // Check for shutdown during reconnect delay
-                    for _ in 0..self.config.reconnect_interval {
-                        if self.shutdown_flag.load(Ordering::Relaxed) {
-                            info!("[{}] Shutdown flag detected during reconnect delay, exiting", self.camera_id);
-                            return Ok(());
-                        }
-                        sleep(Duration::from_secs(1)).await;
+                    if !self.sleep_unless_shutdown(delay).await {
+                        info!("[{}] Shutdown flag detected during reconnect delay, exiting", self.camera_id);
+                        return Ok(());
                     }
                 }
             }
@@ -130,7 +382,14 @@ impl RtspClient {
     
 
     async fn connect_and_stream(&self) -> Result<()> {
-        info!("[{}] Connecting to RTSP stream: {}", self.camera_id, self.config.url);
+        if let Some(ref test_pattern) = self.config.test_pattern {
+            return self.generate_test_frames(test_pattern).await;
+        }
+        // An ONVIF camera without a resolvable stream is retried, not replaced by test frames
+        if self.config.onvif.is_some() {
+            self.stream_url().await?;
+        }
+        info!("[{}] Connecting to RTSP stream: {}", self.camera_id, crate::audit::redact_url(self.config.url.clone()));
         
         // Try to connect to real RTSP stream first
         match self.connect_real_rtsp().await {
@@ -154,6 +413,9 @@ impl RtspClient {
                             last_frame_time: None,
                             ffmpeg_running: false,
                             duplicate_frames: 0,
+                            ffmpeg_cpu_percent: None,
+                            ffmpeg_rss_kb: None,
+                            clock_drift_ms: crate::clock_drift::get_drift(&self.camera_id).map(|d| d.drift_ms),
                         };
                         mqtt.update_camera_status(self.camera_id.clone(), status).await;
                     }
@@ -164,7 +426,8 @@ impl RtspClient {
                 
                 // For other errors, fall back to test frames
                 info!("[{}] Falling back to test frame generation", self.camera_id);
-                self.generate_test_frames().await?;
+                let fallback = TestPatternConfig { pattern: TestPattern::Gradient, ..Default::default() };
+                self.generate_test_frames(&fallback).await?;
             }
         }
         
@@ -172,121 +435,238 @@ impl RtspClient {
     }
 
     async fn connect_real_rtsp(&self) -> Result<()> {
-        info!("[{}] Connecting to stream: {}", self.camera_id, self.config.url);
+        if let Some(ref device) = self.config.device {
+            info!("[{}] Starting capture from local device {}", self.camera_id, device.name);
+            return self.stream_rtsp_via_ffmpeg().await;
+        }
+        
+        let stream_url = self.stream_url().await?;
+        let source = self.config.onvif.as_ref().map_or(self.config.url.as_str(), |o| o.url.as_str());
+        info!("[{}] Connecting to stream: {}", self.camera_id, source);
         
         // Validate URL format
-        let _url = url::Url::parse(&self.config.url).map_err(|e| {
+        let _url = url::Url::parse(&stream_url).map_err(|e| {
             error!("[{}] Invalid URL format: {}", self.camera_id, e);
             StreamError::rtsp_connection(format!("Invalid URL: {}", e))
         })?;
         
+        if let Some(ref snapshot_source) = self.config.snapshot_source {
+            info!("[{}] Starting snapshot polling every {} ms", self.camera_id, snapshot_source.interval_ms);
+            return self.poll_snapshots(snapshot_source).await;
+        }
+        
         // Use FFmpeg directly for all stream types (RTSP, HTTP, HTTPS, etc.)
         info!("[{}] Starting stream capture via FFmpeg", self.camera_id);
         return self.stream_rtsp_via_ffmpeg().await;
     }
 
-    async fn generate_test_frames(&self) -> Result<()> {
-        info!("Starting test frame generation");
-        let mut _frame_count = 0u64;
+    /// URL to capture from: the configured `url`, or the RTSP URI resolved through ONVIF
+    async fn stream_url(&self) -> Result<String> {
+        let Some(ref onvif) = self.config.onvif else {
+            return Ok(self.config.url.clone());
+        };
+        if let Some(ref url) = *self.resolved_url.read().await {
+            return Ok(url.clone());
+        }
+        info!("[{}] Resolving stream URI through ONVIF service {}", self.camera_id, onvif.url);
+        let url = crate::onvif_source::resolve_stream_uri(&self.camera_id, onvif).await?;
+        *self.resolved_url.write().await = Some(url.clone());
+        Ok(url)
+    }
+
+    /// Resolve the ONVIF stream URI again on the next connection attempt
+    async fn forget_resolved_url(&self) {
+        if self.config.onvif.is_some() {
+            self.resolved_url.write().await.take();
+        }
+    }
+
+    /// Capture by fetching the camera's JPEG snapshot URL at a fixed interval instead of running FFmpeg
+    async fn poll_snapshots(&self, config: &SnapshotSourceConfig) -> Result<()> {
+        // Give up after this many failed requests in a row; start() reconnects after reconnect_interval
+        const MAX_CONSECUTIVE_FAILURES: u32 = 10;
+
+        let mut fetcher = SnapshotFetcher::new(&self.config.url, config)?;
+        let interval = Duration::from_millis(config.interval_ms.max(50));
+        let mut failures = 0u32;
+        let mut frame_count = 0u32;
         let mut last_log_time = tokio::time::Instant::now();
-        
+
         loop {
-            _frame_count += 1;
+            if self.shutdown_flag.load(Ordering::Relaxed) {
+                info!("[{}] Shutdown detected, stopping snapshot polling", self.camera_id);
+                return Ok(());
+            }
+            if self.pipeline.is_standby() {
+                self.wait_in_standby().await;
+                if self.shutdown_flag.load(Ordering::Relaxed) {
+                    return Ok(());
+                }
+                last_log_time = tokio::time::Instant::now();
+                frame_count = 0;
+            }
 
-            let jpeg_data = self.transcoder.create_test_frame().await?;
-            
-            // Send frame directly to broadcast
-            let _ = self.frame_sender.send(jpeg_data.clone());
-            
-            // Update latest frame storage for snapshot API
-            *self.latest_frame.write().await = Some(jpeg_data.clone());
-            
-            // Track picture arrival time for MQTT publishing (non-blocking)
-            if let Some(ref mqtt) = self.mqtt_handle {
-                let now = std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .unwrap()
-                    .as_millis();
-                    
-                let mut last_time_guard = self.last_picture_time.write().await;
-                let time_diff = if let Some(last_time) = *last_time_guard {
-                    now.saturating_sub(last_time)
-                } else {
-                    0 // First picture, no time difference
-                };
-                *last_time_guard = Some(now);
-                drop(last_time_guard);
-                
-                // Spawn MQTT publishing in background to avoid blocking frame processing
-                let mqtt_clone = mqtt.clone();
-                let camera_id_clone = self.camera_id.clone();
-                tokio::spawn(async move {
-                    mqtt_clone.publish_picture_arrival(&camera_id_clone, now, time_diff, 0).await; // Test frames have no meaningful size
-                });
-                
-                // Handle camera-specific MQTT image publishing for test frames
-                if let Some(ref camera_mqtt) = &self.camera_mqtt_config {
-                    let should_publish = if camera_mqtt.publish_interval == 0 {
-                        // Publish every frame when interval is 0
-                        true
-                    } else {
-                        // Check if enough time has passed since last publish
-                        let mut last_publish_guard = self.last_mqtt_publish_time.write().await;
-                        let should_publish = if let Some(last_publish) = *last_publish_guard {
-                            let interval_ms = camera_mqtt.publish_interval;
-                            now.saturating_sub(last_publish) >= interval_ms as u128
-                        } else {
-                            true // First publish
-                        };
-                        
-                        if should_publish {
-                            *last_publish_guard = Some(now);
-                        }
-                        drop(last_publish_guard);
-                        should_publish
-                    };
-                    
-                    if should_publish {
-                        // Clone necessary data for async task
-                        let mqtt_clone2 = mqtt.clone();
-                        let camera_id_clone2 = self.camera_id.clone();
-                        let jpeg_data_clone = jpeg_data.clone();
-                        let topic_name = camera_mqtt.topic_name.clone();
-                        
-                        // Spawn MQTT image publishing in background
-                        tokio::spawn(async move {
-                            if let Err(e) = mqtt_clone2.publish_camera_image(
-                                &camera_id_clone2,
-                                &jpeg_data_clone,
-                                topic_name.as_ref()
-                            ).await {
-                                error!("Failed to publish test camera image for {}: {}", camera_id_clone2, e);
-                            }
-                        });
+            let request_start = tokio::time::Instant::now();
+            match fetcher.fetch().await {
+                Ok(frame) => {
+                    failures = 0;
+                    frame_count += 1;
+                    self.distribute_snapshot(frame).await;
+                }
+                Err(e) => {
+                    failures += 1;
+                    warn!("[{}] Snapshot request failed ({} in a row): {}", self.camera_id, failures, e);
+                    if failures >= MAX_CONSECUTIVE_FAILURES {
+                        return Err(StreamError::rtsp_connection("Snapshot polling repeatedly failed"));
                     }
                 }
             }
-            
-            // Reset frame count every second for test frame generation
+
             let now = tokio::time::Instant::now();
-            if now.duration_since(last_log_time) >= Duration::from_secs(1) {
-                _frame_count = 0;
+            let elapsed = now.duration_since(last_log_time);
+            if elapsed >= Duration::from_secs(1) {
+                let fps = frame_count as f32 / elapsed.as_secs_f32();
+                *self.capture_fps.write().await = fps;
+                crate::throughput_tracker::update_ffmpeg_fps_globally(&self.camera_id, fps).await;
+                crate::throughput_tracker::update_connection_count_globally(&self.camera_id, self.frame_sender.receiver_count() as i32).await;
+                if let Some(ref mqtt) = self.mqtt_handle {
+                    let status = CameraStatus {
+                        id: self.camera_id.clone(),
+                        connected: failures == 0,
+                        capture_fps: fps,
+                        clients_connected: self.frame_sender.receiver_count(),
+                        last_frame_time: Some(Utc::now().to_rfc3339()),
+                        ffmpeg_running: false, // Snapshots are fetched without FFmpeg
+                        duplicate_frames: 0,
+                        ffmpeg_cpu_percent: None,
+                        ffmpeg_rss_kb: None,
+                        clock_drift_ms: crate::clock_drift::get_drift(&self.camera_id).map(|d| d.drift_ms),
+                    };
+                    mqtt.update_camera_status(self.camera_id.clone(), status).await;
+                }
+                frame_count = 0;
                 last_log_time = now;
             }
-            
-            // Generate frames at configured capture FPS
+
+            sleep(interval.saturating_sub(request_start.elapsed())).await;
+        }
+    }
+
+    /// Hand a polled snapshot or generated frame to the same consumers as an FFmpeg frame
+    async fn distribute_snapshot(&self, frame: Bytes) {
+        let frame_size = frame.len();
+        let _ = self.frame_sender.send(frame.clone());
+        self.pipeline.frame_received();
+        *self.latest_frame.write().await = Some(frame.clone());
+        crate::throughput_tracker::record_frame_globally(&self.camera_id, frame_size as i64).await;
+
+        let Some(ref mqtt) = self.mqtt_handle else { return; };
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis();
+        let time_diff = {
+            let mut last_time_guard = self.last_picture_time.write().await;
+            let time_diff = last_time_guard.map_or(0, |last_time| now.saturating_sub(last_time));
+            *last_time_guard = Some(now);
+            time_diff
+        };
+        let mqtt_clone = mqtt.clone();
+        let camera_id = self.camera_id.clone();
+        tokio::spawn(async move {
+            mqtt_clone.publish_picture_arrival(&camera_id, now, time_diff, frame_size).await;
+        });
+
+        if let Some(ref camera_mqtt) = self.camera_mqtt_config {
+            {
+                let mut last_publish_guard = self.last_mqtt_publish_time.write().await;
+                if camera_mqtt.publish_interval > 0
+                    && last_publish_guard.is_some_and(|last| now.saturating_sub(last) < camera_mqtt.publish_interval as u128) {
+                    return;
+                }
+                *last_publish_guard = Some(now);
+            }
+            let mqtt_clone = mqtt.clone();
+            let camera_id = self.camera_id.clone();
+            let topic_name = camera_mqtt.topic_name.clone();
+            tokio::spawn(async move {
+                if let Err(e) = mqtt_clone.publish_camera_image(&camera_id, &frame, topic_name.as_ref()).await {
+                    error!("Failed to publish camera image for {}: {}", camera_id, e);
+                }
+            });
+        }
+    }
+
+    /// Send generated test pattern frames until shutdown; `fps` defaults to the capture framerate
+    async fn generate_test_frames(&self, config: &TestPatternConfig) -> Result<()> {
+        info!("[{}] Starting test pattern generation ({:?}, {}x{})", self.camera_id, config.pattern, config.width, config.height);
+        let mut generator = TestPatternGenerator::new(&self.camera_id, config);
+        let mut in_burst = false;
+        let mut frame_count = 0u32;
+        let mut last_log_time = tokio::time::Instant::now();
+
+        loop {
+            if self.shutdown_flag.load(Ordering::Relaxed) {
+                info!("[{}] Shutdown detected, stopping test pattern generation", self.camera_id);
+                return Ok(());
+            }
+            if self.pipeline.is_standby() {
+                self.wait_in_standby().await;
+                if self.shutdown_flag.load(Ordering::Relaxed) {
+                    return Ok(());
+                }
+                last_log_time = tokio::time::Instant::now();
+                frame_count = 0;
+            }
+
+            let frame_start = tokio::time::Instant::now();
+            if generator.in_motion_burst() != in_burst {
+                in_burst = !in_burst;
+                debug!("[{}] Test pattern motion burst {}", self.camera_id, if in_burst { "started" } else { "ended" });
+            }
+            let frame = self.transcoder.create_test_frame(&mut generator)?;
+            frame_count += 1;
+            self.distribute_snapshot(frame).await;
+
+            let now = tokio::time::Instant::now();
+            let elapsed = now.duration_since(last_log_time);
+            if elapsed >= Duration::from_secs(1) {
+                let fps = frame_count as f32 / elapsed.as_secs_f32();
+                *self.capture_fps.write().await = fps;
+                crate::throughput_tracker::update_ffmpeg_fps_globally(&self.camera_id, fps).await;
+                crate::throughput_tracker::update_connection_count_globally(&self.camera_id, self.frame_sender.receiver_count() as i32).await;
+                if let Some(ref mqtt) = self.mqtt_handle {
+                    let status = CameraStatus {
+                        id: self.camera_id.clone(),
+                        connected: true,
+                        capture_fps: fps,
+                        clients_connected: self.frame_sender.receiver_count(),
+                        last_frame_time: Some(Utc::now().to_rfc3339()),
+                        ffmpeg_running: false, // Test frames are generated without FFmpeg
+                        duplicate_frames: 0,
+                        ffmpeg_cpu_percent: None,
+                        ffmpeg_rss_kb: None,
+                        clock_drift_ms: None,
+                    };
+                    mqtt.update_camera_status(self.camera_id.clone(), status).await;
+                }
+                frame_count = 0;
+                last_log_time = now;
+            }
+
             // Use default of 30 FPS if capture_framerate is 0 (indicating max available)
-            let effective_framerate = if self.capture_framerate == 0 { 30 } else { self.capture_framerate };
-            let frame_duration_ms = 1000 / effective_framerate as u64;
-            tokio::time::sleep(Duration::from_millis(frame_duration_ms)).await;
+            let framerate = config.fps.unwrap_or_else(|| self.pipeline.capture_framerate.load(Ordering::Relaxed));
+            let framerate = if framerate == 0 { 30 } else { framerate };
+            let frame_duration = Duration::from_micros(1_000_000 / framerate as u64);
+            sleep(frame_duration.saturating_sub(frame_start.elapsed())).await;
         }
     }
 
     async fn stream_rtsp_via_ffmpeg(&self) -> Result<()> {
         info!("🎥 Starting direct RTSP to MJPEG streaming via FFmpeg");
         
+        let policy = &self.config.reconnect;
         let mut retry_count = 0;
-        let max_retries = 10;
         
         loop {
             // Check for shutdown signal before starting
@@ -295,6 +675,14 @@ impl RtspClient {
                 return Ok(());
             }
             
+            if self.pipeline.is_standby() {
+                self.wait_in_standby().await;
+                if self.shutdown_flag.load(Ordering::Relaxed) {
+                    return Ok(());
+                }
+                retry_count = 0;
+            }
+            
             match self.run_ffmpeg_process().await {
                 Ok(_) => {
                     info!("FFmpeg process ended normally");
@@ -309,37 +697,104 @@ impl RtspClient {
                     
                     retry_count += 1;
                     error!("FFmpeg process failed (attempt {}): {}", retry_count, e);
+                    self.forget_resolved_url().await;
                     
-                    if retry_count >= max_retries {
-                        error!("FFmpeg failed {} times, giving up", max_retries);
+                    if policy.max_retries > 0 && retry_count >= policy.max_retries {
+                        error!("FFmpeg failed {} times, giving up", policy.max_retries);
                         return Err(StreamError::ffmpeg("FFmpeg process repeatedly failed"));
                     }
+                    if let Some(fail_after) = policy.fail_after_minutes {
+                        if self.pipeline.failing_for().is_some_and(|d| d.num_minutes() >= fail_after as i64) {
+                            return Err(StreamError::ffmpeg("FFmpeg process repeatedly failed"));
+                        }
+                    }
                     
-                    // Exponential backoff: 1s, 2s, 4s, 8s, 16s, max 30s
-                    let delay = std::cmp::min(1u64 << (retry_count - 1), 30);
-                    warn!("[{}] Waiting {} seconds before retrying FFmpeg...", self.camera_id, delay);
+                    // Exponential backoff, by default 1s, 2s, 4s, 8s, 16s, max 30s
+                    let delay = self.pipeline.connection_failed(&e.to_string(), policy.retry_delay(retry_count), policy);
+                    if self.pipeline.is_quarantined() {
+                        warn!("[{}] Camera quarantined, probing FFmpeg again in {} seconds...", self.camera_id, delay.as_secs());
+                    } else {
+                        warn!("[{}] Waiting {:.1} seconds before retrying FFmpeg...", self.camera_id, delay.as_secs_f64());
+                    }
                     
                     // Check for shutdown during delay
-                    for _ in 0..delay {
-                        if self.shutdown_flag.load(Ordering::Relaxed) {
-                            info!("[{}] Shutdown detected during retry delay, stopping FFmpeg", self.camera_id);
-                            return Ok(());
-                        }
-                        tokio::time::sleep(Duration::from_secs(1)).await;
+                    if !self.sleep_unless_shutdown(delay).await {
+                        info!("[{}] Shutdown detected during retry delay, stopping FFmpeg", self.camera_id);
+                        return Ok(());
                     }
                 }
             }
         }
     }
     
+    /// Sleep in steps of at most a second; false if the client is shut down meanwhile.
+    /// Clearing a quarantine ends the sleep early.
+    async fn sleep_unless_shutdown(&self, delay: Duration) -> bool {
+        let deadline = tokio::time::Instant::now() + delay;
+        let retry_now = self.pipeline.retry_now.notified();
+        tokio::pin!(retry_now);
+        loop {
+            if self.shutdown_flag.load(Ordering::Relaxed) {
+                return false;
+            }
+            let now = tokio::time::Instant::now();
+            if now >= deadline {
+                return true;
+            }
+            tokio::select! {
+                _ = sleep((deadline - now).min(Duration::from_secs(1))) => {}
+                _ = &mut retry_now => return true,
+            }
+        }
+    }
+
+    /// Keep FFmpeg stopped until the camera is woken up or the client shuts down
+    async fn wait_in_standby(&self) {
+        info!("[{}] 💤 Camera entered standby, FFmpeg capture stopped", self.camera_id);
+        *self.capture_fps.write().await = 0.0;
+        *self.latest_frame.write().await = None; // Snapshots must not return a stale frame
+        
+        if let Some(ref mqtt) = self.mqtt_handle {
+            let status = CameraStatus {
+                id: self.camera_id.clone(),
+                connected: false,
+                capture_fps: 0.0,
+                clients_connected: self.frame_sender.receiver_count(),
+                last_frame_time: None,
+                ffmpeg_running: false,
+                duplicate_frames: 0,
+                ffmpeg_cpu_percent: None,
+                ffmpeg_rss_kb: None,
+                clock_drift_ms: crate::clock_drift::get_drift(&self.camera_id).map(|d| d.drift_ms),
+            };
+            mqtt.update_camera_status(self.camera_id.clone(), status).await;
+        }
+        
+        while self.pipeline.is_standby() && !self.shutdown_flag.load(Ordering::Relaxed) {
+            tokio::select! {
+                _ = self.pipeline.wake.notified() => {}
+                _ = sleep(Duration::from_secs(1)) => {}
+            }
+        }
+        
+        if !self.shutdown_flag.load(Ordering::Relaxed) {
+            info!("[{}] ⏰ Camera woke up from standby, restarting FFmpeg capture", self.camera_id);
+        }
+    }
+    
     async fn run_ffmpeg_process(&self) -> Result<()> {
         // Use FFmpeg to directly read from RTSP and output MJPEG frames with low latency
-        let ffmpeg = self.ffmpeg_config.as_ref();
-        if self.capture_framerate > 0 {
+        // Snapshot the current pipeline settings; they may be changed at runtime via PipelineControl
+        let mut restart = self.pipeline.restart.subscribe();
+        let ffmpeg_config = self.pipeline.ffmpeg_config.read().await.clone();
+        let capture_framerate = self.pipeline.capture_framerate.load(Ordering::Relaxed);
+        let ffmpeg = ffmpeg_config.as_ref();
+        let stream_url = self.stream_url().await?;
+        if capture_framerate > 0 {
             if let Some(q) = ffmpeg.and_then(|c| c.quality) {
-                info!("Starting FFmpeg with capture framerate: {} FPS, quality: {}", self.capture_framerate, q);
+                info!("Starting FFmpeg with capture framerate: {} FPS, quality: {}", capture_framerate, q);
             } else {
-                info!("Starting FFmpeg with capture framerate: {} FPS, default quality", self.capture_framerate);
+                info!("Starting FFmpeg with capture framerate: {} FPS, default quality", capture_framerate);
             }
         } else {
             if let Some(q) = ffmpeg.and_then(|c| c.quality) {
@@ -357,8 +812,8 @@ impl RtspClient {
             .or(self.transcoding_config.output_framerate)
             .filter(|&fps| fps > 0)  // Only use if > 0
             .map(|fps| fps.to_string());
-        let fps_str = if self.capture_framerate > 0 {
-            Some(format!("fps={}", self.capture_framerate))
+        let fps_str = if capture_framerate > 0 {
+            Some(format!("fps={}", capture_framerate))
         } else {
             None
         };
@@ -384,7 +839,8 @@ impl RtspClient {
                     
                     // Replace placeholders in the command
                     for arg in args {
-                        let replaced_arg = arg.replace("$url", &self.config.url);
+                        let input = self.config.device.as_ref().map_or(stream_url.as_str(), |d| d.name.as_str());
+                        let replaced_arg = arg.replace("$url", input);
                         ffmpeg_args.push(replaced_arg.to_string());
                     }
                 }
@@ -432,7 +888,11 @@ impl RtspClient {
             // No default values - only use what's explicitly configured
             
             // Check if URL is RTSP to add RTSP-specific options
-            let is_rtsp_url = self.config.url.to_lowercase().starts_with("rtsp://");
+            let rtsp_args = match self.config.device {
+                Some(_) => Vec::new(),
+                None => crate::config::rtsp_input_args(&stream_url, &self.config.transport, self.config.tls.as_ref()),
+            };
+            let is_rtsp_url = !rtsp_args.is_empty();
             
             // Add RTSP buffer size if configured (in KB) and URL is RTSP
             if is_rtsp_url {
@@ -443,14 +903,18 @@ impl RtspClient {
                     info!("FFmpeg RTSP buffer size set to: {}", buffer_size_str);
                 }
                 
-                // Add RTSP transport option only for RTSP URLs
-                ffmpeg_args.push("-rtsp_transport".to_string());
-                ffmpeg_args.push(self.config.transport.clone());
+                // Add RTSP transport (and TLS options of rtsps://) only for RTSP URLs
+                ffmpeg_args.extend(rtsp_args);
             }
             
-            // Add input URL
-            ffmpeg_args.push("-i".to_string());
-            ffmpeg_args.push(self.config.url.clone());
+            // Add input URL, or the platform input device of a local camera
+            match self.config.device {
+                Some(ref device) => ffmpeg_args.extend(device.ffmpeg_input_args()),
+                None => {
+                    ffmpeg_args.push("-i".to_string());
+                    ffmpeg_args.push(stream_url.clone());
+                }
+            }
         
             // Add output format (default to mjpeg if not specified)
             let format = ffmpeg
@@ -493,6 +957,25 @@ impl RtspClient {
         // Build video filter chain if needed
         let mut video_filters = Vec::new();
         
+        // Deinterlace first so crop and rotation work on full frames
+        if ffmpeg.and_then(|c| c.deinterlace).unwrap_or(false) {
+            video_filters.push("yadif".to_string());
+        }
+        
+        // Crop in source coordinates, before the image is rotated or scaled
+        if let Some(crop) = ffmpeg.and_then(|c| c.crop.as_ref()).filter(|c| !c.trim().is_empty()) {
+            video_filters.push(format!("crop={}", crop.trim()));
+        }
+        
+        // Rotate clockwise
+        match ffmpeg.and_then(|c| c.rotate) {
+            None | Some(0) => {}
+            Some(90) => video_filters.push("transpose=clock".to_string()),
+            Some(180) => video_filters.push("hflip,vflip".to_string()),
+            Some(270) => video_filters.push("transpose=cclock".to_string()),
+            Some(other) => warn!("[{}] Ignoring unsupported rotation of {} degrees (use 90, 180 or 270)", self.camera_id, other),
+        }
+        
         // Add scale filter if specified
         if let Some(ref scale) = ffmpeg.and_then(|c| c.scale.as_ref()) {
             video_filters.push(format!("scale={}", scale));
@@ -557,6 +1040,12 @@ impl RtspClient {
                 }
             }
         
+            // Periodic key=value statistics on stderr for the stream health monitor
+            if self.config.stream_health.is_some() {
+                ffmpeg_args.push("-progress".to_string());
+                ffmpeg_args.push("pipe:2".to_string());
+            }
+
             ffmpeg_args.push("-".to_string());  // Output to stdout
         }
         
@@ -570,18 +1059,18 @@ impl RtspClient {
         // Log the full FFmpeg command
         let full_command = format!("{} {}", ffmpeg_path, ffmpeg_args.join(" "));
         info!("[{}] FFmpeg command: {}", self.camera_id, full_command);
+        crate::diagnostics::record_ffmpeg_command(&self.camera_id, ffmpeg_path, &ffmpeg_args);
 
-        // Determine stderr handling based on log_stderr config
-        // If log_stderr is not set or invalid, redirect to null to prevent buffer filling
-        let log_mode = ffmpeg.and_then(|c| c.log_stderr.as_ref());
-        let stderr_config = match log_mode {
-            Some(mode) if mode == "file" || mode == "console" || mode == "both" => {
-                std::process::Stdio::piped()
-            }
-            _ => {
-                // Redirect to null device to prevent FFmpeg from blocking on stderr buffer
-                std::process::Stdio::null()
-            }
+        // Determine stderr handling based on log_stderr config and the stream health monitor
+        // If neither needs stderr, redirect to null to prevent buffer filling
+        let log_mode = ffmpeg.and_then(|c| c.log_stderr.as_ref())
+            .filter(|mode| *mode == "file" || *mode == "console" || *mode == "both");
+        let stream_health = self.config.stream_health.clone();
+        let stderr_config = if log_mode.is_some() || stream_health.is_some() {
+            std::process::Stdio::piped()
+        } else {
+            // Redirect to null device to prevent FFmpeg from blocking on stderr buffer
+            std::process::Stdio::null()
         };
 
         let mut ffmpeg_cmd = tokio::process::Command::new(ffmpeg_path)
@@ -594,25 +1083,33 @@ impl RtspClient {
 
         info!("[{}] 📡 FFmpeg process started, reading MJPEG stream from camera", self.camera_id);
 
-        // Handle stderr logging if enabled
-        if let Some(log_mode) = log_mode {
-            if log_mode == "file" || log_mode == "console" || log_mode == "both" {
-                let stderr = ffmpeg_cmd.stderr.take()
-                    .ok_or_else(|| StreamError::ffmpeg("Failed to get FFmpeg stderr"))?;
+        // Track CPU/memory usage of this FFmpeg process until it exits
+        let _resource_guard = ffmpeg_cmd.id()
+            .map(|pid| crate::resources::register_ffmpeg_process(&self.camera_id, pid));
 
-                let log_filename = format!("{}.log", self.camera_id);
-                let camera_id = self.camera_id.clone();
-                let log_mode_clone = log_mode.clone();
+        // Handle stderr logging and stream health parsing if enabled
+        if log_mode.is_some() || stream_health.is_some() {
+            let stderr = ffmpeg_cmd.stderr.take()
+                .ok_or_else(|| StreamError::ffmpeg("Failed to get FFmpeg stderr"))?;
 
-                info!("[{}] FFmpeg stderr logging enabled (mode: {})", self.camera_id, log_mode);
+            let log_filename = format!("{}.log", self.camera_id);
+            let camera_id = self.camera_id.clone();
+            let log_mode_clone = log_mode.cloned();
+            let mqtt_handle = self.mqtt_handle.clone();
 
-                // Spawn a task to handle stderr logging
-                tokio::spawn(async move {
-                    if let Err(e) = log_ffmpeg_stderr(stderr, &log_filename, &camera_id, &log_mode_clone).await {
-                        error!("[{}] Failed to log FFmpeg stderr: {}", camera_id, e);
-                    }
-                });
+            if let Some(log_mode) = log_mode {
+                info!("[{}] FFmpeg stderr logging enabled (mode: {})", self.camera_id, log_mode);
+            }
+            if let Some(ref config) = stream_health {
+                crate::stream_health::process_started(&self.camera_id, config);
             }
+
+            // Spawn a task to handle stderr logging
+            tokio::spawn(async move {
+                if let Err(e) = read_ffmpeg_stderr(stderr, &log_filename, &camera_id, log_mode_clone.as_deref(), stream_health.is_some(), mqtt_handle).await {
+                    error!("[{}] Failed to log FFmpeg stderr: {}", camera_id, e);
+                }
+            });
         }
         
         let stdout = ffmpeg_cmd.stdout.take()
@@ -653,6 +1150,13 @@ impl RtspClient {
                     return Err(StreamError::ffmpeg("FFmpeg data timeout - process will be restarted"));
                 }
                 
+                // Restart requested because the pipeline settings changed
+                _ = restart.changed() => {
+                    info!("[{}] Pipeline settings changed, restarting FFmpeg process", self.camera_id);
+                    let _ = ffmpeg_cmd.kill().await;
+                    return Ok(());
+                }
+                
                 // Monitor FFmpeg process status
                 exit_status = ffmpeg_cmd.wait() => {
                     match exit_status {
@@ -728,6 +1232,7 @@ impl RtspClient {
                             
                             // Send frame directly to broadcast
                             let _ = self.frame_sender.send(Bytes::from(frame_data.clone()));
+                            self.pipeline.frame_received();
                             
                             // Update latest frame storage for snapshot API
                             *self.latest_frame.write().await = Some(Bytes::from(frame_data.clone()));
@@ -830,6 +1335,7 @@ impl RtspClient {
                                     *dup_count_guard = 0; // Reset counter after reading
                                     drop(dup_count_guard);
                                     
+                                    let ffmpeg_usage = crate::resources::get_ffmpeg_usage(&self.camera_id);
                                     let status = CameraStatus {
                                         id: self.camera_id.clone(),
                                         connected: true,
@@ -838,14 +1344,17 @@ impl RtspClient {
                                         last_frame_time: Some(Utc::now().to_rfc3339()),
                                         ffmpeg_running: true,
                                         duplicate_frames: duplicate_count,
+                                        ffmpeg_cpu_percent: ffmpeg_usage.as_ref().map(|u| u.cpu_percent),
+                                        ffmpeg_rss_kb: ffmpeg_usage.as_ref().map(|u| u.rss_kb),
+                                        clock_drift_ms: crate::clock_drift::get_drift(&self.camera_id).map(|d| d.drift_ms),
                                     };
                                     mqtt.update_camera_status(self.camera_id.clone(), status).await;
                                 }
                                 
                                 if self.debug_capture {
-                                    if self.capture_framerate > 0 {
+                                    if capture_framerate > 0 {
                                         trace!("[{}] Capturing: {:2}/s Target: {:2}/s", 
-                                               self.camera_id, frame_count, self.capture_framerate);
+                                               self.camera_id, frame_count, capture_framerate);
                                     } else {
                                         trace!("[{}] Capturing: {:2}/s", self.camera_id, frame_count);
                                     }
@@ -947,16 +1456,20 @@ impl RtspClient {
     }
 }
 
-async fn log_ffmpeg_stderr(
+/// Read FFmpeg's stderr: log it as configured and feed it to the stream health monitor.
+/// `-progress` statistics only go to the monitor.
+async fn read_ffmpeg_stderr(
     stderr: tokio::process::ChildStderr,
     log_filename: &str,
     camera_id: &str,
-    log_mode: &str,
+    log_mode: Option<&str>,
+    stream_health: bool,
+    mqtt_handle: Option<MqttHandle>,
 ) -> Result<()> {
     use tokio::io::{AsyncBufReadExt, BufReader};
     
     // Open or create the log file if needed
-    let mut log_file = if log_mode == "file" || log_mode == "both" {
+    let mut log_file = if let Some(log_mode) = log_mode.filter(|mode| *mode == "file" || *mode == "both") {
         let mut file = OpenOptions::new()
             .create(true)
             .append(true)
@@ -978,6 +1491,20 @@ async fn log_ffmpeg_stderr(
     let mut lines = reader.lines();
     
     while let Some(line) = lines.next_line().await? {
+        if stream_health {
+            if let Some(status) = crate::stream_health::handle_line(camera_id, &line) {
+                if let Some(ref mqtt) = mqtt_handle {
+                    let payload = serde_json::to_string(&status).unwrap_or_default();
+                    if let Err(e) = mqtt.publish_custom(&format!("cameras/{}/stream_health", camera_id), &payload).await {
+                        warn!("[{}] Failed to publish stream health: {}", camera_id, e);
+                    }
+                }
+            }
+            if crate::stream_health::is_progress_line(&line) {
+                continue;
+            }
+        }
+
         // Log to file if enabled
         if let Some(ref mut file) = log_file {
             let log_line = format!("{}\n", line);
@@ -986,7 +1513,7 @@ async fn log_ffmpeg_stderr(
         }
         
         // Log to console if enabled
-        if log_mode == "console" || log_mode == "both" {
+        if log_mode == Some("console") || log_mode == Some("both") {
             info!("[{}] FFmpeg: {}", camera_id, line);
         }
         
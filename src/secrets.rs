@@ -0,0 +1,324 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::RwLock;
+use aws_lc_rs::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use aws_lc_rs::digest::{digest, SHA256};
+use base64::engine::general_purpose::STANDARD as B64;
+use base64::Engine;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{error, info, warn};
+
+use crate::config::{CameraConfig, SecretsConfig};
+use crate::errors::{Result, StreamError};
+
+const PLACEHOLDER_PREFIX: &str = "${secret:";
+// Fallback for secrets missing in the file, e.g. RTSP_SECRET_CAM1_PASS for ${secret:cam1_pass}
+const ENV_PREFIX: &str = "RTSP_SECRET_";
+const FILE_VERSION: u32 = 1;
+
+lazy_static::lazy_static! {
+    static ref STORE: RwLock<SecretStore> = RwLock::new(SecretStore::default());
+}
+
+/// On-disk format: the AES-256-GCM encrypted JSON object of all secrets
+#[derive(Serialize, Deserialize)]
+struct SecretsFile {
+    version: u32,
+    nonce: String,
+    ciphertext: String,
+}
+
+#[derive(Default)]
+struct SecretStore {
+    config: SecretsConfig,
+    values: BTreeMap<String, String>,
+    // Set when the file exists but cannot be decrypted; writing would destroy its secrets
+    load_failed: bool,
+}
+
+/// Where a referenced secret is taken from
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretSource {
+    File,
+    Environment,
+    Missing,
+}
+
+/// Load the secrets file, a file that cannot be decrypted leaves the store read-only and empty
+pub fn init(config: &SecretsConfig) {
+    let mut store = STORE.write().unwrap_or_else(|e| e.into_inner());
+    store.config = config.clone();
+    match load(config) {
+        Ok(Some(values)) => {
+            info!("Loaded {} secrets from {}", values.len(), config.file());
+            store.values = values;
+        }
+        Ok(None) => {}
+        Err(e) => {
+            error!("Failed to load secrets file {}: {}", config.file(), e);
+            store.load_failed = true;
+        }
+    }
+}
+
+/// Whether the name can be used in a `${secret:<name>}` placeholder
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name.len() <= 128
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+fn env_name(name: &str) -> String {
+    format!("{}{}", ENV_PREFIX, name.to_ascii_uppercase().replace(['-', '.'], "_"))
+}
+
+pub fn source(name: &str) -> SecretSource {
+    let store = STORE.read().unwrap_or_else(|e| e.into_inner());
+    if store.values.contains_key(name) {
+        SecretSource::File
+    } else if std::env::var_os(env_name(name)).is_some() {
+        SecretSource::Environment
+    } else {
+        SecretSource::Missing
+    }
+}
+
+fn lookup(name: &str) -> Option<String> {
+    let store = STORE.read().unwrap_or_else(|e| e.into_inner());
+    store.values.get(name).cloned().or_else(|| std::env::var(env_name(name)).ok())
+}
+
+/// Names of the secrets stored in the file
+pub fn names() -> Vec<String> {
+    let store = STORE.read().unwrap_or_else(|e| e.into_inner());
+    store.values.keys().cloned().collect()
+}
+
+/// Store a secret and rewrite the encrypted file
+pub fn set(name: &str, value: &str) -> Result<()> {
+    let mut store = STORE.write().unwrap_or_else(|e| e.into_inner());
+    let mut values = store.values.clone();
+    values.insert(name.to_string(), value.to_string());
+    save(&store, &values)?;
+    store.values = values;
+    Ok(())
+}
+
+/// Remove a secret from the file, false if it was not stored
+pub fn remove(name: &str) -> Result<bool> {
+    let mut store = STORE.write().unwrap_or_else(|e| e.into_inner());
+    if !store.values.contains_key(name) {
+        return Ok(false);
+    }
+    let mut values = store.values.clone();
+    values.remove(name);
+    save(&store, &values)?;
+    store.values = values;
+    Ok(true)
+}
+
+/// Secret names referenced by `${secret:<name>}` placeholders anywhere in the text
+pub fn references(text: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(PLACEHOLDER_PREFIX) {
+        rest = &rest[start + PLACEHOLDER_PREFIX.len()..];
+        let Some(end) = rest.find('}') else { break };
+        let name = rest[..end].to_string();
+        if !names.contains(&name) {
+            names.push(name);
+        }
+        rest = &rest[end + 1..];
+    }
+    names
+}
+
+/// Secret names referenced anywhere in a camera configuration
+pub fn camera_references(camera_config: &CameraConfig) -> Vec<String> {
+    serde_json::to_string(camera_config).map(|json| references(&json)).unwrap_or_default()
+}
+
+/// Replace all `${secret:<name>}` placeholders of a value
+pub fn resolve(text: &str) -> Result<String> {
+    let mut resolved = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(PLACEHOLDER_PREFIX) {
+        resolved.push_str(&rest[..start]);
+        rest = &rest[start + PLACEHOLDER_PREFIX.len()..];
+        let end = rest.find('}')
+            .ok_or_else(|| StreamError::config("Unterminated secret placeholder"))?;
+        let name = &rest[..end];
+        let value = lookup(name)
+            .ok_or_else(|| StreamError::config(format!("Secret '{}' is neither stored nor set as {}", name, env_name(name))))?;
+        resolved.push_str(&value);
+        rest = &rest[end + 1..];
+    }
+    resolved.push_str(rest);
+    Ok(resolved)
+}
+
+/// Whether a provided credential equals a configured value that may contain placeholders.
+/// A value referencing a missing secret matches nothing, never its placeholder text.
+pub fn matches(configured: &str, provided: &str) -> bool {
+    if !configured.contains(PLACEHOLDER_PREFIX) {
+        return configured == provided;
+    }
+    resolve(configured).is_ok_and(|value| value == provided)
+}
+
+/// Copy of the camera configuration with all secrets filled in. Only the pipeline and device
+/// clients get to see it; the stored configuration and the API keep the placeholders.
+pub fn resolve_camera_config(camera_config: &CameraConfig) -> Result<CameraConfig> {
+    let mut value = serde_json::to_value(camera_config)?;
+    if !resolve_value(&mut value)? {
+        return Ok(camera_config.clone());
+    }
+    Ok(serde_json::from_value(value)?)
+}
+
+fn resolve_value(value: &mut Value) -> Result<bool> {
+    match value {
+        Value::String(text) if text.contains(PLACEHOLDER_PREFIX) => {
+            *text = resolve(text)?;
+            Ok(true)
+        }
+        Value::Array(items) => {
+            let mut changed = false;
+            for item in items {
+                changed |= resolve_value(item)?;
+            }
+            Ok(changed)
+        }
+        Value::Object(map) => {
+            let mut changed = false;
+            for item in map.values_mut() {
+                changed |= resolve_value(item)?;
+            }
+            Ok(changed)
+        }
+        _ => Ok(false),
+    }
+}
+
+fn load(config: &SecretsConfig) -> Result<Option<BTreeMap<String, String>>> {
+    let path = Path::new(config.file());
+    if !path.exists() {
+        return Ok(None);
+    }
+    let file: SecretsFile = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+    if file.version != FILE_VERSION {
+        return Err(StreamError::config(format!("Unsupported secrets file version {}", file.version)));
+    }
+    let key = encryption_key(config, false)?
+        .ok_or_else(|| StreamError::config(format!("Neither {} nor the key file {} is set", config.key_env(), config.key_file())))?;
+    let nonce = B64.decode(&file.nonce).ok()
+        .and_then(|nonce| Nonce::try_assume_unique_for_key(&nonce).ok())
+        .ok_or_else(|| StreamError::config("Invalid nonce in secrets file"))?;
+    let mut data = B64.decode(&file.ciphertext)
+        .map_err(|_| StreamError::config("Invalid ciphertext in secrets file"))?;
+    let plaintext = key.open_in_place(nonce, Aad::from(config.file().as_bytes()), &mut data)
+        .map_err(|_| StreamError::config("Secrets file cannot be decrypted with the configured key"))?;
+    Ok(Some(serde_json::from_slice(plaintext)?))
+}
+
+fn save(store: &SecretStore, values: &BTreeMap<String, String>) -> Result<()> {
+    if store.load_failed {
+        return Err(StreamError::config(format!("Secrets file {} could not be loaded at startup, refusing to overwrite it", store.config.file())));
+    }
+    let config = &store.config;
+    let key = encryption_key(config, true)?
+        .ok_or_else(|| StreamError::internal("No key for the secrets file"))?;
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::rngs::OsRng.fill_bytes(&mut nonce);
+    let mut data = serde_json::to_vec(values)?;
+    key.seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::from(config.file().as_bytes()), &mut data)
+        .map_err(|_| StreamError::internal("Failed to encrypt the secrets"))?;
+
+    let file = SecretsFile { version: FILE_VERSION, nonce: B64.encode(nonce), ciphertext: B64.encode(&data) };
+    write_private(Path::new(config.file()), serde_json::to_string_pretty(&file)?.as_bytes())
+}
+
+// The passphrase from the environment, else the key file, which is generated if `create` is set
+fn encryption_key(config: &SecretsConfig, create: bool) -> Result<Option<LessSafeKey>> {
+    let passphrase = match std::env::var(config.key_env()) {
+        Ok(passphrase) if !passphrase.trim().is_empty() => passphrase,
+        _ => {
+            let key_file = config.key_file();
+            match std::fs::read_to_string(&key_file) {
+                Ok(passphrase) => passphrase,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound && create => {
+                    let mut key = [0u8; 32];
+                    rand::rngs::OsRng.fill_bytes(&mut key);
+                    let passphrase: String = key.iter().map(|b| format!("{:02x}", b)).collect();
+                    write_private(Path::new(&key_file), passphrase.as_bytes())?;
+                    warn!("Generated secrets key file {}, keep a backup of it or set {} instead", key_file, config.key_env());
+                    passphrase
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+                Err(e) => return Err(e.into()),
+            }
+        }
+    };
+    let key = digest(&SHA256, passphrase.trim().as_bytes());
+    let key = UnboundKey::new(&AES_256_GCM, key.as_ref())
+        .map_err(|_| StreamError::internal("Invalid secrets key"))?;
+    Ok(Some(LessSafeKey::new(key)))
+}
+
+// Write through a temporary file, readable by the owner only
+pub(crate) fn write_private(path: &Path, data: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    let temp_path = path.with_extension("tmp");
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    std::io::Write::write_all(&mut options.open(&temp_path)?, data)?;
+    std::fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::TokenScope;
+
+    fn store_secret(name: &str, value: &str) {
+        STORE.write().unwrap_or_else(|e| e.into_inner()).values.insert(name.to_string(), value.to_string());
+    }
+
+    #[test]
+    fn matches_resolved_placeholders() {
+        store_secret("test_match_token", "s3cret");
+        assert!(matches("${secret:test_match_token}", "s3cret"));
+        assert!(matches("prefix-${secret:test_match_token}", "prefix-s3cret"));
+        assert!(!matches("${secret:test_match_token}", "${secret:test_match_token}"));
+        assert!(!matches("${secret:test_missing_token}", "${secret:test_missing_token}"));
+        assert!(matches("plain", "plain"));
+        assert!(!matches("plain", "other"));
+    }
+
+    #[test]
+    fn camera_tokens_resolve_placeholders() {
+        store_secret("test_camera_token", "live-token");
+        store_secret("test_scoped_token", "viewer-token");
+        let camera_config: CameraConfig = serde_json::from_value(serde_json::json!({
+            "path": "/cam1",
+            "url": "rtsp://camera/stream",
+            "transport": "tcp",
+            "reconnect_interval": 5,
+            "token": "${secret:test_camera_token}",
+            "tokens": [{ "name": "viewer", "token": "${secret:test_scoped_token}", "scopes": ["live"] }],
+        })).unwrap();
+        assert!(camera_config.token_grants("live-token", TokenScope::Playback));
+        assert!(camera_config.token_grants("viewer-token", TokenScope::Live));
+        assert!(!camera_config.token_grants("viewer-token", TokenScope::Playback));
+        assert!(!camera_config.token_grants("${secret:test_camera_token}", TokenScope::Live));
+        assert!(!camera_config.token_known("${secret:test_scoped_token}"));
+        assert!(camera_config.token_known("viewer-token"));
+    }
+}
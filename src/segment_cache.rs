@@ -0,0 +1,151 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tracing::{debug, info};
+
+lazy_static::lazy_static! {
+    static ref CACHE: Mutex<SegmentCache> = Mutex::new(SegmentCache::default());
+}
+
+/// Byte-bounded LRU of recently played MP4/HLS segments, so that several viewers scrubbing
+/// the same recording do not read the same multi-megabyte blobs again and again
+#[derive(Default)]
+struct SegmentCache {
+    max_bytes: usize,
+    ttl: Duration,
+    bytes: usize,
+    tick: u64,
+    entries: HashMap<String, CachedSegment>,
+    // Last use tick -> key, oldest first
+    lru: BTreeMap<u64, String>,
+    hits: u64,
+    misses: u64,
+}
+
+struct CachedSegment {
+    data: Bytes,
+    cached_at: Instant,
+    last_used: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SegmentCacheStats {
+    pub entries: usize,
+    pub bytes: usize,
+    pub max_bytes: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl SegmentCache {
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.lru.remove(&entry.last_used);
+            self.bytes -= entry.data.len();
+        }
+    }
+
+    fn touch(&mut self, key: &str) -> Option<Bytes> {
+        self.tick += 1;
+        let tick = self.tick;
+        let entry = self.entries.get_mut(key)?;
+        self.lru.remove(&entry.last_used);
+        entry.last_used = tick;
+        self.lru.insert(tick, key.to_string());
+        Some(entry.data.clone())
+    }
+}
+
+/// Set the cache size; 0 disables the cache
+pub fn configure(max_mb: u64, ttl_seconds: u64) {
+    let mut cache = CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    cache.max_bytes = (max_mb as usize).saturating_mul(1024 * 1024);
+    cache.ttl = Duration::from_secs(ttl_seconds);
+    if cache.max_bytes > 0 {
+        info!("Segment cache enabled: {} MB, {}s TTL", max_mb, ttl_seconds);
+    }
+}
+
+pub fn mp4_key(camera_id: &str, start_time: DateTime<Utc>) -> String {
+    format!("{}/mp4/{}", camera_id, start_time.timestamp_micros())
+}
+
+pub fn mp4_file_key(camera_id: &str, filename: &str) -> String {
+    format!("{}/file/{}", camera_id, filename)
+}
+
+pub fn hls_key(camera_id: &str, session_id: i64, segment_index: i32) -> String {
+    format!("{}/hls/{}/{}", camera_id, session_id, segment_index)
+}
+
+pub fn hls_proxy_key(camera_id: &str, session_id: i64, segment_index: i32) -> String {
+    format!("{}/hls-proxy/{}/{}", camera_id, session_id, segment_index)
+}
+
+pub fn playlist_key(camera_id: &str, playlist_id: &str, segment_name: &str) -> String {
+    format!("{}/playlist/{}/{}", camera_id, playlist_id, segment_name)
+}
+
+pub fn get(key: &str) -> Option<Bytes> {
+    let mut cache = CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    if cache.max_bytes == 0 {
+        return None;
+    }
+    let ttl = cache.ttl;
+    if cache.entries.get(key).is_some_and(|entry| entry.cached_at.elapsed() > ttl) {
+        cache.remove(key);
+    }
+    let data = cache.touch(key);
+    if data.is_some() {
+        cache.hits += 1;
+        debug!("Segment cache hit for '{}'", key);
+    } else {
+        cache.misses += 1;
+    }
+    data
+}
+
+/// Cache a segment, evicting the least recently used ones; segments larger than a quarter
+/// of the cache are not cached so that one export does not flush everything else
+pub fn insert(key: &str, data: Bytes) {
+    let mut cache = CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    if cache.max_bytes == 0 || data.len() > cache.max_bytes / 4 {
+        return;
+    }
+    cache.remove(key);
+    while cache.bytes + data.len() > cache.max_bytes {
+        let Some((_, oldest)) = cache.lru.pop_first() else { break };
+        if let Some(entry) = cache.entries.remove(&oldest) {
+            cache.bytes -= entry.data.len();
+        }
+    }
+    cache.tick += 1;
+    let tick = cache.tick;
+    cache.bytes += data.len();
+    cache.lru.insert(tick, key.to_string());
+    cache.entries.insert(key.to_string(), CachedSegment { data, cached_at: Instant::now(), last_used: tick });
+}
+
+/// Drop all cached segments of a camera, e.g. after recordings were deleted, merged or split
+pub fn invalidate_camera(camera_id: &str) {
+    let mut cache = CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    let prefix = format!("{}/", camera_id);
+    let keys: Vec<String> = cache.entries.keys().filter(|key| key.starts_with(&prefix)).cloned().collect();
+    for key in keys {
+        cache.remove(&key);
+    }
+}
+
+pub fn stats() -> SegmentCacheStats {
+    let cache = CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    SegmentCacheStats {
+        entries: cache.entries.len(),
+        bytes: cache.bytes,
+        max_bytes: cache.max_bytes,
+        hits: cache.hits,
+        misses: cache.misses,
+    }
+}
@@ -0,0 +1,222 @@
+use std::sync::Arc;
+use base64::{engine::general_purpose::STANDARD as B64, Engine as _};
+use bytes::Bytes;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::time::{timeout, Duration};
+use tokio_rustls::rustls;
+use tokio_rustls::TlsConnector;
+use tracing::debug;
+
+use crate::config::{SmtpConfig, SmtpTlsMode};
+use crate::errors::{Result, StreamError};
+
+// Upper bound for a complete SMTP conversation
+const SEND_TIMEOUT_SECS: u64 = 60;
+
+pub struct Attachment {
+    pub filename: String,
+    pub content_type: &'static str,
+    pub data: Bytes,
+}
+
+pub struct Email {
+    pub to: Vec<String>,
+    pub subject: String,
+    pub body: String,
+    pub attachment: Option<Attachment>,
+}
+
+trait SmtpStream: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> SmtpStream for T {}
+
+struct Connection {
+    stream: BufReader<Box<dyn SmtpStream>>,
+}
+
+impl Connection {
+    /// Read a (possibly multi-line) reply and return its code and text
+    async fn read_reply(&mut self) -> Result<(u16, String)> {
+        let mut text = String::new();
+        loop {
+            let mut line = String::new();
+            if self.stream.read_line(&mut line).await? == 0 {
+                return Err(StreamError::internal("SMTP server closed the connection"));
+            }
+            let line = line.trim_end();
+            let code = line.get(..3).and_then(|c| c.parse::<u16>().ok())
+                .ok_or_else(|| StreamError::internal(format!("Invalid SMTP reply: {}", line)))?;
+            text.push_str(line.get(4..).unwrap_or(""));
+            text.push('\n');
+            // "250-..." continues, "250 ..." ends the reply
+            if line.as_bytes().get(3) != Some(&b'-') {
+                return Ok((code, text));
+            }
+        }
+    }
+
+    async fn expect(&mut self, expected: u16) -> Result<String> {
+        let (code, text) = self.read_reply().await?;
+        if code != expected {
+            return Err(StreamError::internal(format!("SMTP server replied {} (expected {}): {}", code, expected, text.trim_end())));
+        }
+        Ok(text)
+    }
+
+    async fn command(&mut self, command: &str, expected: u16) -> Result<String> {
+        // Never log credentials
+        if !command.starts_with("AUTH") {
+            debug!("SMTP > {}", command);
+        }
+        let stream = self.stream.get_mut();
+        stream.write_all(command.as_bytes()).await?;
+        stream.write_all(b"\r\n").await?;
+        stream.flush().await?;
+        self.expect(expected).await
+    }
+
+    async fn upgrade_tls(self, host: &str) -> Result<Self> {
+        let stream = tls_connector()?.connect(server_name(host)?, self.stream.into_inner()).await?;
+        Ok(Self { stream: BufReader::new(Box::new(stream)) })
+    }
+}
+
+fn tls_connector() -> Result<TlsConnector> {
+    let mut roots = rustls::RootCertStore::empty();
+    roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    let config = rustls::ClientConfig::builder_with_provider(Arc::new(rustls::crypto::aws_lc_rs::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(|e| StreamError::internal(format!("Failed to create TLS configuration: {}", e)))?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    Ok(TlsConnector::from(Arc::new(config)))
+}
+
+fn server_name(host: &str) -> Result<rustls::pki_types::ServerName<'static>> {
+    rustls::pki_types::ServerName::try_from(host.to_string())
+        .map_err(|_| StreamError::config(format!("Invalid SMTP host name '{}'", host)))
+}
+
+/// Send a mail through the configured SMTP server
+pub async fn send_mail(config: &SmtpConfig, email: &Email) -> Result<()> {
+    if email.to.is_empty() {
+        return Err(StreamError::config("No mail recipients configured"));
+    }
+    timeout(Duration::from_secs(SEND_TIMEOUT_SECS), deliver(config, email)).await
+        .map_err(|_| StreamError::internal(format!("SMTP delivery to {}:{} timed out", config.host, config.port)))?
+}
+
+async fn deliver(config: &SmtpConfig, email: &Email) -> Result<()> {
+    let tcp = TcpStream::connect((config.host.as_str(), config.port)).await?;
+    let stream: Box<dyn SmtpStream> = match config.tls {
+        SmtpTlsMode::Tls => Box::new(tls_connector()?.connect(server_name(&config.host)?, tcp).await?),
+        SmtpTlsMode::None | SmtpTlsMode::Starttls => Box::new(tcp),
+    };
+    let mut connection = Connection { stream: BufReader::new(stream) };
+
+    let client_name = gethostname::gethostname().to_string_lossy().to_string();
+
+    connection.expect(220).await?;
+    let mut capabilities = connection.command(&format!("EHLO {}", client_name), 250).await?;
+
+    if config.tls == SmtpTlsMode::Starttls {
+        if !capabilities.lines().any(|l| l.eq_ignore_ascii_case("STARTTLS")) {
+            return Err(StreamError::internal(format!("SMTP server {} does not support STARTTLS", config.host)));
+        }
+        connection.command("STARTTLS", 220).await?;
+        connection = connection.upgrade_tls(&config.host).await?;
+        capabilities = connection.command(&format!("EHLO {}", client_name), 250).await?;
+    }
+
+    if let Some(ref username) = config.username {
+        if !capabilities.lines().any(|l| l.to_ascii_uppercase().starts_with("AUTH")) {
+            return Err(StreamError::internal(format!("SMTP server {} does not offer authentication", config.host)));
+        }
+        let password = config.password.as_deref().unwrap_or("");
+        let credentials = B64.encode(format!("\0{}\0{}", username, password));
+        connection.command(&format!("AUTH PLAIN {}", credentials), 235).await?;
+    }
+
+    connection.command(&format!("MAIL FROM:<{}>", envelope_address(&config.from)), 250).await?;
+    for recipient in &email.to {
+        connection.command(&format!("RCPT TO:<{}>", envelope_address(recipient)), 250).await?;
+    }
+    connection.command("DATA", 354).await?;
+
+    let message = dot_stuff(&build_message(&config.from, email));
+    let stream = connection.stream.get_mut();
+    stream.write_all(message.as_bytes()).await?;
+    stream.write_all(b".\r\n").await?;
+    stream.flush().await?;
+    connection.expect(250).await?;
+
+    // The mail is accepted at this point, a failing QUIT does not matter
+    let _ = connection.command("QUIT", 221).await;
+    Ok(())
+}
+
+/// Extract the address from "Name <user@host>"
+fn envelope_address(address: &str) -> &str {
+    match (address.find('<'), address.rfind('>')) {
+        (Some(start), Some(end)) if start < end => &address[start + 1..end],
+        _ => address.trim(),
+    }
+}
+
+/// RFC 2047 encoding for non-ASCII header values
+fn encode_header(value: &str) -> String {
+    if value.is_ascii() {
+        value.to_string()
+    } else {
+        format!("=?UTF-8?B?{}?=", B64.encode(value))
+    }
+}
+
+fn build_message(from: &str, email: &Email) -> String {
+    let now = chrono::Utc::now();
+    let domain = envelope_address(from).rsplit('@').next().unwrap_or("localhost");
+    let mut message = format!(
+        "From: {}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\nMessage-ID: <{}@{}>\r\nMIME-Version: 1.0\r\n",
+        from,
+        email.to.join(", "),
+        encode_header(&email.subject),
+        now.to_rfc2822(),
+        now.timestamp_nanos_opt().unwrap_or_default(),
+        domain,
+    );
+    let body = email.body.replace("\r\n", "\n").replace('\n', "\r\n");
+
+    match email.attachment {
+        None => {
+            message.push_str("Content-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: 8bit\r\n\r\n");
+            message.push_str(&body);
+            message.push_str("\r\n");
+        }
+        Some(ref attachment) => {
+            let boundary = format!("----=_Part_{}", now.timestamp_millis());
+            message.push_str(&format!("Content-Type: multipart/mixed; boundary=\"{}\"\r\n\r\n", boundary));
+            message.push_str(&format!("--{}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: 8bit\r\n\r\n", boundary));
+            message.push_str(&body);
+            message.push_str("\r\n");
+            message.push_str(&format!(
+                "--{}\r\nContent-Type: {}; name=\"{}\"\r\nContent-Transfer-Encoding: base64\r\nContent-Disposition: attachment; filename=\"{}\"\r\n\r\n",
+                boundary, attachment.content_type, attachment.filename, attachment.filename,
+            ));
+            let encoded = B64.encode(&attachment.data);
+            for chunk in encoded.as_bytes().chunks(76) {
+                message.push_str(std::str::from_utf8(chunk).unwrap_or_default());
+                message.push_str("\r\n");
+            }
+            message.push_str(&format!("--{}--\r\n", boundary));
+        }
+    }
+    message
+}
+
+/// Escape lines starting with "." so they do not end the DATA section
+fn dot_stuff(message: &str) -> String {
+    message.split("\r\n")
+        .map(|line| if line.starts_with('.') { format!(".{}", line) } else { line.to_string() })
+        .collect::<Vec<_>>()
+        .join("\r\n")
+}
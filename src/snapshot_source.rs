@@ -0,0 +1,204 @@
+use bytes::Bytes;
+use md5::{Digest, Md5};
+use reqwest::header::{AUTHORIZATION, WWW_AUTHENTICATE};
+use reqwest::StatusCode;
+use tokio::time::Duration;
+use tracing::debug;
+
+use crate::config::{SnapshotAuth, SnapshotSourceConfig};
+use crate::errors::{Result, StreamError};
+
+/// Fetches JPEG snapshots from a camera's HTTP(S) snapshot URL with Basic or Digest authentication
+pub struct SnapshotFetcher {
+    client: reqwest::Client,
+    url: url::Url,
+    credentials: Option<(String, String)>,
+    auth: SnapshotAuth,
+    // Digest challenge of the last 401 response, reused with an increasing nonce count
+    challenge: Option<DigestChallenge>,
+    // The camera asked for Basic auth, send it with every request from now on
+    basic: bool,
+}
+
+#[derive(Debug, Clone)]
+struct DigestChallenge {
+    realm: String,
+    nonce: String,
+    opaque: Option<String>,
+    qop_auth: bool,
+    nonce_count: u32,
+}
+
+impl SnapshotFetcher {
+    pub fn new(camera_url: &str, config: &SnapshotSourceConfig) -> Result<Self> {
+        let mut url = url::Url::parse(camera_url)?;
+        if url.scheme() != "http" && url.scheme() != "https" {
+            return Err(StreamError::config("Snapshot polling requires an http:// or https:// camera URL"));
+        }
+        // Credentials in the URL are used unless the config sets them explicitly
+        let url_username = urlencoding_decode(url.username());
+        let url_password = url.password().map(urlencoding_decode);
+        let _ = url.set_username("");
+        let _ = url.set_password(None);
+        let username = config.username.clone().or_else(|| Some(url_username).filter(|u| !u.is_empty()));
+        let password = config.password.clone().or(url_password);
+        let credentials = username.map(|u| (u, password.unwrap_or_default()));
+
+        let client = reqwest::Client::builder()
+            .use_rustls_tls()
+            .danger_accept_invalid_certs(config.accept_invalid_certs)
+            .timeout(Duration::from_millis(config.timeout_ms.max(100)))
+            .build()
+            .map_err(|e| StreamError::server(format!("Failed to build HTTP client: {}", e)))?;
+        Ok(Self {
+            client,
+            url,
+            credentials,
+            auth: config.auth,
+            challenge: None,
+            basic: config.auth == SnapshotAuth::Basic,
+        })
+    }
+
+    /// Fetch one snapshot. A 401 answer is retried once with the scheme the camera asked for.
+    pub async fn fetch(&mut self) -> Result<Bytes> {
+        let mut response = self.send().await?;
+        if response.status() == StatusCode::UNAUTHORIZED && self.credentials.is_some() {
+            let challenge = response.headers().get_all(WWW_AUTHENTICATE).iter()
+                .filter_map(|v| v.to_str().ok())
+                .map(str::to_string)
+                .collect::<Vec<_>>();
+            if !self.accept_challenge(&challenge) {
+                return Err(StreamError::rtsp_connection("Snapshot URL requires an unsupported authentication scheme"));
+            }
+            response = self.send().await?;
+        }
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(StreamError::rtsp_connection(format!("Snapshot request failed with status {}", status)));
+        }
+        let body = response.bytes().await
+            .map_err(|e| StreamError::rtsp_connection(format!("Failed to read snapshot: {}", e)))?;
+        if !body.starts_with(&[0xFF, 0xD8]) {
+            return Err(StreamError::rtsp_connection("Snapshot is not a JPEG image"));
+        }
+        Ok(body)
+    }
+
+    async fn send(&mut self) -> Result<reqwest::Response> {
+        let mut request = self.client.get(self.url.clone());
+        if let Some((ref username, ref password)) = self.credentials {
+            if self.basic {
+                request = request.basic_auth(username, Some(password));
+            } else if let Some(ref mut challenge) = self.challenge {
+                challenge.nonce_count += 1;
+                let uri = match self.url.query() {
+                    Some(query) => format!("{}?{}", self.url.path(), query),
+                    None => self.url.path().to_string(),
+                };
+                request = request.header(AUTHORIZATION, challenge.authorization(username, password, &uri));
+            }
+        }
+        request.send().await
+            .map_err(|e| StreamError::rtsp_connection(format!("Snapshot request failed: {}", e)))
+    }
+
+    /// Remember the scheme of a 401 response; false if none of the offered schemes can be used
+    fn accept_challenge(&mut self, headers: &[String]) -> bool {
+        if self.auth != SnapshotAuth::Basic {
+            if let Some(challenge) = headers.iter().find_map(|h| DigestChallenge::parse(h)) {
+                debug!("Snapshot URL {} requested Digest authentication (realm '{}')", self.url, challenge.realm);
+                self.challenge = Some(challenge);
+                self.basic = false;
+                return true;
+            }
+        }
+        if self.auth != SnapshotAuth::Digest && !self.basic
+            && headers.iter().any(|h| h.trim_start().to_ascii_lowercase().starts_with("basic")) {
+            debug!("Snapshot URL {} requested Basic authentication", self.url);
+            self.basic = true;
+            return true;
+        }
+        false
+    }
+}
+
+impl DigestChallenge {
+    fn parse(header: &str) -> Option<Self> {
+        let (scheme, params) = header.trim().split_once(' ')?;
+        if !scheme.eq_ignore_ascii_case("digest") {
+            return None;
+        }
+        let params = parse_auth_params(params);
+        let param = |name: &str| params.iter().find(|(k, _)| k.eq_ignore_ascii_case(name)).map(|(_, v)| v.clone());
+        // Only MD5 is supported; cameras offering SHA-256 usually offer MD5 as a second challenge
+        if param("algorithm").is_some_and(|a| !a.eq_ignore_ascii_case("md5")) {
+            return None;
+        }
+        Some(Self {
+            realm: param("realm").unwrap_or_default(),
+            nonce: param("nonce")?,
+            opaque: param("opaque"),
+            qop_auth: param("qop").is_some_and(|q| q.split(',').any(|q| q.trim().eq_ignore_ascii_case("auth"))),
+            nonce_count: 0,
+        })
+    }
+
+    /// Authorization header value for a GET of `uri` (RFC 2617)
+    fn authorization(&self, username: &str, password: &str, uri: &str) -> String {
+        let ha1 = md5_hex(&format!("{}:{}:{}", username, self.realm, password));
+        let ha2 = md5_hex(&format!("GET:{}", uri));
+        let mut header = format!("Digest username=\"{}\", realm=\"{}\", nonce=\"{}\", uri=\"{}\", algorithm=MD5",
+                                 username, self.realm, self.nonce, uri);
+        if self.qop_auth {
+            let nc = format!("{:08x}", self.nonce_count);
+            let cnonce = uuid::Uuid::new_v4().simple().to_string();
+            let response = md5_hex(&format!("{}:{}:{}:{}:auth:{}", ha1, self.nonce, nc, cnonce, ha2));
+            header.push_str(&format!(", qop=auth, nc={}, cnonce=\"{}\", response=\"{}\"", nc, cnonce, response));
+        } else {
+            let response = md5_hex(&format!("{}:{}:{}", ha1, self.nonce, ha2));
+            header.push_str(&format!(", response=\"{}\"", response));
+        }
+        if let Some(ref opaque) = self.opaque {
+            header.push_str(&format!(", opaque=\"{}\"", opaque));
+        }
+        header
+    }
+}
+
+// Split `key=value, key="quoted, value"` pairs of a WWW-Authenticate header
+fn parse_auth_params(params: &str) -> Vec<(String, String)> {
+    let mut result = Vec::new();
+    let mut rest = params.trim();
+    while let Some((key, after)) = rest.split_once('=') {
+        let key = key.trim().trim_start_matches(',').trim().to_string();
+        let after = after.trim_start();
+        let (value, remaining) = if let Some(quoted) = after.strip_prefix('"') {
+            match quoted.find('"') {
+                Some(end) => (&quoted[..end], &quoted[end + 1..]),
+                None => (quoted, ""),
+            }
+        } else {
+            match after.find(',') {
+                Some(end) => (&after[..end], &after[end..]),
+                None => (after, ""),
+            }
+        };
+        result.push((key, value.trim().to_string()));
+        rest = remaining.trim_start().trim_start_matches(',');
+    }
+    result
+}
+
+fn md5_hex(input: &str) -> String {
+    Md5::digest(input.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Percent-decode the user info of a URL (e.g. %40 in a password)
+pub(crate) fn urlencoding_decode(value: &str) -> String {
+    url::form_urlencoded::parse(format!("v={}", value.replace('+', "%2B")).as_bytes())
+        .next()
+        .map(|(_, v)| v.into_owned())
+        .unwrap_or_else(|| value.to_string())
+}
@@ -0,0 +1,710 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use tracing::{info, warn};
+
+use crate::database::{
+    apply_retention, AuditEntry, AuditQuery, BulkDeleteResult, DatabaseProvider, DeletedRecordingStats,
+    FrameStream, HlsPlaylist, HlsSegment, IntegrityHash, MotionHeatmap, Mp4Archive, PageRequest,
+    ReasonScope, RecordedFrame, RecordingEvent, RecordingHlsSegment, RecordingQuery, RecordingSession,
+    SchemaInfo, SessionAttachment, SessionLock, SessionMergeStats, SessionSplitStats, SessionStats, StorageStats,
+    ThroughputStats, TimeGap, VideoSegment,
+};
+use crate::errors::{Result, StreamError};
+
+/// Recording database of a camera split in two: frames and everything else go to the frame
+/// database, MP4 and HLS segments to the media database. Sessions live in the frame database
+/// and are mirrored under the same id to the media database, whose segment queries join them.
+pub struct SplitDatabase {
+    camera_id: String,
+    frames: Arc<dyn DatabaseProvider>,
+    media: Arc<dyn DatabaseProvider>,
+    // Sessions already mirrored, so segment writes skip the lookup
+    mirrored: Mutex<HashSet<i64>>,
+}
+
+impl SplitDatabase {
+    pub fn new(camera_id: &str, frames: Arc<dyn DatabaseProvider>, media: Arc<dyn DatabaseProvider>) -> Self {
+        Self {
+            camera_id: camera_id.to_string(),
+            frames,
+            media,
+            mirrored: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Copy the current state of a session to the media database
+    async fn mirror(&self, session_id: i64) -> Result<()> {
+        if let Some(session) = self.frames.get_recording_session(session_id).await? {
+            self.media.mirror_recording_session(&session).await?;
+            self.mirrored.lock().unwrap().insert(session_id);
+        }
+        Ok(())
+    }
+
+    /// Mirror a session before its first segment is written
+    async fn ensure_mirrored(&self, session_id: i64) -> Result<()> {
+        if self.mirrored.lock().unwrap().contains(&session_id) {
+            return Ok(());
+        }
+        self.mirror(session_id).await
+    }
+}
+
+#[async_trait]
+impl DatabaseProvider for SplitDatabase {
+    async fn initialize(&self) -> Result<()> {
+        self.frames.initialize().await?;
+        self.media.initialize().await
+    }
+
+    async fn create_recording_session(
+        &self,
+        camera_id: &str,
+        reason: Option<&str>,
+        start_time: chrono::DateTime<chrono::Utc>,
+    ) -> Result<i64> {
+        let session_id = self.frames.create_recording_session(camera_id, reason, start_time).await?;
+        self.mirror(session_id).await?;
+        Ok(session_id)
+    }
+
+    async fn stop_recording_session(&self, session_id: i64) -> Result<()> {
+        self.frames.stop_recording_session(session_id).await?;
+        self.mirror(session_id).await
+    }
+
+    async fn get_active_recordings(&self, camera_id: &str) -> Result<Vec<RecordingSession>> {
+        self.frames.get_active_recordings(camera_id).await
+    }
+
+    async fn get_session_reason(&self, session_id: i64) -> Result<Option<String>> {
+        self.frames.get_session_reason(session_id).await
+    }
+
+    async fn update_session_reason(&self, session_id: i64, reason: &str) -> Result<()> {
+        self.frames.update_session_reason(session_id, reason).await?;
+        self.mirror(session_id).await
+    }
+
+    async fn get_recording_session(&self, session_id: i64) -> Result<Option<RecordingSession>> {
+        self.frames.get_recording_session(session_id).await
+    }
+
+    async fn add_recorded_frame(
+        &self,
+        session_id: i64,
+        camera_id: &str,
+        timestamp: DateTime<Utc>,
+        frame_number: i64,
+        frame_data: &[u8],
+    ) -> Result<i64> {
+        self.frames.add_recorded_frame(session_id, camera_id, timestamp, frame_number, frame_data).await
+    }
+
+    async fn add_recorded_frames_bulk(
+        &self,
+        session_id: i64,
+        camera_id: &str,
+        frames: &[(DateTime<Utc>, i64, Vec<u8>)],
+    ) -> Result<u64> {
+        self.frames.add_recorded_frames_bulk(session_id, camera_id, frames).await
+    }
+
+    async fn list_recordings(&self, query: &RecordingQuery) -> Result<Vec<RecordingSession>> {
+        self.frames.list_recordings(query).await
+    }
+
+    async fn list_recordings_filtered(&self, camera_id: &str, from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>, reason: Option<&str>, page: &PageRequest) -> Result<Vec<RecordingSession>> {
+        self.frames.list_recordings_filtered(camera_id, from, to, reason, page).await
+    }
+
+    async fn count_recordings_filtered(&self, camera_id: &str, from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>, reason: Option<&str>) -> Result<i64> {
+        self.frames.count_recordings_filtered(camera_id, from, to, reason).await
+    }
+
+    async fn get_recorded_frames(
+        &self,
+        session_id: i64,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Result<Vec<RecordedFrame>> {
+        self.frames.get_recorded_frames(session_id, from, to).await
+    }
+
+    async fn delete_old_frames(
+        &self,
+        camera_id: Option<&str>,
+        older_than: DateTime<Utc>,
+        reasons: &ReasonScope,
+    ) -> Result<usize> {
+        self.frames.delete_old_frames(camera_id, older_than, reasons).await
+    }
+
+    async fn thin_old_frames(
+        &self,
+        camera_id: &str,
+        older_than: DateTime<Utc>,
+        interval_ms: i64,
+        reasons: &ReasonScope,
+    ) -> Result<usize> {
+        self.frames.thin_old_frames(camera_id, older_than, interval_ms, reasons).await
+    }
+
+    async fn delete_unused_sessions(
+        &self,
+        camera_id: Option<&str>,
+    ) -> Result<usize> {
+        // A session without frames may still have segments in the media database
+        let camera_id = camera_id.unwrap_or(&self.camera_id);
+        let mut deleted = 0;
+        for session_id in self.list_unused_sessions(camera_id).await? {
+            self.frames.delete_recording_session(session_id).await?;
+            self.mirrored.lock().unwrap().remove(&session_id);
+            deleted += 1;
+        }
+        if deleted > 0 {
+            info!("Deleted {} unused sessions for camera '{}'", deleted, camera_id);
+        }
+        // Mirrors of sessions without segments, they are mirrored again when needed
+        deleted += self.media.delete_unused_sessions(Some(camera_id)).await?;
+        Ok(deleted)
+    }
+
+    async fn list_unused_sessions(&self, camera_id: &str) -> Result<Vec<i64>> {
+        let unused_media: HashSet<i64> = self.media.list_unused_sessions(camera_id).await?.into_iter().collect();
+        let mut unused = Vec::new();
+        for session_id in self.frames.list_unused_sessions(camera_id).await? {
+            if unused_media.contains(&session_id) || self.media.get_recording_session(session_id).await?.is_none() {
+                unused.push(session_id);
+            }
+        }
+        Ok(unused)
+    }
+
+    async fn mirror_recording_session(&self, session: &RecordingSession) -> Result<()> {
+        self.frames.mirror_recording_session(session).await?;
+        self.media.mirror_recording_session(session).await
+    }
+
+    async fn get_frame_at_timestamp(
+        &self,
+        camera_id: &str,
+        timestamp: DateTime<Utc>,
+        tolerance_seconds: Option<i64>,
+    ) -> Result<Option<RecordedFrame>> {
+        self.frames.get_frame_at_timestamp(camera_id, timestamp, tolerance_seconds).await
+    }
+
+    async fn create_frame_stream(
+        &self,
+        camera_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Box<dyn FrameStream>> {
+        self.frames.create_frame_stream(camera_id, from, to).await
+    }
+
+    async fn get_database_size(&self) -> Result<i64> {
+        Ok(self.frames.get_database_size().await? + self.media.get_database_size().await?)
+    }
+
+    async fn get_schema_info(&self) -> Result<Vec<SchemaInfo>> {
+        let mut info = self.frames.get_schema_info().await?;
+        info.extend(self.media.get_schema_info().await?);
+        Ok(info)
+    }
+
+    async fn get_storage_stats(&self, camera_id: &str) -> Result<StorageStats> {
+        let frames = self.frames.get_storage_stats(camera_id).await?;
+        let media = self.media.get_storage_stats(camera_id).await?;
+        Ok(StorageStats {
+            frame_count: frames.frame_count,
+            frame_bytes: frames.frame_bytes,
+            oldest_recording: frames.oldest_recording.into_iter().chain(media.oldest_recording).min(),
+            newest_recording: frames.newest_recording.into_iter().chain(media.newest_recording).max(),
+            ..media
+        })
+    }
+
+    async fn get_session_stats(&self, session_ids: &[i64], gap_threshold_ms: i64) -> Result<HashMap<i64, SessionStats>> {
+        let mut stats = self.frames.get_session_stats(session_ids, gap_threshold_ms).await?;
+        let media = self.media.get_session_stats(session_ids, gap_threshold_ms).await?;
+        for (session_id, entry) in stats.iter_mut() {
+            if let Some(media) = media.get(session_id) {
+                entry.mp4_segments = media.mp4_segments;
+                entry.mp4_bytes = media.mp4_bytes;
+                entry.hls_segments = media.hls_segments;
+                entry.hls_bytes = media.hls_bytes;
+            }
+        }
+        Ok(stats)
+    }
+
+    async fn list_frame_gaps(&self, session_id: i64, gap_threshold_ms: i64, limit: i64) -> Result<Vec<TimeGap>> {
+        self.frames.list_frame_gaps(session_id, gap_threshold_ms, limit).await
+    }
+
+    async fn add_video_segment(&self, segment: &VideoSegment) -> Result<i64> {
+        self.ensure_mirrored(segment.session_id).await?;
+        self.media.add_video_segment(segment).await
+    }
+
+    async fn update_video_segment_progress(&self, segment: &VideoSegment) -> Result<()> {
+        self.media.update_video_segment_progress(segment).await
+    }
+
+    async fn list_video_segments(
+        &self,
+        camera_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<VideoSegment>> {
+        self.media.list_video_segments(camera_id, from, to).await
+    }
+
+    async fn list_video_segments_filtered(
+        &self,
+        camera_id: &str,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+        reason: Option<&str>,
+        page: &PageRequest,
+    ) -> Result<Vec<VideoSegment>> {
+        self.media.list_video_segments_filtered(camera_id, from, to, reason, page).await
+    }
+
+    async fn count_video_segments_filtered(
+        &self,
+        camera_id: &str,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+        reason: Option<&str>,
+    ) -> Result<i64> {
+        self.media.count_video_segments_filtered(camera_id, from, to, reason).await
+    }
+
+    async fn delete_old_video_segments(
+        &self,
+        camera_id: Option<&str>,
+        older_than: DateTime<Utc>,
+        reasons: &ReasonScope,
+    ) -> Result<usize> {
+        self.media.delete_old_video_segments(camera_id, older_than, reasons).await
+    }
+
+    async fn cleanup_database(
+        &self,
+        config: &crate::config::RecordingConfig,
+        camera_configs: &std::collections::HashMap<String, crate::config::CameraConfig>,
+    ) -> Result<()> {
+        let frames_lock = self.frames.lock_for_cleanup().await;
+        let media_lock = self.media.lock_for_cleanup().await;
+        let total_deleted = apply_retention(self, Some(&self.camera_id), config, camera_configs).await;
+        drop(media_lock);
+        drop(frames_lock);
+
+        if total_deleted > 0 && config.vacuum_after_cleanup {
+            info!("Deleted {} total rows, compacting frame and media databases", total_deleted);
+            for database in [&self.frames, &self.media] {
+                if let Err(e) = database.compact_database(config.vacuum_mode).await {
+                    tracing::error!("Error vacuuming database: {}", e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn get_video_segment_by_time(
+        &self,
+        camera_id: &str,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Option<VideoSegment>> {
+        self.media.get_video_segment_by_time(camera_id, timestamp).await
+    }
+
+    async fn store_hls_playlist(&self, playlist: &HlsPlaylist) -> Result<()> {
+        self.media.store_hls_playlist(playlist).await
+    }
+
+    async fn store_hls_segment(&self, segment: &HlsSegment) -> Result<()> {
+        self.media.store_hls_segment(segment).await
+    }
+
+    async fn store_hls_playlist_with_segments(&self, playlist: &HlsPlaylist, segments: &[HlsSegment]) -> Result<()> {
+        self.media.store_hls_playlist_with_segments(playlist, segments).await
+    }
+
+    async fn get_hls_playlist(&self, playlist_id: &str) -> Result<Option<HlsPlaylist>> {
+        self.media.get_hls_playlist(playlist_id).await
+    }
+
+    async fn get_hls_segment(&self, playlist_id: &str, segment_name: &str) -> Result<Option<HlsSegment>> {
+        self.media.get_hls_segment(playlist_id, segment_name).await
+    }
+
+    async fn cleanup_expired_hls(&self) -> Result<usize> {
+        self.media.cleanup_expired_hls().await
+    }
+
+    async fn add_recording_hls_segment(&self, segment: &RecordingHlsSegment) -> Result<i64> {
+        self.ensure_mirrored(segment.session_id).await?;
+        self.media.add_recording_hls_segment(segment).await
+    }
+
+    async fn list_recording_hls_segments(
+        &self,
+        session_id: i64,
+        from_time: Option<DateTime<Utc>>,
+        to_time: Option<DateTime<Utc>>,
+    ) -> Result<Vec<RecordingHlsSegment>> {
+        self.media.list_recording_hls_segments(session_id, from_time, to_time).await
+    }
+
+    async fn get_recording_hls_segments_for_timerange(
+        &self,
+        camera_id: &str,
+        from_time: DateTime<Utc>,
+        to_time: DateTime<Utc>,
+        session_id: Option<i64>,
+    ) -> Result<Vec<RecordingHlsSegment>> {
+        self.media.get_recording_hls_segments_for_timerange(camera_id, from_time, to_time, session_id).await
+    }
+
+    async fn delete_old_recording_hls_segments(
+        &self,
+        retention_duration: &str,
+        camera_id: Option<&str>,
+        reasons: &ReasonScope,
+    ) -> Result<usize> {
+        self.media.delete_old_recording_hls_segments(retention_duration, camera_id, reasons).await
+    }
+
+    async fn get_recording_hls_segment_by_session_and_index(
+        &self,
+        session_id: i64,
+        segment_index: i32,
+    ) -> Result<Option<RecordingHlsSegment>> {
+        self.media.get_recording_hls_segment_by_session_and_index(session_id, segment_index).await
+    }
+
+    async fn get_recording_hls_proxy_segment(
+        &self,
+        session_id: i64,
+        segment_index: i32,
+    ) -> Result<Option<Vec<u8>>> {
+        self.media.get_recording_hls_proxy_segment(session_id, segment_index).await
+    }
+
+    async fn get_last_hls_segment_index_for_session(
+        &self,
+        session_id: i64,
+    ) -> Result<Option<i32>> {
+        self.media.get_last_hls_segment_index_for_session(session_id).await
+    }
+
+    async fn set_session_keep_flag(
+        &self,
+        session_id: i64,
+        keep_session: bool,
+    ) -> Result<()> {
+        self.frames.set_session_keep_flag(session_id, keep_session).await?;
+        self.mirror(session_id).await
+    }
+
+    async fn delete_recording_session(&self, session_id: i64) -> Result<DeletedRecordingStats> {
+        // The frame database checks lock and status, the media copy only holds segments
+        let mut stats = self.frames.delete_recording_session(session_id).await?;
+        self.mirrored.lock().unwrap().remove(&session_id);
+        if self.media.get_recording_session(session_id).await?.is_some() {
+            let media = self.media.delete_recording_session(session_id).await?;
+            stats.mp4_segments_deleted = media.mp4_segments_deleted;
+            stats.hls_segments_deleted = media.hls_segments_deleted;
+        }
+        Ok(stats)
+    }
+
+    async fn merge_recording_sessions(&self, first_session_id: i64, second_session_id: i64) -> Result<SessionMergeStats> {
+        // Mirror both before the merge removes the second session from the frame database
+        self.mirror(first_session_id).await?;
+        self.mirror(second_session_id).await?;
+        let mut stats = self.frames.merge_recording_sessions(first_session_id, second_session_id).await?;
+        self.mirrored.lock().unwrap().remove(&second_session_id);
+        let media = self.media.merge_recording_sessions(first_session_id, second_session_id).await?;
+        stats.mp4_segments_moved = media.mp4_segments_moved;
+        stats.hls_segments_moved = media.hls_segments_moved;
+        self.mirror(first_session_id).await?;
+        Ok(stats)
+    }
+
+    async fn split_recording_session(&self, _session_id: i64, _split_time: DateTime<Utc>) -> Result<SessionSplitStats> {
+        // The new session id would have to be the same in both databases
+        Err(StreamError::config("Splitting sessions is not supported with a separate media database"))
+    }
+
+    async fn delete_mp4_segment_by_filename(&self, camera_id: &str, filename: &str) -> Result<i64> {
+        self.media.delete_mp4_segment_by_filename(camera_id, filename).await
+    }
+
+    async fn delete_mp4_segments_bulk(&self, camera_id: &str, filenames: Vec<String>) -> Result<BulkDeleteResult> {
+        self.media.delete_mp4_segments_bulk(camera_id, filenames).await
+    }
+
+    async fn delete_hls_segments_by_session(&self, session_id: i64) -> Result<u64> {
+        self.media.delete_hls_segments_by_session(session_id).await
+    }
+
+    async fn delete_hls_segments_by_timerange(&self, camera_id: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<u64> {
+        self.media.delete_hls_segments_by_timerange(camera_id, from, to).await
+    }
+
+    async fn vacuum_tables(&self) -> Result<()> {
+        self.frames.vacuum_tables().await?;
+        self.media.vacuum_tables().await
+    }
+
+    async fn compact_database(&self, mode: crate::config::VacuumMode) -> Result<()> {
+        self.frames.compact_database(mode).await?;
+        self.media.compact_database(mode).await
+    }
+
+    async fn get_mp4_segments_in_range(&self, camera_id: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<crate::export_jobs::Mp4SegmentInfo>> {
+        self.media.get_mp4_segments_in_range(camera_id, from, to).await
+    }
+
+    async fn extract_mp4_segment_to_file(&self, camera_id: &str, start_time: DateTime<Utc>, output_path: &str) -> Result<()> {
+        self.media.extract_mp4_segment_to_file(camera_id, start_time, output_path).await
+    }
+
+    async fn record_throughput_stats(
+        &self,
+        camera_id: &str,
+        timestamp: DateTime<Utc>,
+        bytes_per_second: i64,
+        frame_count: i32,
+        ffmpeg_fps: f32,
+        connection_count: i32,
+    ) -> Result<()> {
+        self.frames.record_throughput_stats(camera_id, timestamp, bytes_per_second, frame_count, ffmpeg_fps, connection_count).await
+    }
+
+    async fn get_throughput_stats(
+        &self,
+        camera_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<ThroughputStats>> {
+        self.frames.get_throughput_stats(camera_id, from, to).await
+    }
+
+    async fn add_audit_entry(&self, entry: &AuditEntry) -> Result<i64> {
+        self.frames.add_audit_entry(entry).await
+    }
+
+    async fn list_audit_entries(&self, query: &AuditQuery, page: &PageRequest) -> Result<Vec<AuditEntry>> {
+        self.frames.list_audit_entries(query, page).await
+    }
+
+    async fn count_audit_entries(&self, query: &AuditQuery) -> Result<i64> {
+        self.frames.count_audit_entries(query).await
+    }
+
+    async fn add_recording_event(&self, event: &RecordingEvent) -> Result<i64> {
+        self.frames.add_recording_event(event).await
+    }
+
+    async fn list_recording_events(
+        &self,
+        camera_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<RecordingEvent>> {
+        self.frames.list_recording_events(camera_id, from, to).await
+    }
+
+    async fn get_recording_event(&self, event_id: i64) -> Result<Option<RecordingEvent>> {
+        self.frames.get_recording_event(event_id).await
+    }
+
+    async fn delete_old_recording_events(
+        &self,
+        camera_id: Option<&str>,
+        older_than: DateTime<Utc>,
+        reasons: &ReasonScope,
+    ) -> Result<usize> {
+        self.frames.delete_old_recording_events(camera_id, older_than, reasons).await
+    }
+
+    async fn cleanup_old_throughput_stats(
+        &self,
+        older_than: DateTime<Utc>,
+    ) -> Result<u64> {
+        self.frames.cleanup_old_throughput_stats(older_than).await
+    }
+
+    async fn add_integrity_hash(&self, hash: &IntegrityHash) -> Result<()> {
+        self.frames.add_integrity_hash(hash).await
+    }
+
+    async fn list_integrity_hashes(
+        &self,
+        camera_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<IntegrityHash>> {
+        self.frames.list_integrity_hashes(camera_id, from, to).await
+    }
+
+    async fn delete_old_integrity_hashes(
+        &self,
+        camera_id: Option<&str>,
+        data_type: &str,
+        older_than: DateTime<Utc>,
+        reasons: &ReasonScope,
+    ) -> Result<usize> {
+        self.frames.delete_old_integrity_hashes(camera_id, data_type, older_than, reasons).await
+    }
+
+    async fn list_integrity_hashes_to_thin(
+        &self,
+        camera_id: &str,
+        older_than: DateTime<Utc>,
+        interval_ms: i64,
+    ) -> Result<Vec<IntegrityHash>> {
+        self.frames.list_integrity_hashes_to_thin(camera_id, older_than, interval_ms).await
+    }
+
+    async fn update_integrity_hash(&self, hash: &IntegrityHash) -> Result<()> {
+        self.frames.update_integrity_hash(hash).await
+    }
+
+    async fn add_recording_thumbnail(&self, camera_id: &str, timestamp: DateTime<Utc>, thumbnail: &[u8]) -> Result<()> {
+        self.frames.add_recording_thumbnail(camera_id, timestamp, thumbnail).await
+    }
+
+    async fn get_recording_thumbnail(
+        &self,
+        session_id: i64,
+        timestamp: Option<DateTime<Utc>>,
+    ) -> Result<Option<(DateTime<Utc>, Vec<u8>)>> {
+        self.frames.get_recording_thumbnail(session_id, timestamp).await
+    }
+
+    async fn delete_orphaned_thumbnails(&self, camera_id: Option<&str>) -> Result<usize> {
+        self.frames.delete_orphaned_thumbnails(camera_id).await
+    }
+
+    async fn store_motion_heatmap(&self, heatmap: &MotionHeatmap) -> Result<()> {
+        self.frames.store_motion_heatmap(heatmap).await
+    }
+
+    async fn list_motion_heatmaps(
+        &self,
+        camera_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<MotionHeatmap>> {
+        self.frames.list_motion_heatmaps(camera_id, from, to).await
+    }
+
+    async fn delete_old_motion_heatmaps(&self, camera_id: &str, older_than: DateTime<Utc>) -> Result<usize> {
+        self.frames.delete_old_motion_heatmaps(camera_id, older_than).await
+    }
+
+    async fn add_session_attachment(&self, attachment: &SessionAttachment) -> Result<()> {
+        self.frames.add_session_attachment(attachment).await
+    }
+
+    async fn list_session_attachments(&self, session_id: i64) -> Result<Vec<SessionAttachment>> {
+        self.frames.list_session_attachments(session_id).await
+    }
+
+    async fn get_session_attachment(&self, attachment_id: &str) -> Result<Option<SessionAttachment>> {
+        self.frames.get_session_attachment(attachment_id).await
+    }
+
+    async fn list_session_attachments_in_range(
+        &self,
+        camera_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<SessionAttachment>> {
+        self.frames.list_session_attachments_in_range(camera_id, from, to).await
+    }
+
+    async fn delete_session_attachment(&self, attachment_id: &str) -> Result<bool> {
+        self.frames.delete_session_attachment(attachment_id).await
+    }
+
+    async fn delete_orphaned_attachments(&self, camera_id: Option<&str>) -> Result<usize> {
+        self.frames.delete_orphaned_attachments(camera_id).await
+    }
+
+    async fn lock_recording_session(&self, lock: &SessionLock) -> Result<()> {
+        self.frames.lock_recording_session(lock).await?;
+        // The media database keeps segments of locked sessions out of the cold tier
+        self.mirror(lock.session_id).await?;
+        if let Err(e) = self.media.lock_recording_session(lock).await {
+            // A half-locked session could lose its segments to the cold tier, so roll the lock back
+            warn!("Failed to lock session {} in the media database: {}", lock.session_id, e);
+            if let Err(rollback) = self.frames.unlock_recording_session(lock.session_id).await {
+                warn!("Failed to roll back the lock of session {}: {}", lock.session_id, rollback);
+            }
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    async fn get_session_lock(&self, session_id: i64) -> Result<Option<SessionLock>> {
+        self.frames.get_session_lock(session_id).await
+    }
+
+    async fn list_session_locks(&self, camera_id: &str) -> Result<Vec<SessionLock>> {
+        self.frames.list_session_locks(camera_id).await
+    }
+
+    async fn request_session_unlock(&self, session_id: i64, requested_by: &str) -> Result<()> {
+        self.frames.request_session_unlock(session_id, requested_by).await
+    }
+
+    async fn unlock_recording_session(&self, session_id: i64) -> Result<bool> {
+        let unlocked = self.frames.unlock_recording_session(session_id).await?;
+        if let Err(e) = self.media.unlock_recording_session(session_id).await {
+            warn!("Failed to unlock session {} in the media database: {}", session_id, e);
+        }
+        Ok(unlocked)
+    }
+
+    async fn list_mp4_segments_to_archive(&self, camera_id: &str, older_than: DateTime<Utc>, limit: i64) -> Result<Vec<VideoSegment>> {
+        self.media.list_mp4_segments_to_archive(camera_id, older_than, limit).await
+    }
+
+    async fn archive_mp4_segment(&self, archive: &Mp4Archive) -> Result<()> {
+        self.media.archive_mp4_segment(archive).await
+    }
+
+    async fn get_mp4_archive(&self, camera_id: &str, start_time: DateTime<Utc>) -> Result<Option<Mp4Archive>> {
+        self.media.get_mp4_archive(camera_id, start_time).await
+    }
+
+    async fn list_archived_mp4_segments(&self, camera_id: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<Mp4Archive>> {
+        self.media.list_archived_mp4_segments(camera_id, from, to).await
+    }
+
+    async fn restore_mp4_segment(&self, camera_id: &str, start_time: DateTime<Utc>, file_path: Option<&str>, mp4_data: Option<&[u8]>) -> Result<()> {
+        self.media.restore_mp4_segment(camera_id, start_time, file_path, mp4_data).await
+    }
+
+    async fn list_rehydrated_mp4_segments(&self, camera_id: &str, before: DateTime<Utc>) -> Result<Vec<Mp4Archive>> {
+        self.media.list_rehydrated_mp4_segments(camera_id, before).await
+    }
+
+    async fn evict_mp4_segment(&self, camera_id: &str, start_time: DateTime<Utc>) -> Result<()> {
+        self.media.evict_mp4_segment(camera_id, start_time).await
+    }
+
+    async fn delete_orphaned_mp4_archives(&self, camera_id: &str) -> Result<usize> {
+        self.media.delete_orphaned_mp4_archives(camera_id).await
+    }
+}
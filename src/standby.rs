@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+use tokio::time::{Duration, interval};
+use tracing::{debug, info};
+
+use crate::config::{CameraConfig, ServerConfig};
+use crate::rtsp_client::PipelineControl;
+use crate::AppState;
+
+// How often idle cameras are checked
+const CHECK_INTERVAL_SECS: u64 = 30;
+
+lazy_static::lazy_static! {
+    // Camera path -> camera id and pipeline, so requests find their camera without scanning all cameras
+    static ref CAMERA_PATHS: RwLock<HashMap<String, (String, PipelineControl)>> = RwLock::new(HashMap::new());
+}
+
+/// Let requests below `path` wake the camera
+pub fn register_path(path: &str, camera_id: &str, pipeline: PipelineControl) {
+    let mut paths = CAMERA_PATHS.write().unwrap_or_else(|e| e.into_inner());
+    paths.retain(|_, (id, _)| id != camera_id);
+    paths.insert(path.trim_end_matches('/').to_string(), (camera_id.to_string(), pipeline));
+}
+
+pub fn unregister_path(camera_id: &str) {
+    CAMERA_PATHS.write().unwrap_or_else(|e| e.into_inner()).retain(|_, (id, _)| id != camera_id);
+}
+
+// The camera whose path is the request path or one of its parents
+fn camera_for_path(path: &str) -> Option<(String, PipelineControl)> {
+    let paths = CAMERA_PATHS.read().unwrap_or_else(|e| e.into_inner());
+    let mut prefix = path.trim_end_matches('/');
+    while !prefix.is_empty() {
+        if let Some(camera) = paths.get(prefix) {
+            return Some(camera.clone());
+        }
+        prefix = &prefix[..prefix.rfind('/')?];
+    }
+    None
+}
+
+/// Effective idle timeout of a camera in minutes (0 = standby disabled)
+pub fn standby_after_minutes(camera_config: &CameraConfig, server_config: &ServerConfig) -> u64 {
+    camera_config.standby_after_minutes.unwrap_or(server_config.standby_after_minutes)
+}
+
+/// Start the task that puts idle cameras into standby. A camera is idle when it has no
+/// frame subscribers (viewers, recording), no pre-recording buffer, no open or upcoming
+/// schedule window and no recent API activity.
+pub fn start_standby_monitor(state: AppState) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = interval(Duration::from_secs(CHECK_INTERVAL_SECS));
+        info!("Started camera standby monitor - checking every {} seconds", CHECK_INTERVAL_SECS);
+
+        loop {
+            interval.tick().await;
+            check_idle_cameras(&state).await;
+        }
+    })
+}
+
+async fn check_idle_cameras(state: &AppState) {
+    let now = chrono::Local::now();
+    let streams: Vec<_> = {
+        let camera_streams = state.camera_streams.read().await;
+        camera_streams.values()
+            .map(|info| (
+                info.camera_id.clone(),
+                standby_after_minutes(&info.camera_config, &state.server_config),
+                info.pre_recording_buffer.is_some(),
+                crate::recording_schedule::keeps_awake(&info.camera_config, now),
+                info.frame_sender.clone(),
+                info.pipeline_control.clone(),
+            ))
+            .collect()
+    };
+
+    for (camera_id, minutes, has_pre_recording, scheduled, frame_sender, pipeline) in streams {
+        // The pre-recording buffer needs continuous capture
+        if minutes == 0 || has_pre_recording {
+            continue;
+        }
+        if pipeline.is_standby() {
+            // Capture has to run when the scheduled recording starts
+            if scheduled && pipeline.wake() {
+                info!("Camera '{}' woken up from standby for its recording schedule", camera_id);
+            }
+            continue;
+        }
+
+        let recording = match state.recording_manager {
+            Some(ref recording_manager) => recording_manager.is_recording(&camera_id).await,
+            None => false,
+        };
+        if recording || scheduled || frame_sender.receiver_count() > 0 {
+            pipeline.touch();
+            continue;
+        }
+
+        let idle_secs = pipeline.idle_secs();
+        debug!("Camera '{}' idle for {} seconds (standby after {} minutes)", camera_id, idle_secs, minutes);
+        if idle_secs >= minutes * 60 {
+            info!("Camera '{}' had no viewers for {} minutes, entering standby", camera_id, minutes);
+            pipeline.enter_standby();
+        }
+    }
+}
+
+/// Wake a camera from standby. Returns true if the camera was in standby.
+pub async fn wake_camera(state: &AppState, camera_id: &str) -> bool {
+    let pipeline = {
+        let camera_streams = state.camera_streams.read().await;
+        camera_streams.values()
+            .find(|info| info.camera_id == camera_id)
+            .map(|info| info.pipeline_control.clone())
+    };
+
+    let woke = pipeline.is_some_and(|p| p.wake());
+    if woke {
+        info!("Camera '{}' woken up from standby", camera_id);
+    }
+    woke
+}
+
+/// Requests to a camera path (WebSocket connections, recording API, snapshots) count as
+/// activity and wake the camera from standby
+pub async fn standby_wake_guard(
+    request: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> axum::response::Response {
+    let path = request.uri().path();
+    if let Some((camera_id, pipeline)) = camera_for_path(path) {
+        if pipeline.wake() {
+            info!("Camera '{}' woken up from standby by request to {}", camera_id, path);
+        }
+    }
+
+    next.run(request).await
+}
@@ -0,0 +1,338 @@
+use async_trait::async_trait;
+use chrono::{DateTime, TimeZone, Utc};
+use serde::Deserialize;
+use sqlx::postgres::{PgPool, PgPoolOptions};
+use sqlx::{QueryBuilder, Row};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::config::{StatsBackendType, StatsConfig};
+use crate::database::ThroughputStats;
+use crate::errors::{Result, StreamError};
+
+// PostgreSQL allows 65535 bind parameters per statement, six per row
+const INSERT_BATCH_ROWS: usize = 5000;
+
+/// Storage for the per-second throughput stats, kept apart from the recording databases
+#[async_trait]
+pub trait StatsStore: Send + Sync {
+    async fn insert_throughput_stats(&self, stats: &[ThroughputStats]) -> Result<()>;
+
+    async fn get_throughput_stats(
+        &self,
+        camera_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<ThroughputStats>>;
+
+    async fn cleanup_old_throughput_stats(&self, older_than: DateTime<Utc>) -> Result<u64>;
+}
+
+/// A connected stats store with the settings the throughput tracker needs
+#[derive(Clone)]
+pub struct StatsBackend {
+    pub store: Arc<dyn StatsStore>,
+    pub flush_interval: Duration,
+    pub retention: Option<chrono::Duration>,
+}
+
+impl StatsBackend {
+    /// Connect to the configured backend and create the stats table
+    pub async fn connect(config: &StatsConfig) -> Result<Self> {
+        if config.table.is_empty() || !config.table.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(StreamError::config(format!("Invalid stats table name '{}'", config.table)));
+        }
+        let retention = parse_retention(&config.retention)?;
+        let store: Arc<dyn StatsStore> = match config.backend {
+            StatsBackendType::Timescaledb => Arc::new(TimescaleStatsStore::connect(config).await?),
+            StatsBackendType::Clickhouse => Arc::new(ClickHouseStatsStore::connect(config, retention).await?),
+        };
+        Ok(Self {
+            store,
+            flush_interval: Duration::from_secs(config.flush_interval_secs.max(1)),
+            retention,
+        })
+    }
+}
+
+fn parse_retention(retention: &str) -> Result<Option<chrono::Duration>> {
+    if retention == "0" {
+        return Ok(None);
+    }
+    humantime::parse_duration(retention).ok()
+        .and_then(|d| chrono::Duration::from_std(d).ok())
+        .filter(|d| *d > chrono::Duration::zero())
+        .map(Some)
+        .ok_or_else(|| StreamError::config(format!("Invalid stats retention '{}'", retention)))
+}
+
+/// TimescaleDB: a PostgreSQL table turned into a hypertable partitioned by time
+pub struct TimescaleStatsStore {
+    pool: PgPool,
+    table: String,
+}
+
+impl TimescaleStatsStore {
+    async fn connect(config: &StatsConfig) -> Result<Self> {
+        let pool = PgPoolOptions::new()
+            .max_connections(2)
+            .connect(&config.url)
+            .await?;
+        let table = config.table.clone();
+
+        sqlx::query(&format!(
+            r#"
+            CREATE TABLE IF NOT EXISTS {} (
+                camera_id TEXT NOT NULL,
+                timestamp TIMESTAMPTZ NOT NULL,
+                bytes_per_second BIGINT NOT NULL,
+                frame_count INTEGER NOT NULL,
+                ffmpeg_fps REAL NOT NULL,
+                connection_count INTEGER NOT NULL
+            )
+            "#,
+            table
+        )).execute(&pool).await?;
+
+        // Works as a plain table if the extension is missing, only slower for long retentions
+        let hypertable = match sqlx::query("SELECT create_hypertable($1::regclass, 'timestamp', if_not_exists => TRUE, migrate_data => TRUE)")
+            .bind(&table)
+            .execute(&pool)
+            .await
+        {
+            Ok(_) => true,
+            Err(e) => {
+                warn!("Stats table '{}' is not a TimescaleDB hypertable ({}), is the timescaledb extension installed?", table, e);
+                false
+            }
+        };
+
+        sqlx::query(&format!(
+            "CREATE INDEX IF NOT EXISTS idx_{}_camera_time ON {} (camera_id, timestamp DESC)",
+            table, table
+        )).execute(&pool).await?;
+
+        info!("Throughput stats are stored in TimescaleDB table '{}'{}", table, if hypertable { " (hypertable)" } else { "" });
+        Ok(Self { pool, table })
+    }
+}
+
+#[async_trait]
+impl StatsStore for TimescaleStatsStore {
+    async fn insert_throughput_stats(&self, stats: &[ThroughputStats]) -> Result<()> {
+        for batch in stats.chunks(INSERT_BATCH_ROWS) {
+            let mut query = QueryBuilder::new(format!(
+                "INSERT INTO {} (camera_id, timestamp, bytes_per_second, frame_count, ffmpeg_fps, connection_count) ",
+                self.table
+            ));
+            query.push_values(batch, |mut row, stat| {
+                row.push_bind(&stat.camera_id)
+                    .push_bind(stat.timestamp)
+                    .push_bind(stat.bytes_per_second)
+                    .push_bind(stat.frame_count)
+                    .push_bind(stat.ffmpeg_fps)
+                    .push_bind(stat.connection_count);
+            });
+            query.build().execute(&self.pool).await?;
+        }
+        Ok(())
+    }
+
+    async fn get_throughput_stats(
+        &self,
+        camera_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<ThroughputStats>> {
+        let query = format!(
+            r#"
+            SELECT camera_id, timestamp, bytes_per_second, frame_count, ffmpeg_fps, connection_count
+            FROM {}
+            WHERE camera_id = $1 AND timestamp >= $2 AND timestamp <= $3
+            ORDER BY timestamp ASC
+            "#,
+            self.table
+        );
+        let rows = sqlx::query(&query)
+            .bind(camera_id)
+            .bind(from)
+            .bind(to)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.into_iter().map(|row| ThroughputStats {
+            camera_id: row.get("camera_id"),
+            timestamp: row.get("timestamp"),
+            bytes_per_second: row.get("bytes_per_second"),
+            frame_count: row.get("frame_count"),
+            ffmpeg_fps: row.get("ffmpeg_fps"),
+            connection_count: row.get("connection_count"),
+        }).collect())
+    }
+
+    async fn cleanup_old_throughput_stats(&self, older_than: DateTime<Utc>) -> Result<u64> {
+        // On a hypertable this only touches the chunks that overlap the cutoff
+        let result = sqlx::query(&format!("DELETE FROM {} WHERE timestamp < $1", self.table))
+            .bind(older_than)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+/// ClickHouse over its HTTP interface; old rows are removed by the table TTL
+pub struct ClickHouseStatsStore {
+    client: reqwest::Client,
+    url: String,
+    database: String,
+    username: Option<String>,
+    password: Option<String>,
+    table: String,
+}
+
+#[derive(Deserialize)]
+struct ClickHouseRow {
+    camera_id: String,
+    timestamp_ms: i64,
+    bytes_per_second: i64,
+    frame_count: i32,
+    ffmpeg_fps: f32,
+    connection_count: i32,
+}
+
+impl ClickHouseStatsStore {
+    async fn connect(config: &StatsConfig, retention: Option<chrono::Duration>) -> Result<Self> {
+        let store = Self {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(30))
+                .build()
+                .map_err(|e| StreamError::internal(format!("Failed to create HTTP client: {}", e)))?,
+            url: config.url.clone(),
+            database: config.database.clone().unwrap_or_else(|| "default".to_string()),
+            username: config.username.clone(),
+            password: config.password.clone(),
+            table: config.table.clone(),
+        };
+
+        store.execute(&format!(
+            r#"
+            CREATE TABLE IF NOT EXISTS {} (
+                camera_id LowCardinality(String),
+                timestamp DateTime64(3, 'UTC'),
+                bytes_per_second Int64,
+                frame_count Int32,
+                ffmpeg_fps Float32,
+                connection_count Int32
+            )
+            ENGINE = MergeTree
+            PARTITION BY toYYYYMMDD(timestamp)
+            ORDER BY (camera_id, timestamp)
+            "#,
+            store.table
+        ), String::new(), &[]).await?;
+
+        // Keep the TTL in line with the configured retention
+        if let Some(retention) = retention {
+            store.execute(&format!(
+                "ALTER TABLE {} MODIFY TTL toDateTime(timestamp) + INTERVAL {} SECOND",
+                store.table, retention.num_seconds()
+            ), String::new(), &[]).await?;
+        }
+
+        info!("Throughput stats are stored in ClickHouse table '{}.{}'", store.database, store.table);
+        Ok(store)
+    }
+
+    async fn execute(&self, query: &str, body: String, params: &[(&str, String)]) -> Result<String> {
+        let mut request = self.client.post(&self.url)
+            .query(&[("query", query), ("database", self.database.as_str())])
+            .query(&[("date_time_input_format", "best_effort"), ("output_format_json_quote_64bit_integers", "0")])
+            .query(params)
+            .body(body);
+        if let Some(ref username) = self.username {
+            request = request.header("X-ClickHouse-User", username);
+        }
+        if let Some(ref password) = self.password {
+            request = request.header("X-ClickHouse-Key", password);
+        }
+        let response = request.send().await
+            .map_err(|e| StreamError::server(format!("ClickHouse request failed: {}", e)))?;
+        let status = response.status();
+        let text = response.text().await
+            .map_err(|e| StreamError::server(format!("ClickHouse request failed: {}", e)))?;
+        if !status.is_success() {
+            return Err(StreamError::server(format!("ClickHouse returned {}: {}", status, text.trim())));
+        }
+        Ok(text)
+    }
+}
+
+#[async_trait]
+impl StatsStore for ClickHouseStatsStore {
+    async fn insert_throughput_stats(&self, stats: &[ThroughputStats]) -> Result<()> {
+        if stats.is_empty() {
+            return Ok(());
+        }
+        let mut body = String::new();
+        for stat in stats {
+            body.push_str(&serde_json::to_string(&serde_json::json!({
+                "camera_id": stat.camera_id,
+                "timestamp": stat.timestamp.to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+                "bytes_per_second": stat.bytes_per_second,
+                "frame_count": stat.frame_count,
+                "ffmpeg_fps": stat.ffmpeg_fps,
+                "connection_count": stat.connection_count,
+            }))?);
+            body.push('\n');
+        }
+        self.execute(&format!("INSERT INTO {} FORMAT JSONEachRow", self.table), body, &[]).await?;
+        Ok(())
+    }
+
+    async fn get_throughput_stats(
+        &self,
+        camera_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<ThroughputStats>> {
+        let query = format!(
+            r#"
+            SELECT camera_id, toUnixTimestamp64Milli(timestamp) AS timestamp_ms,
+                   bytes_per_second, frame_count, ffmpeg_fps, connection_count
+            FROM {}
+            WHERE camera_id = {{camera_id:String}}
+              AND timestamp >= fromUnixTimestamp64Milli({{from:Int64}})
+              AND timestamp <= fromUnixTimestamp64Milli({{to:Int64}})
+            ORDER BY timestamp ASC
+            FORMAT JSONEachRow
+            "#,
+            self.table
+        );
+        let params = [
+            ("param_camera_id", camera_id.to_string()),
+            ("param_from", from.timestamp_millis().to_string()),
+            ("param_to", to.timestamp_millis().to_string()),
+        ];
+        let text = self.execute(&query, String::new(), &params).await?;
+
+        let mut stats = Vec::new();
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let row: ClickHouseRow = serde_json::from_str(line)?;
+            stats.push(ThroughputStats {
+                camera_id: row.camera_id,
+                timestamp: Utc.timestamp_millis_opt(row.timestamp_ms).single().unwrap_or_default(),
+                bytes_per_second: row.bytes_per_second,
+                frame_count: row.frame_count,
+                ffmpeg_fps: row.ffmpeg_fps,
+                connection_count: row.connection_count,
+            });
+        }
+        Ok(stats)
+    }
+
+    async fn cleanup_old_throughput_stats(&self, _older_than: DateTime<Utc>) -> Result<u64> {
+        // The table TTL drops expired rows during merges
+        Ok(0)
+    }
+}
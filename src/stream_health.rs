@@ -0,0 +1,367 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::config::StreamHealthConfig;
+
+// Window of the current bitrate and length of one baseline sample
+const BITRATE_WINDOW: Duration = Duration::from_secs(60);
+// Shortest window a current bitrate is reported for
+const MIN_BITRATE_SPAN: Duration = Duration::from_secs(30);
+// Per-minute bitrates needed before bitrate drops are detected
+const MIN_BASELINE_SAMPLES: usize = 5;
+
+lazy_static::lazy_static! {
+    static ref MONITORS: Mutex<HashMap<String, HealthMonitor>> = Mutex::new(HashMap::new());
+}
+
+/// Stream properties reported by FFmpeg and whether they dropped below their baseline
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamHealth {
+    pub codec: Option<String>,
+    /// Resolution of the camera stream (FFmpeg input), e.g. "1920x1080"
+    pub resolution: Option<String>,
+    /// Resolution the stream is compared with: configured or the highest seen
+    pub expected_resolution: Option<String>,
+    pub input_fps: Option<f32>,
+    pub output_fps: Option<f32>,
+    /// Bitrate of FFmpeg's output over the last minute
+    pub bitrate_kbps: Option<f64>,
+    /// Median of the per-minute bitrates of the baseline window
+    pub baseline_bitrate_kbps: Option<f64>,
+    pub degraded: bool,
+    pub reasons: Vec<String>,
+    pub degraded_since: Option<DateTime<Utc>>,
+    pub resolution_changes: u64,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Line of FFmpeg's stderr that matters for the stream health
+#[derive(Debug, Clone, PartialEq)]
+enum StderrLine {
+    /// Video stream of the input, e.g. `Stream #0:0: Video: h264 (Main), yuvj420p(pc), 1920x1080, 25 fps, 25 tbr`
+    InputVideo { codec: String, width: u32, height: u32, fps: Option<f32> },
+    /// Decoded frames changed size mid-stream, e.g. `... frame changed from size:1920x1080 fmt:yuvj420p to size:1280x720 fmt:yuvj420p`
+    FrameChanged { width: u32, height: u32 },
+    /// `key=value` line of `-progress` output
+    Progress { key: String, value: String },
+    Other,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Section {
+    None,
+    Input,
+    Output,
+}
+
+/// Classifies stderr lines; stream lines are only input streams within an `Input #n` block
+struct StderrParser {
+    section: Section,
+}
+
+impl Default for StderrParser {
+    fn default() -> Self {
+        Self { section: Section::None }
+    }
+}
+
+impl StderrParser {
+    fn parse(&mut self, line: &str) -> StderrLine {
+        let trimmed = line.trim();
+        if trimmed.starts_with("Input #") {
+            self.section = Section::Input;
+            return StderrLine::Other;
+        }
+        if trimmed.starts_with("Output #") || trimmed.starts_with("Stream mapping:") {
+            self.section = Section::Output;
+            return StderrLine::Other;
+        }
+        if let Some(changed) = trimmed.find("frame changed from").map(|pos| &trimmed[pos..]) {
+            return changed.rsplit_once("to size:")
+                .and_then(|(_, rest)| parse_resolution(rest.split_whitespace().next().unwrap_or("")))
+                .map(|(width, height)| StderrLine::FrameChanged { width, height })
+                .unwrap_or(StderrLine::Other);
+        }
+        if self.section == Section::Input && trimmed.starts_with("Stream #") {
+            if let Some(video) = trimmed.split_once("Video: ").map(|(_, video)| video) {
+                return parse_video_stream(video).unwrap_or(StderrLine::Other);
+            }
+        }
+        match progress_field(trimmed) {
+            Some((key, value)) => StderrLine::Progress { key: key.to_string(), value: value.to_string() },
+            None => StderrLine::Other,
+        }
+    }
+}
+
+fn progress_field(line: &str) -> Option<(&str, &str)> {
+    line.split_once('=').filter(|(key, value)| !key.is_empty() && !key.contains(' ') && !value.contains(' '))
+}
+
+/// Whether the line is part of FFmpeg's `-progress` output rather than a log message
+pub fn is_progress_line(line: &str) -> bool {
+    progress_field(line.trim()).is_some()
+}
+
+fn parse_resolution(text: &str) -> Option<(u32, u32)> {
+    let (width, height) = text.split_once('x')?;
+    let resolution = (width.parse().ok()?, height.parse().ok()?);
+    (resolution.0 > 0 && resolution.1 > 0).then_some(resolution)
+}
+
+// Stream description after `Video: `; the fields are comma separated, also within parentheses
+fn parse_video_stream(video: &str) -> Option<StderrLine> {
+    let codec = video.split_whitespace().next()?.trim_end_matches(',').to_string();
+    let mut resolution = None;
+    let mut fps = None;
+    for field in video.split(',').map(str::trim) {
+        let first = field.split_whitespace().next().unwrap_or("");
+        if resolution.is_none() {
+            resolution = parse_resolution(first);
+        }
+        if let Some(value) = field.strip_suffix(" fps") {
+            fps = value.trim().parse().ok();
+        }
+    }
+    let (width, height) = resolution?;
+    Some(StderrLine::InputVideo { codec, width, height, fps })
+}
+
+fn format_resolution((width, height): (u32, u32)) -> String {
+    format!("{}x{}", width, height)
+}
+
+fn pixels((width, height): (u32, u32)) -> u64 {
+    width as u64 * height as u64
+}
+
+/// Baseline and current state of a camera, kept across FFmpeg restarts
+struct HealthMonitor {
+    config: StreamHealthConfig,
+    parser: StderrParser,
+    codec: Option<String>,
+    resolution: Option<(u32, u32)>,
+    max_resolution: Option<(u32, u32)>,
+    resolution_changes: u64,
+    input_fps: Option<f32>,
+    output_fps: Option<f32>,
+    // total_size of the progress block being read
+    pending_size: Option<u64>,
+    // (time, total output bytes) of the current FFmpeg process within the bitrate window
+    samples: VecDeque<(Instant, u64)>,
+    minute_start: Option<(Instant, u64)>,
+    minute_rates: VecDeque<f64>,
+    reasons: Vec<String>,
+    degraded_since: Option<DateTime<Utc>>,
+    updated_at: DateTime<Utc>,
+}
+
+impl HealthMonitor {
+    fn new(config: StreamHealthConfig) -> Self {
+        Self {
+            config,
+            parser: StderrParser::default(),
+            codec: None,
+            resolution: None,
+            max_resolution: None,
+            resolution_changes: 0,
+            input_fps: None,
+            output_fps: None,
+            pending_size: None,
+            samples: VecDeque::new(),
+            minute_start: None,
+            minute_rates: VecDeque::new(),
+            reasons: Vec::new(),
+            degraded_since: None,
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn expected_resolution(&self) -> Option<(u32, u32)> {
+        self.config.expected_resolution.as_deref()
+            .and_then(|r| parse_resolution(&r.replace(':', "x")))
+            .or(self.max_resolution)
+    }
+
+    fn bitrate_kbps(&self) -> Option<f64> {
+        let (&(first_time, first_size), &(last_time, last_size)) = (self.samples.front()?, self.samples.back()?);
+        let span = last_time.duration_since(first_time);
+        (span >= MIN_BITRATE_SPAN)
+            .then(|| last_size.saturating_sub(first_size) as f64 * 8.0 / span.as_secs_f64() / 1000.0)
+    }
+
+    fn baseline_kbps(&self) -> Option<f64> {
+        if self.minute_rates.len() < MIN_BASELINE_SAMPLES.min(self.config.baseline_minutes.max(1) as usize) {
+            return None;
+        }
+        let mut rates: Vec<f64> = self.minute_rates.iter().copied().collect();
+        rates.sort_by(f64::total_cmp);
+        Some(rates[rates.len() / 2])
+    }
+
+    fn set_resolution(&mut self, resolution: (u32, u32)) {
+        if self.resolution.is_some_and(|current| current != resolution) {
+            self.resolution_changes += 1;
+        }
+        self.resolution = Some(resolution);
+        if self.max_resolution.is_none_or(|max| pixels(resolution) > pixels(max)) {
+            self.max_resolution = Some(resolution);
+        }
+    }
+
+    fn add_sample(&mut self, total_size: u64) {
+        let now = Instant::now();
+        // A restarted FFmpeg counts from zero again
+        if self.samples.back().is_some_and(|&(_, size)| total_size < size) {
+            self.samples.clear();
+            self.minute_start = None;
+        }
+        self.samples.push_back((now, total_size));
+        while self.samples.front().is_some_and(|&(time, _)| now.duration_since(time) > BITRATE_WINDOW) {
+            self.samples.pop_front();
+        }
+
+        let (start_time, start_size) = *self.minute_start.get_or_insert((now, total_size));
+        let elapsed = now.duration_since(start_time);
+        if elapsed >= BITRATE_WINDOW {
+            let rate = total_size.saturating_sub(start_size) as f64 * 8.0 / elapsed.as_secs_f64() / 1000.0;
+            // Degraded minutes would pull the baseline down until the drop looks normal
+            if self.reasons.is_empty() {
+                self.minute_rates.push_back(rate);
+                while self.minute_rates.len() > self.config.baseline_minutes.max(1) as usize {
+                    self.minute_rates.pop_front();
+                }
+            }
+            self.minute_start = Some((now, total_size));
+        }
+    }
+
+    /// Re-evaluate the stream, returns true if it became degraded or recovered
+    fn evaluate(&mut self) -> bool {
+        let mut reasons = Vec::new();
+        if let (Some(resolution), Some(expected)) = (self.resolution, self.expected_resolution()) {
+            if pixels(resolution) < pixels(expected) {
+                reasons.push(format!("resolution {} below {}", format_resolution(resolution), format_resolution(expected)));
+            }
+        }
+        if let Some(bitrate) = self.bitrate_kbps() {
+            if let Some(baseline) = self.baseline_kbps() {
+                let threshold = baseline * (100.0 - self.config.bitrate_drop_percent.min(100) as f64) / 100.0;
+                if bitrate < threshold {
+                    reasons.push(format!("bitrate {:.0} kbit/s is {:.0}% below the baseline of {:.0} kbit/s",
+                                         bitrate, (1.0 - bitrate / baseline) * 100.0, baseline));
+                }
+            }
+            if let Some(min_bitrate) = self.config.min_bitrate_kbps.filter(|&min| bitrate < min) {
+                reasons.push(format!("bitrate {:.0} kbit/s below the minimum of {:.0} kbit/s", bitrate, min_bitrate));
+            }
+        }
+
+        let changed = reasons.is_empty() != self.reasons.is_empty();
+        self.degraded_since = match (self.degraded_since, reasons.is_empty()) {
+            (_, true) => None,
+            (Some(since), false) => Some(since),
+            (None, false) => Some(Utc::now()),
+        };
+        self.reasons = reasons;
+        changed
+    }
+
+    fn status(&self) -> StreamHealth {
+        StreamHealth {
+            codec: self.codec.clone(),
+            resolution: self.resolution.map(format_resolution),
+            expected_resolution: self.expected_resolution().map(format_resolution),
+            input_fps: self.input_fps,
+            output_fps: self.output_fps,
+            bitrate_kbps: self.bitrate_kbps().map(|b| b.round()),
+            baseline_bitrate_kbps: self.baseline_kbps().map(|b| b.round()),
+            degraded: !self.reasons.is_empty(),
+            reasons: self.reasons.clone(),
+            degraded_since: self.degraded_since,
+            resolution_changes: self.resolution_changes,
+            updated_at: self.updated_at,
+        }
+    }
+}
+
+/// Prepare for the stderr of a new FFmpeg process; the baseline of earlier processes is kept
+pub fn process_started(camera_id: &str, config: &StreamHealthConfig) {
+    if let Ok(mut monitors) = MONITORS.lock() {
+        let monitor = monitors.entry(camera_id.to_string()).or_insert_with(|| HealthMonitor::new(config.clone()));
+        monitor.config = config.clone();
+        monitor.parser = StderrParser::default();
+        monitor.pending_size = None;
+        monitor.samples.clear();
+        monitor.minute_start = None;
+    }
+}
+
+/// Feed a stderr line of the camera's FFmpeg process. Returns the new status when the stream
+/// became degraded or recovered.
+pub fn handle_line(camera_id: &str, line: &str) -> Option<StreamHealth> {
+    let mut monitors = MONITORS.lock().ok()?;
+    let monitor = monitors.get_mut(camera_id)?;
+    let changed = match monitor.parser.parse(line) {
+        StderrLine::InputVideo { codec, width, height, fps } => {
+            monitor.codec = Some(codec);
+            monitor.input_fps = fps;
+            monitor.set_resolution((width, height));
+            monitor.evaluate()
+        }
+        StderrLine::FrameChanged { width, height } => {
+            info!("[{}] Camera stream changed resolution to {}x{}", camera_id, width, height);
+            monitor.set_resolution((width, height));
+            monitor.evaluate()
+        }
+        StderrLine::Progress { key, value } => match key.as_str() {
+            "total_size" => {
+                monitor.pending_size = value.parse().ok();
+                false
+            }
+            "fps" => {
+                monitor.output_fps = value.parse().ok();
+                false
+            }
+            // Last line of a progress block
+            "progress" => {
+                if let Some(total_size) = monitor.pending_size.take() {
+                    monitor.add_sample(total_size);
+                }
+                monitor.evaluate()
+            }
+            _ => false,
+        },
+        StderrLine::Other => false,
+    };
+    monitor.updated_at = Utc::now();
+    if !changed {
+        return None;
+    }
+
+    let status = monitor.status();
+    if status.degraded {
+        warn!("[{}] Camera stream degraded: {}", camera_id, status.reasons.join(", "));
+    } else {
+        info!("[{}] Camera stream recovered ({} at {} kbit/s)", camera_id,
+              status.resolution.as_deref().unwrap_or("unknown resolution"),
+              status.bitrate_kbps.map_or("unknown".to_string(), |b| b.to_string()));
+    }
+    Some(status)
+}
+
+/// Current stream health of a camera with stream_health enabled
+pub fn get_health(camera_id: &str) -> Option<StreamHealth> {
+    MONITORS.lock().ok()?.get(camera_id).map(HealthMonitor::status)
+}
+
+/// Forget the baseline of a camera, e.g. when it is removed
+pub fn clear_health(camera_id: &str) {
+    if let Ok(mut monitors) = MONITORS.lock() {
+        monitors.remove(camera_id);
+    }
+}
@@ -0,0 +1,303 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use chrono::{DateTime, Utc};
+use image::imageops::FilterType;
+use serde::Serialize;
+use tokio::sync::broadcast;
+use tokio::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+use crate::config::{TamperDetectionConfig, TamperKind};
+use crate::errors::{Result, StreamError};
+use crate::jpeg;
+use crate::AppState;
+
+// Frames are compared as grayscale images of this size
+const GRID_WIDTH: u32 = 64;
+const GRID_HEIGHT: u32 = 36;
+// Samples averaged into the baseline before anything is detected
+const WARMUP_SAMPLES: u32 = 10;
+// Wait before restarting after the camera stream was not available
+const RESTART_DELAY_SECS: u64 = 10;
+
+lazy_static::lazy_static! {
+    static ref EVENT_CHANNELS: Mutex<HashMap<String, broadcast::Sender<TamperEvent>>> = Mutex::new(HashMap::new());
+    static ref STATES: Mutex<HashMap<String, TamperState>> = Mutex::new(HashMap::new());
+}
+
+/// A tamper condition was raised (`active`) or cleared
+#[derive(Debug, Clone, Serialize)]
+pub struct TamperEvent {
+    pub kind: TamperKind,
+    pub active: bool,
+    pub timestamp: DateTime<Utc>,
+    pub luminance: f64,
+    pub scene_similarity: Option<f64>,
+}
+
+impl TamperEvent {
+    fn new(kind: TamperKind, active: bool, sample: &Sample, scene_similarity: Option<f64>) -> Self {
+        Self { kind, active, timestamp: Utc::now(), luminance: sample.mean, scene_similarity }
+    }
+}
+
+/// Current tamper analysis of a camera, as shown in the status APIs
+#[derive(Debug, Clone, Serialize)]
+pub struct TamperState {
+    pub tampered: bool,
+    pub covered_since: Option<DateTime<Utc>>,
+    pub scene_changed_since: Option<DateTime<Utc>>,
+    /// Average brightness (0-255) of the last analyzed frame and its learned baseline
+    pub luminance: f64,
+    pub baseline_luminance: f64,
+    /// Standard deviation of the brightness, low for covered or defocused cameras
+    pub contrast: f64,
+    pub baseline_contrast: f64,
+    /// Correlation (-1.0 - 1.0) of the last frame with the learned scene
+    pub scene_similarity: Option<f64>,
+    /// Baseline is still being learned, nothing is detected yet
+    pub learning: bool,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Subscribe to the tamper events of a camera (nothing is received while its detection is not running)
+pub fn subscribe(camera_id: &str) -> broadcast::Receiver<TamperEvent> {
+    event_sender(camera_id).subscribe()
+}
+
+fn event_sender(camera_id: &str) -> broadcast::Sender<TamperEvent> {
+    let mut channels = EVENT_CHANNELS.lock().unwrap_or_else(|e| e.into_inner());
+    channels.entry(camera_id.to_string())
+        .or_insert_with(|| broadcast::channel(16).0)
+        .clone()
+}
+
+/// Latest tamper analysis of a camera
+pub fn get_state(camera_id: &str) -> Option<TamperState> {
+    STATES.lock().ok()?.get(camera_id).cloned()
+}
+
+/// Forget the tamper state of a camera, e.g. when its detection is stopped
+pub fn clear_state(camera_id: &str) {
+    if let Ok(mut states) = STATES.lock() {
+        states.remove(camera_id);
+    }
+}
+
+/// Analyze a camera's live frames until the task is aborted
+pub async fn run_detector(state: AppState, camera_id: String, config: TamperDetectionConfig) {
+    loop {
+        if let Err(e) = detect(&state, &camera_id, &config).await {
+            warn!("[{}] Tamper detection stopped: {}", camera_id, e);
+        }
+        tokio::time::sleep(Duration::from_secs(RESTART_DELAY_SECS)).await;
+    }
+}
+
+/// Brightness statistics of one analyzed frame
+struct Sample {
+    luma: Vec<f32>,
+    mean: f64,
+    contrast: f64,
+}
+
+/// Learned baseline of a camera and the conditions seen so far
+#[derive(Default)]
+struct Detector {
+    samples: u32,
+    baseline_luminance: f64,
+    baseline_contrast: f64,
+    reference: Vec<f32>,
+    covered_pending: Option<Instant>,
+    scene_pending: Option<Instant>,
+    covered_since: Option<DateTime<Utc>>,
+    scene_changed_since: Option<DateTime<Utc>>,
+}
+
+impl Detector {
+    fn learning(&self) -> bool {
+        self.samples < WARMUP_SAMPLES
+    }
+
+    /// Move the baseline towards the sample by `weight` (0.0 - 1.0)
+    fn learn(&mut self, sample: &Sample, weight: f64) {
+        self.baseline_luminance += (sample.mean - self.baseline_luminance) * weight;
+        self.baseline_contrast += (sample.contrast - self.baseline_contrast) * weight;
+        if self.reference.len() != sample.luma.len() {
+            self.reference = sample.luma.clone();
+            return;
+        }
+        for (reference, value) in self.reference.iter_mut().zip(&sample.luma) {
+            *reference += (value - *reference) * weight as f32;
+        }
+    }
+
+    fn state(&self, sample: &Sample, scene_similarity: Option<f64>) -> TamperState {
+        TamperState {
+            tampered: self.covered_since.is_some() || self.scene_changed_since.is_some(),
+            covered_since: self.covered_since,
+            scene_changed_since: self.scene_changed_since,
+            luminance: sample.mean,
+            baseline_luminance: self.baseline_luminance,
+            contrast: sample.contrast,
+            baseline_contrast: self.baseline_contrast,
+            scene_similarity,
+            learning: self.learning(),
+            updated_at: Utc::now(),
+        }
+    }
+}
+
+async fn detect(state: &AppState, camera_id: &str, config: &TamperDetectionConfig) -> Result<()> {
+    let frame_sender = state.camera_streams.read().await
+        .get(camera_id)
+        .map(|info| info.frame_sender.clone())
+        .ok_or_else(|| StreamError::not_found(format!("Camera '{}' is not running", camera_id)))?;
+
+    let sample_interval = Duration::from_secs_f64(1.0 / config.fps.clamp(0.01, 10.0));
+    // Weight of a new sample in the baseline, so that it follows over `learning_minutes`
+    let learning_rate = (sample_interval.as_secs_f64() / (config.learning_minutes.max(0.1) * 60.0)).min(1.0);
+    let luminance_factor = 1.0 - config.luminance_drop_percent.clamp(0.0, 100.0) / 100.0;
+    let contrast_factor = 1.0 - config.detail_drop_percent.clamp(0.0, 100.0) / 100.0;
+    let covered_delay = Duration::from_secs(config.covered_secs);
+    let scene_change_delay = Duration::from_secs(config.scene_change_secs);
+
+    let mut frames = frame_sender.subscribe();
+    let mut last_sample: Option<Instant> = None;
+    let mut detector = Detector::default();
+    info!("[{}] Started tamper detection ({} fps)", camera_id, config.fps);
+
+    loop {
+        let frame = match frames.recv().await {
+            Ok(frame) => frame,
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        if last_sample.is_some_and(|t| t.elapsed() < sample_interval) {
+            continue;
+        }
+        last_sample = Some(Instant::now());
+
+        let sample = match tokio::task::spawn_blocking(move || analyze(&frame)).await {
+            Ok(Ok(sample)) => sample,
+            Ok(Err(e)) => {
+                debug!("[{}] Skipping frame for tamper detection: {}", camera_id, e);
+                continue;
+            }
+            Err(_) => break,
+        };
+
+        if detector.learning() {
+            detector.samples += 1;
+            detector.learn(&sample, 1.0 / detector.samples as f64);
+            store_state(camera_id, detector.state(&sample, None));
+            continue;
+        }
+
+        let covered = sample.mean < detector.baseline_luminance * luminance_factor
+            || sample.contrast < detector.baseline_contrast * contrast_factor;
+        let similarity = if covered { None } else { correlation(&detector.reference, &sample.luma) };
+
+        if covered {
+            let pending = *detector.covered_pending.get_or_insert_with(Instant::now);
+            if detector.covered_since.is_none() && pending.elapsed() >= covered_delay {
+                detector.covered_since = Some(Utc::now());
+                warn!("[{}] Camera looks covered: brightness {:.0} (baseline {:.0}), contrast {:.1} (baseline {:.1})",
+                      camera_id, sample.mean, detector.baseline_luminance, sample.contrast, detector.baseline_contrast);
+                raise(state, camera_id, config, TamperEvent::new(TamperKind::Covered, true, &sample, similarity)).await;
+            }
+        } else {
+            detector.covered_pending = None;
+            if detector.covered_since.take().is_some() {
+                info!("[{}] Camera no longer looks covered", camera_id);
+                raise(state, camera_id, config, TamperEvent::new(TamperKind::Covered, false, &sample, similarity)).await;
+            }
+
+            // A passing person lowers the similarity only briefly, a moved camera for good
+            match similarity {
+                Some(similarity) if similarity < config.scene_similarity => {
+                    let pending = *detector.scene_pending.get_or_insert_with(Instant::now);
+                    if detector.scene_changed_since.is_none() && pending.elapsed() >= scene_change_delay {
+                        detector.scene_changed_since = Some(Utc::now());
+                        warn!("[{}] Camera scene changed (similarity {:.2}), camera may have been moved", camera_id, similarity);
+                        raise(state, camera_id, config, TamperEvent::new(TamperKind::SceneChanged, true, &sample, Some(similarity))).await;
+                    }
+                }
+                _ => {
+                    detector.scene_pending = None;
+                    if detector.scene_changed_since.take().is_some() {
+                        info!("[{}] Camera scene matches the learned scene again", camera_id);
+                        raise(state, camera_id, config, TamperEvent::new(TamperKind::SceneChanged, false, &sample, similarity)).await;
+                    }
+                }
+            }
+
+            // Gradual changes like daylight are learned, a covered view is not. A moved camera
+            // is learned as well, so its new view becomes the reference over `learning_minutes`.
+            detector.learn(&sample, learning_rate);
+        }
+
+        store_state(camera_id, detector.state(&sample, similarity));
+    }
+    Ok(())
+}
+
+/// Decode a JPEG frame into a small grayscale image and its brightness statistics
+fn analyze(frame: &[u8]) -> Result<Sample> {
+    let image = jpeg::decode(frame)?;
+    let luma: Vec<f32> = image
+        .resize_exact(GRID_WIDTH, GRID_HEIGHT, FilterType::Triangle)
+        .to_luma8()
+        .into_raw()
+        .into_iter()
+        .map(f32::from)
+        .collect();
+    let mean = luma.iter().map(|&v| v as f64).sum::<f64>() / luma.len() as f64;
+    let variance = luma.iter().map(|&v| (v as f64 - mean).powi(2)).sum::<f64>() / luma.len() as f64;
+    Ok(Sample { luma, mean, contrast: variance.sqrt() })
+}
+
+/// Normalized cross-correlation, insensitive to global brightness changes. None for flat images.
+fn correlation(reference: &[f32], luma: &[f32]) -> Option<f64> {
+    if reference.len() != luma.len() || luma.is_empty() {
+        return None;
+    }
+    let mean = |values: &[f32]| values.iter().map(|&v| v as f64).sum::<f64>() / values.len() as f64;
+    let (reference_mean, luma_mean) = (mean(reference), mean(luma));
+    let (mut product, mut reference_energy, mut luma_energy) = (0.0, 0.0, 0.0);
+    for (&a, &b) in reference.iter().zip(luma) {
+        let (a, b) = (a as f64 - reference_mean, b as f64 - luma_mean);
+        product += a * b;
+        reference_energy += a * a;
+        luma_energy += b * b;
+    }
+    let norm = (reference_energy * luma_energy).sqrt();
+    (norm > f64::EPSILON).then(|| product / norm)
+}
+
+fn store_state(camera_id: &str, state: TamperState) {
+    if let Ok(mut states) = STATES.lock() {
+        states.insert(camera_id.to_string(), state);
+    }
+}
+
+/// Record a tamper event on the timeline, publish it to MQTT and hand it to the tamper triggers
+async fn raise(state: &AppState, camera_id: &str, config: &TamperDetectionConfig, event: TamperEvent) {
+    if let Some(ref recording_manager) = state.recording_manager {
+        recording_manager.record_event(camera_id, "tamper", event.kind.name(), serde_json::json!({
+            "active": event.active,
+            "luminance": event.luminance,
+            "scene_similarity": event.scene_similarity,
+        })).await;
+    }
+    if config.mqtt {
+        if let Some(ref mqtt) = state.mqtt_handle {
+            let payload = serde_json::to_string(&event).unwrap_or_default();
+            if let Err(e) = mqtt.publish_custom(&format!("cameras/{}/tamper", camera_id), &payload).await {
+                debug!("[{}] Failed to publish tamper event: {}", camera_id, e);
+            }
+        }
+    }
+    // Without tamper triggers nobody is subscribed
+    let _ = event_sender(camera_id).send(event);
+}
@@ -0,0 +1,130 @@
+use std::collections::HashSet;
+use serde::Serialize;
+use tokio::time::{interval, Duration};
+use tracing::{info, warn};
+
+use crate::config::{DatabaseType, TenantConfig};
+use crate::recording::RecordingManager;
+use crate::AppState;
+
+// How often tenant storage usage is compared against the quotas
+const QUOTA_CHECK_INTERVAL_SECS: u64 = 60;
+
+const BYTES_PER_GB: f64 = 1024.0 * 1024.0 * 1024.0;
+
+/// Recording storage of one tenant camera
+#[derive(Debug, Clone, Serialize)]
+pub struct CameraUsage {
+    pub camera_id: String,
+    pub bytes: Option<i64>,
+}
+
+/// Recording storage of all cameras of a tenant compared to its quota
+#[derive(Debug, Clone, Serialize)]
+pub struct TenantUsage {
+    pub tenant_id: String,
+    pub cameras: Vec<CameraUsage>,
+    pub total_bytes: i64,
+    pub quota_bytes: Option<i64>,
+    pub quota_exceeded: bool,
+}
+
+/// Ids of the cameras assigned to a tenant, sorted
+pub async fn tenant_camera_ids(state: &AppState, tenant_id: &str) -> Vec<String> {
+    let mut camera_ids: Vec<String> = state.camera_configs.read().await
+        .iter()
+        .filter(|(_, config)| config.tenant.as_deref() == Some(tenant_id))
+        .map(|(camera_id, _)| camera_id.clone())
+        .collect();
+    camera_ids.sort();
+    camera_ids
+}
+
+pub async fn storage_usage(state: &AppState, tenant: &TenantConfig) -> TenantUsage {
+    let mut cameras = Vec::new();
+    for camera_id in tenant_camera_ids(state, &tenant.id).await {
+        let bytes = match (&state.recording_manager, &state.recording_config) {
+            (Some(recording_manager), Some(recording_config)) => {
+                camera_storage_bytes(recording_manager, &recording_config.database_type, &camera_id).await
+            }
+            _ => None,
+        };
+        cameras.push(CameraUsage { camera_id, bytes });
+    }
+
+    let total_bytes = cameras.iter().filter_map(|c| c.bytes).sum();
+    let quota_bytes = tenant.storage_quota_gb.map(|gb| (gb * BYTES_PER_GB) as i64);
+    TenantUsage {
+        tenant_id: tenant.id.clone(),
+        cameras,
+        total_bytes,
+        quota_bytes,
+        quota_exceeded: quota_bytes.is_some_and(|quota| total_bytes > quota),
+    }
+}
+
+/// Bytes used by a camera's recordings: its SQLite database file plus MP4 files on disk.
+/// With PostgreSQL the camera's frames, MP4 and HLS segments in the shared database are summed.
+pub(crate) async fn camera_storage_bytes(recording_manager: &RecordingManager, database_type: &DatabaseType, camera_id: &str) -> Option<i64> {
+    let database = recording_manager.get_camera_database(camera_id).await?;
+    let stats = match database.get_storage_stats(camera_id).await {
+        Ok(stats) => stats,
+        Err(e) => {
+            warn!("Failed to get storage stats for camera '{}': {}", camera_id, e);
+            return None;
+        }
+    };
+    match database_type {
+        DatabaseType::SQLite => {
+            let database_size = database.get_database_size().await.unwrap_or_default();
+            Some(database_size + stats.mp4_file_bytes)
+        }
+        DatabaseType::PostgreSQL => Some(stats.frame_bytes + stats.mp4_file_bytes + stats.mp4_database_bytes + stats.hls_bytes),
+    }
+}
+
+/// Start the task that stops and refuses recordings of tenants above their storage quota
+pub fn start_quota_monitor(state: AppState) -> Option<tokio::task::JoinHandle<()>> {
+    let recording_manager = state.recording_manager.clone()?;
+    if !state.server_config.tenants.iter().any(|t| t.storage_quota_gb.is_some()) {
+        return None;
+    }
+
+    Some(tokio::spawn(async move {
+        let mut interval = interval(Duration::from_secs(QUOTA_CHECK_INTERVAL_SECS));
+        let mut exceeded: HashSet<String> = HashSet::new();
+        loop {
+            interval.tick().await;
+            for tenant in state.server_config.tenants.iter().filter(|t| t.storage_quota_gb.is_some()) {
+                let usage = storage_usage(&state, tenant).await;
+                enforce_quota(&recording_manager, &usage, &mut exceeded).await;
+            }
+        }
+    }))
+}
+
+async fn enforce_quota(recording_manager: &RecordingManager, usage: &TenantUsage, exceeded: &mut HashSet<String>) {
+    let tenant_id = usage.tenant_id.as_str();
+    if usage.quota_exceeded {
+        if exceeded.insert(tenant_id.to_string()) {
+            warn!("Tenant '{}' uses {} of {} bytes, stopping its recordings",
+                  tenant_id, usage.total_bytes, usage.quota_bytes.unwrap_or_default());
+        }
+    } else if exceeded.remove(tenant_id) {
+        info!("Tenant '{}' is below its storage quota again, recordings are allowed", tenant_id);
+    }
+
+    for camera in &usage.cameras {
+        if !usage.quota_exceeded {
+            recording_manager.set_quota_exceeded(&camera.camera_id, None).await;
+            continue;
+        }
+        recording_manager.set_quota_exceeded(&camera.camera_id, Some(tenant_id)).await;
+        if recording_manager.is_recording(&camera.camera_id).await {
+            match recording_manager.stop_recording(&camera.camera_id).await {
+                Ok(_) => info!("Stopped recording of camera '{}' (storage quota of tenant '{}')", camera.camera_id, tenant_id),
+                Err(e) => warn!("Failed to stop recording of camera '{}': {}", camera.camera_id, e),
+            }
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use chrono::Utc;
+use image::{Rgb, RgbImage};
+use tokio::time::Instant;
+
+use crate::config::{TestPattern, TestPatternConfig};
+use crate::watermark::{draw_text, text_height, text_width};
+
+// Top two thirds of SMPTE color bars: gray, yellow, cyan, green, magenta, red, blue
+const COLOR_BARS: [Rgb<u8>; 7] = [
+    Rgb([192, 192, 192]),
+    Rgb([192, 192, 0]),
+    Rgb([0, 192, 192]),
+    Rgb([0, 192, 0]),
+    Rgb([192, 0, 192]),
+    Rgb([192, 0, 0]),
+    Rgb([0, 0, 192]),
+];
+// Bottom third: blue, black, magenta, black, cyan, black, gray
+const COLOR_BARS_BOTTOM: [Rgb<u8>; 7] = [
+    Rgb([0, 0, 192]),
+    Rgb([19, 19, 19]),
+    Rgb([192, 0, 192]),
+    Rgb([19, 19, 19]),
+    Rgb([0, 192, 192]),
+    Rgb([19, 19, 19]),
+    Rgb([192, 192, 192]),
+];
+const GRAY: Rgb<u8> = Rgb([128, 128, 128]);
+const TEXT_BACKGROUND: Rgb<u8> = Rgb([0, 0, 0]);
+const TEXT: Rgb<u8> = Rgb([255, 255, 255]);
+const MOTION_OBJECT: Rgb<u8> = Rgb([255, 255, 255]);
+const SWEEP: Rgb<u8> = Rgb([255, 64, 64]);
+// Start of the JPEG comment with the time a frame was generated, read by `bench` to measure latency
+const TIMESTAMP_COMMENT: &[u8] = b"test-pattern-us:";
+
+/// Renders the frames of a test pattern camera, or of a camera that fell back to test frames
+pub struct TestPatternGenerator {
+    camera_id: String,
+    config: TestPatternConfig,
+    width: u32,
+    height: u32,
+    // Static part of the pattern, rendered once
+    background: Option<RgbImage>,
+    started: Instant,
+    frame_index: u64,
+}
+
+impl TestPatternGenerator {
+    pub fn new(camera_id: &str, config: &TestPatternConfig) -> Self {
+        let width = config.width.clamp(64, 3840);
+        let height = config.height.clamp(48, 2160);
+        let background = match config.pattern {
+            TestPattern::ColorBars => Some(color_bars(width, height)),
+            TestPattern::Gray => Some(RgbImage::from_pixel(width, height, GRAY)),
+            TestPattern::Gradient => None,
+        };
+        Self {
+            camera_id: camera_id.to_string(),
+            config: config.clone(),
+            width,
+            height,
+            background,
+            started: Instant::now(),
+            frame_index: 0,
+        }
+    }
+
+    /// Whether a motion burst is running at the moment
+    pub fn in_motion_burst(&self) -> bool {
+        self.burst_progress().is_some()
+    }
+
+    /// Render the next frame
+    pub fn render(&mut self) -> RgbImage {
+        self.frame_index += 1;
+        let elapsed_ms = self.started.elapsed().as_millis() as u64;
+        let mut image = match self.background {
+            Some(ref background) => background.clone(),
+            None => gradient(self.width, self.height, elapsed_ms),
+        };
+
+        if let Some(progress) = self.burst_progress() {
+            self.draw_motion_object(&mut image, progress);
+        }
+
+        if self.config.timestamp {
+            // A marker sweeping along the bottom edge once per second shows stutter and dropped frames
+            let marker = (self.width / 40).max(4);
+            let left = ((elapsed_ms % 1000) * (self.width - marker) as u64 / 1000) as u32;
+            fill_rect(&mut image, left, self.height - marker, marker, marker, SWEEP);
+            self.draw_timestamp(&mut image);
+        }
+        image
+    }
+
+    // Position of the running burst from 0.0 to 1.0
+    fn burst_progress(&self) -> Option<f64> {
+        let burst = self.config.motion_burst.as_ref()?;
+        let interval_ms = burst.interval_secs.max(1) * 1000;
+        let duration_ms = (burst.duration_secs * 1000).min(interval_ms);
+        let position_ms = self.started.elapsed().as_millis() as u64 % interval_ms;
+        (position_ms < duration_ms).then(|| position_ms as f64 / duration_ms.max(1) as f64)
+    }
+
+    // A square crossing the frame from left to right while bouncing up and down
+    fn draw_motion_object(&self, image: &mut RgbImage, progress: f64) {
+        let size = self.height / 4;
+        let left = (progress * (self.width - size) as f64) as u32;
+        let bounce = ((progress * std::f64::consts::PI * 4.0).sin().abs() * (self.height - size) as f64 / 2.0) as u32;
+        let top = (self.height - size) / 4 + bounce;
+        fill_rect(image, left, top, size, size, MOTION_OBJECT);
+    }
+
+    fn draw_timestamp(&self, image: &mut RgbImage) {
+        let lines = [
+            self.camera_id.clone(),
+            Utc::now().format("%Y-%m-%d %H:%M:%S%.3f UTC").to_string(),
+            format!("FRAME {}", self.frame_index),
+        ];
+        let scale = (self.height / 240).max(1);
+        let padding = 2 * scale;
+        let line_height = text_height(scale) + padding;
+        let box_width = lines.iter().map(|line| text_width(line, scale)).max().unwrap_or(0) + 2 * padding;
+        let box_height = line_height * lines.len() as u32 + padding;
+        fill_rect(image, padding, padding, box_width, box_height, TEXT_BACKGROUND);
+        for (index, line) in lines.iter().enumerate() {
+            draw_text(image, line, 2 * padding, 2 * padding + index as u32 * line_height, scale, TEXT);
+        }
+    }
+}
+
+fn color_bars(width: u32, height: u32) -> RgbImage {
+    RgbImage::from_fn(width, height, |x, y| {
+        let bar = (x * COLOR_BARS.len() as u32 / width) as usize;
+        if y < height * 2 / 3 { COLOR_BARS[bar] } else { COLOR_BARS_BOTTOM[bar] }
+    })
+}
+
+fn gradient(width: u32, height: u32, elapsed_ms: u64) -> RgbImage {
+    let t = elapsed_ms as u32;
+    RgbImage::from_fn(width, height, |x, y| {
+        Rgb([
+            ((x + t / 10) % 255) as u8,
+            ((y + t / 20) % 255) as u8,
+            ((x + y + t / 5) % 255) as u8,
+        ])
+    })
+}
+
+// Filled rectangle, clipped at the image border
+fn fill_rect(image: &mut RgbImage, left: u32, top: u32, width: u32, height: u32, color: Rgb<u8>) {
+    for y in top..(top + height).min(image.height()) {
+        for x in left..(left + width).min(image.width()) {
+            image.put_pixel(x, y, color);
+        }
+    }
+}
+
+/// Insert a comment segment with the generation time (Unix microseconds) right after the JPEG start marker
+pub fn add_timestamp_comment(jpeg: Vec<u8>, generated_at_us: i64) -> Vec<u8> {
+    if jpeg.len() < 2 {
+        return jpeg;
+    }
+    let payload = [TIMESTAMP_COMMENT, generated_at_us.to_string().as_bytes()].concat();
+    let mut frame = Vec::with_capacity(jpeg.len() + payload.len() + 4);
+    frame.extend_from_slice(&jpeg[..2]);
+    frame.extend_from_slice(&[0xFF, 0xFE]);
+    frame.extend_from_slice(&(payload.len() as u16 + 2).to_be_bytes());
+    frame.extend_from_slice(&payload);
+    frame.extend_from_slice(&jpeg[2..]);
+    frame
+}
+
+/// Generation time of a test pattern frame, None for frames of real cameras
+pub fn timestamp_comment(frame: &[u8]) -> Option<i64> {
+    if frame.get(..4)? != [0xFF, 0xD8, 0xFF, 0xFE] {
+        return None;
+    }
+    let length = u16::from_be_bytes([*frame.get(4)?, *frame.get(5)?]) as usize;
+    let payload = frame.get(6..4 + length)?;
+    std::str::from_utf8(payload.strip_prefix(TIMESTAMP_COMMENT)?).ok()?.parse().ok()
+}
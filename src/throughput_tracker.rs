@@ -1,13 +1,18 @@
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::{RwLock, OnceCell};
+use tokio::sync::{Mutex, RwLock, OnceCell};
 static GLOBAL_THROUGHPUT_TRACKER: OnceCell<Arc<ThroughputTracker>> = OnceCell::const_new();
 use tokio::time::{Duration, interval};
 use tracing::{info, error, debug};
 use chrono::Utc;
 
-use crate::database::DatabaseProvider;
+use crate::database::{DatabaseProvider, ThroughputStats as StoredThroughputStats};
 use crate::mqtt::{MqttHandle, ThroughputStats as MqttThroughputStats};
+use crate::stats_store::StatsBackend;
+
+// Buffered rows kept while the stats backend is unreachable
+const MAX_PENDING_STATS: usize = 100_000;
+const STATS_CLEANUP_INTERVAL_SECS: u64 = 3600;
 
 #[derive(Debug, Clone)]
 pub struct ThroughputStats {
@@ -47,17 +52,26 @@ pub struct ThroughputTracker {
     databases: Arc<RwLock<HashMap<String, Arc<dyn DatabaseProvider>>>>,
     mqtt_handle: Option<MqttHandle>,
     database_logging_enabled: bool,
+    // Replaces the recording databases as destination when configured
+    stats_backend: Option<StatsBackend>,
+    pending_stats: Mutex<Vec<StoredThroughputStats>>,
 }
 
 impl ThroughputTracker {
-    pub fn new_with_mqtt(mqtt_handle: Option<MqttHandle>, database_logging_enabled: bool) -> Self {
+    pub fn new_with_mqtt(mqtt_handle: Option<MqttHandle>, database_logging_enabled: bool, stats_backend: Option<StatsBackend>) -> Self {
         Self {
             cameras: Arc::new(RwLock::new(HashMap::new())),
             databases: Arc::new(RwLock::new(HashMap::new())),
             mqtt_handle,
             database_logging_enabled,
+            stats_backend,
+            pending_stats: Mutex::new(Vec::new()),
         }
     }
+
+    pub fn stats_backend(&self) -> Option<&StatsBackend> {
+        self.stats_backend.as_ref()
+    }
     
     /// Register a camera for throughput tracking
     pub async fn register_camera(&self, camera_id: &str) {
@@ -108,17 +122,49 @@ impl ThroughputTracker {
         tokio::spawn(async move {
             let mut interval = interval(Duration::from_secs(1));
             info!("Started throughput tracking task - recording every second");
+            let mut elapsed_secs: u64 = 0;
             
             loop {
                 interval.tick().await;
+                elapsed_secs += 1;
                 
                 if let Err(e) = self.record_throughput_stats().await {
                     error!("Failed to record throughput stats: {}", e);
                 }
+                
+                if let Some(ref backend) = self.stats_backend {
+                    if elapsed_secs.is_multiple_of(backend.flush_interval.as_secs()) {
+                        self.flush_stats_backend(backend).await;
+                    }
+                    if let Some(retention) = backend.retention.filter(|_| elapsed_secs.is_multiple_of(STATS_CLEANUP_INTERVAL_SECS)) {
+                        match backend.store.cleanup_old_throughput_stats(Utc::now() - retention).await {
+                            Ok(deleted) if deleted > 0 => info!("Cleaned up {} old throughput stats from the stats backend", deleted),
+                            Ok(_) => {}
+                            Err(e) => error!("Failed to cleanup old throughput stats in the stats backend: {}", e),
+                        }
+                    }
+                }
             }
         })
     }
     
+    /// Write the buffered stats, they are kept for the next attempt if the backend fails
+    async fn flush_stats_backend(&self, backend: &StatsBackend) {
+        let batch = std::mem::take(&mut *self.pending_stats.lock().await);
+        if batch.is_empty() {
+            return;
+        }
+        if let Err(e) = backend.store.insert_throughput_stats(&batch).await {
+            error!("Failed to write {} throughput stats to the stats backend: {}", batch.len(), e);
+            let mut pending = self.pending_stats.lock().await;
+            let mut retained = batch;
+            retained.append(&mut pending);
+            let excess = retained.len().saturating_sub(MAX_PENDING_STATS);
+            retained.drain(..excess);
+            *pending = retained;
+        }
+    }
+    
     /// Record throughput statistics for all cameras
     async fn record_throughput_stats(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let cameras = self.cameras.read().await;
@@ -140,12 +186,21 @@ impl ThroughputTracker {
                 debug!(
                     "Throughput for camera '{}': {} bytes/s, {} frames, {:.1} fps, {} connections (DB: {}, MQTT: {})",
                     camera_id, stats.bytes_per_second, stats.frame_count, stats.ffmpeg_fps, stats.connection_count,
-                    if self.database_logging_enabled { "enabled" } else { "disabled" },
+                    if self.stats_backend.is_some() { "stats backend" } else if self.database_logging_enabled { "enabled" } else { "disabled" },
                     if self.mqtt_handle.is_some() { "enabled" } else { "disabled" }
                 );
                 
-                // Record to database if enabled and database is available
-                if self.database_logging_enabled {
+                // Record to the stats backend if configured, else to the recording database if enabled
+                if self.stats_backend.is_some() {
+                    self.pending_stats.lock().await.push(StoredThroughputStats {
+                        camera_id: camera_id.clone(),
+                        timestamp: now,
+                        bytes_per_second: stats.bytes_per_second,
+                        frame_count: stats.frame_count,
+                        ffmpeg_fps: stats.ffmpeg_fps,
+                        connection_count: stats.connection_count,
+                    });
+                } else if self.database_logging_enabled {
                     if let Some(database) = databases.get(camera_id) {
                         if let Err(e) = database.record_throughput_stats(
                             camera_id,
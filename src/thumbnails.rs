@@ -0,0 +1,82 @@
+use std::sync::Arc;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use tokio::sync::broadcast;
+use tokio::time::{timeout, Duration};
+use tracing::{debug, warn};
+
+use crate::database::DatabaseProvider;
+use crate::errors::Result;
+use crate::jpeg;
+
+// Thumbnails fit into a square of this size, keeping the aspect ratio
+const THUMBNAIL_SIZE: u32 = 320;
+const THUMBNAIL_QUALITY: u8 = 75;
+// How long a new session waits for a live frame for its thumbnail
+const FIRST_FRAME_TIMEOUT_SECS: u64 = 30;
+
+/// Scale a JPEG frame down to a thumbnail
+fn create_thumbnail(frame: &[u8]) -> Result<Vec<u8>> {
+    let image = jpeg::decode(frame)?;
+    let thumbnail = if image.width() > THUMBNAIL_SIZE || image.height() > THUMBNAIL_SIZE {
+        image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+    } else {
+        image
+    };
+
+    jpeg::encode(&thumbnail.to_rgb8(), THUMBNAIL_QUALITY)
+}
+
+/// Create the thumbnail of a frame and store it for the session or segment starting at `timestamp`
+pub async fn store_thumbnail(database: Arc<dyn DatabaseProvider>, camera_id: String, timestamp: DateTime<Utc>, frame: Bytes) {
+    let thumbnail = match tokio::task::spawn_blocking(move || create_thumbnail(&frame)).await {
+        Ok(Ok(thumbnail)) => thumbnail,
+        Ok(Err(e)) => {
+            warn!("Failed to create thumbnail for camera '{}' at {}: {}", camera_id, timestamp, e);
+            return;
+        }
+        Err(e) => {
+            warn!("Thumbnail task for camera '{}' failed: {}", camera_id, e);
+            return;
+        }
+    };
+
+    match database.add_recording_thumbnail(&camera_id, timestamp, &thumbnail).await {
+        Ok(()) => debug!("Stored {} byte thumbnail for camera '{}' at {}", thumbnail.len(), camera_id, timestamp),
+        Err(e) => warn!("Failed to store thumbnail for camera '{}' at {}: {}", camera_id, timestamp, e),
+    }
+}
+
+/// Store the thumbnail of a new session from its first pre-recorded frame, or the next live frame
+pub fn spawn_session_thumbnail(
+    database: Arc<dyn DatabaseProvider>,
+    camera_id: String,
+    session_start: DateTime<Utc>,
+    first_frame: Option<Bytes>,
+    mut frames: broadcast::Receiver<Bytes>,
+) {
+    tokio::spawn(async move {
+        let frame = match first_frame {
+            Some(frame) => frame,
+            None => {
+                let next_frame = async {
+                    loop {
+                        match frames.recv().await {
+                            Ok(frame) => return Some(frame),
+                            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                            Err(broadcast::error::RecvError::Closed) => return None,
+                        }
+                    }
+                };
+                match timeout(Duration::from_secs(FIRST_FRAME_TIMEOUT_SECS), next_frame).await {
+                    Ok(Some(frame)) => frame,
+                    _ => {
+                        debug!("No frame for the thumbnail of camera '{}' session at {}", camera_id, session_start);
+                        return;
+                    }
+                }
+            }
+        };
+        store_thumbnail(database, camera_id, session_start, frame).await;
+    });
+}
@@ -1,47 +1,26 @@
 use bytes::Bytes;
 use tracing::info;
 use crate::errors::Result;
+use crate::jpeg;
+use crate::test_pattern::{self, TestPatternGenerator};
 
 pub struct FrameTranscoder {
+    quality: u8,
 }
 
 impl FrameTranscoder {
     pub async fn new(quality: u8) -> Self {
         info!("Initializing transcoder with quality: {} (using direct FFmpeg streaming)", quality);
-        Self {}
+        Self { quality }
     }
 
-    pub async fn create_test_frame(&self) -> Result<Bytes> {
-        Ok(Bytes::from(self.create_test_jpeg()))
+    /// Render and encode the next test pattern frame
+    pub fn create_test_frame(&self, generator: &mut TestPatternGenerator) -> Result<Bytes> {
+        // Large patterns take several milliseconds to encode
+        tokio::task::block_in_place(|| {
+            let image = generator.render();
+            let jpeg = jpeg::encode(&image, self.quality)?;
+            Ok(Bytes::from(test_pattern::add_timestamp_comment(jpeg, chrono::Utc::now().timestamp_micros())))
+        })
     }
-
-
-    fn create_test_jpeg(&self) -> Vec<u8> {
-        use image::{ImageBuffer, Rgb};
-        
-        let width = 640u32;
-        let height = 480u32;
-        
-        let img = ImageBuffer::from_fn(width, height, |x, y| {
-            let t = std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_millis() as u32;
-            
-            let r = ((x + t / 10) % 255) as u8;
-            let g = ((y + t / 20) % 255) as u8;
-            let b = (((x + y + t / 5) % 255)) as u8;
-            
-            Rgb([r, g, b])
-        });
-        
-        let mut jpeg_data = Vec::new();
-        {
-            let mut cursor = std::io::Cursor::new(&mut jpeg_data);
-            img.write_to(&mut cursor, image::ImageFormat::Jpeg)
-                .expect("Failed to encode JPEG");
-        }
-        
-        jpeg_data
-    }
-}
\ No newline at end of file
+}
@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+use serde_json::Value;
+use tracing::info;
+
+use crate::config::{CameraConfig, FfmpegConfig};
+use crate::errors::{Result, StreamError};
+
+lazy_static::lazy_static! {
+    static ref PROFILES: RwLock<HashMap<String, FfmpegConfig>> = RwLock::new(HashMap::new());
+}
+
+/// Load the profiles of the server configuration
+pub fn init(profiles: &HashMap<String, FfmpegConfig>) {
+    if !profiles.is_empty() {
+        info!("Loaded {} transcoding profiles", profiles.len());
+    }
+    *PROFILES.write().unwrap_or_else(|e| e.into_inner()) = profiles.clone();
+}
+
+/// Whether the name can be used as a profile name
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name.len() <= 64
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+pub fn all() -> HashMap<String, FfmpegConfig> {
+    PROFILES.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+pub fn get(name: &str) -> Option<FfmpegConfig> {
+    PROFILES.read().unwrap_or_else(|e| e.into_inner()).get(name).cloned()
+}
+
+/// Add or replace a profile, returns the previous settings
+pub fn set(name: &str, profile: FfmpegConfig) -> Option<FfmpegConfig> {
+    PROFILES.write().unwrap_or_else(|e| e.into_inner()).insert(name.to_string(), profile)
+}
+
+pub fn remove(name: &str) -> Option<FfmpegConfig> {
+    PROFILES.write().unwrap_or_else(|e| e.into_inner()).remove(name)
+}
+
+/// FFmpeg settings of a camera with its profile applied; settings in the camera's own
+/// `ffmpeg` block override the profile's
+pub fn effective_ffmpeg(camera_config: &CameraConfig) -> Result<Option<FfmpegConfig>> {
+    let Some(ref name) = camera_config.transcoding_profile else {
+        return Ok(camera_config.ffmpeg.clone());
+    };
+    let profile = get(name)
+        .ok_or_else(|| StreamError::config(format!("Transcoding profile '{}' does not exist", name)))?;
+    let Some(ref overrides) = camera_config.ffmpeg else {
+        return Ok(Some(profile));
+    };
+
+    let mut merged = serde_json::to_value(&profile)?;
+    if let (Some(merged), Value::Object(overrides)) = (merged.as_object_mut(), serde_json::to_value(overrides)?) {
+        merged.extend(overrides.into_iter().filter(|(_, value)| !value.is_null()));
+    }
+    Ok(Some(serde_json::from_value(merged)?))
+}
@@ -0,0 +1,573 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use serde::Serialize;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tokio::time::{Duration, Instant};
+use tracing::{info, warn, error, debug};
+
+use crate::config::{self, TriggerAction, TriggerConfig, TriggerSource};
+use crate::errors::{Result, StreamError};
+use crate::mqtt::MqttHandle;
+use crate::ptz::onvif_events::OnvifEventSubscription;
+use crate::AppState;
+
+/// Result of firing a trigger
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum TriggerOutcome {
+    Executed { detail: serde_json::Value },
+    /// A recording started by this trigger is still running and its max duration was extended
+    Extended { session_id: i64 },
+    /// A recording not owned by the trigger system is already running
+    AlreadyRecording { session_id: i64 },
+    Debounced { retry_after_secs: u64 },
+    Disabled,
+}
+
+// Recording started by a trigger, stopped by a watchdog once the deadline passes
+struct TriggerRecording {
+    session_id: i64,
+    deadline: Instant,
+}
+
+/// Dispatches trigger sources (webhook, MQTT, ONVIF and GPIO inputs, plugins, audio level, tamper) to their configured actions
+#[derive(Default)]
+pub struct TriggerManager {
+    last_fired: Mutex<HashMap<(String, String), Instant>>,
+    trigger_recordings: Arc<Mutex<HashMap<String, TriggerRecording>>>,
+    listeners: Mutex<HashMap<String, Vec<JoinHandle<()>>>>,
+    // MQTT topics subscribed for each camera's triggers
+    mqtt_topics: Mutex<HashMap<String, (MqttHandle, Vec<String>)>>,
+}
+
+impl TriggerManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// (Re)start the listeners for a camera's MQTT, ONVIF and GPIO input, audio level and tamper triggers, its analytics plugins, audio analysis, motion heatmap and tamper detection
+    pub async fn start_camera(self: &Arc<Self>, state: &AppState, camera_id: &str, camera_config: &config::CameraConfig) {
+        self.stop_listeners(camera_id).await;
+
+        let mut handles = Vec::new();
+        let mut mqtt_topics = Vec::new();
+        for trigger in camera_config.triggers.iter().filter(|t| t.enabled) {
+            match &trigger.source {
+                // Plugin events are dispatched by the plugin runner
+                TriggerSource::Http | TriggerSource::Plugin { .. } => {}
+                TriggerSource::Mqtt { topic, .. } => {
+                    match &state.mqtt_handle {
+                        Some(mqtt) => {
+                            if let Err(e) = mqtt.subscribe(topic).await {
+                                error!("Failed to subscribe to MQTT topic '{}' for trigger '{}' of camera '{}': {}",
+                                       topic, trigger.id, camera_id, e);
+                            }
+                            mqtt_topics.push(topic.clone());
+                        }
+                        None => warn!("Trigger '{}' of camera '{}' uses MQTT but MQTT is not enabled", trigger.id, camera_id),
+                    }
+                }
+                TriggerSource::OnvifInput { onvif_url, input_token, poll_interval_secs } => {
+                    let ptz = camera_config.ptz.as_ref();
+                    let Some(url) = onvif_url.clone().or_else(|| ptz.and_then(|p| p.onvif_url.clone())) else {
+                        warn!("Trigger '{}' of camera '{}' has no ONVIF URL, skipping", trigger.id, camera_id);
+                        continue;
+                    };
+                    let username = ptz.and_then(|p| p.username.clone());
+                    let password = ptz.and_then(|p| p.password.clone());
+                    handles.push(tokio::spawn(onvif_input_loop(
+                        self.clone(),
+                        state.clone(),
+                        camera_id.to_string(),
+                        trigger.id.clone(),
+                        OnvifInputSettings { url, username, password, input_token: input_token.clone(), poll_interval_secs: *poll_interval_secs },
+                    )));
+                }
+                TriggerSource::Gpio { pin, active_low, poll_interval_ms } => {
+                    handles.push(tokio::spawn(gpio_input_loop(
+                        self.clone(),
+                        state.clone(),
+                        camera_id.to_string(),
+                        trigger.id.clone(),
+                        GpioInputSettings { pin: *pin, active_low: *active_low, poll_interval: Duration::from_millis((*poll_interval_ms).max(10)) },
+                    )));
+                }
+                TriggerSource::AudioLevel { threshold_db, min_duration_ms } => {
+                    if !camera_config.audio_level.as_ref().is_some_and(|a| a.enabled) {
+                        warn!("Trigger '{}' of camera '{}' uses the audio level but audio_level is not enabled", trigger.id, camera_id);
+                    }
+                    handles.push(tokio::spawn(audio_level_loop(
+                        self.clone(),
+                        state.clone(),
+                        camera_id.to_string(),
+                        trigger.id.clone(),
+                        *threshold_db,
+                        Duration::from_millis(*min_duration_ms),
+                    )));
+                }
+                TriggerSource::Tamper { kind } => {
+                    if !camera_config.tamper_detection.as_ref().is_some_and(|t| t.enabled) {
+                        warn!("Trigger '{}' of camera '{}' uses tamper events but tamper_detection is not enabled", trigger.id, camera_id);
+                    }
+                    handles.push(tokio::spawn(tamper_loop(
+                        self.clone(),
+                        state.clone(),
+                        camera_id.to_string(),
+                        trigger.id.clone(),
+                        *kind,
+                    )));
+                }
+            }
+        }
+        self.set_mqtt_topics(camera_id, state.mqtt_handle.as_ref(), mqtt_topics).await;
+
+        if let Some(audio_level) = camera_config.audio_level.as_ref().filter(|a| a.enabled) {
+            handles.push(tokio::spawn(crate::audio_level::run_monitor(
+                state.clone(),
+                camera_id.to_string(),
+                camera_config.clone(),
+                audio_level.clone(),
+            )));
+        }
+
+        if let Some(onvif_metadata) = camera_config.onvif_metadata.as_ref().filter(|m| m.enabled) {
+            handles.push(tokio::spawn(crate::onvif_metadata::run_reader(
+                state.clone(),
+                camera_id.to_string(),
+                camera_config.clone(),
+                onvif_metadata.clone(),
+            )));
+        }
+
+        if let Some(motion_heatmap) = camera_config.motion_heatmap.as_ref().filter(|m| m.enabled) {
+            handles.push(tokio::spawn(crate::motion_heatmap::run_aggregator(
+                state.clone(),
+                camera_id.to_string(),
+                motion_heatmap.clone(),
+            )));
+        }
+
+        if let Some(clock_drift) = camera_config.clock_drift.as_ref().filter(|c| c.enabled) {
+            handles.push(tokio::spawn(crate::clock_drift::run_monitor(
+                camera_id.to_string(),
+                camera_config.clone(),
+                clock_drift.clone(),
+            )));
+        }
+
+        if let Some(tamper_detection) = camera_config.tamper_detection.as_ref().filter(|t| t.enabled) {
+            handles.push(tokio::spawn(crate::tamper::run_detector(
+                state.clone(),
+                camera_id.to_string(),
+                tamper_detection.clone(),
+            )));
+        }
+
+        for plugin in camera_config.plugins.iter().filter(|p| p.enabled) {
+            handles.push(tokio::spawn(crate::plugins::run_plugin(
+                self.clone(),
+                state.clone(),
+                camera_id.to_string(),
+                plugin.clone(),
+            )));
+        }
+
+        if !camera_config.triggers.is_empty() {
+            info!("Registered {} trigger(s) for camera '{}'", camera_config.triggers.len(), camera_id);
+        }
+        if !handles.is_empty() {
+            self.listeners.lock().await.insert(camera_id.to_string(), handles);
+        }
+    }
+
+    pub async fn stop_camera(&self, camera_id: &str) {
+        self.stop_listeners(camera_id).await;
+        self.set_mqtt_topics(camera_id, None, Vec::new()).await;
+    }
+
+    async fn stop_listeners(&self, camera_id: &str) {
+        if let Some(handles) = self.listeners.lock().await.remove(camera_id) {
+            for handle in handles {
+                handle.abort();
+            }
+        }
+        crate::clock_drift::clear_drift(camera_id);
+        crate::tamper::clear_state(camera_id);
+    }
+
+    /// Record the MQTT topics of a camera's triggers and unsubscribe the ones it no longer
+    /// uses, unless the triggers of another camera still need them
+    async fn set_mqtt_topics(&self, camera_id: &str, mqtt: Option<&MqttHandle>, topics: Vec<String>) {
+        let mut mqtt_topics = self.mqtt_topics.lock().await;
+        if let Some((previous_mqtt, previous)) = mqtt_topics.remove(camera_id) {
+            for topic in previous {
+                let in_use = topics.contains(&topic)
+                    || mqtt_topics.values().any(|(_, other)| other.contains(&topic));
+                if in_use {
+                    continue;
+                }
+                if let Err(e) = previous_mqtt.unsubscribe(&topic).await {
+                    warn!("Failed to unsubscribe from MQTT topic '{}' of camera '{}': {}", topic, camera_id, e);
+                }
+            }
+        }
+        if let Some(mqtt) = mqtt.filter(|_| !topics.is_empty()) {
+            mqtt_topics.insert(camera_id.to_string(), (mqtt.clone(), topics));
+        }
+    }
+
+    /// Route inbound MQTT messages to the triggers subscribed to them
+    pub fn start_mqtt_dispatcher(self: &Arc<Self>, state: AppState) {
+        let Some(mqtt) = state.mqtt_handle.clone() else { return; };
+        let manager = self.clone();
+        let mut incoming = mqtt.incoming_messages();
+
+        tokio::spawn(async move {
+            loop {
+                let (topic, payload) = match incoming.recv().await {
+                    Ok(message) => message,
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Trigger dispatcher lagged, skipped {} MQTT messages", skipped);
+                        continue;
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                };
+                let payload = String::from_utf8_lossy(&payload);
+
+                let matching: Vec<(String, String)> = {
+                    let camera_configs = state.camera_configs.read().await;
+                    camera_configs.iter()
+                        .flat_map(|(camera_id, cfg)| cfg.triggers.iter().map(move |t| (camera_id, t)))
+                        .filter(|(_, t)| match &t.source {
+                            TriggerSource::Mqtt { topic: filter, payload: expected } => {
+                                topic_matches(filter, &topic) && expected.as_deref().is_none_or(|p| p == payload.trim())
+                            }
+                            _ => false,
+                        })
+                        .map(|(camera_id, t)| (camera_id.clone(), t.id.clone()))
+                        .collect()
+                };
+
+                for (camera_id, trigger_id) in matching {
+                    manager.fire_and_log(&state, &camera_id, &trigger_id, "mqtt").await;
+                }
+            }
+        });
+    }
+
+    /// Fire a trigger, applying its debounce and executing its action
+    pub async fn fire(&self, state: &AppState, camera_id: &str, trigger_id: &str) -> Result<TriggerOutcome> {
+        let (camera_config, trigger) = {
+            let camera_configs = state.camera_configs.read().await;
+            let camera_config = camera_configs.get(camera_id)
+                .ok_or_else(|| StreamError::not_found(format!("Camera '{}' not found", camera_id)))?;
+            let trigger = camera_config.triggers.iter().find(|t| t.id == trigger_id).cloned()
+                .ok_or_else(|| StreamError::not_found(format!("Trigger '{}' not found for camera '{}'", trigger_id, camera_id)))?;
+            (camera_config.clone(), trigger)
+        };
+
+        if !trigger.enabled {
+            return Ok(TriggerOutcome::Disabled);
+        }
+
+        {
+            let mut last_fired = self.last_fired.lock().await;
+            let key = (camera_id.to_string(), trigger_id.to_string());
+            let debounce = Duration::from_secs(trigger.debounce_secs);
+            if let Some(previous) = last_fired.get(&key) {
+                let elapsed = previous.elapsed();
+                if elapsed < debounce {
+                    return Ok(TriggerOutcome::Debounced { retry_after_secs: (debounce - elapsed).as_secs() + 1 });
+                }
+            }
+            last_fired.insert(key, Instant::now());
+        }
+
+        // Actions need a running capture pipeline
+        crate::standby::wake_camera(state, camera_id).await;
+
+        if let Some(ref alert_manager) = state.alert_manager {
+            alert_manager.motion(state, camera_id, trigger_id).await;
+        }
+
+        let kind = |value: serde_json::Result<serde_json::Value>| value.ok().and_then(|v| v.get("type").cloned());
+        let event_data = serde_json::json!({
+            "source": kind(serde_json::to_value(&trigger.source)),
+            "action": kind(serde_json::to_value(&trigger.action)),
+        });
+        if let Some(ref recording_manager) = state.recording_manager {
+            recording_manager.record_event(camera_id, "trigger", &trigger.id, event_data.clone()).await;
+        }
+        crate::event_notifications::notify(state, camera_id, "trigger", &trigger.id, event_data).await;
+
+        match &trigger.action {
+            TriggerAction::StartRecording { reason } => self.start_recording(state, camera_id, &trigger, reason.as_deref()).await,
+            TriggerAction::PtzPreset { preset } => {
+                let controller = crate::api_ptz::build_ptz_controller(&camera_config)
+                    .map_err(|_| StreamError::config("PTZ is not enabled or not configured for this camera"))?;
+                if let Some(ref ptz_config) = camera_config.ptz {
+                    let client = crate::ptz_arbiter::PtzClient::trigger(&trigger.id, ptz_config);
+                    crate::ptz_arbiter::acquire(camera_id, &client, ptz_config).await
+                        .map_err(|conflict| StreamError::locked(conflict.message()))?;
+                }
+                controller.goto_preset(preset, None).await?;
+                Ok(TriggerOutcome::Executed { detail: serde_json::json!({ "preset": preset }) })
+            }
+            TriggerAction::Snapshot { directory } => {
+                let latest_frame = {
+                    let camera_streams = state.camera_streams.read().await;
+                    camera_streams.get(camera_id).map(|info| info.latest_frame.clone())
+                        .ok_or_else(|| StreamError::not_found(format!("Camera '{}' is not running", camera_id)))?
+                };
+                let frame = latest_frame.read().await.clone()
+                    .ok_or_else(|| StreamError::internal("No frame available yet"))?;
+
+                let directory = directory.clone().unwrap_or_else(|| format!("snapshots/{}", camera_id));
+                tokio::fs::create_dir_all(&directory).await?;
+                let file_name = format!("{}_{}.jpg", chrono::Utc::now().format("%Y%m%d_%H%M%S_%3f"), trigger.id);
+                let path = std::path::Path::new(&directory).join(file_name);
+                tokio::fs::write(&path, &frame).await?;
+                Ok(TriggerOutcome::Executed { detail: serde_json::json!({ "file": path.to_string_lossy(), "size": frame.len() }) })
+            }
+        }
+    }
+
+    pub(crate) async fn fire_and_log(&self, state: &AppState, camera_id: &str, trigger_id: &str, source: &str) {
+        match self.fire(state, camera_id, trigger_id).await {
+            Ok(TriggerOutcome::Debounced { .. }) => debug!("Trigger '{}' of camera '{}' debounced ({})", trigger_id, camera_id, source),
+            Ok(outcome) => info!("Trigger '{}' of camera '{}' fired by {}: {:?}", trigger_id, camera_id, source, outcome),
+            Err(e) => error!("Trigger '{}' of camera '{}' failed: {}", trigger_id, camera_id, e),
+        }
+    }
+
+    async fn start_recording(&self, state: &AppState, camera_id: &str, trigger: &TriggerConfig, reason: Option<&str>) -> Result<TriggerOutcome> {
+        let recording_manager = state.recording_manager.clone()
+            .ok_or_else(|| StreamError::config("Recording is not enabled"))?;
+        let stream_info = state.camera_streams.read().await.get(camera_id).cloned()
+            .ok_or_else(|| StreamError::not_found(format!("Camera '{}' is not running", camera_id)))?;
+
+        let mut trigger_recordings = self.trigger_recordings.lock().await;
+
+        if let Some(active) = recording_manager.get_active_recording(camera_id).await {
+            if let Some(owned) = trigger_recordings.get_mut(camera_id) {
+                if owned.session_id == active.session_id {
+                    if let Some(secs) = trigger.max_duration_secs {
+                        owned.deadline = owned.deadline.max(Instant::now() + Duration::from_secs(secs));
+                    }
+                    return Ok(TriggerOutcome::Extended { session_id: active.session_id });
+                }
+            }
+            // Never interrupt a recording started manually or by the API
+            return Ok(TriggerOutcome::AlreadyRecording { session_id: active.session_id });
+        }
+
+        let reason = reason.map(str::to_string).unwrap_or_else(|| format!("trigger:{}", trigger.id));
+        let session_id = recording_manager.start_recording(
+            camera_id,
+            "trigger",
+            Some(&reason),
+            None,
+            stream_info.frame_sender.clone(),
+            &stream_info.camera_config,
+            stream_info.pre_recording_buffer.as_ref(),
+        ).await?;
+
+        if let Some(secs) = trigger.max_duration_secs {
+            trigger_recordings.insert(camera_id.to_string(), TriggerRecording {
+                session_id,
+                deadline: Instant::now() + Duration::from_secs(secs),
+            });
+            let recordings = self.trigger_recordings.clone();
+            let camera_id = camera_id.to_string();
+            tokio::spawn(async move {
+                loop {
+                    let deadline = match recordings.lock().await.get(&camera_id) {
+                        Some(r) if r.session_id == session_id => r.deadline,
+                        _ => return,
+                    };
+                    if Instant::now() >= deadline {
+                        break;
+                    }
+                    tokio::time::sleep_until(deadline).await;
+                }
+                recordings.lock().await.remove(&camera_id);
+
+                let still_active = recording_manager.get_active_recording(&camera_id).await
+                    .is_some_and(|r| r.session_id == session_id);
+                if still_active {
+                    info!("Trigger recording for camera '{}' reached its max duration, stopping", camera_id);
+                    if let Err(e) = recording_manager.stop_recording(&camera_id).await {
+                        error!("Failed to stop trigger recording for camera '{}': {}", camera_id, e);
+                    }
+                }
+            });
+        }
+
+        Ok(TriggerOutcome::Executed { detail: serde_json::json!({ "session_id": session_id, "reason": reason }) })
+    }
+
+    /// Last time each trigger of a camera fired, as seconds ago
+    pub async fn last_fired_secs(&self, camera_id: &str) -> HashMap<String, u64> {
+        self.last_fired.lock().await.iter()
+            .filter(|((cam, _), _)| cam == camera_id)
+            .map(|((_, trigger_id), at)| (trigger_id.clone(), at.elapsed().as_secs()))
+            .collect()
+    }
+}
+
+struct OnvifInputSettings {
+    url: String,
+    username: Option<String>,
+    password: Option<String>,
+    input_token: Option<String>,
+    poll_interval_secs: u64,
+}
+
+async fn onvif_input_loop(manager: Arc<TriggerManager>, state: AppState, camera_id: String, trigger_id: String, settings: OnvifInputSettings) {
+    const RENEW_INTERVAL: Duration = Duration::from_secs(60);
+    const RETRY_DELAY: Duration = Duration::from_secs(30);
+
+    loop {
+        let subscription = match OnvifEventSubscription::create(settings.url.clone(), settings.username.clone(), settings.password.clone()).await {
+            Ok(s) => s,
+            Err(e) => {
+                warn!("ONVIF event subscription failed for trigger '{}' of camera '{}': {}", trigger_id, camera_id, e);
+                tokio::time::sleep(RETRY_DELAY).await;
+                continue;
+            }
+        };
+        info!("Listening for ONVIF digital input events for trigger '{}' of camera '{}'", trigger_id, camera_id);
+
+        let mut last_renew = Instant::now();
+        loop {
+            match subscription.pull(settings.poll_interval_secs).await {
+                Ok(events) => {
+                    let fired = events.iter().any(|e| {
+                        e.active && settings.input_token.as_ref().is_none_or(|t| e.input_token.as_ref() == Some(t))
+                    });
+                    if fired {
+                        manager.fire_and_log(&state, &camera_id, &trigger_id, "onvif input").await;
+                    }
+                }
+                Err(e) => {
+                    warn!("ONVIF event pull failed for trigger '{}' of camera '{}', resubscribing: {}", trigger_id, camera_id, e);
+                    break;
+                }
+            }
+
+            if last_renew.elapsed() >= RENEW_INTERVAL {
+                if let Err(e) = subscription.renew().await {
+                    warn!("ONVIF subscription renew failed for trigger '{}' of camera '{}': {}", trigger_id, camera_id, e);
+                    break;
+                }
+                last_renew = Instant::now();
+            }
+        }
+
+        tokio::time::sleep(Duration::from_secs(settings.poll_interval_secs.max(1))).await;
+    }
+}
+
+struct GpioInputSettings {
+    pin: u32,
+    active_low: bool,
+    poll_interval: Duration,
+}
+
+/// Poll a sysfs GPIO input and fire the trigger when it changes to active
+async fn gpio_input_loop(manager: Arc<TriggerManager>, state: AppState, camera_id: String, trigger_id: String, settings: GpioInputSettings) {
+    const RETRY_DELAY: Duration = Duration::from_secs(30);
+    let gpio_dir = format!("/sys/class/gpio/gpio{}", settings.pin);
+
+    loop {
+        if let Err(e) = export_gpio_input(settings.pin, &gpio_dir).await {
+            warn!("GPIO {} for trigger '{}' of camera '{}' is not available: {}", settings.pin, trigger_id, camera_id, e);
+            tokio::time::sleep(RETRY_DELAY).await;
+            continue;
+        }
+        info!("Listening for GPIO {} input for trigger '{}' of camera '{}'", settings.pin, trigger_id, camera_id);
+
+        // The state at startup is not an event, only changes to active are
+        let mut was_active = None;
+        let mut interval = tokio::time::interval(settings.poll_interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            let value = match tokio::fs::read_to_string(format!("{}/value", gpio_dir)).await {
+                Ok(value) => value,
+                Err(e) => {
+                    warn!("Failed to read GPIO {} for trigger '{}' of camera '{}': {}", settings.pin, trigger_id, camera_id, e);
+                    break;
+                }
+            };
+            let active = (value.trim() == "1") != settings.active_low;
+            if active && was_active == Some(false) {
+                manager.fire_and_log(&state, &camera_id, &trigger_id, "gpio input").await;
+            }
+            was_active = Some(active);
+        }
+
+        tokio::time::sleep(RETRY_DELAY).await;
+    }
+}
+
+/// Export a GPIO through sysfs unless it already is, and configure it as input
+async fn export_gpio_input(pin: u32, gpio_dir: &str) -> std::io::Result<()> {
+    if tokio::fs::metadata(gpio_dir).await.is_err() {
+        tokio::fs::write("/sys/class/gpio/export", pin.to_string()).await?;
+    }
+    tokio::fs::write(format!("{}/direction", gpio_dir), "in").await
+}
+
+/// Fire a trigger while the camera's audio RMS level stays at or above the threshold for at least `min_duration`
+async fn audio_level_loop(manager: Arc<TriggerManager>, state: AppState, camera_id: String, trigger_id: String, threshold_db: f64, min_duration: Duration) {
+    let mut levels = crate::audio_level::subscribe(&camera_id);
+    let mut loud_since: Option<Instant> = None;
+
+    loop {
+        let level = match levels.recv().await {
+            Ok(level) => level,
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+        };
+        if level.rms_db < threshold_db {
+            loud_since = None;
+            continue;
+        }
+        let since = *loud_since.get_or_insert_with(Instant::now);
+        // Keep firing while it stays loud, the debounce limits repetitions and recordings are extended
+        if since.elapsed() >= min_duration {
+            manager.fire_and_log(&state, &camera_id, &trigger_id, "audio level").await;
+        }
+    }
+}
+
+/// Fire a trigger when the camera gets covered or moved, optionally only for one kind of tamper event
+async fn tamper_loop(manager: Arc<TriggerManager>, state: AppState, camera_id: String, trigger_id: String, kind: Option<config::TamperKind>) {
+    let mut events = crate::tamper::subscribe(&camera_id);
+
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+        };
+        if event.active && kind.is_none_or(|kind| kind == event.kind) {
+            manager.fire_and_log(&state, &camera_id, &trigger_id, "tamper").await;
+        }
+    }
+}
+
+/// MQTT topic filter matching with `+` (single level) and `#` (remaining levels) wildcards
+fn topic_matches(filter: &str, topic: &str) -> bool {
+    let mut topic_levels = topic.split('/');
+    for level in filter.split('/') {
+        match level {
+            "#" => return true,
+            "+" => if topic_levels.next().is_none() { return false; },
+            _ => if topic_levels.next() != Some(level) { return false; },
+        }
+    }
+    topic_levels.next().is_none()
+}
@@ -1,3 +1,4 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use tokio::sync::broadcast;
 use tracing::{info, error};
@@ -5,10 +6,18 @@ use bytes::Bytes;
 
 use crate::config::{CameraConfig, TranscodingConfig, RtspConfig};
 use crate::errors::Result;
-use crate::rtsp_client::RtspClient;
+use crate::rtsp_client::{PipelineControl, RtspClient};
 use crate::mqtt::MqttHandle;
 use crate::pre_recording_buffer::PreRecordingBuffer;
 
+// Frame channel capacity of cameras whose transcoding config sets no `channel_buffer_size`
+static DEFAULT_CHANNEL_CAPACITY: AtomicUsize = AtomicUsize::new(1024);
+
+/// Apply the server's `runtime.frame_channel_capacity`
+pub fn configure_channel_capacity(capacity: usize) {
+    DEFAULT_CHANNEL_CAPACITY.store(capacity.max(1), Ordering::Relaxed);
+}
+
 pub struct VideoStream {
     pub camera_id: String,
     pub frame_sender: Arc<broadcast::Sender<Bytes>>,
@@ -38,12 +47,21 @@ impl VideoStream {
         shutdown_flag: Option<Arc<std::sync::atomic::AtomicBool>>,
         latest_frame: Arc<tokio::sync::RwLock<Option<bytes::Bytes>>>,
     ) -> Result<Self> {
+        // Profile first, its settings may contain secret placeholders too
+        let mut camera_config = camera_config;
+        camera_config.ffmpeg = crate::transcoding_profiles::effective_ffmpeg(&camera_config)?;
+        let camera_config = crate::secrets::resolve_camera_config(&camera_config)?;
+
         // Use camera-specific transcoding config if available, otherwise use default
         let transcoding = camera_config.transcoding_override.as_ref().unwrap_or(&default_transcoding);
         
-        let channel_buffer_size = transcoding.channel_buffer_size.unwrap_or(1024);
-        info!("Creating video stream for camera '{}' on path '{}' with buffer size: {} frames", 
-              camera_id, camera_config.path, channel_buffer_size);
+        let channel_buffer_size = camera_config.frame_channel.as_ref().and_then(|c| c.capacity)
+            .or(transcoding.channel_buffer_size)
+            .or(default_transcoding.channel_buffer_size)
+            .unwrap_or_else(|| DEFAULT_CHANNEL_CAPACITY.load(Ordering::Relaxed))
+            .max(1);
+        info!("Creating video stream for camera '{}' on path '{}' with buffer size: {} frames (lag policy: {:?})", 
+              camera_id, camera_config.path, channel_buffer_size, camera_config.frame_lag_policy());
         
         let (frame_tx, _) = broadcast::channel(channel_buffer_size);
         let frame_tx = Arc::new(frame_tx);
@@ -53,7 +71,14 @@ impl VideoStream {
             url: camera_config.url.clone(),
             transport: camera_config.transport.clone(),
             reconnect_interval: camera_config.reconnect_interval,
+            reconnect: camera_config.reconnect.clone().unwrap_or_default(),
             chunk_read_size: camera_config.chunk_read_size,
+            snapshot_source: camera_config.snapshot_source.clone().filter(|s| s.enabled),
+            device: camera_config.device.clone(),
+            test_pattern: camera_config.test_pattern.clone().filter(|t| t.enabled),
+            onvif: camera_config.onvif.clone().filter(|o| o.enabled),
+            tls: camera_config.tls.clone(),
+            stream_health: camera_config.stream_health.clone().filter(|h| h.enabled),
         };
         
         // Initialize pre-recording buffer if enabled (with proper fallback to global config)
@@ -76,9 +101,12 @@ impl VideoStream {
             let cleanup_interval = camera_config.get_pre_recording_cleanup_interval_seconds()
                 .or_else(|| global_recording_config.map(|cfg| cfg.pre_recording_cleanup_interval_seconds))
                 .unwrap_or(1);
-            info!("Enabling pre-recording buffer for camera '{}' with {} minutes duration and {} second cleanup interval", 
-                  camera_id, buffer_minutes, cleanup_interval);
-            Some(PreRecordingBuffer::new(buffer_minutes, cleanup_interval))
+            let max_size_mb = camera_config.get_pre_recording_max_size_mb()
+                .or_else(|| global_recording_config.map(|cfg| cfg.pre_recording_max_size_mb))
+                .unwrap_or(0);
+            info!("Enabling pre-recording buffer for camera '{}' with {} minutes duration, {} MB limit and {} second cleanup interval", 
+                  camera_id, buffer_minutes, max_size_mb, cleanup_interval);
+            Some(PreRecordingBuffer::new(buffer_minutes, max_size_mb * 1024 * 1024, cleanup_interval))
         } else {
             info!("Pre-recording buffer disabled for camera '{}'", camera_id);
             None
@@ -111,6 +139,10 @@ impl VideoStream {
         self.rtsp_client.get_fps_counter()
     }
     
+    pub fn get_pipeline_control(&self) -> PipelineControl {
+        self.rtsp_client.get_pipeline_control()
+    }
+    
     pub async fn start(self) -> tokio::task::JoinHandle<()> {
         let camera_id = self.camera_id.clone();
         
@@ -174,7 +206,7 @@ impl VideoStream {
             loop {
                 match receiver.recv().await {
                     Ok(frame_data) => {
-                        buffer.add_frame(frame_data).await;
+                        buffer.add_frame(frame_data, crate::clock_drift::capture_time(&camera_id, chrono::Utc::now())).await;
                     }
                     Err(broadcast::error::RecvError::Lagged(skipped)) => {
                         error!("Pre-recording buffer lagged for camera '{}', skipped {} frames", camera_id, skipped);
@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::time::{Duration, Instant, interval};
+use tracing::{debug, error, info};
+
+use crate::config::RecordingConfig;
+use crate::recording::RecordingManager;
+use crate::AppState;
+
+// How often the viewers of cameras are checked
+const CHECK_INTERVAL_SECS: u64 = 1;
+
+/// Reason of sessions recorded for viewers, followed by the identities of everyone who watched
+pub const REASON_PREFIX: &str = "viewers: ";
+
+/// Recording started by the monitor, the only kind it stops again
+struct ViewerRecording {
+    session_id: i64,
+    /// Identities of all viewers since the start, in the order they connected
+    viewers: Vec<String>,
+    last_viewer_at: Instant,
+}
+
+impl ViewerRecording {
+    fn reason(&self) -> String {
+        format!("{}{}", REASON_PREFIX, self.viewers.join(", "))
+    }
+}
+
+/// Start the task that records cameras with viewer recording enabled while at least one
+/// authenticated viewer is connected, stopping `viewer_recording_stop_after_secs` after the last one left
+pub fn start_monitor(state: AppState) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let (Some(recording_manager), Some(recording_config)) = (state.recording_manager.clone(), state.recording_config.clone()) else {
+            return;
+        };
+        let mut interval = interval(Duration::from_secs(CHECK_INTERVAL_SECS));
+        let mut recordings: HashMap<String, ViewerRecording> = HashMap::new();
+        loop {
+            interval.tick().await;
+            check_viewers(&state, &recording_manager, &recording_config, &mut recordings).await;
+        }
+    })
+}
+
+async fn check_viewers(
+    state: &AppState,
+    recording_manager: &Arc<RecordingManager>,
+    recording_config: &RecordingConfig,
+    recordings: &mut HashMap<String, ViewerRecording>,
+) {
+    let streams: Vec<_> = {
+        let camera_streams = state.camera_streams.read().await;
+        camera_streams.values()
+            .filter_map(|info| info.camera_config.viewer_recording_stop_after_secs(recording_config).map(|secs| (info.clone(), secs)))
+            .collect()
+    };
+
+    // Cameras removed or with viewer recording turned off keep no recording of their former viewers
+    let stale: Vec<String> = recordings.keys()
+        .filter(|camera_id| !streams.iter().any(|(info, _)| &info.camera_id == *camera_id))
+        .cloned()
+        .collect();
+    for camera_id in stale {
+        if let Some(recording) = recordings.remove(&camera_id) {
+            stop_owned(recording_manager, &camera_id, recording.session_id, "viewer recording was disabled").await;
+        }
+    }
+
+    for (info, stop_after_secs) in streams {
+        let camera_id = &info.camera_id;
+        let mut viewers: Vec<String> = Vec::new();
+        for viewer in crate::viewers::list(Some(camera_id)) {
+            let identity = viewer.identity.to_string();
+            if viewer.identity.is_authenticated() && !viewers.contains(&identity) {
+                viewers.push(identity);
+            }
+        }
+        let active = recording_manager.get_active_recording(camera_id).await;
+
+        if let Some(recording) = recordings.get_mut(camera_id) {
+            let owned = match active {
+                Some(ref active) if active.session_id == recording.session_id => true,
+                // Session segmentation continues the recording in a new session with the same reason
+                Some(ref active) => is_viewer_session(recording_manager, camera_id, active.session_id).await,
+                None => false,
+            };
+            if !owned {
+                debug!("Viewer recording of camera '{}' was stopped or replaced by another recording", camera_id);
+                recordings.remove(camera_id);
+                continue;
+            }
+            if let Some(ref active) = active {
+                recording.session_id = active.session_id;
+            }
+        }
+
+        match recordings.get_mut(camera_id) {
+            Some(recording) if !viewers.is_empty() => {
+                recording.last_viewer_at = Instant::now();
+                let joined: Vec<String> = viewers.into_iter().filter(|v| !recording.viewers.contains(v)).collect();
+                if joined.is_empty() {
+                    continue;
+                }
+                info!("Viewer recording of camera '{}' (session {}) now watched by {}", camera_id, recording.session_id, joined.join(", "));
+                recording.viewers.extend(joined);
+                if let Some(database) = recording_manager.get_camera_database(camera_id).await {
+                    match database.update_session_reason(recording.session_id, &recording.reason()).await {
+                        Ok(()) => {}
+                        // A locked session keeps the reason it was locked with
+                        Err(crate::errors::StreamError::Locked { .. }) => {
+                            debug!("Session {} is locked, its reason keeps the earlier viewers", recording.session_id);
+                        }
+                        Err(e) => error!("Failed to add viewers to the reason of session {}: {}", recording.session_id, e),
+                    }
+                }
+            }
+            Some(recording) => {
+                if recording.last_viewer_at.elapsed() >= Duration::from_secs(stop_after_secs) {
+                    let session_id = recording.session_id;
+                    recordings.remove(camera_id);
+                    let why = format!("no viewers for {} seconds", stop_after_secs);
+                    stop_owned(recording_manager, camera_id, session_id, &why).await;
+                }
+            }
+            // Never interrupt a recording started manually, by the API or a trigger
+            None if viewers.is_empty() || active.is_some() => {}
+            None => {
+                let mut recording = ViewerRecording { session_id: 0, viewers, last_viewer_at: Instant::now() };
+                let reason = recording.reason();
+                match recording_manager.start_recording(
+                    camera_id,
+                    "viewers",
+                    Some(&reason),
+                    None,
+                    info.frame_sender.clone(),
+                    &info.camera_config,
+                    info.pre_recording_buffer.as_ref(),
+                ).await {
+                    Ok(session_id) => {
+                        info!("Camera '{}' is watched by {}, started viewer recording (session {})", camera_id, recording.viewers.join(", "), session_id);
+                        recording.session_id = session_id;
+                        recordings.insert(camera_id.clone(), recording);
+                    }
+                    Err(e) => error!("Failed to start viewer recording for camera '{}': {}", camera_id, e),
+                }
+            }
+        }
+    }
+}
+
+async fn is_viewer_session(recording_manager: &RecordingManager, camera_id: &str, session_id: i64) -> bool {
+    let Some(database) = recording_manager.get_camera_database(camera_id).await else { return false };
+    matches!(database.get_session_reason(session_id).await, Ok(Some(reason)) if reason.starts_with(REASON_PREFIX))
+}
+
+async fn stop_owned(recording_manager: &RecordingManager, camera_id: &str, session_id: i64, why: &str) {
+    let still_active = recording_manager.get_active_recording(camera_id).await
+        .is_some_and(|r| r.session_id == session_id);
+    if !still_active {
+        return;
+    }
+    info!("Stopping viewer recording of camera '{}' (session {}): {}", camera_id, session_id, why);
+    if let Err(e) = recording_manager.stop_recording(camera_id).await {
+        error!("Failed to stop viewer recording for camera '{}': {}", camera_id, e);
+    }
+}
@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::sync::Notify;
+use tracing::{debug, warn};
+
+use crate::config::{CameraConfig, LagPolicy};
+
+lazy_static::lazy_static! {
+    static ref VIEWERS: Mutex<HashMap<String, Arc<Viewer>>> = Mutex::new(HashMap::new());
+}
+
+/// Kind of live WebSocket connection
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ViewerKind {
+    Live,
+    Dvr,
+    Roi,
+}
+
+/// Who opened the connection, derived from the token it was authorized with
+#[derive(Debug, Clone, Serialize)]
+pub struct ViewerIdentity {
+    /// camera_token, scoped_token, previous_token, session, unknown_token or anonymous
+    #[serde(rename = "type")]
+    pub identity_type: &'static str,
+    /// Token name or session user, else a short hash of the token
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+impl ViewerIdentity {
+    /// Connected with a valid token or login session
+    pub fn is_authenticated(&self) -> bool {
+        !matches!(self.identity_type, "anonymous" | "unknown_token")
+    }
+}
+
+impl std::fmt::Display for ViewerIdentity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.name {
+            Some(ref name) => write!(f, "{}:{}", self.identity_type, name),
+            None => f.write_str(self.identity_type),
+        }
+    }
+}
+
+struct Viewer {
+    connection_id: String,
+    camera_id: String,
+    kind: ViewerKind,
+    remote_addr: SocketAddr,
+    connected_at: DateTime<Utc>,
+    identity: ViewerIdentity,
+    lag_policy: LagPolicy,
+    stats: Arc<ViewerStats>,
+    kick: Notify,
+}
+
+/// Traffic counters of a connection, shared with the task sending the frames
+#[derive(Default)]
+pub struct ViewerStats {
+    bytes_sent: AtomicU64,
+    frames_sent: AtomicU64,
+    lagged_frames: AtomicU64,
+    lag_events: AtomicU64,
+    paused: AtomicBool,
+}
+
+impl ViewerStats {
+    pub fn record_sent(&self, bytes: usize, frames: u64) {
+        self.bytes_sent.fetch_add(bytes as u64, Ordering::Relaxed);
+        self.frames_sent.fetch_add(frames, Ordering::Relaxed);
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ViewerInfo {
+    pub connection_id: String,
+    pub camera_id: String,
+    pub kind: ViewerKind,
+    pub remote_addr: String,
+    pub connected_at: DateTime<Utc>,
+    pub connected_seconds: i64,
+    pub identity: ViewerIdentity,
+    pub bytes_sent: u64,
+    pub frames_sent: u64,
+    /// Frames overwritten in the camera's broadcast channel before this connection read them
+    pub lagged_frames: u64,
+    pub lag_events: u64,
+    pub lag_policy: LagPolicy,
+    /// Frame delivery suspended by the client, the connection stays open
+    pub paused: bool,
+}
+
+impl Viewer {
+    fn info(&self) -> ViewerInfo {
+        ViewerInfo {
+            connection_id: self.connection_id.clone(),
+            camera_id: self.camera_id.clone(),
+            kind: self.kind,
+            remote_addr: self.remote_addr.to_string(),
+            connected_at: self.connected_at,
+            connected_seconds: (Utc::now() - self.connected_at).num_seconds(),
+            identity: self.identity.clone(),
+            bytes_sent: self.stats.bytes_sent.load(Ordering::Relaxed),
+            frames_sent: self.stats.frames_sent.load(Ordering::Relaxed),
+            lagged_frames: self.stats.lagged_frames.load(Ordering::Relaxed),
+            lag_events: self.stats.lag_events.load(Ordering::Relaxed),
+            lag_policy: self.lag_policy,
+            paused: self.stats.paused.load(Ordering::Relaxed),
+        }
+    }
+
+    fn lagged(&self, skipped: u64) -> bool {
+        let stats = &self.stats;
+        stats.lagged_frames.fetch_add(skipped, Ordering::Relaxed);
+        let events = stats.lag_events.fetch_add(1, Ordering::Relaxed) + 1;
+        let disconnect = self.lag_policy == LagPolicy::Disconnect;
+        // The first lag of a connection is worth a warning, repeated ones would flood the log
+        if disconnect || events == 1 {
+            warn!("{:?} viewer {} ({}) of camera {} fell behind, skipped {} frames{}",
+                  self.kind, self.connection_id, self.remote_addr, self.camera_id,
+                  skipped, if disconnect { ", disconnecting" } else { "" });
+        } else {
+            debug!("{:?} viewer {} of camera {} skipped {} frames ({} total)",
+                   self.kind, self.connection_id, self.camera_id,
+                   skipped, stats.lagged_frames.load(Ordering::Relaxed));
+        }
+        disconnect
+    }
+}
+
+/// Registration of a connected viewer, removed from the registry when dropped
+pub struct ViewerGuard {
+    viewer: Arc<Viewer>,
+}
+
+impl ViewerGuard {
+    pub fn stats(&self) -> Arc<ViewerStats> {
+        self.viewer.stats.clone()
+    }
+
+    pub fn lag_reporter(&self) -> LagReporter {
+        LagReporter { viewer: self.viewer.clone() }
+    }
+
+    /// See [`LagReporter::lagged`]
+    pub fn lagged(&self, skipped: u64) -> bool {
+        self.viewer.lagged(skipped)
+    }
+
+    /// Completes once the connection was kicked via the admin API
+    pub async fn kicked(&self) {
+        self.viewer.kick.notified().await
+    }
+}
+
+/// Lag accounting of a viewer for the task reading its frames
+#[derive(Clone)]
+pub struct LagReporter {
+    viewer: Arc<Viewer>,
+}
+
+impl LagReporter {
+    /// Count frames the connection missed because it fell behind the broadcast channel.
+    /// Returns true when the camera's lag policy asks to close the connection.
+    pub fn lagged(&self, skipped: u64) -> bool {
+        self.viewer.lagged(skipped)
+    }
+}
+
+impl Drop for ViewerGuard {
+    fn drop(&mut self) {
+        VIEWERS.lock().unwrap_or_else(|e| e.into_inner()).remove(&self.viewer.connection_id);
+    }
+}
+
+pub fn register(
+    connection_id: &str,
+    camera_id: &str,
+    kind: ViewerKind,
+    remote_addr: SocketAddr,
+    identity: ViewerIdentity,
+    lag_policy: LagPolicy,
+) -> ViewerGuard {
+    let viewer = Arc::new(Viewer {
+        connection_id: connection_id.to_string(),
+        camera_id: camera_id.to_string(),
+        kind,
+        remote_addr,
+        connected_at: Utc::now(),
+        identity,
+        lag_policy,
+        stats: Arc::new(ViewerStats::default()),
+        kick: Notify::new(),
+    });
+    VIEWERS.lock().unwrap_or_else(|e| e.into_inner()).insert(connection_id.to_string(), viewer.clone());
+    ViewerGuard { viewer }
+}
+
+/// Identify the holder of the token a viewer connected with
+pub fn identify(camera_config: &CameraConfig, token: Option<&str>) -> ViewerIdentity {
+    let Some(token) = token else {
+        return ViewerIdentity { identity_type: "anonymous", name: None };
+    };
+    let (identity_type, name) = if camera_config.token.as_deref().is_some_and(|t| crate::secrets::matches(t, token)) {
+        ("camera_token", None)
+    } else if let Some(named) = camera_config.tokens.iter().find(|t| crate::secrets::matches(&t.token, token)) {
+        ("scoped_token", named.name.clone())
+    } else if let Some(previous) = camera_config.previous_tokens.iter().find(|p| p.accepts(token)) {
+        ("previous_token", previous.name.clone())
+    } else if let Some(session) = crate::ldap::get_session(token) {
+        ("session", Some(session.username))
+    } else {
+        ("unknown_token", None)
+    };
+    ViewerIdentity {
+        identity_type,
+        name: name.or_else(|| Some(crate::watermark::token_hash(token))),
+    }
+}
+
+/// Connected viewers, oldest connection first
+pub fn list(camera_id: Option<&str>) -> Vec<ViewerInfo> {
+    let mut viewers: Vec<ViewerInfo> = VIEWERS.lock().unwrap_or_else(|e| e.into_inner())
+        .values()
+        .filter(|viewer| camera_id.is_none_or(|id| viewer.camera_id == id))
+        .map(|viewer| viewer.info())
+        .collect();
+    viewers.sort_by_key(|viewer| viewer.connected_at);
+    viewers
+}
+
+pub fn get(connection_id: &str) -> Option<ViewerInfo> {
+    VIEWERS.lock().unwrap_or_else(|e| e.into_inner()).get(connection_id).map(|viewer| viewer.info())
+}
+
+/// Close a connection; the socket handler ends it on its next wakeup
+pub fn kick(connection_id: &str) -> Option<ViewerInfo> {
+    let viewers = VIEWERS.lock().unwrap_or_else(|e| e.into_inner());
+    let viewer = viewers.get(connection_id)?;
+    // notify_one keeps the permit if the handler is not waiting right now
+    viewer.kick.notify_one();
+    Some(viewer.info())
+}
@@ -6,6 +6,7 @@ use tokio::time::{Duration, Instant};
 use notify::{Config as NotifyConfig, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use tracing::{info, error};
 
+use crate::camera_templates;
 use crate::config;
 use crate::errors::{Result, StreamError};
 
@@ -40,6 +41,14 @@ pub async fn start_camera_config_watcher(app_state: AppState) -> Result<()> {
     watcher.watch(cameras_dir_path, RecursiveMode::NonRecursive)
         .map_err(|e| StreamError::config(&format!("Failed to watch cameras directory: {}", e)))?;
     info!("Started watching cameras directory '{}' for configuration changes", app_state.cameras_directory);
+
+    // Changed templates re-resolve the cameras using them
+    let templates_dir = camera_templates::templates_dir(&app_state.cameras_directory);
+    if templates_dir.is_dir() {
+        watcher.watch(&templates_dir, RecursiveMode::NonRecursive)
+            .map_err(|e| StreamError::config(format!("Failed to watch templates directory: {}", e)))?;
+        info!("Started watching templates directory '{}' for configuration changes", templates_dir.display());
+    }
     
     // Keep watcher alive and handle events with debouncing
     tokio::spawn(async move {
@@ -50,6 +59,9 @@ pub async fn start_camera_config_watcher(app_state: AppState) -> Result<()> {
             // Debounce events for each camera to prevent rapid duplicate calls
             let mut should_process = false;
             if let Some(camera_id) = event.paths.get(0).and_then(|p| get_camera_id_from_path(p)) {
+                let camera_id = if is_template_path(&event.paths[0]) { format!("template:{}", camera_id) } else { camera_id };
+                // Per event kind: the create event of a new file may find it still empty, its modify event must not be dropped
+                let camera_id = format!("{}:{:?}", camera_id, std::mem::discriminant(&event.kind));
                 let now = Instant::now();
                 let should_process_this = if let Some(last_time) = last_events.get(&camera_id) {
                     now.duration_since(*last_time) >= Duration::from_millis(500) // 500ms debounce
@@ -75,6 +87,15 @@ pub async fn start_camera_config_watcher(app_state: AppState) -> Result<()> {
 }
 
 async fn handle_file_event(event: Event, app_state: &AppState) {
+    if event.paths.iter().any(|p| is_template_path(p)) {
+        if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)) {
+            for path in &event.paths {
+                info!("Detected camera template change: {}", path.display());
+            }
+            reload_templated_cameras(app_state).await;
+        }
+        return;
+    }
     match event.kind {
         EventKind::Create(_) => {
             for path in event.paths {
@@ -93,6 +114,10 @@ async fn handle_file_event(event: Event, app_state: &AppState) {
                 if let Some(camera_id) = get_camera_id_from_path(&path) {
                     info!("Detected camera configuration change: {}", camera_id);
                     if let Ok(camera_config) = load_camera_config(&camera_id, &app_state.cameras_directory) {
+                        if is_config_unchanged(&camera_id, &camera_config, app_state).await {
+                            info!("Camera '{}' configuration matches the running config, skipping restart", camera_id);
+                            continue;
+                        }
                         if let Err(e) = app_state.restart_camera(camera_id.clone(), camera_config).await {
                             error!("Failed to restart camera '{}': {}", camera_id, e);
                         }
@@ -116,6 +141,49 @@ async fn handle_file_event(event: Event, app_state: &AppState) {
     }
 }
 
+/// Settings applied at runtime (e.g. via the transcoding API) are saved after the
+/// in-memory config was updated, so the resulting file event must not restart the camera
+async fn is_config_unchanged(camera_id: &str, camera_config: &config::CameraConfig, app_state: &AppState) -> bool {
+    let camera_configs = app_state.camera_configs.read().await;
+    let Some(current) = camera_configs.get(camera_id) else {
+        return false;
+    };
+    match (serde_json::to_value(current), serde_json::to_value(camera_config)) {
+        (Ok(current), Ok(loaded)) => current == loaded,
+        _ => false,
+    }
+}
+
+/// Re-resolve the cameras based on a template and restart those whose effective config changed
+async fn reload_templated_cameras(app_state: &AppState) {
+    let camera_ids: Vec<String> = app_state.camera_configs.read().await.iter()
+        .filter(|(_, camera_config)| camera_config.template.is_some())
+        .map(|(camera_id, _)| camera_id.clone())
+        .collect();
+    for camera_id in camera_ids {
+        let camera_config = match load_camera_config(&camera_id, &app_state.cameras_directory) {
+            Ok(camera_config) => camera_config,
+            Err(e) => {
+                error!("Failed to re-resolve camera '{}' after template change: {}", camera_id, e);
+                continue;
+            }
+        };
+        if is_config_unchanged(&camera_id, &camera_config, app_state).await {
+            continue;
+        }
+        info!("Restarting camera '{}' with its changed template", camera_id);
+        if let Err(e) = app_state.restart_camera(camera_id.clone(), camera_config).await {
+            error!("Failed to restart camera '{}': {}", camera_id, e);
+        }
+    }
+}
+
+fn is_template_path(path: &Path) -> bool {
+    path.parent()
+        .and_then(|dir| dir.file_name())
+        .is_some_and(|dir| dir == camera_templates::TEMPLATES_SUBDIRECTORY)
+}
+
 fn get_camera_id_from_path(path: &Path) -> Option<String> {
     if let Some(file_name) = path.file_name().and_then(|s| s.to_str()) {
         if file_name.ends_with(".json") {
@@ -133,6 +201,6 @@ fn load_camera_config(camera_id: &str, cameras_dir: &str) -> Result<config::Came
     let content = fs::read_to_string(&json_path)
         .map_err(|e| StreamError::config(&format!("Failed to read camera config file {}: {}", json_path, e)))?;
     
-    serde_json::from_str::<config::CameraConfig>(&content)
+    camera_templates::parse_camera_config(&content, cameras_dir)
         .map_err(|e| StreamError::config(&format!("Failed to parse JSON camera config file {}: {}", json_path, e)))
 }
\ No newline at end of file
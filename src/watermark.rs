@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use bytes::Bytes;
+use image::{Rgb, RgbImage};
+
+use crate::config::{CameraConfig, WatermarkConfig, WatermarkPosition};
+use crate::errors::Result;
+use crate::jpeg;
+
+const GLYPH_WIDTH: u32 = 5;
+const GLYPH_HEIGHT: u32 = 7;
+// Font pixels between two glyphs and around the text
+const SPACING: u32 = 1;
+const PADDING: u32 = 2;
+
+lazy_static::lazy_static! {
+    // Last decoded frame per camera, shared by its watermarked and ROI clients so each frame
+    // is decoded once and only the overlay and encoding happen per client
+    static ref DECODED: Mutex<HashMap<String, Arc<Mutex<Option<DecodedFrame>>>>> = Mutex::new(HashMap::new());
+}
+
+struct DecodedFrame {
+    source: Bytes,
+    image: Arc<RgbImage>,
+}
+
+/// Decoded image of a camera's frame. Clients receive clones of the same buffer from the frame
+/// channel, so the first one decodes it and the others wait for and reuse the result.
+pub fn decode_shared(camera_id: &str, frame: &Bytes) -> Result<Arc<RgbImage>> {
+    let slot = DECODED.lock().unwrap_or_else(|e| e.into_inner())
+        .entry(camera_id.to_string())
+        .or_default()
+        .clone();
+    let mut slot = slot.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(ref decoded) = *slot {
+        if decoded.source.as_ptr() == frame.as_ptr() && decoded.source.len() == frame.len() {
+            return Ok(decoded.image.clone());
+        }
+    }
+    let image = Arc::new(jpeg::decode(frame)?.to_rgb8());
+    *slot = Some(DecodedFrame { source: frame.clone(), image: image.clone() });
+    Ok(image)
+}
+
+/// True if frames of the camera may only be sent with a per-client watermark
+pub fn required(camera_config: &CameraConfig) -> bool {
+    camera_config.watermark.as_ref().is_some_and(|w| w.enabled)
+}
+
+/// Drop the decoded frame of a camera that stopped
+pub fn forget(camera_id: &str) {
+    DECODED.lock().unwrap_or_else(|e| e.into_inner()).remove(camera_id);
+}
+
+/// Identifier drawn into the frames sent to one client
+#[derive(Debug, Clone)]
+pub struct Watermark {
+    pub text: String,
+    camera_id: String,
+    position: WatermarkPosition,
+    quality: u8,
+    scale: Option<u32>,
+}
+
+impl Watermark {
+    /// Watermark for a client of the camera, None if watermarking is not enabled. The text
+    /// names the scoped token (or a short hash of the token) followed by the connection id,
+    /// which is logged together with the client address.
+    pub fn for_client(camera_id: &str, camera_config: &CameraConfig, token: Option<&str>, connection_id: &str) -> Option<Self> {
+        let config: &WatermarkConfig = camera_config.watermark.as_ref().filter(|w| w.enabled)?;
+        let holder = match token {
+            Some(token) => camera_config.tokens.iter()
+                .find(|t| crate::secrets::matches(&t.token, token))
+                .and_then(|t| t.name.clone())
+                .unwrap_or_else(|| token_hash(token)),
+            None => "ANON".to_string(),
+        };
+        let connection: String = connection_id.chars().filter(|c| *c != '-').take(8).collect();
+        Some(Self {
+            text: format!("{} {}", holder, connection).to_uppercase(),
+            camera_id: camera_id.to_string(),
+            position: config.position,
+            quality: config.quality.clamp(1, 100),
+            scale: config.scale,
+        })
+    }
+
+    /// Draw the identifier into a copy of the camera's shared decoded frame and encode it again
+    pub fn apply(&self, frame: &Bytes) -> Result<Vec<u8>> {
+        let mut image = decode_shared(&self.camera_id, frame)?.as_ref().clone();
+        self.draw(&mut image);
+        jpeg::encode(&image, self.quality)
+    }
+
+    /// Draw the identifier as white text on a darkened box into the image
+    pub fn draw(&self, image: &mut RgbImage) {
+        let scale = self.scale.unwrap_or(image.height() / 240).max(1);
+        let chars = self.text.chars().count() as u32;
+        let box_width = (chars * (GLYPH_WIDTH + SPACING) - SPACING + 2 * PADDING) * scale;
+        let box_height = (GLYPH_HEIGHT + 2 * PADDING) * scale;
+        if box_width > image.width() || box_height > image.height() {
+            return;
+        }
+        let (left, top) = match self.position {
+            WatermarkPosition::TopLeft => (0, 0),
+            WatermarkPosition::TopRight => (image.width() - box_width, 0),
+            WatermarkPosition::BottomLeft => (0, image.height() - box_height),
+            WatermarkPosition::BottomRight => (image.width() - box_width, image.height() - box_height),
+        };
+
+        for y in top..top + box_height {
+            for x in left..left + box_width {
+                let pixel = image.get_pixel_mut(x, y);
+                pixel.0 = pixel.0.map(|c| c / 3);
+            }
+        }
+        draw_text(image, &self.text, left + PADDING * scale, top + PADDING * scale, scale, Rgb([255, 255, 255]));
+    }
+}
+
+/// Width in pixels of `text` drawn with the bitmap font at `scale`
+pub fn text_width(text: &str, scale: u32) -> u32 {
+    (text.chars().count() as u32 * (GLYPH_WIDTH + SPACING)).saturating_sub(SPACING) * scale
+}
+
+/// Height in pixels of a line drawn with the bitmap font at `scale`
+pub fn text_height(scale: u32) -> u32 {
+    GLYPH_HEIGHT * scale
+}
+
+/// Draw `text` with the bitmap font, its top left corner at `left`/`top`; pixels outside the image are skipped
+pub fn draw_text(image: &mut RgbImage, text: &str, left: u32, top: u32, scale: u32, color: Rgb<u8>) {
+    for (index, c) in text.chars().enumerate() {
+        let glyph_left = left + index as u32 * (GLYPH_WIDTH + SPACING) * scale;
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for column in 0..GLYPH_WIDTH {
+                if bits & (1 << (GLYPH_WIDTH - 1 - column)) == 0 {
+                    continue;
+                }
+                let x0 = glyph_left + column * scale;
+                let y0 = top + row as u32 * scale;
+                for y in y0..(y0 + scale).min(image.height()) {
+                    for x in x0..(x0 + scale).min(image.width()) {
+                        image.put_pixel(x, y, color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// First 8 hex digits of the token's SHA-256, enough to tell tokens apart without revealing them
+pub fn token_hash(token: &str) -> String {
+    aws_lc_rs::digest::digest(&aws_lc_rs::digest::SHA256, token.as_bytes())
+        .as_ref()
+        .iter()
+        .take(4)
+        .map(|b| format!("{:02X}", b))
+        .collect()
+}
+
+// 5x7 bitmap font, one byte per row with the leftmost pixel in bit 4
+fn glyph(c: char) -> [u8; 7] {
+    match c.to_ascii_uppercase() {
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        'A' => [0x0E, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'B' => [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E],
+        'C' => [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E],
+        'D' => [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C],
+        'E' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F],
+        'F' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10],
+        'G' => [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F],
+        'H' => [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'I' => [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F],
+        'M' => [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'P' => [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10],
+        'Q' => [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D],
+        'R' => [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11],
+        'S' => [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E],
+        'T' => [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A],
+        'X' => [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04],
+        'Z' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F],
+        '-' => [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+        '_' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1F],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C],
+        ':' => [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00],
+        ' ' => [0x00; 7],
+        _ => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04],
+    }
+}
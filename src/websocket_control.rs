@@ -1,4 +1,5 @@
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use serde::{Deserialize, Serialize, Deserializer};
 use chrono::{DateTime, Utc};
 use tracing::{info, error, trace, debug};
@@ -10,6 +11,11 @@ use std::path::Path;
 
 use crate::recording::RecordingManager;
 use crate::database::RecordedFrame;
+use crate::config::PtzConfig;
+use crate::ptz::{PtzController, PtzVelocity};
+use crate::ptz_arbiter::{self, PtzClient, PtzHolder};
+use crate::ptz_joystick::PtzJoystick;
+use crate::watermark::Watermark;
 
 
 // Custom deserializer for timestamps that supports both string (ISO format) and number (ms since epoch)
@@ -145,6 +151,14 @@ fn default_sort_order() -> String {
     "newest".to_string()
 }
 
+fn default_stats_interval() -> u64 {
+    2
+}
+
+// Delivered/captured ratio at or above which the stream quality is "good" resp. "degraded"
+const GOOD_DELIVERY_RATIO: f64 = 0.9;
+const DEGRADED_DELIVERY_RATIO: f64 = 0.5;
+
 #[derive(Debug, Deserialize)]
 #[serde(tag = "cmd")]
 pub enum ControlCommand {
@@ -177,6 +191,27 @@ pub enum ControlCommand {
         #[serde(default = "default_sort_order")]
         sort_order: String, // "newest" or "oldest"
     },
+    #[serde(rename = "audio")]
+    AudioLevels {
+        enabled: bool,
+    },
+    #[serde(rename = "stats")]
+    StreamStats {
+        enabled: bool,
+        #[serde(default = "default_stats_interval")]
+        interval_secs: u64,
+    },
+    #[serde(rename = "ptz")]
+    PtzJoystick {
+        pan: f32,
+        tilt: f32,
+        #[serde(default)]
+        zoom: f32,
+    },
+    #[serde(rename = "ptz_control")]
+    PtzControl {
+        enabled: bool,
+    },
 }
 
 #[derive(Debug, Serialize)]
@@ -225,6 +260,125 @@ pub struct ReplayState {
 pub struct LiveStreamState {
     pub active: bool,
     pub stop_sender: Option<broadcast::Sender<()>>,
+    pub counters: Arc<LiveStreamCounters>,
+}
+
+/// Frames of the camera seen by the live stream of one client, kept across live stream restarts
+#[derive(Debug, Default)]
+pub struct LiveStreamCounters {
+    /// Running live stream tasks, a restarted stream may briefly overlap the ending one
+    pub streams: AtomicU64,
+    /// Frames produced by the camera while streaming, including the ones this client missed
+    pub captured: AtomicU64,
+    pub delivered: AtomicU64,
+    /// Frames skipped because the client (or its connection) was too slow
+    pub dropped: AtomicU64,
+    pub delivered_bytes: AtomicU64,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct CounterSnapshot {
+    captured: u64,
+    delivered: u64,
+    dropped: u64,
+    delivered_bytes: u64,
+}
+
+impl LiveStreamCounters {
+    fn snapshot(&self) -> CounterSnapshot {
+        CounterSnapshot {
+            captured: self.captured.load(Ordering::Relaxed),
+            delivered: self.delivered.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            delivered_bytes: self.delivered_bytes.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Periodic stream statistics of one client, sent as type `0x03` messages
+#[derive(Debug, Serialize)]
+pub struct StreamStatsMessage {
+    pub camera_id: String,
+    pub timestamp: DateTime<Utc>,
+    pub interval_secs: f64,
+    /// Whether the live stream of this client is running
+    pub live: bool,
+    pub capture_fps: f64,
+    pub delivered_fps: f64,
+    pub dropped_frames: u64,
+    pub dropped_frames_total: u64,
+    pub bandwidth_kbps: f64,
+    /// "good", "degraded" or "poor" from the delivered/captured ratio, None without live stream
+    pub quality: Option<&'static str>,
+    /// Camera-wide stream health (FFmpeg fps, bitrate, degradation)
+    pub camera: Option<crate::stream_health::StreamHealth>,
+}
+
+impl StreamStatsMessage {
+    fn new(camera_id: &str, live: bool, previous: CounterSnapshot, current: CounterSnapshot, elapsed_secs: f64) -> Self {
+        let elapsed_secs = elapsed_secs.max(0.001);
+        let captured = current.captured.saturating_sub(previous.captured);
+        let delivered = current.delivered.saturating_sub(previous.delivered);
+        let quality = (live && captured > 0).then(|| {
+            let ratio = delivered as f64 / captured as f64;
+            if ratio >= GOOD_DELIVERY_RATIO {
+                "good"
+            } else if ratio >= DEGRADED_DELIVERY_RATIO {
+                "degraded"
+            } else {
+                "poor"
+            }
+        });
+        Self {
+            camera_id: camera_id.to_string(),
+            timestamp: Utc::now(),
+            interval_secs: elapsed_secs,
+            live,
+            capture_fps: captured as f64 / elapsed_secs,
+            delivered_fps: delivered as f64 / elapsed_secs,
+            dropped_frames: current.dropped.saturating_sub(previous.dropped),
+            dropped_frames_total: current.dropped,
+            bandwidth_kbps: current.delivered_bytes.saturating_sub(previous.delivered_bytes) as f64 * 8.0 / 1000.0 / elapsed_secs,
+            quality,
+            camera: crate::stream_health::get_health(camera_id),
+        }
+    }
+}
+
+/// PTZ controller for the joystick command, or the error code and reason why this connection may not move the camera
+pub type PtzAccess = Result<PtzSession, (u16, &'static str)>;
+
+#[derive(Clone)]
+pub struct PtzSession {
+    pub controller: Arc<dyn PtzController>,
+    /// Identity of this connection towards other clients sending PTZ commands
+    pub client: PtzClient,
+    pub config: PtzConfig,
+}
+
+/// Client in control of the PTZ, sent as type `0x04` messages when it changes
+#[derive(Debug, Serialize)]
+pub struct PtzControlMessage {
+    pub camera_id: String,
+    /// None while no client has control
+    pub holder: Option<PtzHolder>,
+    /// Whether the holder is this connection
+    pub own: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AudioLevelState {
+    pub stop_sender: Option<broadcast::Sender<()>>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct StreamStatsState {
+    pub stop_sender: Option<broadcast::Sender<()>>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PtzControlState {
+    pub stop_sender: Option<broadcast::Sender<()>>,
 }
 
 impl Default for ReplayState {
@@ -243,10 +397,27 @@ impl Default for LiveStreamState {
         Self {
             active: false,
             stop_sender: None,
+            counters: Arc::new(LiveStreamCounters::default()),
         }
     }
 }
 
+/// Per-connection state of a control WebSocket, owned by its receive task
+struct ControlSession {
+    camera_id: String,
+    recording_manager: Arc<RecordingManager>,
+    frame_sender: Arc<broadcast::Sender<Bytes>>,
+    replay_state: ReplayState,
+    live_stream_state: LiveStreamState,
+    audio_level_state: AudioLevelState,
+    stream_stats_state: StreamStatsState,
+    ptz_control_state: PtzControlState,
+    ptz: PtzAccess,
+    ptz_joystick: Option<PtzJoystick>,
+    watermark: Option<Watermark>,
+    sender: Arc<tokio::sync::Mutex<futures_util::stream::SplitSink<WebSocket, Message>>>,
+}
+
 pub struct ControlHandler {
     camera_id: String,
     client_id: String,
@@ -254,6 +425,11 @@ pub struct ControlHandler {
     frame_sender: Arc<broadcast::Sender<Bytes>>,
     replay_state: ReplayState,
     live_stream_state: LiveStreamState,
+    audio_level_state: AudioLevelState,
+    stream_stats_state: StreamStatsState,
+    ptz_control_state: PtzControlState,
+    ptz: PtzAccess,
+    watermark: Option<Watermark>,
 }
 
 impl ControlHandler {
@@ -262,14 +438,21 @@ impl ControlHandler {
         client_id: String,
         recording_manager: Arc<RecordingManager>,
         frame_sender: Arc<broadcast::Sender<Bytes>>,
+        ptz: PtzAccess,
+        watermark: Option<Watermark>,
     ) -> Self {
         Self {
             camera_id,
             client_id,
             recording_manager,
             frame_sender,
+            ptz,
+            watermark,
             replay_state: ReplayState::default(),
             live_stream_state: LiveStreamState::default(),
+            audio_level_state: AudioLevelState::default(),
+            stream_stats_state: StreamStatsState::default(),
+            ptz_control_state: PtzControlState::default(),
         }
     }
 
@@ -285,13 +468,21 @@ impl ControlHandler {
         let (cleanup_tx, _cleanup_rx) = broadcast::channel::<()>(1);
 
         // Handle incoming commands
-        let recording_manager = self.recording_manager.clone();
-        let camera_id = self.camera_id.clone();
-        let client_id = self.client_id.clone();
-        let frame_sender = self.frame_sender.clone();
         let sender_clone = sender.clone();
-        let mut replay_state = self.replay_state.clone();
-        let mut live_stream_state = self.live_stream_state.clone();
+        let mut session = ControlSession {
+            camera_id: self.camera_id.clone(),
+            recording_manager: self.recording_manager.clone(),
+            frame_sender: self.frame_sender.clone(),
+            replay_state: self.replay_state.clone(),
+            live_stream_state: self.live_stream_state.clone(),
+            audio_level_state: self.audio_level_state.clone(),
+            stream_stats_state: self.stream_stats_state.clone(),
+            ptz_control_state: self.ptz_control_state.clone(),
+            ptz: self.ptz.clone(),
+            ptz_joystick: None,
+            watermark: self.watermark.clone(),
+            sender: sender.clone(),
+        };
 
         let recv_task = tokio::spawn(async move {
             while let Some(msg) = receiver.next().await {
@@ -301,16 +492,12 @@ impl ControlHandler {
                         
                         match serde_json::from_str::<ControlCommand>(&text) {
                             Ok(command) => {
-                                let response = Self::process_command(
-                                    command,
-                                    &camera_id,
-                                    &client_id,
-                                    &recording_manager,
-                                    frame_sender.clone(),
-                                    &mut replay_state,
-                                    &mut live_stream_state,
-                                    sender_clone.clone(),
-                                ).await;
+                                // Joystick vectors arrive 10-20 times per second and are only answered on errors
+                                let quiet = matches!(command, ControlCommand::PtzJoystick { .. });
+                                let response = Self::process_command(command, &mut session).await;
+                                if quiet && response.code == 200 {
+                                    continue;
+                                }
                                 
                                 if let Ok(response_json) = serde_json::to_string(&response) {
                                     let mut response_bytes = vec![0x01]; // Command response type
@@ -351,7 +538,18 @@ impl ControlHandler {
             info!("Control WebSocket receive task ended");
             
             // Stop any active streams when disconnecting
-            Self::handle_stop(&mut replay_state, &mut live_stream_state).await;
+            Self::handle_stop(&mut session.replay_state, &mut session.live_stream_state).await;
+            if let Some(stop_sender) = session.audio_level_state.stop_sender.take() {
+                let _ = stop_sender.send(());
+            }
+            if let Some(stop_sender) = session.stream_stats_state.stop_sender.take() {
+                let _ = stop_sender.send(());
+            }
+            if let Some(stop_sender) = session.ptz_control_state.stop_sender.take() {
+                let _ = stop_sender.send(());
+            }
+            // Stops a camera still moving
+            drop(session.ptz_joystick.take());
         });
 
         // Wait for tasks to complete with timeout to prevent hanging
@@ -374,48 +572,46 @@ impl ControlHandler {
         info!("Control WebSocket handler ended for camera '{}'", self.camera_id);
     }
 
-    async fn process_command(
-        command: ControlCommand,
-        camera_id: &str,
-        _client_id: &str,
-        recording_manager: &RecordingManager,
-        frame_sender: Arc<broadcast::Sender<Bytes>>,
-        replay_state: &mut ReplayState,
-        live_stream_state: &mut LiveStreamState,
-        sender: Arc<tokio::sync::Mutex<futures_util::stream::SplitSink<WebSocket, Message>>>,
-    ) -> CommandResponse {
+    async fn process_command(command: ControlCommand, session: &mut ControlSession) -> CommandResponse {
+        let camera_id = session.camera_id.as_str();
+        let sender = session.sender.clone();
         match command {
             ControlCommand::StartReplay { from, to } => {
-                Self::handle_start_replay(camera_id, from, to, recording_manager, replay_state, live_stream_state, sender).await
+                Self::handle_start_replay(session, from, to).await
             }
             ControlCommand::Stop => {
-                Self::handle_stop(replay_state, live_stream_state).await
+                Self::handle_stop(&mut session.replay_state, &mut session.live_stream_state).await
             }
             ControlCommand::ReplaySpeed { speed } => {
-                Self::handle_replay_speed(speed, replay_state).await
+                Self::handle_replay_speed(speed, &mut session.replay_state).await
             }
             ControlCommand::StartLiveStream => {
-                Self::handle_start_live_stream(frame_sender, replay_state, live_stream_state, sender).await
+                Self::handle_start_live_stream(session.frame_sender.clone(), &mut session.replay_state, &mut session.live_stream_state, session.watermark.clone(), sender).await
             }
             ControlCommand::GoToTimestamp { timestamp } => {
-                Self::handle_goto_timestamp(camera_id, timestamp, recording_manager, sender).await
+                Self::handle_goto_timestamp(camera_id, timestamp, &session.recording_manager, &session.watermark, sender).await
             }
             ControlCommand::ListSegments { from, to, sort_order } => { // TODO: deprecated
-                Self::handle_list_segments(camera_id, from, to, &sort_order, recording_manager).await
+                Self::handle_list_segments(camera_id, from, to, &sort_order, &session.recording_manager).await
+            }
+            ControlCommand::AudioLevels { enabled } => {
+                Self::handle_audio_levels(camera_id, enabled, &mut session.audio_level_state, sender)
+            }
+            ControlCommand::StreamStats { enabled, interval_secs } => {
+                Self::handle_stream_stats(camera_id, enabled, interval_secs, session.live_stream_state.counters.clone(), &mut session.stream_stats_state, sender)
+            }
+            ControlCommand::PtzJoystick { pan, tilt, zoom } => {
+                Self::handle_ptz_joystick(camera_id, PtzVelocity { pan, tilt, zoom }, &session.recording_manager, &session.ptz, &mut session.ptz_joystick, sender)
+            }
+            ControlCommand::PtzControl { enabled } => {
+                Self::handle_ptz_control(camera_id, enabled, &session.ptz, &mut session.ptz_control_state, sender)
             }
         }
     }
 
 
-    async fn handle_start_replay(
-        camera_id: &str,
-        from: DateTime<Utc>,
-        to: Option<DateTime<Utc>>,
-        recording_manager: &RecordingManager,
-        replay_state: &mut ReplayState,
-        live_stream_state: &mut LiveStreamState,
-        sender: Arc<tokio::sync::Mutex<futures_util::stream::SplitSink<WebSocket, Message>>>,
-    ) -> CommandResponse {
+    async fn handle_start_replay(session: &mut ControlSession, from: DateTime<Utc>, to: Option<DateTime<Utc>>) -> CommandResponse {
+        let ControlSession { camera_id, recording_manager, replay_state, live_stream_state, watermark, sender, .. } = session;
         // Stop any existing replay or live stream
         if replay_state.active || live_stream_state.active {
             Self::handle_stop(replay_state, live_stream_state).await;
@@ -445,6 +641,7 @@ impl ControlHandler {
                 let camera_id_clone = camera_id.to_string();
                 let sender_clone = sender.clone();
                 let recording_manager_clone = recording_manager.clone();
+                let watermark = watermark.clone();
                 
                 tokio::spawn(async move {
                     info!("Starting streaming replay for camera '{}' (estimated {} frames)", camera_id_clone, frame_count);
@@ -489,6 +686,9 @@ impl ControlHandler {
                                     }
                                     
                                     // Send frame with timestamp
+                                    let Some(frame) = watermark_recorded(&watermark, frame).await else {
+                                        continue;
+                                    };
                                     let frame_bytes = Self::encode_frame_with_timestamp(&frame);
                                     
                                     let mut sender_guard = sender_clone.lock().await;
@@ -602,6 +802,7 @@ impl ControlHandler {
         frame_sender: Arc<broadcast::Sender<Bytes>>,
         replay_state: &mut ReplayState,
         live_stream_state: &mut LiveStreamState,
+        watermark: Option<Watermark>,
         sender: Arc<tokio::sync::Mutex<futures_util::stream::SplitSink<WebSocket, Message>>>,
     ) -> CommandResponse {
         // Stop any active replay first
@@ -630,6 +831,8 @@ impl ControlHandler {
 
         // Start the live streaming task
         let sender_clone = sender.clone();
+        let counters = live_stream_state.counters.clone();
+        counters.streams.fetch_add(1, Ordering::Relaxed);
         let _stream_task = tokio::spawn(async move {
             info!("Starting live stream forwarding");
             
@@ -644,6 +847,12 @@ impl ControlHandler {
                     frame_result = frame_receiver.recv() => {
                         match frame_result {
                             Ok(frame_data) => {
+                                counters.captured.fetch_add(1, Ordering::Relaxed);
+                                let Some(frame_data) = watermark_frame(&watermark, frame_data).await else {
+                                    counters.dropped.fetch_add(1, Ordering::Relaxed);
+                                    continue;
+                                };
+
                                 // Create frame with timestamp for live stream
                                 let mut message_data = Vec::new();
                                 
@@ -667,7 +876,8 @@ impl ControlHandler {
                                     }
                                 ).await {
                                     Ok(Ok(())) => {
-                                        // Frame sent successfully
+                                        counters.delivered.fetch_add(1, Ordering::Relaxed);
+                                        counters.delivered_bytes.fetch_add(frame_data.len() as u64, Ordering::Relaxed);
                                     }
                                     Ok(Err(e)) => {
                                         error!("Failed to send live frame: {}, stopping stream", e);
@@ -676,12 +886,15 @@ impl ControlHandler {
                                     Err(_) => {
                                         // Timeout - client is too slow, skip this frame
                                         trace!("Skipped frame due to slow client");
+                                        counters.dropped.fetch_add(1, Ordering::Relaxed);
                                         continue;
                                     }
                                 }
                             }
-                            Err(broadcast::error::RecvError::Lagged(_)) => {
+                            Err(broadcast::error::RecvError::Lagged(skipped)) => {
                                 // Skip lagged frames
+                                counters.captured.fetch_add(skipped, Ordering::Relaxed);
+                                counters.dropped.fetch_add(skipped, Ordering::Relaxed);
                                 continue;
                             }
                             Err(_) => {
@@ -695,6 +908,7 @@ impl ControlHandler {
             
             // Explicitly drop the frame receiver to ensure cleanup
             drop(frame_receiver);
+            counters.streams.fetch_sub(1, Ordering::Relaxed);
             info!("Live stream task ended");
         });
 
@@ -707,6 +921,203 @@ impl ControlHandler {
         CommandResponse::success("Live stream started")
     }
     
+    fn handle_audio_levels(
+        camera_id: &str,
+        enabled: bool,
+        audio_level_state: &mut AudioLevelState,
+        sender: Arc<tokio::sync::Mutex<futures_util::stream::SplitSink<WebSocket, Message>>>,
+    ) -> CommandResponse {
+        if let Some(stop_sender) = audio_level_state.stop_sender.take() {
+            let _ = stop_sender.send(());
+        }
+        if !enabled {
+            return CommandResponse::success("Audio levels stopped");
+        }
+
+        let (stop_sender, mut stop_receiver) = broadcast::channel::<()>(1);
+        let mut levels = crate::audio_level::subscribe(camera_id);
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = stop_receiver.recv() => break,
+                    level = levels.recv() => {
+                        let level = match level {
+                            Ok(level) => level,
+                            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                            Err(broadcast::error::RecvError::Closed) => break,
+                        };
+                        // Protocol byte (0x02 for audio level) followed by JSON
+                        let mut message_data = vec![0x02];
+                        message_data.extend_from_slice(serde_json::to_string(&level).unwrap_or_default().as_bytes());
+                        if sender.lock().await.send(Message::Binary(message_data)).await.is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+            trace!("Audio level forwarding ended");
+        });
+
+        audio_level_state.stop_sender = Some(stop_sender);
+        CommandResponse::success("Audio levels started")
+    }
+
+    fn handle_stream_stats(
+        camera_id: &str,
+        enabled: bool,
+        interval_secs: u64,
+        counters: Arc<LiveStreamCounters>,
+        stream_stats_state: &mut StreamStatsState,
+        sender: Arc<tokio::sync::Mutex<futures_util::stream::SplitSink<WebSocket, Message>>>,
+    ) -> CommandResponse {
+        if let Some(stop_sender) = stream_stats_state.stop_sender.take() {
+            let _ = stop_sender.send(());
+        }
+        if !enabled {
+            return CommandResponse::success("Stream statistics stopped");
+        }
+        if !(1..=60).contains(&interval_secs) {
+            return CommandResponse::error(400, "interval_secs must be between 1 and 60");
+        }
+
+        let (stop_sender, mut stop_receiver) = broadcast::channel::<()>(1);
+        let camera_id = camera_id.to_string();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(interval_secs));
+            interval.tick().await;
+            let mut previous = counters.snapshot();
+            let mut previous_at = tokio::time::Instant::now();
+            loop {
+                tokio::select! {
+                    _ = stop_receiver.recv() => break,
+                    _ = interval.tick() => {
+                        let current = counters.snapshot();
+                        let now = tokio::time::Instant::now();
+                        let live = counters.streams.load(Ordering::Relaxed) > 0;
+                        let stats = StreamStatsMessage::new(&camera_id, live, previous, current, (now - previous_at).as_secs_f64());
+                        previous = current;
+                        previous_at = now;
+
+                        // Protocol byte (0x03 for stream statistics) followed by JSON
+                        let mut message_data = vec![0x03];
+                        message_data.extend_from_slice(serde_json::to_string(&stats).unwrap_or_default().as_bytes());
+                        if sender.lock().await.send(Message::Binary(message_data)).await.is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+            trace!("Stream statistics ended");
+        });
+
+        stream_stats_state.stop_sender = Some(stop_sender);
+        CommandResponse::success_with_data("Stream statistics started", serde_json::json!({ "interval_secs": interval_secs }))
+    }
+
+    fn handle_ptz_joystick(
+        camera_id: &str,
+        velocity: PtzVelocity,
+        recording_manager: &RecordingManager,
+        ptz: &PtzAccess,
+        ptz_joystick: &mut Option<PtzJoystick>,
+        sender: Arc<tokio::sync::Mutex<futures_util::stream::SplitSink<WebSocket, Message>>>,
+    ) -> CommandResponse {
+        let session = match ptz {
+            Ok(session) => session,
+            Err((code, reason)) => return CommandResponse::error(*code, reason),
+        };
+        if ![velocity.pan, velocity.tilt, velocity.zoom].iter().all(|v| (-1.0..=1.0).contains(v)) {
+            return CommandResponse::error(400, "pan, tilt and zoom must be between -1.0 and 1.0");
+        }
+
+        ptz_joystick
+            .get_or_insert_with(|| PtzJoystick::start(
+                camera_id.to_string(),
+                session.controller.clone(),
+                session.client.clone(),
+                session.config.clone(),
+                recording_manager.clone(),
+                sender,
+            ))
+            .update(velocity);
+        CommandResponse::success("PTZ vector accepted")
+    }
+
+    fn handle_ptz_control(
+        camera_id: &str,
+        enabled: bool,
+        ptz: &PtzAccess,
+        ptz_control_state: &mut PtzControlState,
+        sender: Arc<tokio::sync::Mutex<futures_util::stream::SplitSink<WebSocket, Message>>>,
+    ) -> CommandResponse {
+        if let Some(stop_sender) = ptz_control_state.stop_sender.take() {
+            let _ = stop_sender.send(());
+        }
+        // Connections without the 'ptz' scope may still watch who moves the camera
+        if let Err((503, reason)) = ptz {
+            return CommandResponse::error(503, reason);
+        }
+        let own_id = ptz.as_ref().ok().map(|session| session.client.id.clone());
+        let holder = ptz_arbiter::current(camera_id);
+        let data = serde_json::json!({
+            "holder": holder,
+            "own": holder.as_ref().is_some_and(|h| Some(&h.client_id) == own_id.as_ref()),
+            "client_id": own_id,
+            "priority": ptz.as_ref().ok().map(|session| session.client.priority),
+        });
+        if !enabled {
+            return CommandResponse::success_with_data("PTZ control updates stopped", data);
+        }
+
+        let (stop_sender, mut stop_receiver) = broadcast::channel::<()>(1);
+        let mut changes = ptz_arbiter::subscribe(camera_id);
+        let camera_id = camera_id.to_string();
+        tokio::spawn(async move {
+            // Holder last sent, the initial state is part of the command response
+            let mut sent = holder;
+            loop {
+                let expires_in = sent.as_ref().map(|h| (h.expires_at - Utc::now()).to_std().unwrap_or_default());
+                tokio::select! {
+                    _ = stop_receiver.recv() => break,
+                    changed = changes.changed() => {
+                        if changed.is_err() {
+                            break;
+                        }
+                    }
+                    // Leases running out are not announced by the arbiter
+                    _ = tokio::time::sleep(expires_in.unwrap_or_default()), if expires_in.is_some() => {}
+                }
+                let holder = ptz_arbiter::current(&camera_id);
+                let same_lease = match (&sent, &holder) {
+                    (Some(a), Some(b)) => a.client_id == b.client_id && a.since == b.since,
+                    (None, None) => true,
+                    _ => false,
+                };
+                if same_lease {
+                    // A renewed lease only moves the expiry
+                    sent = holder;
+                    continue;
+                }
+                let message = PtzControlMessage {
+                    camera_id: camera_id.clone(),
+                    own: holder.as_ref().is_some_and(|h| Some(&h.client_id) == own_id.as_ref()),
+                    holder: holder.clone(),
+                };
+                // Protocol byte (0x04 for PTZ control) followed by JSON
+                let mut message_data = vec![0x04];
+                message_data.extend_from_slice(serde_json::to_string(&message).unwrap_or_default().as_bytes());
+                if sender.lock().await.send(Message::Binary(message_data)).await.is_err() {
+                    break;
+                }
+                sent = holder;
+            }
+            trace!("PTZ control updates ended");
+        });
+
+        ptz_control_state.stop_sender = Some(stop_sender);
+        CommandResponse::success_with_data("PTZ control updates started", data)
+    }
+
     // Helper function to encode frame with timestamp
     fn encode_frame_with_timestamp(frame: &RecordedFrame) -> Vec<u8> {
         let mut frame_bytes = Vec::new();
@@ -729,10 +1140,14 @@ impl ControlHandler {
         camera_id: &str,
         timestamp: DateTime<Utc>,
         recording_manager: &RecordingManager,
+        watermark: &Option<Watermark>,
         sender: Arc<tokio::sync::Mutex<futures_util::stream::SplitSink<WebSocket, Message>>>,
     ) -> CommandResponse {
         match recording_manager.get_frame_at_timestamp(camera_id, timestamp, Some(1)).await {
             Ok(Some(frame)) => {
+                let Some(frame) = watermark_recorded(watermark, frame).await else {
+                    return CommandResponse::error(500, "Failed to watermark frame");
+                };
                 // Send the frame with timestamp
                 let frame_bytes = Self::encode_frame_with_timestamp(&frame);
                 
@@ -848,10 +1263,36 @@ pub async fn handle_control_websocket(
     client_id: String,
     recording_manager: Arc<RecordingManager>,
     frame_sender: Arc<broadcast::Sender<Bytes>>,
+    ptz: PtzAccess,
+    watermark: Option<Watermark>,
 ) {
     trace!("[CONTROL] handle_control_websocket started for camera {} client {}", camera_id, client_id);
-    let mut handler = ControlHandler::new(camera_id.clone(), client_id.clone(), recording_manager, frame_sender);
+    let mut handler = ControlHandler::new(camera_id.clone(), client_id.clone(), recording_manager, frame_sender, ptz, watermark);
     trace!("[CONTROL] ControlHandler created for camera {} client {}", camera_id, client_id);
     handler.handle_websocket(socket).await;
     trace!("[CONTROL] handle_control_websocket completed for camera {} client {}", camera_id, client_id);
-}
\ No newline at end of file
+}
+
+/// Frame with the connection's watermark drawn into it; frames that fail are not sent unmarked
+async fn watermark_frame(watermark: &Option<Watermark>, frame: Bytes) -> Option<Bytes> {
+    let Some(watermark) = watermark.clone() else {
+        return Some(frame);
+    };
+    match tokio::task::spawn_blocking(move || watermark.apply(&frame)).await {
+        Ok(Ok(data)) => Some(Bytes::from(data)),
+        Ok(Err(e)) => {
+            trace!("Failed to watermark frame: {}", e);
+            None
+        }
+        Err(_) => None,
+    }
+}
+
+/// Recorded frame with the connection's watermark drawn into it
+async fn watermark_recorded(watermark: &Option<Watermark>, mut frame: RecordedFrame) -> Option<RecordedFrame> {
+    if watermark.is_some() {
+        let data = watermark_frame(watermark, Bytes::from(std::mem::take(&mut frame.frame_data))).await?;
+        frame.frame_data = data.into();
+    }
+    Some(frame)
+}
@@ -1,15 +1,21 @@
 use std::sync::Arc;
+use std::collections::HashMap;
 use axum::{
-    extract::{State, WebSocketUpgrade, ConnectInfo},
+    extract::ConnectInfo,
     response::Response,
 };
-use axum::extract::ws::{WebSocket, Message};
-use tokio::sync::broadcast;
-use futures_util::{stream::StreamExt, SinkExt};
+use axum::extract::ws::Message;
+use serde::Deserialize;
+use tokio::sync::{broadcast, watch};
 use tracing::{info, error, warn, trace};
-use bytes::Bytes;
+use bytes::{BufMut, Bytes, BytesMut};
 use crate::mqtt::{MqttHandle, ClientStatus};
-use crate::config::CameraConfig;
+use crate::config::{CameraConfig, OfflineCardConfig};
+use crate::live_socket::{LiveSender, LiveSocket, LiveUpgrade};
+use crate::pre_recording_buffer::PreRecordingBuffer;
+use crate::resume_tokens::{self, ResumeSubscription};
+use crate::viewers::{self, ViewerGuard, ViewerKind, ViewerStats};
+use crate::watermark::Watermark;
 use chrono::Utc;
 use uuid::Uuid;
 use std::net::SocketAddr;
@@ -17,47 +23,136 @@ use std::net::SocketAddr;
 // Rate limiting has been disabled to prevent blocking issues
 // The code has been removed as it was causing dashboard access problems
 
+// Longest wait between two replayed frames, so gaps in the buffer are skipped quickly
+const MAX_REPLAY_FRAME_DELAY_MS: i64 = 1000;
+
+/// Camera stream a live WebSocket client connects to
+pub struct LiveStreamContext {
+    pub frame_sender: Arc<broadcast::Sender<Bytes>>,
+    pub camera_id: String,
+    pub mqtt_handle: Option<MqttHandle>,
+    pub camera_config: CameraConfig,
+    pub pre_recording_buffer: Option<PreRecordingBuffer>,
+}
+
+impl From<crate::CameraStreamInfo> for LiveStreamContext {
+    fn from(stream_info: crate::CameraStreamInfo) -> Self {
+        Self {
+            frame_sender: stream_info.frame_sender,
+            camera_id: stream_info.camera_id,
+            mqtt_handle: stream_info.mqtt_handle,
+            camera_config: stream_info.camera_config,
+            pre_recording_buffer: stream_info.pre_recording_buffer,
+        }
+    }
+}
+
+// Per-client processing of the frames sent to a live WebSocket client
+struct FrameDelivery {
+    watermark: Option<Watermark>,
+    batching: Option<FrameBatching>,
+    offline_card: Option<OfflineCardConfig>,
+    viewer: ViewerGuard,
+    resume: Option<LiveResume>,
+}
+
+// Resumable subscription of a client that connected with `?resume=true` or `?resume_token=...`
+struct LiveResume {
+    subscription: ResumeSubscription,
+    replay_speed: f64,
+    buffer: Option<PreRecordingBuffer>,
+}
+
+/// Text message of a live client, `{"command":"pause"}` or `{"command":"resume"}`
+#[derive(Debug, Deserialize)]
+#[serde(tag = "command", rename_all = "lowercase")]
+enum LiveCommand {
+    Pause,
+    Resume,
+}
+
+/// Frames coalesced into one binary message, each prefixed with its length as 32-bit big-endian integer
+#[derive(Debug, Clone, Copy)]
+struct FrameBatching {
+    max_frames: usize,
+    interval: std::time::Duration,
+}
+
 pub async fn websocket_handler(
-    ws: WebSocketUpgrade,
-    State(frame_sender): State<Arc<broadcast::Sender<Bytes>>>,
+    ws: LiveUpgrade,
     ConnectInfo(addr): ConnectInfo<SocketAddr>,
-    camera_id: String,
-    mqtt_handle: Option<MqttHandle>,
-    _camera_config: CameraConfig,
+    stream: LiveStreamContext,
+    query: HashMap<String, String>,
 ) -> Response {
     // Authentication is handled in camera_handler before this function is called
-    let current_connections = frame_sender.receiver_count();
-    info!("WebSocket upgrade for client {} on camera {} (current connections: {})", addr, camera_id, current_connections);
-       
-    ws.on_upgrade(move |socket| handle_socket(socket, frame_sender, camera_id, mqtt_handle, addr))
+    let current_connections = stream.frame_sender.receiver_count();
+    info!("WebSocket upgrade for client {} on camera {} (current connections: {})", addr, stream.camera_id, current_connections);
+
+    let compression = stream.camera_config.live_websocket.as_ref().is_some_and(|c| c.compression);
+    ws.on_upgrade(compression, move |socket| handle_socket(socket, stream, addr, query))
 }
 
 async fn handle_socket(
-    socket: WebSocket,
-    frame_sender: Arc<broadcast::Sender<Bytes>>,
-    camera_id: String,
-    mqtt_handle: Option<MqttHandle>,
+    socket: LiveSocket,
+    stream: LiveStreamContext,
     client_addr: SocketAddr,
+    query: HashMap<String, String>,
 ) {
+    let LiveStreamContext { frame_sender, camera_id, mqtt_handle, camera_config, pre_recording_buffer } = stream;
     let client_id = Uuid::new_v4().to_string();
     let client_ip = client_addr.ip().to_string();
+    let watermark = Watermark::for_client(&camera_id, &camera_config, query.get("token").map(String::as_str), &client_id);
+    if let Some(ref watermark) = watermark {
+        info!("WebSocket client {} ({}) of camera {} is watermarked as '{}'", client_id, client_ip, camera_id, watermark.text);
+    }
+    // Batches change the message format, so only clients asking for them get them
+    let batch_requested = query.get("batch").is_some_and(|v| v == "true" || v == "1");
+    let batching = camera_config.live_websocket.as_ref()
+        .filter(|c| batch_requested && c.batch_max_frames > 1)
+        .map(|c| FrameBatching {
+            max_frames: c.batch_max_frames,
+            interval: std::time::Duration::from_millis(c.batch_interval_ms),
+        });
+    let offline_card = camera_config.live_websocket.as_ref()
+        .and_then(|c| c.offline_card.clone())
+        .filter(|c| c.enabled);
+    if socket.is_compressed() || batching.is_some() {
+        info!("WebSocket client {} ({}) of camera {} uses permessage-deflate: {}, batching: {:?}",
+              client_id, client_ip, camera_id, socket.is_compressed(), batching);
+    }
     
     trace!("[{}] Starting WebSocket connection setup for camera {}", client_id, camera_id);
     
+    let identity = viewers::identify(&camera_config, query.get("token").map(String::as_str));
+    let viewer = viewers::register(&client_id, &camera_id, ViewerKind::Live, client_addr, identity, camera_config.frame_lag_policy());
+
+    // The resume message would confuse clients that did not ask for it
+    let resume_token = query.get("resume_token").map(String::as_str).filter(|t| !t.is_empty());
+    let resume_requested = resume_token.is_some() || query.get("resume").is_some_and(|v| v == "true" || v == "1");
+    let resume = resume_requested.then(|| {
+        let (replay_speed, ttl) = camera_config.live_resume_settings();
+        LiveResume {
+            subscription: resume_tokens::subscribe(&camera_id, resume_token, ttl),
+            replay_speed,
+            buffer: pre_recording_buffer,
+        }
+    });
+
     // Wrap the entire socket handling in error handling
-    if let Err(e) = handle_socket_inner(socket, frame_sender, camera_id, mqtt_handle, client_addr, client_id, client_ip).await {
+    let delivery = FrameDelivery { watermark, batching, offline_card, viewer, resume };
+    if let Err(e) = handle_socket_inner(socket, frame_sender, camera_id, mqtt_handle, client_id, client_ip, delivery).await {
         error!("WebSocket handling error: {}", e);
     }
 }
 
 async fn handle_socket_inner(
-    socket: WebSocket,
+    socket: LiveSocket,
     frame_sender: Arc<broadcast::Sender<Bytes>>,
     camera_id: String,
     mqtt_handle: Option<MqttHandle>,
-    _client_addr: SocketAddr,
     client_id: String,
     client_ip: String,
+    delivery: FrameDelivery,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     
     // Rate limiting has been disabled to prevent blocking issues
@@ -106,6 +201,13 @@ async fn handle_socket_inner(
 
     let mqtt_handle_clone = mqtt_handle.clone();
     let client_id_clone = client_id.clone();
+    let FrameDelivery { watermark, batching, offline_card, viewer, resume } = delivery;
+    let camera_id_clone = camera_id.clone();
+    let viewer_stats = viewer.stats();
+    let recv_viewer_stats = viewer_stats.clone();
+    let lag_reporter = viewer.lag_reporter();
+    // Paused clients keep their subscription but get no frames until they resume
+    let (pause_sender, mut paused) = watch::channel(false);
     
     trace!("[{}] About to spawn send_task", client_id);
     let task_spawn_start = std::time::Instant::now();
@@ -121,10 +223,84 @@ async fn handle_socket_inner(
         let mut frame_receiver = frame_receiver; // Move the frame_receiver into the task
         let mut last_ping_time = tokio::time::Instant::now();
         
+        if let Some(ref resume) = resume {
+            match start_resumed(&mut sender, resume, &watermark, batching.is_some(), &client_id_clone, &viewer_stats).await {
+                Some(0) => {}
+                Some(replayed) => {
+                    info!("[{}] Replayed {} missed frames of camera {}", client_id_clone, replayed, camera_id_clone);
+                    // Frames queued during the replay were replayed from the buffer already
+                    frame_receiver = frame_receiver.resubscribe();
+                }
+                None => return,
+            }
+        }
+
         trace!("[{}] Starting frame receive loop", client_id_clone);
         
         loop {
-            match frame_receiver.recv().await {
+            if *paused.borrow_and_update() {
+                trace!("[{}] Frame delivery paused", client_id_clone);
+                let resumed = loop {
+                    // Keep pinging so proxies don't close the idle connection
+                    let next_ping = last_ping_time + std::time::Duration::from_secs(8);
+                    tokio::select! {
+                        changed = paused.changed() => {
+                            if changed.is_err() {
+                                break false;
+                            }
+                            if !*paused.borrow_and_update() {
+                                break true;
+                            }
+                        }
+                        _ = tokio::time::sleep_until(next_ping) => {
+                            if sender.send(Message::Ping(vec![])).await.is_err() {
+                                break false;
+                            }
+                            last_ping_time = tokio::time::Instant::now();
+                        }
+                    }
+                };
+                if !resumed {
+                    break;
+                }
+                // Skip the frames queued while paused and continue with the next live one
+                frame_receiver = frame_receiver.resubscribe();
+                trace!("[{}] Frame delivery resumed", client_id_clone);
+            }
+            let received = match offline_card {
+                // Wake up regularly to send the offline card while the camera delivers no frames
+                Some(ref offline_card) => match tokio::time::timeout(
+                    std::time::Duration::from_secs(offline_card.interval_secs.max(1)),
+                    frame_receiver.recv()
+                ).await {
+                    Ok(received) => received,
+                    Err(_) if *paused.borrow() => continue,
+                    Err(_) => {
+                        let Some(card) = prepare_offline_card(offline_card, &camera_id_clone, &watermark, batching.is_some(), &client_id_clone).await else {
+                            continue;
+                        };
+                        let message_len = card.len();
+                        match tokio::time::timeout(
+                            std::time::Duration::from_millis(100),
+                            sender.send(Message::Binary(card.to_vec()))
+                        ).await {
+                            Ok(Ok(())) => viewer_stats.record_sent(message_len, 1),
+                            Ok(Err(_)) => {
+                                error!("WebSocket connection error");
+                                break;
+                            }
+                            Err(_) => {
+                                let _ = sender.flush().await;
+                            }
+                        }
+                        continue;
+                    }
+                },
+                None => frame_receiver.recv().await,
+            };
+            match received {
+                // Pause arrived while waiting for this frame
+                Ok(_) if *paused.borrow() => continue,
                 Ok(frame_data) => {
                     frame_count += 1;
                     
@@ -133,15 +309,58 @@ async fn handle_socket_inner(
                         trace!("[{}] First frame received at {:?}", client_id_clone, task_start_time.elapsed());
                     }
                     fps_frame_count += 1;
+
+                    let Some(mut frame_data) = prepare_frame(&watermark, frame_data, &client_id_clone).await else {
+                        continue;
+                    };
+                    let mut frames_in_message = 1u64;
+                    if let Some(batching) = batching {
+                        let mut batch = BytesMut::with_capacity(frame_data.len() * batching.max_frames);
+                        batch.put_u32(frame_data.len() as u32);
+                        batch.put_slice(&frame_data);
+                        let deadline = tokio::time::Instant::now() + batching.interval;
+                        let mut disconnect = false;
+                        while (frames_in_message as usize) < batching.max_frames {
+                            let frame = match tokio::time::timeout_at(deadline, frame_receiver.recv()).await {
+                                Ok(Ok(frame)) => frame,
+                                Ok(Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped))) => {
+                                    dropped_frames += skipped;
+                                    if lag_reporter.lagged(skipped) {
+                                        disconnect = true;
+                                        break;
+                                    }
+                                    continue;
+                                }
+                                // Closed channel or interval over, send what was collected
+                                Ok(Err(tokio::sync::broadcast::error::RecvError::Closed)) | Err(_) => break,
+                            };
+                            frame_count += 1;
+                            fps_frame_count += 1;
+                            if let Some(frame) = prepare_frame(&watermark, frame, &client_id_clone).await {
+                                batch.put_u32(frame.len() as u32);
+                                batch.put_slice(&frame);
+                                frames_in_message += 1;
+                            }
+                        }
+                        if disconnect {
+                            break;
+                        }
+                        frame_data = batch.freeze();
+                    }
                     
                     // Use timeout for non-blocking send - drop frame if it takes too long
+                    let message_len = frame_data.len();
                     match tokio::time::timeout(
                         std::time::Duration::from_millis(100), // Reasonable timeout for network communication
                         sender.send(Message::Binary(frame_data.to_vec()))
                     ).await {
                         Ok(Ok(())) => {
                             // Frame sent successfully
-                            total_frames_sent += 1;
+                            total_frames_sent += frames_in_message;
+                            viewer_stats.record_sent(message_len, frames_in_message);
+                            if let Some(ref resume) = resume {
+                                resume.subscription.frame_sent(Utc::now());
+                            }
                         }
                         Ok(Err(_)) => {
                             // Connection error
@@ -150,7 +369,7 @@ async fn handle_socket_inner(
                         }
                         Err(_) => {
                             // Timeout - client is too slow, drop this frame
-                            dropped_frames += 1;
+                            dropped_frames += frames_in_message;
                             if dropped_frames % 10 == 0 {
                                 trace!("Dropped {} frames due to slow client", dropped_frames);
                             }
@@ -164,6 +383,9 @@ async fn handle_socket_inner(
                     // This is expected behavior with channel_buffer_size=1
                     dropped_frames += skipped as u64;
                     trace!("WebSocket lagged, dropped {} old frames", skipped);
+                    if lag_reporter.lagged(skipped) {
+                        break;
+                    }
                 }
                 Err(tokio::sync::broadcast::error::RecvError::Closed) => {
                     // Channel closed, exit
@@ -201,7 +423,14 @@ async fn handle_socket_inner(
         while let Some(msg) = receiver.next().await {
             match msg {
                 Ok(Message::Text(text)) => {
-                    trace!("Received text message: {}", text);
+                    match serde_json::from_str::<LiveCommand>(&text) {
+                        Ok(command) => {
+                            let pause = matches!(command, LiveCommand::Pause);
+                            recv_viewer_stats.set_paused(pause);
+                            pause_sender.send_replace(pause);
+                        }
+                        Err(e) => trace!("Ignoring text message {}: {}", text, e),
+                    }
                 }
                 Ok(Message::Binary(_)) => {
                     trace!("Received binary message");
@@ -251,6 +480,11 @@ async fn handle_socket_inner(
                 Err(_) => warn!("[{}] Timeout waiting for send task abort", client_id),
             }
         },
+        _ = viewer.kicked() => {
+            info!("WebSocket client {} of camera {} kicked via API", client_id, camera_id);
+            send_task.abort();
+            recv_task.abort();
+        },
     }
 
     info!("WebSocket client {} disconnected", client_id);
@@ -267,4 +501,95 @@ async fn handle_socket_inner(
     }
     
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Tell the client its resume token, then replay the buffered frames it missed since its previous
+/// connection at `replay_speed` until the replay caught up with the live frames. Returns the number of
+/// replayed frames, `None` if the connection failed.
+async fn start_resumed(
+    sender: &mut LiveSender,
+    resume: &LiveResume,
+    watermark: &Option<Watermark>,
+    batched: bool,
+    client_id: &str,
+    viewer_stats: &ViewerStats,
+) -> Option<u64> {
+    // Only gaps the buffer still covers completely are replayed
+    let mut replay_from = None;
+    if let (Some(from), Some(buffer)) = (resume.subscription.resume_from, resume.buffer.as_ref()) {
+        if buffer.get_first_frame_timestamp().await.is_some_and(|oldest| oldest <= from) {
+            replay_from = Some((from, buffer));
+        }
+    }
+    let message = serde_json::json!({
+        "type": "resume",
+        "token": resume.subscription.token,
+        "resumed": replay_from.is_some(),
+        "resume_from": replay_from.map(|(from, _)| from),
+    });
+    sender.send(Message::Text(message.to_string())).await.ok()?;
+
+    let Some((mut position, buffer)) = replay_from else {
+        return Some(0);
+    };
+    let mut replayed = 0u64;
+    while let Some(frame) = buffer.frame_at_or_after(position, true).await {
+        let delay_ms = (frame.timestamp - position).num_milliseconds().clamp(0, MAX_REPLAY_FRAME_DELAY_MS);
+        if replayed > 0 && delay_ms > 0 {
+            tokio::time::sleep(std::time::Duration::from_secs_f64(delay_ms as f64 / 1000.0 / resume.replay_speed)).await;
+        }
+        position = frame.timestamp;
+        let Some(data) = prepare_frame(watermark, frame.data, client_id).await else {
+            continue;
+        };
+        let data = if batched { length_prefixed(&data) } else { data };
+        let message_len = data.len();
+        sender.send(Message::Binary(data.to_vec())).await.ok()?;
+        viewer_stats.record_sent(message_len, 1);
+        resume.subscription.frame_sent(frame.timestamp);
+        replayed += 1;
+    }
+    Some(replayed)
+}
+
+// A single frame in the batch format
+fn length_prefixed(frame: &[u8]) -> Bytes {
+    let mut batch = BytesMut::with_capacity(frame.len() + 4);
+    batch.put_u32(frame.len() as u32);
+    batch.put_slice(frame);
+    batch.freeze()
+}
+
+// Offline card of the camera if it is offline, watermarked and length-prefixed like the frames of the client
+async fn prepare_offline_card(config: &OfflineCardConfig, camera_id: &str, watermark: &Option<Watermark>, batched: bool, client_id: &str) -> Option<Bytes> {
+    let config = config.clone();
+    let camera_id = camera_id.to_string();
+    let card = match tokio::task::spawn_blocking(move || crate::offline_card::card(&camera_id, &config)).await {
+        Ok(Some(Ok(card))) => card,
+        Ok(Some(Err(e))) => {
+            trace!("[{}] Failed to render offline card: {}", client_id, e);
+            return None;
+        }
+        Ok(None) | Err(_) => return None,
+    };
+    let card = prepare_frame(watermark, card, client_id).await?;
+    if !batched {
+        return Some(card);
+    }
+    Some(length_prefixed(&card))
+}
+
+// Watermarked clients get their own re-encoded copy; frames that fail are not sent unmarked
+async fn prepare_frame(watermark: &Option<Watermark>, frame: Bytes, client_id: &str) -> Option<Bytes> {
+    let Some(watermark) = watermark.clone() else {
+        return Some(frame);
+    };
+    match tokio::task::spawn_blocking(move || watermark.apply(&frame)).await {
+        Ok(Ok(data)) => Some(Bytes::from(data)),
+        Ok(Err(e)) => {
+            trace!("[{}] Failed to watermark frame: {}", client_id, e);
+            None
+        }
+        Err(_) => None,
+    }
+}
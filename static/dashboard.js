@@ -238,6 +238,11 @@ function populateForm(camera) {
     document.getElementById('transport').value = config.transport || 'tcp';
     document.getElementById('reconnect_interval').value = config.reconnect_interval || 5;
     document.getElementById('token').value = config.token || '';
+    document.getElementById('tenant').value = config.tenant || '';
+    document.getElementById('standby_after_minutes').value = (config.standby_after_minutes !== undefined && config.standby_after_minutes !== null) ? config.standby_after_minutes : '';
+    document.getElementById('tokens').value = (config.tokens && config.tokens.length > 0) ? JSON.stringify(config.tokens, null, 2) : '';
+    document.getElementById('triggers').value = (config.triggers && config.triggers.length > 0) ? JSON.stringify(config.triggers, null, 2) : '';
+    document.getElementById('plugins').value = (config.plugins && config.plugins.length > 0) ? JSON.stringify(config.plugins, null, 2) : '';
     
     // Per-camera recording settings
     if (config.recording) {
@@ -251,6 +256,7 @@ function populateForm(camera) {
         document.getElementById('hls_storage_enabled').value = (config.recording.hls_storage_enabled !== undefined && config.recording.hls_storage_enabled !== null) ? config.recording.hls_storage_enabled.toString() : '';
         document.getElementById('hls_storage_retention').value = config.recording.hls_storage_retention || '';
         document.getElementById('hls_segment_seconds').value = config.recording.hls_segment_seconds || '';
+        document.getElementById('hls_encryption_enabled').value = (config.recording.hls_encryption_enabled !== undefined && config.recording.hls_encryption_enabled !== null) ? config.recording.hls_encryption_enabled.toString() : '';
         // Pre-recording buffer settings (memory-only, using new IDs)
         document.getElementById('pre_recording_enabled_camera').value = (config.recording.pre_recording_enabled !== undefined && config.recording.pre_recording_enabled !== null) ? config.recording.pre_recording_enabled.toString() : '';
         document.getElementById('pre_recording_buffer_minutes_camera').value = config.recording.pre_recording_buffer_minutes || '';
@@ -265,11 +271,20 @@ function populateForm(camera) {
         document.getElementById('hls_storage_enabled').value = '';
         document.getElementById('hls_storage_retention').value = '';
         document.getElementById('hls_segment_seconds').value = '';
+        document.getElementById('hls_encryption_enabled').value = '';
         // Pre-recording buffer settings reset (memory-only, using new IDs)
         document.getElementById('pre_recording_enabled_camera').value = '';
         document.getElementById('pre_recording_buffer_minutes_camera').value = '';
     }
     
+    // Audio level settings
+    if (config.audio_level) {
+        document.getElementById('audio_level_enabled').value = config.audio_level.enabled === false ? '' : 'true';
+        document.getElementById('audio_level_interval_ms').value = config.audio_level.interval_ms || '';
+        document.getElementById('audio_level_mqtt').value = (config.audio_level.mqtt !== false).toString();
+        document.getElementById('audio_level_url').value = config.audio_level.url || '';
+    }
+
     // MQTT settings
     if (config.mqtt) {
         document.getElementById('mqtt_publish_interval').value = config.mqtt.publish_interval || 0;
@@ -300,6 +315,9 @@ function populateForm(camera) {
         document.getElementById('ffmpeg_quality').value = config.ffmpeg.quality || '';
         document.getElementById('ffmpeg_use_wallclock_as_timestamps').value = config.ffmpeg.use_wallclock_as_timestamps !== undefined && config.ffmpeg.use_wallclock_as_timestamps !== null ? config.ffmpeg.use_wallclock_as_timestamps.toString() : 'true';
         document.getElementById('ffmpeg_scale').value = config.ffmpeg.scale || '';
+        document.getElementById('ffmpeg_rotate').value = config.ffmpeg.rotate || '';
+        document.getElementById('ffmpeg_crop').value = config.ffmpeg.crop || '';
+        document.getElementById('ffmpeg_deinterlace').value = config.ffmpeg.deinterlace ? 'true' : '';
         document.getElementById('ffmpeg_output_framerate').value = config.ffmpeg.output_framerate || '';
         document.getElementById('ffmpeg_video_bitrate').value = config.ffmpeg.video_bitrate || '';
         document.getElementById('ffmpeg_rtbufsize').value = config.ffmpeg.rtbufsize || '';
@@ -356,10 +374,14 @@ function populateServerConfigForm(config) {
     document.getElementById('config_server_host').value = config.server?.host || '';
     document.getElementById('config_server_port').value = config.server?.port || '';
     document.getElementById('config_server_cors_allow_origin').value = config.server?.cors_allow_origin || '';
+    document.getElementById('config_server_cors_allowed_origins').value = (config.server?.cors_allowed_origins || []).join(', ');
+    document.getElementById('config_server_cors_path_overrides').value = (config.server?.cors_path_overrides || []).length > 0 ? JSON.stringify(config.server.cors_path_overrides, null, 2) : '';
+    document.getElementById('config_server_websocket_origin_check').value = (config.server?.websocket_origin_check || false).toString();
     document.getElementById('config_server_admin_token').value = config.server?.admin_token || '';
     document.getElementById('config_server_cameras_directory').value = config.server?.cameras_directory || '';
     document.getElementById('config_server_mp4_export_path').value = config.server?.mp4_export_path || '';
     document.getElementById('config_server_mp4_export_max_jobs').value = config.server?.mp4_export_max_jobs || '';
+    document.getElementById('config_server_standby_after_minutes').value = config.server?.standby_after_minutes || 0;
 
     // TLS settings
     document.getElementById('config_server_tls_enabled').value = (config.server?.tls?.enabled || false).toString();
@@ -390,16 +412,31 @@ function populateServerConfigForm(config) {
     toggleDatabaseOptions();
     document.getElementById('config_recording_max_frame_size').value = config.recording?.max_frame_size || '';
     document.getElementById('config_recording_frame_storage_retention').value = config.recording?.frame_storage_retention || '';
+    document.getElementById('config_recording_frame_spill_enabled').value = (config.recording?.frame_spill_enabled !== false).toString();
+    document.getElementById('config_recording_frame_spill_path').value = config.recording?.frame_spill_path || '';
+    document.getElementById('config_recording_frame_spill_max_mb').value = config.recording?.frame_spill_max_mb || '';
     document.getElementById('config_recording_mp4_storage_path').value = config.recording?.mp4_storage_path || '';
     document.getElementById('config_recording_mp4_storage_retention').value = config.recording?.mp4_storage_retention || '';
     document.getElementById('config_recording_mp4_segment_minutes').value = config.recording?.mp4_segment_minutes || '';
     document.getElementById('config_recording_mp4_filename_include_reason').value = (config.recording?.mp4_filename_include_reason || false).toString();
     document.getElementById('config_recording_mp4_filename_use_local_time').value = (config.recording?.mp4_filename_use_local_time !== false).toString();
     document.getElementById('config_recording_cleanup_interval_minutes').value = config.recording?.cleanup_interval_minutes || '';
+    document.getElementById('config_recording_reasons').value = (config.recording?.reasons || []).length > 0 ? JSON.stringify(config.recording.reasons, null, 2) : '';
+    document.getElementById('config_recording_frame_archive_tiers').value = (config.recording?.frame_archive_tiers || []).length > 0 ? JSON.stringify(config.recording.frame_archive_tiers, null, 2) : '';
+    document.getElementById('config_recording_vacuum_after_cleanup').value = (config.recording?.vacuum_after_cleanup !== false).toString();
+    document.getElementById('config_recording_vacuum_interval_hours').value = config.recording?.vacuum_interval_hours || '';
+    document.getElementById('config_recording_vacuum_mode').value = config.recording?.vacuum_mode || 'full';
+    document.getElementById('config_recording_sqlite_journal_mode').value = config.recording?.sqlite_journal_mode || 'wal';
+    document.getElementById('config_recording_sqlite_synchronous').value = config.recording?.sqlite_synchronous || 'normal';
+    document.getElementById('config_recording_sqlite_busy_timeout_ms').value = config.recording?.sqlite_busy_timeout_ms ?? '';
+    document.getElementById('config_recording_sqlite_cache_size').value = config.recording?.sqlite_cache_size ?? '';
+    document.getElementById('config_recording_sqlite_mmap_size').value = config.recording?.sqlite_mmap_size ?? '';
     // HLS settings
     document.getElementById('config_recording_hls_storage_enabled').value = (config.recording?.hls_storage_enabled || false).toString();
     document.getElementById('config_recording_hls_storage_retention').value = config.recording?.hls_storage_retention || '';
     document.getElementById('config_recording_hls_segment_seconds').value = config.recording?.hls_segment_seconds || '';
+    document.getElementById('config_recording_hls_encryption_enabled').value = (config.recording?.hls_encryption_enabled || false).toString();
+    document.getElementById('config_recording_hls_key_rotation_minutes').value = config.recording?.hls_key_rotation_minutes || '';
     
     // Pre-recording buffer settings (memory-only)
     document.getElementById('config_recording_pre_recording_enabled_new').value = (config.recording?.pre_recording_enabled || false).toString();
@@ -421,10 +458,15 @@ function collectServerConfigFromForm() {
             host: document.getElementById('config_server_host').value || "0.0.0.0",
             port: parseInt(document.getElementById('config_server_port').value) || 8080,
             cors_allow_origin: document.getElementById('config_server_cors_allow_origin').value || "*",
+            cors_allowed_origins: document.getElementById('config_server_cors_allowed_origins').value
+                .split(',').map(o => o.trim()).filter(o => o.length > 0),
+            cors_path_overrides: JSON.parse(document.getElementById('config_server_cors_path_overrides').value.trim() || '[]'),
+            websocket_origin_check: document.getElementById('config_server_websocket_origin_check').value === 'true',
             admin_token: document.getElementById('config_server_admin_token').value || "",
             cameras_directory: document.getElementById('config_server_cameras_directory').value || null,
             mp4_export_path: document.getElementById('config_server_mp4_export_path').value || "exports",
             mp4_export_max_jobs: parseInt(document.getElementById('config_server_mp4_export_max_jobs').value) || 100,
+            standby_after_minutes: parseInt(document.getElementById('config_server_standby_after_minutes').value) || 0,
             tls: {
                 enabled: document.getElementById('config_server_tls_enabled').value === 'true',
                 cert_path: document.getElementById('config_server_tls_cert_path').value || "certs/server.crt",
@@ -453,14 +495,29 @@ function collectServerConfigFromForm() {
             session_segment_minutes: parseInt(document.getElementById('config_recording_session_segment_minutes').value) || 60,
             max_frame_size: parseInt(document.getElementById('config_recording_max_frame_size').value) || 10485760,
             frame_storage_retention: document.getElementById('config_recording_frame_storage_retention').value || "7d",
+            frame_spill_enabled: document.getElementById('config_recording_frame_spill_enabled').value === 'true',
+            frame_spill_path: document.getElementById('config_recording_frame_spill_path').value || null,
+            frame_spill_max_mb: parseInt(document.getElementById('config_recording_frame_spill_max_mb').value) || 1024,
             mp4_storage_retention: document.getElementById('config_recording_mp4_storage_retention').value || "30d",
             mp4_segment_minutes: parseInt(document.getElementById('config_recording_mp4_segment_minutes').value) || 5,
             mp4_filename_include_reason: document.getElementById('config_recording_mp4_filename_include_reason').value === 'true',
             mp4_filename_use_local_time: document.getElementById('config_recording_mp4_filename_use_local_time').value === 'true',
             cleanup_interval_minutes: parseInt(document.getElementById('config_recording_cleanup_interval_minutes').value) || 60,
+            reasons: JSON.parse(document.getElementById('config_recording_reasons').value.trim() || '[]'),
+            frame_archive_tiers: JSON.parse(document.getElementById('config_recording_frame_archive_tiers').value.trim() || '[]'),
+            vacuum_after_cleanup: document.getElementById('config_recording_vacuum_after_cleanup').value === 'true',
+            vacuum_interval_hours: parseInt(document.getElementById('config_recording_vacuum_interval_hours').value) || 0,
+            vacuum_mode: document.getElementById('config_recording_vacuum_mode').value || 'full',
+            sqlite_journal_mode: document.getElementById('config_recording_sqlite_journal_mode').value || 'wal',
+            sqlite_synchronous: document.getElementById('config_recording_sqlite_synchronous').value || 'normal',
+            sqlite_busy_timeout_ms: Number.isNaN(parseInt(document.getElementById('config_recording_sqlite_busy_timeout_ms').value)) ? 60000 : parseInt(document.getElementById('config_recording_sqlite_busy_timeout_ms').value),
+            sqlite_cache_size: Number.isNaN(parseInt(document.getElementById('config_recording_sqlite_cache_size').value)) ? null : parseInt(document.getElementById('config_recording_sqlite_cache_size').value),
+            sqlite_mmap_size: Number.isNaN(parseInt(document.getElementById('config_recording_sqlite_mmap_size').value)) ? null : parseInt(document.getElementById('config_recording_sqlite_mmap_size').value),
             hls_storage_enabled: document.getElementById('config_recording_hls_storage_enabled').value === 'true',
             hls_storage_retention: document.getElementById('config_recording_hls_storage_retention').value || "30d",
             hls_segment_seconds: parseInt(document.getElementById('config_recording_hls_segment_seconds').value) || 6,
+            hls_encryption_enabled: document.getElementById('config_recording_hls_encryption_enabled').value === 'true',
+            hls_key_rotation_minutes: parseInt(document.getElementById('config_recording_hls_key_rotation_minutes').value) || 60,
             // Pre-recording buffer settings (memory-only)
             pre_recording_enabled: document.getElementById('config_recording_pre_recording_enabled_new').value === 'true',
             pre_recording_buffer_minutes: parseInt(document.getElementById('config_recording_pre_recording_buffer_minutes_new').value) || 1,
@@ -688,6 +745,47 @@ document.getElementById('cameraForm').addEventListener('submit', async (e) => {
         reconnect_interval: parseInt(formData.get('reconnect_interval')),
         token: formData.get('token') || null
     };
+
+    const tenant = (formData.get('tenant') || '').trim();
+    if (tenant) {
+        config.tenant = tenant;
+    }
+
+    const standbyAfterMinutes = formData.get('standby_after_minutes');
+    if (standbyAfterMinutes !== '') {
+        config.standby_after_minutes = parseInt(standbyAfterMinutes);
+    }
+
+    // Scoped tokens are entered as a JSON list
+    const scopedTokens = (formData.get('tokens') || '').trim();
+    if (scopedTokens) {
+        try {
+            config.tokens = JSON.parse(scopedTokens);
+        } catch (err) {
+            showAlert('Scoped tokens must be valid JSON', 'error');
+            return;
+        }
+    }
+
+    const triggers = (formData.get('triggers') || '').trim();
+    if (triggers) {
+        try {
+            config.triggers = JSON.parse(triggers);
+        } catch (err) {
+            showAlert('Triggers must be valid JSON', 'error');
+            return;
+        }
+    }
+
+    const plugins = (formData.get('plugins') || '').trim();
+    if (plugins) {
+        try {
+            config.plugins = JSON.parse(plugins);
+        } catch (err) {
+            showAlert('Plugins must be valid JSON', 'error');
+            return;
+        }
+    }
     
     // Add per-camera recording settings if configured
     const sessionSegmentMinutes = formData.get('session_segment_minutes');
@@ -700,6 +798,7 @@ document.getElementById('cameraForm').addEventListener('submit', async (e) => {
     const hlsStorageEnabled = formData.get('hls_storage_enabled');
     const hlsStorageRetention = formData.get('hls_storage_retention');
     const hlsSegmentSeconds = formData.get('hls_segment_seconds');
+    const hlsEncryptionEnabled = formData.get('hls_encryption_enabled');
     // Pre-recording buffer settings
     const preRecordingEnabled = formData.get('pre_recording_enabled_camera');
     const preRecordingBufferMinutes = formData.get('pre_recording_buffer_minutes_camera');
@@ -709,6 +808,7 @@ document.getElementById('cameraForm').addEventListener('submit', async (e) => {
         (frameStorageEnabled !== '' && frameStorageEnabled !== null) ||
         frameStorageRetention || videoStorageType || videoStorageRetention || videoSegmentMinutes ||
         (hlsStorageEnabled !== '' && hlsStorageEnabled !== null) || hlsStorageRetention || hlsSegmentSeconds ||
+        (hlsEncryptionEnabled !== '' && hlsEncryptionEnabled !== null) ||
         (preRecordingEnabled !== '' && preRecordingEnabled !== null) || preRecordingBufferMinutes) {
         config.recording = {};
         
@@ -740,6 +840,9 @@ document.getElementById('cameraForm').addEventListener('submit', async (e) => {
         if (hlsSegmentSeconds) {
             config.recording.hls_segment_seconds = parseInt(hlsSegmentSeconds);
         }
+        if (hlsEncryptionEnabled !== '' && hlsEncryptionEnabled !== null) {
+            config.recording.hls_encryption_enabled = hlsEncryptionEnabled === 'true';
+        }
         
         // Pre-recording buffer settings (memory-only, using new field names)
         if (preRecordingEnabled !== '' && preRecordingEnabled !== null) {
@@ -750,6 +853,22 @@ document.getElementById('cameraForm').addEventListener('submit', async (e) => {
         }
     }
     
+    // Add audio level config if enabled
+    if (formData.get('audio_level_enabled') === 'true') {
+        config.audio_level = {
+            enabled: true,
+            mqtt: formData.get('audio_level_mqtt') !== 'false'
+        };
+        const audioInterval = formData.get('audio_level_interval_ms');
+        if (audioInterval) {
+            config.audio_level.interval_ms = parseInt(audioInterval);
+        }
+        const audioUrl = (formData.get('audio_level_url') || '').trim();
+        if (audioUrl) {
+            config.audio_level.url = audioUrl;
+        }
+    }
+    
     // Add MQTT config if configured
     const mqttInterval = formData.get('mqtt_publish_interval');
     const mqttTopic = formData.get('mqtt_topic_name');
@@ -763,16 +882,16 @@ document.getElementById('cameraForm').addEventListener('submit', async (e) => {
     // Add FFmpeg config
     const ffmpegConfig = {};
     const ffmpegFields = [
-        'command', 'quality', 'use_wallclock_as_timestamps', 'scale', 'output_framerate', 'video_bitrate',
+        'command', 'quality', 'use_wallclock_as_timestamps', 'scale', 'rotate', 'crop', 'deinterlace', 'output_framerate', 'video_bitrate',
         'rtbufsize', 'log_stderr', 'fflags', 'flags', 'avioflags', 'fps_mode', 'data_timeout_secs'
     ];
     
     ffmpegFields.forEach(field => {
         const value = formData.get(`ffmpeg_${field}`);
         if (value) {
-            if (field === 'quality' || field === 'output_framerate' || field === 'rtbufsize' || field === 'data_timeout_secs') {
+            if (field === 'quality' || field === 'rotate' || field === 'output_framerate' || field === 'rtbufsize' || field === 'data_timeout_secs') {
                 ffmpegConfig[field] = parseInt(value);
-            } else if (field === 'use_wallclock_as_timestamps') {
+            } else if (field === 'use_wallclock_as_timestamps' || field === 'deinterlace') {
                 ffmpegConfig[field] = value === 'true';
             } else {
                 ffmpegConfig[field] = value;